@@ -0,0 +1,39 @@
+//! Prints how many rows and variables every step of the main VM cycle takes.
+//!
+//! cargo run --release --example vm_cycle_profile -- [NUM_CYCLES] [VARIANT] [--json]
+
+use zkevm_circuits::main_vm::profiler::profile_vm_cycles;
+use zkevm_circuits::main_vm::variants::{vm_circuit_variant_by_name, VM_CIRCUIT_VARIANTS};
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let as_json = args.iter().any(|el| el == "--json");
+    let mut positional = args.iter().filter(|el| el.as_str() != "--json");
+
+    let num_cycles = match positional.next() {
+        Some(num_cycles) => num_cycles
+            .parse::<usize>()
+            .expect("number of cycles must be an integer"),
+        None => 4,
+    };
+    let variant_name = positional.next().map(|el| el.as_str()).unwrap_or("full");
+    let Some(variant) = vm_circuit_variant_by_name(variant_name) else {
+        let known: Vec<&str> = VM_CIRCUIT_VARIANTS.iter().map(|el| el.name).collect();
+        eprintln!(
+            "unknown VM variant `{}`, expected one of {:?}",
+            variant_name, known
+        );
+        std::process::exit(1);
+    };
+
+    let profile = profile_vm_cycles(num_cycles, variant);
+    if as_json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&profile).expect("profile is serializable")
+        );
+    } else {
+        println!("VM variant `{}`", variant.name);
+        println!("{}", profile);
+    }
+}
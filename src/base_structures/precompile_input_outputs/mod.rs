@@ -50,3 +50,14 @@ impl<F: SmallField> CSPlaceholder<F> for PrecompileFunctionOutputData<F> {
         }
     }
 }
+
+/// Formal address of a precompile as it appears in the `address` field of the log query
+/// that the VM emits for the precompile call. It's a short address padded to 20 bytes
+pub const fn formal_precompile_address(address: u16) -> zkevm_opcode_defs::ethereum_types::H160 {
+    let [high, low] = address.to_be_bytes();
+    let mut bytes = [0u8; 20];
+    bytes[18] = high;
+    bytes[19] = low;
+
+    zkevm_opcode_defs::ethereum_types::H160(bytes)
+}
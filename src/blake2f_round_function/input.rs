@@ -0,0 +1,96 @@
+use std::collections::VecDeque;
+
+use super::*;
+
+use crate::base_structures::precompile_input_outputs::*;
+use crate::base_structures::vm_state::*;
+use boojum::cs::Variable;
+use boojum::gadgets::queue::*;
+use boojum::gadgets::traits::allocatable::CSAllocatable;
+use boojum::gadgets::traits::allocatable::CSPlaceholder;
+use boojum::gadgets::traits::encodable::CircuitVarLengthEncodable;
+
+use boojum::cs::traits::cs::ConstraintSystem;
+use boojum::field::SmallField;
+use boojum::gadgets::boolean::Boolean;
+use boojum::gadgets::traits::auxiliary::PrettyComparison;
+use boojum::gadgets::traits::selectable::Selectable;
+use boojum::gadgets::traits::witnessable::WitnessHookable;
+use boojum::serde_utils::BigArraySerde;
+
+#[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
+#[derivative(Clone, Copy, Debug)]
+#[DerivePrettyComparison("true")]
+pub struct Blake2fRoundFunctionFSM<F: SmallField> {
+    pub read_precompile_call: Boolean<F>,
+    pub read_words_for_round: Boolean<F>,
+    pub completed: Boolean<F>,
+    pub invalid_input: Boolean<F>,
+    pub blake2b_state: [[UInt8<F>; BLAKE2B_WORD_BYTES]; BLAKE2B_STATE_WIDTH],
+    pub working_vector: [[UInt8<F>; BLAKE2B_WORD_BYTES]; BLAKE2B_WORKING_STATE_WIDTH],
+    pub message: [[UInt8<F>; BLAKE2B_WORD_BYTES]; BLAKE2B_MESSAGE_WORDS],
+    pub timestamp_to_use_for_read: UInt32<F>,
+    pub timestamp_to_use_for_write: UInt32<F>,
+    pub precompile_call_params: Blake2fPrecompileCallParams<F>,
+}
+
+impl<F: SmallField> CSPlaceholder<F> for Blake2fRoundFunctionFSM<F> {
+    fn placeholder<CS: ConstraintSystem<F>>(cs: &mut CS) -> Self {
+        let boolean_false = Boolean::allocated_constant(cs, false);
+        let zero_u8 = UInt8::zero(cs);
+        let zero_u32 = UInt32::zero(cs);
+        Self {
+            read_precompile_call: boolean_false,
+            read_words_for_round: boolean_false,
+            completed: boolean_false,
+            invalid_input: boolean_false,
+            blake2b_state: [[zero_u8; BLAKE2B_WORD_BYTES]; BLAKE2B_STATE_WIDTH],
+            working_vector: [[zero_u8; BLAKE2B_WORD_BYTES]; BLAKE2B_WORKING_STATE_WIDTH],
+            message: [[zero_u8; BLAKE2B_WORD_BYTES]; BLAKE2B_MESSAGE_WORDS],
+            timestamp_to_use_for_read: zero_u32,
+            timestamp_to_use_for_write: zero_u32,
+            precompile_call_params: Blake2fPrecompileCallParams::<F>::placeholder(cs),
+        }
+    }
+}
+
+#[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
+#[derivative(Clone, Copy, Debug)]
+#[DerivePrettyComparison("true")]
+pub struct Blake2fRoundFunctionFSMInputOutput<F: SmallField> {
+    pub internal_fsm: Blake2fRoundFunctionFSM<F>,
+    pub log_queue_state: QueueState<F, QUEUE_STATE_WIDTH>,
+    pub memory_queue_state: QueueState<F, FULL_SPONGE_QUEUE_STATE_WIDTH>,
+}
+
+impl<F: SmallField> CSPlaceholder<F> for Blake2fRoundFunctionFSMInputOutput<F> {
+    fn placeholder<CS: ConstraintSystem<F>>(cs: &mut CS) -> Self {
+        Self {
+            internal_fsm: Blake2fRoundFunctionFSM::placeholder(cs),
+            log_queue_state: QueueState::<F, QUEUE_STATE_WIDTH>::placeholder(cs),
+            memory_queue_state: QueueState::<F, FULL_SPONGE_QUEUE_STATE_WIDTH>::placeholder(cs),
+        }
+    }
+}
+
+pub type Blake2fRoundFunctionCircuitInputOutput<F> = ClosedFormInput<
+    F,
+    Blake2fRoundFunctionFSMInputOutput<F>,
+    PrecompileFunctionInputData<F>,
+    PrecompileFunctionOutputData<F>,
+>;
+pub type Blake2fRoundFunctionCircuitInputOutputWitness<F> = ClosedFormInputWitness<
+    F,
+    Blake2fRoundFunctionFSMInputOutput<F>,
+    PrecompileFunctionInputData<F>,
+    PrecompileFunctionOutputData<F>,
+>;
+
+#[derive(Derivative, serde::Serialize, serde::Deserialize)]
+#[derivative(Clone, Debug, Default)]
+#[serde(bound = "")]
+pub struct Blake2fRoundFunctionCircuitInstanceWitness<F: SmallField> {
+    pub closed_form_input: Blake2fRoundFunctionCircuitInputOutputWitness<F>,
+    pub requests_queue_witness: CircuitQueueRawWitness<F, LogQuery<F>, 4, LOG_QUERY_PACKED_WIDTH>,
+    pub memory_reads_witness: VecDeque<U256>,
+}
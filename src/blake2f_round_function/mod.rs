@@ -0,0 +1,831 @@
+use super::*;
+
+use boojum::field::SmallField;
+
+use boojum::gadgets::traits::witnessable::WitnessHookable;
+
+use boojum::cs::traits::cs::ConstraintSystem;
+use boojum::gadgets::boolean::Boolean;
+use boojum::gadgets::traits::selectable::Selectable;
+use boojum::gadgets::u256::UInt256;
+use boojum::gadgets::u32::UInt32;
+use cs_derive::*;
+
+use crate::ethereum_types::U256;
+use crate::fsm_input_output::circuit_inputs::INPUT_OUTPUT_COMMITMENT_LENGTH;
+use boojum::gadgets::num::Num;
+use zkevm_opcode_defs::system_params::PRECOMPILE_AUX_BYTE;
+
+use crate::base_structures::log_query::*;
+use crate::base_structures::memory_query::*;
+use crate::base_structures::precompile_input_outputs::formal_precompile_address;
+use crate::base_structures::precompile_input_outputs::PrecompileFunctionOutputData;
+use crate::demux_log_queue::StorageLogQueue;
+use crate::fsm_input_output::*;
+use crate::storage_application::ConditionalWitnessAllocator;
+use boojum::algebraic_props::round_function::AlgebraicRoundFunction;
+use boojum::cs::Variable;
+use boojum::gadgets::queue::CircuitQueueWitness;
+use boojum::gadgets::queue::QueueState;
+use boojum::gadgets::traits::allocatable::CSAllocatable;
+use boojum::gadgets::traits::allocatable::{CSAllocatableExt, CSPlaceholder};
+use boojum::gadgets::traits::encodable::CircuitVarLengthEncodable;
+use boojum::gadgets::traits::round_function::CircuitRoundFunction;
+use boojum::gadgets::u160::UInt160;
+use boojum::gadgets::u8::UInt8;
+use std::sync::{Arc, RwLock};
+use zkevm_opcode_defs::ethereum_types::H160;
+
+use crate::base_structures::precompile_input_outputs::*;
+use crate::demux_log_queue::DemuxOutput;
+use crate::precompile_registry::PrecompileCircuit;
+use crate::scheduler::auxiliary::BaseLayerCircuitType;
+
+pub mod input;
+pub mod round_function;
+use self::input::*;
+pub use self::round_function::*;
+
+pub const BLAKE2F_PRECOMPILE_ADDRESS: u16 = 0x09;
+pub const BLAKE2F_PRECOMPILE_FORMAL_ADDRESS: H160 =
+    formal_precompile_address(BLAKE2F_PRECOMPILE_ADDRESS);
+
+pub struct Blake2fPrecompileCircuit;
+
+impl PrecompileCircuit for Blake2fPrecompileCircuit {
+    const NAME: &'static str = "blake2f";
+    const DEMUX_OUTPUT: DemuxOutput = DemuxOutput::Blake2f;
+    const CIRCUIT_TYPE: BaseLayerCircuitType = BaseLayerCircuitType::Blake2fPrecompile;
+
+    type InputData<F: SmallField> = PrecompileFunctionInputData<F>;
+    type OutputData<F: SmallField> = PrecompileFunctionOutputData<F>;
+
+    fn formal_address() -> H160 {
+        BLAKE2F_PRECOMPILE_FORMAL_ADDRESS
+    }
+}
+
+// EIP-152 input is 4 bytes of rounds, 64 bytes of state, 128 bytes of message,
+// 16 bytes of offset counters and a final block flag
+pub const BLAKE2F_INPUT_BYTE_LENGTH: usize = 213;
+pub const MEMORY_READ_QUERIES_PER_CALL: usize = 7;
+pub const BLAKE2F_ROUNDS_PER_CYCLE: usize = BLAKE2B_NUM_SIGMA_PERMUTATIONS;
+
+const BLAKE2F_STATE_OFFSET: usize = 4;
+const BLAKE2F_MESSAGE_OFFSET: usize =
+    BLAKE2F_STATE_OFFSET + BLAKE2B_STATE_WIDTH * BLAKE2B_WORD_BYTES;
+const BLAKE2F_OFFSET_COUNTERS_OFFSET: usize =
+    BLAKE2F_MESSAGE_OFFSET + BLAKE2B_MESSAGE_WORDS * BLAKE2B_WORD_BYTES;
+const BLAKE2F_FINAL_BLOCK_FLAG_OFFSET: usize =
+    BLAKE2F_OFFSET_COUNTERS_OFFSET + 2 * BLAKE2B_WORD_BYTES;
+
+#[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
+#[derivative(Clone, Copy, Debug)]
+// #[DerivePrettyComparison("true")]
+pub struct Blake2fPrecompileCallParams<F: SmallField> {
+    pub input_page: UInt32<F>,
+    pub input_offset: UInt32<F>,
+    pub input_byte_length: UInt32<F>,
+    pub output_page: UInt32<F>,
+    pub output_offset: UInt32<F>,
+    pub num_rounds: UInt32<F>,
+}
+
+impl<F: SmallField> CSPlaceholder<F> for Blake2fPrecompileCallParams<F> {
+    fn placeholder<CS: ConstraintSystem<F>>(cs: &mut CS) -> Self {
+        let zero_u32 = UInt32::zero(cs);
+        Self {
+            input_page: zero_u32,
+            input_offset: zero_u32,
+            input_byte_length: zero_u32,
+            output_page: zero_u32,
+            output_offset: zero_u32,
+            num_rounds: zero_u32,
+        }
+    }
+}
+
+impl<F: SmallField> Blake2fPrecompileCallParams<F> {
+    // Input is placed in memory starting from the word boundary, and number of rounds
+    // is not known until the input is read. Input length is in bytes
+    pub fn from_encoding<CS: ConstraintSystem<F>>(cs: &mut CS, encoding: UInt256<F>) -> Self {
+        let input_offset = encoding.inner[0];
+        let input_byte_length = encoding.inner[1];
+        let output_offset = encoding.inner[2];
+        let input_page = encoding.inner[4];
+        let output_page = encoding.inner[5];
+
+        let num_rounds = UInt32::zero(cs);
+
+        let new = Self {
+            input_page,
+            input_offset,
+            input_byte_length,
+            output_page,
+            output_offset,
+            num_rounds,
+        };
+
+        new
+    }
+}
+
+/// Returns whether the call is valid and the final block flag. Call is only valid if the input
+/// is exactly `BLAKE2F_INPUT_BYTE_LENGTH` bytes long, and the final block flag is either 0 or 1
+pub fn validate_blake2f_input<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    input_byte_length: UInt32<F>,
+    final_block_flag: UInt8<F>,
+) -> (Boolean<F>, Boolean<F>) {
+    let expected_input_byte_length =
+        UInt32::allocated_constant(cs, BLAKE2F_INPUT_BYTE_LENGTH as u32);
+    let one_u8 = UInt8::allocated_constant(cs, 1);
+
+    let input_length_is_valid = UInt32::equals(cs, &input_byte_length, &expected_input_byte_length);
+    let final_block_flag_is_zero = final_block_flag.is_zero(cs);
+    let final_block_flag_is_one = UInt8::equals(cs, &final_block_flag, &one_u8);
+    let final_block_flag_is_valid =
+        Boolean::multi_or(cs, &[final_block_flag_is_zero, final_block_flag_is_one]);
+    let input_is_valid =
+        Boolean::multi_and(cs, &[input_length_is_valid, final_block_flag_is_valid]);
+
+    (input_is_valid, final_block_flag_is_one)
+}
+
+pub fn blake2f_round_function_precompile_inner<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    memory_queue: &mut MemoryQueue<F, R>,
+    precompile_calls_queue: &mut StorageLogQueue<F, R>,
+    memory_read_witness: ConditionalWitnessAllocator<F, UInt256<F>>,
+    mut state: Blake2fRoundFunctionFSM<F>,
+    _round_function: &R,
+    limit: usize,
+) -> Blake2fRoundFunctionFSM<F>
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    assert!(limit <= u32::MAX as usize);
+
+    let precompile_address = UInt160::allocated_constant(cs, BLAKE2F_PRECOMPILE_FORMAL_ADDRESS);
+    let aux_byte_for_precompile = UInt8::allocated_constant(cs, PRECOMPILE_AUX_BYTE);
+
+    let boolean_false = Boolean::allocated_constant(cs, false);
+    let boolean_true = Boolean::allocated_constant(cs, true);
+    let zero_u256 = UInt256::zero(cs);
+
+    // we can have a degenerate case when queue is empty, but it's a first circuit in the queue,
+    // so we taken default FSM state that has state.read_precompile_call = true;
+    let input_queue_is_empty = precompile_calls_queue.is_empty(cs);
+    // we can only skip the full circuit if we are not in any form of progress
+    let can_finish_immediatelly =
+        Boolean::multi_and(cs, &[state.read_precompile_call, input_queue_is_empty]);
+
+    if crate::config::CIRCUIT_VERSOBE {
+        dbg!(can_finish_immediatelly.witness_hook(cs)());
+        dbg!(state.witness_hook(cs)());
+    }
+
+    state.read_precompile_call = state
+        .read_precompile_call
+        .mask_negated(cs, can_finish_immediatelly);
+    state.read_words_for_round = state
+        .read_words_for_round
+        .mask_negated(cs, can_finish_immediatelly);
+    state.completed = Boolean::multi_or(cs, &[state.completed, can_finish_immediatelly]);
+
+    // main work cycle, every cycle performs up to the full set of message permutations
+    for _cycle in 0..limit {
+        if crate::config::CIRCUIT_VERSOBE {
+            dbg!(_cycle);
+            dbg!(state.witness_hook(cs)());
+            dbg!(precompile_calls_queue.into_state().witness_hook(cs)());
+        }
+        // if we are in a proper state then get the ABI from the queue
+        let (precompile_call, _) = precompile_calls_queue.pop_front(cs, state.read_precompile_call);
+
+        Num::conditionally_enforce_equal(
+            cs,
+            state.read_precompile_call,
+            &Num::from_variable(precompile_call.aux_byte.get_variable()),
+            &Num::from_variable(aux_byte_for_precompile.get_variable()),
+        );
+        for (a, b) in precompile_call
+            .address
+            .inner
+            .iter()
+            .zip(precompile_address.inner.iter())
+        {
+            Num::conditionally_enforce_equal(
+                cs,
+                state.read_precompile_call,
+                &Num::from_variable(a.get_variable()),
+                &Num::from_variable(b.get_variable()),
+            );
+        }
+
+        // now compute some parameters that describe the call itself
+
+        let params_encoding = precompile_call.key;
+        let call_params = Blake2fPrecompileCallParams::from_encoding(cs, params_encoding);
+
+        state.precompile_call_params = Blake2fPrecompileCallParams::conditionally_select(
+            cs,
+            state.read_precompile_call,
+            &call_params,
+            &state.precompile_call_params,
+        );
+        // also set timestamps
+        state.timestamp_to_use_for_read = UInt32::conditionally_select(
+            cs,
+            state.read_precompile_call,
+            &precompile_call.timestamp,
+            &state.timestamp_to_use_for_read,
+        );
+
+        // timestamps have large space, so this can be expected
+        let timestamp_to_use_for_write =
+            unsafe { state.timestamp_to_use_for_read.increment_unchecked(cs) };
+        state.timestamp_to_use_for_write = UInt32::conditionally_select(
+            cs,
+            state.read_precompile_call,
+            &timestamp_to_use_for_write,
+            &state.timestamp_to_use_for_write,
+        );
+
+        // ---------------------------------
+        // on a new call we read the full input and initialize the working vector
+
+        let is_new_call = state.read_precompile_call;
+
+        let mut bias_variable = is_new_call.get_variable();
+        let mut input_bytes = Vec::with_capacity(MEMORY_READ_QUERIES_PER_CALL * 32);
+        let mut index = state.precompile_call_params.input_offset;
+        for _ in 0..MEMORY_READ_QUERIES_PER_CALL {
+            let read_query_value =
+                memory_read_witness.conditionally_allocate_biased(cs, is_new_call, bias_variable);
+            bias_variable = read_query_value.inner[0].get_variable();
+
+            input_bytes.extend(read_query_value.to_be_bytes(cs));
+
+            let read_query = MemoryQuery {
+                timestamp: state.timestamp_to_use_for_read,
+                memory_page: state.precompile_call_params.input_page,
+                index,
+                rw_flag: boolean_false,
+                is_ptr: boolean_false,
+                value: read_query_value,
+            };
+
+            // perform read
+            memory_queue.push(cs, read_query, is_new_call);
+
+            // offset is never large enough to overflow
+            index = unsafe { index.increment_unchecked(cs) };
+        }
+
+        // number of rounds is big-endian, while all other words are little-endian,
+        // that matches our representation of BLAKE2b words
+        let num_rounds =
+            UInt32::from_be_bytes(cs, input_bytes[..BLAKE2F_STATE_OFFSET].try_into().unwrap());
+        let h: [Blake2bWord<F>; BLAKE2B_STATE_WIDTH] = std::array::from_fn(|i| {
+            let start = BLAKE2F_STATE_OFFSET + i * BLAKE2B_WORD_BYTES;
+            input_bytes[start..(start + BLAKE2B_WORD_BYTES)]
+                .try_into()
+                .unwrap()
+        });
+        let m: [Blake2bWord<F>; BLAKE2B_MESSAGE_WORDS] = std::array::from_fn(|i| {
+            let start = BLAKE2F_MESSAGE_OFFSET + i * BLAKE2B_WORD_BYTES;
+            input_bytes[start..(start + BLAKE2B_WORD_BYTES)]
+                .try_into()
+                .unwrap()
+        });
+        let t: [Blake2bWord<F>; 2] = std::array::from_fn(|i| {
+            let start = BLAKE2F_OFFSET_COUNTERS_OFFSET + i * BLAKE2B_WORD_BYTES;
+            input_bytes[start..(start + BLAKE2B_WORD_BYTES)]
+                .try_into()
+                .unwrap()
+        });
+        let final_block_flag = input_bytes[BLAKE2F_FINAL_BLOCK_FLAG_OFFSET];
+
+        // invalid call fails without performing any rounds
+        let (input_is_valid, final_block_flag_is_one) = validate_blake2f_input(
+            cs,
+            state.precompile_call_params.input_byte_length,
+            final_block_flag,
+        );
+        let invalid_input = input_is_valid.negated(cs);
+        let num_rounds = num_rounds.mask(cs, input_is_valid);
+
+        let working_vector = blake2b_initial_working_vector(cs, &h, &t, final_block_flag_is_one);
+
+        state.invalid_input =
+            Boolean::conditionally_select(cs, is_new_call, &invalid_input, &state.invalid_input);
+        state.precompile_call_params.num_rounds = UInt32::conditionally_select(
+            cs,
+            is_new_call,
+            &num_rounds,
+            &state.precompile_call_params.num_rounds,
+        );
+        state.blake2b_state = <[Blake2bWord<F>; BLAKE2B_STATE_WIDTH]>::conditionally_select(
+            cs,
+            is_new_call,
+            &h,
+            &state.blake2b_state,
+        );
+        state.working_vector =
+            <[Blake2bWord<F>; BLAKE2B_WORKING_STATE_WIDTH]>::conditionally_select(
+                cs,
+                is_new_call,
+                &working_vector,
+                &state.working_vector,
+            );
+        state.message = <[Blake2bWord<F>; BLAKE2B_MESSAGE_WORDS]>::conditionally_select(
+            cs,
+            is_new_call,
+            &m,
+            &state.message,
+        );
+
+        state.read_words_for_round = Boolean::multi_or(
+            cs,
+            &[state.read_precompile_call, state.read_words_for_round],
+        );
+        state.read_precompile_call = boolean_false;
+
+        // ---------------------------------
+        // Now perform rounds. Every call starts from the beginning of the cycle,
+        // so the message schedule of every round in the cycle is known in advance
+
+        for round in 0..BLAKE2F_ROUNDS_PER_CYCLE {
+            let zero_rounds_left = state.precompile_call_params.num_rounds.is_zero(cs);
+            let rounds_left = zero_rounds_left.negated(cs);
+            let should_run_round =
+                Boolean::multi_and(cs, &[state.read_words_for_round, rounds_left]);
+
+            let mut new_working_vector = state.working_vector;
+            blake2b_round(cs, &mut new_working_vector, &state.message, round);
+            state.working_vector =
+                <[Blake2bWord<F>; BLAKE2B_WORKING_STATE_WIDTH]>::conditionally_select(
+                    cs,
+                    should_run_round,
+                    &new_working_vector,
+                    &state.working_vector,
+                );
+
+            let may_be_new_num_rounds = unsafe {
+                state
+                    .precompile_call_params
+                    .num_rounds
+                    .decrement_unchecked(cs)
+            };
+            state.precompile_call_params.num_rounds = UInt32::conditionally_select(
+                cs,
+                should_run_round,
+                &may_be_new_num_rounds,
+                &state.precompile_call_params.num_rounds,
+            );
+        }
+
+        let no_rounds_left = state.precompile_call_params.num_rounds.is_zero(cs);
+        let write_result = Boolean::multi_and(cs, &[state.read_words_for_round, no_rounds_left]);
+
+        let success = state.invalid_input.negated(cs);
+        let new_state = blake2b_finalize(cs, &state.blake2b_state, &state.working_vector);
+
+        let success_as_u32 = unsafe { UInt32::from_variable_unchecked(success.get_variable()) };
+        let mut success_as_u256 = zero_u256;
+        success_as_u256.inner[0] = success_as_u32;
+
+        let success_query = MemoryQuery {
+            timestamp: state.timestamp_to_use_for_write,
+            memory_page: state.precompile_call_params.output_page,
+            index: state.precompile_call_params.output_offset,
+            rw_flag: boolean_true,
+            is_ptr: boolean_false,
+            value: success_as_u256,
+        };
+
+        // perform writes
+        memory_queue.push(cs, success_query, write_result);
+
+        // new state is written as 64 bytes in the same layout as it was read
+        let mut index = state.precompile_call_params.output_offset;
+        for chunk in new_state.chunks_exact(32 / BLAKE2B_WORD_BYTES) {
+            let be_bytes: [UInt8<F>; 32] = chunk
+                .iter()
+                .flatten()
+                .copied()
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
+            let result = UInt256::from_be_bytes(cs, be_bytes);
+            let result = result.mask(cs, success);
+
+            index = unsafe { index.increment_unchecked(cs) };
+            let result_query = MemoryQuery {
+                timestamp: state.timestamp_to_use_for_write,
+                memory_page: state.precompile_call_params.output_page,
+                index,
+                rw_flag: boolean_true,
+                is_ptr: boolean_false,
+                value: result,
+            };
+
+            memory_queue.push(cs, result_query, write_result);
+        }
+
+        // ---------------------------------
+
+        // update state
+        let input_is_empty = precompile_calls_queue.is_empty(cs);
+        let input_is_not_empty = input_is_empty.negated(cs);
+        let nothing_left = Boolean::multi_and(cs, &[write_result, input_is_empty]);
+        let process_next = Boolean::multi_and(cs, &[write_result, input_is_not_empty]);
+
+        state.read_precompile_call = process_next;
+        state.completed = Boolean::multi_or(cs, &[nothing_left, state.completed]);
+        let t = Boolean::multi_or(cs, &[state.read_precompile_call, state.completed]);
+        state.read_words_for_round = t.negated(cs);
+
+        if crate::config::CIRCUIT_VERSOBE {
+            dbg!(state.witness_hook(cs)());
+            dbg!(precompile_calls_queue.into_state().witness_hook(cs)());
+        }
+    }
+
+    precompile_calls_queue.enforce_consistency(cs);
+
+    state
+}
+
+#[track_caller]
+pub fn blake2f_round_function_entry_point<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    witness: Blake2fRoundFunctionCircuitInstanceWitness<F>,
+    round_function: &R,
+    limit: usize,
+) -> [Num<F>; INPUT_OUTPUT_COMMITMENT_LENGTH]
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    let Blake2fRoundFunctionCircuitInstanceWitness {
+        closed_form_input,
+        requests_queue_witness,
+        memory_reads_witness,
+    } = witness;
+
+    let mut structured_input = Blake2fRoundFunctionCircuitInputOutput::alloc_ignoring_outputs(
+        cs,
+        closed_form_input.clone(),
+    );
+
+    let start_flag = structured_input.start_flag;
+
+    let requests_queue_state_from_input = structured_input.observable_input.initial_log_queue_state;
+
+    // it must be trivial
+    requests_queue_state_from_input.enforce_trivial_head(cs);
+
+    let requests_queue_state_from_fsm = structured_input.hidden_fsm_input.log_queue_state;
+
+    let requests_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &requests_queue_state_from_input,
+        &requests_queue_state_from_fsm,
+    );
+
+    let memory_queue_state_from_input =
+        structured_input.observable_input.initial_memory_queue_state;
+
+    // it must be trivial
+    memory_queue_state_from_input.enforce_trivial_head(cs);
+
+    let memory_queue_state_from_fsm = structured_input.hidden_fsm_input.memory_queue_state;
+
+    let memory_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &memory_queue_state_from_input,
+        &memory_queue_state_from_fsm,
+    );
+
+    let mut requests_queue = StorageLogQueue::<F, R>::from_state(cs, requests_queue_state);
+    let queue_witness = CircuitQueueWitness::from_inner_witness(requests_queue_witness);
+    requests_queue.witness = Arc::new(queue_witness);
+
+    let mut memory_queue = MemoryQueue::<F, R>::from_state(cs, memory_queue_state);
+
+    let read_queries_allocator = ConditionalWitnessAllocator::<F, UInt256<F>> {
+        witness_source: Arc::new(RwLock::new(memory_reads_witness)),
+    };
+
+    let mut starting_fsm_state = Blake2fRoundFunctionFSM::placeholder(cs);
+    starting_fsm_state.read_precompile_call = Boolean::allocated_constant(cs, true);
+
+    let initial_state = Blake2fRoundFunctionFSM::conditionally_select(
+        cs,
+        start_flag,
+        &starting_fsm_state,
+        &structured_input.hidden_fsm_input.internal_fsm,
+    );
+
+    let final_state = blake2f_round_function_precompile_inner::<F, CS, R>(
+        cs,
+        &mut memory_queue,
+        &mut requests_queue,
+        read_queries_allocator,
+        initial_state,
+        round_function,
+        limit,
+    );
+
+    let final_memory_state = memory_queue.into_state();
+    let final_requets_state = requests_queue.into_state();
+
+    // form the final state
+    let done = final_state.completed;
+    structured_input.completion_flag = done;
+    structured_input.observable_output = PrecompileFunctionOutputData::placeholder(cs);
+
+    structured_input.observable_output.final_memory_state = QueueState::conditionally_select(
+        cs,
+        structured_input.completion_flag,
+        &final_memory_state,
+        &structured_input.observable_output.final_memory_state,
+    );
+
+    structured_input.hidden_fsm_output.internal_fsm = final_state;
+    structured_input.hidden_fsm_output.log_queue_state = final_requets_state;
+    structured_input.hidden_fsm_output.memory_queue_state = final_memory_state;
+
+    // self-check
+    structured_input.hook_compare_witness(cs, &closed_form_input);
+
+    use boojum::cs::gates::PublicInputGate;
+
+    let compact_form =
+        ClosedFormInputCompactForm::from_full_form(cs, &structured_input, round_function);
+    let input_commitment = commit_variable_length_encodable_item(cs, &compact_form, round_function);
+    for el in input_commitment.iter() {
+        let gate = PublicInputGate::new(el.get_variable());
+        gate.add_to_cs(cs);
+    }
+
+    input_commitment
+}
+
+#[cfg(test)]
+mod test {
+    use boojum::field::goldilocks::GoldilocksField;
+    use boojum::gadgets::traits::allocatable::CSAllocatable;
+    use boojum::worker::Worker;
+
+    use super::*;
+
+    type F = GoldilocksField;
+    type P = GoldilocksField;
+
+    use boojum::cs::cs_builder::*;
+    use boojum::cs::cs_builder_reference::CsReferenceImplementationBuilder;
+    use boojum::cs::gates::*;
+    use boojum::cs::implementations::reference_cs::CSReferenceImplementation;
+    use boojum::cs::traits::gate::GatePlacementStrategy;
+    use boojum::cs::CSGeometry;
+    use boojum::cs::*;
+    use boojum::gadgets::tables::*;
+
+    fn create_cs(
+        max_trace_len: usize,
+    ) -> CSReferenceImplementation<
+        F,
+        P,
+        DevCSConfig,
+        impl GateConfigurationHolder<F>,
+        impl StaticToolboxHolder,
+    > {
+        let geometry = CSGeometry {
+            num_columns_under_copy_permutation: 80,
+            num_witness_columns: 0,
+            num_constant_columns: 4,
+            max_allowed_constraint_degree: 8,
+        };
+        let max_variables = 1 << 26;
+
+        fn configure<
+            F: SmallField,
+            T: CsBuilderImpl<F, T>,
+            GC: GateConfigurationHolder<F>,
+            TB: StaticToolboxHolder,
+        >(
+            builder: CsBuilder<T, F, GC, TB>,
+        ) -> CsBuilder<T, F, impl GateConfigurationHolder<F>, impl StaticToolboxHolder> {
+            let builder = builder.allow_lookup(
+                LookupParameters::UseSpecializedColumnsWithTableIdAsConstant {
+                    width: 3,
+                    num_repetitions: 16,
+                    share_table_id: true,
+                },
+            );
+
+            let builder = ConstantsAllocatorGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = BooleanConstraintGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseSpecializedColumns {
+                    num_repetitions: 1,
+                    share_constants: false,
+                },
+            );
+            let builder = U8x4FMAGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ZeroCheckGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+                false,
+            );
+            let builder = FmaGateInBaseFieldWithoutConstant::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<32>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<16>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<8>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = DotProductGate::<4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = SelectionGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ParallelSelectionGate::<4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = PublicInputGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ReductionGate::<_, 4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = NopGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+
+            builder
+        }
+
+        let builder_impl =
+            CsReferenceImplementationBuilder::<F, P, DevCSConfig>::new(geometry, max_trace_len);
+        let builder = new_builder::<_, F>(builder_impl);
+
+        let builder = configure(builder);
+        let mut owned_cs = builder.build(max_variables);
+
+        // add tables
+        let table = create_xor8_table();
+        owned_cs.add_lookup_table::<Xor8Table, 3>(table);
+
+        let table = create_byte_split_table::<F, 4>();
+        owned_cs.add_lookup_table::<ByteSplitTable<4>, 3>(table);
+
+        owned_cs
+    }
+
+    fn word_constants<const N: usize>(
+        cs: &mut impl ConstraintSystem<F>,
+        words: [u64; N],
+    ) -> [Blake2bWord<F>; N] {
+        words.map(|el| blake2b_word_constant(cs, el))
+    }
+
+    fn run_compression(num_rounds: usize, final_block: bool) -> Vec<u8> {
+        let mut owned_cs = create_cs(1 << 20);
+        let cs = &mut owned_cs;
+
+        // parameter block of the unkeyed BLAKE2b-512 hash of "abc"
+        let mut h = BLAKE2B_IV;
+        h[0] ^= 0x01010040;
+        let mut m = [0u64; BLAKE2B_MESSAGE_WORDS];
+        m[0] = 0x636261;
+
+        let h = word_constants(cs, h);
+        let m = word_constants(cs, m);
+        let t = word_constants(cs, [3, 0]);
+        let f = Boolean::allocate(cs, final_block);
+
+        let mut v = blake2b_initial_working_vector(cs, &h, &t, f);
+        for round in 0..num_rounds {
+            blake2b_round(cs, &mut v, &m, round % BLAKE2B_NUM_SIGMA_PERMUTATIONS);
+        }
+        let new_state = blake2b_finalize(cs, &h, &v);
+
+        let mut result = vec![];
+        for word in new_state.iter() {
+            for byte in word.iter() {
+                result.push(byte.witness_hook(&*cs)().unwrap());
+            }
+        }
+
+        cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        let worker = Worker::new();
+        assert!(cs.check_if_satisfied(&worker));
+
+        result
+    }
+
+    #[test]
+    fn test_blake2b_compression() {
+        // matches BLAKE2b-512("abc")
+        assert_eq!(
+            hex::encode(run_compression(12, true)),
+            "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+        );
+        assert_eq!(
+            hex::encode(run_compression(12, false)),
+            "75ab69d3190a562c51aef8d88f1c2775876944407270c42c9844252c26d2875298743e7f6d5ea2f2d3e8d226039cd31b4e426ac4f2d3d666a610c2116fde4735"
+        );
+    }
+
+    #[test]
+    fn test_blake2b_compression_with_few_rounds() {
+        assert_eq!(
+            hex::encode(run_compression(0, true)),
+            "08c9bcf367e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d282e6ad7f520e511f6c3e2b8c68059b9442be0454267ce079217e1319cde05b"
+        );
+        assert_eq!(
+            hex::encode(run_compression(1, true)),
+            "b63a380cb2897d521994a85234ee2c181b5f844d2c624c002677e9703449d2fba551b3a8333bcdf5f2f7e08993d53923de3d64fcc68c034e717b9293fed7a421"
+        );
+    }
+
+    #[test]
+    fn test_blake2f_input_validation() {
+        let mut owned_cs = create_cs(1 << 20);
+        let cs = &mut owned_cs;
+
+        let mut results = vec![];
+        for (input_byte_length, final_block_flag) in
+            [(213, 0), (213, 1), (212, 1), (214, 1), (213, 2)]
+        {
+            let input_byte_length = UInt32::allocate_checked(cs, input_byte_length);
+            let final_block_flag = UInt8::allocate_checked(cs, final_block_flag);
+            let (input_is_valid, final_block_flag_is_one) =
+                validate_blake2f_input(cs, input_byte_length, final_block_flag);
+            results.push((
+                input_is_valid.witness_hook(&*cs)().unwrap(),
+                final_block_flag_is_one.witness_hook(&*cs)().unwrap(),
+            ));
+        }
+
+        assert_eq!(
+            results,
+            vec![
+                (true, false),
+                (true, true),
+                (false, true),
+                (false, true),
+                (false, false),
+            ]
+        );
+
+        cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        let worker = Worker::new();
+        assert!(cs.check_if_satisfied(&worker));
+    }
+}
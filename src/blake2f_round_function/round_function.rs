@@ -0,0 +1,192 @@
+use super::*;
+
+use boojum::gadgets::blake2s::mixing_function::xor_many;
+
+pub const BLAKE2B_WORD_BYTES: usize = 8;
+pub const BLAKE2B_STATE_WIDTH: usize = 8;
+pub const BLAKE2B_WORKING_STATE_WIDTH: usize = 16;
+pub const BLAKE2B_MESSAGE_WORDS: usize = 16;
+pub const BLAKE2B_NUM_SIGMA_PERMUTATIONS: usize = 10;
+
+pub const BLAKE2B_IV: [u64; BLAKE2B_STATE_WIDTH] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+pub const BLAKE2B_SIGMA: [[usize; BLAKE2B_MESSAGE_WORDS]; BLAKE2B_NUM_SIGMA_PERMUTATIONS] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+// 64-bit words are represented as little-endian bytes, so rotations by whole bytes are free
+pub type Blake2bWord<F> = [UInt8<F>; BLAKE2B_WORD_BYTES];
+
+pub fn blake2b_word_constant<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    value: u64,
+) -> Blake2bWord<F> {
+    value
+        .to_le_bytes()
+        .map(|el| UInt8::allocated_constant(cs, el))
+}
+
+fn xor_words<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    a: &Blake2bWord<F>,
+    b: &Blake2bWord<F>,
+) -> Blake2bWord<F> {
+    let a = a.map(|el| el.get_variable());
+    let b = b.map(|el| el.get_variable());
+    let result = xor_many(cs, &a, &b);
+
+    result.map(|el| unsafe { UInt8::from_variable_unchecked(el) })
+}
+
+fn add_words<F: SmallField, CS: ConstraintSystem<F>, const N: usize>(
+    cs: &mut CS,
+    words: [&Blake2bWord<F>; N],
+) -> Blake2bWord<F> {
+    let zero_u32 = UInt32::zero(cs);
+    let mut low = zero_u32;
+    let mut high = zero_u32;
+    for word in words.into_iter() {
+        let [b0, b1, b2, b3, b4, b5, b6, b7] = *word;
+        let word_low = UInt32::from_le_bytes(cs, [b0, b1, b2, b3]);
+        let word_high = UInt32::from_le_bytes(cs, [b4, b5, b6, b7]);
+
+        let (new_low, carry) = low.overflowing_add(cs, word_low);
+        let (new_high, _) = high.overflowing_add(cs, word_high);
+        let carry = unsafe { UInt32::from_variable_unchecked(carry.get_variable()) };
+        let (new_high, _) = new_high.overflowing_add(cs, carry);
+
+        low = new_low;
+        high = new_high;
+    }
+
+    let [b0, b1, b2, b3] = low.to_le_bytes(cs);
+    let [b4, b5, b6, b7] = high.to_le_bytes(cs);
+
+    [b0, b1, b2, b3, b4, b5, b6, b7]
+}
+
+fn rotate_right_by_bytes<F: SmallField>(word: &Blake2bWord<F>, bytes: usize) -> Blake2bWord<F> {
+    std::array::from_fn(|i| word[(i + bytes) % BLAKE2B_WORD_BYTES])
+}
+
+// rotation by 63 bits is a rotation to the left by one bit
+fn rotate_right_by_63<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    word: &Blake2bWord<F>,
+) -> Blake2bWord<F> {
+    let top_bits = word.map(|el| {
+        let bits = Num::<F>::from_variable(el.get_variable()).spread_into_bits::<_, 8>(cs);
+        bits[7]
+    });
+
+    let mut minus_shift = F::SHIFTS[8];
+    minus_shift.negate();
+
+    std::array::from_fn(|i| {
+        let carry_in = top_bits[(i + BLAKE2B_WORD_BYTES - 1) % BLAKE2B_WORD_BYTES];
+        let lc = [
+            (word[i].get_variable(), F::SHIFTS[1]),
+            (top_bits[i].get_variable(), minus_shift),
+            (carry_in.get_variable(), F::ONE),
+        ];
+        let as_num = Num::linear_combination(cs, &lc);
+
+        // it's a byte by construction
+        unsafe { UInt8::from_variable_unchecked(as_num.get_variable()) }
+    })
+}
+
+fn mixing_function<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    v: &mut [Blake2bWord<F>; BLAKE2B_WORKING_STATE_WIDTH],
+    [a, b, c, d]: [usize; 4],
+    x: &Blake2bWord<F>,
+    y: &Blake2bWord<F>,
+) {
+    v[a] = add_words(cs, [&v[a], &v[b], x]);
+    let t = xor_words(cs, &v[d], &v[a]);
+    v[d] = rotate_right_by_bytes(&t, 4);
+    v[c] = add_words(cs, [&v[c], &v[d]]);
+    let t = xor_words(cs, &v[b], &v[c]);
+    v[b] = rotate_right_by_bytes(&t, 3);
+
+    v[a] = add_words(cs, [&v[a], &v[b], y]);
+    let t = xor_words(cs, &v[d], &v[a]);
+    v[d] = rotate_right_by_bytes(&t, 2);
+    v[c] = add_words(cs, [&v[c], &v[d]]);
+    let t = xor_words(cs, &v[b], &v[c]);
+    v[b] = rotate_right_by_63(cs, &t);
+}
+
+/// Single round of the BLAKE2b compression function over the working vector,
+/// that uses the message schedule for the given round index modulo 10
+pub fn blake2b_round<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    v: &mut [Blake2bWord<F>; BLAKE2B_WORKING_STATE_WIDTH],
+    m: &[Blake2bWord<F>; BLAKE2B_MESSAGE_WORDS],
+    sigma_index: usize,
+) {
+    let s = &BLAKE2B_SIGMA[sigma_index];
+
+    mixing_function(cs, v, [0, 4, 8, 12], &m[s[0]], &m[s[1]]);
+    mixing_function(cs, v, [1, 5, 9, 13], &m[s[2]], &m[s[3]]);
+    mixing_function(cs, v, [2, 6, 10, 14], &m[s[4]], &m[s[5]]);
+    mixing_function(cs, v, [3, 7, 11, 15], &m[s[6]], &m[s[7]]);
+
+    mixing_function(cs, v, [0, 5, 10, 15], &m[s[8]], &m[s[9]]);
+    mixing_function(cs, v, [1, 6, 11, 12], &m[s[10]], &m[s[11]]);
+    mixing_function(cs, v, [2, 7, 8, 13], &m[s[12]], &m[s[13]]);
+    mixing_function(cs, v, [3, 4, 9, 14], &m[s[14]], &m[s[15]]);
+}
+
+/// Initializes the working vector from the state, offset counters and the final block flag
+pub fn blake2b_initial_working_vector<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    h: &[Blake2bWord<F>; BLAKE2B_STATE_WIDTH],
+    t: &[Blake2bWord<F>; 2],
+    final_block_flag: Boolean<F>,
+) -> [Blake2bWord<F>; BLAKE2B_WORKING_STATE_WIDTH] {
+    let iv = BLAKE2B_IV.map(|el| blake2b_word_constant(cs, el));
+    let mut v = [h[0]; BLAKE2B_WORKING_STATE_WIDTH];
+    v[..BLAKE2B_STATE_WIDTH].copy_from_slice(&h[..]);
+    v[BLAKE2B_STATE_WIDTH..].copy_from_slice(&iv[..]);
+
+    v[12] = xor_words(cs, &v[12], &t[0]);
+    v[13] = xor_words(cs, &v[13], &t[1]);
+
+    let all_ones = blake2b_word_constant(cs, u64::MAX);
+    let inverted = xor_words(cs, &v[14], &all_ones);
+    v[14] = <Blake2bWord<F>>::conditionally_select(cs, final_block_flag, &inverted, &v[14]);
+
+    v
+}
+
+/// Computes the new state as h ^ v[0..8] ^ v[8..16]
+pub fn blake2b_finalize<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    h: &[Blake2bWord<F>; BLAKE2B_STATE_WIDTH],
+    v: &[Blake2bWord<F>; BLAKE2B_WORKING_STATE_WIDTH],
+) -> [Blake2bWord<F>; BLAKE2B_STATE_WIDTH] {
+    std::array::from_fn(|i| {
+        let t = xor_words(cs, &h[i], &v[i]);
+        xor_words(cs, &t, &v[i + BLAKE2B_STATE_WIDTH])
+    })
+}
@@ -0,0 +1,649 @@
+use super::*;
+
+use crate::base_structures::precompile_input_outputs::PrecompileFunctionOutputData;
+use crate::demux_log_queue::StorageLogQueue;
+use crate::ethereum_types::U256;
+use crate::fsm_input_output::circuit_inputs::INPUT_OUTPUT_COMMITMENT_LENGTH;
+
+use arrayvec::ArrayVec;
+use boojum::algebraic_props::round_function::AlgebraicRoundFunction;
+use boojum::cs::traits::cs::ConstraintSystem;
+use boojum::field::SmallField;
+use boojum::gadgets::boolean::Boolean;
+use boojum::gadgets::curves::sw_projective::SWProjectivePoint;
+
+use boojum::gadgets::num::Num;
+use boojum::gadgets::queue::CircuitQueueWitness;
+use boojum::gadgets::queue::QueueState;
+use boojum::gadgets::traits::allocatable::{CSAllocatableExt, CSPlaceholder};
+use boojum::gadgets::traits::round_function::CircuitRoundFunction;
+use boojum::gadgets::traits::selectable::Selectable;
+
+use boojum::gadgets::u160::UInt160;
+use boojum::gadgets::u256::UInt256;
+use boojum::gadgets::u32::UInt32;
+use boojum::gadgets::u8::UInt8;
+
+use std::collections::VecDeque;
+use std::sync::{Arc, RwLock};
+use zkevm_opcode_defs::system_params::PRECOMPILE_AUX_BYTE;
+
+use crate::ecrecover::baseline::convert_uint256_to_field_element;
+use crate::ecrecover::new_optimized::convert_field_element_to_uint256;
+
+#[derive(Derivative, CSSelectable)]
+#[derivative(Clone, Debug)]
+pub struct Bn254EcAddPrecompileCallParams<F: SmallField> {
+    pub input_page: UInt32<F>,
+    pub input_offset: UInt32<F>,
+    pub output_page: UInt32<F>,
+    pub output_offset: UInt32<F>,
+}
+
+impl<F: SmallField> Bn254EcAddPrecompileCallParams<F> {
+    pub fn from_encoding<CS: ConstraintSystem<F>>(_cs: &mut CS, encoding: UInt256<F>) -> Self {
+        let input_offset = encoding.inner[0];
+        let output_offset = encoding.inner[2];
+        let input_page = encoding.inner[4];
+        let output_page = encoding.inner[5];
+
+        let new = Self {
+            input_page,
+            input_offset,
+            output_page,
+            output_offset,
+        };
+
+        new
+    }
+}
+
+const EXCEPTION_FLAGS_ARR_LEN: usize = 2;
+
+/// Checks that the coordinates are in range and either form a point on the curve, or are (0, 0) that encodes
+/// the point at infinity. Returns the point masked to the generator if it's invalid or at infinity,
+/// so it can be safely used in arithmetic formulas, and flags for "infinity" and "invalid" cases
+pub(crate) fn bn254_validate_and_mask_point<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    x: &UInt256<F>,
+    y: &UInt256<F>,
+    base_field_params: &Arc<BN254BaseNNFieldParams>,
+) -> (
+    (BN254BaseNNField<F>, BN254BaseNNField<F>),
+    Boolean<F>,
+    Boolean<F>,
+) {
+    use boojum::pairing::GenericCurveAffine;
+    let curve_b = BN254Affine::b_coeff();
+    let mut curve_b_nn = BN254BaseNNField::<F>::allocated_constant(cs, curve_b, &base_field_params);
+
+    let generator = BN254Affine::one();
+    let (gen_x, gen_y) = generator.into_xy_unchecked();
+    let gen_x_nn = BN254BaseNNField::allocated_constant(cs, gen_x, base_field_params);
+    let gen_y_nn = BN254BaseNNField::allocated_constant(cs, gen_y, base_field_params);
+
+    let bn254_p_u256 = U256([
+        base_field_params.modulus_u1024.as_ref().as_words()[0],
+        base_field_params.modulus_u1024.as_ref().as_words()[1],
+        base_field_params.modulus_u1024.as_ref().as_words()[2],
+        base_field_params.modulus_u1024.as_ref().as_words()[3],
+    ]);
+    let bn254_p_u256 = UInt256::allocated_constant(cs, bn254_p_u256);
+
+    // we check ranges upfront, and conversion function will work over masked values
+    let mut x_as_u256 = *x;
+    let mut y_as_u256 = *y;
+
+    let (_res, x_is_in_range) = x_as_u256.overflowing_sub(cs, &bn254_p_u256);
+    x_as_u256 = x_as_u256.mask(cs, x_is_in_range);
+    let (_res, y_is_in_range) = y_as_u256.overflowing_sub(cs, &bn254_p_u256);
+    y_as_u256 = y_as_u256.mask(cs, y_is_in_range);
+    let coordinates_are_in_range = Boolean::multi_and(cs, &[x_is_in_range, y_is_in_range]);
+
+    // (0, 0) is not on curve, so it's unambiguous encoding of the point at infinity
+    let x_is_zero = x_as_u256.is_zero(cs);
+    let y_is_zero = y_as_u256.is_zero(cs);
+    let is_infinity = Boolean::multi_and(cs, &[x_is_zero, y_is_zero, coordinates_are_in_range]);
+
+    let mut x_fe = convert_uint256_to_field_element(cs, &x_as_u256, &base_field_params);
+    let mut y_fe = convert_uint256_to_field_element(cs, &y_as_u256, &base_field_params);
+
+    // perform on-curve check, curve equation is y^2 = x^3 + b
+    let mut lhs = y_fe.clone();
+    let mut lhs = lhs.mul(cs, &mut y_fe);
+    lhs.normalize(cs);
+
+    let mut rhs = x_fe.clone();
+    let mut rhs = rhs.mul(cs, &mut x_fe);
+    let mut rhs = rhs.mul(cs, &mut x_fe);
+    let mut rhs = rhs.add(cs, &mut curve_b_nn);
+    rhs.normalize(cs);
+
+    let is_on_curve = NonNativeFieldOverU16::equals(cs, &mut lhs, &mut rhs);
+    let is_valid = Boolean::multi_or(cs, &[is_on_curve, is_infinity]);
+    let is_valid = Boolean::multi_and(cs, &[is_valid, coordinates_are_in_range]);
+    let is_invalid = is_valid.negated(cs);
+
+    // we can mask point to ensure that our arithmetic formulas work
+    let should_mask = Boolean::multi_or(cs, &[is_invalid, is_infinity]);
+    let x_fe = NonNativeFieldOverU16::conditionally_select(cs, should_mask, &gen_x_nn, &x_fe);
+    let y_fe = NonNativeFieldOverU16::conditionally_select(cs, should_mask, &gen_y_nn, &y_fe);
+
+    ((x_fe, y_fe), is_infinity, is_invalid)
+}
+
+/// Converts the point into the (x, y) pair of 256-bit integers, where the point at infinity
+/// is encoded as (0, 0)
+pub(crate) fn bn254_point_to_uint256_pair<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    mut point: SWProjectivePoint<F, BN254Affine, BN254BaseNNField<F>>,
+) -> (UInt256<F>, UInt256<F>) {
+    use boojum::pairing::GenericCurveAffine;
+
+    let ((mut x, mut y), is_infinity) = point.convert_to_affine_or_default(cs, BN254Affine::one());
+    x.enforce_reduced(cs);
+    y.enforce_reduced(cs);
+
+    let x = convert_field_element_to_uint256(cs, x);
+    let y = convert_field_element_to_uint256(cs, y);
+
+    let x = x.mask_negated(cs, is_infinity);
+    let y = y.mask_negated(cs, is_infinity);
+
+    (x, y)
+}
+
+fn bn254_ecadd_function_inner<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    x1: &UInt256<F>,
+    y1: &UInt256<F>,
+    x2: &UInt256<F>,
+    y2: &UInt256<F>,
+    base_field_params: &Arc<BN254BaseNNFieldParams>,
+) -> (Boolean<F>, (UInt256<F>, UInt256<F>)) {
+    let mut exception_flags = ArrayVec::<_, EXCEPTION_FLAGS_ARR_LEN>::new();
+
+    // we use non-compressed points, so we:
+    // - check that both points are on curve or are points at infinity
+    // - compute the sum using complete formulas
+
+    let ((x1_fe, y1_fe), p1_is_infinity, p1_is_invalid) =
+        bn254_validate_and_mask_point(cs, x1, y1, base_field_params);
+    exception_flags.push(p1_is_invalid);
+    let ((x2_fe, y2_fe), p2_is_infinity, p2_is_invalid) =
+        bn254_validate_and_mask_point(cs, x2, y2, base_field_params);
+    exception_flags.push(p2_is_invalid);
+
+    // it's safe since we checked not-on-curve above, and masked points otherwise
+    let p1 = SWProjectivePoint::<F, BN254Affine, BN254BaseNNField<F>>::from_xy_unchecked(
+        cs, x1_fe, y1_fe,
+    );
+    let zero_point =
+        SWProjectivePoint::<F, BN254Affine, BN254BaseNNField<F>>::zero(cs, base_field_params);
+    let mut p1 = Selectable::conditionally_select(cs, p1_is_infinity, &zero_point, &p1);
+
+    // addition formulas are complete for the case of affine point not being at infinity,
+    // so P1 == P2 and P1 == -P2 don't require special handling
+    let sum = p1.add_mixed(cs, &mut (x2_fe, y2_fe));
+    let sum = Selectable::conditionally_select(cs, p2_is_infinity, &p1, &sum);
+
+    let (x, y) = bn254_point_to_uint256_pair(cs, sum);
+
+    let any_exception = Boolean::multi_or(cs, &exception_flags[..]);
+    let x = x.mask_negated(cs, any_exception);
+    let y = y.mask_negated(cs, any_exception);
+    let all_ok = any_exception.negated(cs);
+
+    (all_ok, (x, y))
+}
+
+pub fn bn254_ecadd_function_entry_point<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    witness: Bn254EcAddCircuitInstanceWitness<F>,
+    round_function: &R,
+    limit: usize,
+) -> [Num<F>; INPUT_OUTPUT_COMMITMENT_LENGTH]
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    assert!(limit <= u32::MAX as usize);
+
+    let Bn254EcAddCircuitInstanceWitness {
+        closed_form_input,
+        requests_queue_witness,
+        memory_reads_witness,
+    } = witness;
+
+    let memory_reads_witness: VecDeque<_> = memory_reads_witness.into_iter().flatten().collect();
+
+    let precompile_address = UInt160::allocated_constant(cs, BN254_ECADD_PRECOMPILE_FORMAL_ADDRESS);
+    let aux_byte_for_precompile = UInt8::allocated_constant(cs, PRECOMPILE_AUX_BYTE);
+
+    let base_params = Arc::new(bn254_base_field_params());
+
+    let mut structured_input =
+        Bn254EcAddCircuitInputOutput::alloc_ignoring_outputs(cs, closed_form_input.clone());
+    let start_flag = structured_input.start_flag;
+
+    let requests_queue_state_from_input = structured_input.observable_input.initial_log_queue_state;
+
+    // it must be trivial
+    requests_queue_state_from_input.enforce_trivial_head(cs);
+
+    let requests_queue_state_from_fsm = structured_input.hidden_fsm_input.log_queue_state;
+
+    let requests_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &requests_queue_state_from_input,
+        &requests_queue_state_from_fsm,
+    );
+
+    let memory_queue_state_from_input =
+        structured_input.observable_input.initial_memory_queue_state;
+
+    // it must be trivial
+    memory_queue_state_from_input.enforce_trivial_head(cs);
+
+    let memory_queue_state_from_fsm = structured_input.hidden_fsm_input.memory_queue_state;
+
+    let memory_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &memory_queue_state_from_input,
+        &memory_queue_state_from_fsm,
+    );
+
+    let mut requests_queue = StorageLogQueue::<F, R>::from_state(cs, requests_queue_state);
+    let queue_witness = CircuitQueueWitness::from_inner_witness(requests_queue_witness);
+    requests_queue.witness = Arc::new(queue_witness);
+
+    let mut memory_queue = MemoryQueue::<F, R>::from_state(cs, memory_queue_state);
+
+    let one_u32 = UInt32::allocated_constant(cs, 1u32);
+    let zero_u256 = UInt256::zero(cs);
+    let boolean_false = Boolean::allocated_constant(cs, false);
+    let boolean_true = Boolean::allocated_constant(cs, true);
+
+    use crate::storage_application::ConditionalWitnessAllocator;
+    let read_queries_allocator = ConditionalWitnessAllocator::<F, UInt256<F>> {
+        witness_source: Arc::new(RwLock::new(memory_reads_witness)),
+    };
+
+    for _cycle in 0..limit {
+        let is_empty = requests_queue.is_empty(cs);
+        let should_process = is_empty.negated(cs);
+        let (request, _) = requests_queue.pop_front(cs, should_process);
+
+        let mut precompile_call_params =
+            Bn254EcAddPrecompileCallParams::from_encoding(cs, request.key);
+
+        let timestamp_to_use_for_read = request.timestamp;
+        let timestamp_to_use_for_write = timestamp_to_use_for_read.add_no_overflow(cs, one_u32);
+
+        Num::conditionally_enforce_equal(
+            cs,
+            should_process,
+            &Num::from_variable(request.aux_byte.get_variable()),
+            &Num::from_variable(aux_byte_for_precompile.get_variable()),
+        );
+        for (a, b) in request
+            .address
+            .inner
+            .iter()
+            .zip(precompile_address.inner.iter())
+        {
+            Num::conditionally_enforce_equal(
+                cs,
+                should_process,
+                &Num::from_variable(a.get_variable()),
+                &Num::from_variable(b.get_variable()),
+            );
+        }
+
+        let mut read_values = [zero_u256; MEMORY_QUERIES_PER_CALL];
+        let mut bias_variable = should_process.get_variable();
+        for dst in read_values.iter_mut() {
+            let read_query_value: UInt256<F> = read_queries_allocator
+                .conditionally_allocate_biased(cs, should_process, bias_variable);
+            bias_variable = read_query_value.inner[0].get_variable();
+
+            *dst = read_query_value;
+
+            let read_query = MemoryQuery {
+                timestamp: timestamp_to_use_for_read,
+                memory_page: precompile_call_params.input_page,
+                index: precompile_call_params.input_offset,
+                rw_flag: boolean_false,
+                is_ptr: boolean_false,
+                value: read_query_value,
+            };
+
+            let _ = memory_queue.push(cs, read_query, should_process);
+
+            precompile_call_params.input_offset = precompile_call_params
+                .input_offset
+                .add_no_overflow(cs, one_u32);
+        }
+
+        let [x1_as_u256, y1_as_u256, x2_as_u256, y2_as_u256] = read_values;
+
+        let (success, (x, y)) = bn254_ecadd_function_inner(
+            cs,
+            &x1_as_u256,
+            &y1_as_u256,
+            &x2_as_u256,
+            &y2_as_u256,
+            &base_params,
+        );
+
+        let success_as_u32 = unsafe { UInt32::from_variable_unchecked(success.get_variable()) };
+        let mut success_as_u256 = zero_u256;
+        success_as_u256.inner[0] = success_as_u32;
+
+        let success_query = MemoryQuery {
+            timestamp: timestamp_to_use_for_write,
+            memory_page: precompile_call_params.output_page,
+            index: precompile_call_params.output_offset,
+            rw_flag: boolean_true,
+            value: success_as_u256,
+            is_ptr: boolean_false,
+        };
+
+        precompile_call_params.output_offset = precompile_call_params
+            .output_offset
+            .add_no_overflow(cs, one_u32);
+
+        let _ = memory_queue.push(cs, success_query, should_process);
+
+        for value in [x, y].into_iter() {
+            let value_query = MemoryQuery {
+                timestamp: timestamp_to_use_for_write,
+                memory_page: precompile_call_params.output_page,
+                index: precompile_call_params.output_offset,
+                rw_flag: boolean_true,
+                value,
+                is_ptr: boolean_false,
+            };
+
+            precompile_call_params.output_offset = precompile_call_params
+                .output_offset
+                .add_no_overflow(cs, one_u32);
+
+            let _ = memory_queue.push(cs, value_query, should_process);
+        }
+    }
+
+    requests_queue.enforce_consistency(cs);
+
+    // form the final state
+    let done = requests_queue.is_empty(cs);
+    structured_input.completion_flag = done;
+    structured_input.observable_output = PrecompileFunctionOutputData::placeholder(cs);
+
+    let final_memory_state = memory_queue.into_state();
+    let final_requets_state = requests_queue.into_state();
+
+    structured_input.observable_output.final_memory_state = QueueState::conditionally_select(
+        cs,
+        structured_input.completion_flag,
+        &final_memory_state,
+        &structured_input.observable_output.final_memory_state,
+    );
+
+    structured_input.hidden_fsm_output.log_queue_state = final_requets_state;
+    structured_input.hidden_fsm_output.memory_queue_state = final_memory_state;
+
+    // self-check
+    structured_input.hook_compare_witness(cs, &closed_form_input);
+
+    use boojum::cs::gates::PublicInputGate;
+
+    let compact_form =
+        ClosedFormInputCompactForm::from_full_form(cs, &structured_input, round_function);
+    let input_commitment = commit_variable_length_encodable_item(cs, &compact_form, round_function);
+    for el in input_commitment.iter() {
+        let gate = PublicInputGate::new(el.get_variable());
+        gate.add_to_cs(cs);
+    }
+
+    input_commitment
+}
+
+#[cfg(test)]
+mod test {
+    use boojum::field::goldilocks::GoldilocksField;
+    use boojum::gadgets::traits::allocatable::CSAllocatable;
+    use boojum::worker::Worker;
+
+    use super::*;
+
+    type F = GoldilocksField;
+    type P = GoldilocksField;
+
+    use boojum::config::DevCSConfig;
+
+    use boojum::cs::cs_builder::*;
+    use boojum::cs::cs_builder_reference::CsReferenceImplementationBuilder;
+    use boojum::cs::gates::*;
+    use boojum::cs::implementations::reference_cs::CSReferenceImplementation;
+    use boojum::cs::traits::gate::GatePlacementStrategy;
+    use boojum::cs::CSGeometry;
+    use boojum::cs::*;
+    use boojum::gadgets::tables::*;
+    use boojum::pairing::ff::{PrimeField, PrimeFieldRepr};
+    use boojum::pairing::{GenericCurveAffine, GenericCurveProjective};
+
+    fn create_cs(
+        max_trace_len: usize,
+    ) -> CSReferenceImplementation<
+        F,
+        P,
+        DevCSConfig,
+        impl GateConfigurationHolder<F>,
+        impl StaticToolboxHolder,
+    > {
+        let geometry = CSGeometry {
+            num_columns_under_copy_permutation: 80,
+            num_witness_columns: 0,
+            num_constant_columns: 4,
+            max_allowed_constraint_degree: 8,
+        };
+        let max_variables = 1 << 26;
+
+        fn configure<
+            F: SmallField,
+            T: CsBuilderImpl<F, T>,
+            GC: GateConfigurationHolder<F>,
+            TB: StaticToolboxHolder,
+        >(
+            builder: CsBuilder<T, F, GC, TB>,
+        ) -> CsBuilder<T, F, impl GateConfigurationHolder<F>, impl StaticToolboxHolder> {
+            let builder = builder.allow_lookup(
+                LookupParameters::UseSpecializedColumnsWithTableIdAsConstant {
+                    width: 3,
+                    num_repetitions: 16,
+                    share_table_id: true,
+                },
+            );
+
+            let builder = ConstantsAllocatorGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = BooleanConstraintGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseSpecializedColumns {
+                    num_repetitions: 1,
+                    share_constants: false,
+                },
+            );
+            let builder = U8x4FMAGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ZeroCheckGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+                false,
+            );
+            let builder = FmaGateInBaseFieldWithoutConstant::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<32>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<16>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<8>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = DotProductGate::<4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = SelectionGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ParallelSelectionGate::<4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = PublicInputGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ReductionGate::<_, 4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = NopGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+
+            builder
+        }
+
+        let builder_impl =
+            CsReferenceImplementationBuilder::<F, P, DevCSConfig>::new(geometry, max_trace_len);
+        let builder = new_builder::<_, F>(builder_impl);
+
+        let builder = configure(builder);
+        let mut owned_cs = builder.build(max_variables);
+
+        // add tables
+        let table = create_xor8_table();
+        owned_cs.add_lookup_table::<Xor8Table, 3>(table);
+
+        let table = create_byte_split_table::<F, 4>();
+        owned_cs.add_lookup_table::<ByteSplitTable<4>, 3>(table);
+
+        owned_cs
+    }
+
+    fn repr_into_u256<T: PrimeFieldRepr>(repr: T) -> U256 {
+        let mut u256 = U256::zero();
+        u256.0.copy_from_slice(&repr.as_ref()[..4]);
+
+        u256
+    }
+
+    fn point_into_u256_pair(point: BN254Affine) -> (U256, U256) {
+        if point.is_zero() {
+            return (U256::zero(), U256::zero());
+        }
+        let (x, y) = point.into_xy_unchecked();
+
+        (repr_into_u256(x.into_repr()), repr_into_u256(y.into_repr()))
+    }
+
+    fn run_ecadd(p1: (U256, U256), p2: (U256, U256)) -> (bool, (U256, U256)) {
+        let mut owned_cs = create_cs(1 << 20);
+        let cs = &mut owned_cs;
+
+        let base_params = Arc::new(bn254_base_field_params());
+
+        let x1 = UInt256::allocate(cs, p1.0);
+        let y1 = UInt256::allocate(cs, p1.1);
+        let x2 = UInt256::allocate(cs, p2.0);
+        let y2 = UInt256::allocate(cs, p2.1);
+
+        let (no_error, (x, y)) = bn254_ecadd_function_inner(cs, &x1, &y1, &x2, &y2, &base_params);
+
+        let no_error = no_error.witness_hook(&*cs)().unwrap();
+        let x = x.witness_hook(&*cs)().unwrap();
+        let y = y.witness_hook(&*cs)().unwrap();
+
+        cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        let worker = Worker::new();
+        assert!(cs.check_if_satisfied(&worker));
+
+        (no_error, (x, y))
+    }
+
+    #[test]
+    fn test_bn254_ecadd() {
+        let g = BN254Affine::one();
+        let two_g = g
+            .mul(BN254Fr::from_str("2").unwrap().into_repr())
+            .into_affine();
+        let three_g = g
+            .mul(BN254Fr::from_str("3").unwrap().into_repr())
+            .into_affine();
+
+        // generic case
+        let (no_error, result) = run_ecadd(point_into_u256_pair(g), point_into_u256_pair(two_g));
+        assert!(no_error);
+        assert_eq!(result, point_into_u256_pair(three_g));
+
+        // doubling
+        let (no_error, result) = run_ecadd(point_into_u256_pair(g), point_into_u256_pair(g));
+        assert!(no_error);
+        assert_eq!(result, point_into_u256_pair(two_g));
+
+        // P + (-P) = O
+        let mut minus_g = g;
+        minus_g.negate();
+        let (no_error, result) = run_ecadd(point_into_u256_pair(g), point_into_u256_pair(minus_g));
+        assert!(no_error);
+        assert_eq!(result, (U256::zero(), U256::zero()));
+
+        // O + P = P
+        let (no_error, result) =
+            run_ecadd((U256::zero(), U256::zero()), point_into_u256_pair(two_g));
+        assert!(no_error);
+        assert_eq!(result, point_into_u256_pair(two_g));
+    }
+
+    #[test]
+    fn test_bn254_ecadd_invalid_point() {
+        let g = BN254Affine::one();
+        let (x, y) = point_into_u256_pair(g);
+
+        // not on curve
+        let (no_error, result) = run_ecadd((x, y + U256::one()), (x, y));
+        assert!(!no_error);
+        assert_eq!(result, (U256::zero(), U256::zero()));
+
+        // coordinate is not in the field
+        let (no_error, result) = run_ecadd((U256::MAX, y), (x, y));
+        assert!(!no_error);
+        assert_eq!(result, (U256::zero(), U256::zero()));
+    }
+}
@@ -0,0 +1,51 @@
+use std::collections::VecDeque;
+
+use super::*;
+use crate::base_structures::precompile_input_outputs::*;
+use crate::base_structures::vm_state::*;
+use boojum::cs::Variable;
+use boojum::gadgets::queue::*;
+use boojum::gadgets::traits::allocatable::CSAllocatable;
+use boojum::gadgets::traits::allocatable::CSPlaceholder;
+use boojum::gadgets::traits::encodable::CircuitVarLengthEncodable;
+
+use boojum::gadgets::traits::auxiliary::PrettyComparison;
+
+#[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
+#[derivative(Clone, Copy, Debug)]
+#[DerivePrettyComparison("true")]
+pub struct Bn254EcAddCircuitFSMInputOutput<F: SmallField> {
+    pub log_queue_state: QueueState<F, QUEUE_STATE_WIDTH>,
+    pub memory_queue_state: QueueState<F, FULL_SPONGE_QUEUE_STATE_WIDTH>,
+}
+
+impl<F: SmallField> CSPlaceholder<F> for Bn254EcAddCircuitFSMInputOutput<F> {
+    fn placeholder<CS: ConstraintSystem<F>>(cs: &mut CS) -> Self {
+        Self {
+            log_queue_state: QueueState::<F, QUEUE_STATE_WIDTH>::placeholder(cs),
+            memory_queue_state: QueueState::<F, FULL_SPONGE_QUEUE_STATE_WIDTH>::placeholder(cs),
+        }
+    }
+}
+
+pub type Bn254EcAddCircuitInputOutput<F> = ClosedFormInput<
+    F,
+    Bn254EcAddCircuitFSMInputOutput<F>,
+    PrecompileFunctionInputData<F>,
+    PrecompileFunctionOutputData<F>,
+>;
+pub type Bn254EcAddCircuitInputOutputWitness<F> = ClosedFormInputWitness<
+    F,
+    Bn254EcAddCircuitFSMInputOutput<F>,
+    PrecompileFunctionInputData<F>,
+    PrecompileFunctionOutputData<F>,
+>;
+
+#[derive(Derivative, serde::Serialize, serde::Deserialize)]
+#[derivative(Clone, Debug, Default)]
+#[serde(bound = "")]
+pub struct Bn254EcAddCircuitInstanceWitness<F: SmallField> {
+    pub closed_form_input: Bn254EcAddCircuitInputOutputWitness<F>,
+    pub requests_queue_witness: CircuitQueueRawWitness<F, LogQuery<F>, 4, LOG_QUERY_PACKED_WIDTH>,
+    pub memory_reads_witness: VecDeque<[U256; MEMORY_QUERIES_PER_CALL]>,
+}
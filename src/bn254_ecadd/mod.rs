@@ -0,0 +1,84 @@
+use super::*;
+use crate::base_structures::log_query::*;
+use crate::base_structures::memory_query::*;
+
+use crate::ethereum_types::U256;
+
+use crate::fsm_input_output::*;
+
+use boojum::cs::traits::cs::ConstraintSystem;
+use boojum::field::SmallField;
+use boojum::gadgets::boolean::Boolean;
+
+use boojum::gadgets::non_native_field::implementations::*;
+
+use boojum::gadgets::queue::QueueState;
+
+use boojum::gadgets::traits::selectable::Selectable;
+use boojum::gadgets::traits::witnessable::WitnessHookable;
+
+use cs_derive::*;
+
+use crate::base_structures::precompile_input_outputs::formal_precompile_address;
+use zkevm_opcode_defs::ethereum_types::H160;
+
+use crate::base_structures::precompile_input_outputs::*;
+use crate::demux_log_queue::DemuxOutput;
+use crate::precompile_registry::PrecompileCircuit;
+use crate::scheduler::auxiliary::BaseLayerCircuitType;
+
+pub mod input;
+pub use self::input::*;
+
+// x1, y1, x2, y2
+pub const MEMORY_QUERIES_PER_CALL: usize = 4;
+
+pub const BN254_ECADD_PRECOMPILE_ADDRESS: u16 = 0x06;
+pub const BN254_ECADD_PRECOMPILE_FORMAL_ADDRESS: H160 =
+    formal_precompile_address(BN254_ECADD_PRECOMPILE_ADDRESS);
+
+pub struct ECAddPrecompileCircuit;
+
+impl PrecompileCircuit for ECAddPrecompileCircuit {
+    const NAME: &'static str = "ecadd";
+    const DEMUX_OUTPUT: DemuxOutput = DemuxOutput::ECAdd;
+    const CIRCUIT_TYPE: BaseLayerCircuitType = BaseLayerCircuitType::ECAddPrecompile;
+
+    type InputData<F: SmallField> = PrecompileFunctionInputData<F>;
+    type OutputData<F: SmallField> = PrecompileFunctionOutputData<F>;
+
+    fn formal_address() -> H160 {
+        BN254_ECADD_PRECOMPILE_FORMAL_ADDRESS
+    }
+}
+
+pub mod baseline;
+
+// characteristics of the base field for BN254 curve
+pub(crate) use boojum::pairing::bn256::Fq as BN254Fq;
+// order of group of points for BN254 curve
+pub(crate) use boojum::pairing::bn256::Fr as BN254Fr;
+// some affine point
+pub(crate) use boojum::pairing::bn256::G1Affine as BN254Affine;
+
+pub(crate) const BASE_FIELD_REPR_LIMBS: usize = 17;
+pub(crate) const SCALAR_FIELD_REPR_LIMBS: usize = 17;
+pub(crate) const BASE_FIELD_CANONICAL_REPR_LIMBS: usize = 16;
+pub(crate) const SCALAR_FIELD_CANONICAL_REPR_LIMBS: usize = 16;
+
+pub(crate) type BN254BaseNNFieldParams = NonNativeFieldOverU16Params<BN254Fq, 17>;
+pub(crate) type BN254ScalarNNFieldParams = NonNativeFieldOverU16Params<BN254Fr, 17>;
+
+pub(crate) type BN254BaseNNField<F> = NonNativeFieldOverU16<F, BN254Fq, 17>;
+pub(crate) type BN254ScalarNNField<F> = NonNativeFieldOverU16<F, BN254Fr, 17>;
+
+pub(crate) fn bn254_base_field_params() -> BN254BaseNNFieldParams {
+    NonNativeFieldOverU16Params::create()
+}
+
+pub(crate) fn bn254_scalar_field_params() -> BN254ScalarNNFieldParams {
+    NonNativeFieldOverU16Params::create()
+}
+
+// re-exports for integration
+pub use self::baseline::{bn254_ecadd_function_entry_point, Bn254EcAddPrecompileCallParams};
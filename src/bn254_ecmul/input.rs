@@ -0,0 +1,51 @@
+use std::collections::VecDeque;
+
+use super::*;
+use crate::base_structures::precompile_input_outputs::*;
+use crate::base_structures::vm_state::*;
+use boojum::cs::Variable;
+use boojum::gadgets::queue::*;
+use boojum::gadgets::traits::allocatable::CSAllocatable;
+use boojum::gadgets::traits::allocatable::CSPlaceholder;
+use boojum::gadgets::traits::encodable::CircuitVarLengthEncodable;
+
+use boojum::gadgets::traits::auxiliary::PrettyComparison;
+
+#[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
+#[derivative(Clone, Copy, Debug)]
+#[DerivePrettyComparison("true")]
+pub struct Bn254EcMulCircuitFSMInputOutput<F: SmallField> {
+    pub log_queue_state: QueueState<F, QUEUE_STATE_WIDTH>,
+    pub memory_queue_state: QueueState<F, FULL_SPONGE_QUEUE_STATE_WIDTH>,
+}
+
+impl<F: SmallField> CSPlaceholder<F> for Bn254EcMulCircuitFSMInputOutput<F> {
+    fn placeholder<CS: ConstraintSystem<F>>(cs: &mut CS) -> Self {
+        Self {
+            log_queue_state: QueueState::<F, QUEUE_STATE_WIDTH>::placeholder(cs),
+            memory_queue_state: QueueState::<F, FULL_SPONGE_QUEUE_STATE_WIDTH>::placeholder(cs),
+        }
+    }
+}
+
+pub type Bn254EcMulCircuitInputOutput<F> = ClosedFormInput<
+    F,
+    Bn254EcMulCircuitFSMInputOutput<F>,
+    PrecompileFunctionInputData<F>,
+    PrecompileFunctionOutputData<F>,
+>;
+pub type Bn254EcMulCircuitInputOutputWitness<F> = ClosedFormInputWitness<
+    F,
+    Bn254EcMulCircuitFSMInputOutput<F>,
+    PrecompileFunctionInputData<F>,
+    PrecompileFunctionOutputData<F>,
+>;
+
+#[derive(Derivative, serde::Serialize, serde::Deserialize)]
+#[derivative(Clone, Debug, Default)]
+#[serde(bound = "")]
+pub struct Bn254EcMulCircuitInstanceWitness<F: SmallField> {
+    pub closed_form_input: Bn254EcMulCircuitInputOutputWitness<F>,
+    pub requests_queue_witness: CircuitQueueRawWitness<F, LogQuery<F>, 4, LOG_QUERY_PACKED_WIDTH>,
+    pub memory_reads_witness: VecDeque<[U256; MEMORY_QUERIES_PER_CALL]>,
+}
@@ -0,0 +1,88 @@
+use super::*;
+use crate::base_structures::log_query::*;
+use crate::base_structures::memory_query::*;
+
+use crate::ethereum_types::U256;
+
+use crate::fsm_input_output::*;
+
+use boojum::cs::traits::cs::ConstraintSystem;
+use boojum::field::SmallField;
+use boojum::gadgets::boolean::Boolean;
+
+use boojum::gadgets::non_native_field::implementations::*;
+
+use boojum::gadgets::queue::QueueState;
+
+use boojum::gadgets::traits::selectable::Selectable;
+use boojum::gadgets::traits::witnessable::WitnessHookable;
+
+use cs_derive::*;
+
+use crate::base_structures::precompile_input_outputs::formal_precompile_address;
+use zkevm_opcode_defs::ethereum_types::H160;
+
+use crate::base_structures::precompile_input_outputs::*;
+use crate::demux_log_queue::DemuxOutput;
+use crate::precompile_registry::PrecompileCircuit;
+use crate::scheduler::auxiliary::BaseLayerCircuitType;
+
+pub mod input;
+pub use self::input::*;
+
+// x, y, scalar
+pub const MEMORY_QUERIES_PER_CALL: usize = 3;
+
+pub const BN254_ECMUL_PRECOMPILE_ADDRESS: u16 = 0x07;
+pub const BN254_ECMUL_PRECOMPILE_FORMAL_ADDRESS: H160 =
+    formal_precompile_address(BN254_ECMUL_PRECOMPILE_ADDRESS);
+
+pub struct ECMulPrecompileCircuit;
+
+impl PrecompileCircuit for ECMulPrecompileCircuit {
+    const NAME: &'static str = "ecmul";
+    const DEMUX_OUTPUT: DemuxOutput = DemuxOutput::ECMul;
+    const CIRCUIT_TYPE: BaseLayerCircuitType = BaseLayerCircuitType::ECMulPrecompile;
+
+    type InputData<F: SmallField> = PrecompileFunctionInputData<F>;
+    type OutputData<F: SmallField> = PrecompileFunctionOutputData<F>;
+
+    fn formal_address() -> H160 {
+        BN254_ECMUL_PRECOMPILE_FORMAL_ADDRESS
+    }
+}
+
+pub mod baseline;
+
+// curve types and field params are shared with ecAdd
+use crate::bn254_ecadd::{
+    bn254_base_field_params, bn254_scalar_field_params, BN254Affine, BN254BaseNNField,
+    BN254BaseNNFieldParams, BN254Fq, BN254Fr, BN254ScalarNNField, BN254ScalarNNFieldParams,
+    BASE_FIELD_CANONICAL_REPR_LIMBS, BASE_FIELD_REPR_LIMBS, SCALAR_FIELD_CANONICAL_REPR_LIMBS,
+};
+use crate::ecdsa::EcdsaCurve;
+
+/// BN254 for the curve-generic scalar multiplication. There is no endomorphism-based
+/// decomposition and no fixed base tables for it, so only the plain windowed multiplication is used
+#[derive(Clone, Copy, Debug)]
+pub struct Bn254EcdsaCurve;
+
+impl EcdsaCurve<BASE_FIELD_REPR_LIMBS> for Bn254EcdsaCurve {
+    type Base = BN254Fq;
+    type Scalar = BN254Fr;
+    type Affine = BN254Affine;
+
+    const BASE_FIELD_CANONICAL_REPR_LIMBS: usize = BASE_FIELD_CANONICAL_REPR_LIMBS;
+    const SCALAR_FIELD_CANONICAL_REPR_LIMBS: usize = SCALAR_FIELD_CANONICAL_REPR_LIMBS;
+
+    fn base_field_params() -> BN254BaseNNFieldParams {
+        bn254_base_field_params()
+    }
+
+    fn scalar_field_params() -> BN254ScalarNNFieldParams {
+        bn254_scalar_field_params()
+    }
+}
+
+// re-exports for integration
+pub use self::baseline::{bn254_ecmul_function_entry_point, Bn254EcMulPrecompileCallParams};
@@ -0,0 +1,96 @@
+use std::collections::VecDeque;
+
+use super::*;
+
+use crate::base_structures::precompile_input_outputs::*;
+use crate::base_structures::vm_state::*;
+use boojum::cs::Variable;
+use boojum::gadgets::queue::*;
+use boojum::gadgets::traits::allocatable::CSAllocatable;
+use boojum::gadgets::traits::allocatable::CSPlaceholder;
+use boojum::gadgets::traits::encodable::CircuitVarLengthEncodable;
+
+use boojum::cs::traits::cs::ConstraintSystem;
+use boojum::field::SmallField;
+use boojum::gadgets::boolean::Boolean;
+use boojum::gadgets::traits::auxiliary::PrettyComparison;
+use boojum::gadgets::traits::selectable::Selectable;
+use boojum::gadgets::traits::witnessable::WitnessHookable;
+use boojum::serde_utils::BigArraySerde;
+
+#[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
+#[derivative(Clone, Copy, Debug)]
+#[DerivePrettyComparison("true")]
+pub struct Bn254EcPairingFSM<F: SmallField> {
+    pub read_precompile_call: Boolean<F>,
+    pub read_words_for_round: Boolean<F>,
+    pub completed: Boolean<F>,
+    pub miller_loop_accumulator: [UInt256<F>; 12],
+    pub has_exception: Boolean<F>,
+    pub timestamp_to_use_for_read: UInt32<F>,
+    pub timestamp_to_use_for_write: UInt32<F>,
+    pub precompile_call_params: Bn254EcPairingPrecompileCallParams<F>,
+}
+
+impl<F: SmallField> CSPlaceholder<F> for Bn254EcPairingFSM<F> {
+    fn placeholder<CS: ConstraintSystem<F>>(cs: &mut CS) -> Self {
+        let boolean_false = Boolean::allocated_constant(cs, false);
+        let zero_u32 = UInt32::zero(cs);
+        let zero_u256 = UInt256::zero(cs);
+        let one_u256 = UInt256::allocated_constant(cs, U256::one());
+        // accumulator starts from the identity of Fq12
+        let mut miller_loop_accumulator = [zero_u256; 12];
+        miller_loop_accumulator[0] = one_u256;
+        Self {
+            read_precompile_call: boolean_false,
+            read_words_for_round: boolean_false,
+            completed: boolean_false,
+            miller_loop_accumulator,
+            has_exception: boolean_false,
+            timestamp_to_use_for_read: zero_u32,
+            timestamp_to_use_for_write: zero_u32,
+            precompile_call_params: Bn254EcPairingPrecompileCallParams::<F>::placeholder(cs),
+        }
+    }
+}
+
+#[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
+#[derivative(Clone, Copy, Debug)]
+#[DerivePrettyComparison("true")]
+pub struct Bn254EcPairingFSMInputOutput<F: SmallField> {
+    pub internal_fsm: Bn254EcPairingFSM<F>,
+    pub log_queue_state: QueueState<F, QUEUE_STATE_WIDTH>,
+    pub memory_queue_state: QueueState<F, FULL_SPONGE_QUEUE_STATE_WIDTH>,
+}
+
+impl<F: SmallField> CSPlaceholder<F> for Bn254EcPairingFSMInputOutput<F> {
+    fn placeholder<CS: ConstraintSystem<F>>(cs: &mut CS) -> Self {
+        Self {
+            internal_fsm: Bn254EcPairingFSM::placeholder(cs),
+            log_queue_state: QueueState::<F, QUEUE_STATE_WIDTH>::placeholder(cs),
+            memory_queue_state: QueueState::<F, FULL_SPONGE_QUEUE_STATE_WIDTH>::placeholder(cs),
+        }
+    }
+}
+
+pub type Bn254EcPairingCircuitInputOutput<F> = ClosedFormInput<
+    F,
+    Bn254EcPairingFSMInputOutput<F>,
+    PrecompileFunctionInputData<F>,
+    PrecompileFunctionOutputData<F>,
+>;
+pub type Bn254EcPairingCircuitInputOutputWitness<F> = ClosedFormInputWitness<
+    F,
+    Bn254EcPairingFSMInputOutput<F>,
+    PrecompileFunctionInputData<F>,
+    PrecompileFunctionOutputData<F>,
+>;
+
+#[derive(Derivative, serde::Serialize, serde::Deserialize)]
+#[derivative(Clone, Debug, Default)]
+#[serde(bound = "")]
+pub struct Bn254EcPairingCircuitInstanceWitness<F: SmallField> {
+    pub closed_form_input: Bn254EcPairingCircuitInputOutputWitness<F>,
+    pub requests_queue_witness: CircuitQueueRawWitness<F, LogQuery<F>, 4, LOG_QUERY_PACKED_WIDTH>,
+    pub memory_reads_witness: VecDeque<U256>,
+}
@@ -0,0 +1,1106 @@
+use super::*;
+
+use boojum::field::SmallField;
+
+use boojum::gadgets::traits::witnessable::WitnessHookable;
+
+use boojum::cs::traits::cs::ConstraintSystem;
+use boojum::gadgets::boolean::Boolean;
+use boojum::gadgets::traits::selectable::Selectable;
+use boojum::gadgets::u256::UInt256;
+use boojum::gadgets::u32::UInt32;
+use cs_derive::*;
+
+use crate::ethereum_types::U256;
+use crate::fsm_input_output::circuit_inputs::INPUT_OUTPUT_COMMITMENT_LENGTH;
+use boojum::gadgets::num::Num;
+use zkevm_opcode_defs::system_params::PRECOMPILE_AUX_BYTE;
+
+use crate::base_structures::log_query::*;
+use crate::base_structures::memory_query::*;
+use crate::base_structures::precompile_input_outputs::formal_precompile_address;
+use crate::base_structures::precompile_input_outputs::PrecompileFunctionOutputData;
+use crate::demux_log_queue::StorageLogQueue;
+use crate::fsm_input_output::*;
+use crate::storage_application::ConditionalWitnessAllocator;
+use boojum::algebraic_props::round_function::AlgebraicRoundFunction;
+use boojum::cs::Variable;
+use boojum::gadgets::non_native_field::implementations::*;
+use boojum::gadgets::queue::CircuitQueueWitness;
+use boojum::gadgets::queue::QueueState;
+use boojum::gadgets::traits::allocatable::CSAllocatable;
+use boojum::gadgets::traits::allocatable::{CSAllocatableExt, CSPlaceholder};
+use boojum::gadgets::traits::encodable::CircuitVarLengthEncodable;
+use boojum::gadgets::traits::round_function::CircuitRoundFunction;
+use boojum::gadgets::u160::UInt160;
+use boojum::gadgets::u8::UInt8;
+use std::sync::{Arc, RwLock};
+use zkevm_opcode_defs::ethereum_types::H160;
+
+use crate::base_structures::precompile_input_outputs::*;
+use crate::demux_log_queue::DemuxOutput;
+use crate::precompile_registry::PrecompileCircuit;
+use crate::scheduler::auxiliary::BaseLayerCircuitType;
+
+pub mod input;
+pub mod pairing;
+pub mod towers;
+pub use self::input::*;
+
+use self::pairing::*;
+use self::towers::*;
+
+// curve types and field params are shared with ecAdd
+use crate::bn254_ecadd::baseline::bn254_validate_and_mask_point;
+use crate::bn254_ecadd::{
+    bn254_base_field_params, BN254BaseNNField, BN254BaseNNFieldParams, BN254Fq,
+};
+use crate::ecrecover::baseline::convert_uint256_to_field_element;
+use crate::ecrecover::new_optimized::convert_field_element_to_uint256;
+
+pub(crate) use boojum::pairing::bn256::G2Affine as BN254G2Affine;
+
+pub const BN254_ECPAIRING_PRECOMPILE_ADDRESS: u16 = 0x08;
+pub const BN254_ECPAIRING_PRECOMPILE_FORMAL_ADDRESS: H160 =
+    formal_precompile_address(BN254_ECPAIRING_PRECOMPILE_ADDRESS);
+
+pub struct ECPairingPrecompileCircuit;
+
+impl PrecompileCircuit for ECPairingPrecompileCircuit {
+    const NAME: &'static str = "ecpairing";
+    const DEMUX_OUTPUT: DemuxOutput = DemuxOutput::ECPairing;
+    const CIRCUIT_TYPE: BaseLayerCircuitType = BaseLayerCircuitType::ECPairingPrecompile;
+
+    type InputData<F: SmallField> = PrecompileFunctionInputData<F>;
+    type OutputData<F: SmallField> = PrecompileFunctionOutputData<F>;
+
+    fn formal_address() -> H160 {
+        BN254_ECPAIRING_PRECOMPILE_FORMAL_ADDRESS
+    }
+}
+
+#[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
+#[derivative(Clone, Copy, Debug)]
+// #[DerivePrettyComparison("true")]
+pub struct Bn254EcPairingPrecompileCallParams<F: SmallField> {
+    pub input_page: UInt32<F>,
+    pub input_offset: UInt32<F>,
+    pub output_page: UInt32<F>,
+    pub output_offset: UInt32<F>,
+    pub num_pairs: UInt32<F>,
+}
+
+impl<F: SmallField> CSPlaceholder<F> for Bn254EcPairingPrecompileCallParams<F> {
+    fn placeholder<CS: ConstraintSystem<F>>(cs: &mut CS) -> Self {
+        let zero_u32 = UInt32::zero(cs);
+        Self {
+            input_page: zero_u32,
+            input_offset: zero_u32,
+            output_page: zero_u32,
+            output_offset: zero_u32,
+            num_pairs: zero_u32,
+        }
+    }
+}
+
+impl<F: SmallField> Bn254EcPairingPrecompileCallParams<F> {
+    pub fn from_encoding<CS: ConstraintSystem<F>>(_cs: &mut CS, encoding: UInt256<F>) -> Self {
+        let input_offset = encoding.inner[0];
+        let output_offset = encoding.inner[2];
+        let input_page = encoding.inner[4];
+        let output_page = encoding.inner[5];
+
+        let num_pairs = encoding.inner[6];
+
+        let new = Self {
+            input_page,
+            input_offset,
+            output_page,
+            output_offset,
+            num_pairs,
+        };
+
+        new
+    }
+}
+
+// x1, y1, x2_c1, x2_c0, y2_c1, y2_c0 - G2 coordinates are encoded with imaginary part first
+pub const MEMORY_READ_QUERIES_PER_CYCLE: usize = 6;
+
+/// Performs one pair of the pairing check. Returns the Miller loop value to accumulate,
+/// and flags whether the pair is trivial (any point is at infinity) or invalid
+fn bn254_ecpairing_pair_inner<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    words: &[UInt256<F>; MEMORY_READ_QUERIES_PER_CYCLE],
+    params: &Arc<BN254BaseNNFieldParams>,
+) -> (BN254Fq12<F>, Boolean<F>, Boolean<F>) {
+    let [x1, y1, x2_c1, x2_c0, y2_c1, y2_c0] = words;
+
+    let (mut p, p_is_infinity, p_is_invalid) = bn254_validate_and_mask_point(cs, x1, y1, params);
+    let (mut q, q_is_infinity, q_is_invalid) =
+        bn254_validate_and_mask_g2_point(cs, x2_c0, x2_c1, y2_c0, y2_c1, params);
+
+    // points are masked to generators, so the Miller loop is always well defined
+    let miller_loop_result = bn254_miller_loop(cs, &mut p, &mut q, params);
+
+    let is_trivial = Boolean::multi_or(cs, &[p_is_infinity, q_is_infinity]);
+    let is_invalid = Boolean::multi_or(cs, &[p_is_invalid, q_is_invalid]);
+
+    (miller_loop_result, is_trivial, is_invalid)
+}
+
+/// Call whose pairs are all accumulated, and that waits for the final exponentiation
+/// at the end of the instance
+#[derive(Derivative, CSSelectable)]
+#[derivative(Clone, Copy, Debug)]
+struct Bn254EcPairingPendingCall<F: SmallField> {
+    is_set: Boolean<F>,
+    miller_loop_accumulator: [UInt256<F>; 12],
+    has_exception: Boolean<F>,
+    timestamp_to_use_for_write: UInt32<F>,
+    output_page: UInt32<F>,
+    output_offset: UInt32<F>,
+}
+
+impl<F: SmallField> Bn254EcPairingPendingCall<F> {
+    fn empty<CS: ConstraintSystem<F>>(cs: &mut CS, empty_accumulator: [UInt256<F>; 12]) -> Self {
+        let boolean_false = Boolean::allocated_constant(cs, false);
+        let zero_u32 = UInt32::zero(cs);
+
+        Self {
+            is_set: boolean_false,
+            miller_loop_accumulator: empty_accumulator,
+            has_exception: boolean_false,
+            timestamp_to_use_for_write: zero_u32,
+            output_page: zero_u32,
+            output_offset: zero_u32,
+        }
+    }
+}
+
+/// Runs the final exponentiation for the pending call and writes the result. Final exponentiation
+/// is as expensive as a few Miller loops, so it's synthesized once per pending call slot
+/// instead of once per cycle
+fn bn254_ecpairing_finalize_call<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    memory_queue: &mut MemoryQueue<F, R>,
+    pending_call: &Bn254EcPairingPendingCall<F>,
+    params: &Arc<BN254BaseNNFieldParams>,
+) where
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+{
+    let boolean_false = Boolean::allocated_constant(cs, false);
+    let boolean_true = Boolean::allocated_constant(cs, true);
+    let zero_u256 = UInt256::zero(cs);
+
+    let mut accumulator =
+        BN254Fq12::from_uint256_coefficients(cs, &pending_call.miller_loop_accumulator, params);
+    let mut final_exp_result = bn254_final_exponentiation(cs, &mut accumulator, params);
+    let mut one_fq12 = BN254Fq12::one(cs, params);
+    let pairing_is_one = BN254Fq12::equals(cs, &mut final_exp_result, &mut one_fq12);
+
+    let success = pending_call.has_exception.negated(cs);
+    let result = Boolean::multi_and(cs, &[pairing_is_one, success]);
+
+    let success_as_u32 = unsafe { UInt32::from_variable_unchecked(success.get_variable()) };
+    let mut success_as_u256 = zero_u256;
+    success_as_u256.inner[0] = success_as_u32;
+
+    let result_as_u32 = unsafe { UInt32::from_variable_unchecked(result.get_variable()) };
+    let mut result_as_u256 = zero_u256;
+    result_as_u256.inner[0] = result_as_u32;
+
+    let success_query = MemoryQuery {
+        timestamp: pending_call.timestamp_to_use_for_write,
+        memory_page: pending_call.output_page,
+        index: pending_call.output_offset,
+        rw_flag: boolean_true,
+        is_ptr: boolean_false,
+        value: success_as_u256,
+    };
+
+    let result_offset = unsafe { pending_call.output_offset.increment_unchecked(cs) };
+    let result_query = MemoryQuery {
+        timestamp: pending_call.timestamp_to_use_for_write,
+        memory_page: pending_call.output_page,
+        index: result_offset,
+        rw_flag: boolean_true,
+        is_ptr: boolean_false,
+        value: result_as_u256,
+    };
+
+    // perform writes
+    let _ = memory_queue.push(cs, success_query, pending_call.is_set);
+    let _ = memory_queue.push(cs, result_query, pending_call.is_set);
+}
+
+/// Processes one (G1, G2) pair per cycle. Up to `max_calls_per_instance` calls are finished
+/// within the instance, and their results are written after the main cycle. If all the slots
+/// are taken, the remaining cycles are idle and the next call starts in the next instance
+pub fn bn254_ecpairing_precompile_inner<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    memory_queue: &mut MemoryQueue<F, R>,
+    precompile_calls_queue: &mut StorageLogQueue<F, R>,
+    memory_read_witness: ConditionalWitnessAllocator<F, UInt256<F>>,
+    mut state: Bn254EcPairingFSM<F>,
+    _round_function: &R,
+    limit: usize,
+    max_calls_per_instance: usize,
+) -> Bn254EcPairingFSM<F>
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    assert!(limit <= u32::MAX as usize);
+    assert!(max_calls_per_instance > 0);
+
+    let precompile_address =
+        UInt160::allocated_constant(cs, BN254_ECPAIRING_PRECOMPILE_FORMAL_ADDRESS);
+    let aux_byte_for_precompile = UInt8::allocated_constant(cs, PRECOMPILE_AUX_BYTE);
+
+    let boolean_false = Boolean::allocated_constant(cs, false);
+    let boolean_true = Boolean::allocated_constant(cs, true);
+    let zero_u256 = UInt256::zero(cs);
+    let one_u256 = UInt256::allocated_constant(cs, U256::one());
+
+    let params = Arc::new(bn254_base_field_params());
+
+    let mut empty_accumulator = [zero_u256; 12];
+    empty_accumulator[0] = one_u256;
+
+    // we can have a degenerate case when queue is empty, but it's a first circuit in the queue,
+    // so we taken default FSM state that has state.read_precompile_call = true;
+    let input_queue_is_empty = precompile_calls_queue.is_empty(cs);
+    // we can only skip the full circuit if we are not in any form of progress
+    let can_finish_immediatelly =
+        Boolean::multi_and(cs, &[state.read_precompile_call, input_queue_is_empty]);
+
+    if crate::config::CIRCUIT_VERSOBE {
+        dbg!(can_finish_immediatelly.witness_hook(cs)());
+        dbg!(state.witness_hook(cs)());
+    }
+
+    state.read_precompile_call = state
+        .read_precompile_call
+        .mask_negated(cs, can_finish_immediatelly);
+    state.read_words_for_round = state
+        .read_words_for_round
+        .mask_negated(cs, can_finish_immediatelly);
+    state.completed = Boolean::multi_or(cs, &[state.completed, can_finish_immediatelly]);
+
+    // finished calls go into the slots in order, so we track the next free slot as one-hot
+    let empty_pending_call = Bn254EcPairingPendingCall::empty(cs, empty_accumulator);
+    let mut pending_calls = vec![empty_pending_call; max_calls_per_instance];
+    let mut slot_is_next = vec![boolean_false; max_calls_per_instance];
+    slot_is_next[0] = boolean_true;
+
+    // main work cycle, every cycle processes one (G1, G2) pair
+    for _cycle in 0..limit {
+        if crate::config::CIRCUIT_VERSOBE {
+            dbg!(_cycle);
+            dbg!(state.witness_hook(cs)());
+            dbg!(precompile_calls_queue.into_state().witness_hook(cs)());
+        }
+        // if we are in a proper state then get the ABI from the queue
+        let (precompile_call, _) = precompile_calls_queue.pop_front(cs, state.read_precompile_call);
+
+        Num::conditionally_enforce_equal(
+            cs,
+            state.read_precompile_call,
+            &Num::from_variable(precompile_call.aux_byte.get_variable()),
+            &Num::from_variable(aux_byte_for_precompile.get_variable()),
+        );
+        for (a, b) in precompile_call
+            .address
+            .inner
+            .iter()
+            .zip(precompile_address.inner.iter())
+        {
+            Num::conditionally_enforce_equal(
+                cs,
+                state.read_precompile_call,
+                &Num::from_variable(a.get_variable()),
+                &Num::from_variable(b.get_variable()),
+            );
+        }
+
+        // now compute some parameters that describe the call itself
+
+        let params_encoding = precompile_call.key;
+        let call_params = Bn254EcPairingPrecompileCallParams::from_encoding(cs, params_encoding);
+
+        state.precompile_call_params = Bn254EcPairingPrecompileCallParams::conditionally_select(
+            cs,
+            state.read_precompile_call,
+            &call_params,
+            &state.precompile_call_params,
+        );
+        // also set timestamps
+        state.timestamp_to_use_for_read = UInt32::conditionally_select(
+            cs,
+            state.read_precompile_call,
+            &precompile_call.timestamp,
+            &state.timestamp_to_use_for_read,
+        );
+
+        // timestamps have large space, so this can be expected
+        let timestamp_to_use_for_write =
+            unsafe { state.timestamp_to_use_for_read.increment_unchecked(cs) };
+        state.timestamp_to_use_for_write = UInt32::conditionally_select(
+            cs,
+            state.read_precompile_call,
+            &timestamp_to_use_for_write,
+            &state.timestamp_to_use_for_write,
+        );
+
+        let reset_accumulator =
+            Boolean::multi_or(cs, &[state.read_precompile_call, state.completed]);
+        state.miller_loop_accumulator = <[UInt256<F>; 12]>::conditionally_select(
+            cs,
+            reset_accumulator,
+            &empty_accumulator,
+            &state.miller_loop_accumulator,
+        );
+        state.has_exception = state.has_exception.mask_negated(cs, reset_accumulator);
+        state.read_words_for_round = Boolean::multi_or(
+            cs,
+            &[state.read_precompile_call, state.read_words_for_round],
+        );
+        state.read_precompile_call = boolean_false;
+
+        // ---------------------------------
+        // Now perform few memory queries to read content
+
+        let zero_pairs_left = state.precompile_call_params.num_pairs.is_zero(cs);
+        let not_zero_pairs_left = zero_pairs_left.negated(cs);
+        let should_read =
+            Boolean::multi_and(cs, &[state.read_words_for_round, not_zero_pairs_left]);
+
+        let mut read_values = [zero_u256; MEMORY_READ_QUERIES_PER_CYCLE];
+        let mut bias_variable = should_read.get_variable();
+        for dst in read_values.iter_mut() {
+            let read_query_value =
+                memory_read_witness.conditionally_allocate_biased(cs, should_read, bias_variable);
+            bias_variable = read_query_value.inner[0].get_variable();
+
+            *dst = read_query_value;
+
+            let read_query = MemoryQuery {
+                timestamp: state.timestamp_to_use_for_read,
+                memory_page: state.precompile_call_params.input_page,
+                index: state.precompile_call_params.input_offset,
+                rw_flag: boolean_false,
+                is_ptr: boolean_false,
+                value: read_query_value,
+            };
+
+            let may_be_new_offset = unsafe {
+                state
+                    .precompile_call_params
+                    .input_offset
+                    .increment_unchecked(cs)
+            };
+            state.precompile_call_params.input_offset = UInt32::conditionally_select(
+                cs,
+                should_read,
+                &may_be_new_offset,
+                &state.precompile_call_params.input_offset,
+            );
+
+            // perform read
+            memory_queue.push(cs, read_query, should_read);
+        }
+
+        let may_be_new_num_pairs = unsafe {
+            state
+                .precompile_call_params
+                .num_pairs
+                .decrement_unchecked(cs)
+        };
+        state.precompile_call_params.num_pairs = UInt32::conditionally_select(
+            cs,
+            should_read,
+            &may_be_new_num_pairs,
+            &state.precompile_call_params.num_pairs,
+        );
+
+        // if we didn't read anything then values are zeroes, so the pair is trivial
+        let (mut miller_loop_result, pair_is_trivial, pair_is_invalid) =
+            bn254_ecpairing_pair_inner(cs, &read_values, &params);
+
+        let mut accumulator =
+            BN254Fq12::from_uint256_coefficients(cs, &state.miller_loop_accumulator, &params);
+        let new_accumulator = accumulator.mul(cs, &mut miller_loop_result);
+        let pair_is_not_trivial = pair_is_trivial.negated(cs);
+        let should_accumulate = Boolean::multi_and(cs, &[should_read, pair_is_not_trivial]);
+        let accumulator =
+            BN254Fq12::conditionally_select(cs, should_accumulate, &new_accumulator, &accumulator);
+
+        let has_new_exception = Boolean::multi_and(cs, &[should_read, pair_is_invalid]);
+        state.has_exception = Boolean::multi_or(cs, &[state.has_exception, has_new_exception]);
+
+        let no_pairs_left = state.precompile_call_params.num_pairs.is_zero(cs);
+        state.miller_loop_accumulator = accumulator.into_uint256_coefficients(cs);
+
+        // once all the pairs are accumulated the call waits for the finalization in the next free slot
+        let call_is_finished = Boolean::multi_and(cs, &[state.read_words_for_round, no_pairs_left]);
+        let finished_call = Bn254EcPairingPendingCall {
+            is_set: boolean_true,
+            miller_loop_accumulator: state.miller_loop_accumulator,
+            has_exception: state.has_exception,
+            timestamp_to_use_for_write: state.timestamp_to_use_for_write,
+            output_page: state.precompile_call_params.output_page,
+            output_offset: state.precompile_call_params.output_offset,
+        };
+        let mut previous_slot_is_next = boolean_false;
+        for (pending_call, slot_is_next) in pending_calls.iter_mut().zip(slot_is_next.iter_mut()) {
+            let should_save = Boolean::multi_and(cs, &[call_is_finished, *slot_is_next]);
+            *pending_call = Bn254EcPairingPendingCall::conditionally_select(
+                cs,
+                should_save,
+                &finished_call,
+                pending_call,
+            );
+
+            let current_slot_is_next = *slot_is_next;
+            *slot_is_next = Boolean::conditionally_select(
+                cs,
+                call_is_finished,
+                &previous_slot_is_next,
+                &current_slot_is_next,
+            );
+            previous_slot_is_next = current_slot_is_next;
+        }
+        let has_free_slot = Boolean::multi_or(cs, &slot_is_next);
+
+        // update state, we either start a new call, or we are done, or the rest of the cycles
+        // in this instance are idle and the next call starts in the next instance
+        let input_is_empty = precompile_calls_queue.is_empty(cs);
+        let input_is_not_empty = input_is_empty.negated(cs);
+        let nothing_left = Boolean::multi_and(cs, &[call_is_finished, input_is_empty]);
+        let process_next =
+            Boolean::multi_and(cs, &[call_is_finished, input_is_not_empty, has_free_slot]);
+
+        state.read_precompile_call = process_next;
+        state.completed = Boolean::multi_or(cs, &[nothing_left, state.completed]);
+        state.read_words_for_round = state
+            .read_words_for_round
+            .mask_negated(cs, call_is_finished);
+
+        if crate::config::CIRCUIT_VERSOBE {
+            dbg!(state.witness_hook(cs)());
+            dbg!(precompile_calls_queue.into_state().witness_hook(cs)());
+        }
+    }
+
+    for pending_call in pending_calls.iter() {
+        bn254_ecpairing_finalize_call(cs, memory_queue, pending_call, &params);
+    }
+
+    // if the slots ran out, then the next instance starts from the next call
+    let is_in_progress = Boolean::multi_or(
+        cs,
+        &[
+            state.read_precompile_call,
+            state.read_words_for_round,
+            state.completed,
+        ],
+    );
+    let is_waiting_for_next_call = is_in_progress.negated(cs);
+    state.read_precompile_call =
+        Boolean::multi_or(cs, &[state.read_precompile_call, is_waiting_for_next_call]);
+
+    precompile_calls_queue.enforce_consistency(cs);
+
+    state
+}
+
+#[track_caller]
+pub fn bn254_ecpairing_function_entry_point<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    witness: Bn254EcPairingCircuitInstanceWitness<F>,
+    round_function: &R,
+    limit: usize,
+    max_calls_per_instance: usize,
+) -> [Num<F>; INPUT_OUTPUT_COMMITMENT_LENGTH]
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    let Bn254EcPairingCircuitInstanceWitness {
+        closed_form_input,
+        requests_queue_witness,
+        memory_reads_witness,
+    } = witness;
+
+    let mut structured_input =
+        Bn254EcPairingCircuitInputOutput::alloc_ignoring_outputs(cs, closed_form_input.clone());
+
+    let start_flag = structured_input.start_flag;
+
+    let requests_queue_state_from_input = structured_input.observable_input.initial_log_queue_state;
+
+    // it must be trivial
+    requests_queue_state_from_input.enforce_trivial_head(cs);
+
+    let requests_queue_state_from_fsm = structured_input.hidden_fsm_input.log_queue_state;
+
+    let requests_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &requests_queue_state_from_input,
+        &requests_queue_state_from_fsm,
+    );
+
+    let memory_queue_state_from_input =
+        structured_input.observable_input.initial_memory_queue_state;
+
+    // it must be trivial
+    memory_queue_state_from_input.enforce_trivial_head(cs);
+
+    let memory_queue_state_from_fsm = structured_input.hidden_fsm_input.memory_queue_state;
+
+    let memory_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &memory_queue_state_from_input,
+        &memory_queue_state_from_fsm,
+    );
+
+    let mut requests_queue = StorageLogQueue::<F, R>::from_state(cs, requests_queue_state);
+    let queue_witness = CircuitQueueWitness::from_inner_witness(requests_queue_witness);
+    requests_queue.witness = Arc::new(queue_witness);
+
+    let mut memory_queue = MemoryQueue::<F, R>::from_state(cs, memory_queue_state);
+
+    let read_queries_allocator = ConditionalWitnessAllocator::<F, UInt256<F>> {
+        witness_source: Arc::new(RwLock::new(memory_reads_witness)),
+    };
+
+    let mut starting_fsm_state = Bn254EcPairingFSM::placeholder(cs);
+    starting_fsm_state.read_precompile_call = Boolean::allocated_constant(cs, true);
+
+    let initial_state = Bn254EcPairingFSM::conditionally_select(
+        cs,
+        start_flag,
+        &starting_fsm_state,
+        &structured_input.hidden_fsm_input.internal_fsm,
+    );
+
+    let final_state = bn254_ecpairing_precompile_inner::<F, CS, R>(
+        cs,
+        &mut memory_queue,
+        &mut requests_queue,
+        read_queries_allocator,
+        initial_state,
+        round_function,
+        limit,
+        max_calls_per_instance,
+    );
+
+    let final_memory_state = memory_queue.into_state();
+    let final_requets_state = requests_queue.into_state();
+
+    // form the final state
+    let done = final_state.completed;
+    structured_input.completion_flag = done;
+    structured_input.observable_output = PrecompileFunctionOutputData::placeholder(cs);
+
+    structured_input.observable_output.final_memory_state = QueueState::conditionally_select(
+        cs,
+        structured_input.completion_flag,
+        &final_memory_state,
+        &structured_input.observable_output.final_memory_state,
+    );
+
+    structured_input.hidden_fsm_output.internal_fsm = final_state;
+    structured_input.hidden_fsm_output.log_queue_state = final_requets_state;
+    structured_input.hidden_fsm_output.memory_queue_state = final_memory_state;
+
+    // self-check
+    structured_input.hook_compare_witness(cs, &closed_form_input);
+
+    use boojum::cs::gates::PublicInputGate;
+
+    let compact_form =
+        ClosedFormInputCompactForm::from_full_form(cs, &structured_input, round_function);
+    let input_commitment = commit_variable_length_encodable_item(cs, &compact_form, round_function);
+    for el in input_commitment.iter() {
+        let gate = PublicInputGate::new(el.get_variable());
+        gate.add_to_cs(cs);
+    }
+
+    input_commitment
+}
+
+#[cfg(test)]
+mod test {
+    use boojum::algebraic_props::poseidon2_parameters::*;
+    use boojum::field::goldilocks::GoldilocksField;
+    use boojum::gadgets::traits::allocatable::CSAllocatable;
+    use boojum::worker::Worker;
+
+    use super::*;
+
+    type F = GoldilocksField;
+    type P = GoldilocksField;
+    type R = Poseidon2Goldilocks;
+
+    use boojum::config::DevCSConfig;
+
+    use boojum::cs::cs_builder::*;
+    use boojum::cs::cs_builder_reference::CsReferenceImplementationBuilder;
+    use boojum::cs::gates::*;
+    use boojum::cs::implementations::reference_cs::CSReferenceImplementation;
+    use boojum::cs::traits::gate::GatePlacementStrategy;
+    use boojum::cs::CSGeometry;
+    use boojum::cs::*;
+    use boojum::gadgets::tables::*;
+    use boojum::implementations::poseidon2::Poseidon2Goldilocks;
+    use boojum::pairing::bn256::{Fq, Fq2};
+    use boojum::pairing::ff::{Field, PrimeField, PrimeFieldRepr, SqrtField};
+    use boojum::pairing::{GenericCurveAffine, GenericCurveProjective};
+    use std::collections::VecDeque;
+    use zkevm_opcode_defs::PrecompileCallABI;
+
+    use crate::bn254_ecadd::{BN254Affine, BN254Fr};
+
+    fn create_cs(
+        max_trace_len: usize,
+    ) -> CSReferenceImplementation<
+        F,
+        P,
+        DevCSConfig,
+        impl GateConfigurationHolder<F>,
+        impl StaticToolboxHolder,
+    > {
+        let geometry = CSGeometry {
+            num_columns_under_copy_permutation: 80,
+            num_witness_columns: 0,
+            num_constant_columns: 4,
+            max_allowed_constraint_degree: 8,
+        };
+        let max_variables = 1 << 26;
+
+        fn configure<
+            F: SmallField,
+            T: CsBuilderImpl<F, T>,
+            GC: GateConfigurationHolder<F>,
+            TB: StaticToolboxHolder,
+        >(
+            builder: CsBuilder<T, F, GC, TB>,
+        ) -> CsBuilder<T, F, impl GateConfigurationHolder<F>, impl StaticToolboxHolder> {
+            let builder = builder.allow_lookup(
+                LookupParameters::UseSpecializedColumnsWithTableIdAsConstant {
+                    width: 3,
+                    num_repetitions: 16,
+                    share_table_id: true,
+                },
+            );
+
+            let builder = ConstantsAllocatorGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = BooleanConstraintGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseSpecializedColumns {
+                    num_repetitions: 1,
+                    share_constants: false,
+                },
+            );
+            let builder = U8x4FMAGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ZeroCheckGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+                false,
+            );
+            let builder = FmaGateInBaseFieldWithoutConstant::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<32>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<16>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<8>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = DotProductGate::<4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = SelectionGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ParallelSelectionGate::<4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = PublicInputGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ReductionGate::<_, 4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = MatrixMultiplicationGate::<F, 12, Poseidon2GoldilocksExternalMatrix>::configure_builder(builder,GatePlacementStrategy::UseGeneralPurposeColumns);
+            let builder = MatrixMultiplicationGate::<F, 12, Poseidon2GoldilocksInnerMatrix>::configure_builder(builder,GatePlacementStrategy::UseGeneralPurposeColumns);
+            let builder = NopGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+
+            builder
+        }
+
+        let builder_impl =
+            CsReferenceImplementationBuilder::<F, P, DevCSConfig>::new(geometry, max_trace_len);
+        let builder = new_builder::<_, F>(builder_impl);
+
+        let builder = configure(builder);
+        let mut owned_cs = builder.build(max_variables);
+
+        // add tables
+        let table = create_xor8_table();
+        owned_cs.add_lookup_table::<Xor8Table, 3>(table);
+
+        let table = create_byte_split_table::<F, 4>();
+        owned_cs.add_lookup_table::<ByteSplitTable<4>, 3>(table);
+
+        owned_cs
+    }
+    fn repr_into_u256<T: PrimeFieldRepr>(repr: T) -> U256 {
+        let mut u256 = U256::zero();
+        u256.0.copy_from_slice(&repr.as_ref()[..4]);
+
+        u256
+    }
+
+    fn pair_into_u256_words(
+        p: BN254Affine,
+        q: BN254G2Affine,
+    ) -> [U256; MEMORY_READ_QUERIES_PER_CYCLE] {
+        let (x1, y1) = p.into_xy_unchecked();
+        let (x2, y2) = q.into_xy_unchecked();
+
+        [
+            repr_into_u256(x1.into_repr()),
+            repr_into_u256(y1.into_repr()),
+            repr_into_u256(x2.c1.into_repr()),
+            repr_into_u256(x2.c0.into_repr()),
+            repr_into_u256(y2.c1.into_repr()),
+            repr_into_u256(y2.c0.into_repr()),
+        ]
+    }
+
+    fn run_pairing_check(pairs: &[[U256; MEMORY_READ_QUERIES_PER_CYCLE]]) -> (bool, bool) {
+        let mut owned_cs = create_cs(1 << 24);
+        let cs = &mut owned_cs;
+
+        let params = Arc::new(bn254_base_field_params());
+
+        let mut accumulator = BN254Fq12::one(cs, &params);
+        let mut has_exception = Boolean::allocated_constant(cs, false);
+        for pair in pairs.iter() {
+            let words = pair.map(|el| UInt256::allocate(cs, el));
+            let (mut miller_loop_result, is_trivial, is_invalid) =
+                bn254_ecpairing_pair_inner(cs, &words, &params);
+            let new_accumulator = accumulator.mul(cs, &mut miller_loop_result);
+            accumulator =
+                BN254Fq12::conditionally_select(cs, is_trivial, &accumulator, &new_accumulator);
+            has_exception = Boolean::multi_or(cs, &[has_exception, is_invalid]);
+        }
+
+        let mut final_exp_result = bn254_final_exponentiation(cs, &mut accumulator, &params);
+        let mut one_fq12 = BN254Fq12::one(cs, &params);
+        let pairing_is_one = BN254Fq12::equals(cs, &mut final_exp_result, &mut one_fq12);
+
+        let has_exception = has_exception.witness_hook(&*cs)().unwrap();
+        let pairing_is_one = pairing_is_one.witness_hook(&*cs)().unwrap();
+
+        cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        let worker = Worker::new();
+        assert!(cs.check_if_satisfied(&worker));
+
+        (has_exception, pairing_is_one)
+    }
+
+    #[test]
+    fn test_bn254_pairing_check() {
+        let a = BN254Fr::from_str("1234567890123456789").unwrap();
+        let b = BN254Fr::from_str("9876543210987654321").unwrap();
+        let mut ab = a;
+        ab.mul_assign(&b);
+        ab.negate();
+
+        let p = BN254Affine::one();
+        let q = BN254G2Affine::one();
+        let a_p = p.mul(a.into_repr()).into_affine();
+        let b_q = q.mul(b.into_repr()).into_affine();
+        let minus_ab_p = p.mul(ab.into_repr()).into_affine();
+
+        // e(aP, bQ) * e(-abP, Q) == 1
+        let (has_exception, pairing_is_one) = run_pairing_check(&[
+            pair_into_u256_words(a_p, b_q),
+            pair_into_u256_words(minus_ab_p, q),
+        ]);
+        assert!(!has_exception);
+        assert!(pairing_is_one);
+
+        // e(aP, bQ) != 1
+        let (has_exception, pairing_is_one) = run_pairing_check(&[pair_into_u256_words(a_p, b_q)]);
+        assert!(!has_exception);
+        assert!(!pairing_is_one);
+    }
+
+    #[test]
+    fn test_bn254_pairing_check_invalid_inputs() {
+        let p = BN254Affine::one();
+        let q = BN254G2Affine::one();
+
+        // point at infinity in G1 makes the pair trivial
+        let mut words = pair_into_u256_words(p, q);
+        words[0] = U256::zero();
+        words[1] = U256::zero();
+        let (has_exception, pairing_is_one) = run_pairing_check(&[words]);
+        assert!(!has_exception);
+        assert!(pairing_is_one);
+
+        // G2 point is not on the twisted curve
+        let mut words = pair_into_u256_words(p, q);
+        words[5] = words[5] + U256::one();
+        let (has_exception, _) = run_pairing_check(&[words]);
+        assert!(has_exception);
+    }
+
+    // runs the FSM over as many instances as there are limits, passing the state from
+    // one instance to the next one, and returns the written (success, result) for every call
+    fn run_in_instances(
+        calls: &[Vec<[U256; MEMORY_READ_QUERIES_PER_CYCLE]>],
+        limits: &[usize],
+        max_calls_per_instance: usize,
+    ) -> Vec<(U256, U256)> {
+        let memory_read_witness: VecDeque<U256> =
+            calls.iter().flatten().flatten().copied().collect();
+        let memory_read_witness = Arc::new(RwLock::new(memory_read_witness));
+
+        let requests: Vec<LogQueryWitness<F>> = calls
+            .iter()
+            .enumerate()
+            .map(|(call_idx, pairs)| {
+                let precompile_abi = PrecompileCallABI {
+                    input_memory_offset: (call_idx * 64) as u32,
+                    input_memory_length: 0,
+                    output_memory_offset: (call_idx * 2) as u32,
+                    output_memory_length: 2,
+                    memory_page_to_read: 123,
+                    memory_page_to_write: 456,
+                    precompile_interpreted_data: pairs.len() as u64,
+                };
+
+                LogQueryWitness {
+                    address: BN254_ECPAIRING_PRECOMPILE_FORMAL_ADDRESS,
+                    key: precompile_abi.to_u256(),
+                    read_value: U256::zero(),
+                    written_value: U256::zero(),
+                    aux_byte: PRECOMPILE_AUX_BYTE,
+                    rw_flag: true,
+                    rollback: false,
+                    is_service: false,
+                    shard_id: 0,
+                    tx_number_in_block: 0,
+                    timestamp: (call_idx as u32 + 1) * 4,
+                }
+            })
+            .collect();
+        let mut num_requests_popped = 0;
+
+        let mut state_witness = Bn254EcPairingFSM::placeholder_witness();
+        state_witness.read_precompile_call = true;
+
+        let mut written_values = vec![];
+        for (instance_idx, limit) in limits.iter().enumerate() {
+            let mut owned_cs = create_cs(1 << 25);
+            let cs = &mut owned_cs;
+            let mut memory_queue = MemoryQueue::<F, R>::empty(cs);
+            let boolean_true = Boolean::allocated_constant(cs, true);
+
+            // every instance sees the requests that are not yet processed
+            let mut precompile_calls_queue = StorageLogQueue::<F, R>::empty(cs);
+            for el in requests[num_requests_popped..].iter() {
+                let el = LogQuery::allocate(cs, el.clone());
+                precompile_calls_queue.push(cs, el, boolean_true);
+            }
+            let num_requests_before = precompile_calls_queue
+                .witness
+                .elements
+                .read()
+                .unwrap()
+                .len();
+
+            let state = Bn254EcPairingFSM::allocate(cs, state_witness.clone());
+            let round_function = Poseidon2Goldilocks;
+
+            let memory_read_witness = ConditionalWitnessAllocator::<F, UInt256<F>> {
+                witness_source: memory_read_witness.clone(),
+            };
+
+            let new_state = bn254_ecpairing_precompile_inner(
+                cs,
+                &mut memory_queue,
+                &mut precompile_calls_queue,
+                memory_read_witness,
+                state,
+                &round_function,
+                *limit,
+                max_calls_per_instance,
+            );
+
+            let is_last = instance_idx == limits.len() - 1;
+            assert_eq!(new_state.completed.witness_hook(cs)().unwrap(), is_last);
+            state_witness = new_state.witness_hook(cs)().unwrap();
+
+            let num_requests_after = precompile_calls_queue
+                .witness
+                .elements
+                .read()
+                .unwrap()
+                .len();
+            num_requests_popped += num_requests_before - num_requests_after;
+
+            drop(cs);
+
+            for (query, _) in memory_queue.witness.elements.read().unwrap().iter() {
+                if query.rw_flag {
+                    written_values.push(query.value);
+                }
+            }
+
+            let _ = owned_cs.pad_and_shrink();
+            let mut assembly = owned_cs.into_assembly::<std::alloc::Global>();
+            let worker = Worker::new();
+            let is_satisfied = assembly.check_if_satisfied(&worker);
+            assert!(is_satisfied);
+        }
+
+        assert!(memory_read_witness.read().unwrap().is_empty());
+        assert_eq!(num_requests_popped, calls.len());
+
+        written_values.chunks(2).map(|el| (el[0], el[1])).collect()
+    }
+
+    // a point on the twisted curve that is not in the r-torsion subgroup, found by decompression
+    // without cofactor clearing
+    fn g2_point_outside_of_subgroup() -> BN254G2Affine {
+        let b = BN254G2Affine::b_coeff();
+        let mut x = Fq2 {
+            c0: Fq::one(),
+            c1: Fq::one(),
+        };
+        loop {
+            let mut rhs = x;
+            rhs.square();
+            rhs.mul_assign(&x);
+            rhs.add_assign(&b);
+            if let Some(y) = rhs.sqrt() {
+                let point = BN254G2Affine::from_xy_unchecked(x, y);
+                if !point.mul(BN254Fr::char()).is_zero() {
+                    return point;
+                }
+            }
+            x.c0.add_assign(&Fq::one());
+        }
+    }
+
+    #[test]
+    fn test_bn254_pairing_fsm_across_instances() {
+        let a = BN254Fr::from_str("1234567890123456789").unwrap();
+        let b = BN254Fr::from_str("9876543210987654321").unwrap();
+        let mut ab = a;
+        ab.mul_assign(&b);
+        ab.negate();
+
+        let p = BN254Affine::one();
+        let q = BN254G2Affine::one();
+        let a_p = p.mul(a.into_repr()).into_affine();
+        let b_q = q.mul(b.into_repr()).into_affine();
+        let minus_ab_p = p.mul(ab.into_repr()).into_affine();
+
+        let calls = vec![
+            vec![
+                pair_into_u256_words(a_p, b_q),
+                pair_into_u256_words(minus_ab_p, q),
+            ],
+            vec![pair_into_u256_words(a_p, b_q)],
+            vec![],
+        ];
+        let one = U256::one();
+        let zero = U256::zero();
+        let expected = vec![(one, one), (one, zero), (one, one)];
+
+        // every instance finalizes one call, idling after it if there are cycles left
+        assert_eq!(run_in_instances(&calls, &[2, 1, 1], 1), expected);
+        assert_eq!(run_in_instances(&calls, &[3, 2, 1], 1), expected);
+        // first call is split between two instances
+        assert_eq!(run_in_instances(&calls, &[1, 1, 1, 1], 1), expected);
+        // all the calls fit into one instance
+        assert_eq!(run_in_instances(&calls, &[4], 3), expected);
+        assert_eq!(run_in_instances(&calls, &[5], 4), expected);
+        // second instance starts from the call that didn't get a slot
+        assert_eq!(run_in_instances(&calls, &[4, 1], 2), expected);
+        // cycles run out before the slots do
+        assert_eq!(run_in_instances(&calls, &[2, 2], 2), expected);
+    }
+
+    #[test]
+    fn test_bn254_pairing_check_g2_outside_of_subgroup() {
+        let p = BN254Affine::one();
+        let q = g2_point_outside_of_subgroup();
+
+        let (has_exception, _) = run_pairing_check(&[pair_into_u256_words(p, q)]);
+        assert!(has_exception);
+
+        // even if it's paired with infinity
+        let mut words = pair_into_u256_words(p, q);
+        words[0] = U256::zero();
+        words[1] = U256::zero();
+        let (has_exception, _) = run_pairing_check(&[words]);
+        assert!(has_exception);
+
+        let calls = vec![vec![
+            pair_into_u256_words(p, BN254G2Affine::one()),
+            pair_into_u256_words(p, q),
+        ]];
+        let zero = U256::zero();
+        assert_eq!(run_in_instances(&calls, &[2], 1), vec![(zero, zero)]);
+    }
+}
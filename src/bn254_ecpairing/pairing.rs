@@ -0,0 +1,449 @@
+use super::*;
+
+use super::towers::*;
+use boojum::pairing::GenericCurveAffine;
+
+// parameter of the BN254 curve
+const BN254_U: u64 = 4965661367192848881;
+
+// 6u + 2 in non-adjacent form, least significant digit first
+const ATE_LOOP_COUNT_NAF: [i8; 66] = [
+    0, 0, 0, 1, 0, 1, 0, -1, 0, 0, -1, 0, 0, 0, 1, 0, 0, -1, 0, -1, 0, 0, 0, 1, 0, -1, 0, 0, 0, 0,
+    -1, 0, 0, 1, 0, -1, 0, 0, 1, 0, 0, 0, 0, 0, -1, 0, 0, -1, 0, 1, 0, -1, 0, 0, 0, -1, 0, -1, 0,
+    0, 0, 1, 0, -1, 0, 1,
+];
+
+// point Q on the twist belongs to G2 if and only if psi(Q) == [6u^2]Q
+const G2_SUBGROUP_CHECK_SCALAR: u128 = 147946756881789318990833708069417712966;
+
+#[derive(Derivative)]
+#[derivative(Clone, Debug)]
+pub(crate) struct BN254G2AffinePoint<F: SmallField> {
+    pub(crate) x: BN254Fq2<F>,
+    pub(crate) y: BN254Fq2<F>,
+}
+
+impl<F: SmallField> BN254G2AffinePoint<F> {
+    pub(crate) fn generator<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        params: &Arc<BN254BaseNNFieldParams>,
+    ) -> Self {
+        let (x, y) = BN254G2Affine::one().into_xy_unchecked();
+
+        Self {
+            x: BN254Fq2::constant(cs, x.c0, x.c1, params),
+            y: BN254Fq2::constant(cs, y.c0, y.c1, params),
+        }
+    }
+
+    pub(crate) fn negated<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        Self {
+            x: self.x.clone(),
+            y: self.y.negated(cs),
+        }
+    }
+
+    /// Untwist-Frobenius-twist endomorphism
+    pub(crate) fn psi<CS: ConstraintSystem<F>>(
+        &mut self,
+        cs: &mut CS,
+        params: &Arc<BN254BaseNNFieldParams>,
+    ) -> Self {
+        let mut x_coeff = frobenius_coeff(cs, 1, 2, params);
+        let mut y_coeff = frobenius_coeff(cs, 1, 3, params);
+
+        let mut x = self.x.conjugate(cs);
+        let mut y = self.y.conjugate(cs);
+
+        Self {
+            x: x.mul(cs, &mut x_coeff),
+            y: y.mul(cs, &mut y_coeff),
+        }
+    }
+
+    pub(crate) fn conditionally_select<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        flag: Boolean<F>,
+        a: &Self,
+        b: &Self,
+    ) -> Self {
+        Self {
+            x: BN254Fq2::conditionally_select(cs, flag, &a.x, &b.x),
+            y: BN254Fq2::conditionally_select(cs, flag, &a.y, &b.y),
+        }
+    }
+}
+
+// homogeneous projective coordinates, used for complete addition formulas
+#[derive(Derivative)]
+#[derivative(Clone, Debug)]
+struct BN254G2ProjectivePoint<F: SmallField> {
+    x: BN254Fq2<F>,
+    y: BN254Fq2<F>,
+    z: BN254Fq2<F>,
+}
+
+impl<F: SmallField> BN254G2ProjectivePoint<F> {
+    fn from_affine<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        point: &BN254G2AffinePoint<F>,
+        params: &Arc<BN254BaseNNFieldParams>,
+    ) -> Self {
+        Self {
+            x: point.x.clone(),
+            y: point.y.clone(),
+            z: BN254Fq2::one(cs, params),
+        }
+    }
+
+    // complete addition formulas for a = 0 from https://eprint.iacr.org/2015/1060, algorithm 7.
+    // Twisted curve has no points of order 2, so these also work for doubling
+    fn add<CS: ConstraintSystem<F>>(
+        &mut self,
+        cs: &mut CS,
+        other: &mut Self,
+        curve_b3: &mut BN254Fq2<F>,
+    ) -> Self {
+        let mut t0 = self.x.mul(cs, &mut other.x);
+        let mut t1 = self.y.mul(cs, &mut other.y);
+        let mut t2 = self.z.mul(cs, &mut other.z);
+
+        let mut t3 = self.x.add(cs, &mut self.y);
+        let mut t4 = other.x.add(cs, &mut other.y);
+        let mut t3 = t3.mul(cs, &mut t4);
+
+        let mut t4 = t0.add(cs, &mut t1);
+        let mut t3 = t3.sub(cs, &mut t4);
+        let mut t4 = self.y.add(cs, &mut self.z);
+
+        let mut x3 = other.y.add(cs, &mut other.z);
+        let mut t4 = t4.mul(cs, &mut x3);
+        let mut x3 = t1.add(cs, &mut t2);
+
+        let mut t4 = t4.sub(cs, &mut x3);
+        let mut x3 = self.x.add(cs, &mut self.z);
+        let mut y3 = other.x.add(cs, &mut other.z);
+
+        let mut x3 = x3.mul(cs, &mut y3);
+        let mut y3 = t0.add(cs, &mut t2);
+        let mut y3 = x3.sub(cs, &mut y3);
+
+        let mut x3 = t0.double(cs);
+        let mut t0 = x3.add(cs, &mut t0);
+        let mut t2 = curve_b3.mul(cs, &mut t2);
+
+        let mut z3 = t1.add(cs, &mut t2);
+        let mut t1 = t1.sub(cs, &mut t2);
+        let mut y3 = curve_b3.mul(cs, &mut y3);
+
+        let mut x3 = t4.mul(cs, &mut y3);
+        let mut t2 = t3.mul(cs, &mut t1);
+        let x3 = t2.sub(cs, &mut x3);
+
+        let mut y3 = y3.mul(cs, &mut t0);
+        let mut t1 = t1.mul(cs, &mut z3);
+        let y3 = t1.add(cs, &mut y3);
+
+        let mut t0 = t0.mul(cs, &mut t3);
+        let mut z3 = z3.mul(cs, &mut t4);
+        let z3 = z3.add(cs, &mut t0);
+
+        Self {
+            x: x3,
+            y: y3,
+            z: z3,
+        }
+    }
+}
+
+fn is_in_g2_subgroup<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    point: &mut BN254G2AffinePoint<F>,
+    params: &Arc<BN254BaseNNFieldParams>,
+) -> Boolean<F> {
+    let curve_b = BN254G2Affine::b_coeff();
+    let mut curve_b = BN254Fq2::constant(cs, curve_b.c0, curve_b.c1, params);
+    let mut curve_b3 = curve_b.double(cs);
+    let mut curve_b3 = curve_b3.add(cs, &mut curve_b);
+
+    // scalar is a constant, so we do simple double-and-add starting from the top bit
+    let mut base = BN254G2ProjectivePoint::from_affine(cs, point, params);
+    let mut acc = base.clone();
+    let num_bits = 128 - G2_SUBGROUP_CHECK_SCALAR.leading_zeros();
+    for i in (0..(num_bits - 1)).rev() {
+        let mut acc_copy = acc.clone();
+        acc = acc.add(cs, &mut acc_copy, &mut curve_b3);
+        if (G2_SUBGROUP_CHECK_SCALAR >> i) & 1 == 1 {
+            acc = acc.add(cs, &mut base, &mut curve_b3);
+        }
+    }
+
+    let mut psi = point.psi(cs, params);
+
+    // compare in projective form
+    let mut x_scaled = psi.x.mul(cs, &mut acc.z);
+    let mut y_scaled = psi.y.mul(cs, &mut acc.z);
+    let x_is_equal = BN254Fq2::equals(cs, &mut x_scaled, &mut acc.x);
+    let y_is_equal = BN254Fq2::equals(cs, &mut y_scaled, &mut acc.y);
+    acc.z.normalize(cs);
+    let z_is_zero = acc.z.is_zero(cs);
+    let z_is_not_zero = z_is_zero.negated(cs);
+
+    Boolean::multi_and(cs, &[x_is_equal, y_is_equal, z_is_not_zero])
+}
+
+/// Checks that the coordinates are in range and either form a point of G2, or are all zeroes that encode
+/// the point at infinity. Returns the point masked to the generator if it's invalid or at infinity,
+/// so it can be safely used in the Miller loop, and flags for "infinity" and "invalid" cases
+pub(crate) fn bn254_validate_and_mask_g2_point<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    x_c0: &UInt256<F>,
+    x_c1: &UInt256<F>,
+    y_c0: &UInt256<F>,
+    y_c1: &UInt256<F>,
+    params: &Arc<BN254BaseNNFieldParams>,
+) -> (BN254G2AffinePoint<F>, Boolean<F>, Boolean<F>) {
+    let bn254_p_u256 = U256([
+        params.modulus_u1024.as_ref().as_words()[0],
+        params.modulus_u1024.as_ref().as_words()[1],
+        params.modulus_u1024.as_ref().as_words()[2],
+        params.modulus_u1024.as_ref().as_words()[3],
+    ]);
+    let bn254_p_u256 = UInt256::allocated_constant(cs, bn254_p_u256);
+
+    let mut coordinates = [*x_c0, *x_c1, *y_c0, *y_c1];
+    let mut in_range_flags = [Boolean::allocated_constant(cs, false); 4];
+    let mut is_zero_flags = [Boolean::allocated_constant(cs, false); 4];
+    for ((coordinate, in_range), is_zero) in coordinates
+        .iter_mut()
+        .zip(in_range_flags.iter_mut())
+        .zip(is_zero_flags.iter_mut())
+    {
+        let (_res, is_in_range) = coordinate.overflowing_sub(cs, &bn254_p_u256);
+        *coordinate = coordinate.mask(cs, is_in_range);
+        *in_range = is_in_range;
+        *is_zero = coordinate.is_zero(cs);
+    }
+    let coordinates_are_in_range = Boolean::multi_and(cs, &in_range_flags);
+    let all_zeroes = Boolean::multi_and(cs, &is_zero_flags);
+    let is_infinity = Boolean::multi_and(cs, &[all_zeroes, coordinates_are_in_range]);
+
+    let [x_c0, x_c1, y_c0, y_c1] =
+        coordinates.map(|el| convert_uint256_to_field_element(cs, &el, params));
+    let mut point = BN254G2AffinePoint {
+        x: BN254Fq2 { c0: x_c0, c1: x_c1 },
+        y: BN254Fq2 { c0: y_c0, c1: y_c1 },
+    };
+
+    // perform on-curve check, twisted curve equation is y^2 = x^3 + b / xi
+    let curve_b = BN254G2Affine::b_coeff();
+    let mut curve_b = BN254Fq2::constant(cs, curve_b.c0, curve_b.c1, params);
+
+    let mut lhs = point.y.square(cs);
+    let mut rhs = point.x.square(cs);
+    let mut rhs = rhs.mul(cs, &mut point.x);
+    let mut rhs = rhs.add(cs, &mut curve_b);
+    let is_on_curve = BN254Fq2::equals(cs, &mut lhs, &mut rhs);
+
+    // subgroup check only makes sense for points on curve, so we mask the point first
+    let generator = BN254G2AffinePoint::generator(cs, params);
+    let mut point_for_subgroup_check =
+        BN254G2AffinePoint::conditionally_select(cs, is_on_curve, &point, &generator);
+    let is_in_subgroup = is_in_g2_subgroup(cs, &mut point_for_subgroup_check, params);
+
+    let is_valid_point = Boolean::multi_and(cs, &[is_on_curve, is_in_subgroup]);
+    let is_valid = Boolean::multi_or(cs, &[is_valid_point, is_infinity]);
+    let is_valid = Boolean::multi_and(cs, &[is_valid, coordinates_are_in_range]);
+    let is_invalid = is_valid.negated(cs);
+
+    let should_mask = Boolean::multi_or(cs, &[is_invalid, is_infinity]);
+    let point = BN254G2AffinePoint::conditionally_select(cs, should_mask, &generator, &point);
+
+    (point, is_infinity, is_invalid)
+}
+
+// line through T with slope lambda, evaluated at P, that is
+// y_P + (-lambda * x_P) w + (lambda * x_T - y_T) w^3 after untwisting
+fn evaluate_line<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    lambda: &mut BN254Fq2<F>,
+    t: &mut BN254G2AffinePoint<F>,
+    p: &mut (BN254BaseNNField<F>, BN254BaseNNField<F>),
+    params: &Arc<BN254BaseNNFieldParams>,
+) -> BN254Fq12<F> {
+    let zero = BN254Fq2::zero(cs, params);
+
+    let c0 = BN254Fq6 {
+        c0: BN254Fq2 {
+            c0: p.1.clone(),
+            c1: zero.c0.clone(),
+        },
+        c1: zero.clone(),
+        c2: zero.clone(),
+    };
+
+    let mut lambda_times_x_p = lambda.mul_by_base_field(cs, &mut p.0);
+    let mut lambda_times_x_t = lambda.mul(cs, &mut t.x);
+    let c1 = BN254Fq6 {
+        c0: lambda_times_x_p.negated(cs),
+        c1: lambda_times_x_t.sub(cs, &mut t.y),
+        c2: zero,
+    };
+
+    BN254Fq12 { c0, c1 }
+}
+
+// computes T = T + T or T = T + Q in affine form, and returns the line function for the step
+fn miller_loop_step<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    t: &mut BN254G2AffinePoint<F>,
+    q: Option<&mut BN254G2AffinePoint<F>>,
+    p: &mut (BN254BaseNNField<F>, BN254BaseNNField<F>),
+    params: &Arc<BN254BaseNNFieldParams>,
+) -> BN254Fq12<F> {
+    // We work with the points of G2 only, and T is never equal to +-Q, so there are no exceptional cases
+    let (mut lambda, mut q_x) = match q {
+        None => {
+            // lambda = 3 x_T^2 / 2 y_T
+            let mut x_squared = t.x.square(cs);
+            let mut numerator = x_squared.double(cs);
+            let mut numerator = numerator.add(cs, &mut x_squared);
+            let mut denominator = t.y.double(cs);
+            let lambda = numerator.div(cs, &mut denominator);
+
+            (lambda, t.x.clone())
+        }
+        Some(q) => {
+            // lambda = (y_Q - y_T) / (x_Q - x_T)
+            let mut numerator = q.y.sub(cs, &mut t.y);
+            let mut denominator = q.x.sub(cs, &mut t.x);
+            let lambda = numerator.div(cs, &mut denominator);
+
+            (lambda, q.x.clone())
+        }
+    };
+
+    let line = evaluate_line(cs, &mut lambda, t, p, params);
+
+    // x_R = lambda^2 - x_T - x_Q, y_R = lambda (x_T - x_R) - y_T
+    let mut x = lambda.square(cs);
+    let mut x = x.sub(cs, &mut t.x);
+    let mut x = x.sub(cs, &mut q_x);
+    let mut y = t.x.sub(cs, &mut x);
+    let mut y = y.mul(cs, &mut lambda);
+    let y = y.sub(cs, &mut t.y);
+
+    *t = BN254G2AffinePoint { x, y };
+
+    line
+}
+
+/// Optimal ate Miller loop for the pair of valid points not at infinity
+pub(crate) fn bn254_miller_loop<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    p: &mut (BN254BaseNNField<F>, BN254BaseNNField<F>),
+    q: &mut BN254G2AffinePoint<F>,
+    params: &Arc<BN254BaseNNFieldParams>,
+) -> BN254Fq12<F> {
+    let mut f = BN254Fq12::one(cs, params);
+    let mut t = q.clone();
+    let mut q_negated = q.negated(cs);
+
+    for i in (0..(ATE_LOOP_COUNT_NAF.len() - 1)).rev() {
+        if i != ATE_LOOP_COUNT_NAF.len() - 2 {
+            f = f.square(cs);
+        }
+
+        let mut line = miller_loop_step(cs, &mut t, None, p, params);
+        f = f.mul(cs, &mut line);
+
+        let q_to_add = match ATE_LOOP_COUNT_NAF[i] {
+            1 => Some(&mut *q),
+            -1 => Some(&mut q_negated),
+            _ => None,
+        };
+        if let Some(q_to_add) = q_to_add {
+            let mut line = miller_loop_step(cs, &mut t, Some(q_to_add), p, params);
+            f = f.mul(cs, &mut line);
+        }
+    }
+
+    // two more steps with Q1 = pi(Q) and -Q2 = -pi^2(Q)
+    let mut q1 = q.psi(cs, params);
+    let mut q2 = q1.psi(cs, params);
+    let mut q2_negated = q2.negated(cs);
+
+    let mut line = miller_loop_step(cs, &mut t, Some(&mut q1), p, params);
+    f = f.mul(cs, &mut line);
+    let mut line = miller_loop_step(cs, &mut t, Some(&mut q2_negated), p, params);
+    f = f.mul(cs, &mut line);
+
+    f
+}
+
+fn exp_by_u<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    f: &mut BN254Fq12<F>,
+) -> BN254Fq12<F> {
+    let mut result = f.clone();
+    let num_bits = 64 - BN254_U.leading_zeros();
+    for i in (0..(num_bits - 1)).rev() {
+        result = result.square(cs);
+        if (BN254_U >> i) & 1 == 1 {
+            result = result.mul(cs, f);
+        }
+    }
+
+    result
+}
+
+// we work in cyclotomic subgroup, so conjugation is an inversion
+fn exp_by_neg_u<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    f: &mut BN254Fq12<F>,
+) -> BN254Fq12<F> {
+    let mut result = exp_by_u(cs, f);
+    result.conjugate(cs)
+}
+
+/// Final exponentiation with the hard part from https://eprint.iacr.org/2008/490.
+/// It computes a fixed power of the f^((p^12 - 1) / r) that is coprime to r,
+/// that is enough to compare the result with one
+pub(crate) fn bn254_final_exponentiation<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    f: &mut BN254Fq12<F>,
+    params: &Arc<BN254BaseNNFieldParams>,
+) -> BN254Fq12<F> {
+    // easy part: f^((p^6 - 1)(p^2 + 1))
+    let mut f1 = f.conjugate(cs);
+    let mut f2 = f.inverse(cs);
+    let mut r = f1.mul(cs, &mut f2);
+    let mut f2 = r.clone();
+    let mut r = r.frobenius_map(cs, 2, params);
+    let mut r = r.mul(cs, &mut f2);
+
+    // hard part
+    let mut y0 = exp_by_neg_u(cs, &mut r);
+    let mut y1 = y0.square(cs);
+    let mut y2 = y1.square(cs);
+    let mut y3 = y2.mul(cs, &mut y1);
+    let mut y4 = exp_by_neg_u(cs, &mut y3);
+    let mut y5 = y4.square(cs);
+    let mut y6 = exp_by_neg_u(cs, &mut y5);
+    let mut y3 = y3.conjugate(cs);
+    let mut y6 = y6.conjugate(cs);
+    let mut y7 = y6.mul(cs, &mut y4);
+    let mut y8 = y7.mul(cs, &mut y3);
+    let mut y9 = y8.mul(cs, &mut y1);
+    let mut y10 = y8.mul(cs, &mut y4);
+    let mut y11 = y10.mul(cs, &mut r);
+    let mut y12 = y9.frobenius_map(cs, 1, params);
+    let mut y13 = y12.mul(cs, &mut y11);
+    let mut y8 = y8.frobenius_map(cs, 2, params);
+    let mut y14 = y8.mul(cs, &mut y13);
+    let mut r = r.conjugate(cs);
+    let mut y15 = r.mul(cs, &mut y9);
+    let mut y15 = y15.frobenius_map(cs, 3, params);
+
+    y15.mul(cs, &mut y14)
+}
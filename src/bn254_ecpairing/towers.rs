@@ -0,0 +1,634 @@
+use super::*;
+
+use boojum::gadgets::non_native_field::traits::NonNativeField;
+use boojum::pairing::ff::{Field, PrimeField};
+
+// Tower of extensions used by the BN254 pairing:
+// - Fq2 = Fq[u] / (u^2 + 1)
+// - Fq6 = Fq2[v] / (v^3 - xi), where xi = 9 + u
+// - Fq12 = Fq6[w] / (w^2 - v)
+//
+// All the operations take values by mutable reference, the same way as non-native field arithmetic does
+
+// Frobenius coefficients gamma_{k, m} = xi^(m * (p^k - 1) / 6) for k = 1, 2, 3 and m = 1..5,
+// stored as (c0, c1) pairs of Fq2 elements. Basis element of Fq12 with index m is w^m
+const FROBENIUS_COEFFS_C0_C1: [[(&str, &str); 5]; 3] = [
+    [
+        (
+            "8376118865763821496583973867626364092589906065868298776909617916018768340080",
+            "16469823323077808223889137241176536799009286646108169935659301613961712198316",
+        ),
+        (
+            "21575463638280843010398324269430826099269044274347216827212613867836435027261",
+            "10307601595873709700152284273816112264069230130616436755625194854815875713954",
+        ),
+        (
+            "2821565182194536844548159561693502659359617185244120367078079554186484126554",
+            "3505843767911556378687030309984248845540243509899259641013678093033130930403",
+        ),
+        (
+            "2581911344467009335267311115468803099551665605076196740867805258568234346338",
+            "19937756971775647987995932169929341994314640652964949448313374472400716661030",
+        ),
+        (
+            "685108087231508774477564247770172212460312782337200605669322048753928464687",
+            "8447204650696766136447902020341177575205426561248465145919723016860428151883",
+        ),
+    ],
+    [
+        (
+            "21888242871839275220042445260109153167277707414472061641714758635765020556617",
+            "0",
+        ),
+        (
+            "21888242871839275220042445260109153167277707414472061641714758635765020556616",
+            "0",
+        ),
+        (
+            "21888242871839275222246405745257275088696311157297823662689037894645226208582",
+            "0",
+        ),
+        (
+            "2203960485148121921418603742825762020974279258880205651966",
+            "0",
+        ),
+        (
+            "2203960485148121921418603742825762020974279258880205651967",
+            "0",
+        ),
+    ],
+    [
+        (
+            "11697423496358154304825782922584725312912383441159505038794027105778954184319",
+            "303847389135065887422783454877609941456349188919719272345083954437860409601",
+        ),
+        (
+            "3772000881919853776433695186713858239009073593817195771773381919316419345261",
+            "2236595495967245188281701248203181795121068902605861227855261137820944008926",
+        ),
+        (
+            "19066677689644738377698246183563772429336693972053703295610958340458742082029",
+            "18382399103927718843559375435273026243156067647398564021675359801612095278180",
+        ),
+        (
+            "5324479202449903542726783395506214481928257762400643279780343368557297135718",
+            "16208900380737693084919495127334387981393726419856888799917914180988844123039",
+        ),
+        (
+            "8941241848238582420466759817324047081148088512956452953208002715982955420483",
+            "10338197737521362862238855242243140895517409139741313354160881284257516364953",
+        ),
+    ],
+];
+
+pub(crate) fn bn254_fq_from_str(value: &str) -> BN254Fq {
+    BN254Fq::from_str(value).expect("must be a valid field element")
+}
+
+/// Returns xi^(basis_power * (p^power - 1) / 6)
+pub(crate) fn frobenius_coeff<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    power: usize,
+    basis_power: usize,
+    params: &Arc<BN254BaseNNFieldParams>,
+) -> BN254Fq2<F> {
+    let (c0, c1) = FROBENIUS_COEFFS_C0_C1[power - 1][basis_power - 1];
+
+    BN254Fq2::constant(cs, bn254_fq_from_str(c0), bn254_fq_from_str(c1), params)
+}
+
+#[derive(Derivative)]
+#[derivative(Clone, Debug)]
+pub(crate) struct BN254Fq2<F: SmallField> {
+    pub(crate) c0: BN254BaseNNField<F>,
+    pub(crate) c1: BN254BaseNNField<F>,
+}
+
+impl<F: SmallField> BN254Fq2<F> {
+    pub(crate) fn constant<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        c0: BN254Fq,
+        c1: BN254Fq,
+        params: &Arc<BN254BaseNNFieldParams>,
+    ) -> Self {
+        Self {
+            c0: BN254BaseNNField::allocated_constant(cs, c0, params),
+            c1: BN254BaseNNField::allocated_constant(cs, c1, params),
+        }
+    }
+
+    pub(crate) fn zero<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        params: &Arc<BN254BaseNNFieldParams>,
+    ) -> Self {
+        Self::constant(cs, BN254Fq::zero(), BN254Fq::zero(), params)
+    }
+
+    pub(crate) fn one<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        params: &Arc<BN254BaseNNFieldParams>,
+    ) -> Self {
+        Self::constant(cs, BN254Fq::one(), BN254Fq::zero(), params)
+    }
+
+    pub(crate) fn add<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS, other: &mut Self) -> Self {
+        Self {
+            c0: self.c0.add(cs, &mut other.c0),
+            c1: self.c1.add(cs, &mut other.c1),
+        }
+    }
+
+    pub(crate) fn sub<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS, other: &mut Self) -> Self {
+        Self {
+            c0: self.c0.sub(cs, &mut other.c0),
+            c1: self.c1.sub(cs, &mut other.c1),
+        }
+    }
+
+    pub(crate) fn double<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        Self {
+            c0: self.c0.double(cs),
+            c1: self.c1.double(cs),
+        }
+    }
+
+    pub(crate) fn negated<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        Self {
+            c0: self.c0.negated(cs),
+            c1: self.c1.negated(cs),
+        }
+    }
+
+    pub(crate) fn conjugate<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        Self {
+            c0: self.c0.clone(),
+            c1: self.c1.negated(cs),
+        }
+    }
+
+    pub(crate) fn mul<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS, other: &mut Self) -> Self {
+        // Karatsuba: (a0 + a1 u)(b0 + b1 u) = a0 b0 - a1 b1 + ((a0 + a1)(b0 + b1) - a0 b0 - a1 b1) u
+        let mut v0 = self.c0.mul(cs, &mut other.c0);
+        let mut v1 = self.c1.mul(cs, &mut other.c1);
+
+        let c0 = v0.sub(cs, &mut v1);
+
+        let mut a = self.c0.add(cs, &mut self.c1);
+        let mut b = other.c0.add(cs, &mut other.c1);
+        let mut c1 = a.mul(cs, &mut b);
+        let mut c1 = c1.sub(cs, &mut v0);
+        let c1 = c1.sub(cs, &mut v1);
+
+        Self { c0, c1 }
+    }
+
+    pub(crate) fn square<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        // (a0 + a1 u)^2 = (a0 + a1)(a0 - a1) + 2 a0 a1 u
+        let mut a = self.c0.add(cs, &mut self.c1);
+        let mut b = self.c0.sub(cs, &mut self.c1);
+        let c0 = a.mul(cs, &mut b);
+        let mut c1 = self.c0.mul(cs, &mut self.c1);
+        let c1 = c1.double(cs);
+
+        Self { c0, c1 }
+    }
+
+    pub(crate) fn mul_by_base_field<CS: ConstraintSystem<F>>(
+        &mut self,
+        cs: &mut CS,
+        other: &mut BN254BaseNNField<F>,
+    ) -> Self {
+        Self {
+            c0: self.c0.mul(cs, other),
+            c1: self.c1.mul(cs, other),
+        }
+    }
+
+    pub(crate) fn mul_by_xi<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        // (a0 + a1 u)(9 + u) = 9 a0 - a1 + (a0 + 9 a1) u
+        let mut a0_times_2 = self.c0.double(cs);
+        let mut a0_times_4 = a0_times_2.double(cs);
+        let mut a0_times_8 = a0_times_4.double(cs);
+        let mut a0_times_9 = a0_times_8.add(cs, &mut self.c0);
+
+        let mut a1_times_2 = self.c1.double(cs);
+        let mut a1_times_4 = a1_times_2.double(cs);
+        let mut a1_times_8 = a1_times_4.double(cs);
+        let mut a1_times_9 = a1_times_8.add(cs, &mut self.c1);
+
+        Self {
+            c0: a0_times_9.sub(cs, &mut self.c1),
+            c1: a1_times_9.add(cs, &mut self.c0),
+        }
+    }
+
+    /// Caller must ensure that the element is not zero, otherwise the circuit is unsatisfiable
+    pub(crate) fn inverse<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        // (a0 + a1 u)^-1 = (a0 - a1 u) / (a0^2 + a1^2)
+        let mut a0_squared = self.c0.square(cs);
+        let mut a1_squared = self.c1.square(cs);
+        let mut norm = a0_squared.add(cs, &mut a1_squared);
+        norm.normalize(cs);
+        let mut norm_inv = norm.inverse_unchecked(cs);
+
+        let c0 = self.c0.mul(cs, &mut norm_inv);
+        let mut c1 = self.c1.mul(cs, &mut norm_inv);
+        let c1 = c1.negated(cs);
+
+        Self { c0, c1 }
+    }
+
+    /// Caller must ensure that the divisor is not zero, otherwise the circuit is unsatisfiable
+    pub(crate) fn div<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS, other: &mut Self) -> Self {
+        let mut other_inv = other.inverse(cs);
+        self.mul(cs, &mut other_inv)
+    }
+
+    pub(crate) fn normalize<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) {
+        self.c0.normalize(cs);
+        self.c1.normalize(cs);
+    }
+
+    pub(crate) fn equals<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        a: &mut Self,
+        b: &mut Self,
+    ) -> Boolean<F> {
+        a.normalize(cs);
+        b.normalize(cs);
+        let c0_is_equal = NonNativeFieldOverU16::equals(cs, &mut a.c0, &mut b.c0);
+        let c1_is_equal = NonNativeFieldOverU16::equals(cs, &mut a.c1, &mut b.c1);
+
+        Boolean::multi_and(cs, &[c0_is_equal, c1_is_equal])
+    }
+
+    pub(crate) fn is_zero<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Boolean<F> {
+        let c0_is_zero = self.c0.is_zero(cs);
+        let c1_is_zero = self.c1.is_zero(cs);
+
+        Boolean::multi_and(cs, &[c0_is_zero, c1_is_zero])
+    }
+
+    pub(crate) fn conditionally_select<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        flag: Boolean<F>,
+        a: &Self,
+        b: &Self,
+    ) -> Self {
+        Self {
+            c0: NonNativeFieldOverU16::conditionally_select(cs, flag, &a.c0, &b.c0),
+            c1: NonNativeFieldOverU16::conditionally_select(cs, flag, &a.c1, &b.c1),
+        }
+    }
+}
+
+#[derive(Derivative)]
+#[derivative(Clone, Debug)]
+pub(crate) struct BN254Fq6<F: SmallField> {
+    pub(crate) c0: BN254Fq2<F>,
+    pub(crate) c1: BN254Fq2<F>,
+    pub(crate) c2: BN254Fq2<F>,
+}
+
+impl<F: SmallField> BN254Fq6<F> {
+    pub(crate) fn zero<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        params: &Arc<BN254BaseNNFieldParams>,
+    ) -> Self {
+        let zero = BN254Fq2::zero(cs, params);
+        Self {
+            c0: zero.clone(),
+            c1: zero.clone(),
+            c2: zero,
+        }
+    }
+
+    pub(crate) fn one<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        params: &Arc<BN254BaseNNFieldParams>,
+    ) -> Self {
+        let zero = BN254Fq2::zero(cs, params);
+        Self {
+            c0: BN254Fq2::one(cs, params),
+            c1: zero.clone(),
+            c2: zero,
+        }
+    }
+
+    pub(crate) fn add<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS, other: &mut Self) -> Self {
+        Self {
+            c0: self.c0.add(cs, &mut other.c0),
+            c1: self.c1.add(cs, &mut other.c1),
+            c2: self.c2.add(cs, &mut other.c2),
+        }
+    }
+
+    pub(crate) fn sub<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS, other: &mut Self) -> Self {
+        Self {
+            c0: self.c0.sub(cs, &mut other.c0),
+            c1: self.c1.sub(cs, &mut other.c1),
+            c2: self.c2.sub(cs, &mut other.c2),
+        }
+    }
+
+    pub(crate) fn negated<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        Self {
+            c0: self.c0.negated(cs),
+            c1: self.c1.negated(cs),
+            c2: self.c2.negated(cs),
+        }
+    }
+
+    pub(crate) fn mul<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS, other: &mut Self) -> Self {
+        // Karatsuba-like formulas for cubic extension
+        let mut v0 = self.c0.mul(cs, &mut other.c0);
+        let mut v1 = self.c1.mul(cs, &mut other.c1);
+        let mut v2 = self.c2.mul(cs, &mut other.c2);
+
+        // c0 = v0 + xi * ((a1 + a2)(b1 + b2) - v1 - v2)
+        let mut a = self.c1.add(cs, &mut self.c2);
+        let mut b = other.c1.add(cs, &mut other.c2);
+        let mut t = a.mul(cs, &mut b);
+        let mut t = t.sub(cs, &mut v1);
+        let mut t = t.sub(cs, &mut v2);
+        let mut t = t.mul_by_xi(cs);
+        let c0 = v0.add(cs, &mut t);
+
+        // c1 = (a0 + a1)(b0 + b1) - v0 - v1 + xi * v2
+        let mut a = self.c0.add(cs, &mut self.c1);
+        let mut b = other.c0.add(cs, &mut other.c1);
+        let mut t = a.mul(cs, &mut b);
+        let mut t = t.sub(cs, &mut v0);
+        let mut t = t.sub(cs, &mut v1);
+        let mut v2_times_xi = v2.mul_by_xi(cs);
+        let c1 = t.add(cs, &mut v2_times_xi);
+
+        // c2 = (a0 + a2)(b0 + b2) - v0 - v2 + v1
+        let mut a = self.c0.add(cs, &mut self.c2);
+        let mut b = other.c0.add(cs, &mut other.c2);
+        let mut t = a.mul(cs, &mut b);
+        let mut t = t.sub(cs, &mut v0);
+        let mut t = t.sub(cs, &mut v2);
+        let c2 = t.add(cs, &mut v1);
+
+        Self { c0, c1, c2 }
+    }
+
+    pub(crate) fn mul_by_v<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        // (a0 + a1 v + a2 v^2) v = xi a2 + a0 v + a1 v^2
+        Self {
+            c0: self.c2.mul_by_xi(cs),
+            c1: self.c0.clone(),
+            c2: self.c1.clone(),
+        }
+    }
+
+    /// Caller must ensure that the element is not zero, otherwise the circuit is unsatisfiable
+    pub(crate) fn inverse<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        // t0 = a0^2 - xi a1 a2
+        let mut t0 = self.c0.square(cs);
+        let mut tmp = self.c1.mul(cs, &mut self.c2);
+        let mut tmp = tmp.mul_by_xi(cs);
+        let mut t0 = t0.sub(cs, &mut tmp);
+
+        // t1 = xi a2^2 - a0 a1
+        let mut t1 = self.c2.square(cs);
+        let mut t1 = t1.mul_by_xi(cs);
+        let mut tmp = self.c0.mul(cs, &mut self.c1);
+        let mut t1 = t1.sub(cs, &mut tmp);
+
+        // t2 = a1^2 - a0 a2
+        let mut t2 = self.c1.square(cs);
+        let mut tmp = self.c0.mul(cs, &mut self.c2);
+        let mut t2 = t2.sub(cs, &mut tmp);
+
+        // norm = a0 t0 + xi (a2 t1 + a1 t2)
+        let mut tmp_0 = self.c2.mul(cs, &mut t1);
+        let mut tmp_1 = self.c1.mul(cs, &mut t2);
+        let mut tmp = tmp_0.add(cs, &mut tmp_1);
+        let mut tmp = tmp.mul_by_xi(cs);
+        let mut norm = self.c0.mul(cs, &mut t0);
+        let mut norm = norm.add(cs, &mut tmp);
+        let mut norm_inv = norm.inverse(cs);
+
+        Self {
+            c0: t0.mul(cs, &mut norm_inv),
+            c1: t1.mul(cs, &mut norm_inv),
+            c2: t2.mul(cs, &mut norm_inv),
+        }
+    }
+
+    pub(crate) fn conditionally_select<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        flag: Boolean<F>,
+        a: &Self,
+        b: &Self,
+    ) -> Self {
+        Self {
+            c0: BN254Fq2::conditionally_select(cs, flag, &a.c0, &b.c0),
+            c1: BN254Fq2::conditionally_select(cs, flag, &a.c1, &b.c1),
+            c2: BN254Fq2::conditionally_select(cs, flag, &a.c2, &b.c2),
+        }
+    }
+}
+
+#[derive(Derivative)]
+#[derivative(Clone, Debug)]
+pub(crate) struct BN254Fq12<F: SmallField> {
+    pub(crate) c0: BN254Fq6<F>,
+    pub(crate) c1: BN254Fq6<F>,
+}
+
+impl<F: SmallField> BN254Fq12<F> {
+    pub(crate) fn one<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        params: &Arc<BN254BaseNNFieldParams>,
+    ) -> Self {
+        Self {
+            c0: BN254Fq6::one(cs, params),
+            c1: BN254Fq6::zero(cs, params),
+        }
+    }
+
+    pub(crate) fn mul<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS, other: &mut Self) -> Self {
+        // (a0 + a1 w)(b0 + b1 w) = a0 b0 + a1 b1 v + ((a0 + a1)(b0 + b1) - a0 b0 - a1 b1) w
+        let mut v0 = self.c0.mul(cs, &mut other.c0);
+        let mut v1 = self.c1.mul(cs, &mut other.c1);
+
+        let mut v1_times_v = v1.mul_by_v(cs);
+        let c0 = v0.add(cs, &mut v1_times_v);
+
+        let mut a = self.c0.add(cs, &mut self.c1);
+        let mut b = other.c0.add(cs, &mut other.c1);
+        let mut c1 = a.mul(cs, &mut b);
+        let mut c1 = c1.sub(cs, &mut v0);
+        let c1 = c1.sub(cs, &mut v1);
+
+        Self { c0, c1 }
+    }
+
+    pub(crate) fn square<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        let mut other = self.clone();
+        self.mul(cs, &mut other)
+    }
+
+    pub(crate) fn conjugate<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        Self {
+            c0: self.c0.clone(),
+            c1: self.c1.negated(cs),
+        }
+    }
+
+    /// Caller must ensure that the element is not zero, otherwise the circuit is unsatisfiable
+    pub(crate) fn inverse<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        // (a0 + a1 w)^-1 = (a0 - a1 w) / (a0^2 - a1^2 v)
+        let mut a0 = self.c0.clone();
+        let mut a1 = self.c1.clone();
+        let mut a0_squared = self.c0.mul(cs, &mut a0);
+        let mut a1_squared = self.c1.mul(cs, &mut a1);
+        let mut a1_squared_times_v = a1_squared.mul_by_v(cs);
+        let mut norm = a0_squared.sub(cs, &mut a1_squared_times_v);
+        let mut norm_inv = norm.inverse(cs);
+
+        let c0 = self.c0.mul(cs, &mut norm_inv);
+        let mut c1 = self.c1.mul(cs, &mut norm_inv);
+        let c1 = c1.negated(cs);
+
+        Self { c0, c1 }
+    }
+
+    /// Raises the element into p^power, where power is 1, 2 or 3
+    pub(crate) fn frobenius_map<CS: ConstraintSystem<F>>(
+        &mut self,
+        cs: &mut CS,
+        power: usize,
+        params: &Arc<BN254BaseNNFieldParams>,
+    ) -> Self {
+        assert!(power >= 1 && power <= 3);
+
+        // coefficient of v^j in c_i is multiplied by w^(i + 2j)
+        let coeffs = [
+            &mut self.c0.c0,
+            &mut self.c1.c0,
+            &mut self.c0.c1,
+            &mut self.c1.c1,
+            &mut self.c0.c2,
+            &mut self.c1.c2,
+        ];
+        let mut result = Vec::with_capacity(6);
+        for (basis_power, coeff) in coeffs.into_iter().enumerate() {
+            let mut coeff = if power % 2 == 1 {
+                coeff.conjugate(cs)
+            } else {
+                coeff.clone()
+            };
+            if basis_power != 0 {
+                let mut gamma = frobenius_coeff(cs, power, basis_power, params);
+                coeff = coeff.mul(cs, &mut gamma);
+            }
+            result.push(coeff);
+        }
+
+        let [c00, c10, c01, c11, c02, c12]: [BN254Fq2<F>; 6] = result.try_into().unwrap();
+
+        Self {
+            c0: BN254Fq6 {
+                c0: c00,
+                c1: c01,
+                c2: c02,
+            },
+            c1: BN254Fq6 {
+                c0: c10,
+                c1: c11,
+                c2: c12,
+            },
+        }
+    }
+
+    pub(crate) fn equals<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        a: &mut Self,
+        b: &mut Self,
+    ) -> Boolean<F> {
+        let mut flags = Vec::with_capacity(6);
+        for (a, b) in a.coefficients_mut().into_iter().zip(b.coefficients_mut()) {
+            flags.push(BN254Fq2::equals(cs, a, b));
+        }
+
+        Boolean::multi_and(cs, &flags)
+    }
+
+    pub(crate) fn conditionally_select<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        flag: Boolean<F>,
+        a: &Self,
+        b: &Self,
+    ) -> Self {
+        Self {
+            c0: BN254Fq6::conditionally_select(cs, flag, &a.c0, &b.c0),
+            c1: BN254Fq6::conditionally_select(cs, flag, &a.c1, &b.c1),
+        }
+    }
+
+    fn coefficients_mut(&mut self) -> [&mut BN254Fq2<F>; 6] {
+        [
+            &mut self.c0.c0,
+            &mut self.c0.c1,
+            &mut self.c0.c2,
+            &mut self.c1.c0,
+            &mut self.c1.c1,
+            &mut self.c1.c2,
+        ]
+    }
+
+    /// Decodes the element from 12 base field coefficients, in the order c0.c0.c0, c0.c0.c1, c0.c1.c0, ...
+    pub(crate) fn from_uint256_coefficients<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        coeffs: &[UInt256<F>; 12],
+        params: &Arc<BN254BaseNNFieldParams>,
+    ) -> Self {
+        let mut fq2_coeffs = Vec::with_capacity(6);
+        for [c0, c1] in coeffs.array_chunks::<2>() {
+            fq2_coeffs.push(BN254Fq2 {
+                c0: convert_uint256_to_field_element(cs, c0, params),
+                c1: convert_uint256_to_field_element(cs, c1, params),
+            });
+        }
+        let [c00, c01, c02, c10, c11, c12]: [BN254Fq2<F>; 6] = fq2_coeffs.try_into().unwrap();
+
+        Self {
+            c0: BN254Fq6 {
+                c0: c00,
+                c1: c01,
+                c2: c02,
+            },
+            c1: BN254Fq6 {
+                c0: c10,
+                c1: c11,
+                c2: c12,
+            },
+        }
+    }
+
+    /// Encodes the element as 12 base field coefficients in canonical form,
+    /// in the same order as `from_uint256_coefficients` expects
+    pub(crate) fn into_uint256_coefficients<CS: ConstraintSystem<F>>(
+        mut self,
+        cs: &mut CS,
+    ) -> [UInt256<F>; 12] {
+        let zero_u256 = UInt256::zero(cs);
+        let mut result = [zero_u256; 12];
+        for (dst, src) in result
+            .array_chunks_mut::<2>()
+            .zip(self.coefficients_mut().into_iter())
+        {
+            for (dst, src) in dst.iter_mut().zip([&mut src.c0, &mut src.c1]) {
+                src.normalize(cs);
+                src.enforce_reduced(cs);
+                *dst = convert_field_element_to_uint256(cs, src.clone());
+            }
+        }
+
+        result
+    }
+}
@@ -3,3 +3,15 @@ pub const CIRCUIT_VERSOBE: bool = true;
 
 #[cfg(not(feature = "verbose_circuits"))]
 pub const CIRCUIT_VERSOBE: bool = false;
+
+#[cfg(feature = "differential_vm_cycles")]
+pub const DIFFERENTIAL_VM_CYCLES: bool = true;
+
+#[cfg(not(feature = "differential_vm_cycles"))]
+pub const DIFFERENTIAL_VM_CYCLES: bool = false;
+
+#[cfg(feature = "vm_cycle_profiling")]
+pub const VM_CYCLE_PROFILING: bool = true;
+
+#[cfg(not(feature = "vm_cycle_profiling"))]
+pub const VM_CYCLE_PROFILING: bool = false;
@@ -20,7 +20,7 @@ use boojum::serde_utils::BigArraySerde;
 use cs_derive::*;
 use derivative::*;
 
-use super::NUM_DEMUX_OUTPUTS;
+use super::{ALL_DEMUX_OUTPUTS, NUM_DEMUX_OUTPUTS};
 
 #[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
 #[derivative(Clone, Copy, Debug)]
@@ -75,47 +75,11 @@ impl<F: SmallField> LogDemuxerOutputData<F> {
     pub fn all_output_queues_refs(
         &self,
     ) -> BTreeMap<DemuxOutput, &QueueState<F, QUEUE_STATE_WIDTH>> {
-        let tuples = [
-            (
-                DemuxOutput::RollupStorage,
-                &self.output_queue_states[DemuxOutput::RollupStorage as usize],
-            ),
-            (
-                DemuxOutput::PorterStorage,
-                &self.output_queue_states[DemuxOutput::PorterStorage as usize],
-            ),
-            (
-                DemuxOutput::Events,
-                &self.output_queue_states[DemuxOutput::Events as usize],
-            ),
-            (
-                DemuxOutput::L2ToL1Messages,
-                &self.output_queue_states[DemuxOutput::L2ToL1Messages as usize],
-            ),
-            (
-                DemuxOutput::Keccak,
-                &self.output_queue_states[DemuxOutput::Keccak as usize],
-            ),
-            (
-                DemuxOutput::Sha256,
-                &self.output_queue_states[DemuxOutput::Sha256 as usize],
-            ),
-            (
-                DemuxOutput::ECRecover,
-                &self.output_queue_states[DemuxOutput::ECRecover as usize],
-            ),
-            (
-                DemuxOutput::Secp256r1Verify,
-                &self.output_queue_states[DemuxOutput::Secp256r1Verify as usize],
-            ),
-            (
-                DemuxOutput::TransientStorage,
-                &self.output_queue_states[DemuxOutput::TransientStorage as usize],
-            ),
-        ];
-        assert_eq!(tuples.len(), NUM_DEMUX_OUTPUTS);
-
-        BTreeMap::from_iter(tuples.into_iter())
+        BTreeMap::from_iter(
+            ALL_DEMUX_OUTPUTS
+                .into_iter()
+                .map(|el| (el, &self.output_queue_states[el as usize])),
+        )
     }
 }
 
@@ -42,30 +42,81 @@ pub type StorageLogQueueWitness<F> =
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub enum DemuxOutput {
     RollupStorage = 0,
-    PorterStorage,
-    Events,
-    L2ToL1Messages,
-    Keccak,
-    Sha256,
-    ECRecover,
-    Secp256r1Verify,
-    TransientStorage,
+    PorterStorage = 1,
+    Events = 2,
+    L2ToL1Messages = 3,
+    Keccak = 4,
+    Sha256 = 5,
+    ECRecover = 6,
+    Secp256r1Verify = 7,
+    TransientStorage = 8,
+    ECAdd = 9,
+    ECMul = 10,
+    ECPairing = 11,
+    Modexp = 12,
+    Blake2f = 13,
+    Ripemd160 = 14,
+    PointEvaluation = 15,
+    Ed25519Verify = 16,
+    SchnorrVerify = 17,
+    Sha512 = 18,
+    Secp256r1Recover = 19,
+    Secp256r1BatchVerify = 20,
+    Poseidon2 = 21,
+    Bls12_381G1Add = 22,
+    Bls12_381G1Msm = 23,
+    Bls12_381G2Add = 24,
+    Bls12_381G2Msm = 25,
+    Bls12_381PairingCheck = 26,
+    Bls12_381MapFpToG1 = 27,
+    Bls12_381MapFp2ToG2 = 28,
 }
 
-pub const NUM_DEMUX_OUTPUTS: usize = DemuxOutput::TransientStorage as usize + 1;
-
-pub const ALL_DEMUX_OUTPUTS: [DemuxOutput; NUM_DEMUX_OUTPUTS] = [
+/// Outputs that are not precompiles, the rest are taken from the precompile registry
+const NON_PRECOMPILE_DEMUX_OUTPUTS: [DemuxOutput; 5] = [
     DemuxOutput::RollupStorage,
     DemuxOutput::PorterStorage,
     DemuxOutput::Events,
     DemuxOutput::L2ToL1Messages,
-    DemuxOutput::Keccak,
-    DemuxOutput::Sha256,
-    DemuxOutput::ECRecover,
-    DemuxOutput::Secp256r1Verify,
     DemuxOutput::TransientStorage,
 ];
 
+pub const NUM_DEMUX_OUTPUTS: usize =
+    NON_PRECOMPILE_DEMUX_OUTPUTS.len() + crate::precompile_registry::NUM_REGISTERED_PRECOMPILES;
+
+/// All the outputs ordered by their index
+pub const ALL_DEMUX_OUTPUTS: [DemuxOutput; NUM_DEMUX_OUTPUTS] = {
+    let mut all = [None; NUM_DEMUX_OUTPUTS];
+    let mut i = 0;
+    while i < NON_PRECOMPILE_DEMUX_OUTPUTS.len() {
+        let el = NON_PRECOMPILE_DEMUX_OUTPUTS[i];
+        all[el as usize] = Some(el);
+        i += 1;
+    }
+    let mut i = 0;
+    while i < crate::precompile_registry::NUM_REGISTERED_PRECOMPILES {
+        let el = crate::precompile_registry::PRECOMPILE_REGISTRY[i].demux_output;
+        assert!(
+            all[el as usize].is_none(),
+            "demux output index is used twice"
+        );
+        all[el as usize] = Some(el);
+        i += 1;
+    }
+
+    let mut result = [DemuxOutput::RollupStorage; NUM_DEMUX_OUTPUTS];
+    let mut i = 0;
+    while i < NUM_DEMUX_OUTPUTS {
+        match all[i] {
+            Some(el) => result[i] = el,
+            None => panic!("demux output indexes must not have gaps"),
+        }
+        i += 1;
+    }
+
+    result
+};
+
 impl DemuxOutput {
     pub fn is_implemented(&self) -> bool {
         match self {
@@ -80,18 +131,17 @@ impl DemuxOutput {
             Self::Events => EVENT_AUX_BYTE,
             Self::L2ToL1Messages => L1_MESSAGE_AUX_BYTE,
             Self::TransientStorage => TRANSIENT_STORAGE_AUX_BYTE,
-            _ => PRECOMPILE_AUX_BYTE,
+            _ => {
+                crate::precompile_registry::precompile_by_demux_output(*self)
+                    .expect("all the other outputs are registered precompiles")
+                    .aux_byte
+            }
         }
     }
 
     pub fn precompile_address(&self) -> Option<zkevm_opcode_defs::ethereum_types::H160> {
-        match self {
-            Self::Keccak => Some(*zkevm_opcode_defs::system_params::KECCAK256_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS),
-            Self::Sha256 => Some(*zkevm_opcode_defs::system_params::SHA256_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS),
-            Self::ECRecover => Some(*zkevm_opcode_defs::system_params::ECRECOVER_INNER_FUNCTION_PRECOMPILE_FORMAL_ADDRESS),
-            Self::Secp256r1Verify => Some(*zkevm_opcode_defs::system_params::SECP256R1_VERIFY_INNER_FUNCTION_PRECOMPILE_FORMAL_ADDRESS),
-            _ => None,
-        }
+        crate::precompile_registry::precompile_by_demux_output(*self)
+            .map(|precompile| (precompile.formal_address)())
     }
 
     pub fn shard_id(&self) -> Option<u8> {
@@ -0,0 +1,157 @@
+use super::*;
+
+use crate::ethereum_types::U256;
+
+use boojum::cs::traits::cs::ConstraintSystem;
+use boojum::field::SmallField;
+use boojum::gadgets::boolean::Boolean;
+
+use boojum::gadgets::non_native_field::implementations::*;
+
+use boojum::gadgets::traits::selectable::Selectable;
+use boojum::gadgets::u256::UInt256;
+use boojum::gadgets::u32::UInt32;
+use boojum::pairing::ff::{PrimeField, SqrtField};
+use boojum::pairing::GenericCurveAffine;
+
+use cs_derive::*;
+
+pub mod multiplication;
+pub mod verify;
+
+pub use self::multiplication::*;
+pub use self::verify::*;
+
+/// Constants of the GLV endomorphism (x, y) -> (beta * x, y) that acts as multiplication
+/// by lambda, together with the lattice basis for scalar decomposition. Decomposed scalars
+/// must fit into 33 windows of width 4, that is into 132 bits
+#[derive(Clone, Copy, Debug)]
+pub struct GlvParams {
+    // decimal string
+    pub beta: &'static str,
+    // (n - 1) / 2 as hex string, to round the division
+    pub modulus_minus_one_div_two: &'static str,
+    // hex strings, and b2 == a1
+    pub a1: &'static str,
+    pub b1: &'static str,
+    pub a2: &'static str,
+    pub max_decomposition_value: U256,
+}
+
+/// Short Weierstrass curve over 256 bit fields, which is used for ECDSA-like precompiles.
+/// `N` is the number of u16 limbs for non-native field elements
+pub trait EcdsaCurve<const N: usize>: 'static + Clone + Copy + Send + Sync {
+    type Base: PrimeField + SqrtField;
+    type Scalar: PrimeField;
+    type Affine: GenericCurveAffine<Base = Self::Base>;
+
+    const BASE_FIELD_CANONICAL_REPR_LIMBS: usize;
+    const SCALAR_FIELD_CANONICAL_REPR_LIMBS: usize;
+
+    fn base_field_params() -> NonNativeFieldOverU16Params<Self::Base, N>;
+    fn scalar_field_params() -> NonNativeFieldOverU16Params<Self::Scalar, N>;
+
+    /// Ids of the lookup tables with multiples of the generator: for every byte of the scalar
+    /// there are 8 tables, one per u32 word of the coordinates. Curves without such tables
+    /// multiply the generator as any other point
+    fn fixed_base_table_ids<F: SmallField, CS: ConstraintSystem<F>>(
+        _cs: &mut CS,
+    ) -> Option<Vec<[u32; 8]>> {
+        None
+    }
+
+    /// Curves with efficiently computable endomorphism use GLV scalar multiplication
+    fn glv_params() -> Option<GlvParams> {
+        None
+    }
+}
+
+#[derive(Derivative, CSSelectable)]
+#[derivative(Clone, Debug)]
+pub struct EcdsaPrecompileCallParams<F: SmallField> {
+    pub input_page: UInt32<F>,
+    pub input_offset: UInt32<F>,
+    pub output_page: UInt32<F>,
+    pub output_offset: UInt32<F>,
+    // reject signatures with s > n/2
+    pub enforce_low_s: Boolean<F>,
+}
+
+impl<F: SmallField> EcdsaPrecompileCallParams<F> {
+    pub fn from_encoding<CS: ConstraintSystem<F>>(cs: &mut CS, encoding: UInt256<F>) -> Self {
+        let input_offset = encoding.inner[0];
+        let output_offset = encoding.inner[2];
+        let input_page = encoding.inner[4];
+        let output_page = encoding.inner[5];
+        // any non-zero value enables the malleability-strict mode
+        let enforce_low_s = encoding.inner[6].is_zero(cs).negated(cs);
+
+        let new = Self {
+            input_page,
+            input_offset,
+            output_page,
+            output_offset,
+            enforce_low_s,
+        };
+
+        new
+    }
+}
+
+/// Checks that the value is less than the modulus, and masks it to zero otherwise.
+/// Returns the masked value and the exception flag
+pub(crate) fn range_check_and_mask<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    value: &UInt256<F>,
+    modulus: &UInt256<F>,
+) -> (UInt256<F>, Boolean<F>) {
+    let (_res, is_in_range) = value.overflowing_sub(cs, modulus);
+    let value = value.mask(cs, is_in_range);
+    let is_not_in_range = is_in_range.negated(cs);
+
+    (value, is_not_in_range)
+}
+
+pub(crate) fn modulus_as_uint256<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    P: PrimeField,
+    const N: usize,
+>(
+    cs: &mut CS,
+    params: &NonNativeFieldOverU16Params<P, N>,
+) -> UInt256<F> {
+    let modulus = U256([
+        params.modulus_u1024.as_ref().as_words()[0],
+        params.modulus_u1024.as_ref().as_words()[1],
+        params.modulus_u1024.as_ref().as_words()[2],
+        params.modulus_u1024.as_ref().as_words()[3],
+    ]);
+
+    UInt256::allocated_constant(cs, modulus)
+}
+
+/// Returns the exception flag that is set if low-s form is enforced and s > n/2
+pub(crate) fn high_s_exception<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    P: PrimeField,
+    const N: usize,
+>(
+    cs: &mut CS,
+    s: &UInt256<F>,
+    enforce_low_s: Boolean<F>,
+    params: &NonNativeFieldOverU16Params<P, N>,
+) -> Boolean<F> {
+    let modulus = U256([
+        params.modulus_u1024.as_ref().as_words()[0],
+        params.modulus_u1024.as_ref().as_words()[1],
+        params.modulus_u1024.as_ref().as_words()[2],
+        params.modulus_u1024.as_ref().as_words()[3],
+    ]);
+    // modulus is odd, so s <= n/2 is the same as s <= (n - 1)/2
+    let half_modulus = UInt256::allocated_constant(cs, (modulus - U256::one()) >> 1);
+    let (_res, s_is_high) = half_modulus.overflowing_sub(cs, s);
+
+    Boolean::multi_and(cs, &[s_is_high, enforce_low_s])
+}
@@ -0,0 +1,619 @@
+use super::*;
+
+use crate::ecrecover::baseline::convert_uint256_to_field_element;
+use crate::ecrecover::new_optimized::convert_field_element_to_uint256;
+
+use boojum::cs::gates::ConstantAllocatableCS;
+use boojum::cs::Variable;
+use boojum::gadgets::curves::sw_projective::SWProjectivePoint;
+use boojum::gadgets::non_native_field::traits::NonNativeField;
+use boojum::gadgets::num::Num;
+use boojum::gadgets::tables::ByteSplitTable;
+use boojum::gadgets::u16::UInt16;
+use boojum::gadgets::u512::UInt512;
+use boojum::gadgets::u8::UInt8;
+
+use std::sync::Arc;
+
+const WINDOW_WIDTH: usize = 4;
+const PRECOMPUTATION_TABLE_SIZE: usize = (1 << WINDOW_WIDTH) - 1;
+// decomposition scalars can be a little more than 2^128 in practice, so we use 33 chunks of width 4 bits
+const NUM_GLV_MULTIPLICATION_STEPS_FOR_WIDTH_4: usize = 33;
+
+type BaseNNField<F, C, const N: usize> = NonNativeFieldOverU16<F, <C as EcdsaCurve<N>>::Base, N>;
+type ScalarNNField<F, C, const N: usize> =
+    NonNativeFieldOverU16<F, <C as EcdsaCurve<N>>::Scalar, N>;
+type BaseNNFieldParams<C, const N: usize> =
+    NonNativeFieldOverU16Params<<C as EcdsaCurve<N>>::Base, N>;
+type ScalarNNFieldParams<C, const N: usize> =
+    NonNativeFieldOverU16Params<<C as EcdsaCurve<N>>::Scalar, N>;
+type ProjectivePoint<F, C, const N: usize> =
+    SWProjectivePoint<F, <C as EcdsaCurve<N>>::Affine, BaseNNField<F, C, N>>;
+
+/// Multiplies the point by the scalar using windows of width 4, and uses the GLV
+/// decomposition if the curve has an endomorphism
+pub fn width_4_windowed_multiplication<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    C: EcdsaCurve<N>,
+    const N: usize,
+>(
+    cs: &mut CS,
+    point: ProjectivePoint<F, C, N>,
+    scalar: ScalarNNField<F, C, N>,
+    base_field_params: &Arc<BaseNNFieldParams<C, N>>,
+    scalar_field_params: &Arc<ScalarNNFieldParams<C, N>>,
+) -> ProjectivePoint<F, C, N>
+where
+    [(); N + 1]:,
+{
+    match C::glv_params() {
+        Some(glv_params) => glv_width_4_windowed_multiplication::<F, CS, C, N>(
+            cs,
+            point,
+            scalar,
+            glv_params,
+            base_field_params,
+            scalar_field_params,
+        ),
+        None => plain_width_4_windowed_multiplication::<F, CS, C, N>(
+            cs,
+            point,
+            scalar,
+            base_field_params,
+        ),
+    }
+}
+
+// create precomputed table of size 1<<4 - 1
+// there is no 0 * P in the table, we will handle it in the multiplication loop
+fn create_precomputed_table<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    C: EcdsaCurve<N>,
+    const N: usize,
+>(
+    cs: &mut CS,
+    mut point: ProjectivePoint<F, C, N>,
+) -> Vec<(BaseNNField<F, C, N>, BaseNNField<F, C, N>)>
+where
+    [(); N + 1]:,
+{
+    let mut table = Vec::with_capacity(PRECOMPUTATION_TABLE_SIZE);
+    let mut tmp = point.clone();
+    let (mut p_affine, _) = point.convert_to_affine_or_default(cs, C::Affine::one());
+    table.push(p_affine.clone());
+    for _ in 1..PRECOMPUTATION_TABLE_SIZE {
+        // 2P, 3P, ...
+        tmp = tmp.add_mixed(cs, &mut p_affine);
+        let (affine, _) = tmp.convert_to_affine_or_default(cs, C::Affine::one());
+        table.push(affine);
+    }
+    assert_eq!(table.len(), PRECOMPUTATION_TABLE_SIZE);
+
+    table
+}
+
+fn select_from_table<F: SmallField, CS: ConstraintSystem<F>, C: EcdsaCurve<N>, const N: usize>(
+    cs: &mut CS,
+    table: &[(BaseNNField<F, C, N>, BaseNNField<F, C, N>)],
+    window_idx: &Num<F>,
+    comparison_constants: &[Num<F>],
+) -> (BaseNNField<F, C, N>, BaseNNField<F, C, N>)
+where
+    [(); N + 1]:,
+{
+    let (mut selected_part_x, mut selected_part_y) = table[0].clone();
+    for i in 1..PRECOMPUTATION_TABLE_SIZE {
+        let should_select = Num::equals(cs, &comparison_constants[i], window_idx);
+        selected_part_x =
+            Selectable::conditionally_select(cs, should_select, &table[i].0, &selected_part_x);
+        selected_part_y =
+            Selectable::conditionally_select(cs, should_select, &table[i].1, &selected_part_y);
+    }
+
+    (selected_part_x, selected_part_y)
+}
+
+fn allocate_comparison_constants<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+) -> Vec<Num<F>> {
+    let mut comparison_constants = Vec::with_capacity(PRECOMPUTATION_TABLE_SIZE);
+    for i in 1..=PRECOMPUTATION_TABLE_SIZE {
+        let constant = Num::allocated_constant(cs, F::from_u64_unchecked(i as u64));
+        comparison_constants.push(constant);
+    }
+
+    comparison_constants
+}
+
+fn plain_width_4_windowed_multiplication<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    C: EcdsaCurve<N>,
+    const N: usize,
+>(
+    cs: &mut CS,
+    point: ProjectivePoint<F, C, N>,
+    mut scalar: ScalarNNField<F, C, N>,
+    base_field_params: &Arc<BaseNNFieldParams<C, N>>,
+) -> ProjectivePoint<F, C, N>
+where
+    [(); N + 1]:,
+{
+    scalar.enforce_reduced(cs);
+
+    let table = create_precomputed_table::<F, CS, C, N>(cs, point);
+
+    // now decompose every scalar we are interested in
+    let msb_decomposition =
+        to_width_4_window_form(cs, scalar, C::SCALAR_FIELD_CANONICAL_REPR_LIMBS);
+    let num_multiplication_steps = msb_decomposition.len();
+
+    let comparison_constants = allocate_comparison_constants(cs);
+
+    // now we just do double and add
+    let mut acc = SWProjectivePoint::zero(cs, base_field_params);
+    for (idx, window_idx) in msb_decomposition.into_iter().enumerate() {
+        let ignore_part = window_idx.is_zero(cs);
+
+        let mut selected_part =
+            select_from_table::<F, CS, C, N>(cs, &table, &window_idx, &comparison_constants);
+
+        let tmp_acc = acc.add_mixed(cs, &mut selected_part);
+        acc = Selectable::conditionally_select(cs, ignore_part, &acc, &tmp_acc);
+
+        if idx != num_multiplication_steps - 1 {
+            for _ in 0..WINDOW_WIDTH {
+                acc = acc.double(cs);
+            }
+        }
+    }
+
+    acc
+}
+
+fn glv_width_4_windowed_multiplication<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    C: EcdsaCurve<N>,
+    const N: usize,
+>(
+    cs: &mut CS,
+    point: ProjectivePoint<F, C, N>,
+    mut scalar: ScalarNNField<F, C, N>,
+    glv_params: GlvParams,
+    base_field_params: &Arc<BaseNNFieldParams<C, N>>,
+    scalar_field_params: &Arc<ScalarNNFieldParams<C, N>>,
+) -> ProjectivePoint<F, C, N>
+where
+    [(); N + 1]:,
+{
+    scalar.enforce_reduced(cs);
+
+    let beta = C::Base::from_str(glv_params.beta).unwrap();
+    let mut beta = BaseNNField::<F, C, N>::allocated_constant(cs, beta, &base_field_params);
+
+    let bigint_from_hex_str = |cs: &mut CS, s: &str| -> UInt512<F> {
+        let v = U256::from_str_radix(s, 16).unwrap();
+        UInt512::allocated_constant(cs, (v, U256::zero()))
+    };
+
+    let modulus_minus_one_div_two = bigint_from_hex_str(cs, glv_params.modulus_minus_one_div_two);
+
+    let u256_from_hex_str = |cs: &mut CS, s: &str| -> UInt256<F> {
+        let v = U256::from_str_radix(s, 16).unwrap();
+        UInt256::allocated_constant(cs, v)
+    };
+
+    let a1 = u256_from_hex_str(cs, glv_params.a1);
+    let b1 = u256_from_hex_str(cs, glv_params.b1);
+    let a2 = u256_from_hex_str(cs, glv_params.a2);
+    let b2 = a1.clone();
+
+    let boolean_false = Boolean::allocated_constant(cs, false);
+
+    // Scalar decomposition
+    let (k1_was_negated, k1, k2_was_negated, k2) = {
+        let k = convert_field_element_to_uint256(cs, scalar.clone());
+
+        // We take 8 non-zero limbs for the scalar (since it could be of any size), and 4 for B2
+        // (since it fits in 128 bits).
+        let b2_times_k = k.widening_mul(cs, &b2, 8, 4);
+        // can not overflow u512
+        let (b2_times_k, of) = b2_times_k.overflowing_add(cs, &modulus_minus_one_div_two);
+        Boolean::enforce_equal(cs, &of, &boolean_false);
+        let c1 = b2_times_k.to_high();
+
+        // We take 8 non-zero limbs for the scalar (since it could be of any size), and 4 for B1
+        // (since it fits in 128 bits).
+        let b1_times_k = k.widening_mul(cs, &b1, 8, 4);
+        // can not overflow u512
+        let (b1_times_k, of) = b1_times_k.overflowing_add(cs, &modulus_minus_one_div_two);
+        Boolean::enforce_equal(cs, &of, &boolean_false);
+        let c2 = b1_times_k.to_high();
+
+        let mut a1 = convert_uint256_to_field_element(cs, &a1, &scalar_field_params);
+        let mut b1 = convert_uint256_to_field_element(cs, &b1, &scalar_field_params);
+        let mut a2 = convert_uint256_to_field_element(cs, &a2, &scalar_field_params);
+        let mut b2 = a1.clone();
+        let mut c1 = convert_uint256_to_field_element(cs, &c1, &scalar_field_params);
+        let mut c2 = convert_uint256_to_field_element(cs, &c2, &scalar_field_params);
+
+        let mut c1_times_a1 = c1.mul(cs, &mut a1);
+        let mut c2_times_a2 = c2.mul(cs, &mut a2);
+        let mut k1 = scalar.sub(cs, &mut c1_times_a1).sub(cs, &mut c2_times_a2);
+        k1.normalize(cs);
+        let mut c2_times_b2 = c2.mul(cs, &mut b2);
+        let mut k2 = c1.mul(cs, &mut b1).sub(cs, &mut c2_times_b2);
+        k2.normalize(cs);
+
+        let k1_u256 = convert_field_element_to_uint256(cs, k1.clone());
+        let k2_u256 = convert_field_element_to_uint256(cs, k2.clone());
+        let max_k1_or_k2 = UInt256::allocated_constant(cs, glv_params.max_decomposition_value);
+        // we will need k1 and k2 to be < 2^128, so we can compare via subtraction
+        let (_res, k1_out_of_range) = max_k1_or_k2.overflowing_sub(cs, &k1_u256);
+        let k1_negated = k1.negated(cs);
+        let k1 = <ScalarNNField<F, C, N> as NonNativeField<F, C::Scalar>>::conditionally_select(
+            cs,
+            k1_out_of_range,
+            &k1_negated,
+            &k1,
+        );
+        let (_res, k2_out_of_range) = max_k1_or_k2.overflowing_sub(cs, &k2_u256);
+        let k2_negated = k2.negated(cs);
+        let k2 = <ScalarNNField<F, C, N> as NonNativeField<F, C::Scalar>>::conditionally_select(
+            cs,
+            k2_out_of_range,
+            &k2_negated,
+            &k2,
+        );
+
+        (k1_out_of_range, k1, k2_out_of_range, k2)
+    };
+
+    let mut table = create_precomputed_table::<F, CS, C, N>(cs, point);
+
+    let mut endomorphisms_table = table.clone();
+    for (x, _) in endomorphisms_table.iter_mut() {
+        *x = x.mul(cs, &mut beta);
+    }
+
+    // we also know that we will multiply k1 by points, and k2 by their endomorphisms, and if they were
+    // negated above to fit into range, we negate bases here
+    for (_, y) in table.iter_mut() {
+        let negated = y.negated(cs);
+        *y = Selectable::conditionally_select(cs, k1_was_negated, &negated, &*y);
+    }
+
+    for (_, y) in endomorphisms_table.iter_mut() {
+        let negated = y.negated(cs);
+        *y = Selectable::conditionally_select(cs, k2_was_negated, &negated, &*y);
+    }
+
+    // now decompose every scalar we are interested in
+    let k1_msb_decomposition = to_glv_width_4_window_form(cs, k1);
+    let k2_msb_decomposition = to_glv_width_4_window_form(cs, k2);
+
+    let comparison_constants = allocate_comparison_constants(cs);
+
+    // now we do amortized double and add
+    let mut acc = SWProjectivePoint::zero(cs, base_field_params);
+    for (idx, (k1_window_idx, k2_window_idx)) in k1_msb_decomposition
+        .into_iter()
+        .zip(k2_msb_decomposition.into_iter())
+        .enumerate()
+    {
+        let ignore_k1_part = k1_window_idx.is_zero(cs);
+        let ignore_k2_part = k2_window_idx.is_zero(cs);
+
+        let mut selected_k1_part =
+            select_from_table::<F, CS, C, N>(cs, &table, &k1_window_idx, &comparison_constants);
+        let mut selected_k2_part = select_from_table::<F, CS, C, N>(
+            cs,
+            &endomorphisms_table,
+            &k2_window_idx,
+            &comparison_constants,
+        );
+
+        let tmp_acc = acc.add_mixed(cs, &mut selected_k1_part);
+        acc = Selectable::conditionally_select(cs, ignore_k1_part, &acc, &tmp_acc);
+        let tmp_acc = acc.add_mixed(cs, &mut selected_k2_part);
+        acc = Selectable::conditionally_select(cs, ignore_k2_part, &acc, &tmp_acc);
+
+        if idx != NUM_GLV_MULTIPLICATION_STEPS_FOR_WIDTH_4 - 1 {
+            for _ in 0..WINDOW_WIDTH {
+                acc = acc.double(cs);
+            }
+        }
+    }
+
+    acc
+}
+
+/// Computes the sum of the points multiplied by the scalars, that are given in the big-endian
+/// window form of width 4, so all the points share the same chain of doublings. Decompositions
+/// may have different lengths, and the shorter ones are aligned to the least significant window
+pub fn width_4_windowed_multi_scalar_multiplication<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    C: EcdsaCurve<N>,
+    const N: usize,
+>(
+    cs: &mut CS,
+    points: Vec<ProjectivePoint<F, C, N>>,
+    decompositions: Vec<Vec<Num<F>>>,
+    base_field_params: &Arc<BaseNNFieldParams<C, N>>,
+) -> ProjectivePoint<F, C, N>
+where
+    [(); N + 1]:,
+{
+    assert_eq!(points.len(), decompositions.len());
+    assert!(points.len() > 0);
+
+    let tables: Vec<_> = points
+        .into_iter()
+        .map(|point| create_precomputed_table::<F, CS, C, N>(cs, point))
+        .collect();
+    let num_multiplication_steps = decompositions.iter().map(|el| el.len()).max().unwrap();
+
+    let comparison_constants = allocate_comparison_constants(cs);
+
+    let mut acc = SWProjectivePoint::zero(cs, base_field_params);
+    for idx in 0..num_multiplication_steps {
+        for (table, decomposition) in tables.iter().zip(decompositions.iter()) {
+            let offset = num_multiplication_steps - decomposition.len();
+            if idx < offset {
+                continue;
+            }
+            let window_idx = decomposition[idx - offset];
+            let ignore_part = window_idx.is_zero(cs);
+
+            let mut selected_part =
+                select_from_table::<F, CS, C, N>(cs, table, &window_idx, &comparison_constants);
+
+            let tmp_acc = acc.add_mixed(cs, &mut selected_part);
+            acc = Selectable::conditionally_select(cs, ignore_part, &acc, &tmp_acc);
+        }
+
+        if idx != num_multiplication_steps - 1 {
+            for _ in 0..WINDOW_WIDTH {
+                acc = acc.double(cs);
+            }
+        }
+    }
+
+    acc
+}
+
+/// Decomposes the scalar into big-endian windows of width 4
+pub(crate) fn to_width_4_window_form<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    P: PrimeField,
+    const N: usize,
+>(
+    cs: &mut CS,
+    mut scalar: NonNativeFieldOverU16<F, P, N>,
+    canonical_limbs: usize,
+) -> Vec<Num<F>>
+where
+    [(); N + 1]:,
+{
+    scalar.enforce_reduced(cs);
+    let zero_num = Num::zero(cs);
+    for word in scalar.limbs[canonical_limbs..].iter() {
+        let word = Num::from_variable(*word);
+        Num::enforce_equal(cs, &word, &zero_num);
+    }
+
+    let byte_split_id = cs
+        .get_table_id_for_marker::<ByteSplitTable<4>>()
+        .expect("table should exist");
+    let mut result = Vec::with_capacity(canonical_limbs * 4);
+    for word in scalar.limbs[..canonical_limbs].iter().rev() {
+        let word = unsafe { UInt16::from_variable_unchecked(*word) };
+        let [high, low] = word.to_be_bytes(cs);
+        for t in [high, low].into_iter() {
+            let [l, h] = cs.perform_lookup::<1, 2>(byte_split_id, &[t.get_variable()]);
+            let h = Num::from_variable(h);
+            let l = Num::from_variable(l);
+            result.push(h);
+            result.push(l);
+        }
+    }
+    assert_eq!(result.len(), canonical_limbs * 4);
+
+    result
+}
+
+/// Decomposes the GLV half-scalar, that is known to fit into 132 bits, into big-endian windows of width 4
+fn to_glv_width_4_window_form<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    P: PrimeField,
+    const N: usize,
+>(
+    cs: &mut CS,
+    mut limited_width_scalar: NonNativeFieldOverU16<F, P, N>,
+) -> Vec<Num<F>>
+where
+    [(); N + 1]:,
+{
+    // 128 bits are in the full limbs, and the top 4 bits are in the next one
+    const FULL_LIMBS: usize = 128 / 16;
+    assert!(N > FULL_LIMBS);
+
+    limited_width_scalar.enforce_reduced(cs);
+    // we know that width is 128 bits, so just do BE decomposition and put into resulting array
+    let zero_num = Num::zero(cs);
+    for word in limited_width_scalar.limbs[(FULL_LIMBS + 1)..].iter() {
+        let word = Num::from_variable(*word);
+        Num::enforce_equal(cs, &word, &zero_num);
+    }
+
+    let byte_split_id = cs
+        .get_table_id_for_marker::<ByteSplitTable<4>>()
+        .expect("table should exist");
+    let mut result = Vec::with_capacity(NUM_GLV_MULTIPLICATION_STEPS_FOR_WIDTH_4);
+    // special case
+    {
+        let highest_word = limited_width_scalar.limbs[FULL_LIMBS];
+        let word = unsafe { UInt16::from_variable_unchecked(highest_word) };
+        let [high, low] = word.to_be_bytes(cs);
+        Num::enforce_equal(cs, &high.into_num(), &zero_num);
+        let [l, h] = cs.perform_lookup::<1, 2>(byte_split_id, &[low.get_variable()]);
+        Num::enforce_equal(cs, &Num::from_variable(h), &zero_num);
+        let l = Num::from_variable(l);
+        result.push(l);
+    }
+
+    for word in limited_width_scalar.limbs[..FULL_LIMBS].iter().rev() {
+        let word = unsafe { UInt16::from_variable_unchecked(*word) };
+        let [high, low] = word.to_be_bytes(cs);
+        for t in [high, low].into_iter() {
+            let [l, h] = cs.perform_lookup::<1, 2>(byte_split_id, &[t.get_variable()]);
+            let h = Num::from_variable(h);
+            let l = Num::from_variable(l);
+            result.push(h);
+            result.push(l);
+        }
+    }
+    assert_eq!(result.len(), NUM_GLV_MULTIPLICATION_STEPS_FOR_WIDTH_4);
+
+    result
+}
+
+/// Multiplies the generator of the curve by the scalar using the fixed base tables, or
+/// using the windowed multiplication if the curve has no such tables
+pub fn fixed_base_mul_by_generator<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    C: EcdsaCurve<N>,
+    const N: usize,
+>(
+    cs: &mut CS,
+    scalar: ScalarNNField<F, C, N>,
+    base_field_params: &Arc<BaseNNFieldParams<C, N>>,
+) -> ProjectivePoint<F, C, N>
+where
+    [(); N + 1]:,
+{
+    match C::fixed_base_table_ids(cs) {
+        Some(full_table_ids) => fixed_base_mul::<F, CS, C::Scalar, C::Base, C::Affine, N>(
+            cs,
+            scalar,
+            base_field_params,
+            C::SCALAR_FIELD_CANONICAL_REPR_LIMBS,
+            C::BASE_FIELD_CANONICAL_REPR_LIMBS,
+            &full_table_ids,
+        ),
+        None => {
+            let (gen_x, gen_y) = C::Affine::one().into_xy_unchecked();
+            let gen_x_nn = BaseNNField::<F, C, N>::allocated_constant(cs, gen_x, base_field_params);
+            let gen_y_nn = BaseNNField::<F, C, N>::allocated_constant(cs, gen_y, base_field_params);
+            let generator = SWProjectivePoint::from_xy_unchecked(cs, gen_x_nn, gen_y_nn);
+
+            plain_width_4_windowed_multiplication::<F, CS, C, N>(
+                cs,
+                generator,
+                scalar,
+                base_field_params,
+            )
+        }
+    }
+}
+
+pub(crate) fn fixed_base_mul<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    NNS: boojum::pairing::ff::PrimeField,
+    NNB: boojum::pairing::ff::PrimeField + boojum::pairing::ff::SqrtField,
+    NNC: boojum::pairing::GenericCurveAffine<Base = NNB>,
+    const N: usize,
+>(
+    cs: &mut CS,
+    mut scalar: NonNativeFieldOverU16<F, NNS, N>,
+    base_field_params: &Arc<NonNativeFieldOverU16Params<NNB, N>>,
+    scalar_canonical_limbs: usize,
+    base_canonical_limbs_canonical_limbs: usize,
+    fixed_base_table_ids: &[[u32; 8]],
+) -> SWProjectivePoint<F, NNC, NonNativeFieldOverU16<F, NNB, N>>
+where
+    [(); N + 1]:,
+{
+    assert!(base_canonical_limbs_canonical_limbs % 2 == 0);
+    assert!(scalar_canonical_limbs % 2 == 0);
+    assert_eq!(scalar_canonical_limbs * 2, fixed_base_table_ids.len());
+    assert_eq!(base_canonical_limbs_canonical_limbs / 2, 8);
+
+    scalar.enforce_reduced(cs);
+    let is_zero = scalar.is_zero(cs);
+    let bytes = scalar
+        .limbs
+        .iter()
+        .take(scalar_canonical_limbs)
+        .flat_map(|el| unsafe { UInt16::from_variable_unchecked(*el).to_le_bytes(cs) })
+        .collect::<Vec<UInt8<F>>>();
+
+    let zero_point =
+        SWProjectivePoint::<F, NNC, NonNativeFieldOverU16<F, NNB, N>>::zero(cs, base_field_params);
+    let mut acc =
+        SWProjectivePoint::<F, NNC, NonNativeFieldOverU16<F, NNB, N>>::zero(cs, base_field_params);
+
+    fixed_base_table_ids
+        .iter()
+        .copied()
+        .zip(bytes)
+        .rev()
+        .for_each(|(ids, byte)| {
+            let (x, y): (Vec<Variable>, Vec<Variable>) = ids
+                .iter()
+                .flat_map(|id| {
+                    let [x_v, y_v] = cs.perform_lookup::<1, 2>(*id, &[byte.get_variable()]);
+                    let x_v = unsafe { UInt32::from_variable_unchecked(x_v) };
+                    let y_v = unsafe { UInt32::from_variable_unchecked(y_v) };
+                    let x_v = x_v.to_le_bytes(cs);
+                    let y_v = y_v.to_le_bytes(cs);
+                    let x_1 = UInt16::from_le_bytes(cs, x_v[..2].try_into().unwrap());
+                    let x_2 = UInt16::from_le_bytes(cs, x_v[2..].try_into().unwrap());
+                    let y_1 = UInt16::from_le_bytes(cs, y_v[..2].try_into().unwrap());
+                    let y_2 = UInt16::from_le_bytes(cs, y_v[2..].try_into().unwrap());
+                    [
+                        (x_1.get_variable(), y_1.get_variable()),
+                        (x_2.get_variable(), y_2.get_variable()),
+                    ]
+                })
+                .collect::<Vec<(Variable, Variable)>>()
+                .into_iter()
+                .unzip();
+            let zero_var = cs.allocate_constant(F::ZERO);
+            let mut x_arr = [zero_var; N];
+            x_arr[..base_canonical_limbs_canonical_limbs]
+                .copy_from_slice(&x[..base_canonical_limbs_canonical_limbs]);
+            let mut y_arr = [zero_var; N];
+            y_arr[..base_canonical_limbs_canonical_limbs]
+                .copy_from_slice(&y[..base_canonical_limbs_canonical_limbs]);
+            let x = NonNativeFieldOverU16 {
+                limbs: x_arr,
+                non_zero_limbs: base_canonical_limbs_canonical_limbs,
+                tracker: OverflowTracker { max_moduluses: 1 },
+                form: RepresentationForm::Normalized,
+                params: base_field_params.clone(),
+                _marker: std::marker::PhantomData,
+            };
+            let y = NonNativeFieldOverU16 {
+                limbs: y_arr,
+                non_zero_limbs: base_canonical_limbs_canonical_limbs,
+                tracker: OverflowTracker { max_moduluses: 1 },
+                form: RepresentationForm::Normalized,
+                params: base_field_params.clone(),
+                _marker: std::marker::PhantomData,
+            };
+            let new_acc = acc.add_mixed(cs, &mut (x, y));
+            let should_not_update = byte.is_zero(cs);
+            acc = Selectable::conditionally_select(cs, should_not_update, &acc, &new_acc);
+        });
+    acc = Selectable::conditionally_select(cs, is_zero, &zero_point, &acc);
+    acc
+}
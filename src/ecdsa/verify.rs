@@ -0,0 +1,161 @@
+use super::*;
+
+use crate::ecrecover::baseline::convert_uint256_to_field_element;
+use crate::ecrecover::baseline::convert_uint256_to_field_element_masked;
+
+use arrayvec::ArrayVec;
+use boojum::gadgets::curves::sw_projective::SWProjectivePoint;
+
+use std::sync::Arc;
+
+pub const ECDSA_EXCEPTION_FLAGS_ARR_LEN: usize = 9;
+
+/// Verifies ECDSA signature (r, s) of the message hash against the uncompressed public key (x, y).
+/// Returns the success flag, that is false if any of the inputs is malformed, and the
+/// verification result as 0 or 1 in the lowest word. If `enforce_low_s` is set, then signatures
+/// with s > n/2 are treated as malformed
+pub fn ecdsa_verify_function_inner<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    C: EcdsaCurve<N>,
+    const N: usize,
+>(
+    cs: &mut CS,
+    r: &UInt256<F>,
+    s: &UInt256<F>,
+    message_hash: &UInt256<F>,
+    x: &UInt256<F>,
+    y: &UInt256<F>,
+    enforce_low_s: Boolean<F>,
+    base_field_params: &Arc<NonNativeFieldOverU16Params<C::Base, N>>,
+    scalar_field_params: &Arc<NonNativeFieldOverU16Params<C::Scalar, N>>,
+) -> (Boolean<F>, UInt256<F>)
+where
+    [(); N + 1]:,
+{
+    let curve_a = C::Affine::a_coeff();
+    let curve_b = C::Affine::b_coeff();
+
+    let mut curve_a_nn =
+        NonNativeFieldOverU16::<F, C::Base, N>::allocated_constant(cs, curve_a, &base_field_params);
+    let mut curve_b_nn =
+        NonNativeFieldOverU16::<F, C::Base, N>::allocated_constant(cs, curve_b, &base_field_params);
+
+    let generator = C::Affine::one();
+    let (gen_x, gen_y) = generator.into_xy_unchecked();
+    let gen_x_nn = NonNativeFieldOverU16::allocated_constant(cs, gen_x, base_field_params);
+    let gen_y_nn = NonNativeFieldOverU16::allocated_constant(cs, gen_y, base_field_params);
+
+    let n_u256 = modulus_as_uint256(cs, scalar_field_params);
+    let p_u256 = modulus_as_uint256(cs, base_field_params);
+
+    let mut exception_flags = ArrayVec::<_, ECDSA_EXCEPTION_FLAGS_ARR_LEN>::new();
+
+    // we use non-compressed point, so we:
+    // - check that public key is on curve (no special handling of zeroes)
+    // - check verification equation
+
+    // we check ranges upfront. We only need to check <modulus, and conversion functions will perform masking internally for values that are >= 1
+
+    let (r_as_u256, r_is_not_in_range) = range_check_and_mask(cs, r, &n_u256);
+    exception_flags.push(r_is_not_in_range);
+    let (s_as_u256, s_is_not_in_range) = range_check_and_mask(cs, s, &n_u256);
+    exception_flags.push(s_is_not_in_range);
+    let s_is_high = high_s_exception(cs, &s_as_u256, enforce_low_s, scalar_field_params);
+    exception_flags.push(s_is_high);
+    let (x_as_u256, x_is_not_in_range) = range_check_and_mask(cs, x, &p_u256);
+    exception_flags.push(x_is_not_in_range);
+    let (y_as_u256, y_is_not_in_range) = range_check_and_mask(cs, y, &p_u256);
+    exception_flags.push(y_is_not_in_range);
+
+    let mut x_fe = convert_uint256_to_field_element(cs, &x_as_u256, &base_field_params);
+    let mut y_fe = convert_uint256_to_field_element(cs, &y_as_u256, &base_field_params);
+
+    let (mut r_fe, r_is_zero) =
+        convert_uint256_to_field_element_masked(cs, &r_as_u256, &scalar_field_params);
+    exception_flags.push(r_is_zero);
+    let (mut s_fe, s_is_zero) =
+        convert_uint256_to_field_element_masked(cs, &s_as_u256, &scalar_field_params);
+    exception_flags.push(s_is_zero);
+
+    let mut message_hash_fe =
+        convert_uint256_to_field_element(cs, &message_hash, &scalar_field_params);
+
+    // perform on-curve check
+    let mut lhs = y_fe.clone();
+    let mut lhs = lhs.mul(cs, &mut y_fe);
+    lhs.normalize(cs);
+
+    let mut rhs = x_fe.clone();
+    let mut rhs = rhs.mul(cs, &mut x_fe);
+    let mut rhs = rhs.add(cs, &mut curve_a_nn);
+    let mut rhs = rhs.mul(cs, &mut x_fe);
+    let mut rhs = rhs.add(cs, &mut curve_b_nn);
+    rhs.normalize(cs);
+
+    let is_on_curve = NonNativeFieldOverU16::equals(cs, &mut lhs, &mut rhs);
+    let not_on_curve = is_on_curve.negated(cs);
+    exception_flags.push(not_on_curve);
+
+    // we can mask point to ensure that our arithmetic formulas work
+    let x_fe = NonNativeFieldOverU16::conditionally_select(cs, is_on_curve, &x_fe, &gen_x_nn);
+    let y_fe = NonNativeFieldOverU16::conditionally_select(cs, is_on_curve, &y_fe, &gen_y_nn);
+
+    // this always exists (0 was an exception and was masked)
+    let mut s_fe_inversed = s_fe.inverse_unchecked(cs);
+    let mut r_by_s_inv = r_fe.mul(cs, &mut s_fe_inversed);
+    let mut message_hash_by_s_inv = message_hash_fe.mul(cs, &mut s_fe_inversed);
+
+    r_by_s_inv.normalize(cs);
+    message_hash_by_s_inv.normalize(cs);
+
+    // now we do multiplication
+    // it's safe since we checked not-on-curve above
+    let point =
+        SWProjectivePoint::<F, C::Affine, NonNativeFieldOverU16<F, C::Base, N>>::from_xy_unchecked(
+            cs, x_fe, y_fe,
+        );
+    let mut r_by_s_inv_mul_by_pubkey = width_4_windowed_multiplication::<F, CS, C, N>(
+        cs,
+        point,
+        r_by_s_inv,
+        &base_field_params,
+        &scalar_field_params,
+    );
+
+    let mut hash_times_g =
+        fixed_base_mul_by_generator::<F, CS, C, N>(cs, message_hash_by_s_inv, &base_field_params);
+
+    let (mut q_acc, is_infinity) = hash_times_g.convert_to_affine_or_default(cs, C::Affine::one());
+    let q_acc_added = r_by_s_inv_mul_by_pubkey.add_mixed(cs, &mut q_acc);
+    let mut q_acc =
+        Selectable::conditionally_select(cs, is_infinity, &r_by_s_inv_mul_by_pubkey, &q_acc_added);
+
+    let ((mut q_x, _q_y), is_infinity) = q_acc.convert_to_affine_or_default(cs, C::Affine::one());
+    exception_flags.push(is_infinity);
+    let any_exception = Boolean::multi_or(cs, &exception_flags[..]);
+
+    q_x.normalize(cs);
+
+    // now compare mod n. For that we go out of limbs and back
+    let limbs = q_x.limbs;
+    let mut q_x_mod_n = NonNativeFieldOverU16 {
+        limbs: limbs,
+        non_zero_limbs: C::SCALAR_FIELD_CANONICAL_REPR_LIMBS,
+        tracker: OverflowTracker { max_moduluses: 2 }, // |Fr|*2 < |Fq|
+        form: RepresentationForm::Normalized,
+        params: scalar_field_params.clone(),
+        _marker: std::marker::PhantomData,
+    };
+    q_x_mod_n.normalize(cs);
+
+    let signature_equality = NonNativeFieldOverU16::equals(cs, &mut q_x_mod_n, &mut r_fe);
+    let written_value_bool = signature_equality.mask_negated(cs, any_exception);
+    let all_ok = any_exception.negated(cs);
+
+    let mut written_value = UInt256::zero(cs);
+    written_value.inner[0] =
+        unsafe { UInt32::from_variable_unchecked(written_value_bool.get_variable()) };
+
+    (all_ok, written_value)
+}
@@ -19,6 +19,15 @@ use boojum::gadgets::traits::witnessable::WitnessHookable;
 
 use cs_derive::*;
 
+use self::secp256k1::fixed_base_mul_table::FixedBaseMulTable;
+use crate::ecdsa::{EcdsaCurve, GlvParams};
+
+use crate::base_structures::precompile_input_outputs::*;
+use crate::demux_log_queue::DemuxOutput;
+use crate::precompile_registry::PrecompileCircuit;
+use crate::scheduler::auxiliary::BaseLayerCircuitType;
+use zkevm_opcode_defs::ethereum_types::H160;
+
 pub mod input;
 pub use self::input::*;
 
@@ -32,31 +41,176 @@ pub mod naf_abs_div2_table;
 pub mod baseline;
 pub mod new_optimized;
 
+/// Treatment of degenerate inputs of the recovery equation Q = (s * X - hash * G) / r.
+/// r = 0 is never recoverable and always results in failure
+#[derive(Derivative, serde::Serialize, serde::Deserialize)]
+#[derivative(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ZeroMessagePolicy {
+    /// zero message hash, zero s and the point at infinity as a result are all failures
+    Reject,
+    /// zero message hash is a valid scalar, so hash * G is the point at infinity. Zero s
+    /// and the point at infinity as a result are failures
+    #[derivative(Default)]
+    Allow,
+    /// zero message hash and zero s are valid scalars, and if the recovered key is
+    /// the point at infinity the call succeeds with zero address
+    AllowDegenerate,
+}
+
+impl ZeroMessagePolicy {
+    pub const fn allows_zero_message(&self) -> bool {
+        match self {
+            Self::Reject => false,
+            Self::Allow | Self::AllowDegenerate => true,
+        }
+    }
+
+    pub const fn allows_zero_s(&self) -> bool {
+        match self {
+            Self::Reject | Self::Allow => false,
+            Self::AllowDegenerate => true,
+        }
+    }
+
+    pub const fn allows_point_at_infinity(&self) -> bool {
+        match self {
+            Self::Reject | Self::Allow => false,
+            Self::AllowDegenerate => true,
+        }
+    }
+}
+
+/// Configuration of the ecrecover circuit. Every field changes the set of constraints, so every
+/// config has its own verification key. All of them are proven under
+/// `BaseLayerCircuitType::EcrecoverPrecompile`, and the config is chosen by the leaf layer
+/// parameters of this circuit type: the basic circuit VK commitment there must be the one
+/// of the circuit built with the deployment's config
+#[derive(Derivative, serde::Serialize, serde::Deserialize)]
+#[derivative(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EcrecoverCircuitConfig {
+    pub cycles_per_circuit: usize,
+    pub zero_message_policy: ZeroMessagePolicy,
+}
+
 // characteristics of the base field for secp curve
-use self::secp256k1::fq::Fq as Secp256Fq;
+pub(crate) use self::secp256k1::fq::Fq as Secp256Fq;
 // order of group of points for secp curve
-use self::secp256k1::fr::Fr as Secp256Fr;
+pub(crate) use self::secp256k1::fr::Fr as Secp256Fr;
 // some affine point
-use self::secp256k1::PointAffine as Secp256Affine;
+pub(crate) use self::secp256k1::PointAffine as Secp256Affine;
 
 const BASE_FIELD_REPR_LIMBS: usize = 17;
 const SCALAR_FIELD_REPR_LIMBS: usize = 17;
-const BASE_FIELD_CANONICAL_REPR_LIMBS: usize = 16;
-const SCALAR_FIELD_CANONICAL_REPR_LIMBS: usize = 16;
+pub(crate) const BASE_FIELD_CANONICAL_REPR_LIMBS: usize = 16;
+pub(crate) const SCALAR_FIELD_CANONICAL_REPR_LIMBS: usize = 16;
 
-type Secp256BaseNNFieldParams = NonNativeFieldOverU16Params<Secp256Fq, 17>;
-type Secp256ScalarNNFieldParams = NonNativeFieldOverU16Params<Secp256Fr, 17>;
+pub(crate) type Secp256BaseNNFieldParams = NonNativeFieldOverU16Params<Secp256Fq, 17>;
+pub(crate) type Secp256ScalarNNFieldParams = NonNativeFieldOverU16Params<Secp256Fr, 17>;
 
-type Secp256BaseNNField<F> = NonNativeFieldOverU16<F, Secp256Fq, 17>;
-type Secp256ScalarNNField<F> = NonNativeFieldOverU16<F, Secp256Fr, 17>;
+pub(crate) type Secp256BaseNNField<F> = NonNativeFieldOverU16<F, Secp256Fq, 17>;
+pub(crate) type Secp256ScalarNNField<F> = NonNativeFieldOverU16<F, Secp256Fr, 17>;
 
-fn secp256k1_base_field_params() -> Secp256BaseNNFieldParams {
+pub(crate) fn secp256k1_base_field_params() -> Secp256BaseNNFieldParams {
     NonNativeFieldOverU16Params::create()
 }
 
-fn secp256k1_scalar_field_params() -> Secp256ScalarNNFieldParams {
+pub(crate) fn secp256k1_scalar_field_params() -> Secp256ScalarNNFieldParams {
     NonNativeFieldOverU16Params::create()
 }
 
+// GLV consts
+
+// BETA s.t. for any curve point Q = (x,y):
+// lambda * Q = (beta*x mod p, y)
+const BETA: &'static str =
+    "55594575648329892869085402983802832744385952214688224221778511981742606582254";
+// Secp256k1.p - 1 / 2
+// 0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffc2f - 0x1 / 0x2
+const MODULUS_MINUS_ONE_DIV_TWO: &'static str =
+    "7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a0";
+// Decomposition constants
+// Derived through algorithm 3.74 http://tomlr.free.fr/Math%E9matiques/Math%20Complete/Cryptography/Guide%20to%20Elliptic%20Curve%20Cryptography%20-%20D.%20Hankerson,%20A.%20Menezes,%20S.%20Vanstone.pdf
+// NOTE: B2 == A1
+const A1: &'static str = "0x3086d221a7d46bcde86c90e49284eb15";
+const B1: &'static str = "0xe4437ed6010e88286f547fa90abfe4c3";
+const A2: &'static str = "0x114ca50f7a8e2f3f657c1108d9d44cfd8";
+// Decomposition scalars can be a little more than 2^128 in practice, so we use 33 chunks of width 4 bits
+const MAX_DECOMPOSITION_VALUE: U256 = U256([u64::MAX, u64::MAX, 0x0f, 0]);
+
+#[derive(Clone, Copy, Debug)]
+pub struct Secp256k1EcdsaCurve;
+
+impl EcdsaCurve<17> for Secp256k1EcdsaCurve {
+    type Base = Secp256Fq;
+    type Scalar = Secp256Fr;
+    type Affine = Secp256Affine;
+
+    const BASE_FIELD_CANONICAL_REPR_LIMBS: usize = BASE_FIELD_CANONICAL_REPR_LIMBS;
+    const SCALAR_FIELD_CANONICAL_REPR_LIMBS: usize = SCALAR_FIELD_CANONICAL_REPR_LIMBS;
+
+    fn base_field_params() -> Secp256BaseNNFieldParams {
+        secp256k1_base_field_params()
+    }
+
+    fn scalar_field_params() -> Secp256ScalarNNFieldParams {
+        secp256k1_scalar_field_params()
+    }
+
+    fn fixed_base_table_ids<F: SmallField, CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+    ) -> Option<Vec<[u32; 8]>> {
+        let mut full_table_ids = vec![];
+        seq_macro::seq!(C in 0..32 {
+            let ids = [
+                cs.get_table_id_for_marker::<FixedBaseMulTable<0, C>>()
+                    .expect("table must exist"),
+                cs.get_table_id_for_marker::<FixedBaseMulTable<1, C>>()
+                    .expect("table must exist"),
+                cs.get_table_id_for_marker::<FixedBaseMulTable<2, C>>()
+                    .expect("table must exist"),
+                cs.get_table_id_for_marker::<FixedBaseMulTable<3, C>>()
+                    .expect("table must exist"),
+                cs.get_table_id_for_marker::<FixedBaseMulTable<4, C>>()
+                    .expect("table must exist"),
+                cs.get_table_id_for_marker::<FixedBaseMulTable<5, C>>()
+                    .expect("table must exist"),
+                cs.get_table_id_for_marker::<FixedBaseMulTable<6, C>>()
+                    .expect("table must exist"),
+                cs.get_table_id_for_marker::<FixedBaseMulTable<7, C>>()
+                    .expect("table must exist"),
+            ];
+            full_table_ids.push(ids);
+        });
+
+        Some(full_table_ids)
+    }
+
+    fn glv_params() -> Option<GlvParams> {
+        Some(GlvParams {
+            beta: BETA,
+            modulus_minus_one_div_two: MODULUS_MINUS_ONE_DIV_TWO,
+            a1: A1,
+            b1: B1,
+            a2: A2,
+            max_decomposition_value: MAX_DECOMPOSITION_VALUE,
+        })
+    }
+}
+
+pub struct EcrecoverPrecompileCircuit;
+
+impl PrecompileCircuit for EcrecoverPrecompileCircuit {
+    const NAME: &'static str = "ecrecover";
+    const DEMUX_OUTPUT: DemuxOutput = DemuxOutput::ECRecover;
+    const CIRCUIT_TYPE: BaseLayerCircuitType = BaseLayerCircuitType::EcrecoverPrecompile;
+
+    type InputData<F: SmallField> = PrecompileFunctionInputData<F>;
+    type OutputData<F: SmallField> = PrecompileFunctionOutputData<F>;
+
+    fn formal_address() -> H160 {
+        *zkevm_opcode_defs::system_params::ECRECOVER_INNER_FUNCTION_PRECOMPILE_FORMAL_ADDRESS
+    }
+}
+
 // re-exports for integration
 pub use self::new_optimized::{ecrecover_function_entry_point, EcrecoverPrecompileCallParams};
@@ -2,8 +2,10 @@ use super::*;
 
 use crate::base_structures::precompile_input_outputs::PrecompileFunctionOutputData;
 use crate::demux_log_queue::StorageLogQueue;
-use crate::ecrecover::secp256k1::fixed_base_mul_table::FixedBaseMulTable;
-use crate::ethereum_types::U256;
+use crate::ecdsa::{
+    fixed_base_mul_by_generator, high_s_exception, modulus_as_uint256, range_check_and_mask,
+    width_4_windowed_multiplication, EcdsaPrecompileCallParams,
+};
 use crate::fsm_input_output::circuit_inputs::INPUT_OUTPUT_COMMITMENT_LENGTH;
 
 use arrayvec::ArrayVec;
@@ -11,19 +13,16 @@ use boojum::algebraic_props::round_function::AlgebraicRoundFunction;
 use boojum::crypto_bigint::{Zero, U1024};
 use boojum::cs::gates::ConstantAllocatableCS;
 use boojum::cs::traits::cs::ConstraintSystem;
-use boojum::cs::Variable;
 use boojum::field::SmallField;
 use boojum::gadgets::boolean::Boolean;
 use boojum::gadgets::curves::sw_projective::SWProjectivePoint;
 
 use boojum::gadgets::keccak256::keccak256;
 
-use boojum::gadgets::non_native_field::traits::NonNativeField;
 use boojum::gadgets::num::Num;
 use boojum::gadgets::queue::CircuitQueueWitness;
 use boojum::gadgets::queue::QueueState;
 
-use boojum::gadgets::tables::ByteSplitTable;
 use boojum::gadgets::traits::allocatable::{CSAllocatableExt, CSPlaceholder};
 use boojum::gadgets::traits::round_function::CircuitRoundFunction;
 use boojum::gadgets::traits::selectable::Selectable;
@@ -32,7 +31,6 @@ use boojum::gadgets::u16::UInt16;
 use boojum::gadgets::u160::UInt160;
 use boojum::gadgets::u256::UInt256;
 use boojum::gadgets::u32::UInt32;
-use boojum::gadgets::u512::UInt512;
 use boojum::gadgets::u8::UInt8;
 use boojum::pairing::ff::PrimeField;
 use boojum::pairing::GenericCurveAffine;
@@ -44,34 +42,8 @@ use zkevm_opcode_defs::system_params::PRECOMPILE_AUX_BYTE;
 pub use self::input::*;
 
 pub const MEMORY_QUERIES_PER_CALL: usize = 4;
-pub const ALLOW_ZERO_MESSAGE: bool = true;
-
-#[derive(Derivative, CSSelectable)]
-#[derivative(Clone, Debug)]
-pub struct EcrecoverPrecompileCallParams<F: SmallField> {
-    pub input_page: UInt32<F>,
-    pub input_offset: UInt32<F>,
-    pub output_page: UInt32<F>,
-    pub output_offset: UInt32<F>,
-}
-
-impl<F: SmallField> EcrecoverPrecompileCallParams<F> {
-    pub fn from_encoding<CS: ConstraintSystem<F>>(_cs: &mut CS, encoding: UInt256<F>) -> Self {
-        let input_offset = encoding.inner[0];
-        let output_offset = encoding.inner[2];
-        let input_page = encoding.inner[4];
-        let output_page = encoding.inner[5];
-
-        let new = Self {
-            input_page,
-            input_offset,
-            output_page,
-            output_offset,
-        };
 
-        new
-    }
-}
+pub type EcrecoverPrecompileCallParams<F> = EcdsaPrecompileCallParams<F>;
 
 const NUM_WORDS: usize = 17;
 const SECP_B_COEF: u64 = 7;
@@ -81,29 +53,6 @@ const X_POWERS_ARR_LEN: usize = 256;
 const VALID_Y_IN_EXTERNAL_FIELD: u64 = 4;
 const VALID_X_CUBED_IN_EXTERNAL_FIELD: u64 = 9;
 
-// GLV consts
-
-// Decomposition scalars can be a little more than 2^128 in practice, so we use 33 chunks of width 4 bits
-const MAX_DECOMPOSITION_VALUE: U256 = U256([u64::MAX, u64::MAX, 0x0f, 0]);
-// BETA s.t. for any curve point Q = (x,y):
-// lambda * Q = (beta*x mod p, y)
-const BETA: &'static str =
-    "55594575648329892869085402983802832744385952214688224221778511981742606582254";
-// Secp256k1.p - 1 / 2
-// 0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffc2f - 0x1 / 0x2
-const MODULUS_MINUS_ONE_DIV_TWO: &'static str =
-    "7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a0";
-// Decomposition constants
-// Derived through algorithm 3.74 http://tomlr.free.fr/Math%E9matiques/Math%20Complete/Cryptography/Guide%20to%20Elliptic%20Curve%20Cryptography%20-%20D.%20Hankerson,%20A.%20Menezes,%20S.%20Vanstone.pdf
-// NOTE: B2 == A1
-const A1: &'static str = "0x3086d221a7d46bcde86c90e49284eb15";
-const B1: &'static str = "0xe4437ed6010e88286f547fa90abfe4c3";
-const A2: &'static str = "0x114ca50f7a8e2f3f657c1108d9d44cfd8";
-
-const WINDOW_WIDTH: usize = 4;
-const NUM_MULTIPLICATION_STEPS_FOR_WIDTH_4: usize = 33;
-const PRECOMPUTATION_TABLE_SIZE: usize = (1 << WINDOW_WIDTH) - 1;
-
 // assume that constructed field element is not zero
 // if this is not satisfied - set the result to be F::one
 fn convert_uint256_to_field_element_masked<
@@ -205,7 +154,7 @@ fn convert_uint256_to_field_element<
 }
 
 // NOTE: caller must ensure that the field element is normalized, otherwise this will fail.
-fn convert_field_element_to_uint256<
+pub(crate) fn convert_field_element_to_uint256<
     F: SmallField,
     CS: ConstraintSystem<F>,
     P: boojum::pairing::ff::PrimeField,
@@ -232,371 +181,14 @@ fn convert_field_element_to_uint256<
     UInt256 { inner: limbs }
 }
 
-fn width_4_windowed_multiplication<F: SmallField, CS: ConstraintSystem<F>>(
-    cs: &mut CS,
-    mut point: SWProjectivePoint<F, Secp256Affine, Secp256BaseNNField<F>>,
-    mut scalar: Secp256ScalarNNField<F>,
-    base_field_params: &Arc<Secp256BaseNNFieldParams>,
-    scalar_field_params: &Arc<Secp256ScalarNNFieldParams>,
-) -> SWProjectivePoint<F, Secp256Affine, Secp256BaseNNField<F>> {
-    scalar.enforce_reduced(cs);
-
-    let beta = Secp256Fq::from_str(BETA).unwrap();
-    let mut beta = Secp256BaseNNField::allocated_constant(cs, beta, &base_field_params);
-
-    let bigint_from_hex_str = |cs: &mut CS, s: &str| -> UInt512<F> {
-        let v = U256::from_str_radix(s, 16).unwrap();
-        UInt512::allocated_constant(cs, (v, U256::zero()))
-    };
-
-    let modulus_minus_one_div_two = bigint_from_hex_str(cs, MODULUS_MINUS_ONE_DIV_TWO);
-
-    let u256_from_hex_str = |cs: &mut CS, s: &str| -> UInt256<F> {
-        let v = U256::from_str_radix(s, 16).unwrap();
-        UInt256::allocated_constant(cs, v)
-    };
-
-    let a1 = u256_from_hex_str(cs, A1);
-    let b1 = u256_from_hex_str(cs, B1);
-    let a2 = u256_from_hex_str(cs, A2);
-    let b2 = a1.clone();
-
-    let boolean_false = Boolean::allocated_constant(cs, false);
-
-    // Scalar decomposition
-    let (k1_was_negated, k1, k2_was_negated, k2) = {
-        let k = convert_field_element_to_uint256(cs, scalar.clone());
-
-        // We take 8 non-zero limbs for the scalar (since it could be of any size), and 4 for B2
-        // (since it fits in 128 bits).
-        let b2_times_k = k.widening_mul(cs, &b2, 8, 4);
-        // can not overflow u512
-        let (b2_times_k, of) = b2_times_k.overflowing_add(cs, &modulus_minus_one_div_two);
-        Boolean::enforce_equal(cs, &of, &boolean_false);
-        let c1 = b2_times_k.to_high();
-
-        // We take 8 non-zero limbs for the scalar (since it could be of any size), and 4 for B1
-        // (since it fits in 128 bits).
-        let b1_times_k = k.widening_mul(cs, &b1, 8, 4);
-        // can not overflow u512
-        let (b1_times_k, of) = b1_times_k.overflowing_add(cs, &modulus_minus_one_div_two);
-        Boolean::enforce_equal(cs, &of, &boolean_false);
-        let c2 = b1_times_k.to_high();
-
-        let mut a1 = convert_uint256_to_field_element(cs, &a1, &scalar_field_params);
-        let mut b1 = convert_uint256_to_field_element(cs, &b1, &scalar_field_params);
-        let mut a2 = convert_uint256_to_field_element(cs, &a2, &scalar_field_params);
-        let mut b2 = a1.clone();
-        let mut c1 = convert_uint256_to_field_element(cs, &c1, &scalar_field_params);
-        let mut c2 = convert_uint256_to_field_element(cs, &c2, &scalar_field_params);
-
-        let mut c1_times_a1 = c1.mul(cs, &mut a1);
-        let mut c2_times_a2 = c2.mul(cs, &mut a2);
-        let mut k1 = scalar.sub(cs, &mut c1_times_a1).sub(cs, &mut c2_times_a2);
-        k1.normalize(cs);
-        let mut c2_times_b2 = c2.mul(cs, &mut b2);
-        let mut k2 = c1.mul(cs, &mut b1).sub(cs, &mut c2_times_b2);
-        k2.normalize(cs);
-
-        let k1_u256 = convert_field_element_to_uint256(cs, k1.clone());
-        let k2_u256 = convert_field_element_to_uint256(cs, k2.clone());
-        let max_k1_or_k2 = UInt256::allocated_constant(cs, MAX_DECOMPOSITION_VALUE);
-        // we will need k1 and k2 to be < 2^128, so we can compare via subtraction
-        let (_res, k1_out_of_range) = max_k1_or_k2.overflowing_sub(cs, &k1_u256);
-        let k1_negated = k1.negated(cs);
-        // dbg!(k1.witness_hook(cs)());
-        // dbg!(k1_negated.witness_hook(cs)());
-        let k1 = <Secp256ScalarNNField<F> as NonNativeField<F, Secp256Fr>>::conditionally_select(
-            cs,
-            k1_out_of_range,
-            &k1_negated,
-            &k1,
-        );
-        let (_res, k2_out_of_range) = max_k1_or_k2.overflowing_sub(cs, &k2_u256);
-        let k2_negated = k2.negated(cs);
-        // dbg!(k2.witness_hook(cs)());
-        // dbg!(k2_negated.witness_hook(cs)());
-        let k2 = <Secp256ScalarNNField<F> as NonNativeField<F, Secp256Fr>>::conditionally_select(
-            cs,
-            k2_out_of_range,
-            &k2_negated,
-            &k2,
-        );
-
-        (k1_out_of_range, k1, k2_out_of_range, k2)
-    };
-
-    // dbg!(k1.witness_hook(cs)());
-    // dbg!(k2.witness_hook(cs)());
-    // dbg!(k1_was_negated.witness_hook(cs)());
-    // dbg!(k2_was_negated.witness_hook(cs)());
-
-    // create precomputed table of size 1<<4 - 1
-    // there is no 0 * P in the table, we will handle it below
-    let mut table = Vec::with_capacity(PRECOMPUTATION_TABLE_SIZE);
-    let mut tmp = point.clone();
-    let (mut p_affine, _) = point.convert_to_affine_or_default(cs, Secp256Affine::one());
-    table.push(p_affine.clone());
-    for _ in 1..PRECOMPUTATION_TABLE_SIZE {
-        // 2P, 3P, ...
-        tmp = tmp.add_mixed(cs, &mut p_affine);
-        let (affine, _) = tmp.convert_to_affine_or_default(cs, Secp256Affine::one());
-        table.push(affine);
-    }
-    assert_eq!(table.len(), PRECOMPUTATION_TABLE_SIZE);
-
-    let mut endomorphisms_table = table.clone();
-    for (x, _) in endomorphisms_table.iter_mut() {
-        *x = x.mul(cs, &mut beta);
-    }
-
-    // we also know that we will multiply k1 by points, and k2 by their endomorphisms, and if they were
-    // negated above to fit into range, we negate bases here
-    for (_, y) in table.iter_mut() {
-        let negated = y.negated(cs);
-        *y = Selectable::conditionally_select(cs, k1_was_negated, &negated, &*y);
-    }
-
-    for (_, y) in endomorphisms_table.iter_mut() {
-        let negated = y.negated(cs);
-        *y = Selectable::conditionally_select(cs, k2_was_negated, &negated, &*y);
-    }
-
-    // now decompose every scalar we are interested in
-    let k1_msb_decomposition = to_width_4_window_form(cs, k1);
-    let k2_msb_decomposition = to_width_4_window_form(cs, k2);
-
-    let mut comparison_constants = Vec::with_capacity(PRECOMPUTATION_TABLE_SIZE);
-    for i in 1..=PRECOMPUTATION_TABLE_SIZE {
-        let constant = Num::allocated_constant(cs, F::from_u64_unchecked(i as u64));
-        comparison_constants.push(constant);
-    }
-
-    // now we do amortized double and add
-    let mut acc = SWProjectivePoint::zero(cs, base_field_params);
-    assert_eq!(
-        k1_msb_decomposition.len(),
-        NUM_MULTIPLICATION_STEPS_FOR_WIDTH_4
-    );
-    assert_eq!(
-        k2_msb_decomposition.len(),
-        NUM_MULTIPLICATION_STEPS_FOR_WIDTH_4
-    );
-
-    for (idx, (k1_window_idx, k2_window_idx)) in k1_msb_decomposition
-        .into_iter()
-        .zip(k2_msb_decomposition.into_iter())
-        .enumerate()
-    {
-        let ignore_k1_part = k1_window_idx.is_zero(cs);
-        let ignore_k2_part = k2_window_idx.is_zero(cs);
-
-        // dbg!(k1_window_idx.witness_hook(cs)());
-        // dbg!(k2_window_idx.witness_hook(cs)());
-        // dbg!(ignore_k1_part.witness_hook(cs)());
-        // dbg!(ignore_k2_part.witness_hook(cs)());
-
-        let (mut selected_k1_part_x, mut selected_k1_part_y) = table[0].clone();
-        let (mut selected_k2_part_x, mut selected_k2_part_y) = endomorphisms_table[0].clone();
-        for i in 1..PRECOMPUTATION_TABLE_SIZE {
-            let should_select_k1 = Num::equals(cs, &comparison_constants[i], &k1_window_idx);
-            let should_select_k2 = Num::equals(cs, &comparison_constants[i], &k2_window_idx);
-            selected_k1_part_x = Selectable::conditionally_select(
-                cs,
-                should_select_k1,
-                &table[i].0,
-                &selected_k1_part_x,
-            );
-            selected_k1_part_y = Selectable::conditionally_select(
-                cs,
-                should_select_k1,
-                &table[i].1,
-                &selected_k1_part_y,
-            );
-            selected_k2_part_x = Selectable::conditionally_select(
-                cs,
-                should_select_k2,
-                &endomorphisms_table[i].0,
-                &selected_k2_part_x,
-            );
-            selected_k2_part_y = Selectable::conditionally_select(
-                cs,
-                should_select_k2,
-                &endomorphisms_table[i].1,
-                &selected_k2_part_y,
-            );
-        }
-
-        // dbg!(selected_k1_part_x.witness_hook(cs)());
-        // dbg!(selected_k1_part_y.witness_hook(cs)());
-
-        let tmp_acc = acc.add_mixed(cs, &mut (selected_k1_part_x, selected_k1_part_y));
-        acc = Selectable::conditionally_select(cs, ignore_k1_part, &acc, &tmp_acc);
-        let tmp_acc = acc.add_mixed(cs, &mut (selected_k2_part_x, selected_k2_part_y));
-        acc = Selectable::conditionally_select(cs, ignore_k2_part, &acc, &tmp_acc);
-
-        // let ((x, y), _) = acc.convert_to_affine_or_default(cs, Secp256Affine::zero());
-        // dbg!(x.witness_hook(cs)());
-        // dbg!(y.witness_hook(cs)());
-
-        if idx != NUM_MULTIPLICATION_STEPS_FOR_WIDTH_4 - 1 {
-            for _ in 0..WINDOW_WIDTH {
-                acc = acc.double(cs);
-            }
-        }
-    }
-
-    acc
-}
-
-fn to_width_4_window_form<F: SmallField, CS: ConstraintSystem<F>>(
-    cs: &mut CS,
-    mut limited_width_scalar: Secp256ScalarNNField<F>,
-) -> Vec<Num<F>> {
-    limited_width_scalar.enforce_reduced(cs);
-    // we know that width is 128 bits, so just do BE decomposition and put into resulting array
-    let zero_num = Num::zero(cs);
-    for word in limited_width_scalar.limbs[9..].iter() {
-        let word = Num::from_variable(*word);
-        Num::enforce_equal(cs, &word, &zero_num);
-    }
-
-    let byte_split_id = cs
-        .get_table_id_for_marker::<ByteSplitTable<4>>()
-        .expect("table should exist");
-    let mut result = Vec::with_capacity(32);
-    // special case
-    {
-        let highest_word = limited_width_scalar.limbs[8];
-        let word = unsafe { UInt16::from_variable_unchecked(highest_word) };
-        let [high, low] = word.to_be_bytes(cs);
-        Num::enforce_equal(cs, &high.into_num(), &zero_num);
-        let [l, h] = cs.perform_lookup::<1, 2>(byte_split_id, &[low.get_variable()]);
-        Num::enforce_equal(cs, &Num::from_variable(h), &zero_num);
-        let l = Num::from_variable(l);
-        result.push(l);
-    }
-
-    for word in limited_width_scalar.limbs[..8].iter().rev() {
-        let word = unsafe { UInt16::from_variable_unchecked(*word) };
-        let [high, low] = word.to_be_bytes(cs);
-        for t in [high, low].into_iter() {
-            let [l, h] = cs.perform_lookup::<1, 2>(byte_split_id, &[t.get_variable()]);
-            let h = Num::from_variable(h);
-            let l = Num::from_variable(l);
-            result.push(h);
-            result.push(l);
-        }
-    }
-    assert_eq!(result.len(), NUM_MULTIPLICATION_STEPS_FOR_WIDTH_4);
-
-    result
-}
-
-pub(crate) fn fixed_base_mul<
-    F: SmallField,
-    CS: ConstraintSystem<F>,
-    NNS: boojum::pairing::ff::PrimeField,
-    NNB: boojum::pairing::ff::PrimeField + boojum::pairing::ff::SqrtField,
-    NNC: boojum::pairing::GenericCurveAffine<Base = NNB>,
-    const N: usize,
->(
-    cs: &mut CS,
-    mut scalar: NonNativeFieldOverU16<F, NNS, N>,
-    base_field_params: &Arc<NonNativeFieldOverU16Params<NNB, N>>,
-    scalar_canonical_limbs: usize,
-    base_canonical_limbs_canonical_limbs: usize,
-    fixed_base_table_ids: &[[u32; 8]],
-) -> SWProjectivePoint<F, NNC, NonNativeFieldOverU16<F, NNB, N>>
-where
-    [(); N + 1]:,
-{
-    assert!(base_canonical_limbs_canonical_limbs % 2 == 0);
-    assert!(scalar_canonical_limbs % 2 == 0);
-    assert_eq!(scalar_canonical_limbs * 2, fixed_base_table_ids.len());
-    assert_eq!(base_canonical_limbs_canonical_limbs / 2, 8);
-
-    scalar.enforce_reduced(cs);
-    let is_zero = scalar.is_zero(cs);
-    let bytes = scalar
-        .limbs
-        .iter()
-        .take(scalar_canonical_limbs)
-        .flat_map(|el| unsafe { UInt16::from_variable_unchecked(*el).to_le_bytes(cs) })
-        .collect::<Vec<UInt8<F>>>();
-
-    let zero_point =
-        SWProjectivePoint::<F, NNC, NonNativeFieldOverU16<F, NNB, N>>::zero(cs, base_field_params);
-    let mut acc =
-        SWProjectivePoint::<F, NNC, NonNativeFieldOverU16<F, NNB, N>>::zero(cs, base_field_params);
-
-    fixed_base_table_ids
-        .iter()
-        .copied()
-        .zip(bytes)
-        .rev()
-        .for_each(|(ids, byte)| {
-            let (x, y): (Vec<Variable>, Vec<Variable>) = ids
-                .iter()
-                .flat_map(|id| {
-                    let [x_v, y_v] = cs.perform_lookup::<1, 2>(*id, &[byte.get_variable()]);
-                    let x_v = unsafe { UInt32::from_variable_unchecked(x_v) };
-                    let y_v = unsafe { UInt32::from_variable_unchecked(y_v) };
-                    let x_v = x_v.to_le_bytes(cs);
-                    let y_v = y_v.to_le_bytes(cs);
-                    let x_1 = UInt16::from_le_bytes(cs, x_v[..2].try_into().unwrap());
-                    let x_2 = UInt16::from_le_bytes(cs, x_v[2..].try_into().unwrap());
-                    let y_1 = UInt16::from_le_bytes(cs, y_v[..2].try_into().unwrap());
-                    let y_2 = UInt16::from_le_bytes(cs, y_v[2..].try_into().unwrap());
-                    [
-                        (x_1.get_variable(), y_1.get_variable()),
-                        (x_2.get_variable(), y_2.get_variable()),
-                    ]
-                })
-                .collect::<Vec<(Variable, Variable)>>()
-                .into_iter()
-                .unzip();
-            let zero_var = cs.allocate_constant(F::ZERO);
-            let mut x_arr = [zero_var; N];
-            x_arr[..base_canonical_limbs_canonical_limbs]
-                .copy_from_slice(&x[..base_canonical_limbs_canonical_limbs]);
-            let mut y_arr = [zero_var; N];
-            y_arr[..base_canonical_limbs_canonical_limbs]
-                .copy_from_slice(&y[..base_canonical_limbs_canonical_limbs]);
-            let x = NonNativeFieldOverU16 {
-                limbs: x_arr,
-                non_zero_limbs: base_canonical_limbs_canonical_limbs,
-                tracker: OverflowTracker { max_moduluses: 1 },
-                form: RepresentationForm::Normalized,
-                params: base_field_params.clone(),
-                _marker: std::marker::PhantomData,
-            };
-            let y = NonNativeFieldOverU16 {
-                limbs: y_arr,
-                non_zero_limbs: base_canonical_limbs_canonical_limbs,
-                tracker: OverflowTracker { max_moduluses: 1 },
-                form: RepresentationForm::Normalized,
-                params: base_field_params.clone(),
-                _marker: std::marker::PhantomData,
-            };
-            let new_acc = acc.add_mixed(cs, &mut (x, y));
-            let should_not_update = byte.is_zero(cs);
-            acc = Selectable::conditionally_select(cs, should_not_update, &acc, &new_acc);
-        });
-    acc = Selectable::conditionally_select(cs, is_zero, &zero_point, &acc);
-    acc
-}
-
-fn ecrecover_precompile_inner_routine<
-    F: SmallField,
-    CS: ConstraintSystem<F>,
-    const MESSAGE_HASH_CAN_BE_ZERO: bool,
->(
+fn ecrecover_precompile_inner_routine<F: SmallField, CS: ConstraintSystem<F>>(
     cs: &mut CS,
     recid: &UInt8<F>,
     r: &UInt256<F>,
     s: &UInt256<F>,
     message_hash: &UInt256<F>,
+    enforce_low_s: Boolean<F>,
+    zero_message_policy: ZeroMessagePolicy,
     valid_x_in_external_field: Secp256BaseNNField<F>,
     valid_y_in_external_field: Secp256BaseNNField<F>,
     valid_t_in_external_field: Secp256BaseNNField<F>,
@@ -614,21 +206,8 @@ fn ecrecover_precompile_inner_routine<
     let mut minus_one_nn =
         Secp256BaseNNField::<F>::allocated_constant(cs, minus_one, &base_field_params);
 
-    let secp_n_u256 = U256([
-        scalar_field_params.modulus_u1024.as_ref().as_words()[0],
-        scalar_field_params.modulus_u1024.as_ref().as_words()[1],
-        scalar_field_params.modulus_u1024.as_ref().as_words()[2],
-        scalar_field_params.modulus_u1024.as_ref().as_words()[3],
-    ]);
-    let secp_n_u256 = UInt256::allocated_constant(cs, secp_n_u256);
-
-    let secp_p_u256 = U256([
-        base_field_params.modulus_u1024.as_ref().as_words()[0],
-        base_field_params.modulus_u1024.as_ref().as_words()[1],
-        base_field_params.modulus_u1024.as_ref().as_words()[2],
-        base_field_params.modulus_u1024.as_ref().as_words()[3],
-    ]);
-    let secp_p_u256 = UInt256::allocated_constant(cs, secp_p_u256);
+    let secp_n_u256 = modulus_as_uint256(cs, scalar_field_params);
+    let secp_p_u256 = modulus_as_uint256(cs, base_field_params);
 
     let mut exception_flags = ArrayVec::<_, EXCEPTION_FLAGS_ARR_LEN>::new();
 
@@ -645,16 +224,14 @@ fn ecrecover_precompile_inner_routine<
         Num::<F>::from_variable(recid.get_variable()).spread_into_bits::<_, 8>(cs);
 
     let (r_plus_n, of) = r.overflowing_add(cs, &secp_n_u256);
-    let mut x_as_u256 = UInt256::conditionally_select(cs, x_overflow, &r_plus_n, &r);
+    let x_as_u256 = UInt256::conditionally_select(cs, x_overflow, &r_plus_n, &r);
     let error = Boolean::multi_and(cs, &[x_overflow, of]);
     exception_flags.push(error);
 
     // we handle x separately as it is the only element of base field of a curve (not a scalar field element!)
     // check that x < q - order of base point on Secp256 curve
     // if it is not actually the case - mask x to be zero
-    let (_res, is_in_range) = x_as_u256.overflowing_sub(cs, &secp_p_u256);
-    x_as_u256 = x_as_u256.mask(cs, is_in_range);
-    let x_is_not_in_range = is_in_range.negated(cs);
+    let (x_as_u256, x_is_not_in_range) = range_check_and_mask(cs, &x_as_u256, &secp_p_u256);
     exception_flags.push(x_is_not_in_range);
 
     let mut x_fe = convert_uint256_to_field_element(cs, &x_as_u256, &base_field_params);
@@ -662,11 +239,17 @@ fn ecrecover_precompile_inner_routine<
     let (mut r_fe, r_is_zero) =
         convert_uint256_to_field_element_masked(cs, &r, &scalar_field_params);
     exception_flags.push(r_is_zero);
+    // zero s is masked to one for the multiplication, and s * X is replaced by
+    // the point at infinity later if the policy allows it
     let (mut s_fe, s_is_zero) =
         convert_uint256_to_field_element_masked(cs, &s, &scalar_field_params);
-    exception_flags.push(s_is_zero);
+    if zero_message_policy.allows_zero_s() == false {
+        exception_flags.push(s_is_zero);
+    }
+    let s_is_high = high_s_exception(cs, &s, enforce_low_s, scalar_field_params);
+    exception_flags.push(s_is_high);
 
-    let (mut message_hash_fe, message_hash_is_zero) = if MESSAGE_HASH_CAN_BE_ZERO {
+    let (mut message_hash_fe, message_hash_is_zero) = if zero_message_policy.allows_zero_message() {
         (
             convert_uint256_to_field_element(cs, &message_hash, scalar_field_params),
             Boolean::allocated_constant(cs, false),
@@ -781,7 +364,7 @@ fn ecrecover_precompile_inner_routine<
         SWProjectivePoint::<F, Secp256Affine, Secp256BaseNNField<F>>::from_xy_unchecked(cs, x, y);
 
     // now we do multiplication
-    let mut s_times_x = width_4_windowed_multiplication(
+    let mut s_times_x = width_4_windowed_multiplication::<F, CS, Secp256k1EcdsaCurve, 17>(
         cs,
         recovered_point.clone(),
         s_by_r_inv.clone(),
@@ -789,45 +372,25 @@ fn ecrecover_precompile_inner_routine<
         &scalar_field_params,
     );
 
-    let mut full_table_ids = vec![];
-    seq_macro::seq!(C in 0..32 {
-        let ids = [
-            cs.get_table_id_for_marker::<FixedBaseMulTable<0, C>>()
-                .expect("table must exist"),
-            cs.get_table_id_for_marker::<FixedBaseMulTable<1, C>>()
-                .expect("table must exist"),
-            cs.get_table_id_for_marker::<FixedBaseMulTable<2, C>>()
-                .expect("table must exist"),
-            cs.get_table_id_for_marker::<FixedBaseMulTable<3, C>>()
-                .expect("table must exist"),
-            cs.get_table_id_for_marker::<FixedBaseMulTable<4, C>>()
-                .expect("table must exist"),
-            cs.get_table_id_for_marker::<FixedBaseMulTable<5, C>>()
-                .expect("table must exist"),
-            cs.get_table_id_for_marker::<FixedBaseMulTable<6, C>>()
-                .expect("table must exist"),
-            cs.get_table_id_for_marker::<FixedBaseMulTable<7, C>>()
-                .expect("table must exist"),
-        ];
-        full_table_ids.push(ids);
-    });
-
-    let mut hash_times_g = fixed_base_mul::<F, CS, Secp256Fr, Secp256Fq, Secp256Affine, 17>(
+    let mut hash_times_g = fixed_base_mul_by_generator::<F, CS, Secp256k1EcdsaCurve, 17>(
         cs,
         message_hash_by_r_inv_negated,
         &base_field_params,
-        SCALAR_FIELD_CANONICAL_REPR_LIMBS,
-        BASE_FIELD_CANONICAL_REPR_LIMBS,
-        &full_table_ids,
     );
 
     let (mut q_acc, is_infinity) =
         hash_times_g.convert_to_affine_or_default(cs, Secp256Affine::one());
     let q_acc_added = s_times_x.add_mixed(cs, &mut q_acc);
     let mut q_acc = Selectable::conditionally_select(cs, is_infinity, &s_times_x, &q_acc_added);
+    if zero_message_policy.allows_zero_s() {
+        // s * X is the point at infinity, so Q = -hash * G / r
+        q_acc = Selectable::conditionally_select(cs, s_is_zero, &hash_times_g, &q_acc);
+    }
 
     let ((q_x, q_y), is_infinity) = q_acc.convert_to_affine_or_default(cs, Secp256Affine::one());
-    exception_flags.push(is_infinity);
+    if zero_message_policy.allows_point_at_infinity() == false {
+        exception_flags.push(is_infinity);
+    }
     let any_exception = Boolean::multi_or(cs, &exception_flags[..]);
 
     let zero_u8 = UInt8::zero(cs);
@@ -855,6 +418,12 @@ fn ecrecover_precompile_inner_routine<
     let written_value_unmasked = UInt256::from_le_bytes(cs, digest_bytes);
 
     let written_value = written_value_unmasked.mask_negated(cs, any_exception);
+    // there is no address for the point at infinity, so if it's a valid result we write zero
+    let written_value = if zero_message_policy.allows_point_at_infinity() {
+        written_value.mask_negated(cs, is_infinity)
+    } else {
+        written_value
+    };
     let all_ok = any_exception.negated(cs);
 
     (all_ok, written_value)
@@ -868,7 +437,7 @@ pub fn ecrecover_function_entry_point<
     cs: &mut CS,
     witness: EcrecoverCircuitInstanceWitness<F>,
     round_function: &R,
-    limit: usize,
+    config: EcrecoverCircuitConfig,
 ) -> [Num<F>; INPUT_OUTPUT_COMMITMENT_LENGTH]
 where
     [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
@@ -876,6 +445,10 @@ where
     [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
     [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
 {
+    let EcrecoverCircuitConfig {
+        cycles_per_circuit: limit,
+        zero_message_policy,
+    } = config;
     assert!(limit <= u32::MAX as usize);
 
     let EcrecoverCircuitInstanceWitness {
@@ -1028,12 +601,14 @@ where
             }
         }
 
-        let (success, written_value) = ecrecover_precompile_inner_routine::<_, _, ALLOW_ZERO_MESSAGE>(
+        let (success, written_value) = ecrecover_precompile_inner_routine(
             cs,
             &rec_id,
             &r_as_u256,
             &s_as_u256,
             &message_hash_as_u256,
+            precompile_call_params.enforce_low_s,
+            zero_message_policy,
             valid_x_in_external_field.clone(),
             valid_y_in_external_field.clone(),
             valid_t_in_external_field.clone(),
@@ -1362,39 +937,14 @@ mod test {
         let mut seed = Secp256Fr::multiplicative_generator();
         seed = seed.pow([1234]);
 
-        let mut full_table_ids = vec![];
-        seq_macro::seq!(C in 0..32 {
-            let ids = [
-                cs.get_table_id_for_marker::<FixedBaseMulTable<0, C>>()
-                    .expect("table must exist"),
-                cs.get_table_id_for_marker::<FixedBaseMulTable<1, C>>()
-                    .expect("table must exist"),
-                cs.get_table_id_for_marker::<FixedBaseMulTable<2, C>>()
-                    .expect("table must exist"),
-                cs.get_table_id_for_marker::<FixedBaseMulTable<3, C>>()
-                    .expect("table must exist"),
-                cs.get_table_id_for_marker::<FixedBaseMulTable<4, C>>()
-                    .expect("table must exist"),
-                cs.get_table_id_for_marker::<FixedBaseMulTable<5, C>>()
-                    .expect("table must exist"),
-                cs.get_table_id_for_marker::<FixedBaseMulTable<6, C>>()
-                    .expect("table must exist"),
-                cs.get_table_id_for_marker::<FixedBaseMulTable<7, C>>()
-                    .expect("table must exist"),
-            ];
-            full_table_ids.push(ids);
-        });
-
         for _i in 0..16 {
             let scalar = Secp256ScalarNNField::allocate_checked(cs, seed, &scalar_params);
-            let mut result = fixed_base_mul::<GoldilocksField, _, _, _, _, 17>(
-                cs,
-                scalar,
-                &base_params,
-                16,
-                16,
-                &full_table_ids,
-            );
+            let mut result = fixed_base_mul_by_generator::<
+                GoldilocksField,
+                _,
+                Secp256k1EcdsaCurve,
+                17,
+            >(cs, scalar, &base_params);
             let ((result_x, result_y), _) =
                 result.convert_to_affine_or_default(cs, Secp256Affine::one());
 
@@ -1442,8 +992,13 @@ mod test {
             let y = Secp256BaseNNField::allocate_checked(cs, *base.as_xy().1, &base_params);
             let point = SWProjectivePoint::from_xy_unchecked(cs, x, y);
 
-            let mut result =
-                width_4_windowed_multiplication(cs, point, scalar, &base_params, &scalar_params);
+            let mut result = width_4_windowed_multiplication::<_, _, Secp256k1EcdsaCurve, 17>(
+                cs,
+                point,
+                scalar,
+                &base_params,
+                &scalar_params,
+            );
             let ((result_x, result_y), _) =
                 result.convert_to_affine_or_default(cs, Secp256Affine::one());
 
@@ -1490,6 +1045,7 @@ mod test {
         let scalar_params = Arc::new(scalar_params);
         let base_params = Arc::new(base_params);
 
+        let boolean_false = Boolean::allocated_constant(cs, false);
         let valid_x_in_external_field = Secp256BaseNNField::allocated_constant(
             cs,
             Secp256Fq::from_str("9").unwrap(),
@@ -1507,12 +1063,14 @@ mod test {
         );
 
         for _ in 0..5 {
-            let (no_error, digest) = ecrecover_precompile_inner_routine::<_, _, true>(
+            let (no_error, digest) = ecrecover_precompile_inner_routine(
                 cs,
                 &rec_id,
                 &r,
                 &s,
                 &digest,
+                boolean_false,
+                ZeroMessagePolicy::Allow,
                 valid_x_in_external_field.clone(),
                 valid_y_in_external_field.clone(),
                 valid_t_in_external_field.clone(),
@@ -1536,6 +1094,109 @@ mod test {
         assert!(cs.check_if_satisfied(&worker));
     }
 
+    #[test]
+    fn test_signature_in_low_s_mode() {
+        let mut owned_cs = create_cs(1 << 20);
+        let cs = &mut owned_cs;
+
+        let sk = crate::ff::from_hex::<Secp256Fr>(
+            "b5b1870957d373ef0eeffecc6e4812c0fd08f554b37b233526acc331bf1544f7",
+        )
+        .unwrap();
+        let eth_address = hex::decode("12890d2cce102216644c59dae5baed380d84830c").unwrap();
+        let (r, s, _pk, digest) = simulate_signature_for_sk(sk);
+
+        // (r, -s) is a valid signature for the negated nonce point, so it has the opposite recid
+        let mut s_negated = s;
+        s_negated.negate();
+
+        let n = U256::from_str_radix(
+            "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141",
+            16,
+        )
+        .unwrap();
+        let half_n = n >> 1;
+
+        let s_u256 = repr_into_u256(s.into_repr());
+        let s_negated_u256 = repr_into_u256(s_negated.into_repr());
+        let (low_s_u256, high_s_u256, low_s_rec_id) = if s_u256 <= half_n {
+            (s_u256, s_negated_u256, 0u8)
+        } else {
+            (s_negated_u256, s_u256, 1u8)
+        };
+
+        let scalar_params = Arc::new(secp256k1_scalar_field_params());
+        let base_params = Arc::new(secp256k1_base_field_params());
+
+        let r = UInt256::allocate(cs, repr_into_u256(r.into_repr()));
+        let digest = UInt256::allocate(cs, repr_into_u256(digest.into_repr()));
+        let low_s = UInt256::allocate(cs, low_s_u256);
+        let high_s = UInt256::allocate(cs, high_s_u256);
+        let high_s_rec_id = UInt8::allocate_checked(cs, 1 - low_s_rec_id);
+        let low_s_rec_id = UInt8::allocate_checked(cs, low_s_rec_id);
+
+        let boolean_true = Boolean::allocated_constant(cs, true);
+        let valid_x_in_external_field = Secp256BaseNNField::allocated_constant(
+            cs,
+            Secp256Fq::from_str("9").unwrap(),
+            &base_params,
+        );
+        let valid_t_in_external_field = Secp256BaseNNField::allocated_constant(
+            cs,
+            Secp256Fq::from_str("16").unwrap(),
+            &base_params,
+        );
+        let valid_y_in_external_field = Secp256BaseNNField::allocated_constant(
+            cs,
+            Secp256Fq::from_str("4").unwrap(),
+            &base_params,
+        );
+
+        let (no_error, digest_for_low_s) = ecrecover_precompile_inner_routine(
+            cs,
+            &low_s_rec_id,
+            &r,
+            &low_s,
+            &digest,
+            boolean_true,
+            ZeroMessagePolicy::Allow,
+            valid_x_in_external_field.clone(),
+            valid_y_in_external_field.clone(),
+            valid_t_in_external_field.clone(),
+            &base_params,
+            &scalar_params,
+        );
+
+        assert!(no_error.witness_hook(&*cs)().unwrap() == true);
+        let recovered_address = digest_for_low_s.to_be_bytes(cs);
+        let recovered_address = recovered_address.witness_hook(cs)().unwrap();
+        assert_eq!(&recovered_address[12..], &eth_address[..]);
+
+        let (no_error, digest_for_high_s) = ecrecover_precompile_inner_routine(
+            cs,
+            &high_s_rec_id,
+            &r,
+            &high_s,
+            &digest,
+            boolean_true,
+            ZeroMessagePolicy::Allow,
+            valid_x_in_external_field.clone(),
+            valid_y_in_external_field.clone(),
+            valid_t_in_external_field.clone(),
+            &base_params,
+            &scalar_params,
+        );
+
+        assert!(no_error.witness_hook(&*cs)().unwrap() == false);
+        assert!(digest_for_high_s.witness_hook(&*cs)().unwrap() == U256::zero());
+
+        cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        let worker = Worker::new();
+        assert!(cs.check_if_satisfied(&worker));
+    }
+
     #[test]
     fn test_signature_from_reference_vector() {
         let mut owned_cs = create_cs(1 << 20);
@@ -1566,6 +1227,7 @@ mod test {
         let scalar_params = Arc::new(scalar_params);
         let base_params = Arc::new(base_params);
 
+        let boolean_false = Boolean::allocated_constant(cs, false);
         let valid_x_in_external_field = Secp256BaseNNField::allocated_constant(
             cs,
             Secp256Fq::from_str("9").unwrap(),
@@ -1583,12 +1245,14 @@ mod test {
         );
 
         for _ in 0..1 {
-            let (no_error, digest) = ecrecover_precompile_inner_routine::<_, _, true>(
+            let (no_error, digest) = ecrecover_precompile_inner_routine(
                 cs,
                 &rec_id,
                 &r,
                 &s,
                 &digest,
+                boolean_false,
+                ZeroMessagePolicy::Allow,
                 valid_x_in_external_field.clone(),
                 valid_y_in_external_field.clone(),
                 valid_t_in_external_field.clone(),
@@ -1642,6 +1306,7 @@ mod test {
         let scalar_params = Arc::new(scalar_params);
         let base_params = Arc::new(base_params);
 
+        let boolean_false = Boolean::allocated_constant(cs, false);
         let valid_x_in_external_field = Secp256BaseNNField::allocated_constant(
             cs,
             Secp256Fq::from_str("9").unwrap(),
@@ -1659,12 +1324,14 @@ mod test {
         );
 
         for _ in 0..1 {
-            let (no_error, digest) = ecrecover_precompile_inner_routine::<_, _, true>(
+            let (no_error, digest) = ecrecover_precompile_inner_routine(
                 cs,
                 &rec_id,
                 &r,
                 &s,
                 &digest,
+                boolean_false,
+                ZeroMessagePolicy::Allow,
                 valid_x_in_external_field.clone(),
                 valid_y_in_external_field.clone(),
                 valid_t_in_external_field.clone(),
@@ -1730,6 +1397,7 @@ mod test {
         let scalar_params = Arc::new(scalar_params);
         let base_params = Arc::new(base_params);
 
+        let boolean_false = Boolean::allocated_constant(cs, false);
         let valid_x_in_external_field = Secp256BaseNNField::allocated_constant(
             cs,
             Secp256Fq::from_str("9").unwrap(),
@@ -1769,12 +1437,14 @@ mod test {
         }
 
         for (r, s, digest) in all_combinations.into_iter() {
-            let (no_error, _digest) = ecrecover_precompile_inner_routine::<_, _, false>(
+            let (no_error, _digest) = ecrecover_precompile_inner_routine(
                 cs,
                 &rec_id,
                 &r,
                 &s,
                 &digest,
+                boolean_false,
+                ZeroMessagePolicy::Reject,
                 valid_x_in_external_field.clone(),
                 valid_y_in_external_field.clone(),
                 valid_t_in_external_field.clone(),
@@ -1830,6 +1500,7 @@ mod test {
         let scalar_params = Arc::new(scalar_params);
         let base_params = Arc::new(base_params);
 
+        let boolean_false = Boolean::allocated_constant(cs, false);
         let valid_x_in_external_field = Secp256BaseNNField::allocated_constant(
             cs,
             Secp256Fq::from_str("9").unwrap(),
@@ -1847,12 +1518,14 @@ mod test {
         );
 
         for _ in 0..5 {
-            let (no_error, digest) = ecrecover_precompile_inner_routine::<_, _, true>(
+            let (no_error, digest) = ecrecover_precompile_inner_routine(
                 cs,
                 &rec_id,
                 &r,
                 &s,
                 &digest,
+                boolean_false,
+                ZeroMessagePolicy::Allow,
                 valid_x_in_external_field.clone(),
                 valid_y_in_external_field.clone(),
                 valid_t_in_external_field.clone(),
@@ -1876,4 +1549,223 @@ mod test {
         let worker = Worker::new();
         assert!(cs.check_if_satisfied(&worker));
     }
+
+    fn recover_with_policy<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        zero_message_policy: ZeroMessagePolicy,
+        rec_id: u8,
+        r: U256,
+        s: U256,
+        digest: U256,
+    ) -> (bool, U256) {
+        let scalar_params = Arc::new(secp256k1_scalar_field_params());
+        let base_params = Arc::new(secp256k1_base_field_params());
+
+        let rec_id = UInt8::allocate_checked(cs, rec_id);
+        let r = UInt256::allocate(cs, r);
+        let s = UInt256::allocate(cs, s);
+        let digest = UInt256::allocate(cs, digest);
+
+        let boolean_false = Boolean::allocated_constant(cs, false);
+        let valid_x_in_external_field = Secp256BaseNNField::allocated_constant(
+            cs,
+            Secp256Fq::from_str("9").unwrap(),
+            &base_params,
+        );
+        let valid_t_in_external_field = Secp256BaseNNField::allocated_constant(
+            cs,
+            Secp256Fq::from_str("16").unwrap(),
+            &base_params,
+        );
+        let valid_y_in_external_field = Secp256BaseNNField::allocated_constant(
+            cs,
+            Secp256Fq::from_str("4").unwrap(),
+            &base_params,
+        );
+
+        let (no_error, written_value) = ecrecover_precompile_inner_routine(
+            cs,
+            &rec_id,
+            &r,
+            &s,
+            &digest,
+            boolean_false,
+            zero_message_policy,
+            valid_x_in_external_field,
+            valid_y_in_external_field,
+            valid_t_in_external_field,
+            &base_params,
+            &scalar_params,
+        );
+
+        (
+            no_error.witness_hook(&*cs)().unwrap(),
+            written_value.witness_hook(&*cs)().unwrap(),
+        )
+    }
+
+    // signature with zero digest, and the address of the key Q = -(hash / r) * G that
+    // we get from it if s is also zero
+    fn degenerate_inputs() -> (U256, U256, U256) {
+        let sk = crate::ff::from_hex::<Secp256Fr>(
+            "b5b1870957d373ef0eeffecc6e4812c0fd08f554b37b233526acc331bf1544f7",
+        )
+        .unwrap();
+        let (r, _s, _pk, digest) = simulate_signature_for_sk(sk);
+
+        let mut degenerate_sk = digest;
+        degenerate_sk.mul_assign(&r.inverse().unwrap());
+        degenerate_sk.negate();
+
+        (
+            repr_into_u256(r.into_repr()),
+            repr_into_u256(digest.into_repr()),
+            repr_into_u256(degenerate_sk.into_repr()),
+        )
+    }
+
+    #[test]
+    fn test_zero_message_policy_reject() {
+        let mut owned_cs = create_cs(1 << 21);
+        let cs = &mut owned_cs;
+
+        let (r, digest, _) = degenerate_inputs();
+        let sk = crate::ff::from_hex::<Secp256Fr>(
+            "b5b1870957d373ef0eeffecc6e4812c0fd08f554b37b233526acc331bf1544f7",
+        )
+        .unwrap();
+        let (_, s, _, _) = simulate_signature_for_sk(sk);
+        let s = repr_into_u256(s.into_repr());
+
+        // zero message
+        let (no_error, written_value) =
+            recover_with_policy(cs, ZeroMessagePolicy::Reject, 0, r, s, U256::zero());
+        assert!(no_error == false);
+        assert_eq!(written_value, U256::zero());
+
+        // zero s
+        let (no_error, written_value) =
+            recover_with_policy(cs, ZeroMessagePolicy::Reject, 0, r, U256::zero(), digest);
+        assert!(no_error == false);
+        assert_eq!(written_value, U256::zero());
+
+        cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        let worker = Worker::new();
+        assert!(cs.check_if_satisfied(&worker));
+    }
+
+    #[test]
+    fn test_zero_message_policy_allow() {
+        let mut owned_cs = create_cs(1 << 21);
+        let cs = &mut owned_cs;
+
+        let (r, digest, _) = degenerate_inputs();
+        let sk = crate::ff::from_hex::<Secp256Fr>(
+            "b5b1870957d373ef0eeffecc6e4812c0fd08f554b37b233526acc331bf1544f7",
+        )
+        .unwrap();
+        let (_, s, _, _) = simulate_signature_for_sk(sk);
+        let s = repr_into_u256(s.into_repr());
+
+        // zero message is a valid scalar, so we recover s / r * X
+        let (no_error, written_value) =
+            recover_with_policy(cs, ZeroMessagePolicy::Allow, 0, r, s, U256::zero());
+        assert!(no_error == true);
+        assert!(written_value != U256::zero());
+
+        // zero s is still an error
+        let (no_error, written_value) =
+            recover_with_policy(cs, ZeroMessagePolicy::Allow, 0, r, U256::zero(), digest);
+        assert!(no_error == false);
+        assert_eq!(written_value, U256::zero());
+
+        // and so is the point at infinity
+        let (no_error, written_value) = recover_with_policy(
+            cs,
+            ZeroMessagePolicy::Allow,
+            0,
+            r,
+            U256::zero(),
+            U256::zero(),
+        );
+        assert!(no_error == false);
+        assert_eq!(written_value, U256::zero());
+
+        cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        let worker = Worker::new();
+        assert!(cs.check_if_satisfied(&worker));
+    }
+
+    #[test]
+    fn test_zero_message_policy_allow_degenerate() {
+        let mut owned_cs = create_cs(1 << 22);
+        let cs = &mut owned_cs;
+
+        let (r, digest, degenerate_sk) = degenerate_inputs();
+
+        // address of -(hash / r) * G, recovered from the regular signature for this key
+        let degenerate_sk = Secp256Fr::from_str(&degenerate_sk.to_string()).unwrap();
+        let (r_for_sk, s_for_sk, _, digest_for_sk) = simulate_signature_for_sk(degenerate_sk);
+        let mut expected = vec![];
+        for rec_id in [0u8, 1u8] {
+            let (no_error, written_value) = recover_with_policy(
+                cs,
+                ZeroMessagePolicy::AllowDegenerate,
+                rec_id,
+                repr_into_u256(r_for_sk.into_repr()),
+                repr_into_u256(s_for_sk.into_repr()),
+                repr_into_u256(digest_for_sk.into_repr()),
+            );
+            assert!(no_error == true);
+            expected.push(written_value);
+        }
+
+        // zero s, so the recovered key doesn't depend on the parity of X
+        for rec_id in [0u8, 1u8] {
+            let (no_error, written_value) = recover_with_policy(
+                cs,
+                ZeroMessagePolicy::AllowDegenerate,
+                rec_id,
+                r,
+                U256::zero(),
+                digest,
+            );
+            assert!(no_error == true);
+            assert!(expected.contains(&written_value));
+        }
+
+        // zero s and zero message give the point at infinity, that has zero address
+        let (no_error, written_value) = recover_with_policy(
+            cs,
+            ZeroMessagePolicy::AllowDegenerate,
+            0,
+            r,
+            U256::zero(),
+            U256::zero(),
+        );
+        assert!(no_error == true);
+        assert_eq!(written_value, U256::zero());
+
+        // zero r is never recoverable
+        let (no_error, written_value) = recover_with_policy(
+            cs,
+            ZeroMessagePolicy::AllowDegenerate,
+            0,
+            U256::zero(),
+            U256::zero(),
+            digest,
+        );
+        assert!(no_error == false);
+        assert_eq!(written_value, U256::zero());
+
+        cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        let worker = Worker::new();
+        assert!(cs.check_if_satisfied(&worker));
+    }
 }
@@ -0,0 +1,1031 @@
+use super::*;
+
+use crate::base_structures::precompile_input_outputs::PrecompileFunctionOutputData;
+use crate::demux_log_queue::StorageLogQueue;
+use crate::ethereum_types::U256;
+use crate::fsm_input_output::circuit_inputs::INPUT_OUTPUT_COMMITMENT_LENGTH;
+
+use arrayvec::ArrayVec;
+use boojum::algebraic_props::round_function::AlgebraicRoundFunction;
+use boojum::cs::traits::cs::ConstraintSystem;
+use boojum::cs::Variable;
+use boojum::field::SmallField;
+use boojum::gadgets::boolean::Boolean;
+
+use boojum::gadgets::num::Num;
+use boojum::gadgets::queue::CircuitQueueWitness;
+use boojum::gadgets::queue::QueueState;
+use boojum::gadgets::traits::allocatable::{CSAllocatableExt, CSPlaceholder};
+use boojum::gadgets::traits::round_function::CircuitRoundFunction;
+use boojum::gadgets::traits::selectable::Selectable;
+
+use boojum::gadgets::u16::UInt16;
+use boojum::gadgets::u160::UInt160;
+use boojum::gadgets::u256::UInt256;
+use boojum::gadgets::u32::UInt32;
+use boojum::gadgets::u8::UInt8;
+use boojum::pairing::ff::{Field, PrimeField};
+
+use std::collections::VecDeque;
+use std::sync::{Arc, RwLock};
+use zkevm_opcode_defs::system_params::PRECOMPILE_AUX_BYTE;
+
+use super::edwards::Ed25519ExtendedPoint;
+use super::sha512::*;
+use crate::ecrecover::baseline::convert_uint256_to_field_element;
+
+const WINDOW_WIDTH: usize = 4;
+const NUM_MULTIPLICATION_STEPS_FOR_WIDTH_4: usize = 64;
+const PRECOMPUTATION_TABLE_SIZE: usize = (1 << WINDOW_WIDTH) - 1;
+
+/// Input is `MEMORY_QUERIES_PER_CALL` words starting from `input_offset`: message, R, s and public key,
+/// and the output is the success flag followed by the verification result. Message is always exactly
+/// 32 bytes long, so R || A || M fits into a single SHA-512 block. Longer messages must be hashed
+/// by the caller, and shorter ones are verified as if they were left-padded with zeroes
+#[derive(Derivative, CSSelectable)]
+#[derivative(Clone, Debug)]
+pub struct Ed25519VerifyPrecompileCallParams<F: SmallField> {
+    pub input_page: UInt32<F>,
+    pub input_offset: UInt32<F>,
+    pub output_page: UInt32<F>,
+    pub output_offset: UInt32<F>,
+}
+
+impl<F: SmallField> Ed25519VerifyPrecompileCallParams<F> {
+    pub fn from_encoding<CS: ConstraintSystem<F>>(_cs: &mut CS, encoding: UInt256<F>) -> Self {
+        let input_offset = encoding.inner[0];
+        let output_offset = encoding.inner[2];
+        let input_page = encoding.inner[4];
+        let output_page = encoding.inner[5];
+
+        let new = Self {
+            input_page,
+            input_offset,
+            output_page,
+            output_offset,
+        };
+
+        new
+    }
+}
+
+const EXCEPTION_FLAGS_ARR_LEN: usize = 5;
+
+// (p - 5) / 8, little-endian
+const DECOMPRESSION_EXPONENT: [u64; 4] = [
+    0xfffffffffffffffd,
+    0xffffffffffffffff,
+    0xffffffffffffffff,
+    0x0fffffffffffffff,
+];
+// 2^256 mod l, to reduce 512 bit hash output modulo the group order
+const HASH_REDUCTION_CONSTANT: &'static str =
+    "7237005577332262213973186563042994240413239274941949949428319933631315875101";
+// R || A || M is 96 bytes, so it's padded into a single block with bit length of 768
+const SHA512_PADDING_WORD: u64 = 0x8000000000000000;
+const SHA512_MESSAGE_BIT_LENGTH: u64 = 768;
+
+// memory words are big-endian, while RFC 8032 encodes integers and points as little-endian byte strings
+fn reverse_bytes<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    word: &UInt256<F>,
+) -> UInt256<F> {
+    let mut result = *word;
+    for (dst, src) in result.inner.iter_mut().zip(word.inner.iter().rev()) {
+        let bytes = src.to_le_bytes(cs);
+        *dst = UInt32::from_be_bytes(cs, bytes);
+    }
+
+    result
+}
+
+fn pow_by_constant<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    base: &mut Ed25519BaseNNField<F>,
+    exponent: &[u64],
+) -> Ed25519BaseNNField<F> {
+    let num_bits = exponent.len() * 64 - exponent.last().unwrap().leading_zeros() as usize;
+    let mut result = base.clone();
+    for i in (0..(num_bits - 1)).rev() {
+        result = result.square(cs);
+        if (exponent[i / 64] >> (i % 64)) & 1 == 1 {
+            result = result.mul(cs, base);
+        }
+    }
+
+    result
+}
+
+/// Decodes the point from the RFC 8032 encoding, that is little-endian y with the sign of x
+/// in the highest bit. Returns the point masked to the generator if the encoding is invalid,
+/// so it can be safely used in arithmetic formulas, and flags for non-canonical y and for
+/// encodings that don't correspond to any point on curve
+fn ed25519_decompress_and_mask_point<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    encoding: &UInt256<F>,
+    base_field_params: &Arc<Ed25519BaseNNFieldParams>,
+) -> (
+    (Ed25519BaseNNField<F>, Ed25519BaseNNField<F>),
+    Boolean<F>,
+    Boolean<F>,
+) {
+    let curve_d = Ed25519Affine::d_coeff();
+    let mut curve_d_nn = Ed25519BaseNNField::allocated_constant(cs, curve_d, base_field_params);
+    let sqrt_minus_one = Ed25519Affine::sqrt_minus_one();
+    let mut sqrt_minus_one_nn =
+        Ed25519BaseNNField::allocated_constant(cs, sqrt_minus_one, base_field_params);
+    let mut one_nn =
+        Ed25519BaseNNField::allocated_constant(cs, Ed25519Fq::one(), base_field_params);
+
+    let (gen_x, gen_y) = Ed25519Affine::one().into_xy_unchecked();
+    let gen_x_nn = Ed25519BaseNNField::allocated_constant(cs, gen_x, base_field_params);
+    let gen_y_nn = Ed25519BaseNNField::allocated_constant(cs, gen_y, base_field_params);
+
+    let p_u256 = U256([
+        base_field_params.modulus_u1024.as_ref().as_words()[0],
+        base_field_params.modulus_u1024.as_ref().as_words()[1],
+        base_field_params.modulus_u1024.as_ref().as_words()[2],
+        base_field_params.modulus_u1024.as_ref().as_words()[3],
+    ]);
+    let p_u256 = UInt256::allocated_constant(cs, p_u256);
+
+    // the highest bit is the sign of x
+    let mut y_as_u256 = *encoding;
+    let [byte_0, byte_1, byte_2, top_byte] = y_as_u256.inner[7].to_le_bytes(cs);
+    let top_byte_bits = Num::from_variable(top_byte.get_variable()).spread_into_bits::<_, 8>(cs);
+    let sign_flag = top_byte_bits[7];
+
+    let top_byte_lc: Vec<_> = top_byte_bits[..7]
+        .iter()
+        .enumerate()
+        .map(|(i, bit)| (bit.get_variable(), F::SHIFTS[i]))
+        .collect();
+    let top_byte = Num::linear_combination(cs, &top_byte_lc);
+    let top_byte = unsafe { UInt8::from_variable_unchecked(top_byte.get_variable()) };
+    y_as_u256.inner[7] = UInt32::from_le_bytes(cs, [byte_0, byte_1, byte_2, top_byte]);
+
+    // non-canonical encodings are rejected
+    let (_res, y_is_in_range) = y_as_u256.overflowing_sub(cs, &p_u256);
+    let y_as_u256 = y_as_u256.mask(cs, y_is_in_range);
+    let y_is_not_in_range = y_is_in_range.negated(cs);
+
+    let mut y = convert_uint256_to_field_element(cs, &y_as_u256, base_field_params);
+    y.normalize(cs);
+
+    // curve equation gives x^2 = u / v, where u = y^2 - 1 and v = d * y^2 + 1. Note that v is never zero,
+    // since -1 / d is not a square. As p = 5 mod 8, candidate root is x = u * v^3 * (u * v^7)^((p - 5) / 8),
+    // and if v * x^2 = -u instead of u then the root is x * sqrt(-1)
+    let mut y_squared = y.square(cs);
+    let mut u = y_squared.sub(cs, &mut one_nn);
+    u.normalize(cs);
+    let mut v = y_squared.mul(cs, &mut curve_d_nn);
+    let mut v = v.add(cs, &mut one_nn);
+    v.normalize(cs);
+
+    let mut v_squared = v.square(cs);
+    let mut v_cubed = v_squared.mul(cs, &mut v);
+    let mut v_pow_7 = v_cubed.square(cs);
+    let mut v_pow_7 = v_pow_7.mul(cs, &mut v);
+    let mut t = u.mul(cs, &mut v_pow_7);
+    t.normalize(cs);
+
+    let mut t = pow_by_constant(cs, &mut t, &DECOMPRESSION_EXPONENT);
+    let mut x = u.mul(cs, &mut v_cubed);
+    let mut x = x.mul(cs, &mut t);
+    x.normalize(cs);
+
+    let mut x_squared = x.square(cs);
+    let mut v_by_x_squared = v.mul(cs, &mut x_squared);
+    v_by_x_squared.normalize(cs);
+    let mut u_negated = u.negated(cs);
+    u_negated.normalize(cs);
+
+    let is_root = NonNativeFieldOverU16::equals(cs, &mut v_by_x_squared, &mut u);
+    let is_root_of_negation =
+        NonNativeFieldOverU16::equals(cs, &mut v_by_x_squared, &mut u_negated);
+    let is_on_curve = Boolean::multi_or(cs, &[is_root, is_root_of_negation]);
+
+    let mut x_by_sqrt_minus_one = x.mul(cs, &mut sqrt_minus_one_nn);
+    x_by_sqrt_minus_one.normalize(cs);
+    let mut x = Selectable::conditionally_select(cs, is_root, &x, &x_by_sqrt_minus_one);
+    x.enforce_reduced(cs);
+
+    // if the sign flag doesn't match the parity, then we take another root
+    let lowest_limb = unsafe { UInt16::from_variable_unchecked(x.limbs[0]) };
+    let [lowest_byte, _] = lowest_limb.to_le_bytes(cs);
+    let lowest_byte_bits =
+        Num::from_variable(lowest_byte.get_variable()).spread_into_bits::<_, 8>(cs);
+    let x_is_odd = lowest_byte_bits[0];
+
+    let mut x_negated = x.negated(cs);
+    x_negated.normalize(cs);
+    let should_swap = x_is_odd.xor(cs, sign_flag);
+    let x = Selectable::conditionally_select(cs, should_swap, &x_negated, &x);
+
+    // zero has no negative root, so the sign flag must not be set
+    let x_is_zero = x.clone().is_zero(cs);
+    let x_has_invalid_sign = Boolean::multi_and(cs, &[x_is_zero, sign_flag]);
+    let x_has_valid_sign = x_has_invalid_sign.negated(cs);
+    let is_on_curve = Boolean::multi_and(cs, &[is_on_curve, x_has_valid_sign]);
+    let is_not_on_curve = is_on_curve.negated(cs);
+
+    // we can mask point to ensure that our arithmetic formulas work
+    let is_valid = Boolean::multi_and(cs, &[y_is_in_range, is_on_curve]);
+    let x = Selectable::conditionally_select(cs, is_valid, &x, &gen_x_nn);
+    let y = Selectable::conditionally_select(cs, is_valid, &y, &gen_y_nn);
+
+    ((x, y), y_is_not_in_range, is_not_on_curve)
+}
+
+/// Computes k = SHA-512(R || A || M) mod l for the raw memory words
+fn ed25519_challenge<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    r: &UInt256<F>,
+    public_key: &UInt256<F>,
+    message: &UInt256<F>,
+    scalar_field_params: &Arc<Ed25519ScalarNNFieldParams>,
+) -> Ed25519ScalarNNField<F> {
+    let zero_u32 = UInt32::zero(cs);
+    let padding_word = [
+        zero_u32,
+        UInt32::allocated_constant(cs, (SHA512_PADDING_WORD >> 32) as u32),
+    ];
+    let length_word = [
+        UInt32::allocated_constant(cs, SHA512_MESSAGE_BIT_LENGTH as u32),
+        zero_u32,
+    ];
+
+    let mut block = [[zero_u32; 2]; SHA512_BLOCK_WORDS];
+    block[0..4].copy_from_slice(&u256_into_sha512_words(r));
+    block[4..8].copy_from_slice(&u256_into_sha512_words(public_key));
+    block[8..12].copy_from_slice(&u256_into_sha512_words(message));
+    block[12] = padding_word;
+    block[15] = length_word;
+
+    let mut state = sha512_ivs_as_uint32_pairs(cs);
+    sha512_round_function(cs, &mut state, &block);
+
+    // digest is interpreted as 512 bit little-endian integer
+    let low = sha512_words_into_u256(&state[..4]);
+    let low = reverse_bytes(cs, &low);
+    let high = sha512_words_into_u256(&state[4..]);
+    let high = reverse_bytes(cs, &high);
+
+    let mut low = convert_uint256_to_field_element(cs, &low, scalar_field_params);
+    low.normalize(cs);
+    let mut high = convert_uint256_to_field_element(cs, &high, scalar_field_params);
+    high.normalize(cs);
+
+    let reduction_constant = Ed25519Fr::from_str(HASH_REDUCTION_CONSTANT).unwrap();
+    let mut reduction_constant =
+        Ed25519ScalarNNField::allocated_constant(cs, reduction_constant, scalar_field_params);
+    let mut result = high.mul(cs, &mut reduction_constant);
+    let mut result = result.add(cs, &mut low);
+    result.normalize(cs);
+
+    result
+}
+
+fn ed25519_verify_function_inner<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    message: &UInt256<F>,
+    r: &UInt256<F>,
+    s: &UInt256<F>,
+    public_key: &UInt256<F>,
+    base_field_params: &Arc<Ed25519BaseNNFieldParams>,
+    scalar_field_params: &Arc<Ed25519ScalarNNFieldParams>,
+) -> (Boolean<F>, UInt256<F>) {
+    let mut curve_d2 = Ed25519Affine::d_coeff();
+    curve_d2.double();
+    let mut curve_d2_nn = Ed25519BaseNNField::allocated_constant(cs, curve_d2, base_field_params);
+
+    let ed25519_l_u256 = U256([
+        scalar_field_params.modulus_u1024.as_ref().as_words()[0],
+        scalar_field_params.modulus_u1024.as_ref().as_words()[1],
+        scalar_field_params.modulus_u1024.as_ref().as_words()[2],
+        scalar_field_params.modulus_u1024.as_ref().as_words()[3],
+    ]);
+    let ed25519_l_u256 = UInt256::allocated_constant(cs, ed25519_l_u256);
+
+    let mut exception_flags = ArrayVec::<_, EXCEPTION_FLAGS_ARR_LEN>::new();
+
+    // we use cofactorless verification equation [s]B = R + [k]A, and check that:
+    // - s is canonical
+    // - R and A are canonical encodings of the points on curve
+
+    let s_as_u256 = reverse_bytes(cs, s);
+    let (_res, is_in_range) = s_as_u256.overflowing_sub(cs, &ed25519_l_u256);
+    let s_as_u256 = s_as_u256.mask(cs, is_in_range);
+    let s_is_not_in_range = is_in_range.negated(cs);
+    exception_flags.push(s_is_not_in_range);
+
+    let r_encoding = reverse_bytes(cs, r);
+    let ((mut r_x, mut r_y), r_y_is_not_in_range, r_is_not_on_curve) =
+        ed25519_decompress_and_mask_point(cs, &r_encoding, base_field_params);
+    exception_flags.push(r_y_is_not_in_range);
+    exception_flags.push(r_is_not_on_curve);
+
+    let public_key_encoding = reverse_bytes(cs, public_key);
+    let ((a_x, a_y), a_y_is_not_in_range, a_is_not_on_curve) =
+        ed25519_decompress_and_mask_point(cs, &public_key_encoding, base_field_params);
+    exception_flags.push(a_y_is_not_in_range);
+    exception_flags.push(a_is_not_on_curve);
+
+    // hash is computed over the encodings as they are in memory
+    let k = ed25519_challenge(cs, r, public_key, message, scalar_field_params);
+
+    // it's safe since we masked the point above
+    let mut public_key_point = Ed25519ExtendedPoint::from_xy_unchecked(cs, a_x, a_y);
+    let public_key_negated = public_key_point.negated(cs);
+    let mut k_by_public_key_negated = width_4_windowed_multiplication(
+        cs,
+        public_key_negated,
+        k,
+        base_field_params,
+        &mut curve_d2_nn,
+    );
+
+    let mut full_table_ids = vec![];
+    seq_macro::seq!(C in 0..32 {
+        let ids = [
+            cs.get_table_id_for_marker::<Ed25519FixedBaseMulTable<0, C>>()
+                .expect("table must exist"),
+            cs.get_table_id_for_marker::<Ed25519FixedBaseMulTable<1, C>>()
+                .expect("table must exist"),
+            cs.get_table_id_for_marker::<Ed25519FixedBaseMulTable<2, C>>()
+                .expect("table must exist"),
+            cs.get_table_id_for_marker::<Ed25519FixedBaseMulTable<3, C>>()
+                .expect("table must exist"),
+            cs.get_table_id_for_marker::<Ed25519FixedBaseMulTable<4, C>>()
+                .expect("table must exist"),
+            cs.get_table_id_for_marker::<Ed25519FixedBaseMulTable<5, C>>()
+                .expect("table must exist"),
+            cs.get_table_id_for_marker::<Ed25519FixedBaseMulTable<6, C>>()
+                .expect("table must exist"),
+            cs.get_table_id_for_marker::<Ed25519FixedBaseMulTable<7, C>>()
+                .expect("table must exist"),
+        ];
+        full_table_ids.push(ids);
+    });
+
+    let mut s_times_g = fixed_base_mul(
+        cs,
+        &s_as_u256,
+        base_field_params,
+        &mut curve_d2_nn,
+        &full_table_ids,
+    );
+
+    // [s]B - [k]A must be equal to R
+    let mut lhs = s_times_g.add(cs, &mut k_by_public_key_negated, &mut curve_d2_nn);
+    let signature_equality = lhs.equals_affine(cs, &mut r_x, &mut r_y);
+
+    let any_exception = Boolean::multi_or(cs, &exception_flags[..]);
+    let written_value_bool = signature_equality.mask_negated(cs, any_exception);
+    let all_ok = any_exception.negated(cs);
+
+    let mut written_value = UInt256::zero(cs);
+    written_value.inner[0] =
+        unsafe { UInt32::from_variable_unchecked(written_value_bool.get_variable()) };
+
+    (all_ok, written_value)
+}
+
+pub fn ed25519_verify_function_entry_point<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    witness: Ed25519VerifyCircuitInstanceWitness<F>,
+    round_function: &R,
+    limit: usize,
+) -> [Num<F>; INPUT_OUTPUT_COMMITMENT_LENGTH]
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    assert!(limit <= u32::MAX as usize);
+
+    let Ed25519VerifyCircuitInstanceWitness {
+        closed_form_input,
+        requests_queue_witness,
+        memory_reads_witness,
+    } = witness;
+
+    let memory_reads_witness: VecDeque<_> = memory_reads_witness.into_iter().flatten().collect();
+
+    let precompile_address =
+        UInt160::allocated_constant(cs, ED25519_VERIFY_PRECOMPILE_FORMAL_ADDRESS);
+    let aux_byte_for_precompile = UInt8::allocated_constant(cs, PRECOMPILE_AUX_BYTE);
+
+    let scalar_params = Arc::new(ed25519_scalar_field_params());
+    let base_params = Arc::new(ed25519_base_field_params());
+
+    let mut structured_input =
+        Ed25519VerifyCircuitInputOutput::alloc_ignoring_outputs(cs, closed_form_input.clone());
+    let start_flag = structured_input.start_flag;
+
+    let requests_queue_state_from_input = structured_input.observable_input.initial_log_queue_state;
+
+    // it must be trivial
+    requests_queue_state_from_input.enforce_trivial_head(cs);
+
+    let requests_queue_state_from_fsm = structured_input.hidden_fsm_input.log_queue_state;
+
+    let requests_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &requests_queue_state_from_input,
+        &requests_queue_state_from_fsm,
+    );
+
+    let memory_queue_state_from_input =
+        structured_input.observable_input.initial_memory_queue_state;
+
+    // it must be trivial
+    memory_queue_state_from_input.enforce_trivial_head(cs);
+
+    let memory_queue_state_from_fsm = structured_input.hidden_fsm_input.memory_queue_state;
+
+    let memory_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &memory_queue_state_from_input,
+        &memory_queue_state_from_fsm,
+    );
+
+    let mut requests_queue = StorageLogQueue::<F, R>::from_state(cs, requests_queue_state);
+    let queue_witness = CircuitQueueWitness::from_inner_witness(requests_queue_witness);
+    requests_queue.witness = Arc::new(queue_witness);
+
+    let mut memory_queue = MemoryQueue::<F, R>::from_state(cs, memory_queue_state);
+
+    let one_u32 = UInt32::allocated_constant(cs, 1u32);
+    let zero_u256 = UInt256::zero(cs);
+    let boolean_false = Boolean::allocated_constant(cs, false);
+    let boolean_true = Boolean::allocated_constant(cs, true);
+
+    use crate::storage_application::ConditionalWitnessAllocator;
+    let read_queries_allocator = ConditionalWitnessAllocator::<F, UInt256<F>> {
+        witness_source: Arc::new(RwLock::new(memory_reads_witness)),
+    };
+
+    for _cycle in 0..limit {
+        let is_empty = requests_queue.is_empty(cs);
+        let should_process = is_empty.negated(cs);
+        let (request, _) = requests_queue.pop_front(cs, should_process);
+
+        let mut precompile_call_params =
+            Ed25519VerifyPrecompileCallParams::from_encoding(cs, request.key);
+
+        let timestamp_to_use_for_read = request.timestamp;
+        let timestamp_to_use_for_write = timestamp_to_use_for_read.add_no_overflow(cs, one_u32);
+
+        Num::conditionally_enforce_equal(
+            cs,
+            should_process,
+            &Num::from_variable(request.aux_byte.get_variable()),
+            &Num::from_variable(aux_byte_for_precompile.get_variable()),
+        );
+        for (a, b) in request
+            .address
+            .inner
+            .iter()
+            .zip(precompile_address.inner.iter())
+        {
+            Num::conditionally_enforce_equal(
+                cs,
+                should_process,
+                &Num::from_variable(a.get_variable()),
+                &Num::from_variable(b.get_variable()),
+            );
+        }
+
+        let mut read_values = [zero_u256; MEMORY_QUERIES_PER_CALL];
+        let mut bias_variable = should_process.get_variable();
+        for dst in read_values.iter_mut() {
+            let read_query_value: UInt256<F> = read_queries_allocator
+                .conditionally_allocate_biased(cs, should_process, bias_variable);
+            bias_variable = read_query_value.inner[0].get_variable();
+
+            *dst = read_query_value;
+
+            let read_query = MemoryQuery {
+                timestamp: timestamp_to_use_for_read,
+                memory_page: precompile_call_params.input_page,
+                index: precompile_call_params.input_offset,
+                rw_flag: boolean_false,
+                is_ptr: boolean_false,
+                value: read_query_value,
+            };
+
+            let _ = memory_queue.push(cs, read_query, should_process);
+
+            precompile_call_params.input_offset = precompile_call_params
+                .input_offset
+                .add_no_overflow(cs, one_u32);
+        }
+
+        let [message_as_u256, r_as_u256, s_as_u256, public_key_as_u256] = read_values;
+
+        let (success, written_value) = ed25519_verify_function_inner(
+            cs,
+            &message_as_u256,
+            &r_as_u256,
+            &s_as_u256,
+            &public_key_as_u256,
+            &base_params,
+            &scalar_params,
+        );
+
+        let success_as_u32 = unsafe { UInt32::from_variable_unchecked(success.get_variable()) };
+        let mut success_as_u256 = zero_u256;
+        success_as_u256.inner[0] = success_as_u32;
+
+        let success_query = MemoryQuery {
+            timestamp: timestamp_to_use_for_write,
+            memory_page: precompile_call_params.output_page,
+            index: precompile_call_params.output_offset,
+            rw_flag: boolean_true,
+            value: success_as_u256,
+            is_ptr: boolean_false,
+        };
+
+        precompile_call_params.output_offset = precompile_call_params
+            .output_offset
+            .add_no_overflow(cs, one_u32);
+
+        let _ = memory_queue.push(cs, success_query, should_process);
+
+        let value_query = MemoryQuery {
+            timestamp: timestamp_to_use_for_write,
+            memory_page: precompile_call_params.output_page,
+            index: precompile_call_params.output_offset,
+            rw_flag: boolean_true,
+            value: written_value,
+            is_ptr: boolean_false,
+        };
+
+        let _ = memory_queue.push(cs, value_query, should_process);
+    }
+
+    requests_queue.enforce_consistency(cs);
+
+    // form the final state
+    let done = requests_queue.is_empty(cs);
+    structured_input.completion_flag = done;
+    structured_input.observable_output = PrecompileFunctionOutputData::placeholder(cs);
+
+    let final_memory_state = memory_queue.into_state();
+    let final_requets_state = requests_queue.into_state();
+
+    structured_input.observable_output.final_memory_state = QueueState::conditionally_select(
+        cs,
+        structured_input.completion_flag,
+        &final_memory_state,
+        &structured_input.observable_output.final_memory_state,
+    );
+
+    structured_input.hidden_fsm_output.log_queue_state = final_requets_state;
+    structured_input.hidden_fsm_output.memory_queue_state = final_memory_state;
+
+    // self-check
+    structured_input.hook_compare_witness(cs, &closed_form_input);
+
+    use boojum::cs::gates::PublicInputGate;
+
+    let compact_form =
+        ClosedFormInputCompactForm::from_full_form(cs, &structured_input, round_function);
+    let input_commitment = commit_variable_length_encodable_item(cs, &compact_form, round_function);
+    for el in input_commitment.iter() {
+        let gate = PublicInputGate::new(el.get_variable());
+        gate.add_to_cs(cs);
+    }
+
+    input_commitment
+}
+
+fn fixed_base_mul<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    scalar: &UInt256<F>,
+    base_field_params: &Arc<Ed25519BaseNNFieldParams>,
+    curve_d2: &mut Ed25519BaseNNField<F>,
+    fixed_base_table_ids: &[[u32; 8]],
+) -> Ed25519ExtendedPoint<F> {
+    assert_eq!(fixed_base_table_ids.len(), 32);
+
+    let bytes = scalar
+        .inner
+        .iter()
+        .flat_map(|el| el.to_le_bytes(cs))
+        .collect::<Vec<UInt8<F>>>();
+
+    // tables contain the neutral element (0, 1) for zero byte, so we don't need to select
+    let mut acc = Ed25519ExtendedPoint::zero(cs, base_field_params);
+    for (ids, byte) in fixed_base_table_ids.iter().zip(bytes) {
+        let (x, y): (Vec<Variable>, Vec<Variable>) = ids
+            .iter()
+            .flat_map(|id| {
+                let [x_v, y_v] = cs.perform_lookup::<1, 2>(*id, &[byte.get_variable()]);
+                let x_v = unsafe { UInt32::from_variable_unchecked(x_v) };
+                let y_v = unsafe { UInt32::from_variable_unchecked(y_v) };
+                let x_v = x_v.to_le_bytes(cs);
+                let y_v = y_v.to_le_bytes(cs);
+                let x_1 = UInt16::from_le_bytes(cs, x_v[..2].try_into().unwrap());
+                let x_2 = UInt16::from_le_bytes(cs, x_v[2..].try_into().unwrap());
+                let y_1 = UInt16::from_le_bytes(cs, y_v[..2].try_into().unwrap());
+                let y_2 = UInt16::from_le_bytes(cs, y_v[2..].try_into().unwrap());
+                [
+                    (x_1.get_variable(), y_1.get_variable()),
+                    (x_2.get_variable(), y_2.get_variable()),
+                ]
+            })
+            .collect::<Vec<(Variable, Variable)>>()
+            .into_iter()
+            .unzip();
+        let zero_var = cs.allocate_constant(F::ZERO);
+        let mut x_arr = [zero_var; BASE_FIELD_REPR_LIMBS];
+        x_arr[..BASE_FIELD_CANONICAL_REPR_LIMBS]
+            .copy_from_slice(&x[..BASE_FIELD_CANONICAL_REPR_LIMBS]);
+        let mut y_arr = [zero_var; BASE_FIELD_REPR_LIMBS];
+        y_arr[..BASE_FIELD_CANONICAL_REPR_LIMBS]
+            .copy_from_slice(&y[..BASE_FIELD_CANONICAL_REPR_LIMBS]);
+        let x = NonNativeFieldOverU16 {
+            limbs: x_arr,
+            non_zero_limbs: BASE_FIELD_CANONICAL_REPR_LIMBS,
+            tracker: OverflowTracker { max_moduluses: 1 },
+            form: RepresentationForm::Normalized,
+            params: base_field_params.clone(),
+            _marker: std::marker::PhantomData,
+        };
+        let y = NonNativeFieldOverU16 {
+            limbs: y_arr,
+            non_zero_limbs: BASE_FIELD_CANONICAL_REPR_LIMBS,
+            tracker: OverflowTracker { max_moduluses: 1 },
+            form: RepresentationForm::Normalized,
+            params: base_field_params.clone(),
+            _marker: std::marker::PhantomData,
+        };
+        let mut point = Ed25519ExtendedPoint::from_xy_unchecked(cs, x, y);
+        acc = acc.add(cs, &mut point, curve_d2);
+    }
+
+    acc
+}
+
+fn width_4_windowed_multiplication<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    mut point: Ed25519ExtendedPoint<F>,
+    scalar: Ed25519ScalarNNField<F>,
+    base_field_params: &Arc<Ed25519BaseNNFieldParams>,
+    curve_d2: &mut Ed25519BaseNNField<F>,
+) -> Ed25519ExtendedPoint<F> {
+    // create precomputed table of size 1<<4 - 1
+    // there is no 0 * P in the table, we will handle it below
+    let mut table = Vec::with_capacity(PRECOMPUTATION_TABLE_SIZE);
+    let mut tmp = point.clone();
+    table.push(point.clone());
+    for _ in 1..PRECOMPUTATION_TABLE_SIZE {
+        // 2P, 3P, ...
+        tmp = tmp.add(cs, &mut point, curve_d2);
+        table.push(tmp.clone());
+    }
+    assert_eq!(table.len(), PRECOMPUTATION_TABLE_SIZE);
+
+    // now decompose every scalar we are interested in
+    let msb_decomposition = to_width_4_window_form(cs, scalar);
+
+    let mut comparison_constants = Vec::with_capacity(PRECOMPUTATION_TABLE_SIZE);
+    for i in 1..=PRECOMPUTATION_TABLE_SIZE {
+        let constant = Num::allocated_constant(cs, F::from_u64_unchecked(i as u64));
+        comparison_constants.push(constant);
+    }
+
+    // now we just do double and add. Formulas are complete, so zero window just adds the neutral element
+    let zero_point = Ed25519ExtendedPoint::zero(cs, base_field_params);
+    let mut acc = zero_point.clone();
+    assert_eq!(
+        msb_decomposition.len(),
+        NUM_MULTIPLICATION_STEPS_FOR_WIDTH_4
+    );
+
+    for (idx, window_idx) in msb_decomposition.into_iter().enumerate() {
+        let mut selected_part = zero_point.clone();
+        for i in 0..PRECOMPUTATION_TABLE_SIZE {
+            let should_select = Num::equals(cs, &comparison_constants[i], &window_idx);
+            selected_part =
+                Selectable::conditionally_select(cs, should_select, &table[i], &selected_part);
+        }
+
+        acc = acc.add(cs, &mut selected_part, curve_d2);
+
+        if idx != NUM_MULTIPLICATION_STEPS_FOR_WIDTH_4 - 1 {
+            for _ in 0..WINDOW_WIDTH {
+                acc = acc.double(cs);
+            }
+        }
+    }
+
+    acc
+}
+
+fn to_width_4_window_form<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    mut limited_width_scalar: Ed25519ScalarNNField<F>,
+) -> Vec<Num<F>> {
+    limited_width_scalar.enforce_reduced(cs);
+    // we know that width is 256 bits, so just do BE decomposition and put into resulting array
+    let zero_num = Num::zero(cs);
+    for word in limited_width_scalar.limbs[SCALAR_FIELD_CANONICAL_REPR_LIMBS..].iter() {
+        let word = Num::from_variable(*word);
+        Num::enforce_equal(cs, &word, &zero_num);
+    }
+
+    use boojum::gadgets::tables::ByteSplitTable;
+    let byte_split_id = cs
+        .get_table_id_for_marker::<ByteSplitTable<4>>()
+        .expect("table should exist");
+    let mut result = Vec::with_capacity(NUM_MULTIPLICATION_STEPS_FOR_WIDTH_4);
+    for word in limited_width_scalar.limbs[..SCALAR_FIELD_CANONICAL_REPR_LIMBS]
+        .iter()
+        .rev()
+    {
+        let word = unsafe { UInt16::from_variable_unchecked(*word) };
+        let [high, low] = word.to_be_bytes(cs);
+        for t in [high, low].into_iter() {
+            let [l, h] = cs.perform_lookup::<1, 2>(byte_split_id, &[t.get_variable()]);
+            let h = Num::from_variable(h);
+            let l = Num::from_variable(l);
+            result.push(h);
+            result.push(l);
+        }
+    }
+    assert_eq!(result.len(), NUM_MULTIPLICATION_STEPS_FOR_WIDTH_4);
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use boojum::field::goldilocks::GoldilocksField;
+    use boojum::gadgets::traits::allocatable::CSAllocatable;
+    use boojum::worker::Worker;
+
+    use super::*;
+
+    type F = GoldilocksField;
+    type P = GoldilocksField;
+
+    use boojum::config::DevCSConfig;
+
+    use boojum::cs::cs_builder::*;
+    use boojum::cs::cs_builder_reference::CsReferenceImplementationBuilder;
+    use boojum::cs::gates::*;
+    use boojum::cs::implementations::reference_cs::CSReferenceImplementation;
+    use boojum::cs::traits::gate::GatePlacementStrategy;
+    use boojum::cs::CSGeometry;
+    use boojum::cs::*;
+    use boojum::gadgets::tables::*;
+
+    // RFC 8032 secret key from "TEST 1", signing sha256("ed25519 precompile test")
+    const MESSAGE: &'static str =
+        "75ea4c95a7ee2918ee15bc5f1774b1ad25f99e76d23c1b30ee46176ed32204c0";
+    const PUBLIC_KEY: &'static str =
+        "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+    const SIGNATURE: &'static str = "95355530f071fa2550ac9355271b44673635fd35798ce499a71a00d1461ae0c4e81dbce9537185abc1fca4053233d704ea93f3907f28e52484f71b3580d68c07";
+
+    fn create_cs() -> CSReferenceImplementation<
+        F,
+        P,
+        DevCSConfig,
+        impl GateConfigurationHolder<F>,
+        impl StaticToolboxHolder,
+    > {
+        let geometry = CSGeometry {
+            num_columns_under_copy_permutation: 80,
+            num_witness_columns: 0,
+            num_constant_columns: 4,
+            max_allowed_constraint_degree: 8,
+        };
+
+        let max_variables = 1 << 26;
+        let max_trace_len = 1 << 21;
+
+        fn configure<
+            F: SmallField,
+            T: CsBuilderImpl<F, T>,
+            GC: GateConfigurationHolder<F>,
+            TB: StaticToolboxHolder,
+        >(
+            builder: CsBuilder<T, F, GC, TB>,
+        ) -> CsBuilder<T, F, impl GateConfigurationHolder<F>, impl StaticToolboxHolder> {
+            let builder = builder.allow_lookup(
+                LookupParameters::UseSpecializedColumnsWithTableIdAsConstant {
+                    width: 3,
+                    num_repetitions: 16,
+                    share_table_id: true,
+                },
+            );
+
+            let builder = ConstantsAllocatorGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = BooleanConstraintGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseSpecializedColumns {
+                    num_repetitions: 1,
+                    share_constants: false,
+                },
+            );
+            let builder = U8x4FMAGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ZeroCheckGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+                false,
+            );
+            let builder = FmaGateInBaseFieldWithoutConstant::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<32>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<16>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<8>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = DotProductGate::<4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = SelectionGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ParallelSelectionGate::<4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = PublicInputGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ReductionGate::<_, 4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = NopGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+
+            builder
+        }
+
+        let builder_impl =
+            CsReferenceImplementationBuilder::<F, P, DevCSConfig>::new(geometry, max_trace_len);
+        let builder = new_builder::<_, F>(builder_impl);
+
+        let builder = configure(builder);
+        let mut owned_cs = builder.build(max_variables);
+
+        // add tables
+        let table = create_xor8_table();
+        owned_cs.add_lookup_table::<Xor8Table, 3>(table);
+
+        seq_macro::seq!(C in 0..32 {
+            let table = create_ed25519_fixed_base_mul_table::<F, 0, C>();
+            owned_cs.add_lookup_table::<Ed25519FixedBaseMulTable<0, C>, 3>(table);
+            let table = create_ed25519_fixed_base_mul_table::<F, 1, C>();
+            owned_cs.add_lookup_table::<Ed25519FixedBaseMulTable<1, C>, 3>(table);
+            let table = create_ed25519_fixed_base_mul_table::<F, 2, C>();
+            owned_cs.add_lookup_table::<Ed25519FixedBaseMulTable<2, C>, 3>(table);
+            let table = create_ed25519_fixed_base_mul_table::<F, 3, C>();
+            owned_cs.add_lookup_table::<Ed25519FixedBaseMulTable<3, C>, 3>(table);
+            let table = create_ed25519_fixed_base_mul_table::<F, 4, C>();
+            owned_cs.add_lookup_table::<Ed25519FixedBaseMulTable<4, C>, 3>(table);
+            let table = create_ed25519_fixed_base_mul_table::<F, 5, C>();
+            owned_cs.add_lookup_table::<Ed25519FixedBaseMulTable<5, C>, 3>(table);
+            let table = create_ed25519_fixed_base_mul_table::<F, 6, C>();
+            owned_cs.add_lookup_table::<Ed25519FixedBaseMulTable<6, C>, 3>(table);
+            let table = create_ed25519_fixed_base_mul_table::<F, 7, C>();
+            owned_cs.add_lookup_table::<Ed25519FixedBaseMulTable<7, C>, 3>(table);
+        });
+
+        let table = create_byte_split_table::<F, 4>();
+        owned_cs.add_lookup_table::<ByteSplitTable<4>, 3>(table);
+
+        owned_cs
+    }
+
+    fn run_verification(message: &[u8], signature: &[u8], public_key: &[u8]) -> (bool, U256) {
+        let mut owned_cs = create_cs();
+        let cs = &mut owned_cs;
+
+        let scalar_params = Arc::new(ed25519_scalar_field_params());
+        let base_params = Arc::new(ed25519_base_field_params());
+
+        // memory words hold the byte strings as is
+        let message = UInt256::allocate(cs, U256::from_big_endian(message));
+        let r = UInt256::allocate(cs, U256::from_big_endian(&signature[..32]));
+        let s = UInt256::allocate(cs, U256::from_big_endian(&signature[32..]));
+        let public_key = UInt256::allocate(cs, U256::from_big_endian(public_key));
+
+        let (no_error, is_valid) = ed25519_verify_function_inner(
+            cs,
+            &message,
+            &r,
+            &s,
+            &public_key,
+            &base_params,
+            &scalar_params,
+        );
+
+        let no_error = no_error.witness_hook(&*cs)().unwrap();
+        let is_valid = is_valid.witness_hook(&*cs)().unwrap();
+
+        dbg!(cs.next_available_row());
+
+        cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        cs.print_gate_stats();
+        let worker = Worker::new();
+        assert!(cs.check_if_satisfied(&worker));
+
+        (no_error, is_valid)
+    }
+
+    #[test]
+    fn test_ed25519_verification() {
+        let message = hex::decode(MESSAGE).unwrap();
+        let signature = hex::decode(SIGNATURE).unwrap();
+        let public_key = hex::decode(PUBLIC_KEY).unwrap();
+
+        let (no_error, is_valid) = run_verification(&message, &signature, &public_key);
+        assert!(no_error == true);
+        assert!(is_valid == U256::one());
+    }
+
+    #[test]
+    fn test_ed25519_verification_invalid_inputs() {
+        let message = hex::decode(MESSAGE).unwrap();
+        let signature = hex::decode(SIGNATURE).unwrap();
+        let public_key = hex::decode(PUBLIC_KEY).unwrap();
+
+        // different message is a well-formed input with invalid signature
+        let mut other_message = message.clone();
+        other_message[0] ^= 1;
+        let (no_error, is_valid) = run_verification(&other_message, &signature, &public_key);
+        assert!(no_error == true);
+        assert!(is_valid == U256::zero());
+
+        // s + l is not canonical
+        let s = U256::from_little_endian(&signature[32..]);
+        let l = U256::from_dec_str(
+            "7237005577332262213973186563042994240857116359379907606001950938285454250989",
+        )
+        .unwrap();
+        let mut malleated_signature = signature.clone();
+        (s + l).to_little_endian(&mut malleated_signature[32..]);
+        let (no_error, is_valid) = run_verification(&message, &malleated_signature, &public_key);
+        assert!(no_error == false);
+        assert!(is_valid == U256::zero());
+
+        // y = 2 doesn't correspond to any point on curve
+        let mut invalid_public_key = [0u8; 32];
+        invalid_public_key[0] = 2;
+        let (no_error, is_valid) = run_verification(&message, &signature, &invalid_public_key);
+        assert!(no_error == false);
+        assert!(is_valid == U256::zero());
+    }
+
+    #[test]
+    fn test_ed25519_verification_of_short_message() {
+        // RFC 8032 "TEST 2" signs a single byte message
+        let message = hex::decode("72").unwrap();
+        let signature = hex::decode("92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00").unwrap();
+        let public_key =
+            hex::decode("3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c")
+                .unwrap();
+
+        // precompile always hashes 32 bytes of the message word, so it's not the signed message
+        let mut message_word = [0u8; 32];
+        message_word[31..].copy_from_slice(&message);
+        let (no_error, is_valid) = run_verification(&message_word, &signature, &public_key);
+        assert!(no_error == true);
+        assert!(is_valid == U256::zero());
+    }
+}
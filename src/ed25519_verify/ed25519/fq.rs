@@ -0,0 +1,7 @@
+use boojum::pairing::ff::*;
+
+// base field, Q = 0x7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffed
+#[derive(PrimeField)]
+#[PrimeFieldModulus = "57896044618658097711785492504343953926634992332820282019728792003956564819949"]
+#[PrimeFieldGenerator = "2"]
+pub struct Fq(FqRepr);
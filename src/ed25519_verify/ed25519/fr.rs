@@ -0,0 +1,7 @@
+use boojum::pairing::ff::*;
+
+// scalar field, R = 0x1000000000000000000000000000000014def9dea2f79cd65812631a5cf5d3ed
+#[derive(PrimeField)]
+#[PrimeFieldModulus = "7237005577332262213973186563042994240857116359379907606001950938285454250989"]
+#[PrimeFieldGenerator = "2"]
+pub struct Fr(FrRepr);
@@ -0,0 +1,177 @@
+use boojum::pairing::ff::BitIterator;
+use boojum::pairing::ff::*;
+
+pub mod fq;
+pub mod fr;
+
+use fq::*;
+use fr::*;
+
+// twisted Edwards curve -x^2 + y^2 = 1 + d * x^2 * y^2, birationally equivalent to Curve25519
+const CURVE_D: &'static str =
+    "37095705934669439343138083508754565189542113879843219016388785533085940283555";
+// generator of the prime order subgroup, as in RFC 8032
+const GENERATOR_X: &'static str =
+    "15112221349535400772501151409588531511454012693041857206046113283949847762202";
+const GENERATOR_Y: &'static str =
+    "46316835694926478169428394003475163141307993866256225615783033603165251855960";
+// square root of -1 that is used for point decompression
+const SQRT_MINUS_ONE: &'static str =
+    "19681161376707505956807079304988542015446066515923890162744021073123829784752";
+
+/// Affine point of edwards25519. Unlike short Weierstrass curves the neutral element
+/// is a regular point (0, 1), and addition formulas are complete since `d` is not a square
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct PointAffine {
+    pub(crate) x: Fq,
+    pub(crate) y: Fq,
+}
+
+static NAME_STR: &'static str = "Ed25519";
+
+impl ::std::fmt::Display for PointAffine {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "{}(x={}, y={})", NAME_STR, self.x, self.y)
+    }
+}
+
+impl PointAffine {
+    pub fn zero() -> Self {
+        Self {
+            x: Fq::zero(),
+            y: Fq::one(),
+        }
+    }
+
+    pub fn one() -> Self {
+        Self {
+            x: Fq::from_str(GENERATOR_X).unwrap(),
+            y: Fq::from_str(GENERATOR_Y).unwrap(),
+        }
+    }
+
+    pub fn d_coeff() -> Fq {
+        Fq::from_str(CURVE_D).unwrap()
+    }
+
+    pub fn sqrt_minus_one() -> Fq {
+        Fq::from_str(SQRT_MINUS_ONE).unwrap()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.x.is_zero() && self.y == Fq::one()
+    }
+
+    pub fn is_on_curve(&self) -> bool {
+        let mut x2 = self.x;
+        x2.square();
+        let mut y2 = self.y;
+        y2.square();
+
+        // y^2 - x^2
+        let mut lhs = y2;
+        lhs.sub_assign(&x2);
+
+        // 1 + d * x^2 * y^2
+        let mut rhs = Self::d_coeff();
+        rhs.mul_assign(&x2);
+        rhs.mul_assign(&y2);
+        rhs.add_assign(&Fq::one());
+
+        lhs == rhs
+    }
+
+    pub fn as_xy(&self) -> (&Fq, &Fq) {
+        (&self.x, &self.y)
+    }
+
+    pub fn into_xy_unchecked(self) -> (Fq, Fq) {
+        (self.x, self.y)
+    }
+
+    pub fn from_xy_checked(x: Fq, y: Fq) -> Option<Self> {
+        let point = Self { x, y };
+        if point.is_on_curve() {
+            Some(point)
+        } else {
+            None
+        }
+    }
+
+    pub fn negate(&mut self) {
+        self.x.negate();
+    }
+
+    pub fn add_assign(&mut self, other: &Self) {
+        // x3 = (x1 * y2 + y1 * x2) / (1 + d * x1 * x2 * y1 * y2)
+        // y3 = (y1 * y2 + x1 * x2) / (1 - d * x1 * x2 * y1 * y2)
+        let mut x1x2 = self.x;
+        x1x2.mul_assign(&other.x);
+        let mut y1y2 = self.y;
+        y1y2.mul_assign(&other.y);
+        let mut x1y2 = self.x;
+        x1y2.mul_assign(&other.y);
+        let mut y1x2 = self.y;
+        y1x2.mul_assign(&other.x);
+
+        let mut t = Self::d_coeff();
+        t.mul_assign(&x1x2);
+        t.mul_assign(&y1y2);
+
+        let mut x_denominator = Fq::one();
+        x_denominator.add_assign(&t);
+        let mut y_denominator = Fq::one();
+        y_denominator.sub_assign(&t);
+
+        // denominators are never zero for points on curve
+        let mut x = x1y2;
+        x.add_assign(&y1x2);
+        x.mul_assign(&x_denominator.inverse().unwrap());
+
+        let mut y = y1y2;
+        y.add_assign(&x1x2);
+        y.mul_assign(&y_denominator.inverse().unwrap());
+
+        self.x = x;
+        self.y = y;
+    }
+
+    pub fn double(&mut self) {
+        let tmp = *self;
+        self.add_assign(&tmp);
+    }
+
+    fn mul_bits<S: AsRef<[u64]>>(&self, bits: BitIterator<S>) -> PointAffine {
+        let mut res = PointAffine::zero();
+        for i in bits {
+            res.double();
+            if i {
+                res.add_assign(self)
+            }
+        }
+        res
+    }
+
+    pub fn mul<S: Into<<Fr as PrimeField>::Repr>>(&self, by: S) -> PointAffine {
+        let bits = BitIterator::new(by.into());
+        self.mul_bits(bits)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_generator_has_prime_order() {
+        let generator = PointAffine::one();
+        assert!(generator.is_on_curve());
+        assert!(!generator.is_zero());
+
+        let mut order_minus_one = Fr::one();
+        order_minus_one.negate();
+        let mut point = generator.mul(order_minus_one.into_repr());
+        point.add_assign(&generator);
+        assert!(point.is_zero());
+    }
+}
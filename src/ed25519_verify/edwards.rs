@@ -0,0 +1,160 @@
+use super::*;
+
+use boojum::pairing::ff::Field;
+
+/// Point of edwards25519 in extended twisted Edwards coordinates (X : Y : Z : T),
+/// where x = X / Z, y = Y / Z and x * y = T / Z. Since a = -1 is a square and d is not,
+/// the unified addition formula is complete, so no special handling of the neutral
+/// element or of doubling is required
+#[derive(Derivative)]
+#[derivative(Clone, Debug)]
+pub struct Ed25519ExtendedPoint<F: SmallField> {
+    pub x: Ed25519BaseNNField<F>,
+    pub y: Ed25519BaseNNField<F>,
+    pub z: Ed25519BaseNNField<F>,
+    pub t: Ed25519BaseNNField<F>,
+}
+
+impl<F: SmallField> Ed25519ExtendedPoint<F> {
+    pub fn zero<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        params: &Arc<Ed25519BaseNNFieldParams>,
+    ) -> Self {
+        let zero = Ed25519BaseNNField::allocated_constant(cs, Ed25519Fq::zero(), params);
+        let one = Ed25519BaseNNField::allocated_constant(cs, Ed25519Fq::one(), params);
+
+        Self {
+            x: zero.clone(),
+            y: one.clone(),
+            z: one,
+            t: zero,
+        }
+    }
+
+    /// Caller must ensure that the point is on curve
+    pub fn from_xy_unchecked<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        mut x: Ed25519BaseNNField<F>,
+        mut y: Ed25519BaseNNField<F>,
+    ) -> Self {
+        let params = x.params.clone();
+        let z = Ed25519BaseNNField::allocated_constant(cs, Ed25519Fq::one(), &params);
+        let mut t = x.mul(cs, &mut y);
+        t.normalize(cs);
+
+        Self { x, y, z, t }
+    }
+
+    pub fn negated<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        let mut x = self.x.negated(cs);
+        x.normalize(cs);
+        let mut t = self.t.negated(cs);
+        t.normalize(cs);
+
+        Self {
+            x,
+            y: self.y.clone(),
+            z: self.z.clone(),
+            t,
+        }
+    }
+
+    // add-2008-hwcd-3, where `curve_d2` is 2 * d
+    pub fn add<CS: ConstraintSystem<F>>(
+        &mut self,
+        cs: &mut CS,
+        other: &mut Self,
+        curve_d2: &mut Ed25519BaseNNField<F>,
+    ) -> Self {
+        let mut y1_minus_x1 = self.y.sub(cs, &mut self.x);
+        let mut y2_minus_x2 = other.y.sub(cs, &mut other.x);
+        let mut a = y1_minus_x1.mul(cs, &mut y2_minus_x2);
+
+        let mut y1_plus_x1 = self.y.add(cs, &mut self.x);
+        let mut y2_plus_x2 = other.y.add(cs, &mut other.x);
+        let mut b = y1_plus_x1.mul(cs, &mut y2_plus_x2);
+
+        let mut c = self.t.mul(cs, curve_d2);
+        let mut c = c.mul(cs, &mut other.t);
+
+        let mut d = self.z.mul(cs, &mut other.z);
+        let mut d = d.double(cs);
+
+        let mut e = b.sub(cs, &mut a);
+        let mut f = d.sub(cs, &mut c);
+        let mut g = d.add(cs, &mut c);
+        let mut h = b.add(cs, &mut a);
+
+        Self::from_efgh(cs, &mut e, &mut f, &mut g, &mut h)
+    }
+
+    // dbl-2008-hwcd for a = -1
+    pub fn double<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        let mut a = self.x.square(cs);
+        let mut b = self.y.square(cs);
+        let mut c = self.z.square(cs);
+        let mut c = c.double(cs);
+
+        let mut h = a.add(cs, &mut b);
+        let mut x1_plus_y1 = self.x.add(cs, &mut self.y);
+        let mut x1_plus_y1_squared = x1_plus_y1.square(cs);
+        let mut e = h.sub(cs, &mut x1_plus_y1_squared);
+        let mut g = a.sub(cs, &mut b);
+        let mut f = c.add(cs, &mut g);
+
+        Self::from_efgh(cs, &mut e, &mut f, &mut g, &mut h)
+    }
+
+    fn from_efgh<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        e: &mut Ed25519BaseNNField<F>,
+        f: &mut Ed25519BaseNNField<F>,
+        g: &mut Ed25519BaseNNField<F>,
+        h: &mut Ed25519BaseNNField<F>,
+    ) -> Self {
+        let mut x = e.mul(cs, f);
+        x.normalize(cs);
+        let mut y = g.mul(cs, h);
+        y.normalize(cs);
+        let mut z = f.mul(cs, g);
+        z.normalize(cs);
+        let mut t = e.mul(cs, h);
+        t.normalize(cs);
+
+        Self { x, y, z, t }
+    }
+
+    /// Checks that the point is equal to the affine point (x, y), that is X = x * Z and Y = y * Z
+    pub fn equals_affine<CS: ConstraintSystem<F>>(
+        &mut self,
+        cs: &mut CS,
+        x: &mut Ed25519BaseNNField<F>,
+        y: &mut Ed25519BaseNNField<F>,
+    ) -> Boolean<F> {
+        let mut x_by_z = x.mul(cs, &mut self.z);
+        x_by_z.normalize(cs);
+        let mut y_by_z = y.mul(cs, &mut self.z);
+        y_by_z.normalize(cs);
+
+        let x_is_equal = NonNativeFieldOverU16::equals(cs, &mut self.x, &mut x_by_z);
+        let y_is_equal = NonNativeFieldOverU16::equals(cs, &mut self.y, &mut y_by_z);
+
+        Boolean::multi_and(cs, &[x_is_equal, y_is_equal])
+    }
+}
+
+impl<F: SmallField> Selectable<F> for Ed25519ExtendedPoint<F> {
+    fn conditionally_select<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        flag: Boolean<F>,
+        a: &Self,
+        b: &Self,
+    ) -> Self {
+        let x = NonNativeFieldOverU16::conditionally_select(cs, flag, &a.x, &b.x);
+        let y = NonNativeFieldOverU16::conditionally_select(cs, flag, &a.y, &b.y);
+        let z = NonNativeFieldOverU16::conditionally_select(cs, flag, &a.z, &b.z);
+        let t = NonNativeFieldOverU16::conditionally_select(cs, flag, &a.t, &b.t);
+
+        Self { x, y, z, t }
+    }
+}
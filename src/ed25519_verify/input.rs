@@ -0,0 +1,51 @@
+use std::collections::VecDeque;
+
+use super::*;
+use crate::base_structures::precompile_input_outputs::*;
+use crate::base_structures::vm_state::*;
+use boojum::cs::Variable;
+use boojum::gadgets::queue::*;
+use boojum::gadgets::traits::allocatable::CSAllocatable;
+use boojum::gadgets::traits::allocatable::CSPlaceholder;
+use boojum::gadgets::traits::encodable::CircuitVarLengthEncodable;
+
+use boojum::gadgets::traits::auxiliary::PrettyComparison;
+
+#[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
+#[derivative(Clone, Copy, Debug)]
+#[DerivePrettyComparison("true")]
+pub struct Ed25519VerifyCircuitFSMInputOutput<F: SmallField> {
+    pub log_queue_state: QueueState<F, QUEUE_STATE_WIDTH>,
+    pub memory_queue_state: QueueState<F, FULL_SPONGE_QUEUE_STATE_WIDTH>,
+}
+
+impl<F: SmallField> CSPlaceholder<F> for Ed25519VerifyCircuitFSMInputOutput<F> {
+    fn placeholder<CS: ConstraintSystem<F>>(cs: &mut CS) -> Self {
+        Self {
+            log_queue_state: QueueState::<F, QUEUE_STATE_WIDTH>::placeholder(cs),
+            memory_queue_state: QueueState::<F, FULL_SPONGE_QUEUE_STATE_WIDTH>::placeholder(cs),
+        }
+    }
+}
+
+pub type Ed25519VerifyCircuitInputOutput<F> = ClosedFormInput<
+    F,
+    Ed25519VerifyCircuitFSMInputOutput<F>,
+    PrecompileFunctionInputData<F>,
+    PrecompileFunctionOutputData<F>,
+>;
+pub type Ed25519VerifyCircuitInputOutputWitness<F> = ClosedFormInputWitness<
+    F,
+    Ed25519VerifyCircuitFSMInputOutput<F>,
+    PrecompileFunctionInputData<F>,
+    PrecompileFunctionOutputData<F>,
+>;
+
+#[derive(Derivative, serde::Serialize, serde::Deserialize)]
+#[derivative(Clone, Debug, Default)]
+#[serde(bound = "")]
+pub struct Ed25519VerifyCircuitInstanceWitness<F: SmallField> {
+    pub closed_form_input: Ed25519VerifyCircuitInputOutputWitness<F>,
+    pub requests_queue_witness: CircuitQueueRawWitness<F, LogQuery<F>, 4, LOG_QUERY_PACKED_WIDTH>,
+    pub memory_reads_witness: VecDeque<[U256; MEMORY_QUERIES_PER_CALL]>,
+}
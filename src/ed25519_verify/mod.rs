@@ -0,0 +1,94 @@
+use super::*;
+use crate::base_structures::log_query::*;
+use crate::base_structures::memory_query::*;
+
+use crate::ethereum_types::U256;
+
+use crate::fsm_input_output::*;
+
+use boojum::cs::traits::cs::ConstraintSystem;
+use boojum::field::SmallField;
+use boojum::gadgets::boolean::Boolean;
+
+use boojum::gadgets::non_native_field::implementations::*;
+
+use boojum::gadgets::queue::QueueState;
+
+use boojum::gadgets::traits::selectable::Selectable;
+use boojum::gadgets::traits::witnessable::WitnessHookable;
+
+use cs_derive::*;
+use std::sync::Arc;
+
+use crate::base_structures::precompile_input_outputs::formal_precompile_address;
+use zkevm_opcode_defs::ethereum_types::H160;
+
+use crate::base_structures::precompile_input_outputs::*;
+use crate::demux_log_queue::DemuxOutput;
+use crate::precompile_registry::PrecompileCircuit;
+use crate::scheduler::auxiliary::BaseLayerCircuitType;
+
+pub mod input;
+pub use self::input::*;
+
+pub mod ed25519;
+pub mod edwards;
+pub mod fixed_base_mul_table;
+pub mod sha512;
+
+pub use self::fixed_base_mul_table::*;
+
+// message, R, s and public key, where all but the message are in the encoding of RFC 8032.
+// Message is always 32 bytes long
+pub const MEMORY_QUERIES_PER_CALL: usize = 4;
+
+// placed right after the secp256r1 signature verification precompile
+pub const ED25519_VERIFY_PRECOMPILE_ADDRESS: u16 = 0x101;
+pub const ED25519_VERIFY_PRECOMPILE_FORMAL_ADDRESS: H160 =
+    formal_precompile_address(ED25519_VERIFY_PRECOMPILE_ADDRESS);
+
+pub struct Ed25519VerifyPrecompileCircuit;
+
+impl PrecompileCircuit for Ed25519VerifyPrecompileCircuit {
+    const NAME: &'static str = "ed25519_verify";
+    const DEMUX_OUTPUT: DemuxOutput = DemuxOutput::Ed25519Verify;
+    const CIRCUIT_TYPE: BaseLayerCircuitType = BaseLayerCircuitType::Ed25519VerifyPrecompile;
+
+    type InputData<F: SmallField> = PrecompileFunctionInputData<F>;
+    type OutputData<F: SmallField> = PrecompileFunctionOutputData<F>;
+
+    fn formal_address() -> H160 {
+        ED25519_VERIFY_PRECOMPILE_FORMAL_ADDRESS
+    }
+}
+
+pub mod baseline;
+
+// characteristics of the base field for ed25519 curve
+use self::ed25519::fq::Fq as Ed25519Fq;
+// order of the prime order subgroup of ed25519 curve
+use self::ed25519::fr::Fr as Ed25519Fr;
+// some affine point
+use self::ed25519::PointAffine as Ed25519Affine;
+
+const BASE_FIELD_REPR_LIMBS: usize = 17;
+const SCALAR_FIELD_REPR_LIMBS: usize = 17;
+const BASE_FIELD_CANONICAL_REPR_LIMBS: usize = 16;
+const SCALAR_FIELD_CANONICAL_REPR_LIMBS: usize = 16;
+
+type Ed25519BaseNNFieldParams = NonNativeFieldOverU16Params<Ed25519Fq, 17>;
+type Ed25519ScalarNNFieldParams = NonNativeFieldOverU16Params<Ed25519Fr, 17>;
+
+type Ed25519BaseNNField<F> = NonNativeFieldOverU16<F, Ed25519Fq, 17>;
+type Ed25519ScalarNNField<F> = NonNativeFieldOverU16<F, Ed25519Fr, 17>;
+
+fn ed25519_base_field_params() -> Ed25519BaseNNFieldParams {
+    NonNativeFieldOverU16Params::create()
+}
+
+fn ed25519_scalar_field_params() -> Ed25519ScalarNNFieldParams {
+    NonNativeFieldOverU16Params::create()
+}
+
+// re-exports for integration
+pub use self::baseline::{ed25519_verify_function_entry_point, Ed25519VerifyPrecompileCallParams};
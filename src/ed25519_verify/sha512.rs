@@ -0,0 +1,323 @@
+use super::*;
+
+use boojum::cs::Variable;
+use boojum::gadgets::num::Num;
+use boojum::gadgets::u256::UInt256;
+use boojum::gadgets::u32::UInt32;
+
+pub const SHA512_STATE_WIDTH: usize = 8;
+pub const SHA512_BLOCK_WORDS: usize = 16;
+pub const SHA512_NUM_ROUNDS: usize = 80;
+
+pub const SHA512_IV: [u64; SHA512_STATE_WIDTH] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+const ROUND_CONSTANTS: [u64; SHA512_NUM_ROUNDS] = [
+    0x428a2f98d728ae22,
+    0x7137449123ef65cd,
+    0xb5c0fbcfec4d3b2f,
+    0xe9b5dba58189dbbc,
+    0x3956c25bf348b538,
+    0x59f111f1b605d019,
+    0x923f82a4af194f9b,
+    0xab1c5ed5da6d8118,
+    0xd807aa98a3030242,
+    0x12835b0145706fbe,
+    0x243185be4ee4b28c,
+    0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f,
+    0x80deb1fe3b1696b1,
+    0x9bdc06a725c71235,
+    0xc19bf174cf692694,
+    0xe49b69c19ef14ad2,
+    0xefbe4786384f25e3,
+    0x0fc19dc68b8cd5b5,
+    0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275,
+    0x4a7484aa6ea6e483,
+    0x5cb0a9dcbd41fbd4,
+    0x76f988da831153b5,
+    0x983e5152ee66dfab,
+    0xa831c66d2db43210,
+    0xb00327c898fb213f,
+    0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2,
+    0xd5a79147930aa725,
+    0x06ca6351e003826f,
+    0x142929670a0e6e70,
+    0x27b70a8546d22ffc,
+    0x2e1b21385c26c926,
+    0x4d2c6dfc5ac42aed,
+    0x53380d139d95b3df,
+    0x650a73548baf63de,
+    0x766a0abb3c77b2a8,
+    0x81c2c92e47edaee6,
+    0x92722c851482353b,
+    0xa2bfe8a14cf10364,
+    0xa81a664bbc423001,
+    0xc24b8b70d0f89791,
+    0xc76c51a30654be30,
+    0xd192e819d6ef5218,
+    0xd69906245565a910,
+    0xf40e35855771202a,
+    0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8,
+    0x1e376c085141ab53,
+    0x2748774cdf8eeb99,
+    0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63,
+    0x4ed8aa4ae3418acb,
+    0x5b9cca4f7763e373,
+    0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc,
+    0x78a5636f43172f60,
+    0x84c87814a1f0ab72,
+    0x8cc702081a6439ec,
+    0x90befffa23631e28,
+    0xa4506cebde82bde9,
+    0xbef9a3f7b2c67915,
+    0xc67178f2e372532b,
+    0xca273eceea26619c,
+    0xd186b8c721c0c207,
+    0xeada7dd6cde0eb1e,
+    0xf57d4f7fee6ed178,
+    0x06f067aa72176fba,
+    0x0a637dc5a2c898a6,
+    0x113f9804bef90dae,
+    0x1b710b35131c471b,
+    0x28db77f523047d84,
+    0x32caab7b40c72493,
+    0x3c9ebe0a15c9bebc,
+    0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6,
+    0x597f299cfc657e2a,
+    0x5fcb6fab3ad6faec,
+    0x6c44198c4a475817,
+];
+
+// 64 bit words are passed around as [low, high] pairs of u32, and internally
+// all the bitwise operations are performed over the little-endian bit decomposition
+type WordBits<F> = [Boolean<F>; 64];
+
+pub fn sha512_ivs_as_uint32_pairs<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+) -> [[UInt32<F>; 2]; SHA512_STATE_WIDTH] {
+    SHA512_IV.map(|el| {
+        [
+            UInt32::allocated_constant(cs, el as u32),
+            UInt32::allocated_constant(cs, (el >> 32) as u32),
+        ]
+    })
+}
+
+// splits the 256 bit word into big-endian 64 bit words for SHA-512
+pub fn u256_into_sha512_words<F: SmallField>(word: &UInt256<F>) -> [[UInt32<F>; 2]; 4] {
+    std::array::from_fn(|i| [word.inner[6 - 2 * i], word.inner[7 - 2 * i]])
+}
+
+pub fn sha512_words_into_u256<F: SmallField>(words: &[[UInt32<F>; 2]]) -> UInt256<F> {
+    assert_eq!(words.len(), 4);
+    let mut result = UInt256 {
+        inner: [words[0][0]; 8],
+    };
+    for (i, [low, high]) in words.iter().enumerate() {
+        result.inner[6 - 2 * i] = *low;
+        result.inner[7 - 2 * i] = *high;
+    }
+
+    result
+}
+
+fn word_into_bits<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    word: [UInt32<F>; 2],
+) -> WordBits<F> {
+    let [low, high] = word;
+    let low = Num::from_variable(low.get_variable()).spread_into_bits::<_, 32>(cs);
+    let high = Num::from_variable(high.get_variable()).spread_into_bits::<_, 32>(cs);
+
+    std::array::from_fn(|i| if i < 32 { low[i] } else { high[i - 32] })
+}
+
+fn bits_into_word<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    bits: &WordBits<F>,
+) -> [UInt32<F>; 2] {
+    let [low, high] = [&bits[..32], &bits[32..]].map(|half| {
+        let lc: Vec<_> = half
+            .iter()
+            .enumerate()
+            .map(|(i, bit)| (bit.get_variable(), F::SHIFTS[i]))
+            .collect();
+        let value = Num::linear_combination(cs, &lc);
+
+        // it's a u32 by construction
+        unsafe { UInt32::from_variable_unchecked(value.get_variable()) }
+    });
+
+    [low, high]
+}
+
+fn constant_word<F: SmallField, CS: ConstraintSystem<F>>(cs: &mut CS, value: u64) -> WordBits<F> {
+    std::array::from_fn(|i| Boolean::allocated_constant(cs, (value >> i) & 1 == 1))
+}
+
+fn rotate_right<F: SmallField>(word: &WordBits<F>, shift: usize) -> WordBits<F> {
+    std::array::from_fn(|i| word[(i + shift) % 64])
+}
+
+fn shift_right<F: SmallField>(word: &WordBits<F>, shift: usize, zero: Boolean<F>) -> WordBits<F> {
+    std::array::from_fn(|i| {
+        if i + shift < 64 {
+            word[i + shift]
+        } else {
+            zero
+        }
+    })
+}
+
+fn xor_many<F: SmallField, CS: ConstraintSystem<F>, const N: usize>(
+    cs: &mut CS,
+    words: [WordBits<F>; N],
+) -> WordBits<F> {
+    std::array::from_fn(|i| {
+        let mut result = words[0][i];
+        for word in words[1..].iter() {
+            result = result.xor(cs, word[i]);
+        }
+
+        result
+    })
+}
+
+// adds up to 7 words modulo 2^64. Every half is summed separately, so the sum
+// fits into 35 bits and the carry from the low half fits into 3 bits
+fn add_many<F: SmallField, CS: ConstraintSystem<F>, const N: usize>(
+    cs: &mut CS,
+    words: [&WordBits<F>; N],
+) -> WordBits<F> {
+    assert!(N <= 7);
+
+    let mut low_lc: Vec<(Variable, F)> = Vec::with_capacity(N * 32);
+    let mut high_lc: Vec<(Variable, F)> = Vec::with_capacity(N * 32 + 3);
+    for word in words.iter() {
+        for i in 0..32 {
+            low_lc.push((word[i].get_variable(), F::SHIFTS[i]));
+            high_lc.push((word[i + 32].get_variable(), F::SHIFTS[i]));
+        }
+    }
+
+    let low = Num::linear_combination(cs, &low_lc);
+    let low_bits = low.spread_into_bits::<_, 35>(cs);
+    for (i, carry_bit) in low_bits[32..].iter().enumerate() {
+        high_lc.push((carry_bit.get_variable(), F::SHIFTS[i]));
+    }
+
+    let high = Num::linear_combination(cs, &high_lc);
+    let high_bits = high.spread_into_bits::<_, 35>(cs);
+
+    std::array::from_fn(|i| {
+        if i < 32 {
+            low_bits[i]
+        } else {
+            high_bits[i - 32]
+        }
+    })
+}
+
+/// Compresses a single 128 byte block into the state. Block and state words are big-endian
+/// 64 bit words, represented as [low, high] pairs of u32
+pub fn sha512_round_function<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    state: &mut [[UInt32<F>; 2]; SHA512_STATE_WIDTH],
+    block: &[[UInt32<F>; 2]; SHA512_BLOCK_WORDS],
+) {
+    let zero = Boolean::allocated_constant(cs, false);
+
+    // message schedule
+    let mut schedule = Vec::with_capacity(SHA512_NUM_ROUNDS);
+    for word in block.iter() {
+        schedule.push(word_into_bits(cs, *word));
+    }
+    for t in SHA512_BLOCK_WORDS..SHA512_NUM_ROUNDS {
+        let w_15 = schedule[t - 15];
+        let w_2 = schedule[t - 2];
+        let s0 = xor_many(
+            cs,
+            [
+                rotate_right(&w_15, 1),
+                rotate_right(&w_15, 8),
+                shift_right(&w_15, 7, zero),
+            ],
+        );
+        let s1 = xor_many(
+            cs,
+            [
+                rotate_right(&w_2, 19),
+                rotate_right(&w_2, 61),
+                shift_right(&w_2, 6, zero),
+            ],
+        );
+        let w = add_many(cs, [&schedule[t - 16], &s0, &schedule[t - 7], &s1]);
+        schedule.push(w);
+    }
+
+    let initial_state = state.map(|el| word_into_bits(cs, el));
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = initial_state;
+
+    for t in 0..SHA512_NUM_ROUNDS {
+        let sigma_1 = xor_many(
+            cs,
+            [
+                rotate_right(&e, 14),
+                rotate_right(&e, 18),
+                rotate_right(&e, 41),
+            ],
+        );
+        // ch(e, f, g) = if e { f } else { g }
+        let ch: WordBits<F> =
+            std::array::from_fn(|i| Boolean::conditionally_select(cs, e[i], &f[i], &g[i]));
+        let round_constant = constant_word(cs, ROUND_CONSTANTS[t]);
+        let t1 = add_many(cs, [&h, &sigma_1, &ch, &round_constant, &schedule[t]]);
+
+        let sigma_0 = xor_many(
+            cs,
+            [
+                rotate_right(&a, 28),
+                rotate_right(&a, 34),
+                rotate_right(&a, 39),
+            ],
+        );
+        // maj(a, b, c) = if a != b { c } else { a }
+        let maj: WordBits<F> = std::array::from_fn(|i| {
+            let a_xor_b = a[i].xor(cs, b[i]);
+            Boolean::conditionally_select(cs, a_xor_b, &c[i], &a[i])
+        });
+        let t2 = add_many(cs, [&sigma_0, &maj]);
+
+        h = g;
+        g = f;
+        f = e;
+        e = add_many(cs, [&d, &t1]);
+        d = c;
+        c = b;
+        b = a;
+        a = add_many(cs, [&t1, &t2]);
+    }
+
+    for (dst, (initial, result)) in state
+        .iter_mut()
+        .zip(initial_state.iter().zip([a, b, c, d, e, f, g, h].iter()))
+    {
+        let sum = add_many(cs, [initial, result]);
+        *dst = bits_into_word(cs, &sum);
+    }
+}
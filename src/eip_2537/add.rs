@@ -0,0 +1,282 @@
+use super::*;
+
+use super::baseline::bls12_381_per_call_function_entry_point;
+use super::curves::*;
+use crate::fsm_input_output::circuit_inputs::INPUT_OUTPUT_COMMITMENT_LENGTH;
+
+use boojum::algebraic_props::round_function::AlgebraicRoundFunction;
+use boojum::gadgets::num::Num;
+use boojum::gadgets::traits::allocatable::CSAllocatableExt;
+use boojum::gadgets::traits::round_function::CircuitRoundFunction;
+
+// Addition doesn't require points to be in the prime order subgroup, only on curve
+
+fn bls12_381_g1add_inner<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    words: &[UInt256<F>; 2 * G1_POINT_WORDS],
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> (Boolean<F>, [UInt256<F>; G1_POINT_WORDS]) {
+    let p_words = std::array::from_fn(|i| words[i]);
+    let q_words = std::array::from_fn(|i| words[G1_POINT_WORDS + i]);
+    let (p, p_is_infinity, p_is_invalid) =
+        bls12_381_validate_and_mask_g1_point(cs, &p_words, false, params);
+    let (q, q_is_infinity, q_is_invalid) =
+        bls12_381_validate_and_mask_g1_point(cs, &q_words, false, params);
+
+    // complete formulas handle P == Q and P == -Q, and points at infinity are just never added
+    let mut acc = Bls12_381G1ProjectivePoint::zero(cs, params);
+    for (point, is_infinity) in [(p, p_is_infinity), (q, q_is_infinity)].into_iter() {
+        let sum = acc.add_mixed(cs, &mut point.clone());
+        acc = Selectable::conditionally_select(cs, is_infinity, &acc, &sum);
+    }
+
+    let result = bls12_381_encode_g1_point(cs, &mut acc);
+
+    let any_exception = Boolean::multi_or(cs, &[p_is_invalid, q_is_invalid]);
+    let result = result.map(|el| el.mask_negated(cs, any_exception));
+    let success = any_exception.negated(cs);
+
+    (success, result)
+}
+
+fn bls12_381_g2add_inner<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    words: &[UInt256<F>; 2 * G2_POINT_WORDS],
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> (Boolean<F>, [UInt256<F>; G2_POINT_WORDS]) {
+    let p_words = std::array::from_fn(|i| words[i]);
+    let q_words = std::array::from_fn(|i| words[G2_POINT_WORDS + i]);
+    let (p, p_is_infinity, p_is_invalid) =
+        bls12_381_validate_and_mask_g2_point(cs, &p_words, false, params);
+    let (q, q_is_infinity, q_is_invalid) =
+        bls12_381_validate_and_mask_g2_point(cs, &q_words, false, params);
+
+    let mut curve_b3 = g2_curve_b3(cs, params);
+    let mut acc = Bls12_381G2ProjectivePoint::zero(cs, params);
+    for (point, is_infinity) in [(p, p_is_infinity), (q, q_is_infinity)].into_iter() {
+        let mut point = Bls12_381G2ProjectivePoint::from_affine(cs, &point, params);
+        let sum = acc.add(cs, &mut point, &mut curve_b3);
+        acc = Bls12_381G2ProjectivePoint::conditionally_select(cs, is_infinity, &acc, &sum);
+    }
+
+    let result = bls12_381_encode_g2_point(cs, &mut acc, params);
+
+    let any_exception = Boolean::multi_or(cs, &[p_is_invalid, q_is_invalid]);
+    let result = result.map(|el| el.mask_negated(cs, any_exception));
+    let success = any_exception.negated(cs);
+
+    (success, result)
+}
+
+pub fn bls12_381_g1add_function_entry_point<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    witness: Bls12_381CircuitInstanceWitness<F>,
+    round_function: &R,
+    limit: usize,
+) -> [Num<F>; INPUT_OUTPUT_COMMITMENT_LENGTH]
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    let params = Arc::new(bls12_381_base_field_params());
+
+    bls12_381_per_call_function_entry_point(
+        cs,
+        witness,
+        round_function,
+        limit,
+        BLS12_381_G1ADD_PRECOMPILE_FORMAL_ADDRESS,
+        |cs: &mut CS, words: &[UInt256<F>; 2 * G1_POINT_WORDS]| {
+            bls12_381_g1add_inner(cs, words, &params)
+        },
+    )
+}
+
+pub fn bls12_381_g2add_function_entry_point<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    witness: Bls12_381CircuitInstanceWitness<F>,
+    round_function: &R,
+    limit: usize,
+) -> [Num<F>; INPUT_OUTPUT_COMMITMENT_LENGTH]
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    let params = Arc::new(bls12_381_base_field_params());
+
+    bls12_381_per_call_function_entry_point(
+        cs,
+        witness,
+        round_function,
+        limit,
+        BLS12_381_G2ADD_PRECOMPILE_FORMAL_ADDRESS,
+        |cs: &mut CS, words: &[UInt256<F>; 2 * G2_POINT_WORDS]| {
+            bls12_381_g2add_inner(cs, words, &params)
+        },
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test_utils::*;
+    use super::*;
+
+    use boojum::gadgets::traits::allocatable::CSAllocatable;
+    use boojum::pairing::bls12_381::Fr as Bls12_381Fr;
+    use boojum::pairing::ff::PrimeField;
+    use boojum::pairing::{GenericCurveAffine, GenericCurveProjective};
+    use boojum::worker::Worker;
+
+    fn run_g1add(input: [U256; 2 * G1_POINT_WORDS]) -> (bool, [U256; G1_POINT_WORDS]) {
+        let mut owned_cs = create_cs(1 << 21);
+        let cs = &mut owned_cs;
+
+        let params = Arc::new(bls12_381_base_field_params());
+        let words = input.map(|el| UInt256::allocate(cs, el));
+        let (success, result) = bls12_381_g1add_inner(cs, &words, &params);
+
+        let success = success.witness_hook(&*cs)().unwrap();
+        let result = result.map(|el| el.witness_hook(&*cs)().unwrap());
+
+        cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        let worker = Worker::new();
+        assert!(cs.check_if_satisfied(&worker));
+
+        (success, result)
+    }
+
+    fn run_g2add(input: [U256; 2 * G2_POINT_WORDS]) -> (bool, [U256; G2_POINT_WORDS]) {
+        let mut owned_cs = create_cs(1 << 22);
+        let cs = &mut owned_cs;
+
+        let params = Arc::new(bls12_381_base_field_params());
+        let words = input.map(|el| UInt256::allocate(cs, el));
+        let (success, result) = bls12_381_g2add_inner(cs, &words, &params);
+
+        let success = success.witness_hook(&*cs)().unwrap();
+        let result = result.map(|el| el.witness_hook(&*cs)().unwrap());
+
+        cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        let worker = Worker::new();
+        assert!(cs.check_if_satisfied(&worker));
+
+        (success, result)
+    }
+
+    fn concat<const N: usize, const M: usize>(a: [U256; N], b: [U256; N]) -> [U256; M] {
+        assert_eq!(2 * N, M);
+        std::array::from_fn(|i| if i < N { a[i] } else { b[i - N] })
+    }
+
+    #[test]
+    fn test_bls12_381_g1add() {
+        let g = Bls12_381G1Affine::one();
+        let two_g = g
+            .mul(Bls12_381Fr::from_str("2").unwrap().into_repr())
+            .into_affine();
+        let three_g = g
+            .mul(Bls12_381Fr::from_str("3").unwrap().into_repr())
+            .into_affine();
+        let mut minus_g = g;
+        minus_g.negate();
+
+        // generic case
+        let (success, result) = run_g1add(concat(g1_into_u256_words(g), g1_into_u256_words(two_g)));
+        assert!(success);
+        assert_eq!(result, g1_into_u256_words(three_g));
+
+        // doubling
+        let (success, result) = run_g1add(concat(g1_into_u256_words(g), g1_into_u256_words(g)));
+        assert!(success);
+        assert_eq!(result, g1_into_u256_words(two_g));
+
+        // P + (-P) = O
+        let (success, result) =
+            run_g1add(concat(g1_into_u256_words(g), g1_into_u256_words(minus_g)));
+        assert!(success);
+        assert_eq!(result, [U256::zero(); G1_POINT_WORDS]);
+
+        // O + P = P
+        let (success, result) = run_g1add(concat(
+            [U256::zero(); G1_POINT_WORDS],
+            g1_into_u256_words(two_g),
+        ));
+        assert!(success);
+        assert_eq!(result, g1_into_u256_words(two_g));
+    }
+
+    #[test]
+    fn test_bls12_381_g1add_invalid_point() {
+        let g = g1_into_u256_words(Bls12_381G1Affine::one());
+
+        // not on curve
+        let mut not_on_curve = g;
+        not_on_curve[3] = not_on_curve[3] + U256::one();
+        let (success, result) = run_g1add(concat(not_on_curve, g));
+        assert!(!success);
+        assert_eq!(result, [U256::zero(); G1_POINT_WORDS]);
+
+        // top bytes of the coordinate are not zero
+        let mut not_padded = g;
+        not_padded[0] = not_padded[0] + (U256::one() << 255);
+        let (success, result) = run_g1add(concat(g, not_padded));
+        assert!(!success);
+        assert_eq!(result, [U256::zero(); G1_POINT_WORDS]);
+    }
+
+    #[test]
+    fn test_bls12_381_g2add() {
+        let g = Bls12_381G2Affine::one();
+        let two_g = g
+            .mul(Bls12_381Fr::from_str("2").unwrap().into_repr())
+            .into_affine();
+        let three_g = g
+            .mul(Bls12_381Fr::from_str("3").unwrap().into_repr())
+            .into_affine();
+        let mut minus_g = g;
+        minus_g.negate();
+
+        let (success, result) = run_g2add(concat(g2_into_u256_words(g), g2_into_u256_words(two_g)));
+        assert!(success);
+        assert_eq!(result, g2_into_u256_words(three_g));
+
+        let (success, result) = run_g2add(concat(g2_into_u256_words(g), g2_into_u256_words(g)));
+        assert!(success);
+        assert_eq!(result, g2_into_u256_words(two_g));
+
+        let (success, result) =
+            run_g2add(concat(g2_into_u256_words(g), g2_into_u256_words(minus_g)));
+        assert!(success);
+        assert_eq!(result, [U256::zero(); G2_POINT_WORDS]);
+
+        let (success, result) = run_g2add(concat(
+            g2_into_u256_words(two_g),
+            [U256::zero(); G2_POINT_WORDS],
+        ));
+        assert!(success);
+        assert_eq!(result, g2_into_u256_words(two_g));
+
+        // not on curve
+        let mut not_on_curve = g2_into_u256_words(g);
+        not_on_curve[7] = not_on_curve[7] + U256::one();
+        let (success, result) = run_g2add(concat(g2_into_u256_words(g), not_on_curve));
+        assert!(!success);
+        assert_eq!(result, [U256::zero(); G2_POINT_WORDS]);
+    }
+}
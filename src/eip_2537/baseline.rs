@@ -0,0 +1,233 @@
+use super::*;
+
+use crate::base_structures::precompile_input_outputs::PrecompileFunctionOutputData;
+use crate::demux_log_queue::StorageLogQueue;
+use crate::fsm_input_output::circuit_inputs::INPUT_OUTPUT_COMMITMENT_LENGTH;
+
+use boojum::algebraic_props::round_function::AlgebraicRoundFunction;
+use boojum::gadgets::num::Num;
+use boojum::gadgets::queue::CircuitQueueWitness;
+use boojum::gadgets::traits::allocatable::CSAllocatableExt;
+use boojum::gadgets::traits::round_function::CircuitRoundFunction;
+use boojum::gadgets::u160::UInt160;
+use boojum::gadgets::u8::UInt8;
+
+use std::sync::RwLock;
+use zkevm_opcode_defs::system_params::PRECOMPILE_AUX_BYTE;
+
+/// Entry point shared by the precompiles that process one call per cycle: it reads `INPUT_WORDS` words,
+/// and writes the success flag followed by `OUTPUT_WORDS` words returned by `call_inner`.
+/// Output words must be masked to zero by `call_inner` if the call is not successful
+pub(crate) fn bls12_381_per_call_function_entry_point<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+    const INPUT_WORDS: usize,
+    const OUTPUT_WORDS: usize,
+>(
+    cs: &mut CS,
+    witness: Bls12_381CircuitInstanceWitness<F>,
+    round_function: &R,
+    limit: usize,
+    precompile_formal_address: H160,
+    mut call_inner: impl FnMut(
+        &mut CS,
+        &[UInt256<F>; INPUT_WORDS],
+    ) -> (Boolean<F>, [UInt256<F>; OUTPUT_WORDS]),
+) -> [Num<F>; INPUT_OUTPUT_COMMITMENT_LENGTH]
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    assert!(limit <= u32::MAX as usize);
+
+    let Bls12_381CircuitInstanceWitness {
+        closed_form_input,
+        requests_queue_witness,
+        memory_reads_witness,
+    } = witness;
+
+    let precompile_address = UInt160::allocated_constant(cs, precompile_formal_address);
+    let aux_byte_for_precompile = UInt8::allocated_constant(cs, PRECOMPILE_AUX_BYTE);
+
+    let mut structured_input =
+        Bls12_381CircuitInputOutput::alloc_ignoring_outputs(cs, closed_form_input.clone());
+    let start_flag = structured_input.start_flag;
+
+    let requests_queue_state_from_input = structured_input.observable_input.initial_log_queue_state;
+
+    // it must be trivial
+    requests_queue_state_from_input.enforce_trivial_head(cs);
+
+    let requests_queue_state_from_fsm = structured_input.hidden_fsm_input.log_queue_state;
+
+    let requests_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &requests_queue_state_from_input,
+        &requests_queue_state_from_fsm,
+    );
+
+    let memory_queue_state_from_input =
+        structured_input.observable_input.initial_memory_queue_state;
+
+    // it must be trivial
+    memory_queue_state_from_input.enforce_trivial_head(cs);
+
+    let memory_queue_state_from_fsm = structured_input.hidden_fsm_input.memory_queue_state;
+
+    let memory_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &memory_queue_state_from_input,
+        &memory_queue_state_from_fsm,
+    );
+
+    let mut requests_queue = StorageLogQueue::<F, R>::from_state(cs, requests_queue_state);
+    let queue_witness = CircuitQueueWitness::from_inner_witness(requests_queue_witness);
+    requests_queue.witness = Arc::new(queue_witness);
+
+    let mut memory_queue = MemoryQueue::<F, R>::from_state(cs, memory_queue_state);
+
+    let one_u32 = UInt32::allocated_constant(cs, 1u32);
+    let zero_u256 = UInt256::zero(cs);
+    let boolean_false = Boolean::allocated_constant(cs, false);
+    let boolean_true = Boolean::allocated_constant(cs, true);
+
+    use crate::storage_application::ConditionalWitnessAllocator;
+    let read_queries_allocator = ConditionalWitnessAllocator::<F, UInt256<F>> {
+        witness_source: Arc::new(RwLock::new(memory_reads_witness)),
+    };
+
+    for _cycle in 0..limit {
+        let is_empty = requests_queue.is_empty(cs);
+        let should_process = is_empty.negated(cs);
+        let (request, _) = requests_queue.pop_front(cs, should_process);
+
+        let mut precompile_call_params =
+            Bls12_381PrecompileCallParams::from_encoding(cs, request.key);
+
+        let timestamp_to_use_for_read = request.timestamp;
+        let timestamp_to_use_for_write = timestamp_to_use_for_read.add_no_overflow(cs, one_u32);
+
+        Num::conditionally_enforce_equal(
+            cs,
+            should_process,
+            &Num::from_variable(request.aux_byte.get_variable()),
+            &Num::from_variable(aux_byte_for_precompile.get_variable()),
+        );
+        for (a, b) in request
+            .address
+            .inner
+            .iter()
+            .zip(precompile_address.inner.iter())
+        {
+            Num::conditionally_enforce_equal(
+                cs,
+                should_process,
+                &Num::from_variable(a.get_variable()),
+                &Num::from_variable(b.get_variable()),
+            );
+        }
+
+        let mut read_values = [zero_u256; INPUT_WORDS];
+        let mut bias_variable = should_process.get_variable();
+        for dst in read_values.iter_mut() {
+            let read_query_value: UInt256<F> = read_queries_allocator
+                .conditionally_allocate_biased(cs, should_process, bias_variable);
+            bias_variable = read_query_value.inner[0].get_variable();
+
+            *dst = read_query_value;
+
+            let read_query = MemoryQuery {
+                timestamp: timestamp_to_use_for_read,
+                memory_page: precompile_call_params.input_page,
+                index: precompile_call_params.input_offset,
+                rw_flag: boolean_false,
+                is_ptr: boolean_false,
+                value: read_query_value,
+            };
+
+            let _ = memory_queue.push(cs, read_query, should_process);
+
+            precompile_call_params.input_offset = precompile_call_params
+                .input_offset
+                .add_no_overflow(cs, one_u32);
+        }
+
+        let (success, result) = call_inner(cs, &read_values);
+
+        let success_as_u32 = unsafe { UInt32::from_variable_unchecked(success.get_variable()) };
+        let mut success_as_u256 = zero_u256;
+        success_as_u256.inner[0] = success_as_u32;
+
+        let success_query = MemoryQuery {
+            timestamp: timestamp_to_use_for_write,
+            memory_page: precompile_call_params.output_page,
+            index: precompile_call_params.output_offset,
+            rw_flag: boolean_true,
+            value: success_as_u256,
+            is_ptr: boolean_false,
+        };
+
+        precompile_call_params.output_offset = precompile_call_params
+            .output_offset
+            .add_no_overflow(cs, one_u32);
+
+        let _ = memory_queue.push(cs, success_query, should_process);
+
+        for value in result.into_iter() {
+            let value_query = MemoryQuery {
+                timestamp: timestamp_to_use_for_write,
+                memory_page: precompile_call_params.output_page,
+                index: precompile_call_params.output_offset,
+                rw_flag: boolean_true,
+                value,
+                is_ptr: boolean_false,
+            };
+
+            precompile_call_params.output_offset = precompile_call_params
+                .output_offset
+                .add_no_overflow(cs, one_u32);
+
+            let _ = memory_queue.push(cs, value_query, should_process);
+        }
+    }
+
+    requests_queue.enforce_consistency(cs);
+
+    // form the final state
+    let done = requests_queue.is_empty(cs);
+    structured_input.completion_flag = done;
+    structured_input.observable_output = PrecompileFunctionOutputData::placeholder(cs);
+
+    let final_memory_state = memory_queue.into_state();
+    let final_requets_state = requests_queue.into_state();
+
+    structured_input.observable_output.final_memory_state = QueueState::conditionally_select(
+        cs,
+        structured_input.completion_flag,
+        &final_memory_state,
+        &structured_input.observable_output.final_memory_state,
+    );
+
+    structured_input.hidden_fsm_output.log_queue_state = final_requets_state;
+    structured_input.hidden_fsm_output.memory_queue_state = final_memory_state;
+
+    // self-check
+    structured_input.hook_compare_witness(cs, &closed_form_input);
+
+    use boojum::cs::gates::PublicInputGate;
+
+    let compact_form =
+        ClosedFormInputCompactForm::from_full_form(cs, &structured_input, round_function);
+    let input_commitment = commit_variable_length_encodable_item(cs, &compact_form, round_function);
+    for el in input_commitment.iter() {
+        let gate = PublicInputGate::new(el.get_variable());
+        gate.add_to_cs(cs);
+    }
+
+    input_commitment
+}
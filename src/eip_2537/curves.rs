@@ -0,0 +1,607 @@
+use super::*;
+
+use crate::kzg_point_evaluation::baseline::{
+    convert_field_element_to_u32_words, convert_u32_words_to_field_element, is_in_g1_subgroup,
+    u32_words_less_than, u64_words_into_u32_words, G1_COMPRESSED_WORDS,
+};
+use crate::kzg_point_evaluation::pairing::BLS12_381_X;
+use crate::kzg_point_evaluation::towers::*;
+
+use boojum::gadgets::curves::sw_projective::SWProjectivePoint;
+use boojum::pairing::GenericCurveAffine;
+
+pub(crate) type Bls12_381G1ProjectivePoint<F> =
+    SWProjectivePoint<F, Bls12_381G1Affine, Bls12_381BaseNNField<F>>;
+
+// coefficients of the untwist-Frobenius-twist endomorphism psi(x, y) = (conj(x) * c_x, conj(y) * c_y),
+// where c_x = 1 / xi^((p - 1) / 3) and c_y = 1 / xi^((p - 1) / 2), as (c0, c1) pairs
+const PSI_COEFF_X: (&str, &str) = (
+    "0",
+    "4002409555221667392624310435006688643935503118305586438271171395842971157480381377015405980053539358417135540939437",
+);
+const PSI_COEFF_Y: (&str, &str) = (
+    "2973677408986561043442465346520108879172042883009249989176415018091420807192182638567116318576472649347015917690530",
+    "1028732146235106349975324479215795277384839936929757896155643118032610843298655225875571310552543014690878354869257",
+);
+
+pub(crate) fn bls12_381_group_order() -> U256 {
+    let params = bls12_381_scalar_field_params();
+    U256(std::array::from_fn(|i| {
+        params.modulus_u1024.as_ref().as_words()[i]
+    }))
+}
+
+// high word keeps the top 128 bits, that must be zero, and the next 128 bits of the element
+fn fp_words_into_u32_words<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    words: &[UInt256<F>; FP_WORDS],
+) -> [UInt32<F>; G1_COMPRESSED_WORDS] {
+    let [hi, lo] = words;
+    let mut result = [UInt32::zero(cs); G1_COMPRESSED_WORDS];
+    result[..8].copy_from_slice(&lo.inner);
+    result[8..].copy_from_slice(&hi.inner[..4]);
+
+    result
+}
+
+/// Decodes the element of the base field. Returns the element masked to zero if the encoding
+/// is not canonical, and flags for "zero" and "invalid" cases
+pub(crate) fn bls12_381_decode_fp<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    words: &[UInt256<F>; FP_WORDS],
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> (Bls12_381BaseNNField<F>, Boolean<F>, Boolean<F>) {
+    let padding_is_zero = words[0].inner[4..]
+        .iter()
+        .map(|el| el.is_zero(cs))
+        .collect::<Vec<_>>();
+    let padding_is_zero = Boolean::multi_and(cs, &padding_is_zero);
+
+    let value_words = fp_words_into_u32_words(cs, words);
+    let modulus_words: [u32; G1_COMPRESSED_WORDS] =
+        u64_words_into_u32_words(std::array::from_fn::<_, 6, _>(|i| {
+            params.modulus_u1024.as_ref().as_words()[i]
+        }));
+    let modulus_words = modulus_words.map(|el| UInt32::allocated_constant(cs, el));
+    let is_in_range = u32_words_less_than(cs, &value_words, &modulus_words);
+
+    let is_valid = Boolean::multi_and(cs, &[padding_is_zero, is_in_range]);
+    let is_invalid = is_valid.negated(cs);
+    let value_words = value_words.map(|el| el.mask(cs, is_valid));
+    let words_are_zero = value_words.map(|el| el.is_zero(cs));
+    let is_zero = Boolean::multi_and(cs, &words_are_zero);
+
+    let element = convert_u32_words_to_field_element(cs, &value_words, params);
+
+    (element, is_zero, is_invalid)
+}
+
+/// Caller must ensure that the encoding is canonical, e.g. it was produced by `bls12_381_encode_fp`
+pub(crate) fn bls12_381_decode_fp_unchecked<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    words: &[UInt256<F>; FP_WORDS],
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> Bls12_381BaseNNField<F> {
+    let value_words = fp_words_into_u32_words(cs, words);
+
+    convert_u32_words_to_field_element(cs, &value_words, params)
+}
+
+pub(crate) fn bls12_381_encode_fp<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    element: Bls12_381BaseNNField<F>,
+) -> [UInt256<F>; FP_WORDS] {
+    let value_words = convert_field_element_to_u32_words(cs, element);
+    let mut hi = UInt256::zero(cs);
+    hi.inner[..4].copy_from_slice(&value_words[8..]);
+    let lo = UInt256 {
+        inner: std::array::from_fn(|i| value_words[i]),
+    };
+
+    [hi, lo]
+}
+
+fn g1_generator<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> (Bls12_381BaseNNField<F>, Bls12_381BaseNNField<F>) {
+    let (gen_x, gen_y) = Bls12_381G1Affine::one().into_xy_unchecked();
+
+    (
+        Bls12_381BaseNNField::allocated_constant(cs, gen_x, params),
+        Bls12_381BaseNNField::allocated_constant(cs, gen_y, params),
+    )
+}
+
+/// Checks that the coordinates are canonical and either form a point on curve, or are all zeroes that encode
+/// the point at infinity. If `check_subgroup` is set, the point must also belong to G1.
+/// Returns the point masked to the generator if it's invalid or at infinity, so it can be safely used
+/// in arithmetic formulas, and flags for "infinity" and "invalid" cases
+pub(crate) fn bls12_381_validate_and_mask_g1_point<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    words: &[UInt256<F>; G1_POINT_WORDS],
+    check_subgroup: bool,
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> (
+    (Bls12_381BaseNNField<F>, Bls12_381BaseNNField<F>),
+    Boolean<F>,
+    Boolean<F>,
+) {
+    let [x_hi, x_lo, y_hi, y_lo] = *words;
+    let (mut x, x_is_zero, x_is_invalid) = bls12_381_decode_fp(cs, &[x_hi, x_lo], params);
+    let (mut y, y_is_zero, y_is_invalid) = bls12_381_decode_fp(cs, &[y_hi, y_lo], params);
+
+    let encoding_is_invalid = Boolean::multi_or(cs, &[x_is_invalid, y_is_invalid]);
+    let encoding_is_valid = encoding_is_invalid.negated(cs);
+    let is_infinity = Boolean::multi_and(cs, &[x_is_zero, y_is_zero, encoding_is_valid]);
+
+    // curve equation is y^2 = x^3 + b
+    let curve_b = Bls12_381G1Affine::b_coeff();
+    let mut curve_b_nn = Bls12_381BaseNNField::allocated_constant(cs, curve_b, params);
+
+    let mut lhs = y.square(cs);
+    lhs.normalize(cs);
+    let mut rhs = x.square(cs);
+    let mut rhs = rhs.mul(cs, &mut x);
+    let mut rhs = rhs.add(cs, &mut curve_b_nn);
+    rhs.normalize(cs);
+    let is_on_curve = NonNativeFieldOverU16::equals(cs, &mut lhs, &mut rhs);
+
+    // subgroup check only makes sense for points on curve, so we mask the point first
+    let (gen_x, gen_y) = g1_generator(cs, params);
+    let x = Selectable::conditionally_select(cs, is_on_curve, &x, &gen_x);
+    let y = Selectable::conditionally_select(cs, is_on_curve, &y, &gen_y);
+
+    let is_valid_point = if check_subgroup {
+        let is_in_subgroup =
+            is_in_g1_subgroup(cs, &(x.clone(), y.clone()), bls12_381_group_order());
+        Boolean::multi_and(cs, &[is_on_curve, is_in_subgroup])
+    } else {
+        is_on_curve
+    };
+
+    let is_valid = Boolean::multi_or(cs, &[is_valid_point, is_infinity]);
+    let is_valid = Boolean::multi_and(cs, &[is_valid, encoding_is_valid]);
+    let is_invalid = is_valid.negated(cs);
+
+    let should_mask = Boolean::multi_or(cs, &[is_invalid, is_infinity]);
+    let x = Selectable::conditionally_select(cs, should_mask, &gen_x, &x);
+    let y = Selectable::conditionally_select(cs, should_mask, &gen_y, &y);
+
+    ((x, y), is_infinity, is_invalid)
+}
+
+/// Decodes the point that was produced by `bls12_381_encode_g1_point`. Returns the point
+/// masked to the generator if it's at infinity, and the flag for this case
+pub(crate) fn bls12_381_decode_g1_point_unchecked<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    words: &[UInt256<F>; G1_POINT_WORDS],
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> (
+    (Bls12_381BaseNNField<F>, Bls12_381BaseNNField<F>),
+    Boolean<F>,
+) {
+    let [x_hi, x_lo, y_hi, y_lo] = *words;
+    let words_are_zero = words.map(|el| el.is_zero(cs));
+    let is_infinity = Boolean::multi_and(cs, &words_are_zero);
+
+    let x = bls12_381_decode_fp_unchecked(cs, &[x_hi, x_lo], params);
+    let y = bls12_381_decode_fp_unchecked(cs, &[y_hi, y_lo], params);
+
+    let (gen_x, gen_y) = g1_generator(cs, params);
+    let x = Selectable::conditionally_select(cs, is_infinity, &gen_x, &x);
+    let y = Selectable::conditionally_select(cs, is_infinity, &gen_y, &y);
+
+    ((x, y), is_infinity)
+}
+
+/// Encodes the point in affine form, the point at infinity is encoded by zeroes
+pub(crate) fn bls12_381_encode_g1_point<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    point: &mut Bls12_381G1ProjectivePoint<F>,
+) -> [UInt256<F>; G1_POINT_WORDS] {
+    let ((x, y), is_infinity) = point.convert_to_affine_or_default(cs, Bls12_381G1Affine::one());
+    let [x_hi, x_lo] = bls12_381_encode_fp(cs, x);
+    let [y_hi, y_lo] = bls12_381_encode_fp(cs, y);
+
+    [x_hi, x_lo, y_hi, y_lo].map(|el| el.mask_negated(cs, is_infinity))
+}
+
+#[derive(Derivative)]
+#[derivative(Clone, Debug)]
+pub(crate) struct Bls12_381G2AffinePoint<F: SmallField> {
+    pub(crate) x: Bls12_381Fq2<F>,
+    pub(crate) y: Bls12_381Fq2<F>,
+}
+
+impl<F: SmallField> Bls12_381G2AffinePoint<F> {
+    pub(crate) fn generator<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        params: &Arc<Bls12_381BaseNNFieldParams>,
+    ) -> Self {
+        let (x, y) = Bls12_381G2Affine::one().into_xy_unchecked();
+
+        Self {
+            x: Bls12_381Fq2::constant(cs, x.c0, x.c1, params),
+            y: Bls12_381Fq2::constant(cs, y.c0, y.c1, params),
+        }
+    }
+
+    pub(crate) fn negated<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        Self {
+            x: self.x.clone(),
+            y: self.y.negated(cs),
+        }
+    }
+
+    /// Untwist-Frobenius-twist endomorphism
+    pub(crate) fn psi<CS: ConstraintSystem<F>>(
+        &mut self,
+        cs: &mut CS,
+        params: &Arc<Bls12_381BaseNNFieldParams>,
+    ) -> Self {
+        let (mut x_coeff, mut y_coeff) = psi_coeffs(cs, params);
+        let mut x = self.x.conjugate(cs);
+        let mut y = self.y.conjugate(cs);
+
+        Self {
+            x: x.mul(cs, &mut x_coeff),
+            y: y.mul(cs, &mut y_coeff),
+        }
+    }
+
+    pub(crate) fn conditionally_select<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        flag: Boolean<F>,
+        a: &Self,
+        b: &Self,
+    ) -> Self {
+        Self {
+            x: Bls12_381Fq2::conditionally_select(cs, flag, &a.x, &b.x),
+            y: Bls12_381Fq2::conditionally_select(cs, flag, &a.y, &b.y),
+        }
+    }
+}
+
+fn psi_coeffs<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> (Bls12_381Fq2<F>, Bls12_381Fq2<F>) {
+    let [x_coeff, y_coeff] = [PSI_COEFF_X, PSI_COEFF_Y].map(|(c0, c1)| {
+        Bls12_381Fq2::constant(
+            cs,
+            bls12_381_fq_from_str(c0),
+            bls12_381_fq_from_str(c1),
+            params,
+        )
+    });
+
+    (x_coeff, y_coeff)
+}
+
+pub(crate) fn g2_curve_b3<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> Bls12_381Fq2<F> {
+    let curve_b = Bls12_381G2Affine::b_coeff();
+    let mut curve_b = Bls12_381Fq2::constant(cs, curve_b.c0, curve_b.c1, params);
+    let mut curve_b2 = curve_b.double(cs);
+
+    curve_b2.add(cs, &mut curve_b)
+}
+
+// homogeneous projective coordinates, used for complete addition formulas
+#[derive(Derivative)]
+#[derivative(Clone, Debug)]
+pub(crate) struct Bls12_381G2ProjectivePoint<F: SmallField> {
+    pub(crate) x: Bls12_381Fq2<F>,
+    pub(crate) y: Bls12_381Fq2<F>,
+    pub(crate) z: Bls12_381Fq2<F>,
+}
+
+impl<F: SmallField> Bls12_381G2ProjectivePoint<F> {
+    pub(crate) fn zero<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        params: &Arc<Bls12_381BaseNNFieldParams>,
+    ) -> Self {
+        Self {
+            x: Bls12_381Fq2::zero(cs, params),
+            y: Bls12_381Fq2::one(cs, params),
+            z: Bls12_381Fq2::zero(cs, params),
+        }
+    }
+
+    pub(crate) fn from_affine<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        point: &Bls12_381G2AffinePoint<F>,
+        params: &Arc<Bls12_381BaseNNFieldParams>,
+    ) -> Self {
+        Self {
+            x: point.x.clone(),
+            y: point.y.clone(),
+            z: Bls12_381Fq2::one(cs, params),
+        }
+    }
+
+    // complete addition formulas for a = 0 from https://eprint.iacr.org/2015/1060, algorithm 7.
+    // Twisted curve has no points of order 2, so these also work for doubling
+    pub(crate) fn add<CS: ConstraintSystem<F>>(
+        &mut self,
+        cs: &mut CS,
+        other: &mut Self,
+        curve_b3: &mut Bls12_381Fq2<F>,
+    ) -> Self {
+        let mut t0 = self.x.mul(cs, &mut other.x);
+        let mut t1 = self.y.mul(cs, &mut other.y);
+        let mut t2 = self.z.mul(cs, &mut other.z);
+
+        let mut t3 = self.x.add(cs, &mut self.y);
+        let mut t4 = other.x.add(cs, &mut other.y);
+        let mut t3 = t3.mul(cs, &mut t4);
+
+        let mut t4 = t0.add(cs, &mut t1);
+        let mut t3 = t3.sub(cs, &mut t4);
+        let mut t4 = self.y.add(cs, &mut self.z);
+
+        let mut x3 = other.y.add(cs, &mut other.z);
+        let mut t4 = t4.mul(cs, &mut x3);
+        let mut x3 = t1.add(cs, &mut t2);
+
+        let mut t4 = t4.sub(cs, &mut x3);
+        let mut x3 = self.x.add(cs, &mut self.z);
+        let mut y3 = other.x.add(cs, &mut other.z);
+
+        let mut x3 = x3.mul(cs, &mut y3);
+        let mut y3 = t0.add(cs, &mut t2);
+        let mut y3 = x3.sub(cs, &mut y3);
+
+        let mut x3 = t0.double(cs);
+        let mut t0 = x3.add(cs, &mut t0);
+        let mut t2 = curve_b3.mul(cs, &mut t2);
+
+        let mut z3 = t1.add(cs, &mut t2);
+        let mut t1 = t1.sub(cs, &mut t2);
+        let mut y3 = curve_b3.mul(cs, &mut y3);
+
+        let mut x3 = t4.mul(cs, &mut y3);
+        let mut t2 = t3.mul(cs, &mut t1);
+        let x3 = t2.sub(cs, &mut x3);
+
+        let mut y3 = y3.mul(cs, &mut t0);
+        let mut t1 = t1.mul(cs, &mut z3);
+        let y3 = t1.add(cs, &mut y3);
+
+        let mut t0 = t0.mul(cs, &mut t3);
+        let mut z3 = z3.mul(cs, &mut t4);
+        let z3 = z3.add(cs, &mut t0);
+
+        Self {
+            x: x3,
+            y: y3,
+            z: z3,
+        }
+    }
+
+    pub(crate) fn double<CS: ConstraintSystem<F>>(
+        &mut self,
+        cs: &mut CS,
+        curve_b3: &mut Bls12_381Fq2<F>,
+    ) -> Self {
+        let mut other = self.clone();
+        self.add(cs, &mut other, curve_b3)
+    }
+
+    pub(crate) fn negated<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        Self {
+            x: self.x.clone(),
+            y: self.y.negated(cs),
+            z: self.z.clone(),
+        }
+    }
+
+    pub(crate) fn psi<CS: ConstraintSystem<F>>(
+        &mut self,
+        cs: &mut CS,
+        params: &Arc<Bls12_381BaseNNFieldParams>,
+    ) -> Self {
+        let (mut x_coeff, mut y_coeff) = psi_coeffs(cs, params);
+        let mut x = self.x.conjugate(cs);
+        let mut y = self.y.conjugate(cs);
+
+        Self {
+            x: x.mul(cs, &mut x_coeff),
+            y: y.mul(cs, &mut y_coeff),
+            z: self.z.conjugate(cs),
+        }
+    }
+
+    pub(crate) fn conditionally_select<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        flag: Boolean<F>,
+        a: &Self,
+        b: &Self,
+    ) -> Self {
+        Self {
+            x: Bls12_381Fq2::conditionally_select(cs, flag, &a.x, &b.x),
+            y: Bls12_381Fq2::conditionally_select(cs, flag, &a.y, &b.y),
+            z: Bls12_381Fq2::conditionally_select(cs, flag, &a.z, &b.z),
+        }
+    }
+
+    /// Returns the point in affine form, masked to the generator if it's at infinity,
+    /// and the flag for this case
+    pub(crate) fn into_affine<CS: ConstraintSystem<F>>(
+        &mut self,
+        cs: &mut CS,
+        params: &Arc<Bls12_381BaseNNFieldParams>,
+    ) -> (Bls12_381G2AffinePoint<F>, Boolean<F>) {
+        self.z.normalize(cs);
+        let is_infinity = self.z.is_zero(cs);
+        let one = Bls12_381Fq2::one(cs, params);
+        let mut z = Bls12_381Fq2::conditionally_select(cs, is_infinity, &one, &self.z);
+        let mut z_inv = z.inverse(cs);
+
+        let point = Bls12_381G2AffinePoint {
+            x: self.x.mul(cs, &mut z_inv),
+            y: self.y.mul(cs, &mut z_inv),
+        };
+        let generator = Bls12_381G2AffinePoint::generator(cs, params);
+        let point =
+            Bls12_381G2AffinePoint::conditionally_select(cs, is_infinity, &generator, &point);
+
+        (point, is_infinity)
+    }
+}
+
+fn is_in_g2_subgroup<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    point: &mut Bls12_381G2AffinePoint<F>,
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> Boolean<F> {
+    let mut curve_b3 = g2_curve_b3(cs, params);
+
+    // point Q on the twist belongs to G2 if and only if psi(Q) == [x]Q, and x is negative
+    let mut base = Bls12_381G2ProjectivePoint::from_affine(cs, point, params);
+    let mut acc = base.clone();
+    let num_bits = 64 - BLS12_381_X.leading_zeros();
+    for i in (0..(num_bits - 1)).rev() {
+        acc = acc.double(cs, &mut curve_b3);
+        if (BLS12_381_X >> i) & 1 == 1 {
+            acc = acc.add(cs, &mut base, &mut curve_b3);
+        }
+    }
+
+    let mut psi = point.psi(cs, params);
+
+    // compare in projective form
+    let mut x_scaled = psi.x.mul(cs, &mut acc.z);
+    let mut y_scaled = psi.y.mul(cs, &mut acc.z);
+    let mut acc_y_negated = acc.y.negated(cs);
+    let x_is_equal = Bls12_381Fq2::equals(cs, &mut x_scaled, &mut acc.x);
+    let y_is_equal = Bls12_381Fq2::equals(cs, &mut y_scaled, &mut acc_y_negated);
+    acc.z.normalize(cs);
+    let z_is_zero = acc.z.is_zero(cs);
+    let z_is_not_zero = z_is_zero.negated(cs);
+
+    Boolean::multi_and(cs, &[x_is_equal, y_is_equal, z_is_not_zero])
+}
+
+/// Decodes the element of Fq2 given by c0 and c1 coefficients. Returns the element masked to zero
+/// if the encoding is not canonical, and flags for "zero" and "invalid" cases
+pub(crate) fn bls12_381_decode_fp2<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    words: &[UInt256<F>; FP2_WORDS],
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> (Bls12_381Fq2<F>, Boolean<F>, Boolean<F>) {
+    let [c0_hi, c0_lo, c1_hi, c1_lo] = *words;
+    let (c0, c0_is_zero, c0_is_invalid) = bls12_381_decode_fp(cs, &[c0_hi, c0_lo], params);
+    let (c1, c1_is_zero, c1_is_invalid) = bls12_381_decode_fp(cs, &[c1_hi, c1_lo], params);
+
+    let is_zero = Boolean::multi_and(cs, &[c0_is_zero, c1_is_zero]);
+    let is_invalid = Boolean::multi_or(cs, &[c0_is_invalid, c1_is_invalid]);
+
+    (Bls12_381Fq2 { c0, c1 }, is_zero, is_invalid)
+}
+
+pub(crate) fn bls12_381_encode_fp2<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    element: Bls12_381Fq2<F>,
+) -> [UInt256<F>; FP2_WORDS] {
+    let [c0_hi, c0_lo] = bls12_381_encode_fp(cs, element.c0);
+    let [c1_hi, c1_lo] = bls12_381_encode_fp(cs, element.c1);
+
+    [c0_hi, c0_lo, c1_hi, c1_lo]
+}
+
+/// Checks that the coordinates are canonical and either form a point on the twisted curve, or are all
+/// zeroes that encode the point at infinity. If `check_subgroup` is set, the point must also belong to G2.
+/// Returns the point masked to the generator if it's invalid or at infinity, and flags for "infinity"
+/// and "invalid" cases
+pub(crate) fn bls12_381_validate_and_mask_g2_point<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    words: &[UInt256<F>; G2_POINT_WORDS],
+    check_subgroup: bool,
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> (Bls12_381G2AffinePoint<F>, Boolean<F>, Boolean<F>) {
+    let x_words = std::array::from_fn(|i| words[i]);
+    let y_words = std::array::from_fn(|i| words[FP2_WORDS + i]);
+    let (x, x_is_zero, x_is_invalid) = bls12_381_decode_fp2(cs, &x_words, params);
+    let (y, y_is_zero, y_is_invalid) = bls12_381_decode_fp2(cs, &y_words, params);
+    let mut point = Bls12_381G2AffinePoint { x, y };
+
+    let encoding_is_invalid = Boolean::multi_or(cs, &[x_is_invalid, y_is_invalid]);
+    let encoding_is_valid = encoding_is_invalid.negated(cs);
+    let is_infinity = Boolean::multi_and(cs, &[x_is_zero, y_is_zero, encoding_is_valid]);
+
+    // twisted curve equation is y^2 = x^3 + b'
+    let curve_b = Bls12_381G2Affine::b_coeff();
+    let mut curve_b = Bls12_381Fq2::constant(cs, curve_b.c0, curve_b.c1, params);
+
+    let mut lhs = point.y.square(cs);
+    let mut rhs = point.x.square(cs);
+    let mut rhs = rhs.mul(cs, &mut point.x);
+    let mut rhs = rhs.add(cs, &mut curve_b);
+    let is_on_curve = Bls12_381Fq2::equals(cs, &mut lhs, &mut rhs);
+
+    // subgroup check only makes sense for points on curve, so we mask the point first
+    let generator = Bls12_381G2AffinePoint::generator(cs, params);
+    let mut point =
+        Bls12_381G2AffinePoint::conditionally_select(cs, is_on_curve, &point, &generator);
+
+    let is_valid_point = if check_subgroup {
+        let is_in_subgroup = is_in_g2_subgroup(cs, &mut point, params);
+        Boolean::multi_and(cs, &[is_on_curve, is_in_subgroup])
+    } else {
+        is_on_curve
+    };
+
+    let is_valid = Boolean::multi_or(cs, &[is_valid_point, is_infinity]);
+    let is_valid = Boolean::multi_and(cs, &[is_valid, encoding_is_valid]);
+    let is_invalid = is_valid.negated(cs);
+
+    let should_mask = Boolean::multi_or(cs, &[is_invalid, is_infinity]);
+    let point = Bls12_381G2AffinePoint::conditionally_select(cs, should_mask, &generator, &point);
+
+    (point, is_infinity, is_invalid)
+}
+
+/// Decodes the point that was produced by `bls12_381_encode_g2_point`. Returns the point
+/// masked to the generator if it's at infinity, and the flag for this case
+pub(crate) fn bls12_381_decode_g2_point_unchecked<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    words: &[UInt256<F>; G2_POINT_WORDS],
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> (Bls12_381G2AffinePoint<F>, Boolean<F>) {
+    let words_are_zero = words.map(|el| el.is_zero(cs));
+    let is_infinity = Boolean::multi_and(cs, &words_are_zero);
+
+    let [x_c0, x_c1, y_c0, y_c1] = std::array::from_fn(|i| {
+        let words = [words[2 * i], words[2 * i + 1]];
+        bls12_381_decode_fp_unchecked(cs, &words, params)
+    });
+    let point = Bls12_381G2AffinePoint {
+        x: Bls12_381Fq2 { c0: x_c0, c1: x_c1 },
+        y: Bls12_381Fq2 { c0: y_c0, c1: y_c1 },
+    };
+
+    let generator = Bls12_381G2AffinePoint::generator(cs, params);
+    let point = Bls12_381G2AffinePoint::conditionally_select(cs, is_infinity, &generator, &point);
+
+    (point, is_infinity)
+}
+
+/// Encodes the point in affine form, the point at infinity is encoded by zeroes
+pub(crate) fn bls12_381_encode_g2_point<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    point: &mut Bls12_381G2ProjectivePoint<F>,
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> [UInt256<F>; G2_POINT_WORDS] {
+    let (point, is_infinity) = point.into_affine(cs, params);
+    let [x_c0_hi, x_c0_lo, x_c1_hi, x_c1_lo] = bls12_381_encode_fp2(cs, point.x);
+    let [y_c0_hi, y_c0_lo, y_c1_hi, y_c1_lo] = bls12_381_encode_fp2(cs, point.y);
+
+    [
+        x_c0_hi, x_c0_lo, x_c1_hi, x_c1_lo, y_c0_hi, y_c0_lo, y_c1_hi, y_c1_lo,
+    ]
+    .map(|el| el.mask_negated(cs, is_infinity))
+}
@@ -0,0 +1,222 @@
+use std::collections::VecDeque;
+
+use super::*;
+
+use crate::base_structures::precompile_input_outputs::*;
+use crate::base_structures::vm_state::*;
+use boojum::cs::Variable;
+use boojum::gadgets::queue::*;
+use boojum::gadgets::traits::allocatable::CSAllocatable;
+use boojum::gadgets::traits::allocatable::CSPlaceholder;
+use boojum::gadgets::traits::encodable::CircuitVarLengthEncodable;
+
+use boojum::gadgets::traits::auxiliary::PrettyComparison;
+use boojum::serde_utils::BigArraySerde;
+
+// Add and map-to-curve precompiles process one call per cycle, so they only carry the queue states
+
+#[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
+#[derivative(Clone, Copy, Debug)]
+#[DerivePrettyComparison("true")]
+pub struct Bls12_381CircuitFSMInputOutput<F: SmallField> {
+    pub log_queue_state: QueueState<F, QUEUE_STATE_WIDTH>,
+    pub memory_queue_state: QueueState<F, FULL_SPONGE_QUEUE_STATE_WIDTH>,
+}
+
+impl<F: SmallField> CSPlaceholder<F> for Bls12_381CircuitFSMInputOutput<F> {
+    fn placeholder<CS: ConstraintSystem<F>>(cs: &mut CS) -> Self {
+        Self {
+            log_queue_state: QueueState::<F, QUEUE_STATE_WIDTH>::placeholder(cs),
+            memory_queue_state: QueueState::<F, FULL_SPONGE_QUEUE_STATE_WIDTH>::placeholder(cs),
+        }
+    }
+}
+
+pub type Bls12_381CircuitInputOutput<F> = ClosedFormInput<
+    F,
+    Bls12_381CircuitFSMInputOutput<F>,
+    PrecompileFunctionInputData<F>,
+    PrecompileFunctionOutputData<F>,
+>;
+pub type Bls12_381CircuitInputOutputWitness<F> = ClosedFormInputWitness<
+    F,
+    Bls12_381CircuitFSMInputOutput<F>,
+    PrecompileFunctionInputData<F>,
+    PrecompileFunctionOutputData<F>,
+>;
+
+#[derive(Derivative, serde::Serialize, serde::Deserialize)]
+#[derivative(Clone, Debug, Default)]
+#[serde(bound = "")]
+pub struct Bls12_381CircuitInstanceWitness<F: SmallField> {
+    pub closed_form_input: Bls12_381CircuitInputOutputWitness<F>,
+    pub requests_queue_witness: CircuitQueueRawWitness<F, LogQuery<F>, 4, LOG_QUERY_PACKED_WIDTH>,
+    // words of all the calls one after another
+    pub memory_reads_witness: VecDeque<U256>,
+}
+
+// MSM processes one (point, scalar) pair per cycle, and keeps the sum of processed pairs
+// in the same encoding as the output, so all zeroes is the point at infinity
+
+#[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
+#[derivative(Clone, Copy, Debug)]
+#[DerivePrettyComparison("true")]
+pub struct Bls12_381MsmFSM<F: SmallField, const POINT_WORDS: usize> {
+    pub read_precompile_call: Boolean<F>,
+    pub read_words_for_round: Boolean<F>,
+    pub completed: Boolean<F>,
+    pub accumulator: [UInt256<F>; POINT_WORDS],
+    pub has_exception: Boolean<F>,
+    pub timestamp_to_use_for_read: UInt32<F>,
+    pub timestamp_to_use_for_write: UInt32<F>,
+    pub precompile_call_params: Bls12_381PrecompileCallParams<F>,
+}
+
+impl<F: SmallField, const POINT_WORDS: usize> CSPlaceholder<F> for Bls12_381MsmFSM<F, POINT_WORDS> {
+    fn placeholder<CS: ConstraintSystem<F>>(cs: &mut CS) -> Self {
+        let boolean_false = Boolean::allocated_constant(cs, false);
+        let zero_u32 = UInt32::zero(cs);
+        let zero_u256 = UInt256::zero(cs);
+        Self {
+            read_precompile_call: boolean_false,
+            read_words_for_round: boolean_false,
+            completed: boolean_false,
+            accumulator: [zero_u256; POINT_WORDS],
+            has_exception: boolean_false,
+            timestamp_to_use_for_read: zero_u32,
+            timestamp_to_use_for_write: zero_u32,
+            precompile_call_params: Bls12_381PrecompileCallParams::<F>::placeholder(cs),
+        }
+    }
+}
+
+#[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
+#[derivative(Clone, Copy, Debug)]
+#[DerivePrettyComparison("true")]
+pub struct Bls12_381MsmFSMInputOutput<F: SmallField, const POINT_WORDS: usize> {
+    pub internal_fsm: Bls12_381MsmFSM<F, POINT_WORDS>,
+    pub log_queue_state: QueueState<F, QUEUE_STATE_WIDTH>,
+    pub memory_queue_state: QueueState<F, FULL_SPONGE_QUEUE_STATE_WIDTH>,
+}
+
+impl<F: SmallField, const POINT_WORDS: usize> CSPlaceholder<F>
+    for Bls12_381MsmFSMInputOutput<F, POINT_WORDS>
+{
+    fn placeholder<CS: ConstraintSystem<F>>(cs: &mut CS) -> Self {
+        Self {
+            internal_fsm: Bls12_381MsmFSM::placeholder(cs),
+            log_queue_state: QueueState::<F, QUEUE_STATE_WIDTH>::placeholder(cs),
+            memory_queue_state: QueueState::<F, FULL_SPONGE_QUEUE_STATE_WIDTH>::placeholder(cs),
+        }
+    }
+}
+
+pub type Bls12_381MsmCircuitInputOutput<F, const POINT_WORDS: usize> = ClosedFormInput<
+    F,
+    Bls12_381MsmFSMInputOutput<F, POINT_WORDS>,
+    PrecompileFunctionInputData<F>,
+    PrecompileFunctionOutputData<F>,
+>;
+pub type Bls12_381MsmCircuitInputOutputWitness<F, const POINT_WORDS: usize> =
+    ClosedFormInputWitness<
+        F,
+        Bls12_381MsmFSMInputOutput<F, POINT_WORDS>,
+        PrecompileFunctionInputData<F>,
+        PrecompileFunctionOutputData<F>,
+    >;
+
+#[derive(Derivative, serde::Serialize, serde::Deserialize)]
+#[derivative(Clone, Debug, Default)]
+#[serde(bound = "")]
+pub struct Bls12_381MsmCircuitInstanceWitness<F: SmallField, const POINT_WORDS: usize> {
+    pub closed_form_input: Bls12_381MsmCircuitInputOutputWitness<F, POINT_WORDS>,
+    pub requests_queue_witness: CircuitQueueRawWitness<F, LogQuery<F>, 4, LOG_QUERY_PACKED_WIDTH>,
+    pub memory_reads_witness: VecDeque<U256>,
+}
+
+pub type Bls12_381G1MsmFSM<F> = Bls12_381MsmFSM<F, G1_POINT_WORDS>;
+pub type Bls12_381G2MsmFSM<F> = Bls12_381MsmFSM<F, G2_POINT_WORDS>;
+pub type Bls12_381G1MsmCircuitInstanceWitness<F> =
+    Bls12_381MsmCircuitInstanceWitness<F, G1_POINT_WORDS>;
+pub type Bls12_381G2MsmCircuitInstanceWitness<F> =
+    Bls12_381MsmCircuitInstanceWitness<F, G2_POINT_WORDS>;
+
+// Miller loop value of Fq12 is kept as 12 elements of Fq2 with the same encoding as the coordinates
+pub const MILLER_LOOP_ACCUMULATOR_WORDS: usize = 6 * 2 * FP2_WORDS;
+
+#[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
+#[derivative(Clone, Copy, Debug)]
+#[DerivePrettyComparison("true")]
+pub struct Bls12_381PairingCheckFSM<F: SmallField> {
+    pub read_precompile_call: Boolean<F>,
+    pub read_words_for_round: Boolean<F>,
+    pub completed: Boolean<F>,
+    pub miller_loop_accumulator: [UInt256<F>; MILLER_LOOP_ACCUMULATOR_WORDS],
+    pub has_exception: Boolean<F>,
+    pub timestamp_to_use_for_read: UInt32<F>,
+    pub timestamp_to_use_for_write: UInt32<F>,
+    pub precompile_call_params: Bls12_381PrecompileCallParams<F>,
+}
+
+impl<F: SmallField> CSPlaceholder<F> for Bls12_381PairingCheckFSM<F> {
+    fn placeholder<CS: ConstraintSystem<F>>(cs: &mut CS) -> Self {
+        let boolean_false = Boolean::allocated_constant(cs, false);
+        let zero_u32 = UInt32::zero(cs);
+        let zero_u256 = UInt256::zero(cs);
+        let one_u256 = UInt256::allocated_constant(cs, U256::one());
+        // accumulator starts from the identity of Fq12, low word of the first coefficient is one
+        let mut miller_loop_accumulator = [zero_u256; MILLER_LOOP_ACCUMULATOR_WORDS];
+        miller_loop_accumulator[1] = one_u256;
+        Self {
+            read_precompile_call: boolean_false,
+            read_words_for_round: boolean_false,
+            completed: boolean_false,
+            miller_loop_accumulator,
+            has_exception: boolean_false,
+            timestamp_to_use_for_read: zero_u32,
+            timestamp_to_use_for_write: zero_u32,
+            precompile_call_params: Bls12_381PrecompileCallParams::<F>::placeholder(cs),
+        }
+    }
+}
+
+#[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
+#[derivative(Clone, Copy, Debug)]
+#[DerivePrettyComparison("true")]
+pub struct Bls12_381PairingCheckFSMInputOutput<F: SmallField> {
+    pub internal_fsm: Bls12_381PairingCheckFSM<F>,
+    pub log_queue_state: QueueState<F, QUEUE_STATE_WIDTH>,
+    pub memory_queue_state: QueueState<F, FULL_SPONGE_QUEUE_STATE_WIDTH>,
+}
+
+impl<F: SmallField> CSPlaceholder<F> for Bls12_381PairingCheckFSMInputOutput<F> {
+    fn placeholder<CS: ConstraintSystem<F>>(cs: &mut CS) -> Self {
+        Self {
+            internal_fsm: Bls12_381PairingCheckFSM::placeholder(cs),
+            log_queue_state: QueueState::<F, QUEUE_STATE_WIDTH>::placeholder(cs),
+            memory_queue_state: QueueState::<F, FULL_SPONGE_QUEUE_STATE_WIDTH>::placeholder(cs),
+        }
+    }
+}
+
+pub type Bls12_381PairingCheckCircuitInputOutput<F> = ClosedFormInput<
+    F,
+    Bls12_381PairingCheckFSMInputOutput<F>,
+    PrecompileFunctionInputData<F>,
+    PrecompileFunctionOutputData<F>,
+>;
+pub type Bls12_381PairingCheckCircuitInputOutputWitness<F> = ClosedFormInputWitness<
+    F,
+    Bls12_381PairingCheckFSMInputOutput<F>,
+    PrecompileFunctionInputData<F>,
+    PrecompileFunctionOutputData<F>,
+>;
+
+#[derive(Derivative, serde::Serialize, serde::Deserialize)]
+#[derivative(Clone, Debug, Default)]
+#[serde(bound = "")]
+pub struct Bls12_381PairingCheckCircuitInstanceWitness<F: SmallField> {
+    pub closed_form_input: Bls12_381PairingCheckCircuitInputOutputWitness<F>,
+    pub requests_queue_witness: CircuitQueueRawWitness<F, LogQuery<F>, 4, LOG_QUERY_PACKED_WIDTH>,
+    pub memory_reads_witness: VecDeque<U256>,
+}
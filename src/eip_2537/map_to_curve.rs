@@ -0,0 +1,774 @@
+use super::*;
+
+use super::baseline::bls12_381_per_call_function_entry_point;
+use super::curves::*;
+use crate::fsm_input_output::circuit_inputs::INPUT_OUTPUT_COMMITMENT_LENGTH;
+use crate::kzg_point_evaluation::baseline::{
+    convert_field_element_to_u32_words, pow_by_constant, HALF_MODULUS, SQRT_EXPONENT,
+};
+use crate::kzg_point_evaluation::pairing::BLS12_381_X;
+use crate::kzg_point_evaluation::towers::*;
+
+use boojum::algebraic_props::round_function::AlgebraicRoundFunction;
+use boojum::gadgets::num::Num;
+use boojum::gadgets::traits::allocatable::CSAllocatableExt;
+use boojum::gadgets::traits::round_function::CircuitRoundFunction;
+
+// Simplified SWU map to the curves isogenous to G1 and G2 and the isogeny maps, as in RFC 9380.
+// Constants are decimal, and coefficients of polynomials are given from the lowest degree
+
+const G1_SSWU_A: &str = "12190336318893619529228877361869031420615612348429846051986726275283378313155663745811710833465465981901188123677";
+const G1_SSWU_B: &str = "2906670324641927570491258158026293881577086121416628140204402091718288198173574630967936031029026176254968826637280";
+const G1_SSWU_Z: &str = "11";
+const G1_SSWU_MINUS_B_OVER_A: &str = "1165829013300031051498189320085913366300917435352691079268722832469236884678791583237077507172460989948189414825084";
+const G1_SSWU_B_OVER_ZA: &str = "2804858857133937099622193571436483626014013193105397454853431114229365119931628302936765775078151302032269524150292";
+// sqrt(-Z^3)
+const G1_SSWU_C2: &str = "590728492726997966099618626120482682095437733689734941576187760067601492637096712773899690116980580577616657575925";
+
+const G1_ISOGENY_X_NUM: [&str; 12] = [
+    "2712959285290305970661081772124144179193819192423276218370281158706191519995889425075952244140278856085036081760695",
+    "3564859427549639835253027846704205725951033235539816243131874237388832081954622352624080767121604606753339903542203",
+    "2051387046688339481714726479723076305756384619135044672831882917686431912682625619320120082313093891743187631791280",
+    "3612713941521031012780325893181011392520079402153354595775735142359240110423346445050803899623018402874731133626465",
+    "2247053637822768981792833880270996398470828564809439728372634811976089874056583714987807553397615562273407692740057",
+    "3415427104483187489859740871640064348492611444552862448295571438270821994900526625562705192993481400731539293415811",
+    "2067521456483432583860405634125513059912765526223015704616050604591207046392807563217109432457129564962571408764292",
+    "3650721292069012982822225637849018828271936405382082649291891245623305084633066170122780668657208923883092359301262",
+    "1239271775787030039269460763652455868148971086016832054354147730155061349388626624328773377658494412538595239256855",
+    "3479374185711034293956731583912244564891370843071137483962415222733470401948838363051960066766720884717833231600798",
+    "2492756312273161536685660027440158956721981129429869601638362407515627529461742974364729223659746272460004902959995",
+    "1058488477413994682556770863004536636444795456512795473806825292198091015005841418695586811009326456605062948114985",
+];
+const G1_ISOGENY_X_DEN: [&str; 11] = [
+    "1353092447850172218905095041059784486169131709710991428415161466575141675351394082965234118340787683181925558786844",
+    "2822220997908397120956501031591772354860004534930174057793539372552395729721474912921980407622851861692773516917759",
+    "1717937747208385987946072944131378949849282930538642983149296304709633281382731764122371874602115081850953846504985",
+    "501624051089734157816582944025690868317536915684467868346388760435016044027032505306995281054569109955275640941784",
+    "3025903087998593826923738290305187197829899948335370692927241015584233559365859980023579293766193297662657497834014",
+    "2224140216975189437834161136818943039444741035168992629437640302964164227138031844090123490881551522278632040105125",
+    "1146414465848284837484508420047674663876992808692209238763293935905506532411661921697047880549716175045414621825594",
+    "3179090966864399634396993677377903383656908036827452986467581478509513058347781039562481806409014718357094150199902",
+    "1549317016540628014674302140786462938410429359529923207442151939696344988707002602944342203885692366490121021806145",
+    "1442797143427491432630626390066422021593505165588630398337491100088557278058060064930663878153124164818522816175370",
+    "1",
+];
+const G1_ISOGENY_Y_NUM: [&str; 16] = [
+    "1393399195776646641963150658816615410692049723305861307490980409834842911816308830479576739332720113414154429643571",
+    "2968610969752762946134106091152102846225411740689724909058016729455736597929366401532929068084731548131227395540630",
+    "122933100683284845219599644396874530871261396084070222155796123161881094323788483360414289333111221370374027338230",
+    "303251954782077855462083823228569901064301365507057490567314302006681283228886645653148231378803311079384246777035",
+    "1353972356724735644398279028378555627591260676383150667237975415318226973994509601413730187583692624416197017403099",
+    "3443977503653895028417260979421240655844034880950251104724609885224259484262346958661845148165419691583810082940400",
+    "718493410301850496156792713845282235942975872282052335612908458061560958159410402177452633054233549648465863759602",
+    "1466864076415884313141727877156167508644960317046160398342634861648153052436926062434809922037623519108138661903145",
+    "1536886493137106337339531461344158973554574987550750910027365237255347020572858445054025958480906372033954157667719",
+    "2171468288973248519912068884667133903101171670397991979582205855298465414047741472281361964966463442016062407908400",
+    "3915937073730221072189646057898966011292434045388986394373682715266664498392389619761133407846638689998746172899634",
+    "3802409194827407598156407709510350851173404795262202653149767739163117554648574333789388883640862266596657730112910",
+    "1707589313757812493102695021134258021969283151093981498394095062397393499601961942449581422761005023512037430861560",
+    "349697005987545415860583335313370109325490073856352967581197273584891698473628451945217286148025358795756956811571",
+    "885704436476567581377743161796735879083481447641210566405057346859953524538988296201011389016649354976986251207243",
+    "3370924952219000111210625390420697640496067348723987858345031683392215988129398381698161406651860675722373763741188",
+];
+const G1_ISOGENY_Y_DEN: [&str; 16] = [
+    "3396434800020507717552209507749485772788165484415495716688989613875369612529138640646200921379825018840894888371137",
+    "3907278185868397906991868466757978732688957419873771881240086730384895060595583602347317992689443299391009456758845",
+    "854914566454823955479427412036002165304466268547334760894270240966182605542146252771872707010378658178126128834546",
+    "3496628876382137961119423566187258795236027183112131017519536056628828830323846696121917502443333849318934945158166",
+    "1828256966233331991927609917644344011503610008134915752990581590799656305331275863706710232159635159092657073225757",
+    "1362317127649143894542621413133849052553333099883364300946623208643344298804722863920546222860227051989127113848748",
+    "3443845896188810583748698342858554856823966611538932245284665132724280883115455093457486044009395063504744802318172",
+    "3484671274283470572728732863557945897902920439975203610275006103818288159899345245633896492713412187296754791689945",
+    "3755735109429418587065437067067640634211015783636675372165599470771975919172394156249639331555277748466603540045130",
+    "3459661102222301807083870307127272890283709299202626530836335779816726101522661683404130556379097384249447658110805",
+    "742483168411032072323733249644347333168432665415341249073150659015707795549260947228694495111018381111866512337576",
+    "1662231279858095762833829698537304807741442669992646287950513237989158777254081548205552083108208170765474149568658",
+    "1668238650112823419388205992952852912407572045257706138925379268508860023191233729074751042562151098884528280913356",
+    "369162719928976119195087327055926326601627748362769544198813069133429557026740823593067700396825489145575282378487",
+    "2164195715141237148945939585099633032390257748382945597506236650132835917087090097395995817229686247227784224263055",
+    "1",
+];
+
+const G2_SSWU_A: (&str, &str) = ("0", "240");
+const G2_SSWU_B: (&str, &str) = ("1012", "1012");
+// -(2 + i)
+const G2_SSWU_Z: (&str, &str) = (
+    "4002409555221667393417789825735904156556882819939007885332058136124031650490837864442687629129015664037894272559785",
+    "4002409555221667393417789825735904156556882819939007885332058136124031650490837864442687629129015664037894272559786",
+);
+const G2_SSWU_MINUS_B_OVER_A: (&str, &str) = (
+    "1267429692486861341248966778149702982909679559647352497021818409772610022655431990406851082557521626945333186310595",
+    "2734979862734806052168823047586201173647203260291655388310239726351421627835405874035836546571494037092561086249192",
+);
+const G2_SSWU_B_OVER_ZA: (&str, &str) = (
+    "253485938497372268249793355629940596581935911929470499404363681954522004531086398081370216511504325389066637262119",
+    "3241951739729550588668409758846082366811075084150596387118967090260465636897578670198576979594502687870694360773430",
+);
+
+const G2_ISOGENY_X_NUM: [(&str, &str); 4] = [
+    (
+        "889424345604814976315064405719089812568196182208668418962679585805340366775741747653930584250892369786198727235542",
+        "889424345604814976315064405719089812568196182208668418962679585805340366775741747653930584250892369786198727235542",
+    ),
+    (
+        "0",
+        "2668273036814444928945193217157269437704588546626005256888038757416021100327225242961791752752677109358596181706522",
+    ),
+    (
+        "2668273036814444928945193217157269437704588546626005256888038757416021100327225242961791752752677109358596181706526",
+        "1334136518407222464472596608578634718852294273313002628444019378708010550163612621480895876376338554679298090853261",
+    ),
+    (
+        "3557697382419259905260257622876359250272784728834673675850718343221361467102966990615722337003569479144794908942033",
+        "0",
+    ),
+];
+const G2_ISOGENY_X_DEN: [(&str, &str); 3] = [
+    (
+        "0",
+        "4002409555221667393417789825735904156556882819939007885332058136124031650490837864442687629129015664037894272559715",
+    ),
+    (
+        "12",
+        "4002409555221667393417789825735904156556882819939007885332058136124031650490837864442687629129015664037894272559775",
+    ),
+    ("1", "0"),
+];
+const G2_ISOGENY_Y_NUM: [(&str, &str); 4] = [
+    (
+        "3261222600550988246488569487636662646083386001431784202863158481286248011511053074731078808919938689216061999863558",
+        "3261222600550988246488569487636662646083386001431784202863158481286248011511053074731078808919938689216061999863558",
+    ),
+    (
+        "0",
+        "889424345604814976315064405719089812568196182208668418962679585805340366775741747653930584250892369786198727235518",
+    ),
+    (
+        "2668273036814444928945193217157269437704588546626005256888038757416021100327225242961791752752677109358596181706524",
+        "1334136518407222464472596608578634718852294273313002628444019378708010550163612621480895876376338554679298090853263",
+    ),
+    (
+        "2816510427748580758331037284777117739799287910327449993381818688383577828123182200904113516794492504322962636245776",
+        "0",
+    ),
+];
+const G2_ISOGENY_Y_DEN: [(&str, &str); 4] = [
+    (
+        "4002409555221667393417789825735904156556882819939007885332058136124031650490837864442687629129015664037894272559355",
+        "4002409555221667393417789825735904156556882819939007885332058136124031650490837864442687629129015664037894272559355",
+    ),
+    (
+        "0",
+        "4002409555221667393417789825735904156556882819939007885332058136124031650490837864442687629129015664037894272559571",
+    ),
+    (
+        "18",
+        "4002409555221667393417789825735904156556882819939007885332058136124031650490837864442687629129015664037894272559769",
+    ),
+    ("1", "0"),
+];
+
+// (p - 3) / 4 for the base field modulus p, little-endian
+const SQRT_FP2_EXPONENT: [u64; 6] = [
+    0xee7fbfffffffeaaa,
+    0x07aaffffac54ffff,
+    0xd9cc34a83dac3d89,
+    0xd91dd2e13ce144af,
+    0x92c6e9ed90d2eb35,
+    0x0680447a8e5ff9a6,
+];
+
+fn fp_constant<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    value: &str,
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> Bls12_381BaseNNField<F> {
+    Bls12_381BaseNNField::allocated_constant(cs, bls12_381_fq_from_str(value), params)
+}
+
+fn fp2_constant<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    (c0, c1): (&str, &str),
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> Bls12_381Fq2<F> {
+    Bls12_381Fq2::constant(
+        cs,
+        bls12_381_fq_from_str(c0),
+        bls12_381_fq_from_str(c1),
+        params,
+    )
+}
+
+// parity of the canonical representation
+fn fp_sgn0<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    element: &Bls12_381BaseNNField<F>,
+) -> Boolean<F> {
+    let words = convert_field_element_to_u32_words(cs, element.clone());
+    let bits = Num::from_variable(words[0].get_variable()).spread_into_bits::<_, 32>(cs);
+
+    bits[0]
+}
+
+fn fp2_sgn0<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    element: &mut Bls12_381Fq2<F>,
+) -> Boolean<F> {
+    element.normalize(cs);
+    let sign_0 = fp_sgn0(cs, &element.c0);
+    let zero_0 = element.c0.is_zero(cs);
+    let sign_1 = fp_sgn0(cs, &element.c1);
+    let sign_1_if_zero_0 = Boolean::multi_and(cs, &[zero_0, sign_1]);
+
+    Boolean::multi_or(cs, &[sign_0, sign_1_if_zero_0])
+}
+
+fn fp2_pow_by_constant<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    base: &mut Bls12_381Fq2<F>,
+    exponent: &[u64],
+) -> Bls12_381Fq2<F> {
+    let num_bits = exponent.len() * 64 - exponent.last().unwrap().leading_zeros() as usize;
+    let mut result = base.clone();
+    for i in (0..(num_bits - 1)).rev() {
+        result = result.square(cs);
+        if (exponent[i / 64] >> (i % 64)) & 1 == 1 {
+            result = result.mul(cs, base);
+        }
+    }
+
+    result
+}
+
+fn evaluate_fp_polynomial<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    coeffs: &[&str],
+    x: &mut Bls12_381BaseNNField<F>,
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> Bls12_381BaseNNField<F> {
+    let (leading, rest) = coeffs.split_last().unwrap();
+    let mut result = fp_constant(cs, leading, params);
+    for coeff in rest.iter().rev() {
+        let mut coeff = fp_constant(cs, coeff, params);
+        let mut product = result.mul(cs, x);
+        result = product.add(cs, &mut coeff);
+    }
+    result.normalize(cs);
+
+    result
+}
+
+fn evaluate_fp2_polynomial<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    coeffs: &[(&str, &str)],
+    x: &mut Bls12_381Fq2<F>,
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> Bls12_381Fq2<F> {
+    let (leading, rest) = coeffs.split_last().unwrap();
+    let mut result = fp2_constant(cs, *leading, params);
+    for coeff in rest.iter().rev() {
+        let mut coeff = fp2_constant(cs, *coeff, params);
+        let mut product = result.mul(cs, x);
+        result = product.add(cs, &mut coeff);
+    }
+    result.normalize(cs);
+
+    result
+}
+
+/// Maps the element to the point of the curve 11-isogenous to G1
+fn g1_sswu<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    u: &mut Bls12_381BaseNNField<F>,
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> (Bls12_381BaseNNField<F>, Bls12_381BaseNNField<F>) {
+    let mut a = fp_constant(cs, G1_SSWU_A, params);
+    let mut b = fp_constant(cs, G1_SSWU_B, params);
+    let mut z = fp_constant(cs, G1_SSWU_Z, params);
+    let mut minus_b_over_a = fp_constant(cs, G1_SSWU_MINUS_B_OVER_A, params);
+    let b_over_za = fp_constant(cs, G1_SSWU_B_OVER_ZA, params);
+    let mut c2 = fp_constant(cs, G1_SSWU_C2, params);
+    let mut one = fp_constant(cs, "1", params);
+
+    // tv1 = Z^2 u^4 + Z u^2, x1 = -B / A (1 + 1 / tv1), or B / ZA if tv1 is zero
+    let mut u_squared = u.square(cs);
+    let mut z_u_squared = z.mul(cs, &mut u_squared);
+    let mut tv1 = z_u_squared.square(cs);
+    let mut tv1 = tv1.add(cs, &mut z_u_squared);
+    tv1.normalize(cs);
+    let tv1_is_zero = tv1.is_zero(cs);
+    let mut tv1 = Selectable::conditionally_select(cs, tv1_is_zero, &one, &tv1);
+    let mut tv1_inv = tv1.inverse_unchecked(cs);
+    let mut x1 = tv1_inv.add(cs, &mut one);
+    let x1 = x1.mul(cs, &mut minus_b_over_a);
+    let mut x1 = Selectable::conditionally_select(cs, tv1_is_zero, &b_over_za, &x1);
+
+    // gx1 = x1^3 + A x1 + B
+    let mut gx1 = x1.square(cs);
+    let mut gx1 = gx1.add(cs, &mut a);
+    let mut gx1 = gx1.mul(cs, &mut x1);
+    let mut gx1 = gx1.add(cs, &mut b);
+    gx1.normalize(cs);
+
+    let mut y1 = pow_by_constant(cs, &mut gx1, &SQRT_EXPONENT);
+    let mut y1_squared = y1.square(cs);
+    y1_squared.normalize(cs);
+    let gx1_is_square = NonNativeFieldOverU16::equals(cs, &mut y1_squared, &mut gx1);
+
+    // otherwise y1^2 = -gx1, and gx2 = Z^3 u^6 gx1, so y2 = sqrt(-Z^3) u^3 y1 is the root of gx2
+    let x2 = z_u_squared.mul(cs, &mut x1);
+    let mut y2 = y1.mul(cs, &mut u_squared);
+    let mut y2 = y2.mul(cs, u);
+    let y2 = y2.mul(cs, &mut c2);
+
+    let x = Selectable::conditionally_select(cs, gx1_is_square, &x1, &x2);
+    let mut y = Selectable::conditionally_select(cs, gx1_is_square, &y1, &y2);
+    y.normalize(cs);
+
+    let u_sign = fp_sgn0(cs, u);
+    let y_sign = fp_sgn0(cs, &y);
+    let should_negate = u_sign.xor(cs, y_sign);
+    let y_negated = y.negated(cs);
+    let y = Selectable::conditionally_select(cs, should_negate, &y_negated, &y);
+
+    (x, y)
+}
+
+/// Maps the element to the point of G1, that may be at infinity
+fn bls12_381_map_fp_to_g1<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    u: &mut Bls12_381BaseNNField<F>,
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> Bls12_381G1ProjectivePoint<F> {
+    let (mut x, mut y) = g1_sswu(cs, u, params);
+
+    // isogeny map, denominators are zero only for the points that map to infinity
+    let mut x_num = evaluate_fp_polynomial(cs, &G1_ISOGENY_X_NUM, &mut x, params);
+    let mut x_den = evaluate_fp_polynomial(cs, &G1_ISOGENY_X_DEN, &mut x, params);
+    let mut y_num = evaluate_fp_polynomial(cs, &G1_ISOGENY_Y_NUM, &mut x, params);
+    let mut y_den = evaluate_fp_polynomial(cs, &G1_ISOGENY_Y_DEN, &mut x, params);
+
+    let x_den_is_zero = x_den.is_zero(cs);
+    let y_den_is_zero = y_den.is_zero(cs);
+    let is_infinity = Boolean::multi_or(cs, &[x_den_is_zero, y_den_is_zero]);
+
+    let one = fp_constant(cs, "1", params);
+    let mut x_den = Selectable::conditionally_select(cs, is_infinity, &one, &x_den);
+    let mut y_den = Selectable::conditionally_select(cs, is_infinity, &one, &y_den);
+    let mut x_den_inv = x_den.inverse_unchecked(cs);
+    let mut y_den_inv = y_den.inverse_unchecked(cs);
+    let x = x_num.mul(cs, &mut x_den_inv);
+    let mut y = y.mul(cs, &mut y_num);
+    let y = y.mul(cs, &mut y_den_inv);
+
+    // clear cofactor by multiplication with h_eff = 1 - x
+    let h_eff = BLS12_381_X + 1;
+    let mut point = (x, y);
+    let mut acc =
+        Bls12_381G1ProjectivePoint::from_xy_unchecked(cs, point.0.clone(), point.1.clone());
+    let num_bits = 64 - h_eff.leading_zeros();
+    for i in (0..(num_bits - 1)).rev() {
+        acc = acc.double(cs);
+        if (h_eff >> i) & 1 == 1 {
+            acc = acc.add_mixed(cs, &mut point);
+        }
+    }
+
+    let zero = Bls12_381G1ProjectivePoint::zero(cs, params);
+    Selectable::conditionally_select(cs, is_infinity, &zero, &acc)
+}
+
+fn bls12_381_map_fp_to_g1_inner<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    words: &[UInt256<F>; FP_WORDS],
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> (Boolean<F>, [UInt256<F>; G1_POINT_WORDS]) {
+    let (mut u, _, u_is_invalid) = bls12_381_decode_fp(cs, words, params);
+    let mut point = bls12_381_map_fp_to_g1(cs, &mut u, params);
+    let result = bls12_381_encode_g1_point(cs, &mut point);
+
+    let result = result.map(|el| el.mask_negated(cs, u_is_invalid));
+    let success = u_is_invalid.negated(cs);
+
+    (success, result)
+}
+
+/// Maps the element to the point of the curve 3-isogenous to the twist
+fn g2_sswu<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    u: &mut Bls12_381Fq2<F>,
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> Bls12_381G2AffinePoint<F> {
+    let mut a = fp2_constant(cs, G2_SSWU_A, params);
+    let mut b = fp2_constant(cs, G2_SSWU_B, params);
+    let mut z = fp2_constant(cs, G2_SSWU_Z, params);
+    let mut minus_b_over_a = fp2_constant(cs, G2_SSWU_MINUS_B_OVER_A, params);
+    let b_over_za = fp2_constant(cs, G2_SSWU_B_OVER_ZA, params);
+    let mut one = Bls12_381Fq2::one(cs, params);
+
+    // the same as for G1, but tv1 is zero only if u is zero
+    let mut u_squared = u.square(cs);
+    let mut z_u_squared = z.mul(cs, &mut u_squared);
+    let mut tv1 = z_u_squared.square(cs);
+    let mut tv1 = tv1.add(cs, &mut z_u_squared);
+    tv1.normalize(cs);
+    let tv1_is_zero = tv1.is_zero(cs);
+    let mut tv1 = Bls12_381Fq2::conditionally_select(cs, tv1_is_zero, &one, &tv1);
+    let mut tv1_inv = tv1.inverse(cs);
+    let mut x1 = tv1_inv.add(cs, &mut one);
+    let x1 = x1.mul(cs, &mut minus_b_over_a);
+    let mut x1 = Bls12_381Fq2::conditionally_select(cs, tv1_is_zero, &b_over_za, &x1);
+
+    let mut x2 = z_u_squared.mul(cs, &mut x1);
+
+    let mut curve_rhs = |cs: &mut CS, x: &mut Bls12_381Fq2<F>| {
+        let mut gx = x.square(cs);
+        let mut gx = gx.add(cs, &mut a);
+        let mut gx = gx.mul(cs, x);
+        gx.add(cs, &mut b)
+    };
+    let mut gx1 = curve_rhs(cs, &mut x1);
+    let gx2 = curve_rhs(cs, &mut x2);
+
+    // element of Fq2 is a square if and only if its norm is a square in Fq
+    let mut c0_squared = gx1.c0.square(cs);
+    let mut c1_squared = gx1.c1.square(cs);
+    let mut norm = c0_squared.add(cs, &mut c1_squared);
+    norm.normalize(cs);
+    let mut legendre = pow_by_constant(cs, &mut norm, &HALF_MODULUS);
+    legendre.normalize(cs);
+    let mut minus_one = one.c0.negated(cs);
+    minus_one.normalize(cs);
+    let gx1_is_not_square = NonNativeFieldOverU16::equals(cs, &mut legendre, &mut minus_one);
+
+    let x = Bls12_381Fq2::conditionally_select(cs, gx1_is_not_square, &x2, &x1);
+    let mut gx = Bls12_381Fq2::conditionally_select(cs, gx1_is_not_square, &gx2, &gx1);
+
+    // square root for p = 3 mod 4, algorithm 9 from https://eprint.iacr.org/2012/685
+    let mut a1 = fp2_pow_by_constant(cs, &mut gx, &SQRT_FP2_EXPONENT);
+    let mut alpha = a1.square(cs);
+    let mut alpha = alpha.mul(cs, &mut gx);
+    let mut x0 = a1.mul(cs, &mut gx);
+
+    let mut minus_one = one.negated(cs);
+    let alpha_is_minus_one = Bls12_381Fq2::equals(cs, &mut alpha, &mut minus_one);
+    // i * x0
+    let y_if_minus_one = Bls12_381Fq2 {
+        c0: x0.c1.negated(cs),
+        c1: x0.c0.clone(),
+    };
+    let mut one_plus_alpha = alpha.add(cs, &mut one);
+    let mut b = fp2_pow_by_constant(cs, &mut one_plus_alpha, &HALF_MODULUS);
+    let y_otherwise = b.mul(cs, &mut x0);
+    let mut y =
+        Bls12_381Fq2::conditionally_select(cs, alpha_is_minus_one, &y_if_minus_one, &y_otherwise);
+
+    let u_sign = fp2_sgn0(cs, u);
+    let y_sign = fp2_sgn0(cs, &mut y);
+    let should_negate = u_sign.xor(cs, y_sign);
+    let y_negated = y.negated(cs);
+    let y = Bls12_381Fq2::conditionally_select(cs, should_negate, &y_negated, &y);
+
+    Bls12_381G2AffinePoint { x, y }
+}
+
+fn mul_by_bls_x_abs<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    point: &mut Bls12_381G2ProjectivePoint<F>,
+    curve_b3: &mut Bls12_381Fq2<F>,
+) -> Bls12_381G2ProjectivePoint<F> {
+    let mut acc = point.clone();
+    let num_bits = 64 - BLS12_381_X.leading_zeros();
+    for i in (0..(num_bits - 1)).rev() {
+        acc = acc.double(cs, curve_b3);
+        if (BLS12_381_X >> i) & 1 == 1 {
+            acc = acc.add(cs, point, curve_b3);
+        }
+    }
+
+    acc
+}
+
+fn bls12_381_map_fp2_to_g2<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    u: &mut Bls12_381Fq2<F>,
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> Bls12_381G2ProjectivePoint<F> {
+    let mut point = g2_sswu(cs, u, params);
+
+    let mut x_num = evaluate_fp2_polynomial(cs, &G2_ISOGENY_X_NUM, &mut point.x, params);
+    let mut x_den = evaluate_fp2_polynomial(cs, &G2_ISOGENY_X_DEN, &mut point.x, params);
+    let mut y_num = evaluate_fp2_polynomial(cs, &G2_ISOGENY_Y_NUM, &mut point.x, params);
+    let mut y_den = evaluate_fp2_polynomial(cs, &G2_ISOGENY_Y_DEN, &mut point.x, params);
+
+    let x_den_is_zero = x_den.is_zero(cs);
+    let y_den_is_zero = y_den.is_zero(cs);
+    let is_infinity = Boolean::multi_or(cs, &[x_den_is_zero, y_den_is_zero]);
+
+    let one = Bls12_381Fq2::one(cs, params);
+    let mut x_den = Bls12_381Fq2::conditionally_select(cs, is_infinity, &one, &x_den);
+    let mut y_den = Bls12_381Fq2::conditionally_select(cs, is_infinity, &one, &y_den);
+    let x = x_num.div(cs, &mut x_den);
+    let mut y = point.y.mul(cs, &mut y_num);
+    let y = y.div(cs, &mut y_den);
+
+    // clear cofactor with the endomorphism, as in RFC 9380, appendix G.3:
+    // h_eff P = [x^2 - x - 1] P + [x - 1] psi(P) + psi^2(2P)
+    let mut curve_b3 = g2_curve_b3(cs, params);
+    let mut p =
+        Bls12_381G2ProjectivePoint::from_affine(cs, &Bls12_381G2AffinePoint { x, y }, params);
+
+    let mut t1 = mul_by_bls_x_abs(cs, &mut p, &mut curve_b3);
+    let mut t1 = t1.negated(cs);
+    let mut t2 = p.psi(cs, params);
+    let mut t3 = p.double(cs, &mut curve_b3);
+    let mut t3 = t3.psi(cs, params);
+    let mut t3 = t3.psi(cs, params);
+    let mut t2_negated = t2.negated(cs);
+    let mut t3 = t3.add(cs, &mut t2_negated, &mut curve_b3);
+    let mut t2 = t1.add(cs, &mut t2, &mut curve_b3);
+    let mut t2 = mul_by_bls_x_abs(cs, &mut t2, &mut curve_b3);
+    let mut t2 = t2.negated(cs);
+    let mut t3 = t3.add(cs, &mut t2, &mut curve_b3);
+    let mut t1_negated = t1.negated(cs);
+    let mut t3 = t3.add(cs, &mut t1_negated, &mut curve_b3);
+    let mut p_negated = p.negated(cs);
+    let result = t3.add(cs, &mut p_negated, &mut curve_b3);
+
+    let zero = Bls12_381G2ProjectivePoint::zero(cs, params);
+    Bls12_381G2ProjectivePoint::conditionally_select(cs, is_infinity, &zero, &result)
+}
+
+fn bls12_381_map_fp2_to_g2_inner<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    words: &[UInt256<F>; FP2_WORDS],
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> (Boolean<F>, [UInt256<F>; G2_POINT_WORDS]) {
+    let (mut u, _, u_is_invalid) = bls12_381_decode_fp2(cs, words, params);
+    let mut point = bls12_381_map_fp2_to_g2(cs, &mut u, params);
+    let result = bls12_381_encode_g2_point(cs, &mut point, params);
+
+    let result = result.map(|el| el.mask_negated(cs, u_is_invalid));
+    let success = u_is_invalid.negated(cs);
+
+    (success, result)
+}
+
+pub fn bls12_381_map_fp_to_g1_function_entry_point<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    witness: Bls12_381CircuitInstanceWitness<F>,
+    round_function: &R,
+    limit: usize,
+) -> [Num<F>; INPUT_OUTPUT_COMMITMENT_LENGTH]
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    let params = Arc::new(bls12_381_base_field_params());
+
+    bls12_381_per_call_function_entry_point(
+        cs,
+        witness,
+        round_function,
+        limit,
+        BLS12_381_MAP_FP_TO_G1_PRECOMPILE_FORMAL_ADDRESS,
+        |cs: &mut CS, words: &[UInt256<F>; FP_WORDS]| {
+            bls12_381_map_fp_to_g1_inner(cs, words, &params)
+        },
+    )
+}
+
+pub fn bls12_381_map_fp2_to_g2_function_entry_point<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    witness: Bls12_381CircuitInstanceWitness<F>,
+    round_function: &R,
+    limit: usize,
+) -> [Num<F>; INPUT_OUTPUT_COMMITMENT_LENGTH]
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    let params = Arc::new(bls12_381_base_field_params());
+
+    bls12_381_per_call_function_entry_point(
+        cs,
+        witness,
+        round_function,
+        limit,
+        BLS12_381_MAP_FP2_TO_G2_PRECOMPILE_FORMAL_ADDRESS,
+        |cs: &mut CS, words: &[UInt256<F>; FP2_WORDS]| {
+            bls12_381_map_fp2_to_g2_inner(cs, words, &params)
+        },
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test_utils::*;
+    use super::*;
+
+    use boojum::gadgets::traits::allocatable::CSAllocatable;
+    use boojum::worker::Worker;
+
+    // 48 bytes big-endian, as in the test vectors of RFC 9380
+    fn fp_words_from_hex(value: &str) -> [U256; FP_WORDS] {
+        assert_eq!(value.len(), 96);
+        [
+            U256::from_str_radix(&value[..32], 16).unwrap(),
+            U256::from_str_radix(&value[32..], 16).unwrap(),
+        ]
+    }
+
+    fn words_from_hex<const N: usize>(values: &[&str]) -> [U256; N] {
+        let words: Vec<U256> = values.iter().flat_map(|el| fp_words_from_hex(el)).collect();
+        words.try_into().unwrap()
+    }
+
+    fn run_map_fp_to_g1(input: [U256; FP_WORDS]) -> (bool, [U256; G1_POINT_WORDS]) {
+        let mut owned_cs = create_cs(1 << 21);
+        let cs = &mut owned_cs;
+
+        let params = Arc::new(bls12_381_base_field_params());
+        let words = input.map(|el| UInt256::allocate(cs, el));
+        let (success, result) = bls12_381_map_fp_to_g1_inner(cs, &words, &params);
+
+        let success = success.witness_hook(&*cs)().unwrap();
+        let result = result.map(|el| el.witness_hook(&*cs)().unwrap());
+
+        cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        let worker = Worker::new();
+        assert!(cs.check_if_satisfied(&worker));
+
+        (success, result)
+    }
+
+    fn run_map_fp2_to_g2(input: [U256; FP2_WORDS]) -> (bool, [U256; G2_POINT_WORDS]) {
+        let mut owned_cs = create_cs(1 << 23);
+        let cs = &mut owned_cs;
+
+        let params = Arc::new(bls12_381_base_field_params());
+        let words = input.map(|el| UInt256::allocate(cs, el));
+        let (success, result) = bls12_381_map_fp2_to_g2_inner(cs, &words, &params);
+
+        let success = success.witness_hook(&*cs)().unwrap();
+        let result = result.map(|el| el.witness_hook(&*cs)().unwrap());
+
+        cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        let worker = Worker::new();
+        assert!(cs.check_if_satisfied(&worker));
+
+        (success, result)
+    }
+
+    #[test]
+    fn test_bls12_381_map_fp_to_g1() {
+        // test vectors from RFC 9380, appendix J.9.2, for messages "" and "abc"
+        let (success, result) = run_map_fp_to_g1(fp_words_from_hex(
+            "156c8a6a2c184569d69a76be144b5cdc5141d2d2ca4fe341f011e25e3969c55ad9e9b9ce2eb833c81a908e5fa4ac5f03",
+        ));
+        assert!(success);
+        assert_eq!(
+            result,
+            words_from_hex(&[
+                "184bb665c37ff561a89ec2122dd343f20e0f4cbcaec84e3c3052ea81d1834e192c426074b02ed3dca4e7676ce4ce48ba",
+                "04407b8d35af4dacc809927071fc0405218f1401a6d15af775810e4e460064bcc9468beeba82fdc751be70476c888bf3",
+            ])
+        );
+
+        let (success, result) = run_map_fp_to_g1(fp_words_from_hex(
+            "147e1ed29f06e4c5079b9d14fc89d2820d32419b990c1c7bb7dbea2a36a045124b31ffbde7c99329c05c559af1c6cc82",
+        ));
+        assert!(success);
+        assert_eq!(
+            result,
+            words_from_hex(&[
+                "009769f3ab59bfd551d53a5f846b9984c59b97d6842b20a2c565baa167945e3d026a3755b6345df8ec7e6acb6868ae6d",
+                "1532c00cf61aa3d0ce3e5aa20c3b531a2abd2c770a790a2613818303c6b830ffc0ecf6c357af3317b9575c567f11cd2c",
+            ])
+        );
+
+        // exceptional case of the map
+        let (success, result) = run_map_fp_to_g1([U256::zero(); FP_WORDS]);
+        assert!(success);
+        assert_eq!(
+            result,
+            words_from_hex(&[
+                "11a9a0372b8f332d5c30de9ad14e50372a73fa4c45d5f2fa5097f2d6fb93bcac592f2e1711ac43db0519870c7d0ea415",
+                "092c0f994164a0719f51c24ba3788de240ff926b55f58c445116e8bc6a47cd63392fd4e8e22bdf9feaa96ee773222133",
+            ])
+        );
+    }
+
+    #[test]
+    fn test_bls12_381_map_fp_to_g1_invalid_input() {
+        // modulus itself is not a canonical encoding
+        let (success, result) = run_map_fp_to_g1(fp_words_from_hex(
+            "1a0111ea397fe69a4b1ba7b6434bacd764774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffaaab",
+        ));
+        assert!(!success);
+        assert_eq!(result, [U256::zero(); G1_POINT_WORDS]);
+    }
+
+    #[test]
+    fn test_bls12_381_map_fp2_to_g2() {
+        // test vectors from RFC 9380, appendix J.10.2, for messages "" and "abc"
+        let (success, result) = run_map_fp2_to_g2(words_from_hex(&[
+            "07355d25caf6e7f2f0cb2812ca0e513bd026ed09dda65b177500fa31714e09ea0ded3a078b526bed3307f804d4b93b04",
+            "02829ce3c021339ccb5caf3e187f6370e1e2a311dec9b75363117063ab2015603ff52c3d3b98f19c2f65575e99e8b78c",
+        ]));
+        assert!(success);
+        assert_eq!(
+            result,
+            words_from_hex(&[
+                "00e7f4568a82b4b7dc1f14c6aaa055edf51502319c723c4dc2688c7fe5944c213f510328082396515734b6612c4e7bb7",
+                "126b855e9e69b1f691f816e48ac6977664d24d99f8724868a184186469ddfd4617367e94527d4b74fc86413483afb35b",
+                "0caead0fd7b6176c01436833c79d305c78be307da5f6af6c133c47311def6ff1e0babf57a0fb5539fce7ee12407b0a42",
+                "1498aadcf7ae2b345243e281ae076df6de84455d766ab6fcdaad71fab60abb2e8b980a440043cd305db09d283c895e3d",
+            ])
+        );
+
+        let (success, result) = run_map_fp2_to_g2(words_from_hex(&[
+            "138879a9559e24cecee8697b8b4ad32cced053138ab913b99872772dc753a2967ed50aabc907937aefb2439ba06cc50c",
+            "0a1ae7999ea9bab1dcc9ef8887a6cb6e8f1e22566015428d220b7eec90ffa70ad1f624018a9ad11e78d588bd3617f9f2",
+        ]));
+        assert!(success);
+        assert_eq!(
+            result,
+            words_from_hex(&[
+                "108ed59fd9fae381abfd1d6bce2fd2fa220990f0f837fa30e0f27914ed6e1454db0d1ee957b219f61da6ff8be0d6441f",
+                "0296238ea82c6d4adb3c838ee3cb2346049c90b96d602d7bb1b469b905c9228be25c627bffee872def773d5b2a2eb57d",
+                "033f90f6057aadacae7963b0a0b379dd46750c1c94a6357c99b65f63b79e321ff50fe3053330911c56b6ceea08fee656",
+                "153606c417e59fb331b7ae6bce4fbf7c5190c33ce9402b5ebe2b70e44fca614f3f1382a3625ed5493843d0b0a652fc3f",
+            ])
+        );
+
+        // exceptional case of the map
+        let (success, result) = run_map_fp2_to_g2([U256::zero(); FP2_WORDS]);
+        assert!(success);
+        assert_eq!(
+            result,
+            words_from_hex(&[
+                "018320896ec9eef9d5e619848dc29ce266f413d02dd31d9b9d44ec0c79cd61f18b075ddba6d7bd20b7ff27a4b324bfce",
+                "0a67d12118b5a35bb02d2e86b3ebfa7e23410db93de39fb06d7025fa95e96ffa428a7a27c3ae4dd4b40bd251ac658892",
+                "0260e03644d1a2c321256b3246bad2b895cad13890cbe6f85df55106a0d334604fb143c7a042d878006271865bc35941",
+                "04c69777a43f0bda07679d5805e63f18cf4e0e7c6112ac7f70266d199b4f76ae27c6269a3ceebdae30806e9a76aadf5c",
+            ])
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use super::*;
+use crate::base_structures::log_query::*;
+use crate::base_structures::memory_query::*;
+
+use crate::ethereum_types::U256;
+
+use crate::fsm_input_output::*;
+
+use boojum::cs::traits::cs::ConstraintSystem;
+use boojum::field::SmallField;
+use boojum::gadgets::boolean::Boolean;
+
+use boojum::gadgets::non_native_field::implementations::*;
+
+use boojum::gadgets::queue::QueueState;
+
+use boojum::gadgets::traits::allocatable::{CSAllocatable, CSPlaceholder};
+use boojum::gadgets::traits::encodable::CircuitVarLengthEncodable;
+use boojum::gadgets::traits::selectable::Selectable;
+use boojum::gadgets::traits::witnessable::WitnessHookable;
+use boojum::gadgets::u256::UInt256;
+use boojum::gadgets::u32::UInt32;
+
+use cs_derive::*;
+use std::sync::Arc;
+
+use crate::base_structures::precompile_input_outputs::formal_precompile_address;
+use zkevm_opcode_defs::ethereum_types::H160;
+
+use crate::base_structures::precompile_input_outputs::*;
+use crate::demux_log_queue::DemuxOutput;
+use crate::precompile_registry::PrecompileCircuit;
+use crate::scheduler::auxiliary::BaseLayerCircuitType;
+
+pub mod input;
+pub use self::input::*;
+
+pub mod add;
+pub mod baseline;
+pub mod curves;
+pub mod map_to_curve;
+pub mod msm;
+pub mod pairing;
+pub mod pairing_check;
+#[cfg(test)]
+mod test_utils;
+
+// field and curve types are shared with the KZG point evaluation
+pub(crate) use crate::kzg_point_evaluation::{
+    bls12_381_base_field_params, bls12_381_scalar_field_params, Bls12_381BaseNNField,
+    Bls12_381BaseNNFieldParams, Bls12_381Fq, Bls12_381G1Affine, Bls12_381G2Affine,
+    BASE_FIELD_CANONICAL_REPR_LIMBS, BASE_FIELD_REPR_LIMBS,
+};
+
+// Element of the base field takes 64 bytes with the top 16 bytes being zero, so it's encoded by two words
+pub const FP_WORDS: usize = 2;
+pub const FP2_WORDS: usize = 2 * FP_WORDS;
+pub const G1_POINT_WORDS: usize = 2 * FP_WORDS;
+pub const G2_POINT_WORDS: usize = 2 * FP2_WORDS;
+// scalars are arbitrary 32 bytes integers
+pub const SCALAR_WORDS: usize = 1;
+
+pub const G1_MSM_PAIR_WORDS: usize = G1_POINT_WORDS + SCALAR_WORDS;
+pub const G2_MSM_PAIR_WORDS: usize = G2_POINT_WORDS + SCALAR_WORDS;
+pub const PAIRING_PAIR_WORDS: usize = G1_POINT_WORDS + G2_POINT_WORDS;
+
+pub const BLS12_381_G1ADD_PRECOMPILE_ADDRESS: u16 = 0x0b;
+pub const BLS12_381_G1MSM_PRECOMPILE_ADDRESS: u16 = 0x0c;
+pub const BLS12_381_G2ADD_PRECOMPILE_ADDRESS: u16 = 0x0d;
+pub const BLS12_381_G2MSM_PRECOMPILE_ADDRESS: u16 = 0x0e;
+pub const BLS12_381_PAIRING_CHECK_PRECOMPILE_ADDRESS: u16 = 0x0f;
+pub const BLS12_381_MAP_FP_TO_G1_PRECOMPILE_ADDRESS: u16 = 0x10;
+pub const BLS12_381_MAP_FP2_TO_G2_PRECOMPILE_ADDRESS: u16 = 0x11;
+
+pub const BLS12_381_G1ADD_PRECOMPILE_FORMAL_ADDRESS: H160 =
+    formal_precompile_address(BLS12_381_G1ADD_PRECOMPILE_ADDRESS);
+pub const BLS12_381_G1MSM_PRECOMPILE_FORMAL_ADDRESS: H160 =
+    formal_precompile_address(BLS12_381_G1MSM_PRECOMPILE_ADDRESS);
+pub const BLS12_381_G2ADD_PRECOMPILE_FORMAL_ADDRESS: H160 =
+    formal_precompile_address(BLS12_381_G2ADD_PRECOMPILE_ADDRESS);
+pub const BLS12_381_G2MSM_PRECOMPILE_FORMAL_ADDRESS: H160 =
+    formal_precompile_address(BLS12_381_G2MSM_PRECOMPILE_ADDRESS);
+pub const BLS12_381_PAIRING_CHECK_PRECOMPILE_FORMAL_ADDRESS: H160 =
+    formal_precompile_address(BLS12_381_PAIRING_CHECK_PRECOMPILE_ADDRESS);
+pub const BLS12_381_MAP_FP_TO_G1_PRECOMPILE_FORMAL_ADDRESS: H160 =
+    formal_precompile_address(BLS12_381_MAP_FP_TO_G1_PRECOMPILE_ADDRESS);
+pub const BLS12_381_MAP_FP2_TO_G2_PRECOMPILE_FORMAL_ADDRESS: H160 =
+    formal_precompile_address(BLS12_381_MAP_FP2_TO_G2_PRECOMPILE_ADDRESS);
+
+pub struct Bls12_381G1AddPrecompileCircuit;
+
+impl PrecompileCircuit for Bls12_381G1AddPrecompileCircuit {
+    const NAME: &'static str = "bls12_381_g1add";
+    const DEMUX_OUTPUT: DemuxOutput = DemuxOutput::Bls12_381G1Add;
+    const CIRCUIT_TYPE: BaseLayerCircuitType = BaseLayerCircuitType::Bls12_381G1AddPrecompile;
+
+    type InputData<F: SmallField> = PrecompileFunctionInputData<F>;
+    type OutputData<F: SmallField> = PrecompileFunctionOutputData<F>;
+
+    fn formal_address() -> H160 {
+        BLS12_381_G1ADD_PRECOMPILE_FORMAL_ADDRESS
+    }
+}
+
+pub struct Bls12_381G1MsmPrecompileCircuit;
+
+impl PrecompileCircuit for Bls12_381G1MsmPrecompileCircuit {
+    const NAME: &'static str = "bls12_381_g1msm";
+    const DEMUX_OUTPUT: DemuxOutput = DemuxOutput::Bls12_381G1Msm;
+    const CIRCUIT_TYPE: BaseLayerCircuitType = BaseLayerCircuitType::Bls12_381G1MsmPrecompile;
+
+    type InputData<F: SmallField> = PrecompileFunctionInputData<F>;
+    type OutputData<F: SmallField> = PrecompileFunctionOutputData<F>;
+
+    fn formal_address() -> H160 {
+        BLS12_381_G1MSM_PRECOMPILE_FORMAL_ADDRESS
+    }
+}
+
+pub struct Bls12_381G2AddPrecompileCircuit;
+
+impl PrecompileCircuit for Bls12_381G2AddPrecompileCircuit {
+    const NAME: &'static str = "bls12_381_g2add";
+    const DEMUX_OUTPUT: DemuxOutput = DemuxOutput::Bls12_381G2Add;
+    const CIRCUIT_TYPE: BaseLayerCircuitType = BaseLayerCircuitType::Bls12_381G2AddPrecompile;
+
+    type InputData<F: SmallField> = PrecompileFunctionInputData<F>;
+    type OutputData<F: SmallField> = PrecompileFunctionOutputData<F>;
+
+    fn formal_address() -> H160 {
+        BLS12_381_G2ADD_PRECOMPILE_FORMAL_ADDRESS
+    }
+}
+
+pub struct Bls12_381G2MsmPrecompileCircuit;
+
+impl PrecompileCircuit for Bls12_381G2MsmPrecompileCircuit {
+    const NAME: &'static str = "bls12_381_g2msm";
+    const DEMUX_OUTPUT: DemuxOutput = DemuxOutput::Bls12_381G2Msm;
+    const CIRCUIT_TYPE: BaseLayerCircuitType = BaseLayerCircuitType::Bls12_381G2MsmPrecompile;
+
+    type InputData<F: SmallField> = PrecompileFunctionInputData<F>;
+    type OutputData<F: SmallField> = PrecompileFunctionOutputData<F>;
+
+    fn formal_address() -> H160 {
+        BLS12_381_G2MSM_PRECOMPILE_FORMAL_ADDRESS
+    }
+}
+
+pub struct Bls12_381PairingCheckPrecompileCircuit;
+
+impl PrecompileCircuit for Bls12_381PairingCheckPrecompileCircuit {
+    const NAME: &'static str = "bls12_381_pairing_check";
+    const DEMUX_OUTPUT: DemuxOutput = DemuxOutput::Bls12_381PairingCheck;
+    const CIRCUIT_TYPE: BaseLayerCircuitType =
+        BaseLayerCircuitType::Bls12_381PairingCheckPrecompile;
+
+    type InputData<F: SmallField> = PrecompileFunctionInputData<F>;
+    type OutputData<F: SmallField> = PrecompileFunctionOutputData<F>;
+
+    fn formal_address() -> H160 {
+        BLS12_381_PAIRING_CHECK_PRECOMPILE_FORMAL_ADDRESS
+    }
+}
+
+pub struct Bls12_381MapFpToG1PrecompileCircuit;
+
+impl PrecompileCircuit for Bls12_381MapFpToG1PrecompileCircuit {
+    const NAME: &'static str = "bls12_381_map_fp_to_g1";
+    const DEMUX_OUTPUT: DemuxOutput = DemuxOutput::Bls12_381MapFpToG1;
+    const CIRCUIT_TYPE: BaseLayerCircuitType = BaseLayerCircuitType::Bls12_381MapFpToG1Precompile;
+
+    type InputData<F: SmallField> = PrecompileFunctionInputData<F>;
+    type OutputData<F: SmallField> = PrecompileFunctionOutputData<F>;
+
+    fn formal_address() -> H160 {
+        BLS12_381_MAP_FP_TO_G1_PRECOMPILE_FORMAL_ADDRESS
+    }
+}
+
+pub struct Bls12_381MapFp2ToG2PrecompileCircuit;
+
+impl PrecompileCircuit for Bls12_381MapFp2ToG2PrecompileCircuit {
+    const NAME: &'static str = "bls12_381_map_fp2_to_g2";
+    const DEMUX_OUTPUT: DemuxOutput = DemuxOutput::Bls12_381MapFp2ToG2;
+    const CIRCUIT_TYPE: BaseLayerCircuitType = BaseLayerCircuitType::Bls12_381MapFp2ToG2Precompile;
+
+    type InputData<F: SmallField> = PrecompileFunctionInputData<F>;
+    type OutputData<F: SmallField> = PrecompileFunctionOutputData<F>;
+
+    fn formal_address() -> H160 {
+        BLS12_381_MAP_FP2_TO_G2_PRECOMPILE_FORMAL_ADDRESS
+    }
+}
+
+#[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
+#[derivative(Clone, Copy, Debug)]
+pub struct Bls12_381PrecompileCallParams<F: SmallField> {
+    pub input_page: UInt32<F>,
+    pub input_offset: UInt32<F>,
+    pub output_page: UInt32<F>,
+    pub output_offset: UInt32<F>,
+    // only used by MSM and pairing check, that take a variable number of pairs
+    pub num_pairs: UInt32<F>,
+}
+
+impl<F: SmallField> CSPlaceholder<F> for Bls12_381PrecompileCallParams<F> {
+    fn placeholder<CS: ConstraintSystem<F>>(cs: &mut CS) -> Self {
+        let zero_u32 = UInt32::zero(cs);
+        Self {
+            input_page: zero_u32,
+            input_offset: zero_u32,
+            output_page: zero_u32,
+            output_offset: zero_u32,
+            num_pairs: zero_u32,
+        }
+    }
+}
+
+impl<F: SmallField> Bls12_381PrecompileCallParams<F> {
+    pub fn from_encoding<CS: ConstraintSystem<F>>(_cs: &mut CS, encoding: UInt256<F>) -> Self {
+        let input_offset = encoding.inner[0];
+        let output_offset = encoding.inner[2];
+        let input_page = encoding.inner[4];
+        let output_page = encoding.inner[5];
+
+        let num_pairs = encoding.inner[6];
+
+        let new = Self {
+            input_page,
+            input_offset,
+            output_page,
+            output_offset,
+            num_pairs,
+        };
+
+        new
+    }
+}
+
+// re-exports for integration
+pub use self::add::{bls12_381_g1add_function_entry_point, bls12_381_g2add_function_entry_point};
+pub use self::map_to_curve::{
+    bls12_381_map_fp2_to_g2_function_entry_point, bls12_381_map_fp_to_g1_function_entry_point,
+};
+pub use self::msm::{bls12_381_g1msm_function_entry_point, bls12_381_g2msm_function_entry_point};
+pub use self::pairing_check::bls12_381_pairing_check_function_entry_point;
@@ -0,0 +1,687 @@
+use super::*;
+
+use super::curves::*;
+use crate::demux_log_queue::StorageLogQueue;
+use crate::fsm_input_output::circuit_inputs::INPUT_OUTPUT_COMMITMENT_LENGTH;
+use crate::kzg_point_evaluation::baseline::u256_into_bits;
+use crate::storage_application::ConditionalWitnessAllocator;
+
+use boojum::algebraic_props::round_function::AlgebraicRoundFunction;
+use boojum::gadgets::num::Num;
+use boojum::gadgets::queue::CircuitQueueWitness;
+use boojum::gadgets::traits::allocatable::CSAllocatableExt;
+use boojum::gadgets::traits::round_function::CircuitRoundFunction;
+use boojum::gadgets::u160::UInt160;
+use boojum::gadgets::u8::UInt8;
+
+use std::sync::RwLock;
+use zkevm_opcode_defs::system_params::PRECOMPILE_AUX_BYTE;
+
+/// Multiplies the point of the pair by its scalar and adds the product to the accumulator.
+/// Returns the new accumulator and the flag that the point is invalid
+fn bls12_381_g1msm_pair_inner<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    words: &[UInt256<F>; G1_MSM_PAIR_WORDS],
+    accumulator: &[UInt256<F>; G1_POINT_WORDS],
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> ([UInt256<F>; G1_POINT_WORDS], Boolean<F>) {
+    let point_words = std::array::from_fn(|i| words[i]);
+    let (mut point, point_is_infinity, point_is_invalid) =
+        bls12_381_validate_and_mask_g1_point(cs, &point_words, true, params);
+    let scalar_bits = u256_into_bits(cs, &words[G1_POINT_WORDS]);
+
+    // scalar is not reduced modulo the group order, so we go over all the bits
+    let mut acc = Bls12_381G1ProjectivePoint::zero(cs, params);
+    for bit in scalar_bits.into_iter().rev() {
+        acc = acc.double(cs);
+        let sum = acc.add_mixed(cs, &mut point);
+        acc = Selectable::conditionally_select(cs, bit, &sum, &acc);
+    }
+
+    // point at infinity was masked to the generator, so we drop its product
+    let zero = Bls12_381G1ProjectivePoint::zero(cs, params);
+    let mut acc = Selectable::conditionally_select(cs, point_is_infinity, &zero, &acc);
+
+    let (mut previous, previous_is_infinity) =
+        bls12_381_decode_g1_point_unchecked(cs, accumulator, params);
+    let sum = acc.add_mixed(cs, &mut previous);
+    let mut acc = Selectable::conditionally_select(cs, previous_is_infinity, &acc, &sum);
+
+    let result = bls12_381_encode_g1_point(cs, &mut acc);
+
+    (result, point_is_invalid)
+}
+
+fn bls12_381_g2msm_pair_inner<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    words: &[UInt256<F>; G2_MSM_PAIR_WORDS],
+    accumulator: &[UInt256<F>; G2_POINT_WORDS],
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> ([UInt256<F>; G2_POINT_WORDS], Boolean<F>) {
+    let point_words = std::array::from_fn(|i| words[i]);
+    let (point, point_is_infinity, point_is_invalid) =
+        bls12_381_validate_and_mask_g2_point(cs, &point_words, true, params);
+    let scalar_bits = u256_into_bits(cs, &words[G2_POINT_WORDS]);
+
+    let mut curve_b3 = g2_curve_b3(cs, params);
+    let mut point = Bls12_381G2ProjectivePoint::from_affine(cs, &point, params);
+    let mut acc = Bls12_381G2ProjectivePoint::zero(cs, params);
+    for bit in scalar_bits.into_iter().rev() {
+        acc = acc.double(cs, &mut curve_b3);
+        let sum = acc.add(cs, &mut point, &mut curve_b3);
+        acc = Bls12_381G2ProjectivePoint::conditionally_select(cs, bit, &sum, &acc);
+    }
+
+    let zero = Bls12_381G2ProjectivePoint::zero(cs, params);
+    let mut acc =
+        Bls12_381G2ProjectivePoint::conditionally_select(cs, point_is_infinity, &zero, &acc);
+
+    let (previous, previous_is_infinity) =
+        bls12_381_decode_g2_point_unchecked(cs, accumulator, params);
+    let mut previous = Bls12_381G2ProjectivePoint::from_affine(cs, &previous, params);
+    let sum = acc.add(cs, &mut previous, &mut curve_b3);
+    let mut acc =
+        Bls12_381G2ProjectivePoint::conditionally_select(cs, previous_is_infinity, &acc, &sum);
+
+    let result = bls12_381_encode_g2_point(cs, &mut acc, params);
+
+    (result, point_is_invalid)
+}
+
+/// FSM shared by G1 and G2 MSM precompiles, every cycle processes one (point, scalar) pair
+fn bls12_381_msm_precompile_inner<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+    const POINT_WORDS: usize,
+    const PAIR_WORDS: usize,
+>(
+    cs: &mut CS,
+    memory_queue: &mut MemoryQueue<F, R>,
+    precompile_calls_queue: &mut StorageLogQueue<F, R>,
+    memory_read_witness: ConditionalWitnessAllocator<F, UInt256<F>>,
+    mut state: Bls12_381MsmFSM<F, POINT_WORDS>,
+    precompile_formal_address: H160,
+    limit: usize,
+    mut pair_inner: impl FnMut(
+        &mut CS,
+        &[UInt256<F>; PAIR_WORDS],
+        &[UInt256<F>; POINT_WORDS],
+    ) -> ([UInt256<F>; POINT_WORDS], Boolean<F>),
+) -> Bls12_381MsmFSM<F, POINT_WORDS>
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    assert!(limit <= u32::MAX as usize);
+
+    let precompile_address = UInt160::allocated_constant(cs, precompile_formal_address);
+    let aux_byte_for_precompile = UInt8::allocated_constant(cs, PRECOMPILE_AUX_BYTE);
+
+    let boolean_false = Boolean::allocated_constant(cs, false);
+    let boolean_true = Boolean::allocated_constant(cs, true);
+    let zero_u256 = UInt256::zero(cs);
+
+    // all zeroes encode the point at infinity
+    let empty_accumulator = [zero_u256; POINT_WORDS];
+
+    // we can have a degenerate case when queue is empty, but it's a first circuit in the queue,
+    // so we taken default FSM state that has state.read_precompile_call = true;
+    let input_queue_is_empty = precompile_calls_queue.is_empty(cs);
+    // we can only skip the full circuit if we are not in any form of progress
+    let can_finish_immediatelly =
+        Boolean::multi_and(cs, &[state.read_precompile_call, input_queue_is_empty]);
+
+    state.read_precompile_call = state
+        .read_precompile_call
+        .mask_negated(cs, can_finish_immediatelly);
+    state.read_words_for_round = state
+        .read_words_for_round
+        .mask_negated(cs, can_finish_immediatelly);
+    state.completed = Boolean::multi_or(cs, &[state.completed, can_finish_immediatelly]);
+
+    for _cycle in 0..limit {
+        // if we are in a proper state then get the ABI from the queue
+        let (precompile_call, _) = precompile_calls_queue.pop_front(cs, state.read_precompile_call);
+
+        Num::conditionally_enforce_equal(
+            cs,
+            state.read_precompile_call,
+            &Num::from_variable(precompile_call.aux_byte.get_variable()),
+            &Num::from_variable(aux_byte_for_precompile.get_variable()),
+        );
+        for (a, b) in precompile_call
+            .address
+            .inner
+            .iter()
+            .zip(precompile_address.inner.iter())
+        {
+            Num::conditionally_enforce_equal(
+                cs,
+                state.read_precompile_call,
+                &Num::from_variable(a.get_variable()),
+                &Num::from_variable(b.get_variable()),
+            );
+        }
+
+        let call_params = Bls12_381PrecompileCallParams::from_encoding(cs, precompile_call.key);
+
+        state.precompile_call_params = Bls12_381PrecompileCallParams::conditionally_select(
+            cs,
+            state.read_precompile_call,
+            &call_params,
+            &state.precompile_call_params,
+        );
+        state.timestamp_to_use_for_read = UInt32::conditionally_select(
+            cs,
+            state.read_precompile_call,
+            &precompile_call.timestamp,
+            &state.timestamp_to_use_for_read,
+        );
+
+        // timestamps have large space, so this can be expected
+        let timestamp_to_use_for_write =
+            unsafe { state.timestamp_to_use_for_read.increment_unchecked(cs) };
+        state.timestamp_to_use_for_write = UInt32::conditionally_select(
+            cs,
+            state.read_precompile_call,
+            &timestamp_to_use_for_write,
+            &state.timestamp_to_use_for_write,
+        );
+
+        let reset_accumulator =
+            Boolean::multi_or(cs, &[state.read_precompile_call, state.completed]);
+        state.accumulator = <[UInt256<F>; POINT_WORDS]>::conditionally_select(
+            cs,
+            reset_accumulator,
+            &empty_accumulator,
+            &state.accumulator,
+        );
+        state.has_exception = state.has_exception.mask_negated(cs, reset_accumulator);
+
+        // call without pairs is invalid
+        let call_is_empty = call_params.num_pairs.is_zero(cs);
+        let call_is_empty = Boolean::multi_and(cs, &[state.read_precompile_call, call_is_empty]);
+        state.has_exception = Boolean::multi_or(cs, &[state.has_exception, call_is_empty]);
+
+        state.read_words_for_round = Boolean::multi_or(
+            cs,
+            &[state.read_precompile_call, state.read_words_for_round],
+        );
+        state.read_precompile_call = boolean_false;
+
+        // ---------------------------------
+        // Now perform few memory queries to read content
+
+        let zero_pairs_left = state.precompile_call_params.num_pairs.is_zero(cs);
+        let not_zero_pairs_left = zero_pairs_left.negated(cs);
+        let should_read =
+            Boolean::multi_and(cs, &[state.read_words_for_round, not_zero_pairs_left]);
+
+        let mut read_values = [zero_u256; PAIR_WORDS];
+        let mut bias_variable = should_read.get_variable();
+        for dst in read_values.iter_mut() {
+            let read_query_value =
+                memory_read_witness.conditionally_allocate_biased(cs, should_read, bias_variable);
+            bias_variable = read_query_value.inner[0].get_variable();
+
+            *dst = read_query_value;
+
+            let read_query = MemoryQuery {
+                timestamp: state.timestamp_to_use_for_read,
+                memory_page: state.precompile_call_params.input_page,
+                index: state.precompile_call_params.input_offset,
+                rw_flag: boolean_false,
+                is_ptr: boolean_false,
+                value: read_query_value,
+            };
+
+            let may_be_new_offset = unsafe {
+                state
+                    .precompile_call_params
+                    .input_offset
+                    .increment_unchecked(cs)
+            };
+            state.precompile_call_params.input_offset = UInt32::conditionally_select(
+                cs,
+                should_read,
+                &may_be_new_offset,
+                &state.precompile_call_params.input_offset,
+            );
+
+            // perform read
+            memory_queue.push(cs, read_query, should_read);
+        }
+
+        let may_be_new_num_pairs = unsafe {
+            state
+                .precompile_call_params
+                .num_pairs
+                .decrement_unchecked(cs)
+        };
+        state.precompile_call_params.num_pairs = UInt32::conditionally_select(
+            cs,
+            should_read,
+            &may_be_new_num_pairs,
+            &state.precompile_call_params.num_pairs,
+        );
+
+        // if we didn't read anything then the point is at infinity, but we still keep the old accumulator
+        let (new_accumulator, pair_is_invalid) = pair_inner(cs, &read_values, &state.accumulator);
+        state.accumulator = <[UInt256<F>; POINT_WORDS]>::conditionally_select(
+            cs,
+            should_read,
+            &new_accumulator,
+            &state.accumulator,
+        );
+
+        let has_new_exception = Boolean::multi_and(cs, &[should_read, pair_is_invalid]);
+        state.has_exception = Boolean::multi_or(cs, &[state.has_exception, has_new_exception]);
+
+        let no_pairs_left = state.precompile_call_params.num_pairs.is_zero(cs);
+        let write_result = Boolean::multi_and(cs, &[state.read_words_for_round, no_pairs_left]);
+
+        let success = state.has_exception.negated(cs);
+        let success_as_u32 = unsafe { UInt32::from_variable_unchecked(success.get_variable()) };
+        let mut success_as_u256 = zero_u256;
+        success_as_u256.inner[0] = success_as_u32;
+
+        let success_query = MemoryQuery {
+            timestamp: state.timestamp_to_use_for_write,
+            memory_page: state.precompile_call_params.output_page,
+            index: state.precompile_call_params.output_offset,
+            rw_flag: boolean_true,
+            is_ptr: boolean_false,
+            value: success_as_u256,
+        };
+
+        // perform writes
+        let _ = memory_queue.push(cs, success_query, write_result);
+
+        let mut output_offset = state.precompile_call_params.output_offset;
+        for value in state.accumulator.into_iter() {
+            output_offset = unsafe { output_offset.increment_unchecked(cs) };
+            let value_query = MemoryQuery {
+                timestamp: state.timestamp_to_use_for_write,
+                memory_page: state.precompile_call_params.output_page,
+                index: output_offset,
+                rw_flag: boolean_true,
+                is_ptr: boolean_false,
+                value: value.mask(cs, success),
+            };
+
+            let _ = memory_queue.push(cs, value_query, write_result);
+        }
+
+        // ---------------------------------
+
+        // update state
+        let input_is_empty = precompile_calls_queue.is_empty(cs);
+        let input_is_not_empty = input_is_empty.negated(cs);
+        let nothing_left = Boolean::multi_and(cs, &[write_result, input_is_empty]);
+        let process_next = Boolean::multi_and(cs, &[write_result, input_is_not_empty]);
+
+        state.read_precompile_call = process_next;
+        state.completed = Boolean::multi_or(cs, &[nothing_left, state.completed]);
+        let t = Boolean::multi_or(cs, &[state.read_precompile_call, state.completed]);
+        state.read_words_for_round = t.negated(cs);
+    }
+
+    precompile_calls_queue.enforce_consistency(cs);
+
+    state
+}
+
+fn bls12_381_msm_function_entry_point<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+    const POINT_WORDS: usize,
+    const PAIR_WORDS: usize,
+>(
+    cs: &mut CS,
+    witness: Bls12_381MsmCircuitInstanceWitness<F, POINT_WORDS>,
+    round_function: &R,
+    limit: usize,
+    precompile_formal_address: H160,
+    pair_inner: impl FnMut(
+        &mut CS,
+        &[UInt256<F>; PAIR_WORDS],
+        &[UInt256<F>; POINT_WORDS],
+    ) -> ([UInt256<F>; POINT_WORDS], Boolean<F>),
+) -> [Num<F>; INPUT_OUTPUT_COMMITMENT_LENGTH]
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    let Bls12_381MsmCircuitInstanceWitness {
+        closed_form_input,
+        requests_queue_witness,
+        memory_reads_witness,
+    } = witness;
+
+    let mut structured_input =
+        Bls12_381MsmCircuitInputOutput::<F, POINT_WORDS>::alloc_ignoring_outputs(
+            cs,
+            closed_form_input.clone(),
+        );
+
+    let start_flag = structured_input.start_flag;
+
+    let requests_queue_state_from_input = structured_input.observable_input.initial_log_queue_state;
+
+    // it must be trivial
+    requests_queue_state_from_input.enforce_trivial_head(cs);
+
+    let requests_queue_state_from_fsm = structured_input.hidden_fsm_input.log_queue_state;
+
+    let requests_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &requests_queue_state_from_input,
+        &requests_queue_state_from_fsm,
+    );
+
+    let memory_queue_state_from_input =
+        structured_input.observable_input.initial_memory_queue_state;
+
+    // it must be trivial
+    memory_queue_state_from_input.enforce_trivial_head(cs);
+
+    let memory_queue_state_from_fsm = structured_input.hidden_fsm_input.memory_queue_state;
+
+    let memory_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &memory_queue_state_from_input,
+        &memory_queue_state_from_fsm,
+    );
+
+    let mut requests_queue = StorageLogQueue::<F, R>::from_state(cs, requests_queue_state);
+    let queue_witness = CircuitQueueWitness::from_inner_witness(requests_queue_witness);
+    requests_queue.witness = Arc::new(queue_witness);
+
+    let mut memory_queue = MemoryQueue::<F, R>::from_state(cs, memory_queue_state);
+
+    let read_queries_allocator = ConditionalWitnessAllocator::<F, UInt256<F>> {
+        witness_source: Arc::new(RwLock::new(memory_reads_witness)),
+    };
+
+    let mut starting_fsm_state = Bls12_381MsmFSM::<F, POINT_WORDS>::placeholder(cs);
+    starting_fsm_state.read_precompile_call = Boolean::allocated_constant(cs, true);
+
+    let initial_state = Bls12_381MsmFSM::conditionally_select(
+        cs,
+        start_flag,
+        &starting_fsm_state,
+        &structured_input.hidden_fsm_input.internal_fsm,
+    );
+
+    let final_state = bls12_381_msm_precompile_inner::<F, CS, R, POINT_WORDS, PAIR_WORDS>(
+        cs,
+        &mut memory_queue,
+        &mut requests_queue,
+        read_queries_allocator,
+        initial_state,
+        precompile_formal_address,
+        limit,
+        pair_inner,
+    );
+
+    let final_memory_state = memory_queue.into_state();
+    let final_requets_state = requests_queue.into_state();
+
+    // form the final state
+    let done = final_state.completed;
+    structured_input.completion_flag = done;
+    structured_input.observable_output = PrecompileFunctionOutputData::placeholder(cs);
+
+    structured_input.observable_output.final_memory_state = QueueState::conditionally_select(
+        cs,
+        structured_input.completion_flag,
+        &final_memory_state,
+        &structured_input.observable_output.final_memory_state,
+    );
+
+    structured_input.hidden_fsm_output.internal_fsm = final_state;
+    structured_input.hidden_fsm_output.log_queue_state = final_requets_state;
+    structured_input.hidden_fsm_output.memory_queue_state = final_memory_state;
+
+    // self-check
+    structured_input.hook_compare_witness(cs, &closed_form_input);
+
+    use boojum::cs::gates::PublicInputGate;
+
+    let compact_form =
+        ClosedFormInputCompactForm::from_full_form(cs, &structured_input, round_function);
+    let input_commitment = commit_variable_length_encodable_item(cs, &compact_form, round_function);
+    for el in input_commitment.iter() {
+        let gate = PublicInputGate::new(el.get_variable());
+        gate.add_to_cs(cs);
+    }
+
+    input_commitment
+}
+
+#[track_caller]
+pub fn bls12_381_g1msm_function_entry_point<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    witness: Bls12_381G1MsmCircuitInstanceWitness<F>,
+    round_function: &R,
+    limit: usize,
+) -> [Num<F>; INPUT_OUTPUT_COMMITMENT_LENGTH]
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    let params = Arc::new(bls12_381_base_field_params());
+
+    bls12_381_msm_function_entry_point(
+        cs,
+        witness,
+        round_function,
+        limit,
+        BLS12_381_G1MSM_PRECOMPILE_FORMAL_ADDRESS,
+        |cs: &mut CS,
+         words: &[UInt256<F>; G1_MSM_PAIR_WORDS],
+         accumulator: &[UInt256<F>; G1_POINT_WORDS]| {
+            bls12_381_g1msm_pair_inner(cs, words, accumulator, &params)
+        },
+    )
+}
+
+#[track_caller]
+pub fn bls12_381_g2msm_function_entry_point<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    witness: Bls12_381G2MsmCircuitInstanceWitness<F>,
+    round_function: &R,
+    limit: usize,
+) -> [Num<F>; INPUT_OUTPUT_COMMITMENT_LENGTH]
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    let params = Arc::new(bls12_381_base_field_params());
+
+    bls12_381_msm_function_entry_point(
+        cs,
+        witness,
+        round_function,
+        limit,
+        BLS12_381_G2MSM_PRECOMPILE_FORMAL_ADDRESS,
+        |cs: &mut CS,
+         words: &[UInt256<F>; G2_MSM_PAIR_WORDS],
+         accumulator: &[UInt256<F>; G2_POINT_WORDS]| {
+            bls12_381_g2msm_pair_inner(cs, words, accumulator, &params)
+        },
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test_utils::*;
+    use super::*;
+
+    use boojum::gadgets::traits::allocatable::CSAllocatable;
+    use boojum::pairing::bls12_381::Fr as Bls12_381Fr;
+    use boojum::pairing::ff::{Field, PrimeField};
+    use boojum::pairing::{GenericCurveAffine, GenericCurveProjective};
+    use boojum::worker::Worker;
+
+    // runs the pairs one after another through the accumulator, the same way the FSM does
+    fn run_g1msm(pairs: &[[U256; G1_MSM_PAIR_WORDS]]) -> (bool, [U256; G1_POINT_WORDS]) {
+        let mut owned_cs = create_cs(1 << 23);
+        let cs = &mut owned_cs;
+
+        let params = Arc::new(bls12_381_base_field_params());
+        let mut accumulator = [UInt256::zero(cs); G1_POINT_WORDS];
+        let mut exceptions = vec![];
+        for pair in pairs.iter() {
+            let words = pair.map(|el| UInt256::allocate(cs, el));
+            let (new_accumulator, is_invalid) =
+                bls12_381_g1msm_pair_inner(cs, &words, &accumulator, &params);
+            accumulator = new_accumulator;
+            exceptions.push(is_invalid);
+        }
+        let has_exception = Boolean::multi_or(cs, &exceptions);
+
+        let success = !has_exception.witness_hook(&*cs)().unwrap();
+        let result = accumulator.map(|el| el.witness_hook(&*cs)().unwrap());
+
+        cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        let worker = Worker::new();
+        assert!(cs.check_if_satisfied(&worker));
+
+        (success, result)
+    }
+
+    fn run_g2msm(pairs: &[[U256; G2_MSM_PAIR_WORDS]]) -> (bool, [U256; G2_POINT_WORDS]) {
+        let mut owned_cs = create_cs(1 << 24);
+        let cs = &mut owned_cs;
+
+        let params = Arc::new(bls12_381_base_field_params());
+        let mut accumulator = [UInt256::zero(cs); G2_POINT_WORDS];
+        let mut exceptions = vec![];
+        for pair in pairs.iter() {
+            let words = pair.map(|el| UInt256::allocate(cs, el));
+            let (new_accumulator, is_invalid) =
+                bls12_381_g2msm_pair_inner(cs, &words, &accumulator, &params);
+            accumulator = new_accumulator;
+            exceptions.push(is_invalid);
+        }
+        let has_exception = Boolean::multi_or(cs, &exceptions);
+
+        let success = !has_exception.witness_hook(&*cs)().unwrap();
+        let result = accumulator.map(|el| el.witness_hook(&*cs)().unwrap());
+
+        cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        let worker = Worker::new();
+        assert!(cs.check_if_satisfied(&worker));
+
+        (success, result)
+    }
+
+    fn pair_words<const N: usize, const M: usize>(point: [U256; N], scalar: U256) -> [U256; M] {
+        assert_eq!(N + 1, M);
+        std::array::from_fn(|i| if i < N { point[i] } else { scalar })
+    }
+
+    #[test]
+    fn test_bls12_381_g1msm() {
+        let g = Bls12_381G1Affine::one();
+        let a = Bls12_381Fr::from_str("1234567").unwrap();
+        let b = Bls12_381Fr::from_str("7654321").unwrap();
+        let a_g = g.mul(a.into_repr()).into_affine();
+
+        // a * G + b * (a * G) = (a + a * b) * G
+        let mut expected_scalar = a;
+        expected_scalar.mul_assign(&b);
+        expected_scalar.add_assign(&a);
+        let expected = g.mul(expected_scalar.into_repr()).into_affine();
+
+        let (success, result) = run_g1msm(&[
+            pair_words(g1_into_u256_words(g), U256::from(1234567u64)),
+            pair_words(g1_into_u256_words(a_g), U256::from(7654321u64)),
+        ]);
+        assert!(success);
+        assert_eq!(result, g1_into_u256_words(expected));
+
+        // zero scalar and point at infinity don't change the sum
+        let (success, result) = run_g1msm(&[
+            pair_words(g1_into_u256_words(g), U256::zero()),
+            pair_words([U256::zero(); G1_POINT_WORDS], U256::from(5u64)),
+            pair_words(g1_into_u256_words(a_g), U256::one()),
+        ]);
+        assert!(success);
+        assert_eq!(result, g1_into_u256_words(a_g));
+
+        // scalar is not reduced, so r * G is the point at infinity
+        let (success, result) =
+            run_g1msm(&[pair_words(g1_into_u256_words(g), bls12_381_group_order())]);
+        assert!(success);
+        assert_eq!(result, [U256::zero(); G1_POINT_WORDS]);
+    }
+
+    #[test]
+    fn test_bls12_381_g1msm_invalid_point() {
+        let g = g1_into_u256_words(Bls12_381G1Affine::one());
+
+        // not on curve
+        let mut not_on_curve = g;
+        not_on_curve[3] = not_on_curve[3] + U256::one();
+        let (success, _) = run_g1msm(&[pair_words(not_on_curve, U256::one())]);
+        assert!(!success);
+
+        // (0, 2) is on curve y^2 = x^3 + 4, but not in G1
+        let not_in_subgroup = [U256::zero(), U256::zero(), U256::zero(), U256::from(2u64)];
+        let (success, _) = run_g1msm(&[
+            pair_words(g, U256::one()),
+            pair_words(not_in_subgroup, U256::one()),
+        ]);
+        assert!(!success);
+    }
+
+    #[test]
+    fn test_bls12_381_g2msm() {
+        let g = Bls12_381G2Affine::one();
+        let a = Bls12_381Fr::from_str("1234567").unwrap();
+        let a_g = g.mul(a.into_repr()).into_affine();
+        let mut expected_scalar = a;
+        expected_scalar.double();
+        let expected = g.mul(expected_scalar.into_repr()).into_affine();
+
+        // a * G + 1 * (a * G) = 2a * G
+        let (success, result) = run_g2msm(&[
+            pair_words(g2_into_u256_words(g), U256::from(1234567u64)),
+            pair_words(g2_into_u256_words(a_g), U256::one()),
+        ]);
+        assert!(success);
+        assert_eq!(result, g2_into_u256_words(expected));
+
+        // not on curve
+        let mut not_on_curve = g2_into_u256_words(g);
+        not_on_curve[7] = not_on_curve[7] + U256::one();
+        let (success, _) = run_g2msm(&[pair_words(not_on_curve, U256::one())]);
+        assert!(!success);
+    }
+}
@@ -0,0 +1,167 @@
+use super::*;
+
+use super::curves::*;
+use crate::kzg_point_evaluation::pairing::BLS12_381_X;
+use crate::kzg_point_evaluation::towers::*;
+
+// We use M-type twist, so the line through T with slope lambda, evaluated at P and multiplied by w^3, is
+// (lambda * x_T - y_T) + (-lambda * x_P) w^2 + y_P w^3, the same as for the lines of fixed points of G2
+fn evaluate_line<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    lambda: &mut Bls12_381Fq2<F>,
+    t: &mut Bls12_381G2AffinePoint<F>,
+    p: &mut (Bls12_381BaseNNField<F>, Bls12_381BaseNNField<F>),
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> Bls12_381Fq12<F> {
+    let zero = Bls12_381Fq2::zero(cs, params);
+
+    let mut lambda_times_x_t = lambda.mul(cs, &mut t.x);
+    let mut lambda_negated = lambda.negated(cs);
+    let c0 = Bls12_381Fq6 {
+        c0: lambda_times_x_t.sub(cs, &mut t.y),
+        c1: lambda_negated.mul_by_base_field(cs, &mut p.0),
+        c2: zero.clone(),
+    };
+    let c1 = Bls12_381Fq6 {
+        c0: zero.clone(),
+        c1: Bls12_381Fq2 {
+            c0: p.1.clone(),
+            c1: zero.c0.clone(),
+        },
+        c2: zero,
+    };
+
+    Bls12_381Fq12 { c0, c1 }
+}
+
+// computes T = T + T or T = T + Q in affine form, and returns the line function for the step
+fn miller_loop_step<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    t: &mut Bls12_381G2AffinePoint<F>,
+    q: Option<&mut Bls12_381G2AffinePoint<F>>,
+    p: &mut (Bls12_381BaseNNField<F>, Bls12_381BaseNNField<F>),
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> Bls12_381Fq12<F> {
+    // We work with the points of G2 only, and T is never equal to +-Q, so there are no exceptional cases
+    let (mut lambda, mut q_x) = match q {
+        None => {
+            // lambda = 3 x_T^2 / 2 y_T
+            let mut x_squared = t.x.square(cs);
+            let mut numerator = x_squared.double(cs);
+            let mut numerator = numerator.add(cs, &mut x_squared);
+            let mut denominator = t.y.double(cs);
+            let lambda = numerator.div(cs, &mut denominator);
+
+            (lambda, t.x.clone())
+        }
+        Some(q) => {
+            // lambda = (y_Q - y_T) / (x_Q - x_T)
+            let mut numerator = q.y.sub(cs, &mut t.y);
+            let mut denominator = q.x.sub(cs, &mut t.x);
+            let lambda = numerator.div(cs, &mut denominator);
+
+            (lambda, q.x.clone())
+        }
+    };
+
+    let line = evaluate_line(cs, &mut lambda, t, p, params);
+
+    // x_R = lambda^2 - x_T - x_Q, y_R = lambda (x_T - x_R) - y_T
+    let mut x = lambda.square(cs);
+    let mut x = x.sub(cs, &mut t.x);
+    let mut x = x.sub(cs, &mut q_x);
+    let mut y = t.x.sub(cs, &mut x);
+    let mut y = y.mul(cs, &mut lambda);
+    let y = y.sub(cs, &mut t.y);
+
+    *t = Bls12_381G2AffinePoint { x, y };
+
+    line
+}
+
+/// Optimal ate Miller loop for the pair of valid points not at infinity
+pub(crate) fn bls12_381_miller_loop<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    p: &mut (Bls12_381BaseNNField<F>, Bls12_381BaseNNField<F>),
+    q: &mut Bls12_381G2AffinePoint<F>,
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> Bls12_381Fq12<F> {
+    let mut f = Bls12_381Fq12::one(cs, params);
+    let mut t = q.clone();
+
+    let num_bits = 64 - BLS12_381_X.leading_zeros();
+    for i in (0..(num_bits - 1)).rev() {
+        if i != num_bits - 2 {
+            f = f.square(cs);
+        }
+
+        let mut line = miller_loop_step(cs, &mut t, None, p, params);
+        f = f.mul(cs, &mut line);
+
+        if (BLS12_381_X >> i) & 1 == 1 {
+            let mut line = miller_loop_step(cs, &mut t, Some(&mut *q), p, params);
+            f = f.mul(cs, &mut line);
+        }
+    }
+
+    // parameter of the curve is negative
+    f.conjugate(cs)
+}
+
+fn fq12_coefficients_mut<F: SmallField>(f: &mut Bls12_381Fq12<F>) -> [&mut Bls12_381Fq2<F>; 6] {
+    [
+        &mut f.c0.c0,
+        &mut f.c0.c1,
+        &mut f.c0.c2,
+        &mut f.c1.c0,
+        &mut f.c1.c1,
+        &mut f.c1.c2,
+    ]
+}
+
+/// Caller must ensure that the encoding is canonical, e.g. it was produced by `bls12_381_fq12_into_words`
+pub(crate) fn bls12_381_fq12_from_words<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    words: &[UInt256<F>; MILLER_LOOP_ACCUMULATOR_WORDS],
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> Bls12_381Fq12<F> {
+    let mut coeffs = Vec::with_capacity(6);
+    for [c0_hi, c0_lo, c1_hi, c1_lo] in words.array_chunks::<FP2_WORDS>() {
+        coeffs.push(Bls12_381Fq2 {
+            c0: bls12_381_decode_fp_unchecked(cs, &[*c0_hi, *c0_lo], params),
+            c1: bls12_381_decode_fp_unchecked(cs, &[*c1_hi, *c1_lo], params),
+        });
+    }
+    let [c00, c01, c02, c10, c11, c12]: [Bls12_381Fq2<F>; 6] = coeffs.try_into().unwrap();
+
+    Bls12_381Fq12 {
+        c0: Bls12_381Fq6 {
+            c0: c00,
+            c1: c01,
+            c2: c02,
+        },
+        c1: Bls12_381Fq6 {
+            c0: c10,
+            c1: c11,
+            c2: c12,
+        },
+    }
+}
+
+/// Encodes the element as 6 coefficients of Fq2 in canonical form,
+/// in the same order as `bls12_381_fq12_from_words` expects
+pub(crate) fn bls12_381_fq12_into_words<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    mut f: Bls12_381Fq12<F>,
+) -> [UInt256<F>; MILLER_LOOP_ACCUMULATOR_WORDS] {
+    let zero_u256 = UInt256::zero(cs);
+    let mut result = [zero_u256; MILLER_LOOP_ACCUMULATOR_WORDS];
+    for (dst, src) in result
+        .array_chunks_mut::<FP2_WORDS>()
+        .zip(fq12_coefficients_mut(&mut f).into_iter())
+    {
+        *dst = bls12_381_encode_fp2(cs, src.clone());
+    }
+
+    result
+}
@@ -0,0 +1,760 @@
+use super::*;
+
+use super::curves::*;
+use super::pairing::*;
+use crate::demux_log_queue::StorageLogQueue;
+use crate::fsm_input_output::circuit_inputs::INPUT_OUTPUT_COMMITMENT_LENGTH;
+use crate::kzg_point_evaluation::pairing::bls12_381_final_exponentiation;
+use crate::kzg_point_evaluation::towers::*;
+use crate::storage_application::ConditionalWitnessAllocator;
+
+use boojum::algebraic_props::round_function::AlgebraicRoundFunction;
+use boojum::gadgets::num::Num;
+use boojum::gadgets::queue::CircuitQueueWitness;
+use boojum::gadgets::traits::allocatable::CSAllocatableExt;
+use boojum::gadgets::traits::round_function::CircuitRoundFunction;
+use boojum::gadgets::u160::UInt160;
+use boojum::gadgets::u8::UInt8;
+
+use std::sync::RwLock;
+use zkevm_opcode_defs::system_params::PRECOMPILE_AUX_BYTE;
+
+/// Performs one pair of the pairing check. Returns the Miller loop value to accumulate,
+/// and flags whether the pair is trivial (any point is at infinity) or invalid
+fn bls12_381_pairing_pair_inner<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    words: &[UInt256<F>; PAIRING_PAIR_WORDS],
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> (Bls12_381Fq12<F>, Boolean<F>, Boolean<F>) {
+    let p_words = std::array::from_fn(|i| words[i]);
+    let q_words = std::array::from_fn(|i| words[G1_POINT_WORDS + i]);
+
+    let (mut p, p_is_infinity, p_is_invalid) =
+        bls12_381_validate_and_mask_g1_point(cs, &p_words, true, params);
+    let (mut q, q_is_infinity, q_is_invalid) =
+        bls12_381_validate_and_mask_g2_point(cs, &q_words, true, params);
+
+    // points are masked to generators, so the Miller loop is always well defined
+    let miller_loop_result = bls12_381_miller_loop(cs, &mut p, &mut q, params);
+
+    let is_trivial = Boolean::multi_or(cs, &[p_is_infinity, q_is_infinity]);
+    let is_invalid = Boolean::multi_or(cs, &[p_is_invalid, q_is_invalid]);
+
+    (miller_loop_result, is_trivial, is_invalid)
+}
+
+/// Runs the final exponentiation for the call whose pairs are all accumulated, and writes
+/// the result. Final exponentiation costs more than a Miller loop, so it's synthesized
+/// once per instance instead of once per cycle
+fn bls12_381_pairing_check_finalize_call<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    memory_queue: &mut MemoryQueue<F, R>,
+    precompile_calls_queue: &mut StorageLogQueue<F, R>,
+    mut state: Bls12_381PairingCheckFSM<F>,
+    finalize_call: Boolean<F>,
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> Bls12_381PairingCheckFSM<F>
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+{
+    let boolean_false = Boolean::allocated_constant(cs, false);
+    let boolean_true = Boolean::allocated_constant(cs, true);
+    let zero_u256 = UInt256::zero(cs);
+
+    let mut accumulator = bls12_381_fq12_from_words(cs, &state.miller_loop_accumulator, params);
+    let mut final_exp_result = bls12_381_final_exponentiation(cs, &mut accumulator, params);
+    let mut one_fq12 = Bls12_381Fq12::one(cs, params);
+    let pairing_is_one = Bls12_381Fq12::equals(cs, &mut final_exp_result, &mut one_fq12);
+
+    let success = state.has_exception.negated(cs);
+    let result = Boolean::multi_and(cs, &[pairing_is_one, success]);
+
+    let success_as_u32 = unsafe { UInt32::from_variable_unchecked(success.get_variable()) };
+    let mut success_as_u256 = zero_u256;
+    success_as_u256.inner[0] = success_as_u32;
+
+    let result_as_u32 = unsafe { UInt32::from_variable_unchecked(result.get_variable()) };
+    let mut result_as_u256 = zero_u256;
+    result_as_u256.inner[0] = result_as_u32;
+
+    let success_query = MemoryQuery {
+        timestamp: state.timestamp_to_use_for_write,
+        memory_page: state.precompile_call_params.output_page,
+        index: state.precompile_call_params.output_offset,
+        rw_flag: boolean_true,
+        is_ptr: boolean_false,
+        value: success_as_u256,
+    };
+
+    let result_offset = unsafe {
+        state
+            .precompile_call_params
+            .output_offset
+            .increment_unchecked(cs)
+    };
+    let result_query = MemoryQuery {
+        timestamp: state.timestamp_to_use_for_write,
+        memory_page: state.precompile_call_params.output_page,
+        index: result_offset,
+        rw_flag: boolean_true,
+        is_ptr: boolean_false,
+        value: result_as_u256,
+    };
+
+    // perform writes
+    let _ = memory_queue.push(cs, success_query, finalize_call);
+    let _ = memory_queue.push(cs, result_query, finalize_call);
+
+    // update state, next instance either starts a new call or we are done
+    let input_is_empty = precompile_calls_queue.is_empty(cs);
+    let input_is_not_empty = input_is_empty.negated(cs);
+    let nothing_left = Boolean::multi_and(cs, &[finalize_call, input_is_empty]);
+    let process_next = Boolean::multi_and(cs, &[finalize_call, input_is_not_empty]);
+
+    state.read_precompile_call = Boolean::multi_or(cs, &[state.read_precompile_call, process_next]);
+    state.completed = Boolean::multi_or(cs, &[nothing_left, state.completed]);
+
+    state
+}
+
+pub fn bls12_381_pairing_check_precompile_inner<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    memory_queue: &mut MemoryQueue<F, R>,
+    precompile_calls_queue: &mut StorageLogQueue<F, R>,
+    memory_read_witness: ConditionalWitnessAllocator<F, UInt256<F>>,
+    mut state: Bls12_381PairingCheckFSM<F>,
+    _round_function: &R,
+    limit: usize,
+) -> Bls12_381PairingCheckFSM<F>
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    assert!(limit <= u32::MAX as usize);
+
+    let precompile_address =
+        UInt160::allocated_constant(cs, BLS12_381_PAIRING_CHECK_PRECOMPILE_FORMAL_ADDRESS);
+    let aux_byte_for_precompile = UInt8::allocated_constant(cs, PRECOMPILE_AUX_BYTE);
+
+    let boolean_false = Boolean::allocated_constant(cs, false);
+    let zero_u256 = UInt256::zero(cs);
+    let one_u256 = UInt256::allocated_constant(cs, U256::one());
+
+    let params = Arc::new(bls12_381_base_field_params());
+
+    let mut empty_accumulator = [zero_u256; MILLER_LOOP_ACCUMULATOR_WORDS];
+    empty_accumulator[1] = one_u256;
+
+    // we can have a degenerate case when queue is empty, but it's a first circuit in the queue,
+    // so we taken default FSM state that has state.read_precompile_call = true;
+    let input_queue_is_empty = precompile_calls_queue.is_empty(cs);
+    // we can only skip the full circuit if we are not in any form of progress
+    let can_finish_immediatelly =
+        Boolean::multi_and(cs, &[state.read_precompile_call, input_queue_is_empty]);
+
+    if crate::config::CIRCUIT_VERSOBE {
+        dbg!(can_finish_immediatelly.witness_hook(cs)());
+        dbg!(state.witness_hook(cs)());
+    }
+
+    state.read_precompile_call = state
+        .read_precompile_call
+        .mask_negated(cs, can_finish_immediatelly);
+    state.read_words_for_round = state
+        .read_words_for_round
+        .mask_negated(cs, can_finish_immediatelly);
+    state.completed = Boolean::multi_or(cs, &[state.completed, can_finish_immediatelly]);
+
+    // at most one call per instance is finalized, after all its pairs are accumulated
+    let mut finalize_call = boolean_false;
+
+    // main work cycle, every cycle processes one (G1, G2) pair
+    for _cycle in 0..limit {
+        if crate::config::CIRCUIT_VERSOBE {
+            dbg!(_cycle);
+            dbg!(state.witness_hook(cs)());
+            dbg!(precompile_calls_queue.into_state().witness_hook(cs)());
+        }
+        // if we are in a proper state then get the ABI from the queue
+        let (precompile_call, _) = precompile_calls_queue.pop_front(cs, state.read_precompile_call);
+
+        Num::conditionally_enforce_equal(
+            cs,
+            state.read_precompile_call,
+            &Num::from_variable(precompile_call.aux_byte.get_variable()),
+            &Num::from_variable(aux_byte_for_precompile.get_variable()),
+        );
+        for (a, b) in precompile_call
+            .address
+            .inner
+            .iter()
+            .zip(precompile_address.inner.iter())
+        {
+            Num::conditionally_enforce_equal(
+                cs,
+                state.read_precompile_call,
+                &Num::from_variable(a.get_variable()),
+                &Num::from_variable(b.get_variable()),
+            );
+        }
+
+        // now compute some parameters that describe the call itself
+
+        let params_encoding = precompile_call.key;
+        let call_params = Bls12_381PrecompileCallParams::from_encoding(cs, params_encoding);
+
+        state.precompile_call_params = Bls12_381PrecompileCallParams::conditionally_select(
+            cs,
+            state.read_precompile_call,
+            &call_params,
+            &state.precompile_call_params,
+        );
+        // also set timestamps
+        state.timestamp_to_use_for_read = UInt32::conditionally_select(
+            cs,
+            state.read_precompile_call,
+            &precompile_call.timestamp,
+            &state.timestamp_to_use_for_read,
+        );
+
+        // timestamps have large space, so this can be expected
+        let timestamp_to_use_for_write =
+            unsafe { state.timestamp_to_use_for_read.increment_unchecked(cs) };
+        state.timestamp_to_use_for_write = UInt32::conditionally_select(
+            cs,
+            state.read_precompile_call,
+            &timestamp_to_use_for_write,
+            &state.timestamp_to_use_for_write,
+        );
+
+        let reset_accumulator =
+            Boolean::multi_or(cs, &[state.read_precompile_call, state.completed]);
+        state.miller_loop_accumulator =
+            <[UInt256<F>; MILLER_LOOP_ACCUMULATOR_WORDS]>::conditionally_select(
+                cs,
+                reset_accumulator,
+                &empty_accumulator,
+                &state.miller_loop_accumulator,
+            );
+        state.has_exception = state.has_exception.mask_negated(cs, reset_accumulator);
+
+        // unlike ecPairing, the call without pairs is invalid
+        let call_is_empty = call_params.num_pairs.is_zero(cs);
+        let call_is_empty = Boolean::multi_and(cs, &[state.read_precompile_call, call_is_empty]);
+        state.has_exception = Boolean::multi_or(cs, &[state.has_exception, call_is_empty]);
+
+        state.read_words_for_round = Boolean::multi_or(
+            cs,
+            &[state.read_precompile_call, state.read_words_for_round],
+        );
+        state.read_precompile_call = boolean_false;
+
+        // ---------------------------------
+        // Now perform few memory queries to read content
+
+        let zero_pairs_left = state.precompile_call_params.num_pairs.is_zero(cs);
+        let not_zero_pairs_left = zero_pairs_left.negated(cs);
+        let should_read =
+            Boolean::multi_and(cs, &[state.read_words_for_round, not_zero_pairs_left]);
+
+        let mut read_values = [zero_u256; PAIRING_PAIR_WORDS];
+        let mut bias_variable = should_read.get_variable();
+        for dst in read_values.iter_mut() {
+            let read_query_value =
+                memory_read_witness.conditionally_allocate_biased(cs, should_read, bias_variable);
+            bias_variable = read_query_value.inner[0].get_variable();
+
+            *dst = read_query_value;
+
+            let read_query = MemoryQuery {
+                timestamp: state.timestamp_to_use_for_read,
+                memory_page: state.precompile_call_params.input_page,
+                index: state.precompile_call_params.input_offset,
+                rw_flag: boolean_false,
+                is_ptr: boolean_false,
+                value: read_query_value,
+            };
+
+            let may_be_new_offset = unsafe {
+                state
+                    .precompile_call_params
+                    .input_offset
+                    .increment_unchecked(cs)
+            };
+            state.precompile_call_params.input_offset = UInt32::conditionally_select(
+                cs,
+                should_read,
+                &may_be_new_offset,
+                &state.precompile_call_params.input_offset,
+            );
+
+            // perform read
+            memory_queue.push(cs, read_query, should_read);
+        }
+
+        let may_be_new_num_pairs = unsafe {
+            state
+                .precompile_call_params
+                .num_pairs
+                .decrement_unchecked(cs)
+        };
+        state.precompile_call_params.num_pairs = UInt32::conditionally_select(
+            cs,
+            should_read,
+            &may_be_new_num_pairs,
+            &state.precompile_call_params.num_pairs,
+        );
+
+        // if we didn't read anything then values are zeroes, so the pair is trivial
+        let (mut miller_loop_result, pair_is_trivial, pair_is_invalid) =
+            bls12_381_pairing_pair_inner(cs, &read_values, &params);
+
+        let mut accumulator =
+            bls12_381_fq12_from_words(cs, &state.miller_loop_accumulator, &params);
+        let new_accumulator = accumulator.mul(cs, &mut miller_loop_result);
+        let pair_is_not_trivial = pair_is_trivial.negated(cs);
+        let should_accumulate = Boolean::multi_and(cs, &[should_read, pair_is_not_trivial]);
+        let accumulator = Bls12_381Fq12::conditionally_select(
+            cs,
+            should_accumulate,
+            &new_accumulator,
+            &accumulator,
+        );
+
+        let has_new_exception = Boolean::multi_and(cs, &[should_read, pair_is_invalid]);
+        state.has_exception = Boolean::multi_or(cs, &[state.has_exception, has_new_exception]);
+
+        let no_pairs_left = state.precompile_call_params.num_pairs.is_zero(cs);
+        state.miller_loop_accumulator = bls12_381_fq12_into_words(cs, accumulator);
+
+        // once all the pairs are accumulated the call waits for the finalization step,
+        // so the rest of the cycles in this instance are idle
+        let call_is_finished = Boolean::multi_and(cs, &[state.read_words_for_round, no_pairs_left]);
+        finalize_call = Boolean::multi_or(cs, &[finalize_call, call_is_finished]);
+        state.read_words_for_round = state
+            .read_words_for_round
+            .mask_negated(cs, call_is_finished);
+
+        if crate::config::CIRCUIT_VERSOBE {
+            dbg!(state.witness_hook(cs)());
+            dbg!(precompile_calls_queue.into_state().witness_hook(cs)());
+        }
+    }
+
+    let state = bls12_381_pairing_check_finalize_call(
+        cs,
+        memory_queue,
+        precompile_calls_queue,
+        state,
+        finalize_call,
+        &params,
+    );
+
+    precompile_calls_queue.enforce_consistency(cs);
+
+    state
+}
+
+#[track_caller]
+pub fn bls12_381_pairing_check_function_entry_point<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    witness: Bls12_381PairingCheckCircuitInstanceWitness<F>,
+    round_function: &R,
+    limit: usize,
+) -> [Num<F>; INPUT_OUTPUT_COMMITMENT_LENGTH]
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    let Bls12_381PairingCheckCircuitInstanceWitness {
+        closed_form_input,
+        requests_queue_witness,
+        memory_reads_witness,
+    } = witness;
+
+    let mut structured_input = Bls12_381PairingCheckCircuitInputOutput::alloc_ignoring_outputs(
+        cs,
+        closed_form_input.clone(),
+    );
+
+    let start_flag = structured_input.start_flag;
+
+    let requests_queue_state_from_input = structured_input.observable_input.initial_log_queue_state;
+
+    // it must be trivial
+    requests_queue_state_from_input.enforce_trivial_head(cs);
+
+    let requests_queue_state_from_fsm = structured_input.hidden_fsm_input.log_queue_state;
+
+    let requests_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &requests_queue_state_from_input,
+        &requests_queue_state_from_fsm,
+    );
+
+    let memory_queue_state_from_input =
+        structured_input.observable_input.initial_memory_queue_state;
+
+    // it must be trivial
+    memory_queue_state_from_input.enforce_trivial_head(cs);
+
+    let memory_queue_state_from_fsm = structured_input.hidden_fsm_input.memory_queue_state;
+
+    let memory_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &memory_queue_state_from_input,
+        &memory_queue_state_from_fsm,
+    );
+
+    let mut requests_queue = StorageLogQueue::<F, R>::from_state(cs, requests_queue_state);
+    let queue_witness = CircuitQueueWitness::from_inner_witness(requests_queue_witness);
+    requests_queue.witness = Arc::new(queue_witness);
+
+    let mut memory_queue = MemoryQueue::<F, R>::from_state(cs, memory_queue_state);
+
+    let read_queries_allocator = ConditionalWitnessAllocator::<F, UInt256<F>> {
+        witness_source: Arc::new(RwLock::new(memory_reads_witness)),
+    };
+
+    let mut starting_fsm_state = Bls12_381PairingCheckFSM::placeholder(cs);
+    starting_fsm_state.read_precompile_call = Boolean::allocated_constant(cs, true);
+
+    let initial_state = Bls12_381PairingCheckFSM::conditionally_select(
+        cs,
+        start_flag,
+        &starting_fsm_state,
+        &structured_input.hidden_fsm_input.internal_fsm,
+    );
+
+    let final_state = bls12_381_pairing_check_precompile_inner::<F, CS, R>(
+        cs,
+        &mut memory_queue,
+        &mut requests_queue,
+        read_queries_allocator,
+        initial_state,
+        round_function,
+        limit,
+    );
+
+    let final_memory_state = memory_queue.into_state();
+    let final_requets_state = requests_queue.into_state();
+
+    // form the final state
+    let done = final_state.completed;
+    structured_input.completion_flag = done;
+    structured_input.observable_output = PrecompileFunctionOutputData::placeholder(cs);
+
+    structured_input.observable_output.final_memory_state = QueueState::conditionally_select(
+        cs,
+        structured_input.completion_flag,
+        &final_memory_state,
+        &structured_input.observable_output.final_memory_state,
+    );
+
+    structured_input.hidden_fsm_output.internal_fsm = final_state;
+    structured_input.hidden_fsm_output.log_queue_state = final_requets_state;
+    structured_input.hidden_fsm_output.memory_queue_state = final_memory_state;
+
+    // self-check
+    structured_input.hook_compare_witness(cs, &closed_form_input);
+
+    use boojum::cs::gates::PublicInputGate;
+
+    let compact_form =
+        ClosedFormInputCompactForm::from_full_form(cs, &structured_input, round_function);
+    let input_commitment = commit_variable_length_encodable_item(cs, &compact_form, round_function);
+    for el in input_commitment.iter() {
+        let gate = PublicInputGate::new(el.get_variable());
+        gate.add_to_cs(cs);
+    }
+
+    input_commitment
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test_utils::*;
+    use super::*;
+
+    use boojum::gadgets::traits::allocatable::CSAllocatable;
+    use boojum::implementations::poseidon2::Poseidon2Goldilocks;
+    use boojum::pairing::bls12_381::Fr as Bls12_381Fr;
+    use boojum::pairing::ff::{Field, PrimeField};
+    use boojum::pairing::{GenericCurveAffine, GenericCurveProjective};
+    use boojum::worker::Worker;
+    use std::collections::VecDeque;
+    use zkevm_opcode_defs::PrecompileCallABI;
+
+    type R = Poseidon2Goldilocks;
+
+    fn pair_into_u256_words(
+        p: Bls12_381G1Affine,
+        q: Bls12_381G2Affine,
+    ) -> [U256; PAIRING_PAIR_WORDS] {
+        let p_words = g1_into_u256_words(p);
+        let q_words = g2_into_u256_words(q);
+
+        std::array::from_fn(|i| {
+            if i < G1_POINT_WORDS {
+                p_words[i]
+            } else {
+                q_words[i - G1_POINT_WORDS]
+            }
+        })
+    }
+
+    fn run_pairing_check(pairs: &[[U256; PAIRING_PAIR_WORDS]]) -> (bool, bool) {
+        let mut owned_cs = create_cs(1 << 25);
+        let cs = &mut owned_cs;
+
+        let params = Arc::new(bls12_381_base_field_params());
+
+        // accumulator goes through the same encoding as in the FSM
+        let mut accumulator = Bls12_381Fq12::one(cs, &params);
+        let mut has_exception = Boolean::allocated_constant(cs, false);
+        for pair in pairs.iter() {
+            let words = pair.map(|el| UInt256::allocate(cs, el));
+            let (mut miller_loop_result, is_trivial, is_invalid) =
+                bls12_381_pairing_pair_inner(cs, &words, &params);
+            let new_accumulator = accumulator.mul(cs, &mut miller_loop_result);
+            let new_accumulator =
+                Bls12_381Fq12::conditionally_select(cs, is_trivial, &accumulator, &new_accumulator);
+            let accumulator_words = bls12_381_fq12_into_words(cs, new_accumulator);
+            accumulator = bls12_381_fq12_from_words(cs, &accumulator_words, &params);
+            has_exception = Boolean::multi_or(cs, &[has_exception, is_invalid]);
+        }
+
+        let mut final_exp_result = bls12_381_final_exponentiation(cs, &mut accumulator, &params);
+        let mut one_fq12 = Bls12_381Fq12::one(cs, &params);
+        let pairing_is_one = Bls12_381Fq12::equals(cs, &mut final_exp_result, &mut one_fq12);
+
+        let has_exception = has_exception.witness_hook(&*cs)().unwrap();
+        let pairing_is_one = pairing_is_one.witness_hook(&*cs)().unwrap();
+
+        cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        let worker = Worker::new();
+        assert!(cs.check_if_satisfied(&worker));
+
+        (has_exception, pairing_is_one)
+    }
+
+    #[test]
+    fn test_bls12_381_pairing_check() {
+        let a = Bls12_381Fr::from_str("1234567890123456789").unwrap();
+        let b = Bls12_381Fr::from_str("9876543210987654321").unwrap();
+        let mut ab = a;
+        ab.mul_assign(&b);
+        ab.negate();
+
+        let p = Bls12_381G1Affine::one();
+        let q = Bls12_381G2Affine::one();
+        let a_p = p.mul(a.into_repr()).into_affine();
+        let b_q = q.mul(b.into_repr()).into_affine();
+        let minus_ab_p = p.mul(ab.into_repr()).into_affine();
+
+        // e(aP, bQ) * e(-abP, Q) == 1
+        let (has_exception, pairing_is_one) = run_pairing_check(&[
+            pair_into_u256_words(a_p, b_q),
+            pair_into_u256_words(minus_ab_p, q),
+        ]);
+        assert!(!has_exception);
+        assert!(pairing_is_one);
+
+        // e(aP, bQ) != 1
+        let (has_exception, pairing_is_one) = run_pairing_check(&[pair_into_u256_words(a_p, b_q)]);
+        assert!(!has_exception);
+        assert!(!pairing_is_one);
+    }
+
+    #[test]
+    fn test_bls12_381_pairing_check_invalid_inputs() {
+        let p = Bls12_381G1Affine::one();
+        let q = Bls12_381G2Affine::one();
+
+        // point at infinity in G2 makes the pair trivial
+        let mut words = pair_into_u256_words(p, q);
+        for word in words[G1_POINT_WORDS..].iter_mut() {
+            *word = U256::zero();
+        }
+        let (has_exception, pairing_is_one) = run_pairing_check(&[words]);
+        assert!(!has_exception);
+        assert!(pairing_is_one);
+
+        // G1 point is on curve, but not in the subgroup
+        let mut words = pair_into_u256_words(p, q);
+        words[..G1_POINT_WORDS].copy_from_slice(&[
+            U256::zero(),
+            U256::zero(),
+            U256::zero(),
+            U256::from(2u64),
+        ]);
+        let (has_exception, _) = run_pairing_check(&[words]);
+        assert!(has_exception);
+    }
+
+    // runs the FSM over as many instances as there are limits, passing the state from
+    // one instance to the next one, and returns the written (success, result) for every call
+    fn run_in_instances(
+        calls: &[Vec<[U256; PAIRING_PAIR_WORDS]>],
+        limits: &[usize],
+    ) -> Vec<(U256, U256)> {
+        let memory_read_witness: VecDeque<U256> =
+            calls.iter().flatten().flatten().copied().collect();
+        let memory_read_witness = Arc::new(RwLock::new(memory_read_witness));
+
+        let requests: Vec<LogQueryWitness<F>> = calls
+            .iter()
+            .enumerate()
+            .map(|(call_idx, pairs)| {
+                let precompile_abi = PrecompileCallABI {
+                    input_memory_offset: (call_idx * 64) as u32,
+                    input_memory_length: 0,
+                    output_memory_offset: (call_idx * 2) as u32,
+                    output_memory_length: 2,
+                    memory_page_to_read: 123,
+                    memory_page_to_write: 456,
+                    precompile_interpreted_data: pairs.len() as u64,
+                };
+
+                LogQueryWitness {
+                    address: BLS12_381_PAIRING_CHECK_PRECOMPILE_FORMAL_ADDRESS,
+                    key: precompile_abi.to_u256(),
+                    read_value: U256::zero(),
+                    written_value: U256::zero(),
+                    aux_byte: PRECOMPILE_AUX_BYTE,
+                    rw_flag: true,
+                    rollback: false,
+                    is_service: false,
+                    shard_id: 0,
+                    tx_number_in_block: 0,
+                    timestamp: (call_idx as u32 + 1) * 4,
+                }
+            })
+            .collect();
+        let mut num_requests_popped = 0;
+
+        let mut state_witness = Bls12_381PairingCheckFSM::placeholder_witness();
+        state_witness.read_precompile_call = true;
+
+        let mut written_values = vec![];
+        for (instance_idx, limit) in limits.iter().enumerate() {
+            let mut owned_cs = create_cs(1 << 26);
+            let cs = &mut owned_cs;
+            let mut memory_queue = MemoryQueue::<F, R>::empty(cs);
+            let boolean_true = Boolean::allocated_constant(cs, true);
+
+            // every instance sees the requests that are not yet processed
+            let mut precompile_calls_queue = StorageLogQueue::<F, R>::empty(cs);
+            for el in requests[num_requests_popped..].iter() {
+                let el = LogQuery::allocate(cs, el.clone());
+                precompile_calls_queue.push(cs, el, boolean_true);
+            }
+            let num_requests_before = precompile_calls_queue
+                .witness
+                .elements
+                .read()
+                .unwrap()
+                .len();
+
+            let state = Bls12_381PairingCheckFSM::allocate(cs, state_witness.clone());
+            let round_function = Poseidon2Goldilocks;
+
+            let memory_read_witness = ConditionalWitnessAllocator::<F, UInt256<F>> {
+                witness_source: memory_read_witness.clone(),
+            };
+
+            let new_state = bls12_381_pairing_check_precompile_inner(
+                cs,
+                &mut memory_queue,
+                &mut precompile_calls_queue,
+                memory_read_witness,
+                state,
+                &round_function,
+                *limit,
+            );
+
+            let is_last = instance_idx == limits.len() - 1;
+            assert_eq!(new_state.completed.witness_hook(cs)().unwrap(), is_last);
+            state_witness = new_state.witness_hook(cs)().unwrap();
+
+            let num_requests_after = precompile_calls_queue
+                .witness
+                .elements
+                .read()
+                .unwrap()
+                .len();
+            num_requests_popped += num_requests_before - num_requests_after;
+
+            for (query, _) in memory_queue.witness.elements.read().unwrap().iter() {
+                if query.rw_flag {
+                    written_values.push(query.value);
+                }
+            }
+
+            owned_cs.pad_and_shrink();
+            let mut assembly = owned_cs.into_assembly::<std::alloc::Global>();
+            let worker = Worker::new();
+            assert!(assembly.check_if_satisfied(&worker));
+        }
+
+        assert!(memory_read_witness.read().unwrap().is_empty());
+        assert_eq!(num_requests_popped, calls.len());
+
+        written_values.chunks(2).map(|el| (el[0], el[1])).collect()
+    }
+
+    #[test]
+    fn test_bls12_381_pairing_check_fsm_across_instances() {
+        let a = Bls12_381Fr::from_str("1234567890123456789").unwrap();
+        let b = Bls12_381Fr::from_str("9876543210987654321").unwrap();
+        let mut ab = a;
+        ab.mul_assign(&b);
+        ab.negate();
+
+        let p = Bls12_381G1Affine::one();
+        let q = Bls12_381G2Affine::one();
+        let a_p = p.mul(a.into_repr()).into_affine();
+        let b_q = q.mul(b.into_repr()).into_affine();
+        let minus_ab_p = p.mul(ab.into_repr()).into_affine();
+
+        let calls = vec![
+            vec![
+                pair_into_u256_words(a_p, b_q),
+                pair_into_u256_words(minus_ab_p, q),
+            ],
+            vec![pair_into_u256_words(a_p, b_q)],
+            vec![],
+        ];
+        let one = U256::one();
+        let zero = U256::zero();
+        // unlike ecPairing, the call without pairs fails
+        let expected = vec![(one, one), (one, zero), (zero, zero)];
+
+        // every instance finalizes one call, idling after it if there are cycles left
+        assert_eq!(run_in_instances(&calls, &[2, 1, 1]), expected);
+        assert_eq!(run_in_instances(&calls, &[3, 2, 1]), expected);
+        // first call is split between two instances
+        assert_eq!(run_in_instances(&calls, &[1, 1, 1, 1]), expected);
+    }
+}
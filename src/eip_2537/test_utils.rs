@@ -0,0 +1,192 @@
+// Helpers shared by the tests of EIP-2537 precompiles
+
+use super::*;
+
+use boojum::algebraic_props::poseidon2_parameters::*;
+use boojum::config::DevCSConfig;
+use boojum::cs::cs_builder::*;
+use boojum::cs::cs_builder_reference::CsReferenceImplementationBuilder;
+use boojum::cs::gates::*;
+use boojum::cs::implementations::reference_cs::CSReferenceImplementation;
+use boojum::cs::traits::gate::GatePlacementStrategy;
+use boojum::cs::CSGeometry;
+use boojum::cs::*;
+use boojum::field::goldilocks::GoldilocksField;
+use boojum::gadgets::tables::*;
+use boojum::pairing::ff::{PrimeField, PrimeFieldRepr};
+
+pub(crate) type F = GoldilocksField;
+pub(crate) type P = GoldilocksField;
+
+pub(crate) fn create_cs(
+    max_trace_len: usize,
+) -> CSReferenceImplementation<
+    F,
+    P,
+    DevCSConfig,
+    impl GateConfigurationHolder<F>,
+    impl StaticToolboxHolder,
+> {
+    let geometry = CSGeometry {
+        num_columns_under_copy_permutation: 80,
+        num_witness_columns: 0,
+        num_constant_columns: 4,
+        max_allowed_constraint_degree: 8,
+    };
+    let max_variables = 1 << 27;
+
+    fn configure<
+        F: SmallField,
+        T: CsBuilderImpl<F, T>,
+        GC: GateConfigurationHolder<F>,
+        TB: StaticToolboxHolder,
+    >(
+        builder: CsBuilder<T, F, GC, TB>,
+    ) -> CsBuilder<T, F, impl GateConfigurationHolder<F>, impl StaticToolboxHolder> {
+        let builder = builder.allow_lookup(
+            LookupParameters::UseSpecializedColumnsWithTableIdAsConstant {
+                width: 3,
+                num_repetitions: 16,
+                share_table_id: true,
+            },
+        );
+
+        let builder = ConstantsAllocatorGate::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+        );
+        let builder = BooleanConstraintGate::configure_builder(
+            builder,
+            GatePlacementStrategy::UseSpecializedColumns {
+                num_repetitions: 1,
+                share_constants: false,
+            },
+        );
+        let builder = U8x4FMAGate::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+        );
+        let builder = ZeroCheckGate::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+            false,
+        );
+        let builder = FmaGateInBaseFieldWithoutConstant::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+        );
+        let builder = UIntXAddGate::<32>::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+        );
+        let builder = UIntXAddGate::<16>::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+        );
+        let builder = UIntXAddGate::<8>::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+        );
+        let builder = DotProductGate::<4>::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+        );
+        let builder = SelectionGate::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+        );
+        let builder = ParallelSelectionGate::<4>::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+        );
+        let builder = PublicInputGate::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+        );
+        let builder = ReductionGate::<_, 4>::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+        );
+        let builder =
+            MatrixMultiplicationGate::<F, 12, Poseidon2GoldilocksExternalMatrix>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+        let builder =
+            MatrixMultiplicationGate::<F, 12, Poseidon2GoldilocksInnerMatrix>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+        let builder =
+            NopGate::configure_builder(builder, GatePlacementStrategy::UseGeneralPurposeColumns);
+
+        builder
+    }
+
+    let builder_impl =
+        CsReferenceImplementationBuilder::<F, P, DevCSConfig>::new(geometry, max_trace_len);
+    let builder = new_builder::<_, F>(builder_impl);
+
+    let builder = configure(builder);
+    let mut owned_cs = builder.build(max_variables);
+
+    // add tables
+    let table = create_xor8_table();
+    owned_cs.add_lookup_table::<Xor8Table, 3>(table);
+
+    let table = create_byte_split_table::<F, 4>();
+    owned_cs.add_lookup_table::<ByteSplitTable<4>, 3>(table);
+
+    owned_cs
+}
+
+pub(crate) fn fq_into_u256_words(value: Bls12_381Fq) -> [U256; FP_WORDS] {
+    let repr = value.into_repr();
+    let words = repr.as_ref();
+
+    [
+        U256([words[4], words[5], 0, 0]),
+        U256([words[0], words[1], words[2], words[3]]),
+    ]
+}
+
+pub(crate) fn u256_words_into_fq(words: &[U256]) -> Bls12_381Fq {
+    let [hi, lo] = [words[0], words[1]];
+    assert!(hi.0[2] == 0 && hi.0[3] == 0);
+    let mut repr = <Bls12_381Fq as PrimeField>::Repr::default();
+    repr.as_mut()[..4].copy_from_slice(&lo.0);
+    repr.as_mut()[4..].copy_from_slice(&hi.0[..2]);
+
+    Bls12_381Fq::from_repr(repr).unwrap()
+}
+
+pub(crate) fn g1_into_u256_words(point: Bls12_381G1Affine) -> [U256; G1_POINT_WORDS] {
+    use boojum::pairing::GenericCurveAffine;
+
+    if point.is_zero() {
+        return [U256::zero(); G1_POINT_WORDS];
+    }
+    let (x, y) = point.into_xy_unchecked();
+    let [x_hi, x_lo] = fq_into_u256_words(x);
+    let [y_hi, y_lo] = fq_into_u256_words(y);
+
+    [x_hi, x_lo, y_hi, y_lo]
+}
+
+pub(crate) fn g2_into_u256_words(point: Bls12_381G2Affine) -> [U256; G2_POINT_WORDS] {
+    use boojum::pairing::GenericCurveAffine;
+
+    let mut words = [U256::zero(); G2_POINT_WORDS];
+    if point.is_zero() {
+        return words;
+    }
+    let (x, y) = point.into_xy_unchecked();
+    for (dst, src) in words
+        .array_chunks_mut::<FP_WORDS>()
+        .zip([x.c0, x.c1, y.c0, y.c1])
+    {
+        *dst = fq_into_u256_words(src);
+    }
+
+    words
+}
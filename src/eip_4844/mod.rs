@@ -31,8 +31,9 @@ use self::input::*;
 use boojum::pairing::bls12_381::fr::Fr as Bls12_381Fr;
 
 const NUM_WORDS_FR: usize = 17;
-type Bls12_381ScalarNNFieldParams = NonNativeFieldOverU16Params<Bls12_381Fr, NUM_WORDS_FR>;
-type Bls12_381ScalarNNField<F> = NonNativeFieldOverU16<F, Bls12_381Fr, 17>;
+pub(crate) type Bls12_381ScalarNNFieldParams =
+    NonNativeFieldOverU16Params<Bls12_381Fr, NUM_WORDS_FR>;
+pub(crate) type Bls12_381ScalarNNField<F> = NonNativeFieldOverU16<F, Bls12_381Fr, 17>;
 
 // turns 128 bits into a Bls12 field element.
 fn convert_truncated_keccak_digest_to_field_element<F: SmallField, CS: ConstraintSystem<F>>(
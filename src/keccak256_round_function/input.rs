@@ -30,10 +30,12 @@ pub struct Keccak256RoundFunctionFSM<F: SmallField> {
     pub read_precompile_call: Boolean<F>,
     pub read_unaligned_words_for_round: Boolean<F>,
     pub padding_round: Boolean<F>,
+    pub squeeze_round: Boolean<F>,
     pub completed: Boolean<F>,
     pub keccak_internal_state: [[[UInt8<F>; BYTES_PER_WORD]; LANE_WIDTH]; LANE_WIDTH],
     pub timestamp_to_use_for_read: UInt32<F>,
     pub timestamp_to_use_for_write: UInt32<F>,
+    pub squeeze_lane_offset: UInt8<F>,
     pub precompile_call_params: Keccak256PrecompileCallParams<F>,
     pub buffer: ByteBuffer<F, KECCAK_PRECOMPILE_BUFFER_SIZE>,
 }
@@ -47,10 +49,12 @@ impl<F: SmallField> CSPlaceholder<F> for Keccak256RoundFunctionFSM<F> {
             read_precompile_call: boolean_false,
             read_unaligned_words_for_round: boolean_false,
             padding_round: boolean_false,
+            squeeze_round: boolean_false,
             completed: boolean_false,
             keccak_internal_state: [[[zero_u8; BYTES_PER_WORD]; LANE_WIDTH]; LANE_WIDTH],
             timestamp_to_use_for_read: zero_u32,
             timestamp_to_use_for_write: zero_u32,
+            squeeze_lane_offset: zero_u8,
             precompile_call_params: Keccak256PrecompileCallParams::<F>::placeholder(cs),
             buffer: ByteBuffer::<F, KECCAK_PRECOMPILE_BUFFER_SIZE>::placeholder(cs),
         }
@@ -37,11 +37,44 @@ use boojum::gadgets::u160::UInt160;
 use boojum::gadgets::u8::UInt8;
 use std::sync::{Arc, RwLock};
 
+use crate::base_structures::precompile_input_outputs::*;
+use crate::demux_log_queue::DemuxOutput;
+use crate::precompile_registry::PrecompileCircuit;
+use crate::scheduler::auxiliary::BaseLayerCircuitType;
+use zkevm_opcode_defs::ethereum_types::H160;
+
 pub mod buffer;
 
 pub mod input;
+
 use self::input::*;
 
+pub struct Keccak256PrecompileCircuit;
+
+impl PrecompileCircuit for Keccak256PrecompileCircuit {
+    const NAME: &'static str = "keccak256";
+    const DEMUX_OUTPUT: DemuxOutput = DemuxOutput::Keccak;
+    const CIRCUIT_TYPE: BaseLayerCircuitType = BaseLayerCircuitType::KeccakPrecompile;
+
+    type InputData<F: SmallField> = PrecompileFunctionInputData<F>;
+    type OutputData<F: SmallField> = PrecompileFunctionOutputData<F>;
+
+    fn formal_address() -> H160 {
+        *zkevm_opcode_defs::system_params::KECCAK256_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS
+    }
+}
+
+// Hashing mode is selected by the lowest word of `precompile_interpreted_data`. Any unknown value
+// falls back to Keccak-256, so existing callers that leave it zero are not affected
+pub const KECCAK256_MODE: u32 = 0;
+pub const SHA3_256_MODE: u32 = 1;
+pub const SHAKE128_MODE: u32 = 2;
+pub const SHAKE256_MODE: u32 = 3;
+
+pub const KECCAK256_DOMAIN_SEPARATOR: u8 = 0x01;
+pub const SHA3_DOMAIN_SEPARATOR: u8 = 0x06;
+pub const SHAKE_DOMAIN_SEPARATOR: u8 = 0x1f;
+
 #[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
 #[derivative(Clone, Copy, Debug)]
 // #[DerivePrettyComparison("true")]
@@ -51,11 +84,15 @@ pub struct Keccak256PrecompileCallParams<F: SmallField> {
     pub input_memory_byte_length: UInt32<F>,
     pub output_page: UInt32<F>,
     pub output_word_offset: UInt32<F>,
+    pub output_words_left: UInt32<F>,
+    pub domain_separator: UInt8<F>,
+    pub uses_shake128_rate: Boolean<F>,
     pub needs_full_padding_round: Boolean<F>,
 }
 
 impl<F: SmallField> CSPlaceholder<F> for Keccak256PrecompileCallParams<F> {
     fn placeholder<CS: ConstraintSystem<F>>(cs: &mut CS) -> Self {
+        let zero_u8 = UInt8::zero(cs);
         let zero_u32 = UInt32::zero(cs);
         let boolean_false = Boolean::allocated_constant(cs, false);
         Self {
@@ -64,25 +101,71 @@ impl<F: SmallField> CSPlaceholder<F> for Keccak256PrecompileCallParams<F> {
             input_memory_byte_length: zero_u32,
             output_page: zero_u32,
             output_word_offset: zero_u32,
+            output_words_left: zero_u32,
+            domain_separator: zero_u8,
+            uses_shake128_rate: boolean_false,
             needs_full_padding_round: boolean_false,
         }
     }
 }
 
 impl<F: SmallField> Keccak256PrecompileCallParams<F> {
-    // from PrecompileCallABI
+    // from PrecompileCallABI. For SHAKE modes the output memory length is the number of 32 byte words
+    // to squeeze, and at least one word is always written
     pub fn from_encoding<CS: ConstraintSystem<F>>(cs: &mut CS, encoding: UInt256<F>) -> Self {
         let input_memory_byte_offset = encoding.inner[0];
         let input_memory_byte_length = encoding.inner[1];
 
         let output_word_offset = encoding.inner[2];
+        let requested_output_words = encoding.inner[3];
 
         let input_page = encoding.inner[4];
         let output_page = encoding.inner[5];
 
+        let mode = encoding.inner[6];
+        let sha3_256_mode = UInt32::allocated_constant(cs, SHA3_256_MODE);
+        let shake128_mode = UInt32::allocated_constant(cs, SHAKE128_MODE);
+        let shake256_mode = UInt32::allocated_constant(cs, SHAKE256_MODE);
+        let is_sha3_256 = UInt32::equals(cs, &mode, &sha3_256_mode);
+        let is_shake128 = UInt32::equals(cs, &mode, &shake128_mode);
+        let is_shake256 = UInt32::equals(cs, &mode, &shake256_mode);
+        let is_shake = Boolean::multi_or(cs, &[is_shake128, is_shake256]);
+
+        let keccak256_domain_separator = UInt8::allocated_constant(cs, KECCAK256_DOMAIN_SEPARATOR);
+        let sha3_domain_separator = UInt8::allocated_constant(cs, SHA3_DOMAIN_SEPARATOR);
+        let shake_domain_separator = UInt8::allocated_constant(cs, SHAKE_DOMAIN_SEPARATOR);
+        let domain_separator = UInt8::conditionally_select(
+            cs,
+            is_sha3_256,
+            &sha3_domain_separator,
+            &keccak256_domain_separator,
+        );
+        let domain_separator =
+            UInt8::conditionally_select(cs, is_shake, &shake_domain_separator, &domain_separator);
+
+        // fixed length modes always output a single word
+        let one_u32 = UInt32::allocated_constant(cs, 1);
+        let no_output_words_requested = requested_output_words.is_zero(cs);
+        let shake_output_words = UInt32::conditionally_select(
+            cs,
+            no_output_words_requested,
+            &one_u32,
+            &requested_output_words,
+        );
+        let output_words_left =
+            UInt32::conditionally_select(cs, is_shake, &shake_output_words, &one_u32);
+
         let (_, rem) = input_memory_byte_length.div_by_constant(cs, KECCAK_RATE_BYTES as u32);
+        let needs_full_padding_round_for_keccak256_rate = rem.is_zero(cs);
+        let (_, rem) = input_memory_byte_length.div_by_constant(cs, SHAKE128_RATE_BYTES as u32);
+        let needs_full_padding_round_for_shake128_rate = rem.is_zero(cs);
 
-        let needs_full_padding_round = rem.is_zero(cs);
+        let needs_full_padding_round = Boolean::conditionally_select(
+            cs,
+            is_shake128,
+            &needs_full_padding_round_for_shake128_rate,
+            &needs_full_padding_round_for_keccak256_rate,
+        );
 
         let new = Self {
             input_page,
@@ -90,6 +173,9 @@ impl<F: SmallField> Keccak256PrecompileCallParams<F> {
             input_memory_byte_length,
             output_page,
             output_word_offset,
+            output_words_left,
+            domain_separator,
+            uses_shake128_rate: is_shake128,
             needs_full_padding_round,
         };
 
@@ -97,7 +183,7 @@ impl<F: SmallField> Keccak256PrecompileCallParams<F> {
     }
 }
 
-fn trivial_mapping_function<
+pub(crate) fn trivial_mapping_function<
     F: SmallField,
     CS: ConstraintSystem<F>,
     const N: usize,
@@ -144,6 +230,11 @@ fn trivial_mapping_function<
 use boojum::gadgets::keccak256::KECCAK_RATE_BYTES;
 
 pub const KECCAK256_RATE_IN_U64_WORDS: usize = 17;
+// SHAKE128 has the largest rate of all supported modes, so blocks are always formed of this size
+// and bytes above the rate of the current mode are left as zeroes
+pub const SHAKE128_RATE_BYTES: usize = 168;
+pub const SHAKE128_RATE_IN_U64_WORDS: usize = 21;
+pub const U64_WORDS_PER_OUTPUT_WORD: usize = 4;
 pub const MEMORY_EQURIES_PER_CYCLE: usize = 6; // we need to read as much as possible to use a round function every cycle
 pub const NUM_U64_WORDS_PER_CYCLE: usize = 4 * MEMORY_EQURIES_PER_CYCLE;
 pub const NEW_BYTES_PER_CYCLE: usize = 8 * NUM_U64_WORDS_PER_CYCLE;
@@ -185,10 +276,10 @@ where
     let one_num = Num::allocated_constant(cs, F::ONE);
 
     let empty_buffer = ByteBuffer::<F, KECCAK_PRECOMPILE_BUFFER_SIZE>::placeholder(cs);
-
-    let mut full_padding_buffer = [zero_u8; KECCAK_RATE_BYTES];
-    full_padding_buffer[0] = UInt8::allocated_constant(cs, 0x01);
-    full_padding_buffer[KECCAK_RATE_BYTES - 1] = UInt8::allocated_constant(cs, 0x80);
+    let last_byte_padding_marker = UInt8::allocated_constant(cs, 0x80);
+    let lanes_per_output_word = UInt8::allocated_constant(cs, U64_WORDS_PER_OUTPUT_WORD as u8);
+    let lane_offsets: [UInt8<F>; SHAKE128_RATE_IN_U64_WORDS] =
+        std::array::from_fn(|i| UInt8::allocated_constant(cs, i as u8));
 
     // we can have a degenerate case when queue is empty, but it's a first circuit in the queue,
     // so we taken default FSM state that has state.read_precompile_call = true;
@@ -209,14 +300,14 @@ where
         .mask_negated(cs, can_finish_immediatelly);
     state.completed = Boolean::multi_or(cs, &[state.completed, can_finish_immediatelly]);
 
+    // we collect the absorbed bytes and compare the first output word against the reference implementation
     #[allow(unused_variables)]
-    let mut keccak_self_verifier = None;
+    let mut keccak_self_verifier: Option<(bool, Vec<u8>)> = None;
     if <CS::Config as CSConfig>::DebugConfig::PERFORM_RUNTIME_ASSERTS == true {
-        use zkevm_opcode_defs::sha3::Digest;
         if state.read_precompile_call.witness_hook(cs)().unwrap() == true {
-            keccak_self_verifier = Some((true, zkevm_opcode_defs::sha3::Keccak256::new()));
+            keccak_self_verifier = Some((true, vec![]));
         } else {
-            keccak_self_verifier = Some((false, zkevm_opcode_defs::sha3::Keccak256::new()));
+            keccak_self_verifier = Some((false, vec![]));
         }
     }
 
@@ -233,6 +324,7 @@ where
             dbg!(state.read_precompile_call.witness_hook(cs)());
             dbg!(state.read_unaligned_words_for_round.witness_hook(cs)());
             dbg!(state.padding_round.witness_hook(cs)());
+            dbg!(state.squeeze_round.witness_hook(cs)());
             dbg!(state.completed.witness_hook(cs)());
             dbg!(state
                 .precompile_call_params
@@ -249,10 +341,8 @@ where
         }
 
         if <CS::Config as CSConfig>::DebugConfig::PERFORM_RUNTIME_ASSERTS == true {
-            use zkevm_opcode_defs::sha3::Digest;
             if state.read_precompile_call.witness_hook(cs)().unwrap() == true {
-                *keccak_self_verifier.as_mut().unwrap() =
-                    (true, zkevm_opcode_defs::sha3::Keccak256::new());
+                *keccak_self_verifier.as_mut().unwrap() = (true, vec![]);
             }
         }
 
@@ -392,6 +482,9 @@ where
             )
         };
 
+        let uses_shake128_rate = state.precompile_call_params.uses_shake128_rate;
+        let buffer_capacity = UInt8::allocated_constant(cs, KECCAK_PRECOMPILE_BUFFER_SIZE as u8);
+
         let mut bias_variable = should_read_in_general.get_variable();
         // logic in short - we always try to read from memory into buffer,
         // and every time execute 1 keccak256 round function
@@ -418,16 +511,34 @@ where
                 &at_most_meaningful_bytes_in_query,
             );
 
-            let nothing_to_read = meaningful_bytes_in_query.is_zero(cs);
-            let have_something_to_read = nothing_to_read.negated(cs);
             let bytes_to_fill =
                 unsafe { UInt8::from_variable_unchecked(meaningful_bytes_in_query.get_variable()) };
             let enough_buffer_space = state.buffer.can_fill_bytes(cs, bytes_to_fill);
+
+            // SHAKE128 rate is larger than what the buffer always has after the reads, so in this mode
+            // we also fill the buffer up to the end with a part of the word, and read the rest of it later
+            let not_enough_buffer_space = enough_buffer_space.negated(cs);
+            let fill_partially =
+                Boolean::multi_and(cs, &[uses_shake128_rate, not_enough_buffer_space]);
+            let (free_space, _) = buffer_capacity.overflowing_sub(cs, &state.buffer.filled);
+            let bytes_to_fill =
+                UInt8::conditionally_select(cs, fill_partially, &free_space, &bytes_to_fill);
+            let free_space = unsafe { UInt32::from_variable_unchecked(free_space.get_variable()) };
+            let meaningful_bytes_in_query = UInt32::conditionally_select(
+                cs,
+                fill_partially,
+                &free_space,
+                &meaningful_bytes_in_query,
+            );
+            let can_fill = Boolean::multi_or(cs, &[enough_buffer_space, fill_partially]);
+
+            let nothing_to_read = meaningful_bytes_in_query.is_zero(cs);
+            let have_something_to_read = nothing_to_read.negated(cs);
             let should_read = Boolean::multi_and(
                 cs,
                 &[
                     have_something_to_read,
-                    enough_buffer_space,
+                    can_fill,
                     state.read_unaligned_words_for_round,
                 ],
             );
@@ -503,14 +614,74 @@ where
             .input_memory_byte_length
             .is_zero(cs);
 
+        let uses_keccak256_rate = uses_shake128_rate.negated(cs);
+        let domain_separator = state.precompile_call_params.domain_separator;
+
         let currently_filled = state.buffer.filled;
-        let almost_filled = UInt8::allocated_constant(cs, (KECCAK_RATE_BYTES - 1) as u8);
+        let keccak256_rate_almost_filled =
+            UInt8::allocated_constant(cs, (KECCAK_RATE_BYTES - 1) as u8);
+        let shake128_rate_almost_filled =
+            UInt8::allocated_constant(cs, (SHAKE128_RATE_BYTES - 1) as u8);
+        let almost_filled = UInt8::conditionally_select(
+            cs,
+            uses_shake128_rate,
+            &shake128_rate_almost_filled,
+            &keccak256_rate_almost_filled,
+        );
         let do_one_byte_of_padding = UInt8::equals(cs, &currently_filled, &almost_filled);
+        // the first block of unaligned input may be shorter than SHAKE128 rate, and then we only read
+        // in this cycle and absorb in the next one. Keccak256 rate is always filled
+        let keccak256_rate_bytes = UInt8::allocated_constant(cs, KECCAK_RATE_BYTES as u8);
+        let shake128_rate_bytes = UInt8::allocated_constant(cs, SHAKE128_RATE_BYTES as u8);
+        let rate_bytes = UInt8::conditionally_select(
+            cs,
+            uses_shake128_rate,
+            &shake128_rate_bytes,
+            &keccak256_rate_bytes,
+        );
+        let (_, less_than_rate_filled) = currently_filled.overflowing_sub(cs, &rate_bytes);
+        let have_bytes_left = zero_bytes_left.negated(cs);
+        let wait_for_more_input = Boolean::multi_and(
+            cs,
+            &[
+                state.read_unaligned_words_for_round,
+                less_than_rate_filled,
+                have_bytes_left,
+            ],
+        );
+        let buffer_before_absorption = state.buffer;
+
         // NOTE: we have already precomputed if we will need a full padding round, so we just take something form buffer
         // and run keccak premutation
-        let mut input = state
+        let mut input = [zero_u8; SHAKE128_RATE_BYTES];
+        let keccak256_rate_part = state
             .buffer
             .consume::<CS, KECCAK_RATE_BYTES>(cs, boolean_true);
+        input[..KECCAK_RATE_BYTES].copy_from_slice(&keccak256_rate_part);
+        // SHAKE128 takes a few more bytes, otherwise we roll the buffer back
+        let buffer_after_keccak256_rate_part = state.buffer;
+        let shake128_rate_part = state
+            .buffer
+            .consume::<CS, { SHAKE128_RATE_BYTES - KECCAK_RATE_BYTES }>(cs, boolean_true);
+        state.buffer = ByteBuffer::<F, KECCAK_PRECOMPILE_BUFFER_SIZE>::conditionally_select(
+            cs,
+            uses_shake128_rate,
+            &state.buffer,
+            &buffer_after_keccak256_rate_part,
+        );
+        for (dst, src) in input[KECCAK_RATE_BYTES..]
+            .iter_mut()
+            .zip(shake128_rate_part.iter())
+        {
+            *dst = src.mask(cs, uses_shake128_rate);
+        }
+        state.buffer = ByteBuffer::<F, KECCAK_PRECOMPILE_BUFFER_SIZE>::conditionally_select(
+            cs,
+            wait_for_more_input,
+            &buffer_before_absorption,
+            &state.buffer,
+        );
+
         let buffer_now_empty = state.buffer.filled.is_zero(cs);
         let no_extra_padding_round_required = state
             .precompile_call_params
@@ -527,83 +698,189 @@ where
         );
 
         if <CS::Config as CSConfig>::DebugConfig::PERFORM_RUNTIME_ASSERTS == true {
-            use zkevm_opcode_defs::sha3::Digest;
-            if state.padding_round.witness_hook(cs)().unwrap() == false {
-                if apply_padding.witness_hook(cs)().unwrap() == true {
-                    let bytes_to_feed = currently_filled.witness_hook(cs)().unwrap();
-                    let buffer_to_feed = input.witness_hook(cs)().unwrap();
-                    dbg!(hex::encode(&buffer_to_feed[..(bytes_to_feed as usize)]));
-                    keccak_self_verifier
-                        .as_mut()
-                        .unwrap()
-                        .1
-                        .update(&buffer_to_feed[..(bytes_to_feed as usize)]);
+            if state.padding_round.witness_hook(cs)().unwrap() == false
+                && state.squeeze_round.witness_hook(cs)().unwrap() == false
+                && wait_for_more_input.witness_hook(cs)().unwrap() == false
+            {
+                let buffer_to_feed = input.witness_hook(cs)().unwrap();
+                let bytes_to_feed = if apply_padding.witness_hook(cs)().unwrap() == true {
+                    currently_filled.witness_hook(cs)().unwrap() as usize
+                } else if uses_shake128_rate.witness_hook(cs)().unwrap() == true {
+                    SHAKE128_RATE_BYTES
                 } else {
-                    let buffer_to_feed = input.witness_hook(cs)().unwrap();
-                    dbg!(hex::encode(&buffer_to_feed[..]));
-                    keccak_self_verifier
-                        .as_mut()
-                        .unwrap()
-                        .1
-                        .update(&buffer_to_feed[..]);
-                }
+                    KECCAK_RATE_BYTES
+                };
+                dbg!(hex::encode(&buffer_to_feed[..bytes_to_feed]));
+                keccak_self_verifier
+                    .as_mut()
+                    .unwrap()
+                    .1
+                    .extend_from_slice(&buffer_to_feed[..bytes_to_feed]);
             } else {
                 // we absorb nothing, and "finalize" will take care of the rest
             }
         }
 
         let mut tmp = currently_filled.into_num();
-        let pad_constant = UInt8::allocated_constant(cs, 0x01);
-        for dst in input[..(KECCAK_RATE_BYTES - 1)].iter_mut() {
+        for dst in input[..(SHAKE128_RATE_BYTES - 1)].iter_mut() {
             let pad_this_byte = tmp.is_zero(cs);
             let apply_padding = Boolean::multi_and(cs, &[apply_padding, pad_this_byte]);
-            *dst = UInt8::conditionally_select(cs, apply_padding, &pad_constant, &*dst);
+            *dst = UInt8::conditionally_select(cs, apply_padding, &domain_separator, &*dst);
             tmp = tmp.sub(cs, &one_num);
         }
 
-        let normal_last_byte_padding_value = UInt8::allocated_constant(cs, 0x80);
-        let special_last_byte_paddings_value = UInt8::allocated_constant(cs, 0x81);
+        // domain separator never has the highest bit set, so we can just add the marker
+        let special_last_byte_padding_value = domain_separator
+            .into_num()
+            .add(cs, &last_byte_padding_marker.into_num());
+        let special_last_byte_padding_value = unsafe {
+            UInt8::from_variable_unchecked(special_last_byte_padding_value.get_variable())
+        };
         let last_byte_padding_value = UInt8::conditionally_select(
             cs,
             do_one_byte_of_padding,
-            &special_last_byte_paddings_value,
-            &normal_last_byte_padding_value,
-        );
-        input[KECCAK_RATE_BYTES - 1] = UInt8::conditionally_select(
-            cs,
-            apply_padding,
-            &last_byte_padding_value,
-            &input[KECCAK_RATE_BYTES - 1],
+            &special_last_byte_padding_value,
+            &last_byte_padding_marker,
         );
+        for (last_byte_idx, uses_this_rate) in [
+            (KECCAK_RATE_BYTES - 1, uses_keccak256_rate),
+            (SHAKE128_RATE_BYTES - 1, uses_shake128_rate),
+        ] {
+            let apply_padding = Boolean::multi_and(cs, &[apply_padding, uses_this_rate]);
+            input[last_byte_idx] = UInt8::conditionally_select(
+                cs,
+                apply_padding,
+                &last_byte_padding_value,
+                &input[last_byte_idx],
+            );
+        }
+
+        let mut full_padding_buffer = [zero_u8; SHAKE128_RATE_BYTES];
+        full_padding_buffer[0] = domain_separator;
+        full_padding_buffer[KECCAK_RATE_BYTES - 1] =
+            last_byte_padding_marker.mask(cs, uses_keccak256_rate);
+        full_padding_buffer[SHAKE128_RATE_BYTES - 1] =
+            last_byte_padding_marker.mask(cs, uses_shake128_rate);
 
         let input =
             UInt8::<F>::parallel_select(cs, state.padding_round, &full_padding_buffer, &input);
+        // when squeezing we only run the permutation
+        let input = input.map(|el| el.mask_negated(cs, state.squeeze_round));
         if crate::config::CIRCUIT_VERSOBE {
             dbg!(input.witness_hook(cs)().map(|el| hex::encode(&el)));
         }
 
         // manually absorb and run round function
+        let state_before_permutation = state.keccak_internal_state;
         let squeezed =
             keccak256_absorb_and_run_permutation(cs, &mut state.keccak_internal_state, &input);
 
+        // when squeezing more than one word the output word may start anywhere within the rate of
+        // the current state, and may continue in the state after the next permutation
+        let lanes_before_permutation = state_as_lanes(&state_before_permutation);
+        let lanes_after_permutation = state_as_lanes(&state.keccak_internal_state);
+        let lane_offset_flags =
+            lane_offsets.map(|el| UInt8::equals(cs, &state.squeeze_lane_offset, &el));
+        let mut squeezed_word_for_rate = [[zero_u8; keccak256::KECCAK256_DIGEST_SIZE]; 2];
+        for (dst, rate_in_lanes) in squeezed_word_for_rate
+            .iter_mut()
+            .zip([KECCAK256_RATE_IN_U64_WORDS, SHAKE128_RATE_IN_U64_WORDS])
+        {
+            let mut lanes_stream = lanes_before_permutation[..rate_in_lanes].to_vec();
+            lanes_stream.extend_from_slice(&lanes_after_permutation[..U64_WORDS_PER_OUTPUT_WORD]);
+            for (k, dst) in dst
+                .array_chunks_mut::<{ keccak256::BYTES_PER_WORD }>()
+                .enumerate()
+            {
+                for (flag, src) in lane_offset_flags[..rate_in_lanes]
+                    .iter()
+                    .zip(lanes_stream[k..].iter())
+                {
+                    *dst = UInt8::parallel_select(cs, *flag, src, &*dst);
+                }
+            }
+        }
+        let squeezed_word = UInt8::parallel_select(
+            cs,
+            uses_shake128_rate,
+            &squeezed_word_for_rate[1],
+            &squeezed_word_for_rate[0],
+        );
+        let squeezed = UInt8::parallel_select(cs, state.squeeze_round, &squeezed_word, &squeezed);
+
+        // we only need the next permutation if the output word doesn't fit into the current rate
+        let keccak256_last_offset_without_permutation = UInt8::allocated_constant(
+            cs,
+            (KECCAK256_RATE_IN_U64_WORDS - U64_WORDS_PER_OUTPUT_WORD) as u8,
+        );
+        let shake128_last_offset_without_permutation = UInt8::allocated_constant(
+            cs,
+            (SHAKE128_RATE_IN_U64_WORDS - U64_WORDS_PER_OUTPUT_WORD) as u8,
+        );
+        let last_offset_without_permutation = UInt8::conditionally_select(
+            cs,
+            uses_shake128_rate,
+            &shake128_last_offset_without_permutation,
+            &keccak256_last_offset_without_permutation,
+        );
+        let (_, needs_next_permutation) =
+            last_offset_without_permutation.overflowing_sub(cs, &state.squeeze_lane_offset);
+        let keep_state = needs_next_permutation.negated(cs);
+        let keep_state = Boolean::multi_and(cs, &[state.squeeze_round, keep_state]);
+        let keep_state = Boolean::multi_or(cs, &[keep_state, wait_for_more_input]);
+        for (dst, src) in state
+            .keccak_internal_state
+            .iter_mut()
+            .flatten()
+            .zip(state_before_permutation.iter().flatten())
+        {
+            *dst = UInt8::parallel_select(cs, keep_state, src, &*dst);
+        }
+
+        let keccak256_rate_in_lanes = lane_offsets[KECCAK256_RATE_IN_U64_WORDS];
+        let shake128_rate_in_lanes =
+            UInt8::allocated_constant(cs, SHAKE128_RATE_IN_U64_WORDS as u8);
+        let rate_in_lanes = UInt8::conditionally_select(
+            cs,
+            uses_shake128_rate,
+            &shake128_rate_in_lanes,
+            &keccak256_rate_in_lanes,
+        );
+        let advanced_lane_offset = state
+            .squeeze_lane_offset
+            .add_no_overflow(cs, lanes_per_output_word);
+        let (wrapped_lane_offset, _) = advanced_lane_offset.overflowing_sub(cs, &rate_in_lanes);
+        let next_lane_offset = UInt8::conditionally_select(
+            cs,
+            needs_next_permutation,
+            &wrapped_lane_offset,
+            &advanced_lane_offset,
+        );
+        // after absorbtion the first output word is taken from the beginning of the state
+        state.squeeze_lane_offset = UInt8::conditionally_select(
+            cs,
+            state.squeeze_round,
+            &next_lane_offset,
+            &lanes_per_output_word,
+        );
+
         let absorbed_and_padded = apply_padding;
         // dbg!(absorbed_and_padded.witness_hook(cs)());
         // dbg!(state.padding_round.witness_hook(cs)());
-        let finished_processing_current_request =
-            Boolean::multi_or(cs, &[absorbed_and_padded, state.padding_round]);
-        let write_result = finished_processing_current_request;
+        let finished_absorbing = Boolean::multi_or(cs, &[absorbed_and_padded, state.padding_round]);
+        let write_result = Boolean::multi_or(cs, &[finished_absorbing, state.squeeze_round]);
 
         if <CS::Config as CSConfig>::DebugConfig::PERFORM_RUNTIME_ASSERTS == true {
-            use zkevm_opcode_defs::sha3::Digest;
             if write_result.witness_hook(cs)().unwrap() == true {
                 if keccak_self_verifier.as_mut().unwrap().0 == true {
                     keccak_self_verifier.as_mut().unwrap().0 = false;
-                    let mut output = [0u8; 32];
-                    let internal_state = std::mem::replace(
-                        &mut keccak_self_verifier.as_mut().unwrap().1,
-                        zkevm_opcode_defs::sha3::Keccak256::new(),
+                    let input = std::mem::take(&mut keccak_self_verifier.as_mut().unwrap().1);
+                    let params = state.precompile_call_params.witness_hook(cs)().unwrap();
+                    let output = reference_first_output_word(
+                        &input,
+                        params.domain_separator,
+                        params.uses_shake128_rate,
                     );
-                    output.copy_from_slice(internal_state.finalize().as_slice());
                     let circuit_result = squeezed.witness_hook(cs)().unwrap();
                     assert_eq!(output, circuit_result);
                 }
@@ -612,7 +889,7 @@ where
 
         let result = UInt256::from_be_bytes(cs, squeezed);
         if crate::config::CIRCUIT_VERSOBE {
-            if finished_processing_current_request.witness_hook(cs)().unwrap() {
+            if write_result.witness_hook(cs)().unwrap() {
                 dbg!(result.witness_hook(cs)());
             }
         }
@@ -629,13 +906,47 @@ where
         // perform write
         memory_queue.push(cs, write_query, write_result);
 
+        let may_be_new_output_word_offset = unsafe {
+            state
+                .precompile_call_params
+                .output_word_offset
+                .increment_unchecked(cs)
+        };
+        state.precompile_call_params.output_word_offset = UInt32::conditionally_select(
+            cs,
+            write_result,
+            &may_be_new_output_word_offset,
+            &state.precompile_call_params.output_word_offset,
+        );
+        // there is always at least one word left if we write
+        let may_be_new_output_words_left = unsafe {
+            state
+                .precompile_call_params
+                .output_words_left
+                .decrement_unchecked(cs)
+        };
+        state.precompile_call_params.output_words_left = UInt32::conditionally_select(
+            cs,
+            write_result,
+            &may_be_new_output_words_left,
+            &state.precompile_call_params.output_words_left,
+        );
+        let no_output_words_left = state.precompile_call_params.output_words_left.is_zero(cs);
+        let have_output_words_left = no_output_words_left.negated(cs);
+        let finished_processing_current_request =
+            Boolean::multi_and(cs, &[write_result, no_output_words_left]);
+
         // ---------------------------------
 
         // update FSM state
         let input_is_empty = precompile_calls_queue.is_empty(cs);
         let input_is_not_empty = input_is_empty.negated(cs);
-        let nothing_left = Boolean::multi_and(cs, &[write_result, input_is_empty]);
-        let process_next = Boolean::multi_and(cs, &[write_result, input_is_not_empty]);
+        let nothing_left =
+            Boolean::multi_and(cs, &[finished_processing_current_request, input_is_empty]);
+        let process_next = Boolean::multi_and(
+            cs,
+            &[finished_processing_current_request, input_is_not_empty],
+        );
 
         state.read_precompile_call = process_next;
         state.completed = Boolean::multi_or(cs, &[nothing_left, state.completed]);
@@ -652,12 +963,16 @@ where
         );
         state.padding_round = needs_full_padding;
 
+        // or continue to squeeze
+        state.squeeze_round = Boolean::multi_and(cs, &[write_result, have_output_words_left]);
+
         // otherwise we just continue
         let t = Boolean::multi_or(
             cs,
             &[
                 state.read_precompile_call,
                 state.padding_round,
+                state.squeeze_round,
                 state.completed,
             ],
         );
@@ -793,18 +1108,22 @@ where
     input_commitment
 }
 
-pub(crate) fn keccak256_absorb_and_run_permutation<F: SmallField, CS: ConstraintSystem<F>>(
+// block can be of any rate, and it's absorbed as a sequence of lanes
+pub(crate) fn keccak256_absorb_and_run_permutation<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    const N: usize,
+>(
     cs: &mut CS,
     state: &mut [[[UInt8<F>; keccak256::BYTES_PER_WORD]; keccak256::LANE_WIDTH];
              keccak256::LANE_WIDTH],
-    block: &[UInt8<F>; keccak256::KECCAK_RATE_BYTES],
+    block: &[UInt8<F>; N],
 ) -> [UInt8<F>; keccak256::KECCAK256_DIGEST_SIZE] {
+    assert!(N % keccak256::BYTES_PER_WORD == 0);
     let mut state_as_variables = state.map(|el| el.map(|el| el.map(|el| el.get_variable())));
     for i in 0..keccak256::LANE_WIDTH {
         for j in 0..keccak256::LANE_WIDTH {
-            if i + keccak256::LANE_WIDTH * j
-                < (keccak256::KECCAK_RATE_BYTES / keccak256::BYTES_PER_WORD)
-            {
+            if i + keccak256::LANE_WIDTH * j < (N / keccak256::BYTES_PER_WORD) {
                 let tmp = block
                     .array_chunks::<{ keccak256::BYTES_PER_WORD }>()
                     .skip(i + keccak256::LANE_WIDTH * j)
@@ -837,6 +1156,46 @@ pub(crate) fn keccak256_absorb_and_run_permutation<F: SmallField, CS: Constraint
     unsafe { result.map(|el| el.assume_init()) }
 }
 
+pub(crate) fn state_as_lanes<F: SmallField>(
+    state: &[[[UInt8<F>; keccak256::BYTES_PER_WORD]; keccak256::LANE_WIDTH]; keccak256::LANE_WIDTH],
+) -> [[UInt8<F>; keccak256::BYTES_PER_WORD]; keccak256::LANE_WIDTH * keccak256::LANE_WIDTH] {
+    std::array::from_fn(|idx| state[idx % keccak256::LANE_WIDTH][idx / keccak256::LANE_WIDTH])
+}
+
+fn reference_first_output_word(
+    input: &[u8],
+    domain_separator: u8,
+    uses_shake128_rate: bool,
+) -> [u8; keccak256::KECCAK256_DIGEST_SIZE] {
+    use zkevm_opcode_defs::sha3::digest::{ExtendableOutput, Update, XofReader};
+
+    fn xof<H: Default + Update + ExtendableOutput>(input: &[u8], output: &mut [u8]) {
+        let mut hasher = H::default();
+        hasher.update(input);
+        hasher.finalize_xof().read(output);
+    }
+
+    let mut output = [0u8; keccak256::KECCAK256_DIGEST_SIZE];
+    match (domain_separator, uses_shake128_rate) {
+        (SHA3_DOMAIN_SEPARATOR, _) => {
+            use zkevm_opcode_defs::sha3::Digest;
+            output.copy_from_slice(zkevm_opcode_defs::sha3::Sha3_256::digest(input).as_slice());
+        }
+        (SHAKE_DOMAIN_SEPARATOR, true) => {
+            xof::<zkevm_opcode_defs::sha3::Shake128>(input, &mut output);
+        }
+        (SHAKE_DOMAIN_SEPARATOR, false) => {
+            xof::<zkevm_opcode_defs::sha3::Shake256>(input, &mut output);
+        }
+        _ => {
+            use zkevm_opcode_defs::sha3::Digest;
+            output.copy_from_slice(zkevm_opcode_defs::sha3::Keccak256::digest(input).as_slice());
+        }
+    }
+
+    output
+}
+
 #[cfg(test)]
 mod test {
     use boojum::algebraic_props::poseidon2_parameters::*;
@@ -994,18 +1353,89 @@ mod test {
         result
     }
 
+    // SHAKE128 may read a word only partially when the buffer is almost full, and then reads it again
+    // later, so the witness follows the same schedule as the circuit
+    fn memory_reads_witness(mode: u32, input: Vec<u8>, unalignement: usize) -> Vec<U256> {
+        let length = input.len();
+        let words = bytes_to_u256_words(input, unalignement);
+        let rate = if mode == SHAKE128_MODE {
+            SHAKE128_RATE_BYTES
+        } else {
+            KECCAK_RATE_BYTES
+        };
+
+        let mut result = vec![];
+        let mut offset = unalignement;
+        let mut bytes_left = length;
+        let mut filled = 0;
+        while bytes_left > 0 {
+            for _ in 0..MEMORY_QUERIES_PER_CYCLE {
+                let meaningful_bytes = std::cmp::min(32 - offset % 32, bytes_left);
+                let bytes_to_fill = if filled + meaningful_bytes <= KECCAK_PRECOMPILE_BUFFER_SIZE {
+                    meaningful_bytes
+                } else if mode == SHAKE128_MODE {
+                    KECCAK_PRECOMPILE_BUFFER_SIZE - filled
+                } else {
+                    0
+                };
+                if bytes_to_fill == 0 {
+                    continue;
+                }
+                result.push(words[offset / 32]);
+                offset += bytes_to_fill;
+                bytes_left -= bytes_to_fill;
+                filled += bytes_to_fill;
+            }
+            if filled >= rate || bytes_left == 0 {
+                filled -= std::cmp::min(filled, rate);
+            }
+        }
+
+        result
+    }
+
+    fn reference_output(mode: u32, input: &[u8], output_words: usize) -> Vec<u8> {
+        use boojum::sha3::digest::{ExtendableOutput, Update, XofReader};
+        use boojum::sha3::Digest;
+
+        let mut output = vec![0u8; 32 * output_words];
+        match mode {
+            SHA3_256_MODE => {
+                output.copy_from_slice(boojum::sha3::Sha3_256::digest(input).as_slice())
+            }
+            SHAKE128_MODE => {
+                let mut hasher = boojum::sha3::Shake128::default();
+                Update::update(&mut hasher, input);
+                hasher.finalize_xof().read(&mut output);
+            }
+            SHAKE256_MODE => {
+                let mut hasher = boojum::sha3::Shake256::default();
+                Update::update(&mut hasher, input);
+                hasher.finalize_xof().read(&mut output);
+            }
+            _ => output.copy_from_slice(boojum::sha3::Keccak256::digest(input).as_slice()),
+        }
+
+        output
+    }
+
     fn test_for_length_and_unalignment(length: usize, unalignement: usize) {
+        test_for_mode_length_and_unalignment(KECCAK256_MODE, length, unalignement, 1);
+    }
+
+    fn test_for_mode_length_and_unalignment(
+        mode: u32,
+        length: usize,
+        unalignement: usize,
+        output_words: usize,
+    ) {
         use rand_new::{Rng, SeedableRng};
         let mut rng = rand_new::rngs::StdRng::from_seed([1u8; 32]);
         let input: Vec<u8> = (0..length).map(|_| rng.gen()).collect();
         dbg!(hex::encode(&input));
-        let input_witness = bytes_to_u256_words(input.clone(), unalignement);
+        let input_witness = memory_reads_witness(mode, input.clone(), unalignement);
 
-        use boojum::sha3::Digest;
-        let reference: [u8; 32] = boojum::sha3::Keccak256::digest(&input)
-            .as_slice()
-            .try_into()
-            .unwrap();
+        let reference = reference_output(mode, &input, output_words);
 
         let mut owned_cs = create_test_cs();
         let cs = &mut owned_cs;
@@ -1015,10 +1445,10 @@ mod test {
             input_memory_offset: unalignement as u32,
             input_memory_length: length as u32,
             output_memory_offset: 0,
-            output_memory_length: 1,
+            output_memory_length: output_words as u32,
             memory_page_to_read: 123,
             memory_page_to_write: 456,
-            precompile_interpreted_data: 0,
+            precompile_interpreted_data: mode as u64,
         };
         let encoded_precompile_abi = precompile_abi.to_u256();
         let boolean_true = Boolean::allocated_constant(cs, true);
@@ -1059,24 +1489,22 @@ mod test {
             memory_read_witness,
             state,
             &round_function,
-            2,
+            2 * (length / KECCAK_RATE_BYTES + 1) + output_words,
         );
 
         dbg!(new_state.witness_hook(cs)().unwrap());
+        assert!(new_state.completed.witness_hook(cs)().unwrap());
 
         drop(cs);
 
-        let output = memory_queue
-            .witness
-            .elements
-            .read()
-            .unwrap()
-            .back()
-            .unwrap()
-            .clone();
-        let mut buffer = [0u8; 32];
-        assert!(output.0.rw_flag);
-        output.0.value.to_big_endian(&mut buffer);
+        let mut buffer = vec![];
+        for (output, _) in memory_queue.witness.elements.read().unwrap().iter() {
+            if output.rw_flag {
+                let mut word = [0u8; 32];
+                output.value.to_big_endian(&mut word);
+                buffer.extend_from_slice(&word);
+            }
+        }
 
         dbg!(hex::encode(&reference));
         dbg!(hex::encode(&buffer));
@@ -1139,4 +1567,49 @@ mod test {
     fn keccak_256_unaligned_two_rounds_but_one_read_round() {
         test_for_length_and_unalignment(166, 22);
     }
+
+    #[test]
+    fn sha3_256_empty_input() {
+        test_for_mode_length_and_unalignment(SHA3_256_MODE, 0, 0, 1);
+    }
+
+    #[test]
+    fn sha3_256_unaligned_one_round_to_the_end() {
+        test_for_mode_length_and_unalignment(SHA3_256_MODE, 135, 31, 1);
+    }
+
+    #[test]
+    fn sha3_256_unaligned_one_round_and_padding_round() {
+        test_for_mode_length_and_unalignment(SHA3_256_MODE, 136, 31, 1);
+    }
+
+    #[test]
+    fn shake128_unaligned_one_round_to_the_end() {
+        test_for_mode_length_and_unalignment(SHAKE128_MODE, 167, 1, 1);
+    }
+
+    #[test]
+    fn shake128_unaligned_one_round_and_padding_round() {
+        test_for_mode_length_and_unalignment(SHAKE128_MODE, 168, 1, 2);
+    }
+
+    #[test]
+    fn shake128_unaligned_two_rounds_and_long_output() {
+        test_for_mode_length_and_unalignment(SHAKE128_MODE, 300, 17, 12);
+    }
+
+    #[test]
+    fn shake128_unaligned_first_block_takes_two_cycles() {
+        test_for_mode_length_and_unalignment(SHAKE128_MODE, 500, 31, 3);
+    }
+
+    #[test]
+    fn shake256_aligned_one_round_and_long_output() {
+        test_for_mode_length_and_unalignment(SHAKE256_MODE, 50, 0, 9);
+    }
+
+    #[test]
+    fn shake256_unaligned_two_rounds_and_long_output() {
+        test_for_mode_length_and_unalignment(SHAKE256_MODE, 200, 22, 5);
+    }
 }
@@ -0,0 +1,973 @@
+use super::*;
+
+use crate::base_structures::precompile_input_outputs::PrecompileFunctionOutputData;
+use crate::demux_log_queue::StorageLogQueue;
+use crate::eip_4844::input::ELEMENTS_PER_4844_BLOCK;
+use crate::ethereum_types::U256;
+use crate::fsm_input_output::circuit_inputs::INPUT_OUTPUT_COMMITMENT_LENGTH;
+
+use arrayvec::ArrayVec;
+use boojum::algebraic_props::round_function::AlgebraicRoundFunction;
+use boojum::cs::traits::cs::ConstraintSystem;
+use boojum::field::SmallField;
+use boojum::gadgets::boolean::Boolean;
+use boojum::gadgets::curves::sw_projective::SWProjectivePoint;
+use boojum::gadgets::sha256::round_function::round_function_over_uint32;
+
+use boojum::gadgets::num::Num;
+use boojum::gadgets::queue::CircuitQueueWitness;
+use boojum::gadgets::queue::QueueState;
+use boojum::gadgets::traits::allocatable::{CSAllocatableExt, CSPlaceholder};
+use boojum::gadgets::traits::round_function::CircuitRoundFunction;
+use boojum::gadgets::traits::selectable::Selectable;
+
+use boojum::gadgets::u16::UInt16;
+use boojum::gadgets::u160::UInt160;
+use boojum::gadgets::u256::UInt256;
+use boojum::gadgets::u32::UInt32;
+use boojum::gadgets::u8::UInt8;
+use boojum::pairing::bls12_381::Fq2 as NativeFq2;
+use boojum::pairing::GenericCurveAffine;
+
+use std::collections::VecDeque;
+use std::sync::{Arc, RwLock};
+use zkevm_opcode_defs::system_params::PRECOMPILE_AUX_BYTE;
+
+use super::pairing::*;
+use super::towers::*;
+
+#[derive(Derivative, CSSelectable)]
+#[derivative(Clone, Debug)]
+pub struct KzgPointEvaluationPrecompileCallParams<F: SmallField> {
+    pub input_page: UInt32<F>,
+    pub input_offset: UInt32<F>,
+    pub output_page: UInt32<F>,
+    pub output_offset: UInt32<F>,
+}
+
+impl<F: SmallField> KzgPointEvaluationPrecompileCallParams<F> {
+    pub fn from_encoding<CS: ConstraintSystem<F>>(_cs: &mut CS, encoding: UInt256<F>) -> Self {
+        let input_offset = encoding.inner[0];
+        let output_offset = encoding.inner[2];
+        let input_page = encoding.inner[4];
+        let output_page = encoding.inner[5];
+
+        let new = Self {
+            input_page,
+            input_offset,
+            output_page,
+            output_offset,
+        };
+
+        new
+    }
+}
+
+const EXCEPTION_FLAGS_ARR_LEN: usize = 6;
+
+// compressed point of G1 takes 48 bytes
+pub(crate) const G1_COMPRESSED_WORDS: usize = 12;
+
+// (p + 1) / 4 for the base field modulus p, little-endian
+pub(crate) const SQRT_EXPONENT: [u64; 6] = [
+    0xee7fbfffffffeaab,
+    0x07aaffffac54ffff,
+    0xd9cc34a83dac3d89,
+    0xd91dd2e13ce144af,
+    0x92c6e9ed90d2eb35,
+    0x0680447a8e5ff9a6,
+];
+
+// (p - 1) / 2 for the base field modulus p, little-endian. Encoding of the point uses the sign flag
+// to tell if y is lexicographically largest, that is greater than this value
+pub(crate) const HALF_MODULUS: [u64; 6] = [
+    0xdcff7fffffffd555,
+    0x0f55ffff58a9ffff,
+    0xb39869507b587b12,
+    0xb23ba5c279c2895f,
+    0x258dd3db21a5d66b,
+    0x0d0088f51cbff34d,
+];
+
+// [tau]G2 from the trusted setup of the Ethereum KZG ceremony, as (c0, c1) pairs of x and y coordinates
+const KZG_SETUP_G2_MONOMIAL_1: [(&str, &str); 2] = [
+    (
+        "3749701713850085193403383609513386037494151572263731328608276629425322978272408394373143740944003571525027436289778",
+        "3347537128081568434923729147580015899756771550835613107520576615563260658656019591232316627827007503930666726825842",
+    ),
+    (
+        "194392958648403190675529552496435226424111592982833162118538452666741235889530674000225873281133418439621742897817",
+        "3447898402727835650716129438012169492682148398295533958400255525306573911008434577489634554212211450783070687991119",
+    ),
+];
+
+pub(crate) fn kzg_setup_tau_g2() -> Bls12_381G2Affine {
+    let [x, y] = KZG_SETUP_G2_MONOMIAL_1.map(|(c0, c1)| NativeFq2 {
+        c0: bls12_381_fq_from_str(c0),
+        c1: bls12_381_fq_from_str(c1),
+    });
+
+    Bls12_381G2Affine::from_xy_checked(x, y).expect("must be a valid point")
+}
+
+pub(crate) fn u64_words_into_u32_words<const N: usize, const M: usize>(words: [u64; N]) -> [u32; M] {
+    assert_eq!(N * 2, M);
+    std::array::from_fn(|i| (words[i / 2] >> (32 * (i % 2))) as u32)
+}
+
+/// Returns the borrow of a - b, where both are little-endian, that is a < b
+pub(crate) fn u32_words_less_than<F: SmallField, CS: ConstraintSystem<F>, const N: usize>(
+    cs: &mut CS,
+    a: &[UInt32<F>; N],
+    b: &[UInt32<F>; N],
+) -> Boolean<F> {
+    let mut borrow = Boolean::allocated_constant(cs, false);
+    for (a, b) in a.iter().zip(b.iter()) {
+        let (_diff, new_borrow) = a.overflowing_sub_with_borrow_in(cs, *b, borrow);
+        borrow = new_borrow;
+    }
+
+    borrow
+}
+
+/// Caller must ensure that the value given by little-endian words is less than the modulus
+pub(crate) fn convert_u32_words_to_field_element<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    words: &[UInt32<F>; G1_COMPRESSED_WORDS],
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> Bls12_381BaseNNField<F> {
+    let zero_var = cs.allocate_constant(F::ZERO);
+    let mut limbs = [zero_var; BASE_FIELD_REPR_LIMBS];
+    for (dst, src) in limbs.array_chunks_mut::<2>().zip(words.iter()) {
+        let [byte_0, byte_1, byte_2, byte_3] = src.to_le_bytes(cs);
+        dst[0] = UInt16::from_le_bytes(cs, [byte_0, byte_1]).get_variable();
+        dst[1] = UInt16::from_le_bytes(cs, [byte_2, byte_3]).get_variable();
+    }
+
+    NonNativeFieldOverU16 {
+        limbs: limbs,
+        non_zero_limbs: BASE_FIELD_CANONICAL_REPR_LIMBS,
+        tracker: OverflowTracker { max_moduluses: 1 },
+        form: RepresentationForm::Normalized,
+        params: params.clone(),
+        _marker: std::marker::PhantomData,
+    }
+}
+
+/// Returns little-endian words of the canonical representation of the element
+pub(crate) fn convert_field_element_to_u32_words<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    mut element: Bls12_381BaseNNField<F>,
+) -> [UInt32<F>; G1_COMPRESSED_WORDS] {
+    element.normalize(cs);
+    element.enforce_reduced(cs);
+
+    let shift = F::from_u64_unchecked(1u64 << 16);
+    let mut result = [UInt32::zero(cs); G1_COMPRESSED_WORDS];
+    for (dst, src) in result
+        .iter_mut()
+        .zip(element.limbs[..BASE_FIELD_CANONICAL_REPR_LIMBS].array_chunks::<2>())
+    {
+        let word = Num::linear_combination(cs, &[(src[0], F::ONE), (src[1], shift)]);
+        // limbs are range checked to 16 bits
+        *dst = unsafe { UInt32::from_variable_unchecked(word.get_variable()) };
+    }
+
+    result
+}
+
+pub(crate) fn pow_by_constant<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    base: &mut Bls12_381BaseNNField<F>,
+    exponent: &[u64],
+) -> Bls12_381BaseNNField<F> {
+    let num_bits = exponent.len() * 64 - exponent.last().unwrap().leading_zeros() as usize;
+    let mut result = base.clone();
+    for i in (0..(num_bits - 1)).rev() {
+        result = result.square(cs);
+        if (exponent[i / 64] >> (i % 64)) & 1 == 1 {
+            result = result.mul(cs, base);
+        }
+    }
+
+    result
+}
+
+pub(crate) fn is_in_g1_subgroup<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    point: &(Bls12_381BaseNNField<F>, Bls12_381BaseNNField<F>),
+    group_order: U256,
+) -> Boolean<F> {
+    // point is on curve, so complete formulas work for any intermediate value
+    let mut acc =
+        SWProjectivePoint::<F, Bls12_381G1Affine, Bls12_381BaseNNField<F>>::from_xy_unchecked(
+            cs,
+            point.0.clone(),
+            point.1.clone(),
+        );
+    let num_bits = 256 - group_order.leading_zeros() as usize;
+    for i in (0..(num_bits - 1)).rev() {
+        acc = acc.double(cs);
+        if group_order.bit(i) {
+            acc = acc.add_mixed(cs, &mut point.clone());
+        }
+    }
+
+    let (_, is_infinity) = acc.convert_to_affine_or_default(cs, Bls12_381G1Affine::one());
+
+    is_infinity
+}
+
+/// Decodes the point of G1 in compressed form from big-endian words, and checks that it's valid,
+/// that is either belongs to G1, or is a canonical encoding of the point at infinity.
+/// Returns the point masked to the generator if it's invalid or at infinity,
+/// so it can be safely used in arithmetic formulas, and flags for "infinity" and "invalid" cases
+pub(crate) fn bls12_381_decompress_and_mask_g1_point<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    words: &[UInt32<F>; G1_COMPRESSED_WORDS],
+    group_order: U256,
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> (
+    (Bls12_381BaseNNField<F>, Bls12_381BaseNNField<F>),
+    Boolean<F>,
+    Boolean<F>,
+) {
+    let curve_b = Bls12_381G1Affine::b_coeff();
+    let mut curve_b_nn = Bls12_381BaseNNField::allocated_constant(cs, curve_b, params);
+
+    let (gen_x, gen_y) = Bls12_381G1Affine::one().into_xy_unchecked();
+    let gen_x_nn = Bls12_381BaseNNField::allocated_constant(cs, gen_x, params);
+    let gen_y_nn = Bls12_381BaseNNField::allocated_constant(cs, gen_y, params);
+
+    // three highest bits of the encoding are flags
+    let [top_byte, byte_1, byte_2, byte_3] = words[0].to_be_bytes(cs);
+    let top_byte_bits = Num::from_variable(top_byte.get_variable()).spread_into_bits::<_, 8>(cs);
+    let is_compressed = top_byte_bits[7];
+    let infinity_flag = top_byte_bits[6];
+    let sign_flag = top_byte_bits[5];
+
+    let top_byte_lc: Vec<_> = top_byte_bits[..5]
+        .iter()
+        .enumerate()
+        .map(|(i, bit)| (bit.get_variable(), F::SHIFTS[i]))
+        .collect();
+    let top_byte = Num::linear_combination(cs, &top_byte_lc);
+    let top_byte = unsafe { UInt8::from_variable_unchecked(top_byte.get_variable()) };
+
+    let mut x_words = *words;
+    x_words[0] = UInt32::from_be_bytes(cs, [top_byte, byte_1, byte_2, byte_3]);
+    x_words.reverse();
+
+    let modulus_words: [u32; G1_COMPRESSED_WORDS] =
+        u64_words_into_u32_words(std::array::from_fn::<_, 6, _>(|i| {
+            params.modulus_u1024.as_ref().as_words()[i]
+        }));
+    let modulus_words = modulus_words.map(|el| UInt32::allocated_constant(cs, el));
+    let x_is_in_range = u32_words_less_than(cs, &x_words, &modulus_words);
+
+    let x_words_are_zero = x_words.map(|el| el.is_zero(cs));
+    let x_is_zero = Boolean::multi_and(cs, &x_words_are_zero);
+
+    let x_words = x_words.map(|el| el.mask(cs, x_is_in_range));
+    let mut x = convert_u32_words_to_field_element(cs, &x_words, params);
+
+    // curve equation is y^2 = x^3 + b. Since p = 3 mod 4, if t = x^3 + b is a quadratic residue
+    // then t^((p + 1) / 4) is its square root
+    let mut t = x.square(cs);
+    let mut t = t.mul(cs, &mut x);
+    let mut t = t.add(cs, &mut curve_b_nn);
+    t.normalize(cs);
+
+    let mut y = pow_by_constant(cs, &mut t, &SQRT_EXPONENT);
+    let mut y_squared = y.square(cs);
+    y_squared.normalize(cs);
+    let t_is_residue = NonNativeFieldOverU16::equals(cs, &mut y_squared, &mut t);
+
+    y.normalize(cs);
+    let mut y_negated = y.negated(cs);
+    y_negated.normalize(cs);
+
+    let y_words = convert_field_element_to_u32_words(cs, y.clone());
+    let half_modulus_words: [u32; G1_COMPRESSED_WORDS] = u64_words_into_u32_words(HALF_MODULUS);
+    let half_modulus_words = half_modulus_words.map(|el| UInt32::allocated_constant(cs, el));
+    let y_is_largest = u32_words_less_than(cs, &half_modulus_words, &y_words);
+
+    // if sign flag doesn't match, then we take another root
+    let should_swap = y_is_largest.xor(cs, sign_flag);
+    let y = Selectable::conditionally_select(cs, should_swap, &y_negated, &y);
+
+    // subgroup check only makes sense for points on curve, so we mask the point first
+    let is_on_curve = Boolean::multi_and(cs, &[x_is_in_range, t_is_residue]);
+    let x = Selectable::conditionally_select(cs, is_on_curve, &x, &gen_x_nn);
+    let y = Selectable::conditionally_select(cs, is_on_curve, &y, &gen_y_nn);
+    let is_in_subgroup = is_in_g1_subgroup(cs, &(x.clone(), y.clone()), group_order);
+
+    // all the bits except the flags must be zero for the point at infinity
+    let sign_is_not_set = sign_flag.negated(cs);
+    let is_infinity = Boolean::multi_and(
+        cs,
+        &[is_compressed, infinity_flag, sign_is_not_set, x_is_zero],
+    );
+
+    let is_not_infinity = infinity_flag.negated(cs);
+    let is_valid_point = Boolean::multi_and(
+        cs,
+        &[is_compressed, is_not_infinity, is_on_curve, is_in_subgroup],
+    );
+    let is_valid = Boolean::multi_or(cs, &[is_valid_point, is_infinity]);
+    let is_invalid = is_valid.negated(cs);
+
+    // we can mask point to ensure that our arithmetic formulas work
+    let should_mask = Boolean::multi_or(cs, &[is_invalid, is_infinity]);
+    let x = Selectable::conditionally_select(cs, should_mask, &gen_x_nn, &x);
+    let y = Selectable::conditionally_select(cs, should_mask, &gen_y_nn, &y);
+
+    ((x, y), is_infinity, is_invalid)
+}
+
+/// Checks that the versioned hash is sha256 of the commitment with the highest byte replaced by the version
+fn kzg_versioned_hash_matches_commitment<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    versioned_hash: &UInt256<F>,
+    commitment_words: &[UInt32<F>; G1_COMPRESSED_WORDS],
+) -> Boolean<F> {
+    // 48 bytes of the commitment fit into the single block together with the padding
+    let zero_u32 = UInt32::zero(cs);
+    let mut sha256_input = [zero_u32; 16];
+    sha256_input[..G1_COMPRESSED_WORDS].copy_from_slice(commitment_words);
+    sha256_input[G1_COMPRESSED_WORDS] = UInt32::allocated_constant(cs, 1 << 31);
+    sha256_input[15] = UInt32::allocated_constant(cs, (G1_COMPRESSED_WORDS * 32) as u32);
+
+    let mut sha256_state = boojum::gadgets::sha256::ivs_as_uint32(cs);
+    let _ = round_function_over_uint32(cs, &mut sha256_state, &sha256_input);
+
+    let [_, byte_1, byte_2, byte_3] = sha256_state[0].to_be_bytes(cs);
+    let version = UInt8::allocated_constant(cs, VERSIONED_HASH_VERSION_KZG);
+    sha256_state[0] = UInt32::from_be_bytes(cs, [version, byte_1, byte_2, byte_3]);
+
+    let mut flags = [Boolean::allocated_constant(cs, false); 8];
+    for ((dst, a), b) in flags
+        .iter_mut()
+        .zip(versioned_hash.inner.iter())
+        .zip(sha256_state.iter().rev())
+    {
+        *dst = UInt32::equals(cs, a, b);
+    }
+
+    Boolean::multi_and(cs, &flags)
+}
+
+pub(crate) fn u256_into_bits<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    value: &UInt256<F>,
+) -> Vec<Boolean<F>> {
+    let mut bits = Vec::with_capacity(256);
+    for word in value.inner.iter() {
+        bits.extend(Num::from_variable(word.get_variable()).spread_into_bits::<_, 32>(cs));
+    }
+
+    bits
+}
+
+// commitment and proof are 48 bytes each, so they share one word
+fn split_into_commitment_and_proof_words<F: SmallField>(
+    words: &[UInt256<F>; 3],
+) -> (
+    [UInt32<F>; G1_COMPRESSED_WORDS],
+    [UInt32<F>; G1_COMPRESSED_WORDS],
+) {
+    let mut be_words = words.iter().flat_map(|el| el.inner.iter().rev().copied());
+    let commitment_words = std::array::from_fn(|_| be_words.next().unwrap());
+    let proof_words = std::array::from_fn(|_| be_words.next().unwrap());
+
+    (commitment_words, proof_words)
+}
+
+fn kzg_point_evaluation_function_inner<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    versioned_hash: &UInt256<F>,
+    z: &UInt256<F>,
+    y: &UInt256<F>,
+    commitment_words: &[UInt32<F>; G1_COMPRESSED_WORDS],
+    proof_words: &[UInt32<F>; G1_COMPRESSED_WORDS],
+    tau_g2: Bls12_381G2Affine,
+    base_field_params: &Arc<Bls12_381BaseNNFieldParams>,
+    scalar_field_params: &Arc<Bls12_381ScalarNNFieldParams>,
+) -> (Boolean<F>, (UInt256<F>, UInt256<F>)) {
+    let mut exception_flags = ArrayVec::<_, EXCEPTION_FLAGS_ARR_LEN>::new();
+
+    // we check that
+    // - versioned hash matches the commitment
+    // - z and y are elements of the scalar field
+    // - commitment and proof are valid points of G1
+    // - e(C - [y]G1 + [z]pi, G2) * e(-pi, [tau]G2) == 1, that is equivalent to
+    //   e(C - [y]G1, G2) == e(pi, [tau - z]G2)
+
+    let hash_matches = kzg_versioned_hash_matches_commitment(cs, versioned_hash, commitment_words);
+    let hash_mismatch = hash_matches.negated(cs);
+    exception_flags.push(hash_mismatch);
+
+    let group_order = U256([
+        scalar_field_params.modulus_u1024.as_ref().as_words()[0],
+        scalar_field_params.modulus_u1024.as_ref().as_words()[1],
+        scalar_field_params.modulus_u1024.as_ref().as_words()[2],
+        scalar_field_params.modulus_u1024.as_ref().as_words()[3],
+    ]);
+    let group_order_u256 = UInt256::allocated_constant(cs, group_order);
+
+    let mut scalars = [*z, *y];
+    for scalar in scalars.iter_mut() {
+        let (_res, is_in_range) = scalar.overflowing_sub(cs, &group_order_u256);
+        *scalar = scalar.mask(cs, is_in_range);
+        let is_not_in_range = is_in_range.negated(cs);
+        exception_flags.push(is_not_in_range);
+    }
+    let [z, y] = scalars;
+
+    let (commitment, commitment_is_infinity, commitment_is_invalid) =
+        bls12_381_decompress_and_mask_g1_point(
+            cs,
+            commitment_words,
+            group_order,
+            base_field_params,
+        );
+    exception_flags.push(commitment_is_invalid);
+    let (proof, proof_is_infinity, proof_is_invalid) =
+        bls12_381_decompress_and_mask_g1_point(cs, proof_words, group_order, base_field_params);
+    exception_flags.push(proof_is_invalid);
+
+    // [z]pi - [y]G1 is computed with double-and-add over the bits of both scalars,
+    // and points at infinity are just never added
+    let mut generator_negated = Bls12_381G1Affine::one();
+    generator_negated.negate();
+    let (gen_x, gen_y) = generator_negated.into_xy_unchecked();
+    let gen_x_nn = Bls12_381BaseNNField::allocated_constant(cs, gen_x, base_field_params);
+    let gen_y_nn = Bls12_381BaseNNField::allocated_constant(cs, gen_y, base_field_params);
+    let generator_negated = (gen_x_nn, gen_y_nn);
+
+    let z_bits = u256_into_bits(cs, &z);
+    let y_bits = u256_into_bits(cs, &y);
+    let proof_is_not_infinity = proof_is_infinity.negated(cs);
+    let commitment_is_not_infinity = commitment_is_infinity.negated(cs);
+
+    let mut acc = SWProjectivePoint::<F, Bls12_381G1Affine, Bls12_381BaseNNField<F>>::zero(
+        cs,
+        base_field_params,
+    );
+    // scalars are less than the group order
+    let num_bits = 256 - group_order.leading_zeros() as usize;
+    for i in (0..num_bits).rev() {
+        acc = acc.double(cs);
+
+        let should_add = Boolean::multi_and(cs, &[z_bits[i], proof_is_not_infinity]);
+        let sum = acc.add_mixed(cs, &mut proof.clone());
+        acc = Selectable::conditionally_select(cs, should_add, &sum, &acc);
+
+        let sum = acc.add_mixed(cs, &mut generator_negated.clone());
+        acc = Selectable::conditionally_select(cs, y_bits[i], &sum, &acc);
+    }
+    let sum = acc.add_mixed(cs, &mut commitment.clone());
+    let mut acc = Selectable::conditionally_select(cs, commitment_is_not_infinity, &sum, &acc);
+
+    let (lhs_point, lhs_is_infinity) =
+        acc.convert_to_affine_or_default(cs, Bls12_381G1Affine::one());
+    let proof_negated = (proof.0.clone(), proof.1.clone().negated(cs));
+
+    let g2_lines = bls12_381_precompute_lines(Bls12_381G2Affine::one());
+    let tau_g2_lines = bls12_381_precompute_lines(tau_g2);
+
+    let mut f = bls12_381_multi_miller_loop_with_fixed_g2(
+        cs,
+        &mut [lhs_point, proof_negated],
+        &[lhs_is_infinity, proof_is_infinity],
+        &[g2_lines, tau_g2_lines],
+        base_field_params,
+    );
+    let mut f = bls12_381_final_exponentiation(cs, &mut f, base_field_params);
+    let mut one = Bls12_381Fq12::one(cs, base_field_params);
+    let pairing_check_passed = Bls12_381Fq12::equals(cs, &mut f, &mut one);
+    let pairing_check_failed = pairing_check_passed.negated(cs);
+    exception_flags.push(pairing_check_failed);
+
+    // return values are constant
+    let field_elements_per_blob =
+        UInt256::allocated_constant(cs, U256::from(ELEMENTS_PER_4844_BLOCK as u64));
+
+    let any_exception = Boolean::multi_or(cs, &exception_flags[..]);
+    let field_elements_per_blob = field_elements_per_blob.mask_negated(cs, any_exception);
+    let bls_modulus = group_order_u256.mask_negated(cs, any_exception);
+    let all_ok = any_exception.negated(cs);
+
+    (all_ok, (field_elements_per_blob, bls_modulus))
+}
+
+pub fn kzg_point_evaluation_function_entry_point<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    witness: KzgPointEvaluationCircuitInstanceWitness<F>,
+    round_function: &R,
+    limit: usize,
+) -> [Num<F>; INPUT_OUTPUT_COMMITMENT_LENGTH]
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    assert!(limit <= u32::MAX as usize);
+
+    let KzgPointEvaluationCircuitInstanceWitness {
+        closed_form_input,
+        requests_queue_witness,
+        memory_reads_witness,
+    } = witness;
+
+    let memory_reads_witness: VecDeque<_> = memory_reads_witness.into_iter().flatten().collect();
+
+    let precompile_address =
+        UInt160::allocated_constant(cs, KZG_POINT_EVALUATION_PRECOMPILE_FORMAL_ADDRESS);
+    let aux_byte_for_precompile = UInt8::allocated_constant(cs, PRECOMPILE_AUX_BYTE);
+
+    let base_field_params = Arc::new(bls12_381_base_field_params());
+    let scalar_field_params = Arc::new(bls12_381_scalar_field_params());
+    let tau_g2 = kzg_setup_tau_g2();
+
+    let mut structured_input =
+        KzgPointEvaluationCircuitInputOutput::alloc_ignoring_outputs(cs, closed_form_input.clone());
+    let start_flag = structured_input.start_flag;
+
+    let requests_queue_state_from_input = structured_input.observable_input.initial_log_queue_state;
+
+    // it must be trivial
+    requests_queue_state_from_input.enforce_trivial_head(cs);
+
+    let requests_queue_state_from_fsm = structured_input.hidden_fsm_input.log_queue_state;
+
+    let requests_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &requests_queue_state_from_input,
+        &requests_queue_state_from_fsm,
+    );
+
+    let memory_queue_state_from_input =
+        structured_input.observable_input.initial_memory_queue_state;
+
+    // it must be trivial
+    memory_queue_state_from_input.enforce_trivial_head(cs);
+
+    let memory_queue_state_from_fsm = structured_input.hidden_fsm_input.memory_queue_state;
+
+    let memory_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &memory_queue_state_from_input,
+        &memory_queue_state_from_fsm,
+    );
+
+    let mut requests_queue = StorageLogQueue::<F, R>::from_state(cs, requests_queue_state);
+    let queue_witness = CircuitQueueWitness::from_inner_witness(requests_queue_witness);
+    requests_queue.witness = Arc::new(queue_witness);
+
+    let mut memory_queue = MemoryQueue::<F, R>::from_state(cs, memory_queue_state);
+
+    let one_u32 = UInt32::allocated_constant(cs, 1u32);
+    let zero_u256 = UInt256::zero(cs);
+    let boolean_false = Boolean::allocated_constant(cs, false);
+    let boolean_true = Boolean::allocated_constant(cs, true);
+
+    use crate::storage_application::ConditionalWitnessAllocator;
+    let read_queries_allocator = ConditionalWitnessAllocator::<F, UInt256<F>> {
+        witness_source: Arc::new(RwLock::new(memory_reads_witness)),
+    };
+
+    for _cycle in 0..limit {
+        let is_empty = requests_queue.is_empty(cs);
+        let should_process = is_empty.negated(cs);
+        let (request, _) = requests_queue.pop_front(cs, should_process);
+
+        let mut precompile_call_params =
+            KzgPointEvaluationPrecompileCallParams::from_encoding(cs, request.key);
+
+        let timestamp_to_use_for_read = request.timestamp;
+        let timestamp_to_use_for_write = timestamp_to_use_for_read.add_no_overflow(cs, one_u32);
+
+        Num::conditionally_enforce_equal(
+            cs,
+            should_process,
+            &Num::from_variable(request.aux_byte.get_variable()),
+            &Num::from_variable(aux_byte_for_precompile.get_variable()),
+        );
+        for (a, b) in request
+            .address
+            .inner
+            .iter()
+            .zip(precompile_address.inner.iter())
+        {
+            Num::conditionally_enforce_equal(
+                cs,
+                should_process,
+                &Num::from_variable(a.get_variable()),
+                &Num::from_variable(b.get_variable()),
+            );
+        }
+
+        let mut read_values = [zero_u256; MEMORY_QUERIES_PER_CALL];
+        let mut bias_variable = should_process.get_variable();
+        for dst in read_values.iter_mut() {
+            let read_query_value: UInt256<F> = read_queries_allocator
+                .conditionally_allocate_biased(cs, should_process, bias_variable);
+            bias_variable = read_query_value.inner[0].get_variable();
+
+            *dst = read_query_value;
+
+            let read_query = MemoryQuery {
+                timestamp: timestamp_to_use_for_read,
+                memory_page: precompile_call_params.input_page,
+                index: precompile_call_params.input_offset,
+                rw_flag: boolean_false,
+                is_ptr: boolean_false,
+                value: read_query_value,
+            };
+
+            let _ = memory_queue.push(cs, read_query, should_process);
+
+            precompile_call_params.input_offset = precompile_call_params
+                .input_offset
+                .add_no_overflow(cs, one_u32);
+        }
+
+        let [versioned_hash, z, y, commitment_high, mixed, proof_low] = read_values;
+
+        let (commitment_words, proof_words) =
+            split_into_commitment_and_proof_words(&[commitment_high, mixed, proof_low]);
+
+        let (success, (field_elements_per_blob, bls_modulus)) = kzg_point_evaluation_function_inner(
+            cs,
+            &versioned_hash,
+            &z,
+            &y,
+            &commitment_words,
+            &proof_words,
+            tau_g2,
+            &base_field_params,
+            &scalar_field_params,
+        );
+
+        let success_as_u32 = unsafe { UInt32::from_variable_unchecked(success.get_variable()) };
+        let mut success_as_u256 = zero_u256;
+        success_as_u256.inner[0] = success_as_u32;
+
+        let success_query = MemoryQuery {
+            timestamp: timestamp_to_use_for_write,
+            memory_page: precompile_call_params.output_page,
+            index: precompile_call_params.output_offset,
+            rw_flag: boolean_true,
+            value: success_as_u256,
+            is_ptr: boolean_false,
+        };
+
+        precompile_call_params.output_offset = precompile_call_params
+            .output_offset
+            .add_no_overflow(cs, one_u32);
+
+        let _ = memory_queue.push(cs, success_query, should_process);
+
+        for value in [field_elements_per_blob, bls_modulus].into_iter() {
+            let value_query = MemoryQuery {
+                timestamp: timestamp_to_use_for_write,
+                memory_page: precompile_call_params.output_page,
+                index: precompile_call_params.output_offset,
+                rw_flag: boolean_true,
+                value,
+                is_ptr: boolean_false,
+            };
+
+            precompile_call_params.output_offset = precompile_call_params
+                .output_offset
+                .add_no_overflow(cs, one_u32);
+
+            let _ = memory_queue.push(cs, value_query, should_process);
+        }
+    }
+
+    requests_queue.enforce_consistency(cs);
+
+    // form the final state
+    let done = requests_queue.is_empty(cs);
+    structured_input.completion_flag = done;
+    structured_input.observable_output = PrecompileFunctionOutputData::placeholder(cs);
+
+    let final_memory_state = memory_queue.into_state();
+    let final_requets_state = requests_queue.into_state();
+
+    structured_input.observable_output.final_memory_state = QueueState::conditionally_select(
+        cs,
+        structured_input.completion_flag,
+        &final_memory_state,
+        &structured_input.observable_output.final_memory_state,
+    );
+
+    structured_input.hidden_fsm_output.log_queue_state = final_requets_state;
+    structured_input.hidden_fsm_output.memory_queue_state = final_memory_state;
+
+    // self-check
+    structured_input.hook_compare_witness(cs, &closed_form_input);
+
+    use boojum::cs::gates::PublicInputGate;
+
+    let compact_form =
+        ClosedFormInputCompactForm::from_full_form(cs, &structured_input, round_function);
+    let input_commitment = commit_variable_length_encodable_item(cs, &compact_form, round_function);
+    for el in input_commitment.iter() {
+        let gate = PublicInputGate::new(el.get_variable());
+        gate.add_to_cs(cs);
+    }
+
+    input_commitment
+}
+
+#[cfg(test)]
+mod test {
+    use boojum::field::goldilocks::GoldilocksField;
+    use boojum::gadgets::traits::allocatable::CSAllocatable;
+    use boojum::worker::Worker;
+
+    use super::*;
+
+    type F = GoldilocksField;
+    type P = GoldilocksField;
+
+    use boojum::config::DevCSConfig;
+
+    use boojum::cs::cs_builder::*;
+    use boojum::cs::cs_builder_reference::CsReferenceImplementationBuilder;
+    use boojum::cs::gates::*;
+    use boojum::cs::implementations::reference_cs::CSReferenceImplementation;
+    use boojum::cs::traits::gate::GatePlacementStrategy;
+    use boojum::cs::CSGeometry;
+    use boojum::cs::*;
+    use boojum::gadgets::tables::*;
+
+    fn create_cs(
+        max_trace_len: usize,
+    ) -> CSReferenceImplementation<
+        F,
+        P,
+        DevCSConfig,
+        impl GateConfigurationHolder<F>,
+        impl StaticToolboxHolder,
+    > {
+        let geometry = CSGeometry {
+            num_columns_under_copy_permutation: 80,
+            num_witness_columns: 0,
+            num_constant_columns: 4,
+            max_allowed_constraint_degree: 8,
+        };
+        let max_variables = 1 << 27;
+
+        fn configure<
+            F: SmallField,
+            T: CsBuilderImpl<F, T>,
+            GC: GateConfigurationHolder<F>,
+            TB: StaticToolboxHolder,
+        >(
+            builder: CsBuilder<T, F, GC, TB>,
+        ) -> CsBuilder<T, F, impl GateConfigurationHolder<F>, impl StaticToolboxHolder> {
+            let builder = builder.allow_lookup(
+                LookupParameters::UseSpecializedColumnsWithTableIdAsConstant {
+                    width: 4,
+                    num_repetitions: 8,
+                    share_table_id: true,
+                },
+            );
+
+            let builder = ConstantsAllocatorGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = BooleanConstraintGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseSpecializedColumns {
+                    num_repetitions: 1,
+                    share_constants: false,
+                },
+            );
+            let builder = U8x4FMAGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ZeroCheckGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+                false,
+            );
+            let builder = FmaGateInBaseFieldWithoutConstant::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<32>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<16>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<8>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = DotProductGate::<4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = SelectionGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ParallelSelectionGate::<4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = PublicInputGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ReductionGate::<_, 4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = NopGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+
+            builder
+        }
+
+        let builder_impl =
+            CsReferenceImplementationBuilder::<F, P, DevCSConfig>::new(geometry, max_trace_len);
+        let builder = new_builder::<_, F>(builder_impl);
+
+        let builder = configure(builder);
+        let mut owned_cs = builder.build(max_variables);
+
+        // add tables
+        let table = create_xor8_table();
+        owned_cs.add_lookup_table::<Xor8Table, 3>(table);
+
+        let table = create_byte_split_table::<F, 4>();
+        owned_cs.add_lookup_table::<ByteSplitTable<4>, 3>(table);
+
+        // tables for sha256 of the commitment
+        let table = create_maj4_table();
+        owned_cs.add_lookup_table::<Maj4Table, 4>(table);
+
+        let table = create_tri_xor_table();
+        owned_cs.add_lookup_table::<TriXor4Table, 4>(table);
+
+        let table = create_ch4_table();
+        owned_cs.add_lookup_table::<Ch4Table, 4>(table);
+
+        let table = create_4bit_chunk_split_table::<F, 1>();
+        owned_cs.add_lookup_table::<chunk4bits::Split4BitChunkTable<1>, 4>(table);
+        let table = create_4bit_chunk_split_table::<F, 2>();
+        owned_cs.add_lookup_table::<chunk4bits::Split4BitChunkTable<2>, 4>(table);
+
+        owned_cs
+    }
+
+    // test vector from the go-ethereum test suite, the proof is made with the trusted setup of the ceremony
+    const VALID_INPUT: &str = "01e798154708fe7789429634053cbf9f99b619f9f084048927333fce637f549b564c0a11a0f704f4fc3e8acfe0f8245f0ad1347b378fbf96e206da11a5d3630624d25032e67a7e6a4910df5834b8fe70e6bcfeeac0352434196bdf4b2485d5a18f59a8d2a1a625a17f3fea0fe5eb8c896db3764f3185481bc22f91b4aaffcca25f26936857bc3a7c2539ea8ec3a952b7873033e038326e87ed3e1276fd140253fa08e9fc25fb2d9a98527fc22a2c9612fbeafdad446cbc7bcdbdcd780af2c16a";
+
+    fn run_point_evaluation(input: &[u8]) -> (bool, (U256, U256)) {
+        assert_eq!(input.len(), MEMORY_QUERIES_PER_CALL * 32);
+
+        let mut owned_cs = create_cs(1 << 24);
+        let cs = &mut owned_cs;
+
+        let base_field_params = Arc::new(bls12_381_base_field_params());
+        let scalar_field_params = Arc::new(bls12_381_scalar_field_params());
+
+        let words: [UInt256<F>; MEMORY_QUERIES_PER_CALL] = std::array::from_fn(|i| {
+            UInt256::allocate(cs, U256::from_big_endian(&input[i * 32..(i + 1) * 32]))
+        });
+        let [versioned_hash, z, y, commitment_high, mixed, proof_low] = words;
+        let (commitment_words, proof_words) =
+            split_into_commitment_and_proof_words(&[commitment_high, mixed, proof_low]);
+
+        let (no_error, (field_elements_per_blob, bls_modulus)) =
+            kzg_point_evaluation_function_inner(
+                cs,
+                &versioned_hash,
+                &z,
+                &y,
+                &commitment_words,
+                &proof_words,
+                kzg_setup_tau_g2(),
+                &base_field_params,
+                &scalar_field_params,
+            );
+
+        let no_error = no_error.witness_hook(&*cs)().unwrap();
+        let field_elements_per_blob = field_elements_per_blob.witness_hook(&*cs)().unwrap();
+        let bls_modulus = bls_modulus.witness_hook(&*cs)().unwrap();
+
+        cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        let worker = Worker::new();
+        assert!(cs.check_if_satisfied(&worker));
+
+        (no_error, (field_elements_per_blob, bls_modulus))
+    }
+
+    #[test]
+    fn test_kzg_point_evaluation() {
+        let input = hex::decode(VALID_INPUT).unwrap();
+
+        let (no_error, result) = run_point_evaluation(&input);
+        assert!(no_error);
+        assert_eq!(
+            result,
+            (
+                U256::from(ELEMENTS_PER_4844_BLOCK as u64),
+                U256::from_str_radix(
+                    "73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001",
+                    16
+                )
+                .unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn test_kzg_point_evaluation_invalid_inputs() {
+        let input = hex::decode(VALID_INPUT).unwrap();
+
+        // wrong claimed value
+        let mut wrong_y = input.clone();
+        wrong_y[95] ^= 1;
+        let (no_error, result) = run_point_evaluation(&wrong_y);
+        assert!(!no_error);
+        assert_eq!(result, (U256::zero(), U256::zero()));
+
+        // versioned hash doesn't match the commitment
+        let mut wrong_hash = input.clone();
+        wrong_hash[0] = 0x02;
+        let (no_error, result) = run_point_evaluation(&wrong_hash);
+        assert!(!no_error);
+        assert_eq!(result, (U256::zero(), U256::zero()));
+
+        // proof is not compressed
+        let mut wrong_proof = input.clone();
+        wrong_proof[144] &= 0x7f;
+        let (no_error, result) = run_point_evaluation(&wrong_proof);
+        assert!(!no_error);
+        assert_eq!(result, (U256::zero(), U256::zero()));
+    }
+}
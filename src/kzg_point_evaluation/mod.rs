@@ -0,0 +1,88 @@
+use super::*;
+use crate::base_structures::log_query::*;
+use crate::base_structures::memory_query::*;
+
+use crate::ethereum_types::U256;
+
+use crate::fsm_input_output::*;
+
+use boojum::cs::traits::cs::ConstraintSystem;
+use boojum::field::SmallField;
+use boojum::gadgets::boolean::Boolean;
+
+use boojum::gadgets::non_native_field::implementations::*;
+
+use boojum::gadgets::queue::QueueState;
+
+use boojum::gadgets::traits::selectable::Selectable;
+use boojum::gadgets::traits::witnessable::WitnessHookable;
+
+use cs_derive::*;
+use std::sync::Arc;
+
+use crate::base_structures::precompile_input_outputs::formal_precompile_address;
+use zkevm_opcode_defs::ethereum_types::H160;
+
+use crate::base_structures::precompile_input_outputs::*;
+use crate::demux_log_queue::DemuxOutput;
+use crate::precompile_registry::PrecompileCircuit;
+use crate::scheduler::auxiliary::BaseLayerCircuitType;
+
+pub mod input;
+pub use self::input::*;
+
+// versioned_hash, z, y, commitment (48 bytes) and proof (48 bytes) that share one word
+pub const MEMORY_QUERIES_PER_CALL: usize = 6;
+
+pub const KZG_POINT_EVALUATION_PRECOMPILE_ADDRESS: u16 = 0x0a;
+pub const KZG_POINT_EVALUATION_PRECOMPILE_FORMAL_ADDRESS: H160 =
+    formal_precompile_address(KZG_POINT_EVALUATION_PRECOMPILE_ADDRESS);
+
+pub struct KzgPointEvaluationPrecompileCircuit;
+
+impl PrecompileCircuit for KzgPointEvaluationPrecompileCircuit {
+    const NAME: &'static str = "kzg_point_evaluation";
+    const DEMUX_OUTPUT: DemuxOutput = DemuxOutput::PointEvaluation;
+    const CIRCUIT_TYPE: BaseLayerCircuitType = BaseLayerCircuitType::KzgPointEvaluationPrecompile;
+
+    type InputData<F: SmallField> = PrecompileFunctionInputData<F>;
+    type OutputData<F: SmallField> = PrecompileFunctionOutputData<F>;
+
+    fn formal_address() -> H160 {
+        KZG_POINT_EVALUATION_PRECOMPILE_FORMAL_ADDRESS
+    }
+}
+
+pub const VERSIONED_HASH_VERSION_KZG: u8 = 0x01;
+
+pub mod baseline;
+pub mod pairing;
+pub mod towers;
+
+// characteristics of the base field for BLS12-381 curve
+pub(crate) use boojum::pairing::bls12_381::Fq as Bls12_381Fq;
+// points of G1 and G2
+pub(crate) use boojum::pairing::bls12_381::G1Affine as Bls12_381G1Affine;
+pub(crate) use boojum::pairing::bls12_381::G2Affine as Bls12_381G2Affine;
+
+// scalar field is shared with EIP-4844 blob commitments
+pub(crate) use crate::eip_4844::Bls12_381ScalarNNFieldParams;
+
+pub(crate) const BASE_FIELD_REPR_LIMBS: usize = 25;
+pub(crate) const BASE_FIELD_CANONICAL_REPR_LIMBS: usize = 24;
+
+pub(crate) type Bls12_381BaseNNFieldParams = NonNativeFieldOverU16Params<Bls12_381Fq, 25>;
+pub(crate) type Bls12_381BaseNNField<F> = NonNativeFieldOverU16<F, Bls12_381Fq, 25>;
+
+pub(crate) fn bls12_381_base_field_params() -> Bls12_381BaseNNFieldParams {
+    NonNativeFieldOverU16Params::create()
+}
+
+pub(crate) fn bls12_381_scalar_field_params() -> Bls12_381ScalarNNFieldParams {
+    NonNativeFieldOverU16Params::create()
+}
+
+// re-exports for integration
+pub use self::baseline::{
+    kzg_point_evaluation_function_entry_point, KzgPointEvaluationPrecompileCallParams,
+};
@@ -0,0 +1,219 @@
+use super::*;
+
+use super::towers::*;
+use boojum::pairing::bls12_381::Fq2 as NativeFq2;
+use boojum::pairing::ff::Field;
+use boojum::pairing::GenericCurveAffine;
+
+// absolute value of the parameter of the BLS12-381 curve, the parameter itself is negative
+pub(crate) const BLS12_381_X: u64 = 0xd201000000010000;
+
+// We use M-type twist, so the line through T with slope lambda, evaluated at P and multiplied by w^3, is
+// (lambda * x_T - y_T) + (-lambda * x_P) w^2 + y_P w^3. Multiplication by w^3 doesn't change the result
+// of the final exponentiation, since w^3 belongs to Fq4
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct PrecomputedLine {
+    pub(crate) lambda_negated: NativeFq2,
+    pub(crate) constant: NativeFq2,
+}
+
+// computes T = T + T or T = T + Q in affine form, and returns the line coefficients for the step
+fn precompute_line_step(
+    t: &mut (NativeFq2, NativeFq2),
+    q: Option<&(NativeFq2, NativeFq2)>,
+) -> PrecomputedLine {
+    let (t_x, t_y) = *t;
+
+    // We work with the points of G2 only, and T is never equal to +-Q, so there are no exceptional cases
+    let (lambda, q_x) = match q {
+        None => {
+            // lambda = 3 x_T^2 / 2 y_T
+            let mut x_squared = t_x;
+            x_squared.square();
+            let mut numerator = x_squared;
+            numerator.double();
+            numerator.add_assign(&x_squared);
+            let mut denominator = t_y;
+            denominator.double();
+            numerator.mul_assign(&denominator.inverse().expect("must not be zero"));
+
+            (numerator, t_x)
+        }
+        Some((q_x, q_y)) => {
+            // lambda = (y_Q - y_T) / (x_Q - x_T)
+            let mut numerator = *q_y;
+            numerator.sub_assign(&t_y);
+            let mut denominator = *q_x;
+            denominator.sub_assign(&t_x);
+            numerator.mul_assign(&denominator.inverse().expect("must not be zero"));
+
+            (numerator, *q_x)
+        }
+    };
+
+    let mut constant = lambda;
+    constant.mul_assign(&t_x);
+    constant.sub_assign(&t_y);
+
+    // x_R = lambda^2 - x_T - x_Q, y_R = lambda (x_T - x_R) - y_T
+    let mut x = lambda;
+    x.square();
+    x.sub_assign(&t_x);
+    x.sub_assign(&q_x);
+    let mut y = t_x;
+    y.sub_assign(&x);
+    y.mul_assign(&lambda);
+    y.sub_assign(&t_y);
+
+    *t = (x, y);
+
+    let mut lambda_negated = lambda;
+    lambda_negated.negate();
+
+    PrecomputedLine {
+        lambda_negated,
+        constant,
+    }
+}
+
+/// Computes the lines of the Miller loop for the fixed point of G2 that is not at infinity.
+/// It's done at synthesis time, so only evaluation at the point of G1 is left for the circuit
+pub(crate) fn bls12_381_precompute_lines(q: Bls12_381G2Affine) -> Vec<PrecomputedLine> {
+    let q = q.into_xy_unchecked();
+    let mut t = q;
+
+    let num_bits = 64 - BLS12_381_X.leading_zeros();
+    let mut lines = Vec::with_capacity(num_bits as usize + BLS12_381_X.count_ones() as usize);
+    for i in (0..(num_bits - 1)).rev() {
+        lines.push(precompute_line_step(&mut t, None));
+        if (BLS12_381_X >> i) & 1 == 1 {
+            lines.push(precompute_line_step(&mut t, Some(&q)));
+        }
+    }
+
+    lines
+}
+
+fn evaluate_line<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    line: &PrecomputedLine,
+    p: &mut (Bls12_381BaseNNField<F>, Bls12_381BaseNNField<F>),
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> Bls12_381Fq12<F> {
+    let zero = Bls12_381Fq2::zero(cs, params);
+    let constant = Bls12_381Fq2::constant(cs, line.constant.c0, line.constant.c1, params);
+    let mut lambda_negated =
+        Bls12_381Fq2::constant(cs, line.lambda_negated.c0, line.lambda_negated.c1, params);
+
+    let c0 = Bls12_381Fq6 {
+        c0: constant,
+        c1: lambda_negated.mul_by_base_field(cs, &mut p.0),
+        c2: zero.clone(),
+    };
+    let c1 = Bls12_381Fq6 {
+        c0: zero.clone(),
+        c1: Bls12_381Fq2 {
+            c0: p.1.clone(),
+            c1: zero.c0.clone(),
+        },
+        c2: zero,
+    };
+
+    Bls12_381Fq12 { c0, c1 }
+}
+
+/// Optimal ate Miller loop over several pairs, where the points of G2 are fixed and given by their lines.
+/// Points of G1 must be valid and not at infinity; pairs with the skip flag set contribute one instead
+pub(crate) fn bls12_381_multi_miller_loop_with_fixed_g2<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    g1_points: &mut [(Bls12_381BaseNNField<F>, Bls12_381BaseNNField<F>)],
+    skip_flags: &[Boolean<F>],
+    g2_lines: &[Vec<PrecomputedLine>],
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> Bls12_381Fq12<F> {
+    assert_eq!(g1_points.len(), skip_flags.len());
+    assert_eq!(g1_points.len(), g2_lines.len());
+
+    let one = Bls12_381Fq12::one(cs, params);
+    let mut f = one.clone();
+
+    let num_bits = 64 - BLS12_381_X.leading_zeros();
+    let mut line_idx = 0;
+    for i in (0..(num_bits - 1)).rev() {
+        if i != num_bits - 2 {
+            f = f.square(cs);
+        }
+
+        let num_steps = if (BLS12_381_X >> i) & 1 == 1 { 2 } else { 1 };
+        for _ in 0..num_steps {
+            for ((p, skip), lines) in g1_points
+                .iter_mut()
+                .zip(skip_flags.iter())
+                .zip(g2_lines.iter())
+            {
+                let line = evaluate_line(cs, &lines[line_idx], p, params);
+                let mut line = Bls12_381Fq12::conditionally_select(cs, *skip, &one, &line);
+                f = f.mul(cs, &mut line);
+            }
+            line_idx += 1;
+        }
+    }
+    assert!(g2_lines.iter().all(|lines| lines.len() == line_idx));
+
+    // parameter of the curve is negative
+    f.conjugate(cs)
+}
+
+// we work in cyclotomic subgroup, so conjugation is an inversion
+fn exp_by_x<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    f: &mut Bls12_381Fq12<F>,
+) -> Bls12_381Fq12<F> {
+    let mut result = f.clone();
+    let num_bits = 64 - BLS12_381_X.leading_zeros();
+    for i in (0..(num_bits - 1)).rev() {
+        result = result.square(cs);
+        if (BLS12_381_X >> i) & 1 == 1 {
+            result = result.mul(cs, f);
+        }
+    }
+
+    result.conjugate(cs)
+}
+
+/// Final exponentiation with the hard part from https://eprint.iacr.org/2020/875.
+/// It computes the cube of f^((p^12 - 1) / r), that is enough to compare the result with one
+pub(crate) fn bls12_381_final_exponentiation<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    f: &mut Bls12_381Fq12<F>,
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> Bls12_381Fq12<F> {
+    // easy part: f^((p^6 - 1)(p^2 + 1))
+    let mut f1 = f.conjugate(cs);
+    let mut f2 = f.inverse(cs);
+    let mut r = f1.mul(cs, &mut f2);
+    let mut f2 = r.clone();
+    let mut r = r.frobenius_map(cs, 2, params);
+    let mut r = r.mul(cs, &mut f2);
+
+    // hard part: power 3 (p^4 - p^2 + 1) / r is decomposed as (x - 1)^2 (x + p) (x^2 + p^2 - 1) + 3
+    let mut y0 = r.square(cs);
+    let mut y1 = exp_by_x(cs, &mut r);
+    let mut y2 = r.conjugate(cs);
+    let mut y1 = y1.mul(cs, &mut y2);
+    let mut y2 = exp_by_x(cs, &mut y1);
+    let mut y1 = y1.conjugate(cs);
+    let mut y1 = y1.mul(cs, &mut y2);
+    let mut y2 = exp_by_x(cs, &mut y1);
+    let mut y1 = y1.frobenius_map(cs, 1, params);
+    let mut y1 = y1.mul(cs, &mut y2);
+    let mut r = r.mul(cs, &mut y0);
+    let mut y0 = exp_by_x(cs, &mut y1);
+    let mut y2 = exp_by_x(cs, &mut y0);
+    let mut y0 = y1.frobenius_map(cs, 2, params);
+    let mut y1 = y1.conjugate(cs);
+    let mut y1 = y1.mul(cs, &mut y2);
+    let mut y1 = y1.mul(cs, &mut y0);
+
+    r.mul(cs, &mut y1)
+}
@@ -0,0 +1,556 @@
+use super::*;
+
+use boojum::gadgets::non_native_field::traits::NonNativeField;
+use boojum::pairing::ff::{Field, PrimeField};
+
+// Tower of extensions used by the BLS12-381 pairing:
+// - Fq2 = Fq[u] / (u^2 + 1)
+// - Fq6 = Fq2[v] / (v^3 - xi), where xi = 1 + u
+// - Fq12 = Fq6[w] / (w^2 - v)
+//
+// All the operations take values by mutable reference, the same way as non-native field arithmetic does
+
+// Frobenius coefficients gamma_{k, m} = xi^(m * (p^k - 1) / 6) for k = 1, 2 and m = 1..5,
+// stored as (c0, c1) pairs of Fq2 elements. Basis element of Fq12 with index m is w^m
+const FROBENIUS_COEFFS_C0_C1: [[(&str, &str); 5]; 2] = [
+    [
+        (
+            "3850754370037169011952147076051364057158807420970682438676050522613628423219637725072182697113062777891589506424760",
+            "151655185184498381465642749684540099398075398968325446656007613510403227271200139370504932015952886146304766135027",
+        ),
+        (
+            "0",
+            "4002409555221667392624310435006688643935503118305586438271171395842971157480381377015405980053539358417135540939436",
+        ),
+        (
+            "1028732146235106349975324479215795277384839936929757896155643118032610843298655225875571310552543014690878354869257",
+            "1028732146235106349975324479215795277384839936929757896155643118032610843298655225875571310552543014690878354869257",
+        ),
+        (
+            "4002409555221667392624310435006688643935503118305586438271171395842971157480381377015405980053539358417135540939437",
+            "0",
+        ),
+        (
+            "877076961050607968509681729531255177986764537961432449499635504522207616027455086505066378536590128544573588734230",
+            "3125332594171059424908108096204648978570118281977575435832422631601824034463382777937621250592425535493320683825557",
+        ),
+    ],
+    [
+        (
+            "793479390729215512621379701633421447060886740281060493010456487427281649075476305620758731620351",
+            "0",
+        ),
+        (
+            "793479390729215512621379701633421447060886740281060493010456487427281649075476305620758731620350",
+            "0",
+        ),
+        (
+            "4002409555221667393417789825735904156556882819939007885332058136124031650490837864442687629129015664037894272559786",
+            "0",
+        ),
+        (
+            "4002409555221667392624310435006688643935503118305586438271171395842971157480381377015405980053539358417135540939436",
+            "0",
+        ),
+        (
+            "4002409555221667392624310435006688643935503118305586438271171395842971157480381377015405980053539358417135540939437",
+            "0",
+        ),
+    ],
+];
+
+pub(crate) fn bls12_381_fq_from_str(value: &str) -> Bls12_381Fq {
+    Bls12_381Fq::from_str(value).expect("must be a valid field element")
+}
+
+/// Returns xi^(basis_power * (p^power - 1) / 6)
+pub(crate) fn frobenius_coeff<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    power: usize,
+    basis_power: usize,
+    params: &Arc<Bls12_381BaseNNFieldParams>,
+) -> Bls12_381Fq2<F> {
+    let (c0, c1) = FROBENIUS_COEFFS_C0_C1[power - 1][basis_power - 1];
+
+    Bls12_381Fq2::constant(
+        cs,
+        bls12_381_fq_from_str(c0),
+        bls12_381_fq_from_str(c1),
+        params,
+    )
+}
+
+#[derive(Derivative)]
+#[derivative(Clone, Debug)]
+pub(crate) struct Bls12_381Fq2<F: SmallField> {
+    pub(crate) c0: Bls12_381BaseNNField<F>,
+    pub(crate) c1: Bls12_381BaseNNField<F>,
+}
+
+impl<F: SmallField> Bls12_381Fq2<F> {
+    pub(crate) fn constant<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        c0: Bls12_381Fq,
+        c1: Bls12_381Fq,
+        params: &Arc<Bls12_381BaseNNFieldParams>,
+    ) -> Self {
+        Self {
+            c0: Bls12_381BaseNNField::allocated_constant(cs, c0, params),
+            c1: Bls12_381BaseNNField::allocated_constant(cs, c1, params),
+        }
+    }
+
+    pub(crate) fn zero<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        params: &Arc<Bls12_381BaseNNFieldParams>,
+    ) -> Self {
+        Self::constant(cs, Bls12_381Fq::zero(), Bls12_381Fq::zero(), params)
+    }
+
+    pub(crate) fn one<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        params: &Arc<Bls12_381BaseNNFieldParams>,
+    ) -> Self {
+        Self::constant(cs, Bls12_381Fq::one(), Bls12_381Fq::zero(), params)
+    }
+
+    pub(crate) fn add<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS, other: &mut Self) -> Self {
+        Self {
+            c0: self.c0.add(cs, &mut other.c0),
+            c1: self.c1.add(cs, &mut other.c1),
+        }
+    }
+
+    pub(crate) fn sub<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS, other: &mut Self) -> Self {
+        Self {
+            c0: self.c0.sub(cs, &mut other.c0),
+            c1: self.c1.sub(cs, &mut other.c1),
+        }
+    }
+
+    pub(crate) fn double<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        Self {
+            c0: self.c0.double(cs),
+            c1: self.c1.double(cs),
+        }
+    }
+
+    pub(crate) fn negated<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        Self {
+            c0: self.c0.negated(cs),
+            c1: self.c1.negated(cs),
+        }
+    }
+
+    pub(crate) fn conjugate<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        Self {
+            c0: self.c0.clone(),
+            c1: self.c1.negated(cs),
+        }
+    }
+
+    pub(crate) fn mul<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS, other: &mut Self) -> Self {
+        // Karatsuba: (a0 + a1 u)(b0 + b1 u) = a0 b0 - a1 b1 + ((a0 + a1)(b0 + b1) - a0 b0 - a1 b1) u
+        let mut v0 = self.c0.mul(cs, &mut other.c0);
+        let mut v1 = self.c1.mul(cs, &mut other.c1);
+
+        let c0 = v0.sub(cs, &mut v1);
+
+        let mut a = self.c0.add(cs, &mut self.c1);
+        let mut b = other.c0.add(cs, &mut other.c1);
+        let mut c1 = a.mul(cs, &mut b);
+        let mut c1 = c1.sub(cs, &mut v0);
+        let c1 = c1.sub(cs, &mut v1);
+
+        Self { c0, c1 }
+    }
+
+    pub(crate) fn square<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        // (a0 + a1 u)^2 = (a0 + a1)(a0 - a1) + 2 a0 a1 u
+        let mut a = self.c0.add(cs, &mut self.c1);
+        let mut b = self.c0.sub(cs, &mut self.c1);
+        let c0 = a.mul(cs, &mut b);
+        let mut c1 = self.c0.mul(cs, &mut self.c1);
+        let c1 = c1.double(cs);
+
+        Self { c0, c1 }
+    }
+
+    pub(crate) fn mul_by_base_field<CS: ConstraintSystem<F>>(
+        &mut self,
+        cs: &mut CS,
+        other: &mut Bls12_381BaseNNField<F>,
+    ) -> Self {
+        Self {
+            c0: self.c0.mul(cs, other),
+            c1: self.c1.mul(cs, other),
+        }
+    }
+
+    pub(crate) fn mul_by_xi<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        // (a0 + a1 u)(1 + u) = a0 - a1 + (a0 + a1) u
+        Self {
+            c0: self.c0.sub(cs, &mut self.c1),
+            c1: self.c0.add(cs, &mut self.c1),
+        }
+    }
+
+    /// Caller must ensure that the element is not zero, otherwise the circuit is unsatisfiable
+    pub(crate) fn inverse<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        // (a0 + a1 u)^-1 = (a0 - a1 u) / (a0^2 + a1^2)
+        let mut a0_squared = self.c0.square(cs);
+        let mut a1_squared = self.c1.square(cs);
+        let mut norm = a0_squared.add(cs, &mut a1_squared);
+        norm.normalize(cs);
+        let mut norm_inv = norm.inverse_unchecked(cs);
+
+        let c0 = self.c0.mul(cs, &mut norm_inv);
+        let mut c1 = self.c1.mul(cs, &mut norm_inv);
+        let c1 = c1.negated(cs);
+
+        Self { c0, c1 }
+    }
+
+    /// Caller must ensure that the divisor is not zero, otherwise the circuit is unsatisfiable
+    pub(crate) fn div<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS, other: &mut Self) -> Self {
+        let mut other_inv = other.inverse(cs);
+        self.mul(cs, &mut other_inv)
+    }
+
+    pub(crate) fn normalize<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) {
+        self.c0.normalize(cs);
+        self.c1.normalize(cs);
+    }
+
+    pub(crate) fn equals<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        a: &mut Self,
+        b: &mut Self,
+    ) -> Boolean<F> {
+        a.normalize(cs);
+        b.normalize(cs);
+        let c0_is_equal = NonNativeFieldOverU16::equals(cs, &mut a.c0, &mut b.c0);
+        let c1_is_equal = NonNativeFieldOverU16::equals(cs, &mut a.c1, &mut b.c1);
+
+        Boolean::multi_and(cs, &[c0_is_equal, c1_is_equal])
+    }
+
+    pub(crate) fn is_zero<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Boolean<F> {
+        let c0_is_zero = self.c0.is_zero(cs);
+        let c1_is_zero = self.c1.is_zero(cs);
+
+        Boolean::multi_and(cs, &[c0_is_zero, c1_is_zero])
+    }
+
+    pub(crate) fn conditionally_select<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        flag: Boolean<F>,
+        a: &Self,
+        b: &Self,
+    ) -> Self {
+        Self {
+            c0: NonNativeFieldOverU16::conditionally_select(cs, flag, &a.c0, &b.c0),
+            c1: NonNativeFieldOverU16::conditionally_select(cs, flag, &a.c1, &b.c1),
+        }
+    }
+}
+
+#[derive(Derivative)]
+#[derivative(Clone, Debug)]
+pub(crate) struct Bls12_381Fq6<F: SmallField> {
+    pub(crate) c0: Bls12_381Fq2<F>,
+    pub(crate) c1: Bls12_381Fq2<F>,
+    pub(crate) c2: Bls12_381Fq2<F>,
+}
+
+impl<F: SmallField> Bls12_381Fq6<F> {
+    pub(crate) fn zero<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        params: &Arc<Bls12_381BaseNNFieldParams>,
+    ) -> Self {
+        let zero = Bls12_381Fq2::zero(cs, params);
+        Self {
+            c0: zero.clone(),
+            c1: zero.clone(),
+            c2: zero,
+        }
+    }
+
+    pub(crate) fn one<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        params: &Arc<Bls12_381BaseNNFieldParams>,
+    ) -> Self {
+        let zero = Bls12_381Fq2::zero(cs, params);
+        Self {
+            c0: Bls12_381Fq2::one(cs, params),
+            c1: zero.clone(),
+            c2: zero,
+        }
+    }
+
+    pub(crate) fn add<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS, other: &mut Self) -> Self {
+        Self {
+            c0: self.c0.add(cs, &mut other.c0),
+            c1: self.c1.add(cs, &mut other.c1),
+            c2: self.c2.add(cs, &mut other.c2),
+        }
+    }
+
+    pub(crate) fn sub<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS, other: &mut Self) -> Self {
+        Self {
+            c0: self.c0.sub(cs, &mut other.c0),
+            c1: self.c1.sub(cs, &mut other.c1),
+            c2: self.c2.sub(cs, &mut other.c2),
+        }
+    }
+
+    pub(crate) fn negated<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        Self {
+            c0: self.c0.negated(cs),
+            c1: self.c1.negated(cs),
+            c2: self.c2.negated(cs),
+        }
+    }
+
+    pub(crate) fn mul<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS, other: &mut Self) -> Self {
+        // Karatsuba-like formulas for cubic extension
+        let mut v0 = self.c0.mul(cs, &mut other.c0);
+        let mut v1 = self.c1.mul(cs, &mut other.c1);
+        let mut v2 = self.c2.mul(cs, &mut other.c2);
+
+        // c0 = v0 + xi * ((a1 + a2)(b1 + b2) - v1 - v2)
+        let mut a = self.c1.add(cs, &mut self.c2);
+        let mut b = other.c1.add(cs, &mut other.c2);
+        let mut t = a.mul(cs, &mut b);
+        let mut t = t.sub(cs, &mut v1);
+        let mut t = t.sub(cs, &mut v2);
+        let mut t = t.mul_by_xi(cs);
+        let c0 = v0.add(cs, &mut t);
+
+        // c1 = (a0 + a1)(b0 + b1) - v0 - v1 + xi * v2
+        let mut a = self.c0.add(cs, &mut self.c1);
+        let mut b = other.c0.add(cs, &mut other.c1);
+        let mut t = a.mul(cs, &mut b);
+        let mut t = t.sub(cs, &mut v0);
+        let mut t = t.sub(cs, &mut v1);
+        let mut v2_times_xi = v2.mul_by_xi(cs);
+        let c1 = t.add(cs, &mut v2_times_xi);
+
+        // c2 = (a0 + a2)(b0 + b2) - v0 - v2 + v1
+        let mut a = self.c0.add(cs, &mut self.c2);
+        let mut b = other.c0.add(cs, &mut other.c2);
+        let mut t = a.mul(cs, &mut b);
+        let mut t = t.sub(cs, &mut v0);
+        let mut t = t.sub(cs, &mut v2);
+        let c2 = t.add(cs, &mut v1);
+
+        Self { c0, c1, c2 }
+    }
+
+    pub(crate) fn mul_by_v<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        // (a0 + a1 v + a2 v^2) v = xi a2 + a0 v + a1 v^2
+        Self {
+            c0: self.c2.mul_by_xi(cs),
+            c1: self.c0.clone(),
+            c2: self.c1.clone(),
+        }
+    }
+
+    /// Caller must ensure that the element is not zero, otherwise the circuit is unsatisfiable
+    pub(crate) fn inverse<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        // t0 = a0^2 - xi a1 a2
+        let mut t0 = self.c0.square(cs);
+        let mut tmp = self.c1.mul(cs, &mut self.c2);
+        let mut tmp = tmp.mul_by_xi(cs);
+        let mut t0 = t0.sub(cs, &mut tmp);
+
+        // t1 = xi a2^2 - a0 a1
+        let mut t1 = self.c2.square(cs);
+        let mut t1 = t1.mul_by_xi(cs);
+        let mut tmp = self.c0.mul(cs, &mut self.c1);
+        let mut t1 = t1.sub(cs, &mut tmp);
+
+        // t2 = a1^2 - a0 a2
+        let mut t2 = self.c1.square(cs);
+        let mut tmp = self.c0.mul(cs, &mut self.c2);
+        let mut t2 = t2.sub(cs, &mut tmp);
+
+        // norm = a0 t0 + xi (a2 t1 + a1 t2)
+        let mut tmp_0 = self.c2.mul(cs, &mut t1);
+        let mut tmp_1 = self.c1.mul(cs, &mut t2);
+        let mut tmp = tmp_0.add(cs, &mut tmp_1);
+        let mut tmp = tmp.mul_by_xi(cs);
+        let mut norm = self.c0.mul(cs, &mut t0);
+        let mut norm = norm.add(cs, &mut tmp);
+        let mut norm_inv = norm.inverse(cs);
+
+        Self {
+            c0: t0.mul(cs, &mut norm_inv),
+            c1: t1.mul(cs, &mut norm_inv),
+            c2: t2.mul(cs, &mut norm_inv),
+        }
+    }
+
+    pub(crate) fn conditionally_select<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        flag: Boolean<F>,
+        a: &Self,
+        b: &Self,
+    ) -> Self {
+        Self {
+            c0: Bls12_381Fq2::conditionally_select(cs, flag, &a.c0, &b.c0),
+            c1: Bls12_381Fq2::conditionally_select(cs, flag, &a.c1, &b.c1),
+            c2: Bls12_381Fq2::conditionally_select(cs, flag, &a.c2, &b.c2),
+        }
+    }
+}
+
+#[derive(Derivative)]
+#[derivative(Clone, Debug)]
+pub(crate) struct Bls12_381Fq12<F: SmallField> {
+    pub(crate) c0: Bls12_381Fq6<F>,
+    pub(crate) c1: Bls12_381Fq6<F>,
+}
+
+impl<F: SmallField> Bls12_381Fq12<F> {
+    pub(crate) fn one<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        params: &Arc<Bls12_381BaseNNFieldParams>,
+    ) -> Self {
+        Self {
+            c0: Bls12_381Fq6::one(cs, params),
+            c1: Bls12_381Fq6::zero(cs, params),
+        }
+    }
+
+    pub(crate) fn mul<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS, other: &mut Self) -> Self {
+        // (a0 + a1 w)(b0 + b1 w) = a0 b0 + a1 b1 v + ((a0 + a1)(b0 + b1) - a0 b0 - a1 b1) w
+        let mut v0 = self.c0.mul(cs, &mut other.c0);
+        let mut v1 = self.c1.mul(cs, &mut other.c1);
+
+        let mut v1_times_v = v1.mul_by_v(cs);
+        let c0 = v0.add(cs, &mut v1_times_v);
+
+        let mut a = self.c0.add(cs, &mut self.c1);
+        let mut b = other.c0.add(cs, &mut other.c1);
+        let mut c1 = a.mul(cs, &mut b);
+        let mut c1 = c1.sub(cs, &mut v0);
+        let c1 = c1.sub(cs, &mut v1);
+
+        Self { c0, c1 }
+    }
+
+    pub(crate) fn square<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        let mut other = self.clone();
+        self.mul(cs, &mut other)
+    }
+
+    pub(crate) fn conjugate<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        Self {
+            c0: self.c0.clone(),
+            c1: self.c1.negated(cs),
+        }
+    }
+
+    /// Caller must ensure that the element is not zero, otherwise the circuit is unsatisfiable
+    pub(crate) fn inverse<CS: ConstraintSystem<F>>(&mut self, cs: &mut CS) -> Self {
+        // (a0 + a1 w)^-1 = (a0 - a1 w) / (a0^2 - a1^2 v)
+        let mut a0 = self.c0.clone();
+        let mut a1 = self.c1.clone();
+        let mut a0_squared = self.c0.mul(cs, &mut a0);
+        let mut a1_squared = self.c1.mul(cs, &mut a1);
+        let mut a1_squared_times_v = a1_squared.mul_by_v(cs);
+        let mut norm = a0_squared.sub(cs, &mut a1_squared_times_v);
+        let mut norm_inv = norm.inverse(cs);
+
+        let c0 = self.c0.mul(cs, &mut norm_inv);
+        let mut c1 = self.c1.mul(cs, &mut norm_inv);
+        let c1 = c1.negated(cs);
+
+        Self { c0, c1 }
+    }
+
+    /// Raises the element into p^power, where power is 1 or 2
+    pub(crate) fn frobenius_map<CS: ConstraintSystem<F>>(
+        &mut self,
+        cs: &mut CS,
+        power: usize,
+        params: &Arc<Bls12_381BaseNNFieldParams>,
+    ) -> Self {
+        assert!(power >= 1 && power <= 2);
+
+        // coefficient of v^j in c_i is multiplied by w^(i + 2j)
+        let coeffs = [
+            &mut self.c0.c0,
+            &mut self.c1.c0,
+            &mut self.c0.c1,
+            &mut self.c1.c1,
+            &mut self.c0.c2,
+            &mut self.c1.c2,
+        ];
+        let mut result = Vec::with_capacity(6);
+        for (basis_power, coeff) in coeffs.into_iter().enumerate() {
+            let mut coeff = if power % 2 == 1 {
+                coeff.conjugate(cs)
+            } else {
+                coeff.clone()
+            };
+            if basis_power != 0 {
+                let mut gamma = frobenius_coeff(cs, power, basis_power, params);
+                coeff = coeff.mul(cs, &mut gamma);
+            }
+            result.push(coeff);
+        }
+
+        let [c00, c10, c01, c11, c02, c12]: [Bls12_381Fq2<F>; 6] = result.try_into().unwrap();
+
+        Self {
+            c0: Bls12_381Fq6 {
+                c0: c00,
+                c1: c01,
+                c2: c02,
+            },
+            c1: Bls12_381Fq6 {
+                c0: c10,
+                c1: c11,
+                c2: c12,
+            },
+        }
+    }
+
+    pub(crate) fn equals<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        a: &mut Self,
+        b: &mut Self,
+    ) -> Boolean<F> {
+        let mut flags = Vec::with_capacity(6);
+        for (a, b) in a.coefficients_mut().into_iter().zip(b.coefficients_mut()) {
+            flags.push(Bls12_381Fq2::equals(cs, a, b));
+        }
+
+        Boolean::multi_and(cs, &flags)
+    }
+
+    pub(crate) fn conditionally_select<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        flag: Boolean<F>,
+        a: &Self,
+        b: &Self,
+    ) -> Self {
+        Self {
+            c0: Bls12_381Fq6::conditionally_select(cs, flag, &a.c0, &b.c0),
+            c1: Bls12_381Fq6::conditionally_select(cs, flag, &a.c1, &b.c1),
+        }
+    }
+
+    fn coefficients_mut(&mut self) -> [&mut Bls12_381Fq2<F>; 6] {
+        [
+            &mut self.c0.c0,
+            &mut self.c0.c1,
+            &mut self.c0.c2,
+            &mut self.c1.c0,
+            &mut self.c1.c1,
+            &mut self.c1.c2,
+        ]
+    }
+}
@@ -14,20 +14,34 @@ pub use boojum::ethereum_types;
 pub mod config;
 
 pub mod base_structures;
+pub mod blake2f_round_function;
+pub mod bn254_ecadd;
+pub mod bn254_ecmul;
+pub mod bn254_ecpairing;
 pub mod code_unpacker_sha256;
 pub mod demux_log_queue;
+pub mod ecdsa;
 pub mod ecrecover;
+pub mod ed25519_verify;
+pub mod eip_2537;
 pub mod eip_4844;
 pub mod fsm_input_output;
 pub mod keccak256_round_function;
+pub mod kzg_point_evaluation;
 pub mod linear_hasher;
 pub mod log_sorter;
 pub mod main_vm;
+pub mod modexp;
+pub mod poseidon2_hash;
+pub mod precompile_registry;
 pub mod ram_permutation;
 pub mod recursion;
+pub mod ripemd160_round_function;
 pub mod scheduler;
+pub mod schnorr_verify;
 pub mod secp256r1_verify;
 pub mod sha256_round_function;
+pub mod sha512_round_function;
 pub mod sort_decommittment_requests;
 pub mod storage_application;
 pub mod storage_validity_by_grand_product;
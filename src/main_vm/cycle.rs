@@ -19,6 +19,9 @@ use crate::base_structures::vm_state::saved_context::ExecutionContextRecord;
 use crate::base_structures::vm_state::{ArithmeticFlagsPort, GlobalContext};
 use crate::base_structures::vm_state::{VmLocalState, FULL_SPONGE_QUEUE_STATE_WIDTH};
 use crate::main_vm::opcodes::*;
+use crate::main_vm::profiler::{VmCycleStep, VmCycleStepsProfiler};
+use crate::main_vm::tracer::*;
+use crate::main_vm::variants::*;
 use crate::main_vm::witness_oracle::SynchronizedWitnessOracle;
 use crate::main_vm::witness_oracle::WitnessOracle;
 use boojum::cs::traits::cs::DstBuffer;
@@ -29,12 +32,15 @@ pub(crate) fn vm_cycle<
     CS: ConstraintSystem<F>,
     R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
     W: WitnessOracle<F>,
+    T: VmCycleTracer<F>,
 >(
     cs: &mut CS,
     current_state: VmLocalState<F>,
     witness_oracle: &SynchronizedWitnessOracle<F, W>,
     global_context: &GlobalContext<F>,
     round_function: &R,
+    variant: &VmCircuitVariant,
+    tracer: &mut T,
 ) -> VmLocalState<F>
 where
     [(); <ExecutionContextRecord<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
@@ -44,32 +50,61 @@ where
 {
     // first we create a pre-state
 
-    if crate::config::CIRCUIT_VERSOBE {
-        println!("------------------------------------------------------------");
-        println!("Start of new cycle");
-        // synchronization point
-        let _current_state = current_state.witness_hook(&*cs)().unwrap();
-        // dbg!(_current_state);
-        dbg!(_current_state.pending_exception);
-        dbg!(_current_state.callstack.current_context.saved_context.pc);
-        dbg!(_current_state.flags);
-        dbg!(
-            _current_state
-                .callstack
-                .current_context
-                .saved_context
-                .ergs_remaining
-        );
+    let should_trace =
+        tracer.is_enabled() && <CS::Config as CSConfig>::WitnessConfig::EVALUATE_WITNESS;
+
+    // synchronization point
+    let state_before_cycle = if should_trace {
+        current_state.witness_hook(&*cs)()
+    } else {
+        None
+    };
+
+    if let Some(state) = state_before_cycle.as_ref() {
+        tracer.on_event(VmCycleEvent::CycleStarted {
+            pc: state.callstack.current_context.saved_context.pc,
+            timestamp: state.timestamp,
+            callstack_depth: state.callstack.context_stack_depth,
+            pending_exception: state.pending_exception,
+            flags: state.flags.clone(),
+        });
     }
 
+    let mut profiler = VmCycleStepsProfiler::<F>::start(&*cs);
+
     let (draft_next_state, common_opcode_state, opcode_carry_parts) =
         create_prestate(cs, current_state, witness_oracle, round_function);
+    enforce_opcode_is_supported(
+        cs,
+        &common_opcode_state.decoded_opcode.properties_bits,
+        variant,
+    );
+    profiler.checkpoint(&*cs, VmCycleStep::Prestate);
 
-    if crate::config::CIRCUIT_VERSOBE {
+    if let Some(state) = state_before_cycle.as_ref() {
         // synchronization point
-        let _common_opcode_state = common_opcode_state.witness_hook(&*cs)().unwrap();
-        dbg!(_common_opcode_state.src0);
-        dbg!(_common_opcode_state.src1);
+        let common_opcode_state = common_opcode_state.witness_hook(&*cs)();
+        let did_skip_cycle = opcode_carry_parts.did_skip_cycle.witness_hook(&*cs)();
+        if let (Some(common_opcode_state), Some(did_skip_cycle)) =
+            (common_opcode_state, did_skip_cycle)
+        {
+            let decoded_opcode = &common_opcode_state.decoded_opcode;
+            tracer.on_event(VmCycleEvent::OpcodeDecoded {
+                skipped: did_skip_cycle,
+                executes_pending_exception: state.pending_exception,
+                opcode: trace_opcode(
+                    &decoded_opcode.properties_bits,
+                    &decoded_opcode.src_regs_selectors,
+                    &decoded_opcode.dst_regs_selectors,
+                    decoded_opcode.imm0,
+                    decoded_opcode.imm1,
+                ),
+            });
+            tracer.on_event(VmCycleEvent::SourcesResolved {
+                src0: common_opcode_state.src0,
+                src1: common_opcode_state.src1,
+            });
+        }
     }
 
     // then we apply each opcode and accumulate state diffs
@@ -83,6 +118,7 @@ where
         &opcode_carry_parts,
         &mut diffs_accumulator,
     );
+    profiler.checkpoint(&*cs, VmCycleStep::Nop);
     apply_add_sub(
         cs,
         &draft_next_state,
@@ -90,6 +126,7 @@ where
         &opcode_carry_parts,
         &mut diffs_accumulator,
     );
+    profiler.checkpoint(&*cs, VmCycleStep::AddSub);
     apply_jump(
         cs,
         &draft_next_state,
@@ -97,6 +134,7 @@ where
         &opcode_carry_parts,
         &mut diffs_accumulator,
     );
+    profiler.checkpoint(&*cs, VmCycleStep::Jump);
     apply_binop(
         cs,
         &draft_next_state,
@@ -104,6 +142,7 @@ where
         &opcode_carry_parts,
         &mut diffs_accumulator,
     );
+    profiler.checkpoint(&*cs, VmCycleStep::Binop);
     apply_context(
         cs,
         &draft_next_state,
@@ -111,6 +150,7 @@ where
         &opcode_carry_parts,
         &mut diffs_accumulator,
     );
+    profiler.checkpoint(&*cs, VmCycleStep::Context);
     apply_ptr(
         cs,
         &draft_next_state,
@@ -118,6 +158,7 @@ where
         &opcode_carry_parts,
         &mut diffs_accumulator,
     );
+    profiler.checkpoint(&*cs, VmCycleStep::Ptr);
     apply_log(
         cs,
         &draft_next_state,
@@ -127,6 +168,7 @@ where
         witness_oracle,
         round_function,
     );
+    profiler.checkpoint(&*cs, VmCycleStep::Log);
     apply_calls_and_ret(
         cs,
         &draft_next_state,
@@ -136,21 +178,30 @@ where
         witness_oracle,
         global_context,
         round_function,
+        variant.excludes(ExcludableOpcodeFamily::FarCall),
     );
-    apply_mul_div(
-        cs,
-        &draft_next_state,
-        &common_opcode_state,
-        &opcode_carry_parts,
-        &mut diffs_accumulator,
-    );
-    apply_shifts(
-        cs,
-        &draft_next_state,
-        &common_opcode_state,
-        &opcode_carry_parts,
-        &mut diffs_accumulator,
-    );
+    profiler.checkpoint(&*cs, VmCycleStep::CallsAndRet);
+    // excluded opcodes are never applied, so they can not produce any state diffs
+    if variant.excludes(ExcludableOpcodeFamily::MulDiv) == false {
+        apply_mul_div(
+            cs,
+            &draft_next_state,
+            &common_opcode_state,
+            &opcode_carry_parts,
+            &mut diffs_accumulator,
+        );
+    }
+    profiler.checkpoint(&*cs, VmCycleStep::MulDiv);
+    if variant.excludes(ExcludableOpcodeFamily::Shifts) == false {
+        apply_shifts(
+            cs,
+            &draft_next_state,
+            &common_opcode_state,
+            &opcode_carry_parts,
+            &mut diffs_accumulator,
+        );
+    }
+    profiler.checkpoint(&*cs, VmCycleStep::Shifts);
     apply_uma(
         cs,
         &draft_next_state,
@@ -160,6 +211,15 @@ where
         witness_oracle,
         round_function,
     );
+    profiler.checkpoint(&*cs, VmCycleStep::Uma);
+
+    if state_before_cycle.is_some() {
+        if let Some(fired) = collect_fired_state_diffs(&*cs, &diffs_accumulator) {
+            for diff in fired.into_iter() {
+                tracer.on_event(VmCycleEvent::StateDiff { diff });
+            }
+        }
+    }
 
     // and finally apply state diffs
 
@@ -309,9 +369,26 @@ where
 
     let dst0_update_register = Boolean::multi_or(cs, &[can_update_dst0_as_register_only, t]);
 
-    if crate::config::CIRCUIT_VERSOBE {
-        dbg!(dst0_value.witness_hook(&*cs)().unwrap());
-        dbg!(dst1_value.witness_hook(&*cs)().unwrap());
+    if state_before_cycle.is_some() {
+        let dst0 = dst0.witness_hook(&*cs)();
+        let dst1 = VMRegister {
+            is_pointer: dst1_is_ptr,
+            value: dst1_value,
+        }
+        .witness_hook(&*cs)();
+        let dst0_written_to_memory = perform_dst0_memory_write_update.witness_hook(&*cs)();
+        let dst0_location = opcode_carry_parts.dst0_memory_location.witness_hook(&*cs)();
+        if let (Some(dst0), Some(dst1), Some(dst0_written_to_memory), Some(dst0_location)) =
+            (dst0, dst1, dst0_written_to_memory, dst0_location)
+        {
+            tracer.on_event(VmCycleEvent::DestinationsResolved {
+                dst0,
+                dst1,
+                dst0_written_to_memory,
+                dst0_page: dst0_location.page,
+                dst0_index: dst0_location.index,
+            });
+        }
     }
 
     // We should update registers, and the only "exotic" case is if someone tries to put
@@ -830,14 +907,45 @@ where
 
     // actually enforce_sponges
 
+    if state_before_cycle.is_some() {
+        for sponge in selected_sponges_to_enforce.iter() {
+            if let Some(sponge) = sponge.witness_hook(&*cs)() {
+                if sponge.should_enforce {
+                    tracer.on_event(VmCycleEvent::SpongePushed {
+                        initial_state: sponge.initial_state,
+                        final_state: sponge.final_state,
+                    });
+                }
+            }
+        }
+    }
+
     enforce_sponges(cs, &selected_sponges_to_enforce, round_function);
+    profiler.checkpoint(&*cs, VmCycleStep::StateDiffs);
+    profiler.finish();
 
-    if crate::config::CIRCUIT_VERSOBE {
+    if let Some(state) = state_before_cycle {
         // synchronization point
-        let _wit = new_state.witness_hook(&*cs)().unwrap();
-        dbg!(_wit.registers);
-        // dbg!(_wit.memory_queue_length);
-        println!("End of cycle");
+        if let Some(new_state) = new_state.witness_hook(&*cs)() {
+            let context_before = &state.callstack.current_context.saved_context;
+            let context_after = &new_state.callstack.current_context.saved_context;
+            tracer.on_event(VmCycleEvent::ErgsChanged {
+                before: context_before.ergs_remaining,
+                after: context_after.ergs_remaining,
+            });
+            tracer.on_event(VmCycleEvent::PubdataChanged {
+                revert_counter_before: state.pubdata_revert_counter,
+                revert_counter_after: new_state.pubdata_revert_counter,
+                total_spent_before: context_before.total_pubdata_spent,
+                total_spent_after: context_after.total_pubdata_spent,
+            });
+            tracer.on_event(VmCycleEvent::CycleFinished {
+                pc: context_after.pc,
+                timestamp: new_state.timestamp,
+                pending_exception: new_state.pending_exception,
+                registers: new_state.registers,
+            });
+        }
     }
 
     new_state
@@ -871,14 +979,6 @@ fn may_be_write_memory<
 where
     [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
 {
-    if crate::config::CIRCUIT_VERSOBE {
-        if should_write_dst0.witness_hook(&*cs)().unwrap() {
-            println!("Will write DST0 to memory");
-            dbg!(location.witness_hook(&*cs)().unwrap());
-            dbg!(dst0_value.witness_hook(&*cs)().unwrap());
-        }
-    }
-
     let MemoryLocation { page, index } = location;
     let boolean_true = Boolean::allocated_constant(cs, true);
 
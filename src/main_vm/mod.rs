@@ -21,9 +21,14 @@ pub mod loading;
 pub mod opcode_bitmask;
 pub mod opcodes;
 pub mod pre_state;
+pub mod profiler;
+pub mod recorded_trace;
+pub mod reference_executor;
 pub mod register_input_view;
 pub mod state_diffs;
+pub mod tracer;
 pub mod utils;
+pub mod variants;
 pub mod witness_oracle;
 
 use crate::base_structures::decommit_query::DecommitQuery;
@@ -38,6 +43,8 @@ use crate::fsm_input_output::commit_variable_length_encodable_item;
 use crate::fsm_input_output::ClosedFormInputCompactForm;
 use crate::main_vm::cycle::vm_cycle;
 use crate::main_vm::loading::initial_bootloader_state;
+use crate::main_vm::tracer::VmCycleTracer;
+use crate::main_vm::variants::VmCircuitVariant;
 use crate::main_vm::witness_oracle::{SynchronizedWitnessOracle, WitnessOracle};
 use boojum::algebraic_props::round_function::AlgebraicRoundFunction;
 use boojum::gadgets::traits::allocatable::{CSAllocatableExt, CSPlaceholder};
@@ -49,11 +56,14 @@ pub fn main_vm_entry_point<
     CS: ConstraintSystem<F>,
     R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
     W: WitnessOracle<F>,
+    T: VmCycleTracer<F>,
 >(
     cs: &mut CS,
     witness: VmCircuitWitness<F, W>,
     round_function: &R,
     limit: usize,
+    variant: &VmCircuitVariant,
+    tracer: &mut T,
 ) -> [Num<F>; INPUT_OUTPUT_COMMITMENT_LENGTH]
 where
     [(); <ExecutionContextRecord<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
@@ -98,15 +108,73 @@ where
 
     let synchronized_oracle = SynchronizedWitnessOracle::new(witness_oracle);
 
+    let mut differential_summary =
+        crate::main_vm::reference_executor::differential::DifferentialCheckSummary::default();
+
     // we run `limit` of "normal" cycles
-    for _cycle_idx in 0..limit {
-        state = vm_cycle(
-            cs,
-            state,
-            &synchronized_oracle,
-            &per_block_context,
-            round_function,
-        );
+    for cycle_idx in 0..limit {
+        tracer.start_cycle(cycle_idx);
+        if crate::config::DIFFERENTIAL_VM_CYCLES
+            && <CS::Config as CSConfig>::WitnessConfig::EVALUATE_WITNESS
+        {
+            use crate::main_vm::reference_executor::differential::*;
+            use crate::main_vm::reference_executor::ReferenceExecutionError;
+
+            let (new_state, outcome) = vm_cycle_with_differential_check(
+                cs,
+                state,
+                &synchronized_oracle,
+                &per_block_context,
+                round_function,
+                variant,
+                tracer,
+            );
+            match outcome {
+                DifferentialCycleOutcome::Matches => differential_summary.matched_cycles += 1,
+                DifferentialCycleOutcome::Unsupported(
+                    err @ ReferenceExecutionError::ExcludedByVariant { .. },
+                ) => panic!("VM cycle {} can not be proven: {}", cycle_idx, err),
+                DifferentialCycleOutcome::Unsupported(err) => {
+                    differential_summary.record_unsupported(&err)
+                }
+                DifferentialCycleOutcome::Diverged(divergence) => panic!(
+                    "VM cycle {} diverged from the reference executor: {}",
+                    cycle_idx, divergence
+                ),
+            }
+            state = new_state;
+        } else {
+            state = vm_cycle(
+                cs,
+                state,
+                &synchronized_oracle,
+                &per_block_context,
+                round_function,
+                variant,
+                tracer,
+            );
+        }
+    }
+
+    if crate::config::DIFFERENTIAL_VM_CYCLES {
+        use crate::main_vm::reference_executor::differential::record_differential_check_summary;
+
+        // unsupported cycles are not an error of the circuit, but we should not pretend that they were checked.
+        // Our own tests must only execute what the reference executor mirrors
+        if differential_summary.num_unsupported_cycles() > 0 {
+            assert!(
+                !cfg!(test),
+                "differential check of VM cycles is incomplete. {}",
+                differential_summary
+            );
+            if crate::config::CIRCUIT_VERSOBE {
+                println!(
+                    "WARNING: differential check of VM cycles is incomplete. {}",
+                    differential_summary
+                );
+            }
+        }
+        record_differential_check_summary(&differential_summary);
     }
 
     // here we have too large state to run self-tests, so we will compare it only against the full committments
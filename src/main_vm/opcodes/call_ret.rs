@@ -35,6 +35,7 @@ pub(crate) fn apply_calls_and_ret<
     witness_oracle: &SynchronizedWitnessOracle<F, W>,
     global_context: &GlobalContext<F>,
     round_function: &R,
+    skip_far_call: bool,
 ) where
     [(); <ExecutionContextRecord<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
     [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
@@ -51,18 +52,23 @@ pub(crate) fn apply_calls_and_ret<
         witness_oracle,
     );
 
-    let far_call_data = callstack_candidate_for_far_call(
-        cs,
-        draft_vm_state,
-        common_opcode_state,
-        opcode_carry_parts,
-        witness_oracle,
-        global_context,
-        &common_part,
-        &far_call_abi,
-        &call_ret_forwarding_mode,
-        round_function,
-    );
+    // if VM variant excludes far call we do not need to synthesize it
+    let far_call_data = if skip_far_call {
+        FarCallData::not_applied(cs, draft_vm_state)
+    } else {
+        callstack_candidate_for_far_call(
+            cs,
+            draft_vm_state,
+            common_opcode_state,
+            opcode_carry_parts,
+            witness_oracle,
+            global_context,
+            &common_part,
+            &far_call_abi,
+            &call_ret_forwarding_mode,
+            round_function,
+        )
+    };
 
     let ret_data = callstack_candidate_for_ret(
         cs,
@@ -51,6 +51,47 @@ pub(crate) struct FarCallData<F: SmallField> {
     pub(crate) new_memory_pages_counter: UInt32<F>,
 }
 
+/// Far call produces this number of sponges: code hash read and decommittment request
+pub(crate) const NUM_FAR_CALL_PENDING_SPONGES: usize = 4;
+
+impl<F: SmallField> FarCallData<F> {
+    /// Candidate for the circuits that never apply far call. It keeps the state as is,
+    /// and has the same shape as the real one, so selection between candidates is not affected
+    pub(crate) fn not_applied<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        draft_vm_state: &VmLocalState<F>,
+    ) -> Self {
+        let boolean_false = Boolean::allocated_constant(cs, false);
+        let zero_num = Num::zero(cs);
+        let current_context = draft_vm_state.callstack.current_context;
+
+        let mut pending_sponges = ArrayVec::new();
+        for _ in 0..NUM_FAR_CALL_PENDING_SPONGES {
+            pending_sponges.push((
+                boolean_false,
+                [zero_num; FULL_SPONGE_QUEUE_STATE_WIDTH],
+                [zero_num; FULL_SPONGE_QUEUE_STATE_WIDTH],
+            ));
+        }
+
+        Self {
+            apply_far_call: boolean_false,
+            old_context: current_context.saved_context,
+            new_context: current_context.saved_context,
+            new_decommittment_queue_tail: draft_vm_state.code_decommittment_queue_state,
+            new_decommittment_queue_len: draft_vm_state.code_decommittment_queue_length,
+            new_forward_queue_tail: current_context.log_queue_forward_tail,
+            new_forward_queue_len: current_context.log_queue_forward_part_length,
+            pending_sponges,
+            specific_registers_updates: [None; REGISTERS_COUNT],
+            specific_registers_zeroing: [None; REGISTERS_COUNT],
+            remove_ptr_on_specific_registers: [None; REGISTERS_COUNT],
+            pending_exception: boolean_false,
+            new_memory_pages_counter: draft_vm_state.memory_page_counter,
+        }
+    }
+}
+
 #[derive(Derivative, CSAllocatable, WitnessHookable)]
 #[derivative(Clone, Copy, Debug)]
 
@@ -903,7 +944,7 @@ where
         round_function,
     );
 
-    assert_eq!(all_pending_sponges.len(), 4);
+    assert_eq!(all_pending_sponges.len(), NUM_FAR_CALL_PENDING_SPONGES);
 
     let exception = Boolean::multi_or(cs, &[exception, not_enough_ergs_to_decommit]);
 
@@ -6,7 +6,7 @@ use crate::base_structures::vm_state::ArithmeticFlagsPort;
 use arrayvec::ArrayVec;
 use boojum::gadgets::u256::{decompose_u256_as_u32x8, UInt256};
 
-fn u256_from_limbs<F: SmallField>(limbs: &[F]) -> U256 {
+pub(crate) fn u256_from_limbs<F: SmallField>(limbs: &[F]) -> U256 {
     debug_assert_eq!(limbs.len(), 8);
 
     let mut byte_array = [0u8; 32];
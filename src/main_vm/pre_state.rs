@@ -90,10 +90,6 @@ pub fn create_prestate<
     let pending_exception = current_state.pending_exception;
     let execute_cycle = should_skip_cycle.negated(cs);
 
-    if crate::config::CIRCUIT_VERSOBE {
-        dbg!(execution_has_ended.witness_hook(&*cs)().unwrap());
-    }
-
     // we should even try to perform a read only if we have something to do this cycle
     let should_try_to_read_opcode = execute_cycle.mask_negated(cs, pending_exception);
 
@@ -205,15 +201,6 @@ pub fn create_prestate<
         &opcode,
     );
 
-    if crate::config::CIRCUIT_VERSOBE {
-        if should_skip_cycle.witness_hook(&*cs)().unwrap() {
-            println!("Skipping cycle");
-        }
-        if execute_pending_exception_at_this_cycle.witness_hook(&*cs)().unwrap() {
-            println!("Executing pending exception");
-        }
-    }
-
     // mask if we would be ok with NOPing. This masks a full 8-byte opcode, and not properties bitspread
     // We mask if this cycle is just NOPing till the end of circuit
     let opcode = mask_into_nop(cs, should_skip_cycle, opcode);
@@ -343,11 +330,6 @@ pub fn create_prestate<
     let heap_page = unsafe { stack_page.increment_unchecked(cs) };
     let aux_heap_page = unsafe { heap_page.increment_unchecked(cs) };
 
-    if crate::config::CIRCUIT_VERSOBE {
-        dbg!(decoded_opcode.imm0.witness_hook(&*cs)().unwrap());
-        dbg!(decoded_opcode.imm1.witness_hook(&*cs)().unwrap());
-    }
-
     let (memory_location_for_src0, new_sp_after_src0, should_read_memory_for_src0) =
         resolve_memory_region_and_index_for_source(
             cs,
@@ -0,0 +1,483 @@
+use super::*;
+
+use crate::fsm_input_output::circuit_inputs::main_vm::{VmCircuitInputOutput, VmCircuitWitness};
+use crate::main_vm::recorded_trace::RecordedTraceOracle;
+use crate::main_vm::tracer::NoopVmCycleTracer;
+use crate::main_vm::variants::VmCircuitVariant;
+use boojum::algebraic_props::poseidon2_parameters::{
+    Poseidon2GoldilocksExternalMatrix, Poseidon2GoldilocksInnerMatrix,
+};
+use boojum::cs::cs_builder::*;
+use boojum::cs::cs_builder_reference::CsReferenceImplementationBuilder;
+use boojum::cs::gates::*;
+use boojum::cs::implementations::reference_cs::CSReferenceImplementation;
+use boojum::cs::traits::gate::GatePlacementStrategy;
+use boojum::cs::*;
+use boojum::field::goldilocks::GoldilocksField;
+use boojum::gadgets::tables::binop_table::{create_binop_table, BinopTable};
+use boojum::gadgets::tables::*;
+use boojum::gadgets::traits::allocatable::CSAllocatable;
+use boojum::implementations::poseidon2::Poseidon2Goldilocks;
+
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Amount of circuit resources allocated by some piece of synthesis
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub struct ConstraintCost {
+    /// General purpose rows. Gates and lookups placed into specialized columns are not visible here,
+    /// so the profiling constraint system places everything into general purpose columns
+    pub rows: usize,
+    pub variables: usize,
+}
+
+impl ConstraintCost {
+    pub fn of<F: SmallField, CS: ConstraintSystem<F>>(cs: &CS) -> Self {
+        Self {
+            rows: cs.next_available_row(),
+            variables: cs.next_available_place_idx() as usize,
+        }
+    }
+
+    fn delta_since(&self, earlier: &Self) -> Self {
+        Self {
+            rows: self.rows - earlier.rows,
+            variables: self.variables - earlier.variables,
+        }
+    }
+
+    fn accumulate(&mut self, other: &Self) {
+        self.rows += other.rows;
+        self.variables += other.variables;
+    }
+}
+
+/// Parts of `vm_cycle` that are profiled separately. `StateDiffs` covers everything after
+/// the opcodes are applied: selection of the updates, memory write for dst0, relations and sponges
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VmCycleStep {
+    Prestate,
+    Nop,
+    AddSub,
+    Jump,
+    Binop,
+    Context,
+    Ptr,
+    Log,
+    CallsAndRet,
+    MulDiv,
+    Shifts,
+    Uma,
+    StateDiffs,
+}
+
+impl VmCycleStep {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Prestate => "create_prestate",
+            Self::Nop => "nop",
+            Self::AddSub => "add_sub",
+            Self::Jump => "jump",
+            Self::Binop => "binop",
+            Self::Context => "context",
+            Self::Ptr => "ptr",
+            Self::Log => "log",
+            Self::CallsAndRet => "calls_and_ret",
+            Self::MulDiv => "mul_div",
+            Self::Shifts => "shifts",
+            Self::Uma => "uma",
+            Self::StateDiffs => "state_diffs",
+        }
+    }
+}
+
+/// Constraint cost of the synthesized VM cycles, accumulated per step
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub struct VmCycleProfile {
+    pub cycles: usize,
+    pub steps: BTreeMap<VmCycleStep, ConstraintCost>,
+}
+
+impl VmCycleProfile {
+    pub fn record(&mut self, step: VmCycleStep, cost: ConstraintCost) {
+        match self.steps.get_mut(&step) {
+            Some(existing) => existing.accumulate(&cost),
+            None => {
+                self.steps.insert(step, cost);
+            }
+        }
+    }
+
+    pub fn total(&self) -> ConstraintCost {
+        let mut total = ConstraintCost::default();
+        for cost in self.steps.values() {
+            total.accumulate(cost);
+        }
+
+        total
+    }
+}
+
+impl fmt::Display for VmCycleProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cycles = std::cmp::max(self.cycles, 1);
+        let total = self.total();
+
+        writeln!(
+            f,
+            "{:<16} {:>12} {:>8} {:>16}",
+            "step", "rows/cycle", "share", "variables/cycle"
+        )?;
+        for (step, cost) in self.steps.iter() {
+            let share = if total.rows == 0 {
+                0f64
+            } else {
+                100f64 * (cost.rows as f64) / (total.rows as f64)
+            };
+            writeln!(
+                f,
+                "{:<16} {:>12} {:>7.2}% {:>16}",
+                step.name(),
+                cost.rows / cycles,
+                share,
+                cost.variables / cycles,
+            )?;
+        }
+        write!(
+            f,
+            "{:<16} {:>12} {:>7.2}% {:>16} ({} cycles)",
+            "total",
+            total.rows / cycles,
+            100f64,
+            total.variables / cycles,
+            self.cycles,
+        )
+    }
+}
+
+thread_local! {
+    static VM_CYCLE_PROFILE: RefCell<VmCycleProfile> = RefCell::new(VmCycleProfile::default());
+    static PROFILING_FORCED: Cell<bool> = Cell::new(false);
+}
+
+/// Returns the profile of all the cycles synthesized by this thread since the last call, and resets it.
+/// Profile is only collected in the `vm_cycle_profiling` build, or inside of `profile_vm_cycles`
+pub fn take_vm_cycle_profile() -> VmCycleProfile {
+    VM_CYCLE_PROFILE.with(|el| std::mem::take(&mut *el.borrow_mut()))
+}
+
+fn profiling_enabled() -> bool {
+    crate::config::VM_CYCLE_PROFILING || PROFILING_FORCED.with(|el| el.get())
+}
+
+/// Constraint system with the `reference_vm_geometry` and all the VM tables, where every gate and lookup
+/// is placed into general purpose columns, so the number of rows accounts for all of them
+pub fn create_vm_cycle_profiling_cs(
+    max_trace_len: usize,
+) -> CSReferenceImplementation<
+    GoldilocksField,
+    GoldilocksField,
+    DevCSConfig,
+    impl GateConfigurationHolder<GoldilocksField>,
+    impl StaticToolboxHolder,
+> {
+    type F = GoldilocksField;
+
+    let max_variables = 1 << 26;
+
+    fn configure<
+        T: CsBuilderImpl<F, T>,
+        GC: GateConfigurationHolder<F>,
+        TB: StaticToolboxHolder,
+    >(
+        builder: CsBuilder<T, F, GC, TB>,
+    ) -> CsBuilder<T, F, impl GateConfigurationHolder<F>, impl StaticToolboxHolder> {
+        let builder = builder.allow_lookup(LookupParameters::TableIdAsConstant {
+            width: 3,
+            share_table_id: true,
+        });
+
+        let builder = ConstantsAllocatorGate::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+        );
+        let builder = BooleanConstraintGate::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+        );
+        let builder = U8x4FMAGate::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+        );
+        let builder = ZeroCheckGate::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+            false,
+        );
+        let builder = FmaGateInBaseFieldWithoutConstant::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+        );
+        let builder = UIntXAddGate::<32>::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+        );
+        let builder = UIntXAddGate::<16>::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+        );
+        let builder = UIntXAddGate::<8>::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+        );
+        let builder = DotProductGate::<4>::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+        );
+        let builder = SelectionGate::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+        );
+        let builder = ParallelSelectionGate::<4>::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+        );
+        let builder = PublicInputGate::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+        );
+        let builder = ReductionGate::<_, 4>::configure_builder(
+            builder,
+            GatePlacementStrategy::UseGeneralPurposeColumns,
+        );
+        let builder =
+            MatrixMultiplicationGate::<F, 12, Poseidon2GoldilocksExternalMatrix>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+        let builder =
+            MatrixMultiplicationGate::<F, 12, Poseidon2GoldilocksInnerMatrix>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+        let builder =
+            NopGate::configure_builder(builder, GatePlacementStrategy::UseGeneralPurposeColumns);
+
+        builder
+    }
+
+    let builder_impl = CsReferenceImplementationBuilder::<F, F, DevCSConfig>::new(
+        crate::main_vm::cycle::reference_vm_geometry(),
+        max_trace_len,
+    );
+    let builder = new_builder::<_, F>(builder_impl);
+
+    let builder = configure(builder);
+    let mut owned_cs = builder.build(max_variables);
+
+    // add tables
+    let table = create_xor8_table();
+    owned_cs.add_lookup_table::<Xor8Table, 3>(table);
+
+    let table = create_and8_table();
+    owned_cs.add_lookup_table::<And8Table, 3>(table);
+
+    let table = create_byte_split_table::<F, 1>();
+    owned_cs.add_lookup_table::<ByteSplitTable<1>, 3>(table);
+
+    let table = create_byte_split_table::<F, 2>();
+    owned_cs.add_lookup_table::<ByteSplitTable<2>, 3>(table);
+
+    let table = create_byte_split_table::<F, 3>();
+    owned_cs.add_lookup_table::<ByteSplitTable<3>, 3>(table);
+
+    let table = create_byte_split_table::<F, 4>();
+    owned_cs.add_lookup_table::<ByteSplitTable<4>, 3>(table);
+
+    let table = create_binop_table();
+    owned_cs.add_lookup_table::<BinopTable, 3>(table);
+
+    use crate::tables::*;
+
+    let table = create_subpc_bitmask_table::<F>();
+    owned_cs.add_lookup_table::<VMSubPCToBitmaskTable, 3>(table);
+
+    let table = create_opcodes_decoding_and_pricing_table::<F>();
+    owned_cs.add_lookup_table::<VMOpcodeDecodingTable, 3>(table);
+
+    let table = create_conditionals_resolution_table::<F>();
+    owned_cs.add_lookup_table::<VMConditionalResolutionTable, 3>(table);
+
+    let table = create_integer_to_bitmask_table::<F>(4, REG_IDX_TO_BITMASK_TABLE_NAME);
+    owned_cs.add_lookup_table::<RegisterIndexToBitmaskTable, 3>(table);
+
+    let table = create_shift_to_num_converter_table::<F>();
+    owned_cs.add_lookup_table::<BitshiftTable, 3>(table);
+
+    let table = create_integer_set_ith_bit_table::<F>(5, UMA_SHIFT_TO_BITMASK_TABLE_NAME);
+    owned_cs.add_lookup_table::<UMAShiftToBitmaskTable, 3>(table);
+
+    let table = create_uma_ptr_read_bitmask_table::<F>();
+    owned_cs.add_lookup_table::<UMAPtrReadCleanupTable, 3>(table);
+
+    let table = create_call_costs_and_stipends_table::<F>();
+    owned_cs.add_lookup_table::<CallCostsAndStipendsTable, 3>(table);
+
+    let table = create_pubdata_cost_validity_table::<F>();
+    owned_cs.add_lookup_table::<PubdataCostValidityTable, 3>(table);
+
+    let table = create_test_bit_table::<F>();
+    owned_cs.add_lookup_table::<TestBitTable, 3>(table);
+
+    owned_cs
+}
+
+/// Synthesizes `num_cycles` VM cycles of the given variant on the profiling constraint system and
+/// returns their profile. The circuit is the same for every cycle, so cycles are run from the
+/// placeholder state and skip execution. Works in any build
+pub fn profile_vm_cycles(num_cycles: usize, variant: &VmCircuitVariant) -> VmCycleProfile {
+    type F = GoldilocksField;
+
+    let mut cs = create_vm_cycle_profiling_cs(1 << 24);
+    let witness = VmCircuitWitness {
+        closed_form_input: VmCircuitInputOutput::<F>::placeholder_witness(),
+        witness_oracle: RecordedTraceOracle::<F>::default(),
+    };
+
+    // keep whatever was collected by the caller
+    let previous_profile = take_vm_cycle_profile();
+    let was_forced = PROFILING_FORCED.with(|el| el.replace(true));
+    let _ = super::main_vm_entry_point(
+        &mut cs,
+        witness,
+        &Poseidon2Goldilocks,
+        num_cycles,
+        variant,
+        &mut NoopVmCycleTracer,
+    );
+    PROFILING_FORCED.with(|el| el.set(was_forced));
+    let profile = take_vm_cycle_profile();
+    VM_CYCLE_PROFILE.with(|el| *el.borrow_mut() = previous_profile);
+
+    profile
+}
+
+/// Measures the cost of consecutive steps of a single cycle. Does nothing if profiling is disabled
+pub(crate) struct VmCycleStepsProfiler<F: SmallField> {
+    last_checkpoint: Option<ConstraintCost>,
+    _marker: std::marker::PhantomData<F>,
+}
+
+impl<F: SmallField> VmCycleStepsProfiler<F> {
+    pub(crate) fn start<CS: ConstraintSystem<F>>(cs: &CS) -> Self {
+        let last_checkpoint = if profiling_enabled() {
+            Some(ConstraintCost::of::<F, CS>(cs))
+        } else {
+            None
+        };
+
+        Self {
+            last_checkpoint,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Attributes everything allocated since the previous checkpoint to the `step`
+    pub(crate) fn checkpoint<CS: ConstraintSystem<F>>(&mut self, cs: &CS, step: VmCycleStep) {
+        if let Some(last_checkpoint) = self.last_checkpoint.as_mut() {
+            let now = ConstraintCost::of::<F, CS>(cs);
+            let cost = now.delta_since(last_checkpoint);
+            *last_checkpoint = now;
+            VM_CYCLE_PROFILE.with(|el| el.borrow_mut().record(step, cost));
+        }
+    }
+
+    pub(crate) fn finish(self) {
+        if self.last_checkpoint.is_some() {
+            VM_CYCLE_PROFILE.with(|el| el.borrow_mut().cycles += 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::main_vm::variants::FULL_VM_VARIANT;
+
+    #[test]
+    fn test_vm_cycle_profile_table() {
+        let mut profile = VmCycleProfile::default();
+        profile.cycles = 2;
+        profile.record(
+            VmCycleStep::Prestate,
+            ConstraintCost {
+                rows: 100,
+                variables: 1000,
+            },
+        );
+        profile.record(
+            VmCycleStep::AddSub,
+            ConstraintCost {
+                rows: 60,
+                variables: 500,
+            },
+        );
+        profile.record(
+            VmCycleStep::AddSub,
+            ConstraintCost {
+                rows: 40,
+                variables: 500,
+            },
+        );
+
+        assert_eq!(
+            profile.total(),
+            ConstraintCost {
+                rows: 200,
+                variables: 2000,
+            }
+        );
+
+        let table = profile.to_string();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].starts_with("create_prestate"));
+        assert!(lines[1].contains("50.00%"));
+        assert!(lines[2].starts_with("add_sub"));
+        assert!(lines[3].ends_with("(2 cycles)"));
+    }
+
+    #[test]
+    fn test_profile_vm_cycles() {
+        let profiles: Vec<_> = (1..=3)
+            .map(|num_cycles| profile_vm_cycles(num_cycles, &FULL_VM_VARIANT))
+            .collect();
+        assert_eq!(profiles[2].cycles, 3);
+        assert_eq!(profiles[2].steps.len(), 13);
+
+        // add_sub allocates both the sum and the difference of two 8-limb words
+        let add_sub = profiles[0].steps[&VmCycleStep::AddSub];
+        assert!(add_sub.rows > 0);
+        assert!(add_sub.variables >= 2 * 8);
+
+        // constants are only allocated by the first cycle, and then every cycle is the same
+        let added_by_cycle = |idx: usize, step: Option<VmCycleStep>| {
+            let cost = |profile: &VmCycleProfile| match step {
+                Some(step) => profile.steps[&step].variables,
+                None => profile.total().variables,
+            };
+            cost(&profiles[idx]) - cost(&profiles[idx - 1])
+        };
+        assert_eq!(
+            added_by_cycle(1, Some(VmCycleStep::AddSub)),
+            added_by_cycle(2, Some(VmCycleStep::AddSub))
+        );
+        assert!(added_by_cycle(1, Some(VmCycleStep::AddSub)) > 0);
+        assert_eq!(added_by_cycle(1, None), added_by_cycle(2, None));
+
+        // profile of the caller is not affected
+        assert_eq!(take_vm_cycle_profile().cycles, 0);
+    }
+}
@@ -0,0 +1,645 @@
+use crate::ethereum_types::U256;
+
+use crate::base_structures::decommit_query::DecommitQueryWitness;
+use crate::base_structures::log_query::LogQueryWitness;
+use crate::base_structures::memory_query::MemoryQueryWitness;
+use crate::base_structures::vm_state::saved_context::ExecutionContextRecordWitness;
+use crate::main_vm::witness_oracle::{MemoryWitness, WitnessOracle};
+use boojum::field::SmallField;
+use boojum::gadgets::traits::allocatable::CSAllocatable;
+
+use super::*;
+
+use std::fmt;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// Every recorded trace file starts with these bytes
+pub const RECORDED_TRACE_MAGIC: [u8; 8] = *b"ZKVMTRC\0";
+
+/// Version of the on-disk layout of `RecordedTrace`. Must be bumped on any change of it
+pub const RECORDED_TRACE_FORMAT_VERSION: u32 = 1;
+
+/// Single witness value together with the parameters of the request that consumed it,
+/// so we can detect that the circuit asks for something else than was recorded
+#[derive(Derivative, serde::Serialize, serde::Deserialize)]
+#[derivative(Clone, Debug)]
+pub struct RecordedWitness<K, V> {
+    pub key: K,
+    pub value: V,
+}
+
+/// Witness that the VM circuit requests from the oracle, in the order of requests.
+/// Only the requests with `execute == true` are recorded
+#[derive(Derivative, serde::Serialize, serde::Deserialize)]
+#[derivative(Clone, Default(bound = ""))]
+#[serde(bound = "")]
+pub struct RecordedTrace<F: SmallField> {
+    /// Keyed by (timestamp, memory page, index)
+    pub memory_reads: Vec<RecordedWitness<(u32, u32, u32), MemoryWitness>>,
+    /// Keyed by (timestamp, storage key). Only the storage accesses are recorded
+    pub storage_reads: Vec<RecordedWitness<(u32, U256), U256>>,
+    /// Keyed by (timestamp, storage key)
+    pub cold_warm_refunds: Vec<RecordedWitness<(u32, U256), u32>>,
+    /// Keyed by (timestamp, storage key)
+    pub pubdata_costs: Vec<RecordedWitness<(u32, U256), u32>>,
+    /// Keyed by (timestamp, storage key)
+    pub rollback_queue_heads: Vec<RecordedWitness<(u32, U256), [F; 4]>>,
+    /// Keyed by the timestamp of the call
+    pub rollback_queue_tails_for_calls: Vec<RecordedWitness<u32, [F; 4]>>,
+    /// Keyed by the callstack depth before the return
+    pub callstack_pops: Vec<RecordedWitness<u32, (ExecutionContextRecordWitness<F>, [F; 12])>>,
+    /// Keyed by (timestamp, code hash)
+    pub decommit_pages: Vec<RecordedWitness<(u32, U256), u32>>,
+}
+
+impl<F: SmallField> RecordedTrace<F> {
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self, RecordedTraceError> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != RECORDED_TRACE_MAGIC {
+            return Err(RecordedTraceError::InvalidMagic(magic));
+        }
+
+        let mut version = [0u8; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != RECORDED_TRACE_FORMAT_VERSION {
+            return Err(RecordedTraceError::UnsupportedVersion {
+                found: version,
+                expected: RECORDED_TRACE_FORMAT_VERSION,
+            });
+        }
+
+        let trace = bincode::deserialize_from(reader)?;
+
+        Ok(trace)
+    }
+
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), RecordedTraceError> {
+        writer.write_all(&RECORDED_TRACE_MAGIC)?;
+        writer.write_all(&RECORDED_TRACE_FORMAT_VERSION.to_le_bytes())?;
+        bincode::serialize_into(&mut writer, self)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, RecordedTraceError> {
+        let file = std::fs::File::open(path)?;
+        Self::read_from(std::io::BufReader::new(file))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), RecordedTraceError> {
+        let file = std::fs::File::create(path)?;
+        self.write_to(std::io::BufWriter::new(file))
+    }
+}
+
+pub const NUM_TRACE_STREAMS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum TraceStream {
+    MemoryReads,
+    StorageReads,
+    ColdWarmRefunds,
+    PubdataCosts,
+    RollbackQueueHeads,
+    RollbackQueueTailsForCalls,
+    CallstackPops,
+    DecommitPages,
+}
+
+impl fmt::Display for TraceStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::MemoryReads => "memory reads",
+            Self::StorageReads => "storage reads",
+            Self::ColdWarmRefunds => "cold/warm refunds",
+            Self::PubdataCosts => "pubdata costs",
+            Self::RollbackQueueHeads => "rollback queue heads",
+            Self::RollbackQueueTailsForCalls => "rollback queue tails for calls",
+            Self::CallstackPops => "callstack pops",
+            Self::DecommitPages => "decommit pages",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug)]
+pub enum RecordedTraceError {
+    Io(std::io::Error),
+    Encoding(bincode::Error),
+    InvalidMagic([u8; 8]),
+    UnsupportedVersion {
+        found: u32,
+        expected: u32,
+    },
+    /// Circuit requested more witness than was recorded
+    Exhausted {
+        stream: TraceStream,
+        position: usize,
+        requested: String,
+    },
+    /// Circuit requested a witness for different parameters than were recorded
+    Desync {
+        stream: TraceStream,
+        position: usize,
+        recorded: String,
+        requested: String,
+    },
+    /// Circuit finished without consuming all the recorded witness
+    NotFullyConsumed {
+        stream: TraceStream,
+        consumed: usize,
+        recorded: usize,
+    },
+}
+
+impl fmt::Display for RecordedTraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to access recorded trace: {}", e),
+            Self::Encoding(e) => write!(f, "failed to decode recorded trace: {}", e),
+            Self::InvalidMagic(magic) => {
+                write!(
+                    f,
+                    "not a recorded trace file, magic is 0x{}",
+                    hex::encode(magic)
+                )
+            }
+            Self::UnsupportedVersion { found, expected } => write!(
+                f,
+                "recorded trace has format version {}, but only version {} is supported",
+                found, expected
+            ),
+            Self::Exhausted {
+                stream,
+                position,
+                requested,
+            } => write!(
+                f,
+                "{} are exhausted: circuit requested witness #{} for {}, but only {} were recorded",
+                stream, position, requested, position
+            ),
+            Self::Desync {
+                stream,
+                position,
+                recorded,
+                requested,
+            } => write!(
+                f,
+                "{} desynchronized at witness #{}: recorded for {}, but circuit requested {}",
+                stream, position, recorded, requested
+            ),
+            Self::NotFullyConsumed {
+                stream,
+                consumed,
+                recorded,
+            } => write!(
+                f,
+                "{} are not fully consumed: circuit used {} out of {} recorded",
+                stream, consumed, recorded
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RecordedTraceError {}
+
+impl From<std::io::Error> for RecordedTraceError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<bincode::Error> for RecordedTraceError {
+    fn from(value: bincode::Error) -> Self {
+        Self::Encoding(value)
+    }
+}
+
+fn next_recorded_witness<'a, K: PartialEq + fmt::Debug, V>(
+    records: &'a [RecordedWitness<K, V>],
+    cursor: &mut usize,
+    stream: TraceStream,
+    requested: K,
+) -> Result<&'a V, RecordedTraceError> {
+    let position = *cursor;
+    let Some(record) = records.get(position) else {
+        return Err(RecordedTraceError::Exhausted {
+            stream,
+            position,
+            requested: format!("{:?}", requested),
+        });
+    };
+    if record.key != requested {
+        return Err(RecordedTraceError::Desync {
+            stream,
+            position,
+            recorded: format!("{:?}", record.key),
+            requested: format!("{:?}", requested),
+        });
+    }
+    *cursor += 1;
+
+    Ok(&record.value)
+}
+
+/// Witness oracle that replays `RecordedTrace`, e.g. to reproduce a failing VM instance without
+/// running the witness generator. It panics with `RecordedTraceError` on the first request that doesn't
+/// match the trace, as otherwise we would only get an unsatisfied circuit
+#[derive(Derivative, serde::Serialize, serde::Deserialize)]
+#[derivative(Clone, Default(bound = ""))]
+#[serde(bound = "")]
+pub struct RecordedTraceOracle<F: SmallField> {
+    pub trace: RecordedTrace<F>,
+    pub cursors: [usize; NUM_TRACE_STREAMS],
+}
+
+impl<F: SmallField> RecordedTraceOracle<F> {
+    pub fn new(trace: RecordedTrace<F>) -> Self {
+        Self {
+            trace,
+            cursors: [0; NUM_TRACE_STREAMS],
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, RecordedTraceError> {
+        RecordedTrace::load(path).map(Self::new)
+    }
+
+    fn streams_len(&self) -> [(TraceStream, usize); NUM_TRACE_STREAMS] {
+        let trace = &self.trace;
+        [
+            (TraceStream::MemoryReads, trace.memory_reads.len()),
+            (TraceStream::StorageReads, trace.storage_reads.len()),
+            (TraceStream::ColdWarmRefunds, trace.cold_warm_refunds.len()),
+            (TraceStream::PubdataCosts, trace.pubdata_costs.len()),
+            (
+                TraceStream::RollbackQueueHeads,
+                trace.rollback_queue_heads.len(),
+            ),
+            (
+                TraceStream::RollbackQueueTailsForCalls,
+                trace.rollback_queue_tails_for_calls.len(),
+            ),
+            (TraceStream::CallstackPops, trace.callstack_pops.len()),
+            (TraceStream::DecommitPages, trace.decommit_pages.len()),
+        ]
+    }
+
+    pub fn check_fully_consumed(&self) -> Result<(), RecordedTraceError> {
+        for ((stream, recorded), consumed) in
+            self.streams_len().into_iter().zip(self.cursors.into_iter())
+        {
+            if consumed != recorded {
+                return Err(RecordedTraceError::NotFullyConsumed {
+                    stream,
+                    consumed,
+                    recorded,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn try_get_memory_witness_for_read(
+        &mut self,
+        timestamp: u32,
+        memory_page: u32,
+        index: u32,
+    ) -> Result<MemoryWitness, RecordedTraceError> {
+        next_recorded_witness(
+            &self.trace.memory_reads,
+            &mut self.cursors[TraceStream::MemoryReads as usize],
+            TraceStream::MemoryReads,
+            (timestamp, memory_page, index),
+        )
+        .cloned()
+    }
+
+    pub fn try_get_storage_read_witness(
+        &mut self,
+        key: &LogQueryWitness<F>,
+    ) -> Result<U256, RecordedTraceError> {
+        next_recorded_witness(
+            &self.trace.storage_reads,
+            &mut self.cursors[TraceStream::StorageReads as usize],
+            TraceStream::StorageReads,
+            (key.timestamp, key.key),
+        )
+        .copied()
+    }
+
+    pub fn try_get_cold_warm_refund(
+        &mut self,
+        query: &LogQueryWitness<F>,
+    ) -> Result<u32, RecordedTraceError> {
+        next_recorded_witness(
+            &self.trace.cold_warm_refunds,
+            &mut self.cursors[TraceStream::ColdWarmRefunds as usize],
+            TraceStream::ColdWarmRefunds,
+            (query.timestamp, query.key),
+        )
+        .copied()
+    }
+
+    pub fn try_get_pubdata_cost_for_query(
+        &mut self,
+        query: &LogQueryWitness<F>,
+    ) -> Result<u32, RecordedTraceError> {
+        next_recorded_witness(
+            &self.trace.pubdata_costs,
+            &mut self.cursors[TraceStream::PubdataCosts as usize],
+            TraceStream::PubdataCosts,
+            (query.timestamp, query.key),
+        )
+        .copied()
+    }
+
+    pub fn try_get_rollback_queue_witness(
+        &mut self,
+        key: &LogQueryWitness<F>,
+    ) -> Result<[F; 4], RecordedTraceError> {
+        next_recorded_witness(
+            &self.trace.rollback_queue_heads,
+            &mut self.cursors[TraceStream::RollbackQueueHeads as usize],
+            TraceStream::RollbackQueueHeads,
+            (key.timestamp, key.key),
+        )
+        .copied()
+    }
+
+    pub fn try_get_rollback_queue_tail_witness_for_call(
+        &mut self,
+        timestamp: u32,
+    ) -> Result<[F; 4], RecordedTraceError> {
+        next_recorded_witness(
+            &self.trace.rollback_queue_tails_for_calls,
+            &mut self.cursors[TraceStream::RollbackQueueTailsForCalls as usize],
+            TraceStream::RollbackQueueTailsForCalls,
+            timestamp,
+        )
+        .copied()
+    }
+
+    pub fn try_get_callstack_witness(
+        &mut self,
+        depth: u32,
+    ) -> Result<(ExecutionContextRecordWitness<F>, [F; 12]), RecordedTraceError> {
+        next_recorded_witness(
+            &self.trace.callstack_pops,
+            &mut self.cursors[TraceStream::CallstackPops as usize],
+            TraceStream::CallstackPops,
+            depth,
+        )
+        .cloned()
+    }
+
+    pub fn try_get_decommittment_request_suggested_page(
+        &mut self,
+        request: &DecommitQueryWitness<F>,
+    ) -> Result<u32, RecordedTraceError> {
+        next_recorded_witness(
+            &self.trace.decommit_pages,
+            &mut self.cursors[TraceStream::DecommitPages as usize],
+            TraceStream::DecommitPages,
+            (request.timestamp, request.code_hash),
+        )
+        .copied()
+    }
+}
+
+fn unwrap_recorded<T>(result: Result<T, RecordedTraceError>) -> T {
+    result.unwrap_or_else(|e| panic!("{}", e))
+}
+
+impl<F: SmallField> WitnessOracle<F> for RecordedTraceOracle<F> {
+    fn get_memory_witness_for_read(
+        &mut self,
+        timestamp: u32,
+        memory_page: u32,
+        index: u32,
+        execute: bool,
+    ) -> MemoryWitness {
+        if execute {
+            unwrap_recorded(self.try_get_memory_witness_for_read(timestamp, memory_page, index))
+        } else {
+            MemoryWitness::default()
+        }
+    }
+    fn push_memory_witness(&mut self, _memory_query: &MemoryQueryWitness<F>, _execute: bool) {}
+    fn get_storage_read_witness(
+        &mut self,
+        key: &LogQueryWitness<F>,
+        needs_witness: bool,
+        execute: bool,
+    ) -> U256 {
+        if execute && needs_witness {
+            unwrap_recorded(self.try_get_storage_read_witness(key))
+        } else {
+            U256::zero()
+        }
+    }
+    fn get_cold_warm_refund(
+        &mut self,
+        query: &LogQueryWitness<F>,
+        _is_write: bool,
+        execute: bool,
+    ) -> u32 {
+        if execute {
+            unwrap_recorded(self.try_get_cold_warm_refund(query))
+        } else {
+            0
+        }
+    }
+    fn get_pubdata_cost_for_query(
+        &mut self,
+        query: &LogQueryWitness<F>,
+        _is_write: bool,
+        execute: bool,
+    ) -> u32 {
+        if execute {
+            unwrap_recorded(self.try_get_pubdata_cost_for_query(query))
+        } else {
+            0
+        }
+    }
+    fn push_storage_witness(&mut self, _key: &LogQueryWitness<F>, _execute: bool) {}
+    fn get_rollback_queue_witness(&mut self, key: &LogQueryWitness<F>, execute: bool) -> [F; 4] {
+        if execute {
+            unwrap_recorded(self.try_get_rollback_queue_witness(key))
+        } else {
+            [F::ZERO; 4]
+        }
+    }
+    fn get_rollback_queue_tail_witness_for_call(
+        &mut self,
+        timestamp: u32,
+        execute: bool,
+    ) -> [F; 4] {
+        if execute {
+            unwrap_recorded(self.try_get_rollback_queue_tail_witness_for_call(timestamp))
+        } else {
+            [F::ZERO; 4]
+        }
+    }
+    // the recorded pops already contain the saved frames, so there is nothing to track on pushes
+    fn report_new_callstack_frame(
+        &mut self,
+        _new_record: &ExecutionContextRecordWitness<F>,
+        _new_depth: u32,
+        _is_call: bool,
+        _execute: bool,
+    ) {
+    }
+    fn push_callstack_witness(
+        &mut self,
+        _current_record: &ExecutionContextRecordWitness<F>,
+        _current_depth: u32,
+        _execute: bool,
+    ) {
+    }
+    fn get_callstack_witness(
+        &mut self,
+        execute: bool,
+        depth: u32,
+    ) -> (ExecutionContextRecordWitness<F>, [F; 12]) {
+        if execute {
+            unwrap_recorded(self.try_get_callstack_witness(depth))
+        } else {
+            (ExecutionContextRecord::placeholder_witness(), [F::ZERO; 12])
+        }
+    }
+    fn get_decommittment_request_suggested_page(
+        &mut self,
+        request: &DecommitQueryWitness<F>,
+        execute: bool,
+    ) -> u32 {
+        if execute {
+            unwrap_recorded(self.try_get_decommittment_request_suggested_page(request))
+        } else {
+            0
+        }
+    }
+    fn at_completion(self) {
+        unwrap_recorded(self.check_fully_consumed());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use boojum::field::goldilocks::GoldilocksField;
+
+    type F = GoldilocksField;
+
+    fn test_trace() -> RecordedTrace<F> {
+        let mut trace = RecordedTrace::<F>::default();
+        trace.memory_reads = vec![
+            RecordedWitness {
+                key: (4, 1, 0),
+                value: MemoryWitness {
+                    value: U256::from(42u64),
+                    is_ptr: false,
+                },
+            },
+            RecordedWitness {
+                key: (8, 1, 1),
+                value: MemoryWitness {
+                    value: U256::from(43u64),
+                    is_ptr: true,
+                },
+            },
+        ];
+        trace.rollback_queue_tails_for_calls = vec![RecordedWitness {
+            key: 12,
+            value: [F::from_u64_unchecked(7); 4],
+        }];
+
+        trace
+    }
+
+    #[test]
+    fn test_recorded_trace_roundtrip() {
+        let mut buffer = vec![];
+        test_trace().write_to(&mut buffer).unwrap();
+        let trace = RecordedTrace::<F>::read_from(&buffer[..]).unwrap();
+
+        let mut oracle = RecordedTraceOracle::new(trace);
+        // requests that are not executed don't consume the trace
+        let witness = oracle.get_memory_witness_for_read(4, 1, 0, false);
+        assert_eq!(witness.value, U256::zero());
+
+        let witness = oracle.get_memory_witness_for_read(4, 1, 0, true);
+        assert_eq!(witness.value, U256::from(42u64));
+        assert!(!witness.is_ptr);
+        let witness = oracle.get_memory_witness_for_read(8, 1, 1, true);
+        assert_eq!(witness.value, U256::from(43u64));
+        assert!(witness.is_ptr);
+        assert!(matches!(
+            oracle.check_fully_consumed(),
+            Err(RecordedTraceError::NotFullyConsumed {
+                stream: TraceStream::RollbackQueueTailsForCalls,
+                consumed: 0,
+                recorded: 1,
+            })
+        ));
+
+        let tail = oracle.get_rollback_queue_tail_witness_for_call(12, true);
+        assert_eq!(tail, [F::from_u64_unchecked(7); 4]);
+        oracle.at_completion();
+    }
+
+    #[test]
+    fn test_recorded_trace_rejects_other_versions() {
+        let mut buffer = vec![];
+        test_trace().write_to(&mut buffer).unwrap();
+        buffer[RECORDED_TRACE_MAGIC.len()] += 1;
+
+        assert!(matches!(
+            RecordedTrace::<F>::read_from(&buffer[..]),
+            Err(RecordedTraceError::UnsupportedVersion {
+                found: 2,
+                expected: RECORDED_TRACE_FORMAT_VERSION,
+            })
+        ));
+    }
+
+    #[test]
+    fn test_recorded_trace_desync() {
+        let mut oracle = RecordedTraceOracle::new(test_trace());
+        let _ = oracle.get_memory_witness_for_read(4, 1, 0, true);
+
+        let result = oracle.try_get_memory_witness_for_read(8, 2, 1);
+        assert!(matches!(
+            result,
+            Err(RecordedTraceError::Desync {
+                stream: TraceStream::MemoryReads,
+                position: 1,
+                ..
+            })
+        ));
+        // failed request doesn't move the cursor
+        let _ = oracle.get_memory_witness_for_read(8, 1, 1, true);
+
+        let result = oracle.try_get_memory_witness_for_read(12, 1, 2);
+        assert!(matches!(
+            result,
+            Err(RecordedTraceError::Exhausted {
+                stream: TraceStream::MemoryReads,
+                position: 2,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "memory reads desynchronized at witness #0")]
+    fn test_recorded_trace_oracle_panics_on_desync() {
+        let mut oracle = RecordedTraceOracle::new(test_trace());
+        let _ = oracle.get_memory_witness_for_read(4, 1, 1, true);
+    }
+}
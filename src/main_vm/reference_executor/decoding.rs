@@ -0,0 +1,170 @@
+use super::*;
+
+use crate::base_structures::vm_state::ArithmeticFlagsPortWitness;
+use crate::bit_width_to_bitmask;
+use crate::main_vm::decoded_opcode::{
+    NUM_DST_REGISTERS, NUM_SRC_REGISTERS, OPCODE_PROPS_BITMASK_FOR_BITSPREAD_ENCODING,
+    REGISTER_ENCODING_BITS, VARIANT_AND_CONDITION_ENCODING_MASK, VARIANT_ENCODING_MASK,
+};
+use crate::main_vm::opcode_bitmask::{
+    OPCODE_FLAGS_BITS, OPCODE_VARIANT_BITS, TOTAL_OPCODE_DESCRIPTION_BITS_FLATTENED,
+};
+use crate::tables::conditional::{integer_into_flags, resolve_condition};
+
+use zkevm_opcode_defs::{
+    ImmMemHandlerFlags, Opcode, CONDITIONAL_BITS_SHIFT, OPCODE_INPUT_VARIANT_FLAGS,
+    OPCODE_TYPE_BITS,
+};
+
+const CONDITION_ENCODING_MASK: u64 = bit_width_to_bitmask(3);
+
+const OPCODE_VARIANT_OFFSET: usize = OPCODE_TYPE_BITS;
+const OPCODE_FLAGS_OFFSET: usize = OPCODE_VARIANT_OFFSET + OPCODE_VARIANT_BITS;
+const INPUT_VARIANT_OFFSET: usize = OPCODE_FLAGS_OFFSET + OPCODE_FLAGS_BITS;
+const OUTPUT_VARIANT_OFFSET: usize = INPUT_VARIANT_OFFSET + OPCODE_INPUT_VARIANT_FLAGS;
+
+/// Out of circuit counterpart of `OpcodeBitmask`, that keeps the properties bitspread as an integer
+/// with the same bit layout
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NativeOpcodeBitmask(pub u64);
+
+impl NativeOpcodeBitmask {
+    #[inline]
+    fn bit(&self, idx: usize) -> bool {
+        (self.0 >> idx) & 1 == 1
+    }
+
+    pub fn boolean_for_opcode(&self, opcode: Opcode) -> bool {
+        self.bit(opcode.variant_idx())
+    }
+
+    pub fn boolean_for_variant(&self, opcode: Opcode) -> bool {
+        self.bit(OPCODE_VARIANT_OFFSET + opcode.materialize_subvariant_idx())
+    }
+
+    pub fn flag(&self, idx: usize) -> bool {
+        debug_assert!(idx < OPCODE_FLAGS_BITS);
+        self.bit(OPCODE_FLAGS_OFFSET + idx)
+    }
+
+    pub fn boolean_for_src_mem_access(&self, access_type: ImmMemHandlerFlags) -> bool {
+        self.bit(INPUT_VARIANT_OFFSET + access_type.variant_index())
+    }
+
+    pub fn boolean_for_dst_mem_access(&self, access_type: ImmMemHandlerFlags) -> bool {
+        assert!(access_type.is_allowed_for_dst());
+        self.bit(OUTPUT_VARIANT_OFFSET + access_type.variant_index())
+    }
+}
+
+/// Out of circuit counterpart of `OpcodePropertiesDecoding`. Register selectors are kept
+/// as indexes instead of bitmasks, and `None` means that no register is addressed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NativeOpcodeDecoding {
+    pub properties_bits: NativeOpcodeBitmask,
+    pub src_regs_selectors: [Option<usize>; NUM_SRC_REGISTERS],
+    pub dst_regs_selectors: [Option<usize>; NUM_DST_REGISTERS],
+    pub imm0: u16,
+    pub imm1: u16,
+}
+
+pub(crate) fn encode_flags_natively<F: SmallField>(flags: &ArithmeticFlagsPortWitness<F>) -> u8 {
+    (flags.overflow_or_less_than as u8)
+        | ((flags.equal as u8) << 1)
+        | ((flags.greater_than as u8) << 2)
+}
+
+// same as the `RegisterIndexToBitmaskTable`: 0 is "no register", and N addresses register N - 1
+fn register_selector(encoding: u8) -> Option<usize> {
+    if encoding == 0 {
+        None
+    } else {
+        Some(encoding as usize - 1)
+    }
+}
+
+/// Mirrors `perform_initial_decoding`: resolves the condition and the fast exceptions,
+/// and masks the opcode into PANIC or NOP if necessary. Returns decoded opcode and ergs left
+/// after paying for it
+pub fn perform_reference_decoding(
+    raw_opcode: u64,
+    encoded_flags: u8,
+    is_kernel_mode: bool,
+    is_static_context: bool,
+    callstack_is_full: bool,
+    ergs_left: u32,
+    did_skip_cycle: bool,
+) -> (NativeOpcodeDecoding, u32) {
+    let word_0 = raw_opcode as u32;
+    let word_1 = (raw_opcode >> 32) as u32;
+
+    let variant_and_condition = (word_0 as u64) & VARIANT_AND_CONDITION_ENCODING_MASK;
+    let variant = (variant_and_condition & VARIANT_ENCODING_MASK) as usize;
+    let condition = (variant_and_condition >> CONDITIONAL_BITS_SHIFT) & CONDITION_ENCODING_MASK;
+
+    let ergs_cost = zkevm_opcode_defs::OPCODES_PRICES[variant] as u32;
+    let opcode_boolean_spread_data = zkevm_opcode_defs::OPCODES_PROPS_INTEGER_BITMASKS[variant];
+
+    let (of, eq, gt) = integer_into_flags(encoded_flags);
+    let condition =
+        zkevm_opcode_defs::condition::Condition::materialize_variant(condition as usize);
+    let condition_if_not_masked_later = resolve_condition(condition, of, eq, gt);
+
+    let properties = opcode_boolean_spread_data & OPCODE_PROPS_BITMASK_FOR_BITSPREAD_ENCODING;
+    let aux_bits = opcode_boolean_spread_data >> TOTAL_OPCODE_DESCRIPTION_BITS_FLATTENED;
+    let aux_bit = |idx: usize| (aux_bits >> idx) & 1 == 1;
+
+    let masked_ergs_cost = if did_skip_cycle { 0 } else { ergs_cost };
+    let (ergs_left, out_of_ergs_exception) = ergs_left.overflowing_sub(masked_ergs_cost);
+    let ergs_left = if out_of_ergs_exception { 0 } else { ergs_left };
+
+    let requires_kernel_mode = aux_bit(zkevm_opcode_defs::KERNER_MODE_FLAG_IDX);
+    let can_be_used_in_static_context =
+        aux_bit(zkevm_opcode_defs::CAN_BE_USED_IN_STATIC_CONTEXT_FLAG_IDX);
+    let explicit_panic = aux_bit(zkevm_opcode_defs::EXPLICIT_PANIC_FLAG_IDX);
+
+    let kernel_mode_exception = requires_kernel_mode && !is_kernel_mode;
+    let write_in_static_exception = is_static_context && !can_be_used_in_static_context;
+
+    let mask_into_panic = explicit_panic
+        || out_of_ergs_exception
+        || kernel_mode_exception
+        || write_in_static_exception
+        || callstack_is_full;
+    let mask_into_nop = !mask_into_panic && !condition_if_not_masked_later;
+
+    let properties = if mask_into_panic {
+        *zkevm_opcode_defs::PANIC_BITSPREAD_U64 & OPCODE_PROPS_BITMASK_FOR_BITSPREAD_ENCODING
+    } else if mask_into_nop {
+        *zkevm_opcode_defs::NOP_BITSPREAD_U64 & OPCODE_PROPS_BITMASK_FOR_BITSPREAD_ENCODING
+    } else {
+        properties
+    };
+
+    let mask_any = mask_into_nop || mask_into_panic;
+    let (src_regs_encoding, dst_regs_encoding) = if mask_any {
+        (0u8, 0u8)
+    } else {
+        ((word_0 >> 16) as u8, (word_0 >> 24) as u8)
+    };
+
+    const NIBBLE_MASK: u8 = (1u8 << REGISTER_ENCODING_BITS) - 1;
+    let src_regs_selectors = [
+        register_selector(src_regs_encoding & NIBBLE_MASK),
+        register_selector(src_regs_encoding >> REGISTER_ENCODING_BITS),
+    ];
+    let dst_regs_selectors = [
+        register_selector(dst_regs_encoding & NIBBLE_MASK),
+        register_selector(dst_regs_encoding >> REGISTER_ENCODING_BITS),
+    ];
+
+    let decoded = NativeOpcodeDecoding {
+        properties_bits: NativeOpcodeBitmask(properties),
+        src_regs_selectors,
+        dst_regs_selectors,
+        imm0: word_1 as u16,
+        imm1: (word_1 >> 16) as u16,
+    };
+
+    (decoded, ergs_left)
+}
@@ -0,0 +1,268 @@
+use super::*;
+
+use crate::base_structures::decommit_query::DecommitQuery;
+use crate::base_structures::log_query::LogQuery;
+use crate::base_structures::memory_query::MemoryQuery;
+use crate::base_structures::vm_state::saved_context::ExecutionContextRecord;
+use crate::base_structures::vm_state::{GlobalContext, VmLocalState};
+use crate::main_vm::cycle::vm_cycle;
+use crate::main_vm::tracer::VmCycleTracer;
+use crate::main_vm::variants::VmCircuitVariant;
+use crate::main_vm::witness_oracle::SynchronizedWitnessOracle;
+use boojum::cs::traits::cs::ConstraintSystem;
+use boojum::gadgets::traits::allocatable::CSAllocatableExt;
+use boojum::gadgets::traits::round_function::CircuitRoundFunction;
+use boojum::gadgets::traits::witnessable::WitnessHookable;
+
+/// First field (in declaration order) where the circuit and the reference executor disagree
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateDivergence {
+    pub field: String,
+    pub circuit: String,
+    pub reference: String,
+}
+
+impl fmt::Display for StateDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` differs: circuit = {}, reference = {}",
+            self.field, self.circuit, self.reference
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DifferentialCycleOutcome {
+    Matches,
+    Unsupported(ReferenceExecutionError),
+    Diverged(StateDivergence),
+}
+
+/// Cycle counts of the differential run. Cycles that the reference executor can not replay
+/// (see `UNSUPPORTED_OPCODE_FAMILIES`) are counted by the reason, so the caller can report
+/// what was left unchecked
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DifferentialCheckSummary {
+    pub matched_cycles: usize,
+    pub unsupported_cycles: std::collections::BTreeMap<String, usize>,
+}
+
+impl DifferentialCheckSummary {
+    pub fn record_unsupported(&mut self, error: &ReferenceExecutionError) {
+        *self
+            .unsupported_cycles
+            .entry(error.to_string())
+            .or_default() += 1;
+    }
+
+    pub fn num_unsupported_cycles(&self) -> usize {
+        self.unsupported_cycles.values().sum()
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        self.matched_cycles += other.matched_cycles;
+        for (reason, count) in other.unsupported_cycles.iter() {
+            *self.unsupported_cycles.entry(reason.clone()).or_default() += count;
+        }
+    }
+}
+
+impl fmt::Display for DifferentialCheckSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} cycles matched the reference executor, {} cycles were not checked",
+            self.matched_cycles,
+            self.num_unsupported_cycles()
+        )?;
+        for (reason, count) in self.unsupported_cycles.iter() {
+            write!(f, "\n  {} cycles: {}", count, reason)?;
+        }
+
+        Ok(())
+    }
+}
+
+thread_local! {
+    static DIFFERENTIAL_CHECK_SUMMARY: std::cell::RefCell<DifferentialCheckSummary> =
+        std::cell::RefCell::new(DifferentialCheckSummary::default());
+}
+
+/// Returns the summary of all the VM circuits synthesized by this thread with the differential check
+/// since the last call, and resets it
+pub fn take_differential_check_summary() -> DifferentialCheckSummary {
+    DIFFERENTIAL_CHECK_SUMMARY.with(|el| std::mem::take(&mut *el.borrow_mut()))
+}
+
+pub(crate) fn record_differential_check_summary(summary: &DifferentialCheckSummary) {
+    DIFFERENTIAL_CHECK_SUMMARY.with(|el| el.borrow_mut().merge(summary));
+}
+
+fn check_field<T: PartialEq + std::fmt::Debug>(
+    field: String,
+    circuit: &T,
+    reference: &T,
+) -> Option<StateDivergence> {
+    if circuit == reference {
+        None
+    } else {
+        Some(StateDivergence {
+            field,
+            circuit: format!("{:?}", circuit),
+            reference: format!("{:?}", reference),
+        })
+    }
+}
+
+macro_rules! compare_fields {
+    ($circuit:expr, $reference:expr, [$($($field:ident).+),* $(,)?]) => {
+        $(
+            if let Some(divergence) = check_field(
+                stringify!($($field).+).replace(' ', ""),
+                &$circuit.$($field).+,
+                &$reference.$($field).+,
+            ) {
+                return Some(divergence);
+            }
+        )*
+    };
+}
+
+/// Compares two VM states field by field and reports the first mismatch
+pub fn compare_vm_states<F: SmallField>(
+    circuit: &VmLocalStateWitness<F>,
+    reference: &VmLocalStateWitness<F>,
+) -> Option<StateDivergence> {
+    compare_fields!(circuit, reference, [previous_code_word]);
+
+    for (idx, (a, b)) in circuit
+        .registers
+        .iter()
+        .zip(reference.registers.iter())
+        .enumerate()
+    {
+        if let Some(divergence) = check_field(
+            format!("registers[{}].is_pointer", idx),
+            &a.is_pointer,
+            &b.is_pointer,
+        ) {
+            return Some(divergence);
+        }
+        if let Some(divergence) =
+            check_field(format!("registers[{}].value", idx), &a.value, &b.value)
+        {
+            return Some(divergence);
+        }
+    }
+
+    compare_fields!(
+        circuit,
+        reference,
+        [
+            flags.overflow_or_less_than,
+            flags.equal,
+            flags.greater_than,
+            timestamp,
+            memory_page_counter,
+            tx_number_in_block,
+            previous_code_page,
+            previous_super_pc,
+            pending_exception,
+            pubdata_revert_counter,
+            callstack.current_context.saved_context.this,
+            callstack.current_context.saved_context.caller,
+            callstack.current_context.saved_context.code_address,
+            callstack.current_context.saved_context.code_page,
+            callstack.current_context.saved_context.base_page,
+            callstack.current_context.saved_context.heap_upper_bound,
+            callstack.current_context.saved_context.aux_heap_upper_bound,
+            callstack.current_context.saved_context.reverted_queue_head,
+            callstack.current_context.saved_context.reverted_queue_tail,
+            callstack
+                .current_context
+                .saved_context
+                .reverted_queue_segment_len,
+            callstack.current_context.saved_context.pc,
+            callstack.current_context.saved_context.sp,
+            callstack
+                .current_context
+                .saved_context
+                .exception_handler_loc,
+            callstack.current_context.saved_context.ergs_remaining,
+            callstack.current_context.saved_context.is_static_execution,
+            callstack.current_context.saved_context.is_kernel_mode,
+            callstack.current_context.saved_context.this_shard_id,
+            callstack.current_context.saved_context.caller_shard_id,
+            callstack.current_context.saved_context.code_shard_id,
+            callstack
+                .current_context
+                .saved_context
+                .context_u128_value_composite,
+            callstack.current_context.saved_context.is_local_call,
+            callstack.current_context.saved_context.total_pubdata_spent,
+            callstack.current_context.saved_context.stipend,
+            callstack.current_context.log_queue_forward_tail,
+            callstack.current_context.log_queue_forward_part_length,
+            callstack.context_stack_depth,
+            callstack.stack_sponge_state,
+            memory_queue_state,
+            memory_queue_length,
+            code_decommittment_queue_state,
+            code_decommittment_queue_length,
+            context_composite_u128,
+        ]
+    );
+
+    None
+}
+
+/// Runs `vm_cycle` and replays the same cycle on the reference executor using a snapshot
+/// of the witness oracle taken before the cycle. Requires witness to be resolved immediately
+pub(crate) fn vm_cycle_with_differential_check<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+    W: WitnessOracle<F>,
+    T: VmCycleTracer<F>,
+>(
+    cs: &mut CS,
+    current_state: VmLocalState<F>,
+    witness_oracle: &SynchronizedWitnessOracle<F, W>,
+    global_context: &GlobalContext<F>,
+    round_function: &R,
+    variant: &VmCircuitVariant,
+    tracer: &mut T,
+) -> (VmLocalState<F>, DifferentialCycleOutcome)
+where
+    [(); <ExecutionContextRecord<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <DecommitQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+{
+    let state_before = current_state.witness_hook(&*cs)().expect("state witness must be resolved");
+    let mut oracle_snapshot = witness_oracle.inner.read().expect("not poisoned").clone();
+
+    let new_state = vm_cycle(
+        cs,
+        current_state,
+        witness_oracle,
+        global_context,
+        round_function,
+        variant,
+        tracer,
+    );
+
+    let circuit_state = new_state.witness_hook(&*cs)().expect("state witness must be resolved");
+
+    let outcome = match reference_vm_cycle::<F, R, W>(&state_before, &mut oracle_snapshot, variant)
+    {
+        Ok(reference_state) => match compare_vm_states(&circuit_state, &reference_state) {
+            None => DifferentialCycleOutcome::Matches,
+            Some(divergence) => DifferentialCycleOutcome::Diverged(divergence),
+        },
+        Err(err) => DifferentialCycleOutcome::Unsupported(err),
+    };
+
+    (new_state, outcome)
+}
@@ -0,0 +1,1080 @@
+use crate::ethereum_types::U256;
+
+use crate::base_structures::memory_query::MemoryQueryWitness;
+use crate::base_structures::register::VMRegisterWitness;
+use crate::base_structures::vm_state::callstack::CallstackWitness;
+use crate::base_structures::vm_state::saved_context::{
+    ExecutionContextRecordWitness, EXECUTION_CONTEXT_RECORD_ENCODING_WIDTH,
+};
+use crate::base_structures::vm_state::{
+    ArithmeticFlagsPortWitness, VmLocalStateWitness, FULL_SPONGE_QUEUE_STATE_WIDTH,
+};
+use crate::main_vm::utils::{SUB_PC_BITS, SUB_PC_MASK};
+use crate::main_vm::variants::VmCircuitVariant;
+use crate::main_vm::witness_oracle::{MemoryWitness, WitnessOracle};
+use boojum::algebraic_props::round_function::AlgebraicRoundFunction;
+use boojum::field::SmallField;
+
+use super::*;
+
+use std::fmt;
+
+pub mod decoding;
+pub mod differential;
+mod opcodes;
+
+use self::decoding::*;
+
+use zkevm_opcode_defs::{ImmMemHandlerFlags, Opcode, REGISTERS_COUNT};
+
+// families that are not mirrored yet. Cycles that execute them (including pending exceptions, that are
+// executed as `ret`) are reported as unsupported and remain unchecked, everything else is mirrored
+pub const UNSUPPORTED_OPCODE_FAMILIES: [(Opcode, &'static str); 4] = [
+    (
+        Opcode::FarCall(zkevm_opcode_defs::FarCallOpcode::Normal),
+        "far_call",
+    ),
+    (Opcode::Ret(zkevm_opcode_defs::RetOpcode::Ok), "ret"),
+    (
+        Opcode::Log(zkevm_opcode_defs::LogOpcode::StorageRead),
+        "log",
+    ),
+    (Opcode::UMA(zkevm_opcode_defs::UMAOpcode::HeapRead), "uma"),
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReferenceExecutionError {
+    /// Opcode family is not implemented by the reference executor
+    UnsupportedOpcode(&'static str),
+    /// Decoding resulted in the INVALID opcode, that the circuit can never accept
+    InvalidOpcode,
+    /// Opcode is from the family excluded by the circuit variant, so the circuit can not accept it
+    ExcludedByVariant {
+        variant: &'static str,
+        family: &'static str,
+    },
+}
+
+impl fmt::Display for ReferenceExecutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedOpcode(family) => write!(
+                f,
+                "opcode family `{}` is not supported by the reference executor",
+                family
+            ),
+            Self::InvalidOpcode => write!(f, "decoding resulted in the INVALID opcode"),
+            Self::ExcludedByVariant { variant, family } => write!(
+                f,
+                "opcode family `{}` is excluded by the VM variant `{}`",
+                family, variant
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReferenceExecutionError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NativeMemoryLocation {
+    pub page: u32,
+    pub index: u32,
+}
+
+/// Out of circuit counterpart of `CommonOpcodeState`
+#[derive(Derivative)]
+#[derivative(Clone, Debug)]
+pub struct NativeCommonOpcodeState<F: SmallField> {
+    pub current_flags: ArithmeticFlagsPortWitness<F>,
+    pub decoded_opcode: NativeOpcodeDecoding,
+    pub src0: VMRegisterWitness<F>,
+    pub src1: VMRegisterWitness<F>,
+    pub timestamp_for_code_or_src_read: u32,
+    pub timestamp_for_first_decommit_or_precompile_read: u32,
+    pub timestamp_for_second_decommit_or_precompile_write: u32,
+    pub timestamp_for_dst_write: u32,
+}
+
+/// Out of circuit counterpart of `AfterDecodingCarryParts`
+#[derive(Clone, Copy, Debug)]
+pub struct NativeAfterDecodingCarryParts {
+    pub did_skip_cycle: bool,
+    pub heap_page: u32,
+    pub aux_heap_page: u32,
+    pub next_pc: u16,
+    pub preliminary_ergs_left: u32,
+    pub dst0_memory_location: NativeMemoryLocation,
+    pub dst0_performs_memory_access: bool,
+}
+
+/// Out of circuit counterpart of `StateDiffsAccumulator`, limited to the kinds of updates
+/// that supported opcodes produce
+#[derive(Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct NativeStateDiffs<F: SmallField> {
+    pub dst_0_values: Vec<(bool, bool, VMRegisterWitness<F>)>,
+    pub dst_1_values: Vec<(bool, VMRegisterWitness<F>)>,
+    pub flags: Vec<(bool, ArithmeticFlagsPortWitness<F>)>,
+    pub pending_exceptions: Vec<bool>,
+    pub new_pc_candidates: Vec<(bool, u16)>,
+    pub new_tx_number: Option<(bool, u32)>,
+    pub context_u128_candidates: Vec<(bool, [u32; 4])>,
+    pub callstacks: Vec<(bool, CallstackWitness<F>)>,
+}
+
+/// Executes one cycle of the VM out of circuit, following the same decoding, opcode application
+/// and state diffs selection rules as `vm_cycle`, and taking the witness from the same oracle.
+/// Opcodes from `UNSUPPORTED_OPCODE_FAMILIES` are not mirrored
+pub fn reference_vm_cycle<
+    F: SmallField,
+    R: AlgebraicRoundFunction<F, 8, 12, 4>,
+    W: WitnessOracle<F>,
+>(
+    current_state: &VmLocalStateWitness<F>,
+    witness_oracle: &mut W,
+    variant: &VmCircuitVariant,
+) -> Result<VmLocalStateWitness<F>, ReferenceExecutionError> {
+    let (draft_next_state, common_opcode_state, opcode_carry_parts) =
+        create_reference_prestate::<F, R, W>(current_state, witness_oracle)?;
+
+    let properties_bits = common_opcode_state.decoded_opcode.properties_bits;
+    // mirrors `enforce_opcode_is_supported`
+    for family in variant.excluded_families.iter() {
+        if family
+            .opcodes()
+            .iter()
+            .any(|el| properties_bits.boolean_for_opcode(*el))
+        {
+            return Err(ReferenceExecutionError::ExcludedByVariant {
+                variant: variant.name,
+                family: family.name(),
+            });
+        }
+    }
+    for (opcode, family) in UNSUPPORTED_OPCODE_FAMILIES.iter() {
+        if properties_bits.boolean_for_opcode(*opcode) {
+            return Err(ReferenceExecutionError::UnsupportedOpcode(family));
+        }
+    }
+
+    let mut diffs = NativeStateDiffs::<F>::default();
+
+    opcodes::apply_add_sub(
+        &draft_next_state,
+        &common_opcode_state,
+        &opcode_carry_parts,
+        &mut diffs,
+    );
+    opcodes::apply_jump(
+        &draft_next_state,
+        &common_opcode_state,
+        &opcode_carry_parts,
+        &mut diffs,
+    );
+    opcodes::apply_binop(
+        &draft_next_state,
+        &common_opcode_state,
+        &opcode_carry_parts,
+        &mut diffs,
+    );
+    opcodes::apply_context(
+        &draft_next_state,
+        &common_opcode_state,
+        &opcode_carry_parts,
+        &mut diffs,
+    );
+    opcodes::apply_ptr(
+        &draft_next_state,
+        &common_opcode_state,
+        &opcode_carry_parts,
+        &mut diffs,
+    );
+    opcodes::apply_near_call::<F, R, W>(
+        &draft_next_state,
+        &common_opcode_state,
+        &opcode_carry_parts,
+        &mut diffs,
+        witness_oracle,
+    );
+    opcodes::apply_mul_div(
+        &draft_next_state,
+        &common_opcode_state,
+        &opcode_carry_parts,
+        &mut diffs,
+    );
+    opcodes::apply_shifts(
+        &draft_next_state,
+        &common_opcode_state,
+        &opcode_carry_parts,
+        &mut diffs,
+    );
+
+    let new_state = apply_reference_state_diffs::<F, R, W>(
+        draft_next_state,
+        &common_opcode_state,
+        &opcode_carry_parts,
+        diffs,
+        witness_oracle,
+    );
+
+    Ok(new_state)
+}
+
+/// Mirrors `create_prestate`
+pub fn create_reference_prestate<
+    F: SmallField,
+    R: AlgebraicRoundFunction<F, 8, 12, 4>,
+    W: WitnessOracle<F>,
+>(
+    current_state: &VmLocalStateWitness<F>,
+    witness_oracle: &mut W,
+) -> Result<
+    (
+        VmLocalStateWitness<F>,
+        NativeCommonOpcodeState<F>,
+        NativeAfterDecodingCarryParts,
+    ),
+    ReferenceExecutionError,
+> {
+    let mut current_state = current_state.clone();
+
+    let should_skip_cycle = current_state.callstack.context_stack_depth == 0;
+    let execute_pending_exception_at_this_cycle = current_state.pending_exception;
+    let should_try_to_read_opcode = !should_skip_cycle && !execute_pending_exception_at_this_cycle;
+
+    // take down the flag
+    current_state.pending_exception = false;
+
+    let current_pc = current_state.callstack.current_context.saved_context.pc;
+    let pc_plus_one = current_pc.wrapping_add(1);
+    let super_pc = current_pc >> SUB_PC_BITS;
+    let sub_pc = current_pc & SUB_PC_MASK;
+
+    let code_page = current_state
+        .callstack
+        .current_context
+        .saved_context
+        .code_page;
+    let can_skip_read = current_state.previous_code_page == code_page
+        && current_state.previous_super_pc == super_pc;
+    let should_read_opcode = should_try_to_read_opcode && !can_skip_read;
+
+    let timestamp_for_code_or_src_read = current_state.timestamp;
+    let timestamp_for_first_decommit_or_precompile_read =
+        timestamp_for_code_or_src_read.wrapping_add(1);
+    let timestamp_for_second_decommit_or_precompile_write =
+        timestamp_for_first_decommit_or_precompile_read.wrapping_add(1);
+    let timestamp_for_dst_write = timestamp_for_second_decommit_or_precompile_write.wrapping_add(1);
+    let next_cycle_timestamp = if should_skip_cycle {
+        current_state.timestamp
+    } else {
+        timestamp_for_dst_write.wrapping_add(1)
+    };
+
+    let code_read = may_be_read_memory::<F, R, W>(
+        &mut current_state,
+        witness_oracle,
+        should_read_opcode,
+        timestamp_for_code_or_src_read,
+        NativeMemoryLocation {
+            page: code_page,
+            index: super_pc as u32,
+        },
+        false,
+    );
+
+    let code_word = if should_read_opcode {
+        code_read.value
+    } else {
+        current_state.previous_code_word
+    };
+
+    // default one is one corresponding to the "highest" bytes in 32 byte word in our BE machine
+    let low_limb_idx = 6 - 2 * (sub_pc as usize);
+    let opcode = (u256_limb(&code_word, low_limb_idx) as u64)
+        | ((u256_limb(&code_word, low_limb_idx + 1) as u64) << 32);
+
+    use zkevm_opcode_defs::decoding::*;
+    let opcode = if should_skip_cycle {
+        EncodingModeProduction::nop_encoding()
+    } else {
+        opcode
+    };
+    let opcode = if execute_pending_exception_at_this_cycle {
+        EncodingModeProduction::exception_revert_encoding()
+    } else {
+        opcode
+    };
+
+    current_state.previous_code_word = code_word;
+    current_state.previous_code_page = code_page;
+    if should_skip_cycle == false {
+        current_state.callstack.current_context.saved_context.pc = pc_plus_one;
+        current_state.previous_super_pc = super_pc;
+    }
+    current_state.timestamp = next_cycle_timestamp;
+
+    let saved_context = &current_state.callstack.current_context.saved_context;
+    let is_kernel_mode = saved_context.is_kernel_mode;
+    let callstack_is_full = current_state.callstack.context_stack_depth
+        == zkevm_opcode_defs::system_params::VM_MAX_STACK_DEPTH;
+
+    let (decoded_opcode, dirty_ergs_left) = perform_reference_decoding(
+        opcode,
+        encode_flags_natively(&current_state.flags),
+        is_kernel_mode,
+        saved_context.is_static_execution,
+        callstack_is_full,
+        saved_context.ergs_remaining,
+        should_skip_cycle,
+    );
+
+    current_state
+        .callstack
+        .current_context
+        .saved_context
+        .ergs_remaining = dirty_ergs_left;
+
+    let properties_bits = decoded_opcode.properties_bits;
+    if properties_bits.boolean_for_opcode(Opcode::Invalid(zkevm_opcode_defs::InvalidOpcode)) {
+        return Err(ReferenceExecutionError::InvalidOpcode);
+    }
+
+    let select_register = |selector: Option<usize>| match selector {
+        Some(idx) => current_state.registers[idx].clone(),
+        None => zero_register(),
+    };
+
+    let draft_src0 = select_register(decoded_opcode.src_regs_selectors[0]);
+    let src1_register = select_register(decoded_opcode.src_regs_selectors[1]);
+    let dst0_register = select_register(decoded_opcode.dst_regs_selectors[0]);
+
+    let src0_reg_lowest = draft_src0.value.low_u32() as u16;
+    let dst0_reg_lowest = dst0_register.value.low_u32() as u16;
+
+    let current_sp = current_state.callstack.current_context.saved_context.sp;
+    let base_page = current_state
+        .callstack
+        .current_context
+        .saved_context
+        .base_page;
+    let stack_page = base_page.wrapping_add(1);
+    let heap_page = stack_page.wrapping_add(1);
+    let aux_heap_page = heap_page.wrapping_add(1);
+
+    let (memory_location_for_src0, new_sp_after_src0, should_read_memory_for_src0) =
+        resolve_reference_source(
+            code_page,
+            stack_page,
+            src0_reg_lowest,
+            &decoded_opcode,
+            current_sp,
+        );
+
+    let (memory_location_for_dst0, new_sp, should_write_memory_for_dst0) = resolve_reference_dest(
+        stack_page,
+        dst0_reg_lowest,
+        &decoded_opcode,
+        new_sp_after_src0,
+    );
+
+    current_state.callstack.current_context.saved_context.sp = new_sp;
+
+    let src0_read = may_be_read_memory::<F, R, W>(
+        &mut current_state,
+        witness_oracle,
+        should_read_memory_for_src0,
+        timestamp_for_code_or_src_read,
+        memory_location_for_src0,
+        true,
+    );
+
+    let src0 = if properties_bits.boolean_for_src_mem_access(ImmMemHandlerFlags::UseRegOnly) {
+        draft_src0
+    } else {
+        VMRegisterWitness {
+            is_pointer: src0_read.is_ptr,
+            value: src0_read.value,
+        }
+    };
+    let src0 = if properties_bits.boolean_for_src_mem_access(ImmMemHandlerFlags::UseImm16Only) {
+        VMRegisterWitness {
+            is_pointer: false,
+            value: U256::from(decoded_opcode.imm0),
+        }
+    } else {
+        src0
+    };
+
+    let swap_operands = {
+        use zkevm_opcode_defs::*;
+
+        let is_assymmetric = properties_bits.boolean_for_opcode(Opcode::Sub(SubOpcode::Sub))
+            || properties_bits.boolean_for_opcode(Opcode::Div(DivOpcode))
+            || properties_bits.boolean_for_opcode(Opcode::Shift(ShiftOpcode::Rol));
+        let t0 = is_assymmetric && properties_bits.flag(SWAP_OPERANDS_FLAG_IDX_FOR_ARITH_OPCODES);
+
+        let is_ptr = properties_bits.boolean_for_opcode(Opcode::Ptr(PtrOpcode::Add));
+        let t1 = is_ptr && properties_bits.flag(SWAP_OPERANDS_FLAG_IDX_FOR_PTR_OPCODE);
+
+        t0 || t1
+    };
+
+    let (mut src0, mut src1) = if swap_operands {
+        (src1_register, src0)
+    } else {
+        (src0, src1_register)
+    };
+
+    let should_erase_src0_ptr_data = {
+        use zkevm_opcode_defs::*;
+
+        let may_take_pointers = properties_bits.boolean_for_opcode(Opcode::Ret(RetOpcode::Ok))
+            || properties_bits.boolean_for_opcode(Opcode::Ptr(PtrOpcode::Add))
+            || properties_bits.boolean_for_opcode(Opcode::UMA(UMAOpcode::AuxHeapRead))
+            || properties_bits.boolean_for_opcode(Opcode::FarCall(FarCallOpcode::Delegate));
+
+        src0.is_pointer && !may_take_pointers && !is_kernel_mode
+    };
+    let should_erase_src1_ptr_data = src1.is_pointer && !is_kernel_mode;
+
+    if should_erase_src0_ptr_data {
+        erase_fat_pointer_data(&mut src0);
+    }
+    if should_erase_src1_ptr_data {
+        erase_fat_pointer_data(&mut src1);
+    }
+
+    let common_opcode_state = NativeCommonOpcodeState {
+        current_flags: current_state.flags.clone(),
+        decoded_opcode,
+        src0,
+        src1,
+        timestamp_for_code_or_src_read,
+        timestamp_for_first_decommit_or_precompile_read,
+        timestamp_for_second_decommit_or_precompile_write,
+        timestamp_for_dst_write,
+    };
+
+    let carry_parts = NativeAfterDecodingCarryParts {
+        did_skip_cycle: should_skip_cycle,
+        heap_page,
+        aux_heap_page,
+        next_pc: pc_plus_one,
+        preliminary_ergs_left: dirty_ergs_left,
+        dst0_memory_location: memory_location_for_dst0,
+        dst0_performs_memory_access: should_write_memory_for_dst0,
+    };
+
+    Ok((current_state, common_opcode_state, carry_parts))
+}
+
+/// Mirrors `resolve_memory_region_and_index_for_source`
+pub fn resolve_reference_source(
+    code_page: u32,
+    stack_page: u32,
+    register_low_value: u16,
+    opcode_props: &NativeOpcodeDecoding,
+    current_sp: u16,
+) -> (NativeMemoryLocation, u16, bool) {
+    let properties_bits = &opcode_props.properties_bits;
+    let use_code = properties_bits.boolean_for_src_mem_access(ImmMemHandlerFlags::UseCodePage);
+    let use_stack_absolute =
+        properties_bits.boolean_for_src_mem_access(ImmMemHandlerFlags::UseAbsoluteOnStack);
+    let use_stack_relative =
+        properties_bits.boolean_for_src_mem_access(ImmMemHandlerFlags::UseStackWithOffset);
+    let use_stack_with_push_pop =
+        properties_bits.boolean_for_src_mem_access(ImmMemHandlerFlags::UseStackWithPushPop);
+
+    let absolute_mode = use_code || use_stack_absolute;
+    let index_for_absolute = register_low_value.wrapping_add(opcode_props.imm0);
+    let index_for_relative = current_sp.wrapping_sub(index_for_absolute);
+
+    let use_stack = use_stack_absolute || use_stack_relative || use_stack_with_push_pop;
+    let is_nop = properties_bits.boolean_for_opcode(Opcode::Nop(zkevm_opcode_defs::NopOpcode));
+    let did_read = (use_stack || use_code) && !is_nop;
+
+    let page = if use_stack { stack_page } else { code_page };
+    let index = if absolute_mode {
+        index_for_absolute
+    } else {
+        index_for_relative
+    };
+    let new_sp = if use_stack_with_push_pop {
+        index_for_relative
+    } else {
+        current_sp
+    };
+
+    let location = NativeMemoryLocation {
+        page,
+        index: index as u32,
+    };
+
+    (location, new_sp, did_read)
+}
+
+/// Mirrors `resolve_memory_region_and_index_for_dest`
+pub fn resolve_reference_dest(
+    stack_page: u32,
+    register_low_value: u16,
+    opcode_props: &NativeOpcodeDecoding,
+    current_sp: u16,
+) -> (NativeMemoryLocation, u16, bool) {
+    let properties_bits = &opcode_props.properties_bits;
+    let use_stack_absolute =
+        properties_bits.boolean_for_dst_mem_access(ImmMemHandlerFlags::UseAbsoluteOnStack);
+    let use_stack_relative =
+        properties_bits.boolean_for_dst_mem_access(ImmMemHandlerFlags::UseStackWithOffset);
+    let use_stack_with_push_pop =
+        properties_bits.boolean_for_dst_mem_access(ImmMemHandlerFlags::UseStackWithPushPop);
+
+    let index_for_absolute = register_low_value.wrapping_add(opcode_props.imm1);
+    let index_for_relative_with_push = current_sp.wrapping_add(index_for_absolute);
+    let index_for_relative = current_sp.wrapping_sub(index_for_absolute);
+
+    let is_nop = properties_bits.boolean_for_opcode(Opcode::Nop(zkevm_opcode_defs::NopOpcode));
+    let did_write =
+        (use_stack_absolute || use_stack_relative || use_stack_with_push_pop) && !is_nop;
+
+    // in the push case we update SP only after, and the memory index is current SP
+    let index = if use_stack_absolute {
+        index_for_absolute
+    } else if use_stack_with_push_pop {
+        current_sp
+    } else {
+        index_for_relative
+    };
+    let new_sp = if use_stack_with_push_pop {
+        index_for_relative_with_push
+    } else {
+        current_sp
+    };
+
+    let location = NativeMemoryLocation {
+        page: stack_page,
+        index: index as u32,
+    };
+
+    (location, new_sp, did_write)
+}
+
+/// Mirrors the final part of `vm_cycle`, that selects and applies the state diffs
+pub fn apply_reference_state_diffs<
+    F: SmallField,
+    R: AlgebraicRoundFunction<F, 8, 12, 4>,
+    W: WitnessOracle<F>,
+>(
+    draft_next_state: VmLocalStateWitness<F>,
+    common_opcode_state: &NativeCommonOpcodeState<F>,
+    opcode_carry_parts: &NativeAfterDecodingCarryParts,
+    diffs: NativeStateDiffs<F>,
+    witness_oracle: &mut W,
+) -> VmLocalStateWitness<F> {
+    let mut new_state = draft_next_state;
+
+    let dst0_update_potentially_to_memory = diffs
+        .dst_0_values
+        .iter()
+        .any(|(can_write_into_memory, applies, _)| *can_write_into_memory && *applies);
+    let can_update_dst0_as_register_only = diffs
+        .dst_0_values
+        .iter()
+        .any(|(can_write_into_memory, applies, _)| !*can_write_into_memory && *applies);
+
+    // opcodes are orthogonal, so at most one candidate applies
+    let dst0 = diffs
+        .dst_0_values
+        .iter()
+        .find(|(_, applies, _)| *applies)
+        .map(|(_, _, value)| value.clone())
+        .unwrap_or_else(zero_register);
+    let dst1_applies = diffs.dst_1_values.iter().any(|(applies, _)| *applies);
+    let dst1 = diffs
+        .dst_1_values
+        .iter()
+        .find(|(applies, _)| *applies)
+        .map(|(_, value)| value.clone())
+        .unwrap_or_else(zero_register);
+
+    let perform_dst0_memory_write_update =
+        opcode_carry_parts.dst0_performs_memory_access && dst0_update_potentially_to_memory;
+
+    let query = MemoryQueryWitness {
+        timestamp: common_opcode_state.timestamp_for_dst_write,
+        memory_page: opcode_carry_parts.dst0_memory_location.page,
+        index: opcode_carry_parts.dst0_memory_location.index,
+        rw_flag: true,
+        is_ptr: dst0.is_pointer,
+        value: dst0.value,
+    };
+    if perform_dst0_memory_write_update {
+        new_state.memory_queue_state =
+            absorb_memory_query::<F, R>(&new_state.memory_queue_state, &query);
+        new_state.memory_queue_length = new_state.memory_queue_length.wrapping_add(1);
+    }
+    witness_oracle.push_memory_witness(&query, perform_dst0_memory_write_update);
+
+    let dst0_update_register = can_update_dst0_as_register_only
+        || (!opcode_carry_parts.dst0_performs_memory_access && dst0_update_potentially_to_memory);
+
+    let decoded_opcode = &common_opcode_state.decoded_opcode;
+    for idx in 0..REGISTERS_COUNT {
+        let write_as_dst0 =
+            dst0_update_register && decoded_opcode.dst_regs_selectors[0] == Some(idx);
+        // dst1 is always register
+        let write_as_dst1 = dst1_applies && decoded_opcode.dst_regs_selectors[1] == Some(idx);
+
+        if write_as_dst0 {
+            new_state.registers[idx] = dst0.clone();
+        }
+        if write_as_dst1 {
+            new_state.registers[idx] = dst1.clone();
+        }
+    }
+
+    for (applies, value) in diffs.new_pc_candidates.into_iter() {
+        if applies {
+            new_state.callstack.current_context.saved_context.pc = value;
+        }
+    }
+
+    if let Some((true, value)) = diffs.new_tx_number {
+        new_state.tx_number_in_block = value;
+    }
+
+    for (applies, value) in diffs.context_u128_candidates.into_iter() {
+        if applies {
+            new_state.context_composite_u128 = value;
+        }
+    }
+
+    for (applies, flags) in diffs.flags.into_iter() {
+        if applies {
+            new_state.flags = flags;
+        }
+    }
+
+    // and now we either replace or not the callstack in full
+    for (applies, callstack) in diffs.callstacks.into_iter() {
+        if applies {
+            new_state.callstack = callstack;
+        }
+    }
+
+    new_state.pending_exception = diffs.pending_exceptions.into_iter().any(|el| el);
+
+    new_state
+}
+
+fn may_be_read_memory<
+    F: SmallField,
+    R: AlgebraicRoundFunction<F, 8, 12, 4>,
+    W: WitnessOracle<F>,
+>(
+    state: &mut VmLocalStateWitness<F>,
+    witness_oracle: &mut W,
+    should_access: bool,
+    timestamp: u32,
+    location: NativeMemoryLocation,
+    may_be_pointer: bool,
+) -> MemoryWitness {
+    let mut witness = witness_oracle.get_memory_witness_for_read(
+        timestamp,
+        location.page,
+        location.index,
+        should_access,
+    );
+    if may_be_pointer == false {
+        witness.is_ptr = false;
+    }
+
+    if should_access {
+        let query = MemoryQueryWitness {
+            timestamp,
+            memory_page: location.page,
+            index: location.index,
+            rw_flag: false,
+            is_ptr: witness.is_ptr,
+            value: witness.value,
+        };
+        state.memory_queue_state = absorb_memory_query::<F, R>(&state.memory_queue_state, &query);
+        state.memory_queue_length = state.memory_queue_length.wrapping_add(1);
+    }
+
+    witness
+}
+
+/// Same packing as `CircuitEncodable` implementation of the `MemoryQuery`
+pub fn encode_memory_query_natively<F: SmallField>(
+    query: &MemoryQueryWitness<F>,
+) -> [F; crate::base_structures::memory_query::MEMORY_QUERY_PACKED_WIDTH] {
+    let limbs: [u32; 8] = std::array::from_fn(|idx| u256_limb(&query.value, idx));
+    let bytes_5 = limbs[5].to_le_bytes();
+    let bytes_6 = limbs[6].to_le_bytes();
+    let bytes_7 = limbs[7].to_le_bytes();
+
+    let pack = |low: u32, bytes: [u8; 3]| {
+        (low as u64)
+            | ((bytes[0] as u64) << 32)
+            | ((bytes[1] as u64) << 40)
+            | ((bytes[2] as u64) << 48)
+    };
+
+    [
+        query.timestamp as u64,
+        query.memory_page as u64,
+        (query.index as u64) | ((query.rw_flag as u64) << 32) | ((query.is_ptr as u64) << 33),
+        pack(limbs[0], [bytes_5[0], bytes_5[1], bytes_5[2]]),
+        pack(limbs[1], [bytes_5[3], bytes_6[0], bytes_6[1]]),
+        pack(limbs[2], [bytes_6[2], bytes_6[3], bytes_7[0]]),
+        pack(limbs[3], [bytes_7[1], bytes_7[2], bytes_7[3]]),
+        limbs[4] as u64,
+    ]
+    .map(|el| F::from_u64_unchecked(el))
+}
+
+/// Same packing as `CircuitEncodable` implementation of the `ExecutionContextRecord`
+pub fn encode_execution_context_record_natively<F: SmallField>(
+    record: &ExecutionContextRecordWitness<F>,
+) -> [F; EXECUTION_CONTEXT_RECORD_ENCODING_WIDTH] {
+    let address_limbs = |address: &crate::ethereum_types::Address| {
+        let limbs = u256_into_limbs(&U256::from_big_endian(address.as_bytes()));
+        [limbs[0], limbs[1], limbs[2], limbs[3], limbs[4]].map(|el| el as u64)
+    };
+    let shard_ids = (record.this_shard_id as u64)
+        | ((record.code_shard_id as u64) << 8)
+        | ((record.caller_shard_id as u64) << 16);
+    let boolean_flags = (record.is_static_execution as u64)
+        | ((record.is_kernel_mode as u64) << 8)
+        | ((record.is_local_call as u64) << 16);
+
+    let mut encoding = [F::ZERO; EXECUTION_CONTEXT_RECORD_ENCODING_WIDTH];
+    let mut dst = encoding.iter_mut();
+    for el in record
+        .reverted_queue_head
+        .iter()
+        .chain(record.reverted_queue_tail.iter())
+    {
+        *dst.next().unwrap() = *el;
+    }
+    let integer_parts = address_limbs(&record.code_address)
+        .into_iter()
+        .chain(address_limbs(&record.this))
+        .chain(address_limbs(&record.caller))
+        .chain(record.context_u128_value_composite.map(|el| el as u64))
+        .chain([
+            record.code_page as u64,
+            record.base_page as u64,
+            record.heap_upper_bound as u64,
+            record.aux_heap_upper_bound as u64,
+            record.ergs_remaining as u64,
+            record.total_pubdata_spent as u64,
+            record.stipend as u64,
+            record.reverted_queue_segment_len as u64,
+            record.sp as u64,
+            record.pc as u64,
+            record.exception_handler_loc as u64,
+            shard_ids,
+            boolean_flags,
+        ]);
+    for el in integer_parts {
+        *dst.next().unwrap() = F::from_u64_unchecked(el);
+    }
+    debug_assert!(dst.next().is_none());
+
+    encoding
+}
+
+/// Absorbs the saved context into the callstack sponge by replacement, same as
+/// `apply_calls_and_ret` does on the call-like path
+pub fn absorb_execution_context_record<F: SmallField, R: AlgebraicRoundFunction<F, 8, 12, 4>>(
+    state: &[F; FULL_SPONGE_QUEUE_STATE_WIDTH],
+    record: &ExecutionContextRecordWitness<F>,
+) -> [F; FULL_SPONGE_QUEUE_STATE_WIDTH] {
+    let encoding = encode_execution_context_record_natively(record);
+    let mut new_state = *state;
+    for chunk in encoding.array_chunks::<8>() {
+        new_state[..8].copy_from_slice(chunk);
+        <R as AlgebraicRoundFunction<F, 8, 12, 4>>::round_function(&mut new_state);
+    }
+
+    new_state
+}
+
+/// Absorbs the query into the full state memory queue by replacement
+pub fn absorb_memory_query<F: SmallField, R: AlgebraicRoundFunction<F, 8, 12, 4>>(
+    state: &[F; FULL_SPONGE_QUEUE_STATE_WIDTH],
+    query: &MemoryQueryWitness<F>,
+) -> [F; FULL_SPONGE_QUEUE_STATE_WIDTH] {
+    let packed_query = encode_memory_query_natively(query);
+    let mut new_state = *state;
+    new_state[..packed_query.len()].copy_from_slice(&packed_query);
+    <R as AlgebraicRoundFunction<F, 8, 12, 4>>::round_function(&mut new_state);
+
+    new_state
+}
+
+pub(crate) fn zero_register<F: SmallField>() -> VMRegisterWitness<F> {
+    VMRegisterWitness {
+        is_pointer: false,
+        value: U256::zero(),
+    }
+}
+
+// we need to erase bits 32-64 and 64-96
+fn erase_fat_pointer_data<F: SmallField>(register: &mut VMRegisterWitness<F>) {
+    let mut limbs = u256_into_limbs(&register.value);
+    limbs[1] = 0;
+    limbs[2] = 0;
+    register.value = u256_from_limbs(limbs);
+    register.is_pointer = false;
+}
+
+#[inline]
+pub(crate) fn u256_limb(value: &U256, idx: usize) -> u32 {
+    (value.0[idx / 2] >> (32 * (idx % 2))) as u32
+}
+
+pub(crate) fn u256_into_limbs(value: &U256) -> [u32; 8] {
+    std::array::from_fn(|idx| u256_limb(value, idx))
+}
+
+pub(crate) fn u256_from_limbs(limbs: [u32; 8]) -> U256 {
+    let mut words = [0u64; 4];
+    for (dst, src) in words.iter_mut().zip(limbs.array_chunks::<2>()) {
+        *dst = (src[0] as u64) | ((src[1] as u64) << 32);
+    }
+
+    U256(words)
+}
+
+#[cfg(test)]
+mod test {
+    use super::differential::compare_vm_states;
+    use super::*;
+    use crate::base_structures::vm_state::VmLocalState;
+    use crate::main_vm::decoded_opcode::OPCODE_PROPS_BITMASK_FOR_BITSPREAD_ENCODING;
+    use crate::main_vm::recorded_trace::{RecordedTrace, RecordedTraceOracle, RecordedWitness};
+    use crate::main_vm::variants::{ARITHMETIC_FREE_VM_VARIANT, FULL_VM_VARIANT};
+    use boojum::field::goldilocks::GoldilocksField;
+    use boojum::gadgets::traits::allocatable::CSAllocatable;
+    use boojum::implementations::poseidon2::Poseidon2Goldilocks;
+
+    type F = GoldilocksField;
+    type R = Poseidon2Goldilocks;
+
+    fn reg_only_variant_idx(opcode: Opcode) -> usize {
+        (0..zkevm_opcode_defs::OPCODES_PROPS_INTEGER_BITMASKS.len())
+            .find(|idx| {
+                let bits = NativeOpcodeBitmask(
+                    zkevm_opcode_defs::OPCODES_PROPS_INTEGER_BITMASKS[*idx]
+                        & OPCODE_PROPS_BITMASK_FOR_BITSPREAD_ENCODING,
+                );
+                bits.boolean_for_opcode(opcode)
+                    && bits.boolean_for_src_mem_access(ImmMemHandlerFlags::UseRegOnly)
+                    && bits.boolean_for_dst_mem_access(ImmMemHandlerFlags::UseRegOnly)
+                    && !bits.flag(zkevm_opcode_defs::SET_FLAGS_FLAG_IDX)
+            })
+            .expect("variant must exist")
+    }
+
+    fn add_variant_idx() -> usize {
+        reg_only_variant_idx(Opcode::Add(zkevm_opcode_defs::AddOpcode::Add))
+    }
+
+    fn running_state() -> VmLocalStateWitness<F> {
+        let mut state = VmLocalState::<F>::placeholder_witness();
+        state.callstack.context_stack_depth = 1;
+        state.timestamp = 1024;
+        state.callstack.current_context.saved_context.code_page = 8;
+        state.callstack.current_context.saved_context.base_page = 16;
+        state.callstack.current_context.saved_context.ergs_remaining = 1000;
+
+        state
+    }
+
+    #[test]
+    fn test_reference_cycle_skips_on_empty_callstack() {
+        let state = VmLocalState::<F>::placeholder_witness();
+        let mut oracle = RecordedTraceOracle::new(RecordedTrace::<F>::default());
+
+        let new_state =
+            reference_vm_cycle::<F, R, _>(&state, &mut oracle, &FULL_VM_VARIANT).unwrap();
+        assert!(compare_vm_states(&state, &new_state).is_none());
+        oracle.check_fully_consumed().unwrap();
+    }
+
+    #[test]
+    fn test_reference_cycle_add() {
+        let variant = add_variant_idx();
+        // add r1, r2, r3 with "always" condition
+        let raw_opcode = (variant as u64)
+            | ((zkevm_opcode_defs::Condition::Always as u64)
+                << zkevm_opcode_defs::CONDITIONAL_BITS_SHIFT)
+            | (0x21u64 << 16)
+            | (0x03u64 << 24);
+
+        let mut trace = RecordedTrace::<F>::default();
+        trace.memory_reads = vec![RecordedWitness {
+            key: (1024, 8, 0),
+            value: MemoryWitness {
+                value: U256::from(raw_opcode) << 192,
+                is_ptr: false,
+            },
+        }];
+        let mut oracle = RecordedTraceOracle::new(trace);
+
+        let mut state = running_state();
+        state.registers[0].value = U256::from(3u64);
+        state.registers[1].value = U256::from(5u64);
+
+        let new_state =
+            reference_vm_cycle::<F, R, _>(&state, &mut oracle, &FULL_VM_VARIANT).unwrap();
+        oracle.check_fully_consumed().unwrap();
+
+        let saved_context = &new_state.callstack.current_context.saved_context;
+        assert_eq!(new_state.registers[2].value, U256::from(8u64));
+        assert_eq!(saved_context.pc, 1);
+        assert_eq!(
+            saved_context.ergs_remaining,
+            1000 - zkevm_opcode_defs::OPCODES_PRICES[variant] as u32
+        );
+        assert_eq!(new_state.timestamp, 1028);
+        assert_eq!(new_state.previous_code_page, 8);
+        // only the code read goes into the memory queue
+        assert_eq!(new_state.memory_queue_length, 1);
+        assert!(new_state.memory_queue_state != state.memory_queue_state);
+
+        // divergences are reported for the first differing field
+        let mut circuit_state = new_state.clone();
+        circuit_state.registers[2].value = U256::from(9u64);
+        circuit_state.timestamp = 1032;
+        let divergence = compare_vm_states(&circuit_state, &new_state).unwrap();
+        assert_eq!(divergence.field, "registers[2].value");
+
+        let mut circuit_state = new_state.clone();
+        circuit_state.callstack.current_context.saved_context.pc = 2;
+        let divergence = compare_vm_states(&circuit_state, &new_state).unwrap();
+        assert_eq!(
+            divergence.field,
+            "callstack.current_context.saved_context.pc"
+        );
+    }
+
+    #[test]
+    fn test_reference_cycle_near_call() {
+        let variant = (0..zkevm_opcode_defs::OPCODES_PROPS_INTEGER_BITMASKS.len())
+            .find(|idx| {
+                let bits = NativeOpcodeBitmask(
+                    zkevm_opcode_defs::OPCODES_PROPS_INTEGER_BITMASKS[*idx]
+                        & OPCODE_PROPS_BITMASK_FOR_BITSPREAD_ENCODING,
+                );
+                bits.boolean_for_opcode(Opcode::NearCall(zkevm_opcode_defs::NearCallOpcode))
+            })
+            .expect("near call variant must exist");
+        // near_call r1, 100, 200
+        let raw_opcode = (variant as u64)
+            | ((zkevm_opcode_defs::Condition::Always as u64)
+                << zkevm_opcode_defs::CONDITIONAL_BITS_SHIFT)
+            | (0x01u64 << 16)
+            | (100u64 << 32)
+            | (200u64 << 48);
+
+        let rollback_tail = [7u64, 8, 9, 10].map(F::from_u64_unchecked);
+        let mut trace = RecordedTrace::<F>::default();
+        trace.memory_reads = vec![RecordedWitness {
+            key: (1024, 8, 0),
+            value: MemoryWitness {
+                value: U256::from(raw_opcode) << 192,
+                is_ptr: false,
+            },
+        }];
+        trace.rollback_queue_tails_for_calls = vec![RecordedWitness {
+            key: 1028,
+            value: rollback_tail,
+        }];
+        let mut oracle = RecordedTraceOracle::new(trace);
+
+        let mut state = running_state();
+        state.registers[0].value = U256::from(30u64);
+        state.flags.equal = true;
+
+        let new_state =
+            reference_vm_cycle::<F, R, _>(&state, &mut oracle, &FULL_VM_VARIANT).unwrap();
+        oracle.check_fully_consumed().unwrap();
+
+        let ergs_left = 1000 - zkevm_opcode_defs::OPCODES_PRICES[variant] as u32;
+        let ergs_passed = 30
+            * zkevm_opcode_defs::system_params::INTERNAL_ERGS_TO_VISIBLE_ERGS_CONVERSION_CONSTANT;
+
+        let callstack = &new_state.callstack;
+        let saved_context = &callstack.current_context.saved_context;
+        assert_eq!(callstack.context_stack_depth, 2);
+        assert_eq!(saved_context.pc, 100);
+        assert_eq!(saved_context.exception_handler_loc, 200);
+        assert_eq!(saved_context.ergs_remaining, ergs_passed);
+        assert!(saved_context.is_local_call);
+        assert_eq!(saved_context.reverted_queue_head, rollback_tail);
+        assert_eq!(saved_context.reverted_queue_tail, rollback_tail);
+        assert!(!new_state.flags.equal);
+
+        // caller's frame is saved with the return address and the ergs it kept
+        let mut saved_frame = state.callstack.current_context.saved_context.clone();
+        saved_frame.pc = 1;
+        saved_frame.ergs_remaining = ergs_left - ergs_passed;
+        assert_eq!(
+            callstack.stack_sponge_state,
+            absorb_execution_context_record::<F, R>(
+                &state.callstack.stack_sponge_state,
+                &saved_frame
+            )
+        );
+    }
+
+    #[test]
+    fn test_reference_cycle_reports_unsupported_opcodes() {
+        let mut state = running_state();
+        // pending exception is executed as a revert, that is not mirrored
+        state.pending_exception = true;
+        let mut oracle = RecordedTraceOracle::new(RecordedTrace::<F>::default());
+
+        assert_eq!(
+            reference_vm_cycle::<F, R, _>(&state, &mut oracle, &FULL_VM_VARIANT),
+            Err(ReferenceExecutionError::UnsupportedOpcode("ret"))
+        );
+    }
+
+    #[test]
+    fn test_reference_cycle_respects_variant() {
+        let variant = reg_only_variant_idx(Opcode::Mul(zkevm_opcode_defs::MulOpcode));
+        // mul r1, r2, r3, r4 with "always" condition
+        let raw_opcode = (variant as u64)
+            | ((zkevm_opcode_defs::Condition::Always as u64)
+                << zkevm_opcode_defs::CONDITIONAL_BITS_SHIFT)
+            | (0x21u64 << 16)
+            | (0x43u64 << 24);
+
+        let mut trace = RecordedTrace::<F>::default();
+        trace.memory_reads = vec![RecordedWitness {
+            key: (1024, 8, 0),
+            value: MemoryWitness {
+                value: U256::from(raw_opcode) << 192,
+                is_ptr: false,
+            },
+        }];
+
+        let state = running_state();
+        let mut oracle = RecordedTraceOracle::new(trace.clone());
+        assert_eq!(
+            reference_vm_cycle::<F, R, _>(&state, &mut oracle, &ARITHMETIC_FREE_VM_VARIANT),
+            Err(ReferenceExecutionError::ExcludedByVariant {
+                variant: "arithmetic_free",
+                family: "mul_div",
+            })
+        );
+
+        let mut oracle = RecordedTraceOracle::new(trace);
+        assert!(reference_vm_cycle::<F, R, _>(&state, &mut oracle, &FULL_VM_VARIANT).is_ok());
+    }
+}
@@ -0,0 +1,511 @@
+use super::*;
+
+use crate::ethereum_types::U512;
+use crate::main_vm::opcode_bitmask::SUPPORTED_ISA_VERSION;
+use zkevm_opcode_defs::*;
+
+fn register_from_u256<F: SmallField>(value: U256) -> VMRegisterWitness<F> {
+    VMRegisterWitness {
+        is_pointer: false,
+        value,
+    }
+}
+
+fn flags_from_booleans<F: SmallField>(
+    overflow_or_less_than: bool,
+    equal: bool,
+    greater_than: bool,
+) -> ArithmeticFlagsPortWitness<F> {
+    ArithmeticFlagsPortWitness {
+        overflow_or_less_than,
+        equal,
+        greater_than,
+    }
+}
+
+pub(crate) fn apply_add_sub<F: SmallField>(
+    _draft_vm_state: &VmLocalStateWitness<F>,
+    common_opcode_state: &NativeCommonOpcodeState<F>,
+    _opcode_carry_parts: &NativeAfterDecodingCarryParts,
+    diffs_accumulator: &mut NativeStateDiffs<F>,
+) {
+    const ADD_OPCODE: Opcode = Opcode::Add(AddOpcode::Add);
+    const SUB_OPCODE: Opcode = Opcode::Sub(SubOpcode::Sub);
+
+    let properties_bits = &common_opcode_state.decoded_opcode.properties_bits;
+    let apply_add = properties_bits.boolean_for_opcode(ADD_OPCODE);
+    let apply_sub = properties_bits.boolean_for_opcode(SUB_OPCODE);
+
+    let a = common_opcode_state.src0.value;
+    let b = common_opcode_state.src1.value;
+    let (result, of) = if apply_add {
+        a.overflowing_add(b)
+    } else {
+        a.overflowing_sub(b)
+    };
+    let result_is_zero = result.is_zero();
+
+    // gt = !of & !zero, so it's !(of || zero)
+    let candidate_flags = flags_from_booleans(of, result_is_zero, !(of || result_is_zero));
+
+    let apply_any = apply_add || apply_sub;
+    let update_flags = apply_any && properties_bits.flag(SET_FLAGS_FLAG_IDX);
+
+    let can_write_into_memory = ADD_OPCODE.can_write_dst0_into_memory(SUPPORTED_ISA_VERSION);
+
+    diffs_accumulator.dst_0_values.push((
+        can_write_into_memory,
+        apply_any,
+        register_from_u256(result),
+    ));
+    diffs_accumulator
+        .flags
+        .push((update_flags, candidate_flags));
+}
+
+pub(crate) fn apply_jump<F: SmallField>(
+    _draft_vm_state: &VmLocalStateWitness<F>,
+    common_opcode_state: &NativeCommonOpcodeState<F>,
+    opcode_carry_parts: &NativeAfterDecodingCarryParts,
+    diffs_accumulator: &mut NativeStateDiffs<F>,
+) {
+    const JUMP_OPCODE: Opcode = Opcode::Jump(JumpOpcode);
+
+    let should_apply = common_opcode_state
+        .decoded_opcode
+        .properties_bits
+        .boolean_for_opcode(JUMP_OPCODE);
+
+    // the new pc is the lowest 16 bits of src0
+    let jump_dst = common_opcode_state.src0.value.low_u32() as u16;
+
+    // save next_pc into dst0
+    let dst0 = register_from_u256(U256::from(opcode_carry_parts.next_pc));
+
+    let can_write_into_memory = JUMP_OPCODE.can_write_dst0_into_memory(SUPPORTED_ISA_VERSION);
+
+    diffs_accumulator
+        .dst_0_values
+        .push((can_write_into_memory, should_apply, dst0));
+    diffs_accumulator
+        .new_pc_candidates
+        .push((should_apply, jump_dst));
+}
+
+pub(crate) fn apply_binop<F: SmallField>(
+    _draft_vm_state: &VmLocalStateWitness<F>,
+    common_opcode_state: &NativeCommonOpcodeState<F>,
+    _opcode_carry_parts: &NativeAfterDecodingCarryParts,
+    diffs_accumulator: &mut NativeStateDiffs<F>,
+) {
+    const AND_OPCODE: Opcode = Opcode::Binop(BinopOpcode::And);
+    const OR_OPCODE: Opcode = Opcode::Binop(BinopOpcode::Or);
+
+    let properties_bits = &common_opcode_state.decoded_opcode.properties_bits;
+    let should_apply = properties_bits.boolean_for_opcode(AND_OPCODE);
+    let is_and = properties_bits.boolean_for_variant(AND_OPCODE);
+    let is_or = properties_bits.boolean_for_variant(OR_OPCODE);
+
+    let a = common_opcode_state.src0.value;
+    let b = common_opcode_state.src1.value;
+    let result = if is_and {
+        a & b
+    } else if is_or {
+        a | b
+    } else {
+        a ^ b
+    };
+
+    let candidate_flags = flags_from_booleans(false, result.is_zero(), false);
+    let update_flags = should_apply && properties_bits.flag(SET_FLAGS_FLAG_IDX);
+
+    let can_write_into_memory = AND_OPCODE.can_write_dst0_into_memory(SUPPORTED_ISA_VERSION);
+
+    diffs_accumulator.dst_0_values.push((
+        can_write_into_memory,
+        should_apply,
+        register_from_u256(result),
+    ));
+    diffs_accumulator
+        .flags
+        .push((update_flags, candidate_flags));
+}
+
+pub(crate) fn apply_context<F: SmallField>(
+    draft_vm_state: &VmLocalStateWitness<F>,
+    common_opcode_state: &NativeCommonOpcodeState<F>,
+    opcode_carry_parts: &NativeAfterDecodingCarryParts,
+    diffs_accumulator: &mut NativeStateDiffs<F>,
+) {
+    use zkevm_opcode_defs::definitions::context::ContextOpcode;
+
+    let properties_bits = &common_opcode_state.decoded_opcode.properties_bits;
+    let should_apply = properties_bits.boolean_for_opcode(Opcode::Context(ContextOpcode::This));
+    let is_variant = |variant| properties_bits.boolean_for_variant(Opcode::Context(variant));
+
+    let is_set_context_u128 = is_variant(ContextOpcode::SetContextU128);
+    let is_increment_tx_number = is_variant(ContextOpcode::IncrementTxNumber);
+
+    let saved_context = &draft_vm_state.callstack.current_context.saved_context;
+
+    let result = if is_variant(ContextOpcode::This) {
+        address_to_u256(&saved_context.this)
+    } else if is_variant(ContextOpcode::Caller) {
+        address_to_u256(&saved_context.caller)
+    } else if is_variant(ContextOpcode::CodeAddress) {
+        address_to_u256(&saved_context.code_address)
+    } else if is_variant(ContextOpcode::Meta) {
+        let revert_counter = if saved_context.is_kernel_mode {
+            draft_vm_state.pubdata_revert_counter
+        } else {
+            0
+        };
+        let shards = u32::from_le_bytes([
+            saved_context.this_shard_id,
+            saved_context.caller_shard_id,
+            saved_context.code_shard_id,
+            0,
+        ]);
+        u256_from_limbs([
+            revert_counter,
+            0,
+            saved_context.heap_upper_bound,
+            saved_context.aux_heap_upper_bound,
+            0,
+            0,
+            0,
+            shards,
+        ])
+    } else if is_variant(ContextOpcode::ErgsLeft) {
+        U256::from(
+            opcode_carry_parts.preliminary_ergs_left
+                / zkevm_opcode_defs::system_params::INTERNAL_ERGS_TO_VISIBLE_ERGS_CONVERSION_CONSTANT,
+        )
+    } else if is_variant(ContextOpcode::Sp) {
+        U256::from(saved_context.sp)
+    } else if is_variant(ContextOpcode::GetContextU128) {
+        let [l0, l1, l2, l3] = saved_context.context_u128_value_composite;
+        u256_from_limbs([l0, l1, l2, l3, 0, 0, 0, 0])
+    } else {
+        U256::zero()
+    };
+
+    let write_to_dst0 = should_apply && !(is_set_context_u128 || is_increment_tx_number);
+
+    let can_write_into_memory =
+        Opcode::Context(ContextOpcode::This).can_write_dst0_into_memory(SUPPORTED_ISA_VERSION);
+
+    diffs_accumulator.dst_0_values.push((
+        can_write_into_memory,
+        write_to_dst0,
+        register_from_u256(result),
+    ));
+
+    let src0_limbs = u256_into_limbs(&common_opcode_state.src0.value);
+    diffs_accumulator.context_u128_candidates.push((
+        should_apply && is_set_context_u128,
+        [src0_limbs[0], src0_limbs[1], src0_limbs[2], src0_limbs[3]],
+    ));
+
+    debug_assert!(diffs_accumulator.new_tx_number.is_none());
+    diffs_accumulator.new_tx_number = Some((
+        should_apply && is_increment_tx_number,
+        draft_vm_state.tx_number_in_block.wrapping_add(1),
+    ));
+}
+
+pub(crate) fn apply_ptr<F: SmallField>(
+    _draft_vm_state: &VmLocalStateWitness<F>,
+    common_opcode_state: &NativeCommonOpcodeState<F>,
+    _opcode_carry_parts: &NativeAfterDecodingCarryParts,
+    diffs_accumulator: &mut NativeStateDiffs<F>,
+) {
+    const PTR_ADD_OPCODE: Opcode = Opcode::Ptr(PtrOpcode::Add);
+    const PTR_SUB_OPCODE: Opcode = Opcode::Ptr(PtrOpcode::Sub);
+    const PTR_PACK_OPCODE: Opcode = Opcode::Ptr(PtrOpcode::Pack);
+    const PTR_SHRINK_OPCODE: Opcode = Opcode::Ptr(PtrOpcode::Shrink);
+
+    let properties_bits = &common_opcode_state.decoded_opcode.properties_bits;
+    let should_apply = properties_bits.boolean_for_opcode(PTR_ADD_OPCODE);
+    let is_ptr_add = properties_bits.boolean_for_variant(PTR_ADD_OPCODE);
+    let is_ptr_sub = properties_bits.boolean_for_variant(PTR_SUB_OPCODE);
+    let is_ptr_pack = properties_bits.boolean_for_variant(PTR_PACK_OPCODE);
+    let is_ptr_shrink = properties_bits.boolean_for_variant(PTR_SHRINK_OPCODE);
+
+    let src0 = &common_opcode_state.src0;
+    let src1 = &common_opcode_state.src1;
+    let src0_limbs = u256_into_limbs(&src0.value);
+    let src1_limbs = u256_into_limbs(&src1.value);
+
+    let args_valid = src0.is_pointer && !src1.is_pointer;
+
+    let src1_32_to_256_is_zero = src1_limbs[1..].iter().all(|el| *el == 0);
+    let src1_0_to_128_is_zero = src1_limbs[..4].iter().all(|el| *el == 0);
+
+    let too_large_offset = !src1_32_to_256_is_zero && (is_ptr_add || is_ptr_sub);
+    let dirty_value_for_pack = !src1_0_to_128_is_zero && is_ptr_pack;
+
+    let (result_for_ptr_add, of) = src0_limbs[0].overflowing_add(src1_limbs[0]);
+    let overflow_panic_if_add = of && is_ptr_add;
+
+    let (result_for_ptr_sub, uf) = src0_limbs[0].overflowing_sub(src1_limbs[0]);
+    let underflow_panic_if_sub = uf && is_ptr_sub;
+
+    let (result_for_ptr_shrink, uf) = src0_limbs[3].overflowing_sub(src1_limbs[0]);
+    let underflow_panic_if_shrink = uf && is_ptr_shrink;
+
+    let any_potential_panic = !args_valid
+        || too_large_offset
+        || dirty_value_for_pack
+        || overflow_panic_if_add
+        || underflow_panic_if_sub
+        || underflow_panic_if_shrink;
+
+    let should_panic = should_apply && any_potential_panic;
+    let ok_to_execute = !any_potential_panic;
+    let should_update_register = should_apply && ok_to_execute;
+
+    let low_u32 = if is_ptr_add {
+        result_for_ptr_add
+    } else if is_ptr_sub {
+        result_for_ptr_sub
+    } else {
+        src0_limbs[0]
+    };
+    let bits_96_to_128 = if is_ptr_shrink {
+        result_for_ptr_shrink
+    } else {
+        src0_limbs[3]
+    };
+    let high_limbs_source = if is_ptr_pack {
+        &src1_limbs
+    } else {
+        &src0_limbs
+    };
+
+    let dst0 = VMRegisterWitness {
+        is_pointer: src0.is_pointer,
+        value: u256_from_limbs([
+            low_u32,
+            src0_limbs[1],
+            src0_limbs[2],
+            bits_96_to_128,
+            high_limbs_source[4],
+            high_limbs_source[5],
+            high_limbs_source[6],
+            high_limbs_source[7],
+        ]),
+    };
+
+    let can_write_into_memory = PTR_ADD_OPCODE.can_write_dst0_into_memory(SUPPORTED_ISA_VERSION);
+
+    diffs_accumulator
+        .dst_0_values
+        .push((can_write_into_memory, should_update_register, dst0));
+    diffs_accumulator.pending_exceptions.push(should_panic);
+}
+
+pub(crate) fn apply_near_call<
+    F: SmallField,
+    R: AlgebraicRoundFunction<F, 8, 12, 4>,
+    W: WitnessOracle<F>,
+>(
+    draft_vm_state: &VmLocalStateWitness<F>,
+    common_opcode_state: &NativeCommonOpcodeState<F>,
+    opcode_carry_parts: &NativeAfterDecodingCarryParts,
+    diffs_accumulator: &mut NativeStateDiffs<F>,
+    witness_oracle: &mut W,
+) {
+    const NEAR_CALL_OPCODE: Opcode = Opcode::NearCall(NearCallOpcode);
+
+    let decoded_opcode = &common_opcode_state.decoded_opcode;
+    let execute = decoded_opcode
+        .properties_bits
+        .boolean_for_opcode(NEAR_CALL_OPCODE);
+
+    let callstack = &draft_vm_state.callstack;
+    let mut current_callstack_entry = callstack.current_context.saved_context.clone();
+    current_callstack_entry.pc = opcode_carry_parts.next_pc;
+
+    let mut new_callstack_entry = current_callstack_entry.clone();
+    // on call-like path we continue the forward queue, but take the rollback queue state from witness
+    let potential_rollback_queue_segment_tail =
+        witness_oracle.get_rollback_queue_tail_witness_for_call(draft_vm_state.timestamp, execute);
+    new_callstack_entry.reverted_queue_tail = potential_rollback_queue_segment_tail;
+    new_callstack_entry.reverted_queue_head = potential_rollback_queue_segment_tail;
+    new_callstack_entry.reverted_queue_segment_len = 0;
+    new_callstack_entry.total_pubdata_spent = 0;
+
+    // ergs are passed in the lowest 32 bits of src0, and the conversion saturates
+    let ergs_passed = (common_opcode_state.src0.value.low_u32() as u64)
+        * (zkevm_opcode_defs::system_params::INTERNAL_ERGS_TO_VISIBLE_ERGS_CONVERSION_CONSTANT
+            as u64);
+    let ergs_passed = u32::try_from(ergs_passed).unwrap_or(u32::MAX);
+
+    let preliminary_ergs_left = opcode_carry_parts.preliminary_ergs_left;
+    let ergs_to_pass = if ergs_passed == 0 {
+        preliminary_ergs_left
+    } else {
+        ergs_passed
+    };
+    // if underflow than we pass everything
+    let (remaining_for_this_context, passed_ergs) =
+        match preliminary_ergs_left.checked_sub(ergs_to_pass) {
+            Some(remaining) => (remaining, ergs_to_pass),
+            None => (0, preliminary_ergs_left),
+        };
+
+    current_callstack_entry.ergs_remaining = remaining_for_this_context;
+    witness_oracle.push_callstack_witness(
+        &current_callstack_entry,
+        callstack.context_stack_depth,
+        execute,
+    );
+
+    new_callstack_entry.ergs_remaining = passed_ergs;
+    new_callstack_entry.pc = decoded_opcode.imm0;
+    new_callstack_entry.exception_handler_loc = decoded_opcode.imm1;
+    new_callstack_entry.is_local_call = true;
+
+    let new_depth = callstack.context_stack_depth.wrapping_add(1);
+    witness_oracle.report_new_callstack_frame(&new_callstack_entry, new_depth, true, execute);
+
+    let mut new_callstack = callstack.clone();
+    new_callstack.stack_sponge_state = absorb_execution_context_record::<F, R>(
+        &callstack.stack_sponge_state,
+        &current_callstack_entry,
+    );
+    new_callstack.context_stack_depth = new_depth;
+    new_callstack.current_context.saved_context = new_callstack_entry;
+
+    // all call-like opcodes reset flags in full
+    diffs_accumulator
+        .flags
+        .push((execute, flags_from_booleans(false, false, false)));
+    diffs_accumulator.callstacks.push((execute, new_callstack));
+}
+
+pub(crate) fn apply_mul_div<F: SmallField>(
+    _draft_vm_state: &VmLocalStateWitness<F>,
+    common_opcode_state: &NativeCommonOpcodeState<F>,
+    _opcode_carry_parts: &NativeAfterDecodingCarryParts,
+    diffs_accumulator: &mut NativeStateDiffs<F>,
+) {
+    const MUL_OPCODE: Opcode = Opcode::Mul(MulOpcode);
+    const DIV_OPCODE: Opcode = Opcode::Div(DivOpcode);
+
+    let properties_bits = &common_opcode_state.decoded_opcode.properties_bits;
+    let should_apply_mul = properties_bits.boolean_for_opcode(MUL_OPCODE);
+    let should_apply_div = properties_bits.boolean_for_opcode(DIV_OPCODE);
+    let apply_any = should_apply_mul || should_apply_div;
+    let should_set_flags = properties_bits.flag(SET_FLAGS_FLAG_IDX);
+
+    let a = common_opcode_state.src0.value;
+    let b = common_opcode_state.src1.value;
+
+    let (result_0, result_1, candidate_flags) = if should_apply_mul {
+        let full: U512 = a.full_mul(b);
+        let mut bytes = [0u8; 64];
+        full.to_little_endian(&mut bytes);
+        let low = U256::from_little_endian(&bytes[..32]);
+        let high = U256::from_little_endian(&bytes[32..]);
+
+        let of = !high.is_zero();
+        let eq = low.is_zero();
+        let flags = flags_from_booleans(of, eq, !of && !eq);
+
+        (low, high, flags)
+    } else {
+        let divisor_is_zero = b.is_zero();
+        // division by zero gives zero quotient and, after masking, zero remainder
+        let (quotient, remainder) = if divisor_is_zero {
+            (U256::zero(), a)
+        } else {
+            a.div_mod(b)
+        };
+        let flags = flags_from_booleans(
+            divisor_is_zero,
+            !divisor_is_zero && quotient.is_zero(),
+            !divisor_is_zero && remainder.is_zero(),
+        );
+        let remainder = if divisor_is_zero {
+            U256::zero()
+        } else {
+            remainder
+        };
+
+        (quotient, remainder, flags)
+    };
+
+    let can_write_into_memory = MUL_OPCODE.can_write_dst0_into_memory(SUPPORTED_ISA_VERSION);
+
+    diffs_accumulator.dst_0_values.push((
+        can_write_into_memory,
+        apply_any,
+        register_from_u256(result_0),
+    ));
+    diffs_accumulator
+        .dst_1_values
+        .push((apply_any, register_from_u256(result_1)));
+    diffs_accumulator
+        .flags
+        .push((apply_any && should_set_flags, candidate_flags));
+}
+
+pub(crate) fn apply_shifts<F: SmallField>(
+    _draft_vm_state: &VmLocalStateWitness<F>,
+    common_opcode_state: &NativeCommonOpcodeState<F>,
+    _opcode_carry_parts: &NativeAfterDecodingCarryParts,
+    diffs_accumulator: &mut NativeStateDiffs<F>,
+) {
+    const SHL_OPCODE: Opcode = Opcode::Shift(ShiftOpcode::Shl);
+    const ROL_OPCODE: Opcode = Opcode::Shift(ShiftOpcode::Rol);
+    const SHR_OPCODE: Opcode = Opcode::Shift(ShiftOpcode::Shr);
+    const ROR_OPCODE: Opcode = Opcode::Shift(ShiftOpcode::Ror);
+
+    let properties_bits = &common_opcode_state.decoded_opcode.properties_bits;
+    let should_apply = properties_bits.boolean_for_opcode(SHL_OPCODE);
+    let is_rol = properties_bits.boolean_for_variant(ROL_OPCODE);
+    let is_shr = properties_bits.boolean_for_variant(SHR_OPCODE);
+    let is_ror = properties_bits.boolean_for_variant(ROR_OPCODE);
+
+    let reg = common_opcode_state.src0.value;
+    // only the lowest byte of src1 is used as a shift amount
+    let shift = (common_opcode_state.src1.value.low_u32() & 0xff) as usize;
+
+    let rotate_left = |shift: usize| {
+        if shift == 0 {
+            reg
+        } else {
+            (reg << shift) | (reg >> (256 - shift))
+        }
+    };
+
+    let result = if is_rol {
+        rotate_left(shift)
+    } else if is_ror {
+        rotate_left((256 - shift) % 256)
+    } else if is_shr {
+        reg >> shift
+    } else {
+        reg << shift
+    };
+
+    let candidate_flags = flags_from_booleans(false, result.is_zero(), false);
+    let set_flags_and_execute = should_apply && properties_bits.flag(SET_FLAGS_FLAG_IDX);
+
+    let can_write_into_memory = SHL_OPCODE.can_write_dst0_into_memory(SUPPORTED_ISA_VERSION);
+
+    diffs_accumulator.dst_0_values.push((
+        can_write_into_memory,
+        should_apply,
+        register_from_u256(result),
+    ));
+    diffs_accumulator
+        .flags
+        .push((set_flags_and_execute, candidate_flags));
+}
+
+fn address_to_u256(address: &crate::ethereum_types::Address) -> U256 {
+    U256::from_big_endian(address.as_bytes())
+}
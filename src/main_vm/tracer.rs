@@ -0,0 +1,539 @@
+use super::*;
+
+use crate::base_structures::register::VMRegisterWitness;
+use crate::base_structures::vm_state::{ArithmeticFlagsPortWitness, FULL_SPONGE_QUEUE_STATE_WIDTH};
+use crate::main_vm::opcode_bitmask::{OpcodeBitmaskWitness, OPCODE_FLAGS_BITS};
+use crate::main_vm::state_diffs::StateDiffsAccumulator;
+
+use std::io::Write;
+
+use zkevm_opcode_defs::{ImmMemHandlerFlags, Opcode, REGISTERS_COUNT};
+
+const OPCODE_FAMILIES: [(Opcode, &'static str); 16] = [
+    (Opcode::Nop(zkevm_opcode_defs::NopOpcode), "nop"),
+    (Opcode::Add(zkevm_opcode_defs::AddOpcode::Add), "add"),
+    (Opcode::Sub(zkevm_opcode_defs::SubOpcode::Sub), "sub"),
+    (Opcode::Mul(zkevm_opcode_defs::MulOpcode), "mul"),
+    (Opcode::Div(zkevm_opcode_defs::DivOpcode), "div"),
+    (Opcode::Jump(zkevm_opcode_defs::JumpOpcode), "jump"),
+    (
+        Opcode::Context(zkevm_opcode_defs::definitions::context::ContextOpcode::This),
+        "context",
+    ),
+    (Opcode::Shift(zkevm_opcode_defs::ShiftOpcode::Shl), "shift"),
+    (Opcode::Binop(zkevm_opcode_defs::BinopOpcode::And), "binop"),
+    (Opcode::Ptr(zkevm_opcode_defs::PtrOpcode::Add), "ptr"),
+    (
+        Opcode::NearCall(zkevm_opcode_defs::NearCallOpcode),
+        "near_call",
+    ),
+    (
+        Opcode::Log(zkevm_opcode_defs::LogOpcode::StorageRead),
+        "log",
+    ),
+    (
+        Opcode::FarCall(zkevm_opcode_defs::FarCallOpcode::Normal),
+        "far_call",
+    ),
+    (Opcode::Ret(zkevm_opcode_defs::RetOpcode::Ok), "ret"),
+    (Opcode::UMA(zkevm_opcode_defs::UMAOpcode::HeapRead), "uma"),
+    (Opcode::Invalid(zkevm_opcode_defs::InvalidOpcode), "invalid"),
+];
+
+const MEMORY_ACCESS_MODES: [ImmMemHandlerFlags; 6] = [
+    ImmMemHandlerFlags::UseRegOnly,
+    ImmMemHandlerFlags::UseStackWithPushPop,
+    ImmMemHandlerFlags::UseStackWithOffset,
+    ImmMemHandlerFlags::UseAbsoluteOnStack,
+    ImmMemHandlerFlags::UseImm16Only,
+    ImmMemHandlerFlags::UseCodePage,
+];
+
+/// Receives structured events for every VM cycle. Events are only produced
+/// if witness is evaluated, and the tracer is enabled
+pub trait VmCycleTracer<F: SmallField> {
+    /// Collecting events requires witness resolution at every cycle, so tracers can opt out
+    fn is_enabled(&self) -> bool {
+        true
+    }
+
+    /// Called by the entry point before every cycle
+    fn start_cycle(&mut self, cycle_idx: usize);
+
+    fn on_event(&mut self, event: VmCycleEvent<F>);
+}
+
+/// Tracer that ignores everything
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopVmCycleTracer;
+
+impl<F: SmallField> VmCycleTracer<F> for NoopVmCycleTracer {
+    fn is_enabled(&self) -> bool {
+        false
+    }
+
+    fn start_cycle(&mut self, _cycle_idx: usize) {}
+
+    fn on_event(&mut self, _event: VmCycleEvent<F>) {}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct TracedOpcode {
+    pub family: &'static str,
+    pub variant: Option<usize>,
+    pub flags: [bool; OPCODE_FLAGS_BITS],
+    pub src_memory_access: Option<String>,
+    pub dst_memory_access: Option<String>,
+    pub src_registers: [Option<usize>; 2],
+    pub dst_registers: [Option<usize>; 2],
+    pub imm0: u16,
+    pub imm1: u16,
+}
+
+/// Update from the `StateDiffsAccumulator` whose applicability flag is set
+#[derive(Derivative, serde::Serialize)]
+#[derivative(Clone, Debug)]
+#[serde(bound = "", tag = "kind", rename_all = "snake_case")]
+pub enum FiredStateDiff<F: SmallField> {
+    Dst0 {
+        can_write_into_memory: bool,
+        value: VMRegisterWitness<F>,
+    },
+    Dst1 {
+        value: VMRegisterWitness<F>,
+    },
+    Flags {
+        flags: ArithmeticFlagsPortWitness<F>,
+    },
+    SpecificRegisterUpdate {
+        register: usize,
+        value: VMRegisterWitness<F>,
+    },
+    RegisterZeroing {
+        register: usize,
+    },
+    PointerMarkerRemoval {
+        register: usize,
+    },
+    PendingException,
+    ErgsLeft {
+        value: u32,
+    },
+    Pc {
+        value: u16,
+    },
+    TxNumber {
+        value: u32,
+    },
+    PubdataRevertCounter {
+        value: u32,
+    },
+    HeapBound {
+        value: u32,
+    },
+    AuxHeapBound {
+        value: u32,
+    },
+    ContextU128 {
+        value: [u32; 4],
+    },
+    Callstack {
+        depth: u32,
+    },
+    DecommitmentQueue {
+        length: u32,
+    },
+    MemoryQueue {
+        length: u32,
+    },
+    LogQueueForward {
+        length: u32,
+    },
+    LogQueueRollback {
+        length: u32,
+    },
+    PubdataCost {
+        cost: i32,
+    },
+}
+
+#[derive(Derivative, serde::Serialize)]
+#[derivative(Clone, Debug)]
+#[serde(bound = "", tag = "event", rename_all = "snake_case")]
+pub enum VmCycleEvent<F: SmallField> {
+    CycleStarted {
+        pc: u16,
+        timestamp: u32,
+        callstack_depth: u32,
+        pending_exception: bool,
+        flags: ArithmeticFlagsPortWitness<F>,
+    },
+    OpcodeDecoded {
+        skipped: bool,
+        executes_pending_exception: bool,
+        opcode: TracedOpcode,
+    },
+    SourcesResolved {
+        src0: VMRegisterWitness<F>,
+        src1: VMRegisterWitness<F>,
+    },
+    StateDiff {
+        diff: FiredStateDiff<F>,
+    },
+    DestinationsResolved {
+        dst0: VMRegisterWitness<F>,
+        dst1: VMRegisterWitness<F>,
+        dst0_written_to_memory: bool,
+        dst0_page: u32,
+        dst0_index: u32,
+    },
+    SpongePushed {
+        initial_state: [F; FULL_SPONGE_QUEUE_STATE_WIDTH],
+        final_state: [F; FULL_SPONGE_QUEUE_STATE_WIDTH],
+    },
+    ErgsChanged {
+        before: u32,
+        after: u32,
+    },
+    PubdataChanged {
+        revert_counter_before: u32,
+        revert_counter_after: u32,
+        total_spent_before: u32,
+        total_spent_after: u32,
+    },
+    CycleFinished {
+        pc: u16,
+        timestamp: u32,
+        pending_exception: bool,
+        registers: [VMRegisterWitness<F>; REGISTERS_COUNT],
+    },
+}
+
+#[derive(serde::Serialize)]
+#[serde(bound = "")]
+struct JsonLine<'a, F: SmallField> {
+    cycle: usize,
+    #[serde(flatten)]
+    event: &'a VmCycleEvent<F>,
+}
+
+/// Writes every event as a single JSON object per line, tagged with the cycle index
+pub struct JsonLinesVmCycleTracer<W: Write> {
+    writer: W,
+    current_cycle: usize,
+    error: Option<std::io::Error>,
+}
+
+impl<W: Write> JsonLinesVmCycleTracer<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            current_cycle: 0,
+            error: None,
+        }
+    }
+
+    /// Flushes the writer and returns it back, or the first error that happened during tracing
+    pub fn finish(mut self) -> std::io::Result<W> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+impl<F: SmallField, W: Write> VmCycleTracer<F> for JsonLinesVmCycleTracer<W> {
+    fn is_enabled(&self) -> bool {
+        // there is no point to continue after the first error
+        self.error.is_none()
+    }
+
+    fn start_cycle(&mut self, cycle_idx: usize) {
+        self.current_cycle = cycle_idx;
+    }
+
+    fn on_event(&mut self, event: VmCycleEvent<F>) {
+        if self.error.is_some() {
+            return;
+        }
+
+        let line = JsonLine {
+            cycle: self.current_cycle,
+            event: &event,
+        };
+        let result = serde_json::to_writer(&mut self.writer, &line)
+            .map_err(std::io::Error::from)
+            .and_then(|_| self.writer.write_all(b"\n"));
+        if let Err(error) = result {
+            self.error = Some(error);
+        }
+    }
+}
+
+fn first_set(booleans: &[bool]) -> Option<usize> {
+    booleans.iter().position(|el| *el)
+}
+
+pub(crate) fn trace_opcode<F: SmallField>(
+    properties_bits: &OpcodeBitmaskWitness<F>,
+    src_regs_selectors: &[[bool; REGISTERS_COUNT]; 2],
+    dst_regs_selectors: &[[bool; REGISTERS_COUNT]; 2],
+    imm0: u16,
+    imm1: u16,
+) -> TracedOpcode {
+    let family = OPCODE_FAMILIES
+        .iter()
+        .find(|(opcode, _)| properties_bits.opcode_type_booleans[opcode.variant_idx()])
+        .map(|(_, family)| *family)
+        .unwrap_or("unknown");
+    let memory_access = |booleans: &[bool]| {
+        MEMORY_ACCESS_MODES
+            .iter()
+            .find(|el| booleans.get(el.variant_index()).copied().unwrap_or(false))
+            .map(|el| format!("{:?}", el))
+    };
+
+    TracedOpcode {
+        family,
+        variant: first_set(&properties_bits.opcode_variant_booleans),
+        flags: properties_bits.flag_booleans,
+        src_memory_access: memory_access(&properties_bits.input_variant_booleans),
+        dst_memory_access: memory_access(&properties_bits.output_variant_booleans),
+        src_registers: src_regs_selectors.map(|el| first_set(&el)),
+        dst_registers: dst_regs_selectors.map(|el| first_set(&el)),
+        imm0,
+        imm1,
+    }
+}
+
+/// Collects all the candidates in the accumulator that will be applied at this cycle.
+/// Returns `None` if witness is not yet resolved
+pub(crate) fn collect_fired_state_diffs<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &CS,
+    diffs_accumulator: &StateDiffsAccumulator<F>,
+) -> Option<Vec<FiredStateDiff<F>>> {
+    let mut fired = vec![];
+
+    for (can_write_into_memory, applies, value) in diffs_accumulator.dst_0_values.iter() {
+        if applies.witness_hook(cs)()? {
+            fired.push(FiredStateDiff::Dst0 {
+                can_write_into_memory: *can_write_into_memory,
+                value: value.witness_hook(cs)()?,
+            });
+        }
+    }
+    for (applies, value) in diffs_accumulator.dst_1_values.iter() {
+        if applies.witness_hook(cs)()? {
+            fired.push(FiredStateDiff::Dst1 {
+                value: value.witness_hook(cs)()?,
+            });
+        }
+    }
+    for (applies, flags) in diffs_accumulator.flags.iter() {
+        if applies.witness_hook(cs)()? {
+            fired.push(FiredStateDiff::Flags {
+                flags: flags.witness_hook(cs)()?,
+            });
+        }
+    }
+    for register in 0..REGISTERS_COUNT {
+        for (applies, value) in diffs_accumulator.specific_registers_updates[register].iter() {
+            if applies.witness_hook(cs)()? {
+                fired.push(FiredStateDiff::SpecificRegisterUpdate {
+                    register,
+                    value: value.witness_hook(cs)()?,
+                });
+            }
+        }
+        for applies in diffs_accumulator.specific_registers_zeroing[register].iter() {
+            if applies.witness_hook(cs)()? {
+                fired.push(FiredStateDiff::RegisterZeroing { register });
+            }
+        }
+        for applies in diffs_accumulator.remove_ptr_on_specific_registers[register].iter() {
+            if applies.witness_hook(cs)()? {
+                fired.push(FiredStateDiff::PointerMarkerRemoval { register });
+            }
+        }
+    }
+    for applies in diffs_accumulator.pending_exceptions.iter() {
+        if applies.witness_hook(cs)()? {
+            fired.push(FiredStateDiff::PendingException);
+        }
+    }
+    for (applies, value) in diffs_accumulator.new_ergs_left_candidates.iter() {
+        if applies.witness_hook(cs)()? {
+            fired.push(FiredStateDiff::ErgsLeft {
+                value: value.witness_hook(cs)()?,
+            });
+        }
+    }
+    for (applies, value) in diffs_accumulator.new_pc_candidates.iter() {
+        if applies.witness_hook(cs)()? {
+            fired.push(FiredStateDiff::Pc {
+                value: value.witness_hook(cs)()?,
+            });
+        }
+    }
+    for (applies, value) in diffs_accumulator.new_tx_number.iter() {
+        if applies.witness_hook(cs)()? {
+            fired.push(FiredStateDiff::TxNumber {
+                value: value.witness_hook(cs)()?,
+            });
+        }
+    }
+    for (applies, value) in diffs_accumulator.new_pubdata_revert_counter.iter() {
+        if applies.witness_hook(cs)()? {
+            fired.push(FiredStateDiff::PubdataRevertCounter {
+                value: value.witness_hook(cs)()?,
+            });
+        }
+    }
+    for (applies, value) in diffs_accumulator.new_heap_bounds.iter() {
+        if applies.witness_hook(cs)()? {
+            fired.push(FiredStateDiff::HeapBound {
+                value: value.witness_hook(cs)()?,
+            });
+        }
+    }
+    for (applies, value) in diffs_accumulator.new_aux_heap_bounds.iter() {
+        if applies.witness_hook(cs)()? {
+            fired.push(FiredStateDiff::AuxHeapBound {
+                value: value.witness_hook(cs)()?,
+            });
+        }
+    }
+    for (applies, value) in diffs_accumulator.context_u128_candidates.iter() {
+        if applies.witness_hook(cs)()? {
+            fired.push(FiredStateDiff::ContextU128 {
+                value: value.witness_hook(cs)()?,
+            });
+        }
+    }
+    for (applies, callstack) in diffs_accumulator.callstacks.iter() {
+        if applies.witness_hook(cs)()? {
+            fired.push(FiredStateDiff::Callstack {
+                depth: callstack.context_stack_depth.witness_hook(cs)()?,
+            });
+        }
+    }
+    for (applies, length, _) in diffs_accumulator.decommitment_queue_candidates.iter() {
+        if applies.witness_hook(cs)()? {
+            fired.push(FiredStateDiff::DecommitmentQueue {
+                length: length.witness_hook(cs)()?,
+            });
+        }
+    }
+    for (applies, length, _) in diffs_accumulator.memory_queue_candidates.iter() {
+        if applies.witness_hook(cs)()? {
+            fired.push(FiredStateDiff::MemoryQueue {
+                length: length.witness_hook(cs)()?,
+            });
+        }
+    }
+    for (applies, length, _) in diffs_accumulator.log_queue_forward_candidates.iter() {
+        if applies.witness_hook(cs)()? {
+            fired.push(FiredStateDiff::LogQueueForward {
+                length: length.witness_hook(cs)()?,
+            });
+        }
+    }
+    for (applies, length, _) in diffs_accumulator.log_queue_rollback_candidates.iter() {
+        if applies.witness_hook(cs)()? {
+            fired.push(FiredStateDiff::LogQueueRollback {
+                length: length.witness_hook(cs)()?,
+            });
+        }
+    }
+    for (applies, cost) in diffs_accumulator.pubdata_cost.iter() {
+        if applies.witness_hook(cs)()? {
+            fired.push(FiredStateDiff::PubdataCost {
+                cost: cost.witness_hook(cs)()? as i32,
+            });
+        }
+    }
+
+    Some(fired)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use boojum::field::goldilocks::GoldilocksField;
+
+    type F = GoldilocksField;
+
+    #[test]
+    fn test_json_lines_tracer_output() {
+        let mut tracer = JsonLinesVmCycleTracer::new(Vec::new());
+        VmCycleTracer::<F>::start_cycle(&mut tracer, 7);
+        tracer.on_event(VmCycleEvent::<F>::ErgsChanged {
+            before: 100,
+            after: 94,
+        });
+        tracer.on_event(VmCycleEvent::<F>::StateDiff {
+            diff: FiredStateDiff::Pc { value: 12 },
+        });
+        tracer.on_event(VmCycleEvent::<F>::SpongePushed {
+            initial_state: [F::from_u64_unchecked(1); FULL_SPONGE_QUEUE_STATE_WIDTH],
+            final_state: [F::from_u64_unchecked(2); FULL_SPONGE_QUEUE_STATE_WIDTH],
+        });
+
+        let output = String::from_utf8(tracer.finish().unwrap()).unwrap();
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|el| serde_json::from_str(el).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            serde_json::json!({"cycle": 7, "event": "ergs_changed", "before": 100, "after": 94})
+        );
+        assert_eq!(
+            lines[1],
+            serde_json::json!({"cycle": 7, "event": "state_diff", "diff": {"kind": "pc", "value": 12}})
+        );
+        assert_eq!(lines[2]["event"], "sponge_pushed");
+        assert_eq!(
+            lines[2]["final_state"].as_array().unwrap().len(),
+            FULL_SPONGE_QUEUE_STATE_WIDTH
+        );
+    }
+
+    #[test]
+    fn test_trace_opcode_family() {
+        let mut properties_bits = OpcodeBitmaskWitness::<F> {
+            opcode_type_booleans: [false; zkevm_opcode_defs::OPCODE_TYPE_BITS],
+            opcode_variant_booleans: [false; crate::main_vm::opcode_bitmask::OPCODE_VARIANT_BITS],
+            flag_booleans: [false; OPCODE_FLAGS_BITS],
+            input_variant_booleans: [false; zkevm_opcode_defs::OPCODE_INPUT_VARIANT_FLAGS],
+            output_variant_booleans: [false; zkevm_opcode_defs::OPCODE_OUTPUT_VARIANT_FLAGS],
+        };
+        let opcode = Opcode::Shift(zkevm_opcode_defs::ShiftOpcode::Ror);
+        properties_bits.opcode_type_booleans[opcode.variant_idx()] = true;
+        properties_bits.opcode_variant_booleans[opcode.materialize_subvariant_idx()] = true;
+        properties_bits.input_variant_booleans
+            [ImmMemHandlerFlags::UseStackWithOffset.variant_index()] = true;
+        properties_bits.output_variant_booleans[ImmMemHandlerFlags::UseRegOnly.variant_index()] =
+            true;
+
+        let mut src_regs = [[false; REGISTERS_COUNT]; 2];
+        src_regs[0][3] = true;
+        let mut dst_regs = [[false; REGISTERS_COUNT]; 2];
+        dst_regs[0][0] = true;
+
+        let traced = trace_opcode(&properties_bits, &src_regs, &dst_regs, 5, 0);
+        assert_eq!(traced.family, "shift");
+        assert_eq!(traced.variant, Some(opcode.materialize_subvariant_idx()));
+        assert_eq!(
+            traced.src_memory_access.as_deref(),
+            Some("UseStackWithOffset")
+        );
+        assert_eq!(traced.dst_memory_access.as_deref(), Some("UseRegOnly"));
+        assert_eq!(traced.src_registers, [Some(3), None]);
+        assert_eq!(traced.dst_registers, [Some(0), None]);
+        assert_eq!(traced.imm0, 5);
+    }
+}
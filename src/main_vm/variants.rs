@@ -0,0 +1,284 @@
+use super::*;
+
+use crate::main_vm::opcode_bitmask::OpcodeBitmask;
+use zkevm_opcode_defs::{DivOpcode, FarCallOpcode, MulOpcode, Opcode, ShiftOpcode};
+
+/// Opcode families that a VM circuit variant may leave out. Each of them corresponds to
+/// the part of `vm_cycle` that is not synthesized if the family is excluded
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExcludableOpcodeFamily {
+    MulDiv,
+    Shifts,
+    FarCall,
+}
+
+impl ExcludableOpcodeFamily {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::MulDiv => "mul_div",
+            Self::Shifts => "shifts",
+            Self::FarCall => "far_call",
+        }
+    }
+
+    /// Opcodes of the family. Any subvariant selects the same opcode type bit, so we only list one
+    pub const fn opcodes(&self) -> &'static [Opcode] {
+        match self {
+            Self::MulDiv => &[Opcode::Mul(MulOpcode), Opcode::Div(DivOpcode)],
+            Self::Shifts => &[Opcode::Shift(ShiftOpcode::Shl)],
+            Self::FarCall => &[Opcode::FarCall(FarCallOpcode::Normal)],
+        }
+    }
+
+    pub fn contains(&self, opcode: Opcode) -> bool {
+        self.opcodes()
+            .iter()
+            .any(|el| el.variant_idx() == opcode.variant_idx())
+    }
+}
+
+/// Main VM circuit that is synthesized for a subset of opcodes. All the variants produce the same
+/// public input for the same witness, and differ only in what opcodes they can execute
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VmCircuitVariant {
+    pub name: &'static str,
+    pub excluded_families: &'static [ExcludableOpcodeFamily],
+}
+
+impl VmCircuitVariant {
+    pub fn excludes(&self, family: ExcludableOpcodeFamily) -> bool {
+        self.excluded_families.contains(&family)
+    }
+
+    pub fn supports_opcode(&self, opcode: Opcode) -> bool {
+        self.excluded_families
+            .iter()
+            .all(|family| family.contains(opcode) == false)
+    }
+}
+
+pub const FULL_VM_VARIANT: VmCircuitVariant = VmCircuitVariant {
+    name: "full",
+    excluded_families: &[],
+};
+
+pub const ARITHMETIC_FREE_VM_VARIANT: VmCircuitVariant = VmCircuitVariant {
+    name: "arithmetic_free",
+    excluded_families: &[
+        ExcludableOpcodeFamily::MulDiv,
+        ExcludableOpcodeFamily::Shifts,
+    ],
+};
+
+pub const NO_FAR_CALL_VM_VARIANT: VmCircuitVariant = VmCircuitVariant {
+    name: "no_far_call",
+    excluded_families: &[ExcludableOpcodeFamily::FarCall],
+};
+
+pub const NUM_VM_CIRCUIT_VARIANTS: usize = 3;
+
+/// All the VM variants that recursion accepts for `BaseLayerCircuitType::VM`. Verification keys
+/// of the variants are expected by the leaf layer in the same order
+pub const VM_CIRCUIT_VARIANTS: [VmCircuitVariant; NUM_VM_CIRCUIT_VARIANTS] = [
+    FULL_VM_VARIANT,
+    ARITHMETIC_FREE_VM_VARIANT,
+    NO_FAR_CALL_VM_VARIANT,
+];
+
+pub fn vm_circuit_variant_by_name(name: &str) -> Option<&'static VmCircuitVariant> {
+    VM_CIRCUIT_VARIANTS.iter().find(|el| el.name == name)
+}
+
+/// Variant that excludes the most opcode families, but can still execute all the `opcodes`
+pub fn smallest_vm_circuit_variant_for_opcodes(
+    opcodes: impl IntoIterator<Item = Opcode>,
+) -> &'static VmCircuitVariant {
+    let opcodes: Vec<Opcode> = opcodes.into_iter().collect();
+
+    VM_CIRCUIT_VARIANTS
+        .iter()
+        .filter(|variant| opcodes.iter().all(|el| variant.supports_opcode(*el)))
+        .max_by_key(|variant| variant.excluded_families.len())
+        .expect("full variant supports everything")
+}
+
+/// Ensures that the opcode that we apply in this cycle is not from the excluded families,
+/// so parts of the cycle responsible for those can be skipped
+pub(crate) fn enforce_opcode_is_supported<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    properties_bits: &OpcodeBitmask<F>,
+    variant: &VmCircuitVariant,
+) {
+    let boolean_false = Boolean::allocated_constant(cs, false);
+    for family in variant.excluded_families.iter() {
+        for opcode in family.opcodes().iter() {
+            let is_excluded_opcode = properties_bits.boolean_for_opcode(*opcode);
+            Boolean::enforce_equal(cs, &is_excluded_opcode, &boolean_false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::main_vm::opcode_bitmask::TOTAL_OPCODE_MEANINGFULL_DESCRIPTION_BITS;
+    use boojum::cs::cs_builder::*;
+    use boojum::cs::cs_builder_reference::CsReferenceImplementationBuilder;
+    use boojum::cs::gates::*;
+    use boojum::cs::traits::gate::GatePlacementStrategy;
+    use boojum::cs::*;
+    use boojum::field::goldilocks::GoldilocksField;
+    use boojum::gadgets::traits::allocatable::CSAllocatable;
+    use boojum::worker::Worker;
+    use std::collections::HashSet;
+    use zkevm_opcode_defs::{AddOpcode, NearCallOpcode, NopOpcode, RetOpcode};
+
+    type F = GoldilocksField;
+
+    fn variant_accepts_opcode(variant: &VmCircuitVariant, opcode: Opcode) -> bool {
+        let geometry = CSGeometry {
+            num_columns_under_copy_permutation: 20,
+            num_witness_columns: 0,
+            num_constant_columns: 4,
+            max_allowed_constraint_degree: 4,
+        };
+
+        fn configure<
+            T: CsBuilderImpl<F, T>,
+            GC: GateConfigurationHolder<F>,
+            TB: StaticToolboxHolder,
+        >(
+            builder: CsBuilder<T, F, GC, TB>,
+        ) -> CsBuilder<T, F, impl GateConfigurationHolder<F>, impl StaticToolboxHolder> {
+            let builder = ConstantsAllocatorGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = BooleanConstraintGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = FmaGateInBaseFieldWithoutConstant::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ReductionGate::<F, 4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ZeroCheckGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+                false,
+            );
+            let builder = NopGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+
+            builder
+        }
+
+        let builder_impl =
+            CsReferenceImplementationBuilder::<F, F, DevCSConfig>::new(geometry, 1 << 10);
+        let builder = new_builder::<_, F>(builder_impl);
+        let builder = configure(builder);
+        let mut owned_cs = builder.build(1 << 12);
+        let cs = &mut owned_cs;
+
+        // opcode type bits go first in the mask
+        let mask: [_; TOTAL_OPCODE_MEANINGFULL_DESCRIPTION_BITS] =
+            std::array::from_fn(|idx| Boolean::allocate(cs, idx == opcode.variant_idx()));
+        let properties_bits = OpcodeBitmask::from_full_mask(mask);
+        let is_opcode = properties_bits.boolean_for_opcode(opcode);
+        assert!(is_opcode.witness_hook(&*cs)().unwrap());
+
+        enforce_opcode_is_supported(cs, &properties_bits, variant);
+
+        cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        let worker = Worker::new();
+        cs.check_if_satisfied(&worker)
+    }
+
+    #[test]
+    fn test_variants_are_consistent() {
+        assert_eq!(VM_CIRCUIT_VARIANTS[0], FULL_VM_VARIANT);
+        assert!(FULL_VM_VARIANT.excluded_families.is_empty());
+
+        let mut names = HashSet::new();
+        for variant in VM_CIRCUIT_VARIANTS.iter() {
+            assert!(names.insert(variant.name), "{}", variant.name);
+            assert_eq!(vm_circuit_variant_by_name(variant.name), Some(variant));
+        }
+
+        // skipped cycles are NOPs and pending exceptions are executed as RET,
+        // so those can never be excluded. Same for near call that shares the callstack logic with them
+        for family in [
+            ExcludableOpcodeFamily::MulDiv,
+            ExcludableOpcodeFamily::Shifts,
+            ExcludableOpcodeFamily::FarCall,
+        ] {
+            assert!(
+                !family.contains(Opcode::Nop(NopOpcode)),
+                "{}",
+                family.name()
+            );
+            assert!(
+                !family.contains(Opcode::Ret(RetOpcode::Panic)),
+                "{}",
+                family.name()
+            );
+            assert!(
+                !family.contains(Opcode::NearCall(NearCallOpcode)),
+                "{}",
+                family.name()
+            );
+        }
+    }
+
+    #[test]
+    fn test_smallest_variant_selection() {
+        let add = Opcode::Add(AddOpcode::Add);
+        let mul = Opcode::Mul(MulOpcode);
+        let ror = Opcode::Shift(ShiftOpcode::Ror);
+        let mimic_call = Opcode::FarCall(FarCallOpcode::Mimic);
+
+        assert!(ARITHMETIC_FREE_VM_VARIANT.supports_opcode(add));
+        assert!(!ARITHMETIC_FREE_VM_VARIANT.supports_opcode(ror));
+        assert!(!NO_FAR_CALL_VM_VARIANT.supports_opcode(mimic_call));
+
+        assert_eq!(
+            smallest_vm_circuit_variant_for_opcodes([add, mimic_call]).name,
+            "arithmetic_free"
+        );
+        assert_eq!(
+            smallest_vm_circuit_variant_for_opcodes([add, mul]).name,
+            "no_far_call"
+        );
+        assert_eq!(
+            smallest_vm_circuit_variant_for_opcodes([mul, mimic_call]).name,
+            "full"
+        );
+    }
+
+    #[test]
+    fn test_excluded_opcode_is_unsatisfiable() {
+        let add = Opcode::Add(AddOpcode::Add);
+        let div = Opcode::Div(DivOpcode);
+        let mimic_call = Opcode::FarCall(FarCallOpcode::Mimic);
+
+        for variant in VM_CIRCUIT_VARIANTS.iter() {
+            assert!(variant_accepts_opcode(variant, add), "{}", variant.name);
+        }
+        assert!(variant_accepts_opcode(&FULL_VM_VARIANT, div));
+        assert!(!variant_accepts_opcode(&ARITHMETIC_FREE_VM_VARIANT, div));
+        assert!(variant_accepts_opcode(
+            &ARITHMETIC_FREE_VM_VARIANT,
+            mimic_call
+        ));
+        assert!(!variant_accepts_opcode(&NO_FAR_CALL_VM_VARIANT, mimic_call));
+    }
+}
@@ -6,7 +6,7 @@ use boojum::field::SmallField;
 
 use super::*;
 
-#[derive(Derivative)]
+#[derive(Derivative, serde::Serialize, serde::Deserialize)]
 #[derivative(Clone, Debug, Default)]
 pub struct MemoryWitness {
     pub value: U256,
@@ -0,0 +1,403 @@
+use super::*;
+
+use boojum::cs::traits::cs::DstBuffer;
+use boojum::gadgets::u32::UInt32;
+
+// Operands of the circuit are kept as arrays of words with the least significant word first,
+// and all the arithmetic is done over the 32-bit limbs of all the words together
+
+fn flatten_limbs<F: SmallField, const N: usize>(words: &[UInt256<F>; N]) -> Vec<UInt32<F>> {
+    words.iter().flat_map(|el| el.inner).collect()
+}
+
+fn words_from_limbs<F: SmallField, const N: usize>(limbs: &[UInt32<F>]) -> [UInt256<F>; N] {
+    assert_eq!(limbs.len(), N * 8);
+
+    std::array::from_fn(|i| UInt256 {
+        inner: limbs[i * 8..(i + 1) * 8].try_into().unwrap(),
+    })
+}
+
+fn native_limbs_from_field_elements<F: SmallField>(limbs: &[F]) -> Vec<u32> {
+    limbs.iter().map(|el| el.as_u64_reduced() as u32).collect()
+}
+
+/// Product of little-endian limbs, has the length of both multipliers together
+pub(crate) fn native_mul(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = vec![0u32; a.len() + b.len()];
+    for (i, a) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, b) in b.iter().enumerate() {
+            let t = (*a as u64) * (*b as u64) + result[i + j] as u64 + carry;
+            result[i + j] = t as u32;
+            carry = t >> 32;
+        }
+        result[i + b.len()] = carry as u32;
+    }
+
+    result
+}
+
+/// Long division of little-endian limbs (Knuth, TAOCP vol. 2, 4.3.1, algorithm D). Quotient has the
+/// length of the numerator, and remainder has the length of the denominator. Division by zero
+/// gives zero quotient and remainder
+pub(crate) fn native_div_rem(u: &[u32], v: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    let mut quotient = vec![0u32; u.len()];
+    let mut remainder = vec![0u32; v.len()];
+
+    let Some(n) = v.iter().rposition(|el| *el != 0).map(|el| el + 1) else {
+        return (quotient, remainder);
+    };
+    let m = u
+        .iter()
+        .rposition(|el| *el != 0)
+        .map(|el| el + 1)
+        .unwrap_or(0);
+    if m < n {
+        remainder[..m].copy_from_slice(&u[..m]);
+        return (quotient, remainder);
+    }
+
+    if n == 1 {
+        let d = v[0] as u64;
+        let mut rem = 0u64;
+        for i in (0..m).rev() {
+            let t = (rem << 32) | u[i] as u64;
+            quotient[i] = (t / d) as u32;
+            rem = t % d;
+        }
+        remainder[0] = rem as u32;
+        return (quotient, remainder);
+    }
+
+    // normalize, so the top limb of the denominator has the highest bit set
+    let shift = v[n - 1].leading_zeros();
+    let shl = |hi: u32, lo: u32| {
+        if shift == 0 {
+            hi
+        } else {
+            (hi << shift) | (lo >> (32 - shift))
+        }
+    };
+    let vn: Vec<u32> = (0..n)
+        .map(|i| shl(v[i], if i > 0 { v[i - 1] } else { 0 }))
+        .collect();
+    let mut un: Vec<u32> = (0..=m)
+        .map(|i| {
+            let hi = if i < m { u[i] } else { 0 };
+            shl(hi, if i > 0 { u[i - 1] } else { 0 })
+        })
+        .collect();
+
+    const BASE: u64 = 1u64 << 32;
+    for j in (0..=(m - n)).rev() {
+        let t = ((un[j + n] as u64) << 32) | un[j + n - 1] as u64;
+        let mut qhat = t / vn[n - 1] as u64;
+        let mut rhat = t % vn[n - 1] as u64;
+        while qhat >= BASE || qhat * vn[n - 2] as u64 > ((rhat << 32) | un[j + n - 2] as u64) {
+            qhat -= 1;
+            rhat += vn[n - 1] as u64;
+            if rhat >= BASE {
+                break;
+            }
+        }
+
+        // multiply and subtract
+        let mut k = 0i64;
+        for i in 0..n {
+            let p = qhat * vn[i] as u64;
+            let t = un[i + j] as i64 - k - (p & 0xffff_ffff) as i64;
+            un[i + j] = t as u32;
+            k = (p >> 32) as i64 - (t >> 32);
+        }
+        let t = un[j + n] as i64 - k;
+        un[j + n] = t as u32;
+
+        quotient[j] = qhat as u32;
+        if t < 0 {
+            // we subtracted too much, so add one denominator back
+            quotient[j] = quotient[j].wrapping_sub(1);
+            let mut carry = 0u64;
+            for i in 0..n {
+                let t = un[i + j] as u64 + vn[i] as u64 + carry;
+                un[i + j] = t as u32;
+                carry = t >> 32;
+            }
+            un[j + n] = un[j + n].wrapping_add(carry as u32);
+        }
+    }
+
+    // denormalize the remainder
+    for i in 0..n {
+        remainder[i] = if shift == 0 {
+            un[i]
+        } else {
+            (un[i] >> shift) | (un[i + 1] << (32 - shift))
+        };
+    }
+
+    (quotient, remainder)
+}
+
+/// Allocates witness variables computed from the limbs of dependencies. The variables are not
+/// range checked, so callers must convert them with `UInt32::from_variable_checked`
+fn allocate_limbs_from_closure<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    FN: FnOnce(&[u32]) -> Vec<u32> + 'static + Send + Sync,
+>(
+    cs: &mut CS,
+    dependencies: &[UInt32<F>],
+    num_limbs: usize,
+    value_fn: FN,
+) -> Vec<Variable> {
+    let outputs: Vec<Variable> = (0..num_limbs)
+        .map(|_| cs.alloc_variable_without_value())
+        .collect();
+
+    if <CS::Config as CSConfig>::WitnessConfig::EVALUATE_WITNESS {
+        let value_fn = move |inputs: &[F], output_buffer: &mut DstBuffer<'_, '_, F>| {
+            let inputs = native_limbs_from_field_elements(inputs);
+            let result = (value_fn)(&inputs);
+            debug_assert_eq!(result.len(), num_limbs);
+            output_buffer.extend(
+                result
+                    .into_iter()
+                    .map(|el| F::from_u64_unchecked(el as u64)),
+            );
+        };
+
+        let dependencies: Vec<Place> = dependencies
+            .iter()
+            .map(|el| el.get_variable().into())
+            .collect();
+        let output_places: Vec<Place> = outputs.iter().map(|el| (*el).into()).collect();
+        cs.set_values_with_dependencies_vararg(&dependencies, &output_places, value_fn);
+    }
+
+    outputs
+}
+
+// NOTE: all the limbs must be range checked by the caller, as `fma_with_carry` only range checks its outputs
+fn enforce_mul_relation<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    a: &[UInt32<F>],
+    b: &[UInt32<F>],
+    rem: &[UInt32<F>],
+    result: &[UInt32<F>],
+) {
+    // a * b + rem = result
+    let len = a.len();
+    assert_eq!(b.len(), len);
+    assert!(rem.len() <= 2 * len);
+    assert_eq!(result.len(), 2 * len);
+
+    let mut partial_result = vec![UInt32::zero(cs); 2 * len];
+    partial_result[..rem.len()].copy_from_slice(rem);
+    for a_idx in 0..len {
+        let mut intermidiate_overflow = UInt32::zero(cs);
+        for b_idx in 0..len {
+            let [low_wrapped, high_wrapped] = UInt32::fma_with_carry(
+                cs,
+                a[a_idx],
+                b[b_idx],
+                partial_result[a_idx + b_idx],
+                intermidiate_overflow,
+            );
+            partial_result[a_idx + b_idx] = low_wrapped.0;
+            intermidiate_overflow = high_wrapped.0;
+        }
+        // place end of chain
+        partial_result[a_idx + len] =
+            partial_result[a_idx + len].add_no_overflow(cs, intermidiate_overflow);
+    }
+    for (lhs, rhs) in partial_result.iter().zip(result.iter()) {
+        Num::enforce_equal(cs, &lhs.into_num(), &rhs.into_num())
+    }
+}
+
+/// Returns whether a < b
+pub(crate) fn is_less_than<F: SmallField, CS: ConstraintSystem<F>, const N: usize>(
+    cs: &mut CS,
+    a: &[UInt256<F>; N],
+    b: &[UInt256<F>; N],
+) -> Boolean<F> {
+    let mut borrow = Boolean::allocated_constant(cs, false);
+    for (a, b) in a.iter().zip(b.iter()) {
+        let (diff, of_0) = a.overflowing_sub(cs, b);
+        let mut borrow_as_u256 = UInt256::zero(cs);
+        borrow_as_u256.inner[0] = unsafe { UInt32::from_variable_unchecked(borrow.get_variable()) };
+        let (_, of_1) = diff.overflowing_sub(cs, &borrow_as_u256);
+        borrow = Boolean::multi_or(cs, &[of_0, of_1]);
+    }
+
+    borrow
+}
+
+pub(crate) fn is_zero<F: SmallField, CS: ConstraintSystem<F>, const N: usize>(
+    cs: &mut CS,
+    a: &[UInt256<F>; N],
+) -> Boolean<F> {
+    let flags = a.map(|el| el.is_zero(cs));
+    Boolean::multi_and(cs, &flags)
+}
+
+pub(crate) fn equals<F: SmallField, CS: ConstraintSystem<F>, const N: usize>(
+    cs: &mut CS,
+    a: &[UInt256<F>; N],
+    b: &[UInt256<F>; N],
+) -> Boolean<F> {
+    let flags: [Boolean<F>; N] = std::array::from_fn(|i| UInt256::equals(cs, &a[i], &b[i]));
+    Boolean::multi_and(cs, &flags)
+}
+
+pub(crate) fn constant_one<F: SmallField, CS: ConstraintSystem<F>, const N: usize>(
+    cs: &mut CS,
+) -> [UInt256<F>; N] {
+    let mut result = [UInt256::zero(cs); N];
+    result[0] = UInt256::allocated_constant(cs, U256::one());
+
+    result
+}
+
+/// Computes a * b mod modulus for non-zero modulus. Multipliers must be reduced,
+/// or one of them must be equal to one
+pub(crate) fn modmul<F: SmallField, CS: ConstraintSystem<F>, const N: usize>(
+    cs: &mut CS,
+    a: &[UInt256<F>; N],
+    b: &[UInt256<F>; N],
+    modulus: &[UInt256<F>; N],
+) -> [UInt256<F>; N] {
+    let a_limbs = flatten_limbs(a);
+    let b_limbs = flatten_limbs(b);
+    let modulus_limbs = flatten_limbs(modulus);
+    let num_limbs = a_limbs.len();
+
+    let mul_result = allocate_limbs_from_closure(
+        cs,
+        &[a_limbs.clone(), b_limbs.clone()].concat(),
+        2 * num_limbs,
+        move |inputs: &[u32]| native_mul(&inputs[..num_limbs], &inputs[num_limbs..]),
+    );
+    // quotient of the division always fits into the same number of limbs as long as a * b < modulus * 2^(256 * N)
+    let quotient_and_remainder = allocate_limbs_from_closure(
+        cs,
+        &[a_limbs, b_limbs, modulus_limbs].concat(),
+        2 * num_limbs,
+        move |inputs: &[u32]| {
+            let product = native_mul(&inputs[..num_limbs], &inputs[num_limbs..2 * num_limbs]);
+            let (quotient, remainder) = native_div_rem(&product, &inputs[2 * num_limbs..]);
+            [&quotient[..num_limbs], &remainder[..]].concat()
+        },
+    );
+
+    modmul_from_witness(cs, a, b, modulus, &mul_result, &quotient_and_remainder)
+}
+
+/// Range checks the witness of `modmul` (product, followed by quotient and remainder)
+/// and enforces that it's a valid reduction of a * b
+pub(crate) fn modmul_from_witness<F: SmallField, CS: ConstraintSystem<F>, const N: usize>(
+    cs: &mut CS,
+    a: &[UInt256<F>; N],
+    b: &[UInt256<F>; N],
+    modulus: &[UInt256<F>; N],
+    mul_result: &[Variable],
+    quotient_and_remainder: &[Variable],
+) -> [UInt256<F>; N] {
+    let boolean_true = Boolean::allocated_constant(cs, true);
+
+    let a_limbs = flatten_limbs(a);
+    let b_limbs = flatten_limbs(b);
+    let modulus_limbs = flatten_limbs(modulus);
+    let num_limbs = a_limbs.len();
+    assert_eq!(mul_result.len(), 2 * num_limbs);
+    assert_eq!(quotient_and_remainder.len(), 2 * num_limbs);
+
+    let mul_result: Vec<UInt32<F>> = mul_result
+        .iter()
+        .map(|el| UInt32::from_variable_checked(cs, *el))
+        .collect();
+    let quotient_and_remainder: Vec<UInt32<F>> = quotient_and_remainder
+        .iter()
+        .map(|el| UInt32::from_variable_checked(cs, *el))
+        .collect();
+    let (quotient, remainder) = quotient_and_remainder.split_at(num_limbs);
+
+    // a * b = mul_result
+    enforce_mul_relation(cs, &a_limbs, &b_limbs, &[], &mul_result);
+    // quotient * modulus + remainder = mul_result
+    enforce_mul_relation(cs, quotient, &modulus_limbs, remainder, &mul_result);
+
+    let remainder = words_from_limbs::<F, N>(remainder);
+    let remainder_is_less_than_modulus = is_less_than(cs, &remainder, modulus);
+    Boolean::enforce_equal(cs, &remainder_is_less_than_modulus, &boolean_true);
+
+    remainder
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ethereum_types::U512;
+    use rand_new::Rng;
+
+    fn u512_from_limbs(limbs: &[u32]) -> U512 {
+        let mut bytes = [0u8; 64];
+        for (dst, src) in bytes.array_chunks_mut::<4>().zip(limbs.iter()) {
+            *dst = src.to_le_bytes();
+        }
+        U512::from_little_endian(&bytes)
+    }
+
+    fn u256_from_limbs(limbs: &[u32]) -> U256 {
+        let mut bytes = [0u8; 32];
+        for (dst, src) in bytes.array_chunks_mut::<4>().zip(limbs.iter()) {
+            *dst = src.to_le_bytes();
+        }
+        U256::from_little_endian(&bytes)
+    }
+
+    #[test]
+    fn test_native_div_rem() {
+        let mut rng = rand_new::thread_rng();
+        for _ in 0..1000 {
+            let num_limbs = rng.gen_range(1..=16);
+            let num_denominator_limbs = rng.gen_range(1..=8);
+            let mut u: Vec<u32> = (0..num_limbs).map(|_| rng.gen()).collect();
+            let mut v: Vec<u32> = (0..num_denominator_limbs).map(|_| rng.gen()).collect();
+            // small and sparse values hit the corner cases of the quotient estimation
+            if rng.gen_bool(0.3) {
+                v[num_denominator_limbs - 1] = rng.gen_range(0..4);
+            }
+            if rng.gen_bool(0.3) {
+                u[num_limbs - 1] = 0;
+            }
+
+            let (quotient, remainder) = native_div_rem(&u, &v);
+            assert_eq!(quotient.len(), u.len());
+            assert_eq!(remainder.len(), v.len());
+
+            let u = u512_from_limbs(&u);
+            let v = u512_from_limbs(&v);
+            if v.is_zero() {
+                assert!(u512_from_limbs(&quotient).is_zero());
+                assert!(u512_from_limbs(&remainder).is_zero());
+            } else {
+                let (expected_quotient, expected_remainder) = u.div_mod(v);
+                assert_eq!(u512_from_limbs(&quotient), expected_quotient);
+                assert_eq!(u512_from_limbs(&remainder), expected_remainder);
+            }
+        }
+    }
+
+    #[test]
+    fn test_native_mul() {
+        let mut rng = rand_new::thread_rng();
+        for _ in 0..1000 {
+            let a: Vec<u32> = (0..8).map(|_| rng.gen()).collect();
+            let b: Vec<u32> = (0..8).map(|_| rng.gen()).collect();
+            let expected = u256_from_limbs(&a).full_mul(u256_from_limbs(&b));
+            assert_eq!(u512_from_limbs(&native_mul(&a, &b)), expected);
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::VecDeque;
+
+use super::*;
+
+use crate::base_structures::precompile_input_outputs::*;
+use crate::base_structures::vm_state::*;
+use boojum::cs::Variable;
+use boojum::gadgets::queue::*;
+use boojum::gadgets::traits::allocatable::CSAllocatable;
+use boojum::gadgets::traits::allocatable::CSPlaceholder;
+use boojum::gadgets::traits::encodable::CircuitVarLengthEncodable;
+
+use boojum::cs::traits::cs::ConstraintSystem;
+use boojum::field::SmallField;
+use boojum::gadgets::boolean::Boolean;
+use boojum::gadgets::traits::auxiliary::PrettyComparison;
+use boojum::gadgets::traits::selectable::Selectable;
+use boojum::gadgets::traits::witnessable::WitnessHookable;
+use boojum::serde_utils::BigArraySerde;
+
+// Base, modulus and accumulator are kept reduced, as N words with the least significant word first
+
+#[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
+#[derivative(Clone, Copy, Debug)]
+#[DerivePrettyComparison("true")]
+pub struct ModexpFSM<F: SmallField, const N: usize> {
+    pub read_precompile_call: Boolean<F>,
+    pub read_words_for_round: Boolean<F>,
+    pub completed: Boolean<F>,
+    pub lengths_over_limit: Boolean<F>,
+    pub base: [UInt256<F>; N],
+    pub modulus: [UInt256<F>; N],
+    pub accumulator: [UInt256<F>; N],
+    pub timestamp_to_use_for_read: UInt32<F>,
+    pub timestamp_to_use_for_write: UInt32<F>,
+    pub precompile_call_params: ModexpPrecompileCallParams<F>,
+}
+
+impl<F: SmallField, const N: usize> CSPlaceholder<F> for ModexpFSM<F, N> {
+    fn placeholder<CS: ConstraintSystem<F>>(cs: &mut CS) -> Self {
+        let boolean_false = Boolean::allocated_constant(cs, false);
+        let zero_u32 = UInt32::zero(cs);
+        let zero_u256 = UInt256::zero(cs);
+        // modulus is never zero, so reductions are always well defined even in the padding cycles
+        let mut one_words = [zero_u256; N];
+        one_words[0] = UInt256::allocated_constant(cs, U256::one());
+        Self {
+            read_precompile_call: boolean_false,
+            read_words_for_round: boolean_false,
+            completed: boolean_false,
+            lengths_over_limit: boolean_false,
+            base: [zero_u256; N],
+            modulus: one_words,
+            accumulator: [zero_u256; N],
+            timestamp_to_use_for_read: zero_u32,
+            timestamp_to_use_for_write: zero_u32,
+            precompile_call_params: ModexpPrecompileCallParams::<F>::placeholder(cs),
+        }
+    }
+}
+
+#[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
+#[derivative(Clone, Copy, Debug)]
+#[DerivePrettyComparison("true")]
+pub struct ModexpFSMInputOutput<F: SmallField, const N: usize> {
+    pub internal_fsm: ModexpFSM<F, N>,
+    pub log_queue_state: QueueState<F, QUEUE_STATE_WIDTH>,
+    pub memory_queue_state: QueueState<F, FULL_SPONGE_QUEUE_STATE_WIDTH>,
+}
+
+impl<F: SmallField, const N: usize> CSPlaceholder<F> for ModexpFSMInputOutput<F, N> {
+    fn placeholder<CS: ConstraintSystem<F>>(cs: &mut CS) -> Self {
+        Self {
+            internal_fsm: ModexpFSM::placeholder(cs),
+            log_queue_state: QueueState::<F, QUEUE_STATE_WIDTH>::placeholder(cs),
+            memory_queue_state: QueueState::<F, FULL_SPONGE_QUEUE_STATE_WIDTH>::placeholder(cs),
+        }
+    }
+}
+
+pub type ModexpCircuitInputOutput<F, const N: usize> = ClosedFormInput<
+    F,
+    ModexpFSMInputOutput<F, N>,
+    PrecompileFunctionInputData<F>,
+    PrecompileFunctionOutputData<F>,
+>;
+pub type ModexpCircuitInputOutputWitness<F, const N: usize> = ClosedFormInputWitness<
+    F,
+    ModexpFSMInputOutput<F, N>,
+    PrecompileFunctionInputData<F>,
+    PrecompileFunctionOutputData<F>,
+>;
+
+#[derive(Derivative, serde::Serialize, serde::Deserialize)]
+#[derivative(Clone, Debug, Default)]
+#[serde(bound = "")]
+pub struct ModexpCircuitInstanceWitness<F: SmallField, const N: usize> {
+    pub closed_form_input: ModexpCircuitInputOutputWitness<F, N>,
+    pub requests_queue_witness: CircuitQueueRawWitness<F, LogQuery<F>, 4, LOG_QUERY_PACKED_WIDTH>,
+    pub memory_reads_witness: VecDeque<U256>,
+}
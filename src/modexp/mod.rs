@@ -0,0 +1,1291 @@
+use super::*;
+
+use boojum::field::SmallField;
+
+use boojum::gadgets::traits::witnessable::WitnessHookable;
+
+use boojum::config::*;
+use boojum::cs::traits::cs::ConstraintSystem;
+use boojum::gadgets::boolean::Boolean;
+use boojum::gadgets::traits::selectable::Selectable;
+use boojum::gadgets::u256::UInt256;
+use boojum::gadgets::u32::UInt32;
+use cs_derive::*;
+
+use crate::ethereum_types::U256;
+use crate::fsm_input_output::circuit_inputs::INPUT_OUTPUT_COMMITMENT_LENGTH;
+use boojum::gadgets::num::Num;
+use zkevm_opcode_defs::system_params::PRECOMPILE_AUX_BYTE;
+
+use crate::base_structures::log_query::*;
+use crate::base_structures::memory_query::*;
+use crate::base_structures::precompile_input_outputs::formal_precompile_address;
+use crate::base_structures::precompile_input_outputs::PrecompileFunctionOutputData;
+use crate::demux_log_queue::StorageLogQueue;
+use crate::fsm_input_output::*;
+use crate::storage_application::ConditionalWitnessAllocator;
+use boojum::algebraic_props::round_function::AlgebraicRoundFunction;
+use boojum::cs::{Place, Variable};
+use boojum::gadgets::queue::CircuitQueueWitness;
+use boojum::gadgets::queue::QueueState;
+use boojum::gadgets::traits::allocatable::CSAllocatable;
+use boojum::gadgets::traits::allocatable::{CSAllocatableExt, CSPlaceholder};
+use boojum::gadgets::traits::encodable::CircuitVarLengthEncodable;
+use boojum::gadgets::traits::round_function::CircuitRoundFunction;
+use boojum::gadgets::u160::UInt160;
+use boojum::gadgets::u8::UInt8;
+use std::sync::{Arc, RwLock};
+use zkevm_opcode_defs::ethereum_types::H160;
+
+use crate::base_structures::precompile_input_outputs::*;
+use crate::demux_log_queue::DemuxOutput;
+use crate::precompile_registry::PrecompileCircuit;
+use crate::scheduler::auxiliary::BaseLayerCircuitType;
+
+pub mod bigint;
+pub mod input;
+use self::bigint::*;
+use self::input::*;
+
+pub const MODEXP_PRECOMPILE_ADDRESS: u16 = 0x05;
+pub const MODEXP_PRECOMPILE_FORMAL_ADDRESS: H160 =
+    formal_precompile_address(MODEXP_PRECOMPILE_ADDRESS);
+
+pub struct ModexpPrecompileCircuit;
+
+impl PrecompileCircuit for ModexpPrecompileCircuit {
+    const NAME: &'static str = "modexp";
+    const DEMUX_OUTPUT: DemuxOutput = DemuxOutput::Modexp;
+    const CIRCUIT_TYPE: BaseLayerCircuitType = BaseLayerCircuitType::ModexpPrecompile;
+
+    type InputData<F: SmallField> = PrecompileFunctionInputData<F>;
+    type OutputData<F: SmallField> = PrecompileFunctionOutputData<F>;
+
+    fn formal_address() -> H160 {
+        MODEXP_PRECOMPILE_FORMAL_ADDRESS
+    }
+}
+
+#[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
+#[derivative(Clone, Copy, Debug)]
+// #[DerivePrettyComparison("true")]
+pub struct ModexpPrecompileCallParams<F: SmallField> {
+    pub input_page: UInt32<F>,
+    pub input_offset: UInt32<F>,
+    pub output_page: UInt32<F>,
+    pub output_offset: UInt32<F>,
+    pub base_byte_length: UInt32<F>,
+    pub exponent_byte_length: UInt32<F>,
+    pub modulus_byte_length: UInt32<F>,
+    pub num_base_words: UInt32<F>,
+    pub num_exponent_words: UInt32<F>,
+    pub num_modulus_words: UInt32<F>,
+}
+
+impl<F: SmallField> CSPlaceholder<F> for ModexpPrecompileCallParams<F> {
+    fn placeholder<CS: ConstraintSystem<F>>(cs: &mut CS) -> Self {
+        let zero_u32 = UInt32::zero(cs);
+        Self {
+            input_page: zero_u32,
+            input_offset: zero_u32,
+            output_page: zero_u32,
+            output_offset: zero_u32,
+            base_byte_length: zero_u32,
+            exponent_byte_length: zero_u32,
+            modulus_byte_length: zero_u32,
+            num_base_words: zero_u32,
+            num_exponent_words: zero_u32,
+            num_modulus_words: zero_u32,
+        }
+    }
+}
+
+impl<F: SmallField> ModexpPrecompileCallParams<F> {
+    // Operands are placed in memory one after another as base, exponent and modulus,
+    // each left-padded with zeroes to the whole number of 32 byte words. Byte lengths of
+    // base and modulus are passed instead of input and output lengths of the PrecompileCallABI,
+    // and byte length of exponent is passed in the lowest word of the interpreted data
+    pub fn from_encoding<CS: ConstraintSystem<F>>(cs: &mut CS, encoding: UInt256<F>) -> Self {
+        let input_offset = encoding.inner[0];
+        let base_byte_length = encoding.inner[1];
+        let output_offset = encoding.inner[2];
+        let modulus_byte_length = encoding.inner[3];
+        let input_page = encoding.inner[4];
+        let output_page = encoding.inner[5];
+
+        let exponent_byte_length = encoding.inner[6];
+
+        let num_base_words = num_words_for_byte_length(cs, base_byte_length);
+        let num_exponent_words = num_words_for_byte_length(cs, exponent_byte_length);
+        let num_modulus_words = num_words_for_byte_length(cs, modulus_byte_length);
+
+        let new = Self {
+            input_page,
+            input_offset,
+            output_page,
+            output_offset,
+            base_byte_length,
+            exponent_byte_length,
+            modulus_byte_length,
+            num_base_words,
+            num_exponent_words,
+            num_modulus_words,
+        };
+
+        new
+    }
+}
+
+fn num_words_for_byte_length<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    byte_length: UInt32<F>,
+) -> UInt32<F> {
+    let (num_full_words, rem) = byte_length.div_by_constant(cs, 32);
+    let rem_is_zero = rem.is_zero(cs);
+    let has_partial_word = rem_is_zero.negated(cs);
+
+    num_full_words.add_no_overflow(cs, unsafe {
+        UInt32::from_variable_unchecked(has_partial_word.get_variable())
+    })
+}
+
+/// Continues left-to-right square and multiply with 256 bits of the exponent word
+fn modexp_over_exponent_word<F: SmallField, CS: ConstraintSystem<F>, const N: usize>(
+    cs: &mut CS,
+    accumulator: &[UInt256<F>; N],
+    base: &[UInt256<F>; N],
+    exponent_word: &UInt256<F>,
+    modulus: &[UInt256<F>; N],
+) -> [UInt256<F>; N] {
+    let mut accumulator = *accumulator;
+    for limb in exponent_word.inner.iter().rev() {
+        let bits = Num::<F>::from_variable(limb.get_variable()).spread_into_bits::<_, 32>(cs);
+        for bit in bits.iter().rev() {
+            accumulator = modmul(cs, &accumulator, &accumulator, modulus);
+            let multiplied = modmul(cs, &accumulator, base, modulus);
+            accumulator =
+                <[UInt256<F>; N]>::conditionally_select(cs, *bit, &multiplied, &accumulator);
+        }
+    }
+
+    accumulator
+}
+
+/// Reads the operand of `num_words` (at most N) words placed in memory with the most
+/// significant word first, and returns it with the least significant word first
+fn read_operand<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+    const N: usize,
+>(
+    cs: &mut CS,
+    memory_queue: &mut MemoryQueue<F, R>,
+    memory_read_witness: &ConditionalWitnessAllocator<F, UInt256<F>>,
+    bias_variable: &mut Variable,
+    should_read: Boolean<F>,
+    num_words: UInt32<F>,
+    offset: UInt32<F>,
+    page: UInt32<F>,
+    timestamp: UInt32<F>,
+) -> [UInt256<F>; N]
+where
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    let boolean_false = Boolean::allocated_constant(cs, false);
+    let zero_u256 = UInt256::zero(cs);
+
+    let mut value = [zero_u256; N];
+    for word_idx in 0..N {
+        let word_idx_u32 = UInt32::allocated_constant(cs, word_idx as u32);
+        let (_, word_is_present) = word_idx_u32.overflowing_sub(cs, num_words);
+        let should_read_word = Boolean::multi_and(cs, &[should_read, word_is_present]);
+
+        let read_query_value =
+            memory_read_witness.conditionally_allocate_biased(cs, should_read_word, *bias_variable);
+        *bias_variable = read_query_value.inner[0].get_variable();
+
+        let word_idx_u32 = word_idx_u32.into_num();
+        let index = offset.into_num().add(cs, &word_idx_u32);
+        let index = unsafe { UInt32::from_variable_unchecked(index.get_variable()) };
+
+        let read_query = MemoryQuery {
+            timestamp,
+            memory_page: page,
+            index,
+            rw_flag: boolean_false,
+            is_ptr: boolean_false,
+            value: read_query_value,
+        };
+
+        // perform read
+        memory_queue.push(cs, read_query, should_read_word);
+
+        // every next word is less significant, so we shift what we have read so far
+        let shifted: [UInt256<F>; N] = std::array::from_fn(|i| {
+            if i == 0 {
+                read_query_value
+            } else {
+                value[i - 1]
+            }
+        });
+        value = <[UInt256<F>; N]>::conditionally_select(cs, should_read_word, &shifted, &value);
+    }
+
+    value
+}
+
+/// Base and modulus have at most N words, and calls with any of the byte lengths above the
+/// limits are not processed and produce a failure output
+pub fn modexp_precompile_inner<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+    const N: usize,
+>(
+    cs: &mut CS,
+    memory_queue: &mut MemoryQueue<F, R>,
+    precompile_calls_queue: &mut StorageLogQueue<F, R>,
+    memory_read_witness: ConditionalWitnessAllocator<F, UInt256<F>>,
+    mut state: ModexpFSM<F, N>,
+    _round_function: &R,
+    limit: usize,
+    max_base_byte_length: u32,
+    max_exponent_byte_length: u32,
+    max_modulus_byte_length: u32,
+) -> ModexpFSM<F, N>
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    assert!(limit <= u32::MAX as usize);
+    assert!(max_base_byte_length as usize <= N * 32);
+    assert!(max_modulus_byte_length as usize <= N * 32);
+    assert!(max_exponent_byte_length < u32::MAX);
+
+    let precompile_address = UInt160::allocated_constant(cs, MODEXP_PRECOMPILE_FORMAL_ADDRESS);
+    let aux_byte_for_precompile = UInt8::allocated_constant(cs, PRECOMPILE_AUX_BYTE);
+
+    let boolean_false = Boolean::allocated_constant(cs, false);
+    let boolean_true = Boolean::allocated_constant(cs, true);
+    let zero_u256 = UInt256::zero(cs);
+    let zero_words = [zero_u256; N];
+    let one_words = constant_one::<F, CS, N>(cs);
+
+    let max_base_byte_length_plus_one = UInt32::allocated_constant(cs, max_base_byte_length + 1);
+    let max_exponent_byte_length_plus_one =
+        UInt32::allocated_constant(cs, max_exponent_byte_length + 1);
+    let max_modulus_byte_length_plus_one =
+        UInt32::allocated_constant(cs, max_modulus_byte_length + 1);
+
+    // we can have a degenerate case when queue is empty, but it's a first circuit in the queue,
+    // so we taken default FSM state that has state.read_precompile_call = true;
+    let input_queue_is_empty = precompile_calls_queue.is_empty(cs);
+    // we can only skip the full circuit if we are not in any form of progress
+    let can_finish_immediatelly =
+        Boolean::multi_and(cs, &[state.read_precompile_call, input_queue_is_empty]);
+
+    if crate::config::CIRCUIT_VERSOBE {
+        dbg!(can_finish_immediatelly.witness_hook(cs)());
+        dbg!(state.witness_hook(cs)());
+    }
+
+    state.read_precompile_call = state
+        .read_precompile_call
+        .mask_negated(cs, can_finish_immediatelly);
+    state.read_words_for_round = state
+        .read_words_for_round
+        .mask_negated(cs, can_finish_immediatelly);
+    state.completed = Boolean::multi_or(cs, &[state.completed, can_finish_immediatelly]);
+
+    // main work cycle, every cycle consumes one word of the exponent
+    for _cycle in 0..limit {
+        if crate::config::CIRCUIT_VERSOBE {
+            dbg!(_cycle);
+            dbg!(state.witness_hook(cs)());
+            dbg!(precompile_calls_queue.into_state().witness_hook(cs)());
+        }
+        // if we are in a proper state then get the ABI from the queue
+        let (precompile_call, _) = precompile_calls_queue.pop_front(cs, state.read_precompile_call);
+
+        Num::conditionally_enforce_equal(
+            cs,
+            state.read_precompile_call,
+            &Num::from_variable(precompile_call.aux_byte.get_variable()),
+            &Num::from_variable(aux_byte_for_precompile.get_variable()),
+        );
+        for (a, b) in precompile_call
+            .address
+            .inner
+            .iter()
+            .zip(precompile_address.inner.iter())
+        {
+            Num::conditionally_enforce_equal(
+                cs,
+                state.read_precompile_call,
+                &Num::from_variable(a.get_variable()),
+                &Num::from_variable(b.get_variable()),
+            );
+        }
+
+        // now compute some parameters that describe the call itself
+
+        let params_encoding = precompile_call.key;
+        let call_params = ModexpPrecompileCallParams::from_encoding(cs, params_encoding);
+
+        state.precompile_call_params = ModexpPrecompileCallParams::conditionally_select(
+            cs,
+            state.read_precompile_call,
+            &call_params,
+            &state.precompile_call_params,
+        );
+        // also set timestamps
+        state.timestamp_to_use_for_read = UInt32::conditionally_select(
+            cs,
+            state.read_precompile_call,
+            &precompile_call.timestamp,
+            &state.timestamp_to_use_for_read,
+        );
+
+        // timestamps have large space, so this can be expected
+        let timestamp_to_use_for_write =
+            unsafe { state.timestamp_to_use_for_read.increment_unchecked(cs) };
+        state.timestamp_to_use_for_write = UInt32::conditionally_select(
+            cs,
+            state.read_precompile_call,
+            &timestamp_to_use_for_write,
+            &state.timestamp_to_use_for_write,
+        );
+
+        // ---------------------------------
+        // on a new call we check the size limits and read base and modulus
+
+        let is_new_call = state.read_precompile_call;
+
+        let (_, base_length_is_in_range) = state
+            .precompile_call_params
+            .base_byte_length
+            .overflowing_sub(cs, max_base_byte_length_plus_one);
+        let (_, exponent_length_is_in_range) = state
+            .precompile_call_params
+            .exponent_byte_length
+            .overflowing_sub(cs, max_exponent_byte_length_plus_one);
+        let (_, modulus_length_is_in_range) = state
+            .precompile_call_params
+            .modulus_byte_length
+            .overflowing_sub(cs, max_modulus_byte_length_plus_one);
+        let lengths_are_in_range = Boolean::multi_and(
+            cs,
+            &[
+                base_length_is_in_range,
+                exponent_length_is_in_range,
+                modulus_length_is_in_range,
+            ],
+        );
+        let lengths_over_limit = lengths_are_in_range.negated(cs);
+        state.lengths_over_limit = Boolean::conditionally_select(
+            cs,
+            is_new_call,
+            &lengths_over_limit,
+            &state.lengths_over_limit,
+        );
+
+        // if we are over the limit then we do not read anything and just write the failure
+        let skip_exponent = Boolean::multi_and(cs, &[is_new_call, lengths_over_limit]);
+        state.precompile_call_params.num_exponent_words = state
+            .precompile_call_params
+            .num_exponent_words
+            .mask_negated(cs, skip_exponent);
+
+        let should_read_operands = Boolean::multi_and(cs, &[is_new_call, lengths_are_in_range]);
+
+        let exponent_offset = state
+            .precompile_call_params
+            .input_offset
+            .into_num()
+            .add(cs, &state.precompile_call_params.num_base_words.into_num());
+        let modulus_offset = exponent_offset.add(
+            cs,
+            &state.precompile_call_params.num_exponent_words.into_num(),
+        );
+        let exponent_offset =
+            unsafe { UInt32::from_variable_unchecked(exponent_offset.get_variable()) };
+        let modulus_offset =
+            unsafe { UInt32::from_variable_unchecked(modulus_offset.get_variable()) };
+
+        // NOTE: memory reads witness must follow the same order: base, modulus, and then exponent words
+        let mut bias_variable = should_read_operands.get_variable();
+        let base = read_operand::<F, CS, R, N>(
+            cs,
+            memory_queue,
+            &memory_read_witness,
+            &mut bias_variable,
+            should_read_operands,
+            state.precompile_call_params.num_base_words,
+            state.precompile_call_params.input_offset,
+            state.precompile_call_params.input_page,
+            state.timestamp_to_use_for_read,
+        );
+        let modulus = read_operand::<F, CS, R, N>(
+            cs,
+            memory_queue,
+            &memory_read_witness,
+            &mut bias_variable,
+            should_read_operands,
+            state.precompile_call_params.num_modulus_words,
+            modulus_offset,
+            state.precompile_call_params.input_page,
+            state.timestamp_to_use_for_read,
+        );
+
+        // everything modulo zero is zero, so we can compute modulo one instead
+        let modulus_is_zero = is_zero(cs, &modulus);
+        let modulus =
+            <[UInt256<F>; N]>::conditionally_select(cs, modulus_is_zero, &one_words, &modulus);
+        let base = modmul(cs, &base, &one_words, &modulus);
+        let modulus_is_one = equals(cs, &modulus, &one_words);
+        let accumulator =
+            <[UInt256<F>; N]>::conditionally_select(cs, modulus_is_one, &zero_words, &one_words);
+
+        state.modulus =
+            <[UInt256<F>; N]>::conditionally_select(cs, is_new_call, &modulus, &state.modulus);
+        state.base = <[UInt256<F>; N]>::conditionally_select(cs, is_new_call, &base, &state.base);
+        state.accumulator = <[UInt256<F>; N]>::conditionally_select(
+            cs,
+            is_new_call,
+            &accumulator,
+            &state.accumulator,
+        );
+        state.precompile_call_params.input_offset = UInt32::conditionally_select(
+            cs,
+            is_new_call,
+            &exponent_offset,
+            &state.precompile_call_params.input_offset,
+        );
+
+        state.read_words_for_round = Boolean::multi_or(
+            cs,
+            &[state.read_precompile_call, state.read_words_for_round],
+        );
+        state.read_precompile_call = boolean_false;
+
+        // ---------------------------------
+        // Now read the next word of the exponent
+
+        let zero_words_left = state.precompile_call_params.num_exponent_words.is_zero(cs);
+        let words_left = zero_words_left.negated(cs);
+        let should_read = Boolean::multi_and(cs, &[state.read_words_for_round, words_left]);
+
+        let exponent_word =
+            memory_read_witness.conditionally_allocate_biased(cs, should_read, bias_variable);
+
+        let read_query = MemoryQuery {
+            timestamp: state.timestamp_to_use_for_read,
+            memory_page: state.precompile_call_params.input_page,
+            index: state.precompile_call_params.input_offset,
+            rw_flag: boolean_false,
+            is_ptr: boolean_false,
+            value: exponent_word,
+        };
+
+        // perform read
+        memory_queue.push(cs, read_query, should_read);
+
+        let may_be_new_offset = unsafe {
+            state
+                .precompile_call_params
+                .input_offset
+                .increment_unchecked(cs)
+        };
+        state.precompile_call_params.input_offset = UInt32::conditionally_select(
+            cs,
+            should_read,
+            &may_be_new_offset,
+            &state.precompile_call_params.input_offset,
+        );
+
+        let may_be_new_num_exponent_words = unsafe {
+            state
+                .precompile_call_params
+                .num_exponent_words
+                .decrement_unchecked(cs)
+        };
+        state.precompile_call_params.num_exponent_words = UInt32::conditionally_select(
+            cs,
+            should_read,
+            &may_be_new_num_exponent_words,
+            &state.precompile_call_params.num_exponent_words,
+        );
+
+        let new_accumulator = modexp_over_exponent_word(
+            cs,
+            &state.accumulator,
+            &state.base,
+            &exponent_word,
+            &state.modulus,
+        );
+        state.accumulator = <[UInt256<F>; N]>::conditionally_select(
+            cs,
+            should_read,
+            &new_accumulator,
+            &state.accumulator,
+        );
+
+        let no_words_left = state.precompile_call_params.num_exponent_words.is_zero(cs);
+        let write_result = Boolean::multi_and(cs, &[state.read_words_for_round, no_words_left]);
+
+        let success = state.lengths_over_limit.negated(cs);
+
+        let success_as_u32 = unsafe { UInt32::from_variable_unchecked(success.get_variable()) };
+        let mut success_as_u256 = zero_u256;
+        success_as_u256.inner[0] = success_as_u32;
+
+        let success_query = MemoryQuery {
+            timestamp: state.timestamp_to_use_for_write,
+            memory_page: state.precompile_call_params.output_page,
+            index: state.precompile_call_params.output_offset,
+            rw_flag: boolean_true,
+            is_ptr: boolean_false,
+            value: success_as_u256,
+        };
+
+        // perform write
+        memory_queue.push(cs, success_query, write_result);
+
+        // result has the length of the modulus and follows the success word, most significant
+        // word first. On failure only the success word is written
+        let write_result_words = Boolean::multi_and(cs, &[write_result, success]);
+        let result_end_offset = state.precompile_call_params.output_offset.into_num().add(
+            cs,
+            &state.precompile_call_params.num_modulus_words.into_num(),
+        );
+        for (word_idx, word) in state.accumulator.iter().enumerate() {
+            let word_idx_u32 = UInt32::allocated_constant(cs, word_idx as u32);
+            let (_, word_is_present) =
+                word_idx_u32.overflowing_sub(cs, state.precompile_call_params.num_modulus_words);
+            let should_write_word = Boolean::multi_and(cs, &[write_result_words, word_is_present]);
+
+            let word_idx_u32 = word_idx_u32.into_num();
+            let index = result_end_offset.sub(cs, &word_idx_u32);
+            let index = unsafe { UInt32::from_variable_unchecked(index.get_variable()) };
+
+            let result_query = MemoryQuery {
+                timestamp: state.timestamp_to_use_for_write,
+                memory_page: state.precompile_call_params.output_page,
+                index,
+                rw_flag: boolean_true,
+                is_ptr: boolean_false,
+                value: *word,
+            };
+
+            // perform write
+            memory_queue.push(cs, result_query, should_write_word);
+        }
+
+        // ---------------------------------
+
+        // update state
+        let input_is_empty = precompile_calls_queue.is_empty(cs);
+        let input_is_not_empty = input_is_empty.negated(cs);
+        let nothing_left = Boolean::multi_and(cs, &[write_result, input_is_empty]);
+        let process_next = Boolean::multi_and(cs, &[write_result, input_is_not_empty]);
+
+        state.read_precompile_call = process_next;
+        state.completed = Boolean::multi_or(cs, &[nothing_left, state.completed]);
+        let t = Boolean::multi_or(cs, &[state.read_precompile_call, state.completed]);
+        state.read_words_for_round = t.negated(cs);
+
+        if crate::config::CIRCUIT_VERSOBE {
+            dbg!(state.witness_hook(cs)());
+            dbg!(precompile_calls_queue.into_state().witness_hook(cs)());
+        }
+    }
+
+    precompile_calls_queue.enforce_consistency(cs);
+
+    state
+}
+
+#[track_caller]
+pub fn modexp_function_entry_point<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+    const N: usize,
+>(
+    cs: &mut CS,
+    witness: ModexpCircuitInstanceWitness<F, N>,
+    round_function: &R,
+    limit: usize,
+    max_base_byte_length: u32,
+    max_exponent_byte_length: u32,
+    max_modulus_byte_length: u32,
+) -> [Num<F>; INPUT_OUTPUT_COMMITMENT_LENGTH]
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    let ModexpCircuitInstanceWitness {
+        closed_form_input,
+        requests_queue_witness,
+        memory_reads_witness,
+    } = witness;
+
+    let mut structured_input =
+        ModexpCircuitInputOutput::alloc_ignoring_outputs(cs, closed_form_input.clone());
+
+    let start_flag = structured_input.start_flag;
+
+    let requests_queue_state_from_input = structured_input.observable_input.initial_log_queue_state;
+
+    // it must be trivial
+    requests_queue_state_from_input.enforce_trivial_head(cs);
+
+    let requests_queue_state_from_fsm = structured_input.hidden_fsm_input.log_queue_state;
+
+    let requests_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &requests_queue_state_from_input,
+        &requests_queue_state_from_fsm,
+    );
+
+    let memory_queue_state_from_input =
+        structured_input.observable_input.initial_memory_queue_state;
+
+    // it must be trivial
+    memory_queue_state_from_input.enforce_trivial_head(cs);
+
+    let memory_queue_state_from_fsm = structured_input.hidden_fsm_input.memory_queue_state;
+
+    let memory_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &memory_queue_state_from_input,
+        &memory_queue_state_from_fsm,
+    );
+
+    let mut requests_queue = StorageLogQueue::<F, R>::from_state(cs, requests_queue_state);
+    let queue_witness = CircuitQueueWitness::from_inner_witness(requests_queue_witness);
+    requests_queue.witness = Arc::new(queue_witness);
+
+    let mut memory_queue = MemoryQueue::<F, R>::from_state(cs, memory_queue_state);
+
+    let read_queries_allocator = ConditionalWitnessAllocator::<F, UInt256<F>> {
+        witness_source: Arc::new(RwLock::new(memory_reads_witness)),
+    };
+
+    let mut starting_fsm_state = ModexpFSM::placeholder(cs);
+    starting_fsm_state.read_precompile_call = Boolean::allocated_constant(cs, true);
+
+    let initial_state = ModexpFSM::conditionally_select(
+        cs,
+        start_flag,
+        &starting_fsm_state,
+        &structured_input.hidden_fsm_input.internal_fsm,
+    );
+
+    let final_state = modexp_precompile_inner::<F, CS, R, N>(
+        cs,
+        &mut memory_queue,
+        &mut requests_queue,
+        read_queries_allocator,
+        initial_state,
+        round_function,
+        limit,
+        max_base_byte_length,
+        max_exponent_byte_length,
+        max_modulus_byte_length,
+    );
+
+    let final_memory_state = memory_queue.into_state();
+    let final_requets_state = requests_queue.into_state();
+
+    // form the final state
+    let done = final_state.completed;
+    structured_input.completion_flag = done;
+    structured_input.observable_output = PrecompileFunctionOutputData::placeholder(cs);
+
+    structured_input.observable_output.final_memory_state = QueueState::conditionally_select(
+        cs,
+        structured_input.completion_flag,
+        &final_memory_state,
+        &structured_input.observable_output.final_memory_state,
+    );
+
+    structured_input.hidden_fsm_output.internal_fsm = final_state;
+    structured_input.hidden_fsm_output.log_queue_state = final_requets_state;
+    structured_input.hidden_fsm_output.memory_queue_state = final_memory_state;
+
+    // self-check
+    structured_input.hook_compare_witness(cs, &closed_form_input);
+
+    use boojum::cs::gates::PublicInputGate;
+
+    let compact_form =
+        ClosedFormInputCompactForm::from_full_form(cs, &structured_input, round_function);
+    let input_commitment = commit_variable_length_encodable_item(cs, &compact_form, round_function);
+    for el in input_commitment.iter() {
+        let gate = PublicInputGate::new(el.get_variable());
+        gate.add_to_cs(cs);
+    }
+
+    input_commitment
+}
+
+#[cfg(test)]
+mod test {
+    use boojum::algebraic_props::poseidon2_parameters::*;
+    use boojum::field::goldilocks::GoldilocksField;
+    use boojum::gadgets::traits::allocatable::CSAllocatable;
+    use boojum::implementations::poseidon2::Poseidon2Goldilocks;
+    use boojum::worker::Worker;
+    use std::collections::VecDeque;
+    use zkevm_opcode_defs::PrecompileCallABI;
+
+    use super::*;
+    use crate::ethereum_types::U512;
+
+    type F = GoldilocksField;
+    type P = GoldilocksField;
+    type R = Poseidon2Goldilocks;
+
+    use boojum::cs::cs_builder::*;
+    use boojum::cs::cs_builder_reference::CsReferenceImplementationBuilder;
+    use boojum::cs::gates::*;
+    use boojum::cs::implementations::reference_cs::CSReferenceImplementation;
+    use boojum::cs::traits::gate::GatePlacementStrategy;
+    use boojum::cs::CSGeometry;
+    use boojum::cs::*;
+    use boojum::gadgets::tables::*;
+
+    fn create_cs(
+        max_trace_len: usize,
+    ) -> CSReferenceImplementation<
+        F,
+        P,
+        DevCSConfig,
+        impl GateConfigurationHolder<F>,
+        impl StaticToolboxHolder,
+    > {
+        let geometry = CSGeometry {
+            num_columns_under_copy_permutation: 80,
+            num_witness_columns: 0,
+            num_constant_columns: 4,
+            max_allowed_constraint_degree: 8,
+        };
+        let max_variables = 1 << 26;
+
+        fn configure<
+            F: SmallField,
+            T: CsBuilderImpl<F, T>,
+            GC: GateConfigurationHolder<F>,
+            TB: StaticToolboxHolder,
+        >(
+            builder: CsBuilder<T, F, GC, TB>,
+        ) -> CsBuilder<T, F, impl GateConfigurationHolder<F>, impl StaticToolboxHolder> {
+            let builder = builder.allow_lookup(
+                LookupParameters::UseSpecializedColumnsWithTableIdAsConstant {
+                    width: 3,
+                    num_repetitions: 16,
+                    share_table_id: true,
+                },
+            );
+
+            let builder = ConstantsAllocatorGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = BooleanConstraintGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseSpecializedColumns {
+                    num_repetitions: 1,
+                    share_constants: false,
+                },
+            );
+            let builder = U8x4FMAGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ZeroCheckGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+                false,
+            );
+            let builder = FmaGateInBaseFieldWithoutConstant::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<32>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<16>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<8>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = DotProductGate::<4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = SelectionGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ParallelSelectionGate::<4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = PublicInputGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ReductionGate::<_, 4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = MatrixMultiplicationGate::<F, 12, Poseidon2GoldilocksExternalMatrix>::configure_builder(builder,GatePlacementStrategy::UseGeneralPurposeColumns);
+            let builder = MatrixMultiplicationGate::<F, 12, Poseidon2GoldilocksInnerMatrix>::configure_builder(builder,GatePlacementStrategy::UseGeneralPurposeColumns);
+            let builder = NopGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+
+            builder
+        }
+
+        let builder_impl =
+            CsReferenceImplementationBuilder::<F, P, DevCSConfig>::new(geometry, max_trace_len);
+        let builder = new_builder::<_, F>(builder_impl);
+
+        let builder = configure(builder);
+        let mut owned_cs = builder.build(max_variables);
+
+        // add tables
+        let table = create_xor8_table();
+        owned_cs.add_lookup_table::<Xor8Table, 3>(table);
+
+        let table = create_byte_split_table::<F, 4>();
+        owned_cs.add_lookup_table::<ByteSplitTable<4>, 3>(table);
+
+        owned_cs
+    }
+    fn modpow_reference(base: U256, exponent: U256, modulus: U256) -> U256 {
+        if modulus.is_zero() {
+            return U256::zero();
+        }
+        let modmul = |a: U256, b: U256| {
+            let (_, rem) = a.full_mul(b).div_mod(U512::from(modulus));
+            U256([rem.0[0], rem.0[1], rem.0[2], rem.0[3]])
+        };
+        let mut result = modmul(U256::one(), U256::one());
+        let base = modmul(base, U256::one());
+        for i in (0..256).rev() {
+            result = modmul(result, result);
+            if exponent.bit(i) {
+                result = modmul(result, base);
+            }
+        }
+
+        result
+    }
+
+    fn limbs_from_words(words: &[U256]) -> Vec<u32> {
+        words
+            .iter()
+            .flat_map(|el| {
+                let mut bytes = [0u8; 32];
+                el.to_little_endian(&mut bytes);
+                bytes
+                    .array_chunks::<4>()
+                    .map(|el| u32::from_le_bytes(*el))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    fn words_from_limbs(limbs: &[u32]) -> Vec<U256> {
+        limbs
+            .chunks(8)
+            .map(|el| {
+                let mut bytes = [0u8; 32];
+                for (dst, src) in bytes.array_chunks_mut::<4>().zip(el.iter()) {
+                    *dst = src.to_le_bytes();
+                }
+                U256::from_little_endian(&bytes)
+            })
+            .collect()
+    }
+
+    // operands are words with the least significant word first, and exponent words are
+    // the most significant word first, same as in memory
+    fn multiword_modpow_reference(
+        base: &[U256],
+        exponent_words: &[U256],
+        modulus: &[U256],
+    ) -> Vec<U256> {
+        let modulus = limbs_from_words(modulus);
+        let modmul = |a: &[u32], b: &[u32]| native_div_rem(&native_mul(a, b), &modulus).1;
+        let mut one = vec![0u32; modulus.len()];
+        one[0] = 1;
+
+        let base = native_div_rem(&limbs_from_words(base), &modulus).1;
+        let mut result = native_div_rem(&one, &modulus).1;
+        for word in exponent_words.iter() {
+            for i in (0..256).rev() {
+                result = modmul(&result, &result);
+                if word.bit(i) {
+                    result = modmul(&result, &base);
+                }
+            }
+        }
+
+        words_from_limbs(&result)
+    }
+
+    fn run_modexp<const N: usize>(
+        base: [U256; N],
+        exponent_words: &[U256],
+        modulus: [U256; N],
+    ) -> [U256; N] {
+        let mut owned_cs = create_cs(1 << 22);
+        let cs = &mut owned_cs;
+
+        let one_words = constant_one::<F, _, N>(cs);
+        let zero_words = [UInt256::zero(cs); N];
+
+        let base = base.map(|el| UInt256::allocate(cs, el));
+        let modulus = modulus.map(|el| UInt256::allocate(cs, el));
+        let modulus_is_zero = is_zero(cs, &modulus);
+        let modulus =
+            <[UInt256<F>; N]>::conditionally_select(cs, modulus_is_zero, &one_words, &modulus);
+        let base = modmul(cs, &base, &one_words, &modulus);
+        let modulus_is_one = equals(cs, &modulus, &one_words);
+        let mut accumulator =
+            <[UInt256<F>; N]>::conditionally_select(cs, modulus_is_one, &zero_words, &one_words);
+
+        for word in exponent_words.iter() {
+            let word = UInt256::allocate(cs, *word);
+            accumulator = modexp_over_exponent_word(cs, &accumulator, &base, &word, &modulus);
+        }
+
+        let result = accumulator.map(|el| el.witness_hook(&*cs)().unwrap());
+
+        cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        let worker = Worker::new();
+        assert!(cs.check_if_satisfied(&worker));
+
+        result
+    }
+
+    fn modmul_witness_is_satisfied(
+        a: U256,
+        b: U256,
+        modulus: U256,
+        mul_result: &[u64],
+        quotient_and_remainder: &[u64],
+    ) -> bool {
+        let mut owned_cs = create_cs(1 << 20);
+        let cs = &mut owned_cs;
+
+        let a = [UInt256::allocate(cs, a)];
+        let b = [UInt256::allocate(cs, b)];
+        let modulus = [UInt256::allocate(cs, modulus)];
+        let mut allocate_witness = |limbs: &[u64]| -> Vec<Variable> {
+            limbs
+                .iter()
+                .map(|el| cs.alloc_single_variable_from_witness(F::from_u64_unchecked(*el)))
+                .collect()
+        };
+        let mul_result = allocate_witness(mul_result);
+        let quotient_and_remainder = allocate_witness(quotient_and_remainder);
+        let _ = modmul_from_witness(cs, &a, &b, &modulus, &mul_result, &quotient_and_remainder);
+
+        cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        let worker = Worker::new();
+        cs.check_if_satisfied(&worker)
+    }
+
+    #[test]
+    fn test_modmul_rejects_out_of_range_witness_limbs() {
+        // (2^32 + 4)^2 = (2^32 + 3) * (2^32 + 5) + 1
+        let modulus = U256::from(0x1_0000_0005u64);
+        let a = U256::from(0x1_0000_0004u64);
+
+        let mut mul_result = vec![0u64; 16];
+        mul_result[..3].copy_from_slice(&[16, 8, 1]);
+        let mut quotient_and_remainder = vec![0u64; 16];
+        quotient_and_remainder[..2].copy_from_slice(&[3, 1]);
+        quotient_and_remainder[8] = 1;
+        assert!(modmul_witness_is_satisfied(
+            a,
+            a,
+            modulus,
+            &mul_result,
+            &quotient_and_remainder
+        ));
+
+        // quotient with the lowest limb of 2^32 + 3 and zero next limb has the same value,
+        // and all the multiplication chains still hold
+        let mut forged_quotient_and_remainder = quotient_and_remainder.clone();
+        forged_quotient_and_remainder[0] = (1u64 << 32) + 3;
+        forged_quotient_and_remainder[1] = 0;
+        assert!(!modmul_witness_is_satisfied(
+            a,
+            a,
+            modulus,
+            &mul_result,
+            &forged_quotient_and_remainder
+        ));
+    }
+
+    #[test]
+    fn test_modexp_over_single_word() {
+        let base = U256::from_dec_str(
+            "98765432109876543210987654321098765432109876543210987654321098765432",
+        )
+        .unwrap();
+        let exponent = U256::from_dec_str("65537").unwrap();
+        let modulus = U256::from_dec_str(
+            "115792089237316195423570985008687907853269984665640564039457584007908834671663",
+        )
+        .unwrap();
+
+        let [result] = run_modexp([base], &[exponent], [modulus]);
+        assert_eq!(result, modpow_reference(base, exponent, modulus));
+
+        // base larger than modulus
+        let [result] = run_modexp([U256::MAX], &[exponent], [U256::from(1000003u64)]);
+        assert_eq!(
+            result,
+            modpow_reference(U256::MAX, exponent, U256::from(1000003u64))
+        );
+    }
+
+    #[test]
+    fn test_modexp_edge_cases() {
+        let base = U256::from(7u64);
+
+        // zero exponent
+        assert_eq!(
+            run_modexp([base], &[U256::zero()], [U256::from(13u64)]),
+            [U256::one()]
+        );
+        // zero and unit modulus
+        assert_eq!(
+            run_modexp([base], &[U256::from(5u64)], [U256::zero()]),
+            [U256::zero()]
+        );
+        assert_eq!(
+            run_modexp([base], &[U256::from(5u64)], [U256::one()]),
+            [U256::zero()]
+        );
+        // 0^0 = 1 by convention
+        assert_eq!(
+            run_modexp([U256::zero()], &[U256::zero()], [U256::from(13u64)]),
+            [U256::one()]
+        );
+    }
+
+    #[test]
+    fn test_modexp_over_multiple_words() {
+        // 7^(2^256 + 3) mod 13
+        let [result] = run_modexp(
+            [U256::from(7u64)],
+            &[U256::one(), U256::from(3u64)],
+            [U256::from(13u64)],
+        );
+        assert_eq!(result, U256::from(6u64));
+    }
+
+    fn test_operand(num_words: usize, seed: u8) -> Vec<U256> {
+        (0..num_words)
+            .map(|el| {
+                let mut word = [0u8; 32];
+                for (idx, dst) in word.iter_mut().enumerate() {
+                    *dst = (el * 32 + idx) as u8 ^ seed;
+                }
+                U256::from_big_endian(&word)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_modexp_over_multiword_operands() {
+        // 1024 bit modulus, as in RSA
+        let mut modulus = test_operand(4, 0xa5);
+        modulus[0] = modulus[0] | U256::one();
+        let modulus: [U256; 4] = modulus.try_into().unwrap();
+        let base: [U256; 4] = test_operand(4, 0x3c).try_into().unwrap();
+        // base is larger than modulus
+        assert!(base[3] > modulus[3]);
+        let exponent = U256::from(65537u64);
+
+        let result = run_modexp(base, &[exponent], modulus);
+        let expected = multiword_modpow_reference(&base, &[exponent], &modulus);
+        assert_eq!(result.to_vec(), expected);
+
+        // reference is consistent with the single word one
+        let base = U256::from(7u64);
+        let modulus = U256::from(13u64);
+        assert_eq!(
+            multiword_modpow_reference(&[base], &[U256::one(), U256::from(3u64)], &[modulus]),
+            vec![U256::from(6u64)]
+        );
+    }
+
+    // (base, exponent, modulus) byte strings of the call, padded to the whole words
+    fn words_of_operand(bytes: &[u8]) -> Vec<U256> {
+        let mut padded = vec![0u8; (32 - bytes.len() % 32) % 32];
+        padded.extend_from_slice(bytes);
+        padded.chunks(32).map(U256::from_big_endian).collect()
+    }
+
+    fn run_modexp_fsm<const N: usize>(
+        calls: &[(Vec<u8>, Vec<u8>, Vec<u8>)],
+        limit: usize,
+        max_base_byte_length: u32,
+        max_exponent_byte_length: u32,
+        max_modulus_byte_length: u32,
+    ) -> Vec<Vec<U256>> {
+        let mut owned_cs = create_cs(1 << 23);
+        let cs = &mut owned_cs;
+        let mut memory_queue = MemoryQueue::<F, R>::empty(cs);
+        let mut precompile_calls_queue = StorageLogQueue::<F, R>::empty(cs);
+        let boolean_true = Boolean::allocated_constant(cs, true);
+
+        let mut memory_read_witness = VecDeque::new();
+        let mut output_offsets = vec![];
+        let mut output_offset = 0;
+        for (call_idx, (base, exponent, modulus)) in calls.iter().enumerate() {
+            let is_over_limit = base.len() as u32 > max_base_byte_length
+                || exponent.len() as u32 > max_exponent_byte_length
+                || modulus.len() as u32 > max_modulus_byte_length;
+            if !is_over_limit {
+                memory_read_witness.extend(words_of_operand(base));
+                memory_read_witness.extend(words_of_operand(modulus));
+                memory_read_witness.extend(words_of_operand(exponent));
+            }
+
+            let precompile_abi = PrecompileCallABI {
+                input_memory_offset: 1000 * call_idx as u32,
+                input_memory_length: base.len() as u32,
+                output_memory_offset: output_offset,
+                output_memory_length: modulus.len() as u32,
+                memory_page_to_read: 123,
+                memory_page_to_write: 456,
+                precompile_interpreted_data: exponent.len() as u64,
+            };
+            output_offsets.push(output_offset);
+            output_offset += 1 + words_of_operand(modulus).len() as u32;
+
+            let el = LogQueryWitness {
+                address: MODEXP_PRECOMPILE_FORMAL_ADDRESS,
+                key: precompile_abi.to_u256(),
+                read_value: U256::zero(),
+                written_value: U256::zero(),
+                aux_byte: PRECOMPILE_AUX_BYTE,
+                rw_flag: true,
+                rollback: false,
+                is_service: false,
+                shard_id: 0,
+                tx_number_in_block: 0,
+                timestamp: (call_idx as u32 + 1) * 4,
+            };
+            let el = LogQuery::allocate(cs, el);
+            precompile_calls_queue.push(cs, el, boolean_true);
+        }
+        let memory_read_witness = Arc::new(RwLock::new(memory_read_witness));
+
+        let mut state = ModexpFSM::<F, N>::placeholder(cs);
+        state.read_precompile_call = boolean_true;
+        let round_function = Poseidon2Goldilocks;
+        let new_state = modexp_precompile_inner(
+            cs,
+            &mut memory_queue,
+            &mut precompile_calls_queue,
+            ConditionalWitnessAllocator::<F, UInt256<F>> {
+                witness_source: memory_read_witness.clone(),
+            },
+            state,
+            &round_function,
+            limit,
+            max_base_byte_length,
+            max_exponent_byte_length,
+            max_modulus_byte_length,
+        );
+        assert!(new_state.completed.witness_hook(cs)().unwrap());
+
+        drop(cs);
+
+        let mut written_values = vec![vec![]; calls.len()];
+        for (query, _) in memory_queue.witness.elements.read().unwrap().iter() {
+            if query.rw_flag {
+                let call_idx = output_offsets
+                    .iter()
+                    .rposition(|el| *el <= query.index)
+                    .unwrap();
+                assert_eq!(
+                    query.index,
+                    output_offsets[call_idx] + written_values[call_idx].len() as u32
+                );
+                written_values[call_idx].push(query.value);
+            }
+        }
+
+        let _ = owned_cs.pad_and_shrink();
+        let mut assembly = owned_cs.into_assembly::<std::alloc::Global>();
+        let worker = Worker::new();
+        assert!(assembly.check_if_satisfied(&worker));
+
+        assert!(memory_read_witness.read().unwrap().is_empty());
+
+        written_values
+    }
+
+    #[test]
+    fn test_modexp_fsm_with_multiword_operands() {
+        let base: Vec<u8> = (0..40).map(|el| el as u8 ^ 0x5a).collect();
+        let exponent: Vec<u8> = (0..33).map(|el| el as u8 ^ 0x13).collect();
+        let mut modulus: Vec<u8> = (0..50).map(|el| el as u8 ^ 0xc3).collect();
+        *modulus.last_mut().unwrap() |= 1;
+
+        let calls = vec![
+            (base.clone(), exponent.clone(), modulus.clone()),
+            // modulus over the limit
+            (base.clone(), exponent.clone(), vec![1u8; 65]),
+            // empty modulus gives empty result
+            (base.clone(), exponent.clone(), vec![]),
+        ];
+        let written_values = run_modexp_fsm::<2>(&calls, 5, 64, 64, 64);
+
+        // reference takes operands with the least significant word first
+        let mut modulus_words = words_of_operand(&modulus);
+        modulus_words.reverse();
+        let mut base_words = words_of_operand(&base);
+        base_words.reverse();
+        let mut result =
+            multiword_modpow_reference(&base_words, &words_of_operand(&exponent), &modulus_words);
+        result.reverse();
+        let mut expected = vec![U256::one()];
+        expected.extend(result);
+        assert_eq!(written_values[0], expected);
+
+        assert_eq!(written_values[1], vec![U256::zero()]);
+        assert_eq!(written_values[2], vec![U256::one()]);
+    }
+}
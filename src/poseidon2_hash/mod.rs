@@ -0,0 +1,831 @@
+use super::*;
+
+use boojum::field::SmallField;
+
+use boojum::gadgets::traits::witnessable::WitnessHookable;
+
+use boojum::cs::traits::cs::ConstraintSystem;
+use boojum::gadgets::boolean::Boolean;
+use boojum::gadgets::traits::selectable::Selectable;
+use boojum::gadgets::u256::UInt256;
+use boojum::gadgets::u32::UInt32;
+use cs_derive::*;
+
+use crate::ethereum_types::U256;
+use crate::fsm_input_output::circuit_inputs::INPUT_OUTPUT_COMMITMENT_LENGTH;
+use boojum::gadgets::num::Num;
+use zkevm_opcode_defs::system_params::PRECOMPILE_AUX_BYTE;
+
+use crate::base_structures::log_query::*;
+use crate::base_structures::memory_query::*;
+use crate::base_structures::precompile_input_outputs::formal_precompile_address;
+use crate::base_structures::precompile_input_outputs::PrecompileFunctionOutputData;
+use crate::demux_log_queue::StorageLogQueue;
+use crate::fsm_input_output::*;
+use crate::storage_application::ConditionalWitnessAllocator;
+use boojum::algebraic_props::round_function::AlgebraicRoundFunction;
+use boojum::cs::Variable;
+use boojum::gadgets::queue::CircuitQueueWitness;
+use boojum::gadgets::queue::QueueState;
+use boojum::gadgets::traits::allocatable::CSAllocatable;
+use boojum::gadgets::traits::allocatable::{CSAllocatableExt, CSPlaceholder};
+use boojum::gadgets::traits::encodable::CircuitVarLengthEncodable;
+use boojum::gadgets::traits::round_function::CircuitRoundFunction;
+use boojum::gadgets::u160::UInt160;
+use boojum::gadgets::u8::UInt8;
+use std::sync::{Arc, RwLock};
+use zkevm_opcode_defs::ethereum_types::H160;
+
+use crate::base_structures::precompile_input_outputs::*;
+use crate::demux_log_queue::DemuxOutput;
+use crate::precompile_registry::PrecompileCircuit;
+use crate::scheduler::auxiliary::BaseLayerCircuitType;
+
+pub mod input;
+use self::input::*;
+
+// placed right after the secp256r1 batch verification precompile
+pub const POSEIDON2_PRECOMPILE_ADDRESS: u16 = 0x106;
+pub const POSEIDON2_PRECOMPILE_FORMAL_ADDRESS: H160 =
+    formal_precompile_address(POSEIDON2_PRECOMPILE_ADDRESS);
+
+pub struct Poseidon2PrecompileCircuit;
+
+impl PrecompileCircuit for Poseidon2PrecompileCircuit {
+    const NAME: &'static str = "poseidon2";
+    const DEMUX_OUTPUT: DemuxOutput = DemuxOutput::Poseidon2;
+    const CIRCUIT_TYPE: BaseLayerCircuitType = BaseLayerCircuitType::Poseidon2Precompile;
+
+    type InputData<F: SmallField> = PrecompileFunctionInputData<F>;
+    type OutputData<F: SmallField> = PrecompileFunctionOutputData<F>;
+
+    fn formal_address() -> H160 {
+        POSEIDON2_PRECOMPILE_FORMAL_ADDRESS
+    }
+}
+
+pub const POSEIDON2_STATE_WIDTH: usize = 12;
+pub const POSEIDON2_RATE: usize = 8;
+pub const POSEIDON2_CAPACITY: usize = 4;
+pub const POSEIDON2_DIGEST_ELEMENTS: usize = 4;
+
+#[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
+#[derivative(Clone, Copy, Debug)]
+// #[DerivePrettyComparison("true")]
+pub struct Poseidon2PrecompileCallParams<F: SmallField> {
+    pub input_page: UInt32<F>,
+    pub input_offset: UInt32<F>,
+    pub output_page: UInt32<F>,
+    pub output_offset: UInt32<F>,
+    pub num_words: UInt32<F>,
+}
+
+impl<F: SmallField> CSPlaceholder<F> for Poseidon2PrecompileCallParams<F> {
+    fn placeholder<CS: ConstraintSystem<F>>(cs: &mut CS) -> Self {
+        let zero_u32 = UInt32::zero(cs);
+        Self {
+            input_page: zero_u32,
+            input_offset: zero_u32,
+            output_page: zero_u32,
+            output_offset: zero_u32,
+            num_words: zero_u32,
+        }
+    }
+}
+
+impl<F: SmallField> Poseidon2PrecompileCallParams<F> {
+    // same layout as for sha256, but the length is a number of 32 byte words to absorb
+    pub fn from_encoding<CS: ConstraintSystem<F>>(_cs: &mut CS, encoding: UInt256<F>) -> Self {
+        let input_offset = encoding.inner[0];
+        let output_offset = encoding.inner[2];
+        let input_page = encoding.inner[4];
+        let output_page = encoding.inner[5];
+
+        let num_words = encoding.inner[6];
+
+        let new = Self {
+            input_page,
+            input_offset,
+            output_page,
+            output_offset,
+            num_words,
+        };
+
+        new
+    }
+}
+
+// one memory word is split into 8 32-bit limbs, that is exactly the rate of the sponge
+pub const MEMORY_READ_QUERIES_PER_CYCLE: usize = 1;
+
+/// Sponge state at the start of the call. Length of the input (in words) goes into the capacity,
+/// so inputs of different lengths never collide even though we do not pad
+pub fn poseidon2_initial_state<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    num_words: UInt32<F>,
+) -> [Num<F>; POSEIDON2_STATE_WIDTH] {
+    let mut state = R::create_empty_state(cs);
+    R::apply_length_specialization(cs, &mut state, num_words.get_variable());
+
+    state.map(|el| Num::from_variable(el))
+}
+
+pub fn poseidon2_absorb_word<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    state: [Num<F>; POSEIDON2_STATE_WIDTH],
+    word: &UInt256<F>,
+) -> [Num<F>; POSEIDON2_STATE_WIDTH] {
+    let to_absorb = word.inner.map(|el| el.into_num());
+    let mut state_to_keep = [state[0]; POSEIDON2_CAPACITY];
+    state_to_keep.copy_from_slice(&state[POSEIDON2_RATE..]);
+    let state = R::absorb_with_replacement_over_nums(cs, to_absorb, state_to_keep);
+
+    R::compute_round_function_over_nums(cs, state)
+}
+
+/// Digest is 4 field elements, written as one memory word where element `i` takes bits
+/// `64 * i..64 * (i + 1)`. Every element is checked to be in the canonical form, so the word
+/// is unique for the given state
+pub fn poseidon2_digest_as_word<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    state: &[Num<F>; POSEIDON2_STATE_WIDTH],
+) -> UInt256<F> {
+    let digest =
+        R::state_into_commitment::<POSEIDON2_DIGEST_ELEMENTS>(&state.map(|el| el.get_variable()))
+            .map(|el| Num::from_variable(el));
+
+    let zero_u8 = UInt8::zero(cs);
+    let boolean_true = Boolean::allocated_constant(cs, true);
+    let modulus = UInt256::allocated_constant(cs, U256::from(F::CHAR));
+
+    let mut le_bytes = [zero_u8; 32];
+    for (dst, src) in le_bytes.array_chunks_mut::<8>().zip(digest.iter()) {
+        let bytes = src.constraint_bit_length_as_bytes(cs, 64);
+        dst.copy_from_slice(&bytes[..]);
+
+        // bytes only give us the value modulo the characteristic
+        let mut element_bytes = [zero_u8; 32];
+        element_bytes[..8].copy_from_slice(&bytes[..]);
+        let element = UInt256::from_le_bytes(cs, element_bytes);
+        let (_, is_canonical) = element.overflowing_sub(cs, &modulus);
+        Boolean::enforce_equal(cs, &is_canonical, &boolean_true);
+    }
+
+    UInt256::from_le_bytes(cs, le_bytes)
+}
+
+pub fn poseidon2_precompile_inner<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    memory_queue: &mut MemoryQueue<F, R>,
+    precompile_calls_queue: &mut StorageLogQueue<F, R>,
+    memory_read_witness: ConditionalWitnessAllocator<F, UInt256<F>>,
+    mut state: Poseidon2FSM<F>,
+    _round_function: &R,
+    limit: usize,
+) -> Poseidon2FSM<F>
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    assert!(limit <= u32::MAX as usize);
+
+    let precompile_address = UInt160::allocated_constant(cs, POSEIDON2_PRECOMPILE_FORMAL_ADDRESS);
+    let aux_byte_for_precompile = UInt8::allocated_constant(cs, PRECOMPILE_AUX_BYTE);
+
+    let boolean_false = Boolean::allocated_constant(cs, false);
+    let boolean_true = Boolean::allocated_constant(cs, true);
+
+    // we can have a degenerate case when queue is empty, but it's a first circuit in the queue,
+    // so we taken default FSM state that has state.read_precompile_call = true;
+    let input_queue_is_empty = precompile_calls_queue.is_empty(cs);
+    // we can only skip the full circuit if we are not in any form of progress
+    let can_finish_immediatelly =
+        Boolean::multi_and(cs, &[state.read_precompile_call, input_queue_is_empty]);
+
+    if crate::config::CIRCUIT_VERSOBE {
+        dbg!(can_finish_immediatelly.witness_hook(cs)());
+        dbg!(state.witness_hook(cs)());
+    }
+
+    state.read_precompile_call = state
+        .read_precompile_call
+        .mask_negated(cs, can_finish_immediatelly);
+    state.read_words_for_round = state
+        .read_words_for_round
+        .mask_negated(cs, can_finish_immediatelly);
+    state.completed = Boolean::multi_or(cs, &[state.completed, can_finish_immediatelly]);
+
+    if crate::config::CIRCUIT_VERSOBE {
+        dbg!(state.witness_hook(cs)());
+        dbg!(precompile_calls_queue.into_state().witness_hook(cs)());
+        memory_read_witness.print_debug_info();
+    }
+    // main work cycle
+    for _cycle in 0..limit {
+        if crate::config::CIRCUIT_VERSOBE {
+            dbg!(_cycle);
+            dbg!(state.witness_hook(cs)());
+            dbg!(precompile_calls_queue.into_state().witness_hook(cs)());
+        }
+        // if we are in a proper state then get the ABI from the queue
+        let (precompile_call, _) = precompile_calls_queue.pop_front(cs, state.read_precompile_call);
+
+        Num::conditionally_enforce_equal(
+            cs,
+            state.read_precompile_call,
+            &Num::from_variable(precompile_call.aux_byte.get_variable()),
+            &Num::from_variable(aux_byte_for_precompile.get_variable()),
+        );
+        for (a, b) in precompile_call
+            .address
+            .inner
+            .iter()
+            .zip(precompile_address.inner.iter())
+        {
+            Num::conditionally_enforce_equal(
+                cs,
+                state.read_precompile_call,
+                &Num::from_variable(a.get_variable()),
+                &Num::from_variable(b.get_variable()),
+            );
+        }
+
+        // now compute some parameters that describe the call itself
+
+        let params_encoding = precompile_call.key;
+        let call_params = Poseidon2PrecompileCallParams::from_encoding(cs, params_encoding);
+
+        state.precompile_call_params = Poseidon2PrecompileCallParams::conditionally_select(
+            cs,
+            state.read_precompile_call,
+            &call_params,
+            &state.precompile_call_params,
+        );
+        // also set timestamps
+        state.timestamp_to_use_for_read = UInt32::conditionally_select(
+            cs,
+            state.read_precompile_call,
+            &precompile_call.timestamp,
+            &state.timestamp_to_use_for_read,
+        );
+
+        // timestamps have large space, so this can be expected
+        let timestamp_to_use_for_write =
+            unsafe { state.timestamp_to_use_for_read.increment_unchecked(cs) };
+        state.timestamp_to_use_for_write = UInt32::conditionally_select(
+            cs,
+            state.read_precompile_call,
+            &timestamp_to_use_for_write,
+            &state.timestamp_to_use_for_write,
+        );
+
+        // new call starts from the fresh sponge, specialized for the full length of the input
+        let initial_sponge_state =
+            poseidon2_initial_state::<F, CS, R>(cs, state.precompile_call_params.num_words);
+        let current_sponge_state = <[Num<F>; POSEIDON2_STATE_WIDTH]>::conditionally_select(
+            cs,
+            state.read_precompile_call,
+            &initial_sponge_state,
+            &state.sponge_state,
+        );
+
+        state.read_words_for_round = Boolean::multi_or(
+            cs,
+            &[state.read_precompile_call, state.read_words_for_round],
+        );
+        state.read_precompile_call = boolean_false;
+
+        // ---------------------------------
+        // Now perform a memory query to read content. Empty input still takes one round,
+        // where we absorb a zero word
+
+        let zero_words_left = state.precompile_call_params.num_words.is_zero(cs);
+        let words_left = zero_words_left.negated(cs);
+        let should_read = Boolean::multi_and(cs, &[state.read_words_for_round, words_left]);
+
+        let read_query_value = memory_read_witness.conditionally_allocate_biased(
+            cs,
+            should_read,
+            should_read.get_variable(),
+        );
+
+        let read_query = MemoryQuery {
+            timestamp: state.timestamp_to_use_for_read,
+            memory_page: state.precompile_call_params.input_page,
+            index: state.precompile_call_params.input_offset,
+            rw_flag: boolean_false,
+            is_ptr: boolean_false,
+            value: read_query_value,
+        };
+
+        // perform read
+        memory_queue.push(cs, read_query, should_read);
+
+        let may_be_new_offset = unsafe {
+            state
+                .precompile_call_params
+                .input_offset
+                .increment_unchecked(cs)
+        };
+        state.precompile_call_params.input_offset = UInt32::conditionally_select(
+            cs,
+            should_read,
+            &may_be_new_offset,
+            &state.precompile_call_params.input_offset,
+        );
+
+        let may_be_new_num_words = unsafe {
+            state
+                .precompile_call_params
+                .num_words
+                .decrement_unchecked(cs)
+        };
+        state.precompile_call_params.num_words = UInt32::conditionally_select(
+            cs,
+            should_read,
+            &may_be_new_num_words,
+            &state.precompile_call_params.num_words,
+        );
+
+        // absorb
+        let new_sponge_state =
+            poseidon2_absorb_word::<F, CS, R>(cs, current_sponge_state, &read_query_value);
+        state.sponge_state = <[Num<F>; POSEIDON2_STATE_WIDTH]>::conditionally_select(
+            cs,
+            state.read_words_for_round,
+            &new_sponge_state,
+            &current_sponge_state,
+        );
+
+        let no_words_left = state.precompile_call_params.num_words.is_zero(cs);
+        let write_result = Boolean::multi_and(cs, &[state.read_words_for_round, no_words_left]);
+
+        let write_word = poseidon2_digest_as_word::<F, CS, R>(cs, &state.sponge_state);
+
+        let write_query = MemoryQuery {
+            timestamp: state.timestamp_to_use_for_write,
+            memory_page: state.precompile_call_params.output_page,
+            index: state.precompile_call_params.output_offset,
+            rw_flag: boolean_true,
+            is_ptr: boolean_false,
+            value: write_word,
+        };
+
+        // perform write
+        memory_queue.push(cs, write_query, write_result);
+
+        // ---------------------------------
+
+        // update state
+        let input_is_empty = precompile_calls_queue.is_empty(cs);
+        let input_is_not_empty = input_is_empty.negated(cs);
+        let nothing_left = Boolean::multi_and(cs, &[write_result, input_is_empty]);
+        let process_next = Boolean::multi_and(cs, &[write_result, input_is_not_empty]);
+
+        state.read_precompile_call = process_next;
+        state.completed = Boolean::multi_or(cs, &[nothing_left, state.completed]);
+        let t = Boolean::multi_or(cs, &[state.read_precompile_call, state.completed]);
+        state.read_words_for_round = t.negated(cs);
+
+        if crate::config::CIRCUIT_VERSOBE {
+            dbg!(state.witness_hook(cs)());
+            dbg!(precompile_calls_queue.into_state().witness_hook(cs)());
+        }
+    }
+
+    if crate::config::CIRCUIT_VERSOBE {
+        dbg!(state.witness_hook(cs)());
+        dbg!(precompile_calls_queue.into_state().witness_hook(cs)());
+    }
+
+    precompile_calls_queue.enforce_consistency(cs);
+
+    state
+}
+#[track_caller]
+pub fn poseidon2_entry_point<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    witness: Poseidon2CircuitInstanceWitness<F>,
+    round_function: &R,
+    limit: usize,
+) -> [Num<F>; INPUT_OUTPUT_COMMITMENT_LENGTH]
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    let Poseidon2CircuitInstanceWitness {
+        closed_form_input,
+        requests_queue_witness,
+        memory_reads_witness,
+    } = witness;
+
+    let mut structured_input =
+        Poseidon2CircuitInputOutput::alloc_ignoring_outputs(cs, closed_form_input.clone());
+
+    let start_flag = structured_input.start_flag;
+
+    let requests_queue_state_from_input = structured_input.observable_input.initial_log_queue_state;
+
+    // it must be trivial
+    requests_queue_state_from_input.enforce_trivial_head(cs);
+
+    let requests_queue_state_from_fsm = structured_input.hidden_fsm_input.log_queue_state;
+
+    let requests_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &requests_queue_state_from_input,
+        &requests_queue_state_from_fsm,
+    );
+
+    let memory_queue_state_from_input =
+        structured_input.observable_input.initial_memory_queue_state;
+
+    // it must be trivial
+    memory_queue_state_from_input.enforce_trivial_head(cs);
+
+    let memory_queue_state_from_fsm = structured_input.hidden_fsm_input.memory_queue_state;
+
+    let memory_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &memory_queue_state_from_input,
+        &memory_queue_state_from_fsm,
+    );
+
+    let mut requests_queue = StorageLogQueue::<F, R>::from_state(cs, requests_queue_state);
+    let queue_witness = CircuitQueueWitness::from_inner_witness(requests_queue_witness);
+    requests_queue.witness = Arc::new(queue_witness);
+
+    let mut memory_queue = MemoryQueue::<F, R>::from_state(cs, memory_queue_state);
+
+    let read_queries_allocator = ConditionalWitnessAllocator::<F, UInt256<F>> {
+        witness_source: Arc::new(RwLock::new(memory_reads_witness)),
+    };
+
+    let mut starting_fsm_state = Poseidon2FSM::placeholder(cs);
+    starting_fsm_state.read_precompile_call = Boolean::allocated_constant(cs, true);
+
+    let initial_state = Poseidon2FSM::conditionally_select(
+        cs,
+        start_flag,
+        &starting_fsm_state,
+        &structured_input.hidden_fsm_input.internal_fsm,
+    );
+
+    let final_state = poseidon2_precompile_inner::<F, CS, R>(
+        cs,
+        &mut memory_queue,
+        &mut requests_queue,
+        read_queries_allocator,
+        initial_state,
+        round_function,
+        limit,
+    );
+
+    let final_memory_state = memory_queue.into_state();
+    let final_requets_state = requests_queue.into_state();
+
+    // form the final state
+    let done = final_state.completed;
+    structured_input.completion_flag = done;
+    structured_input.observable_output = PrecompileFunctionOutputData::placeholder(cs);
+
+    structured_input.observable_output.final_memory_state = QueueState::conditionally_select(
+        cs,
+        structured_input.completion_flag,
+        &final_memory_state,
+        &structured_input.observable_output.final_memory_state,
+    );
+
+    structured_input.hidden_fsm_output.internal_fsm = final_state;
+    structured_input.hidden_fsm_output.log_queue_state = final_requets_state;
+    structured_input.hidden_fsm_output.memory_queue_state = final_memory_state;
+
+    // self-check
+    structured_input.hook_compare_witness(cs, &closed_form_input);
+
+    use boojum::cs::gates::PublicInputGate;
+
+    let compact_form =
+        ClosedFormInputCompactForm::from_full_form(cs, &structured_input, round_function);
+    let input_commitment = commit_variable_length_encodable_item(cs, &compact_form, round_function);
+    for el in input_commitment.iter() {
+        let gate = PublicInputGate::new(el.get_variable());
+        gate.add_to_cs(cs);
+    }
+
+    input_commitment
+}
+#[cfg(test)]
+mod test {
+    use boojum::algebraic_props::poseidon2_parameters::*;
+    use boojum::config::DevCSConfig;
+    use boojum::cs::cs_builder::*;
+    use boojum::cs::gates::*;
+    use boojum::cs::implementations::reference_cs::CSReferenceImplementation;
+    use boojum::cs::traits::gate::*;
+    use boojum::cs::*;
+    use boojum::field::goldilocks::GoldilocksField;
+    use boojum::gadgets::tables::*;
+    use boojum::implementations::poseidon2::Poseidon2Goldilocks;
+    use boojum::worker::Worker;
+    use std::collections::VecDeque;
+    use zkevm_opcode_defs::PrecompileCallABI;
+
+    use super::*;
+
+    type F = GoldilocksField;
+    type P = GoldilocksField;
+    type R = Poseidon2Goldilocks;
+
+    fn create_test_cs() -> CSReferenceImplementation<
+        GoldilocksField,
+        GoldilocksField,
+        DevCSConfig,
+        impl GateConfigurationHolder<GoldilocksField>,
+        impl StaticToolboxHolder,
+    > {
+        let geometry = CSGeometry {
+            num_columns_under_copy_permutation: 100,
+            num_witness_columns: 0,
+            num_constant_columns: 8,
+            max_allowed_constraint_degree: 4,
+        };
+
+        fn configure<
+            T: CsBuilderImpl<F, T>,
+            GC: GateConfigurationHolder<F>,
+            TB: StaticToolboxHolder,
+        >(
+            builder: CsBuilder<T, F, GC, TB>,
+        ) -> CsBuilder<T, F, impl GateConfigurationHolder<F>, impl StaticToolboxHolder> {
+            let builder = builder.allow_lookup(
+                LookupParameters::UseSpecializedColumnsWithTableIdAsConstant {
+                    width: 3,
+                    num_repetitions: 8,
+                    share_table_id: true,
+                },
+            );
+            let builder = ConstantsAllocatorGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = FmaGateInBaseFieldWithoutConstant::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ReductionGate::<F, 4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = BooleanConstraintGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<32>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<16>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<8>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = SelectionGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ZeroCheckGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+                false,
+            );
+            let builder = DotProductGate::<4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = MatrixMultiplicationGate::<F, 12, Poseidon2GoldilocksExternalMatrix>::configure_builder(builder,GatePlacementStrategy::UseGeneralPurposeColumns);
+            let builder = MatrixMultiplicationGate::<F, 12, Poseidon2GoldilocksInnerMatrix>::configure_builder(builder,GatePlacementStrategy::UseGeneralPurposeColumns);
+            let builder = NopGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+
+            builder
+        }
+
+        use boojum::cs::cs_builder_reference::CsReferenceImplementationBuilder;
+
+        let builder_impl =
+            CsReferenceImplementationBuilder::<F, P, DevCSConfig>::new(geometry, 1 << 20);
+        let builder = new_builder::<_, F>(builder_impl);
+
+        let builder = configure(builder);
+        let mut owned_cs = builder.build(1 << 26);
+
+        // byte decompositions of the digest use these tables
+        let table = create_xor8_table();
+        owned_cs.add_lookup_table::<Xor8Table, 3>(table);
+
+        let table = create_byte_split_table::<F, 1>();
+        owned_cs.add_lookup_table::<ByteSplitTable<1>, 3>(table);
+        let table = create_byte_split_table::<F, 2>();
+        owned_cs.add_lookup_table::<ByteSplitTable<2>, 3>(table);
+        let table = create_byte_split_table::<F, 3>();
+        owned_cs.add_lookup_table::<ByteSplitTable<3>, 3>(table);
+        let table = create_byte_split_table::<F, 4>();
+        owned_cs.add_lookup_table::<ByteSplitTable<4>, 3>(table);
+
+        owned_cs
+    }
+
+    fn create_memory_read_witness(words: &[U256]) -> Arc<RwLock<VecDeque<U256>>> {
+        Arc::new(RwLock::new(words.iter().copied().collect()))
+    }
+
+    // hash the words natively, every word is absorbed as its 8 u32 limbs from the lowest one
+    fn reference_digest(words: &[U256]) -> U256 {
+        use boojum::algebraic_props::round_function::{
+            absorb_into_state_vararg, AbsorptionModeOverwrite,
+        };
+
+        let mut input = vec![];
+        for word in words.iter() {
+            for limb in 0..8 {
+                let limb = (*word >> (32 * limb)).low_u32();
+                input.push(F::from_u64_unchecked(limb as u64));
+            }
+        }
+        // empty input is hashed as a zero word
+        if words.is_empty() {
+            input.resize(POSEIDON2_RATE, F::ZERO);
+        }
+
+        let mut state = R::initial_state();
+        R::specialize_for_len(words.len() as u32, &mut state);
+        absorb_into_state_vararg::<F, R, AbsorptionModeOverwrite, 8, 12, 4>(&mut state, &input);
+        let digest = <R as AlgebraicRoundFunction<F, 8, 12, 4>>::state_into_commitment::<
+            POSEIDON2_DIGEST_ELEMENTS,
+        >(&state);
+
+        let mut result = U256::zero();
+        for (dst, src) in result.0.iter_mut().zip(digest.iter()) {
+            *dst = src.as_u64_reduced();
+        }
+
+        result
+    }
+
+    // runs the FSM over as many instances as there are limits, passing the state from
+    // one instance to the next one, and returns the written digest
+    fn run_in_instances(words: &[U256], limits: &[usize]) -> U256 {
+        let memory_read_witness = create_memory_read_witness(words);
+
+        let precompile_abi = PrecompileCallABI {
+            input_memory_offset: 0,
+            input_memory_length: 0,
+            output_memory_offset: 0,
+            output_memory_length: 1,
+            memory_page_to_read: 123,
+            memory_page_to_write: 456,
+            precompile_interpreted_data: words.len() as u64,
+        };
+
+        let mut state_witness = Poseidon2FSM::placeholder_witness();
+        state_witness.read_precompile_call = true;
+
+        let mut written_values = vec![];
+        for (instance_idx, limit) in limits.iter().enumerate() {
+            let mut owned_cs = create_test_cs();
+            let cs = &mut owned_cs;
+            let mut memory_queue = MemoryQueue::<F, R>::empty(cs);
+            let boolean_true = Boolean::allocated_constant(cs, true);
+
+            // only the first instance sees the call, next ones continue it
+            let mut precompile_calls_queue = StorageLogQueue::<F, R>::empty(cs);
+            if instance_idx == 0 {
+                let el = LogQueryWitness {
+                    address: POSEIDON2_PRECOMPILE_FORMAL_ADDRESS,
+                    key: precompile_abi.to_u256(),
+                    read_value: U256::zero(),
+                    written_value: U256::zero(),
+                    aux_byte: PRECOMPILE_AUX_BYTE,
+                    rw_flag: true,
+                    rollback: false,
+                    is_service: false,
+                    shard_id: 0,
+                    tx_number_in_block: 0,
+                    timestamp: 1,
+                };
+                let el = LogQuery::allocate(cs, el);
+                precompile_calls_queue.push(cs, el, boolean_true);
+            }
+
+            let state = Poseidon2FSM::allocate(cs, state_witness.clone());
+            let round_function = Poseidon2Goldilocks;
+
+            let memory_read_witness = ConditionalWitnessAllocator::<F, UInt256<F>> {
+                witness_source: memory_read_witness.clone(),
+            };
+
+            let new_state = poseidon2_precompile_inner(
+                cs,
+                &mut memory_queue,
+                &mut precompile_calls_queue,
+                memory_read_witness,
+                state,
+                &round_function,
+                *limit,
+            );
+
+            let is_last = instance_idx == limits.len() - 1;
+            assert_eq!(new_state.completed.witness_hook(cs)().unwrap(), is_last);
+            state_witness = new_state.witness_hook(cs)().unwrap();
+
+            drop(cs);
+
+            for (query, _) in memory_queue.witness.elements.read().unwrap().iter() {
+                if query.rw_flag {
+                    written_values.push(query.value);
+                }
+            }
+
+            let _ = owned_cs.pad_and_shrink();
+            let mut assembly = owned_cs.into_assembly::<std::alloc::Global>();
+            let worker = Worker::new();
+            let is_satisfied = assembly.check_if_satisfied(&worker);
+            assert!(is_satisfied);
+        }
+
+        assert!(memory_read_witness.read().unwrap().is_empty());
+        assert_eq!(written_values.len(), 1);
+
+        written_values[0]
+    }
+
+    fn test_words(num_words: usize) -> Vec<U256> {
+        (0..num_words)
+            .map(|el| {
+                let mut word = [0u8; 32];
+                for (idx, dst) in word.iter_mut().enumerate() {
+                    *dst = (el * 32 + idx) as u8;
+                }
+                U256::from_big_endian(&word)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn poseidon2_one_word() {
+        let words = test_words(1);
+        let digest = run_in_instances(&words, &[1]);
+        assert_eq!(digest, reference_digest(&words));
+    }
+
+    #[test]
+    fn poseidon2_empty_input() {
+        let digest = run_in_instances(&[], &[1]);
+        assert_eq!(digest, reference_digest(&[]));
+
+        // length goes into the capacity, so it's not the same as a zero word
+        assert!(digest != reference_digest(&[U256::zero()]));
+    }
+
+    #[test]
+    fn poseidon2_long_input_across_instances() {
+        let words = test_words(7);
+        let expected = reference_digest(&words);
+
+        assert_eq!(run_in_instances(&words, &[7]), expected);
+        assert_eq!(run_in_instances(&words, &[3, 3, 1]), expected);
+        assert_eq!(run_in_instances(&words, &[1, 5, 1]), expected);
+    }
+}
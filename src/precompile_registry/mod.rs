@@ -0,0 +1,144 @@
+use crate::demux_log_queue::DemuxOutput;
+use crate::scheduler::auxiliary::BaseLayerCircuitType;
+
+use boojum::field::SmallField;
+use boojum::gadgets::traits::allocatable::CSAllocatable;
+use boojum::gadgets::traits::encodable::CircuitVarLengthEncodable;
+
+use zkevm_opcode_defs::ethereum_types::H160;
+use zkevm_opcode_defs::system_params::PRECOMPILE_AUX_BYTE;
+
+/// Precompile that is processed by its own base layer circuit. Log queries with the
+/// precompile's aux byte and formal address are routed by the demultiplexer into the
+/// `DEMUX_OUTPUT` queue, and the circuit consumes this queue and continues the memory queue.
+/// It's proven under `CIRCUIT_TYPE`, and the scheduler orders precompiles by it
+pub trait PrecompileCircuit: 'static {
+    const NAME: &'static str;
+    const DEMUX_OUTPUT: DemuxOutput;
+    const CIRCUIT_TYPE: BaseLayerCircuitType;
+    const AUX_BYTE: u8 = PRECOMPILE_AUX_BYTE;
+
+    type InputData<F: SmallField>: CSAllocatable<F> + CircuitVarLengthEncodable<F>;
+    type OutputData<F: SmallField>: CSAllocatable<F> + CircuitVarLengthEncodable<F>;
+
+    fn formal_address() -> H160;
+}
+
+/// Type-erased description of a precompile circuit, so we can keep all of them in one list
+#[derive(Clone, Copy, Debug)]
+pub struct PrecompileDescriptor {
+    pub name: &'static str,
+    pub demux_output: DemuxOutput,
+    pub circuit_type: BaseLayerCircuitType,
+    pub aux_byte: u8,
+    pub formal_address: fn() -> H160,
+}
+
+impl PrecompileDescriptor {
+    pub const fn of<P: PrecompileCircuit>() -> Self {
+        Self {
+            name: P::NAME,
+            demux_output: P::DEMUX_OUTPUT,
+            circuit_type: P::CIRCUIT_TYPE,
+            aux_byte: P::AUX_BYTE,
+            formal_address: P::formal_address,
+        }
+    }
+}
+
+pub const NUM_REGISTERED_PRECOMPILES: usize = 24;
+
+/// All the precompile circuits in the order of scheduling. Every precompile takes the memory
+/// queue state produced by the previous one, so all of them must use `PrecompileFunctionInputData`
+/// and `PrecompileFunctionOutputData` as their observable input and output. Demux outputs,
+/// scheduled circuit types and the number of base layer circuits are derived from this list.
+///
+/// Enum variants can not be generated from a constant list, so a new precompile needs
+/// - a `DemuxOutput` variant with the next free value,
+/// - a `BaseLayerCircuitType` variant with the next free value below `EIP4844Repack`,
+/// - `PrecompileCircuit` implementation that refers to both of them,
+/// - an entry at the end of this list and bumped `NUM_REGISTERED_PRECOMPILES`.
+///
+/// If scheduled circuit types no longer fit into recursion tips, the build fails and
+/// `NUM_RECURSION_TIPS_USED` must be raised, that changes the scheduler VK
+pub const PRECOMPILE_REGISTRY: [PrecompileDescriptor; NUM_REGISTERED_PRECOMPILES] = [
+    PrecompileDescriptor::of::<crate::keccak256_round_function::Keccak256PrecompileCircuit>(),
+    PrecompileDescriptor::of::<crate::sha256_round_function::Sha256PrecompileCircuit>(),
+    PrecompileDescriptor::of::<crate::ecrecover::EcrecoverPrecompileCircuit>(),
+    PrecompileDescriptor::of::<crate::secp256r1_verify::Secp256r1VerifyPrecompileCircuit>(),
+    PrecompileDescriptor::of::<crate::bn254_ecadd::ECAddPrecompileCircuit>(),
+    PrecompileDescriptor::of::<crate::bn254_ecmul::ECMulPrecompileCircuit>(),
+    PrecompileDescriptor::of::<crate::bn254_ecpairing::ECPairingPrecompileCircuit>(),
+    PrecompileDescriptor::of::<crate::modexp::ModexpPrecompileCircuit>(),
+    PrecompileDescriptor::of::<crate::blake2f_round_function::Blake2fPrecompileCircuit>(),
+    PrecompileDescriptor::of::<crate::ripemd160_round_function::Ripemd160PrecompileCircuit>(),
+    PrecompileDescriptor::of::<crate::kzg_point_evaluation::KzgPointEvaluationPrecompileCircuit>(),
+    PrecompileDescriptor::of::<crate::ed25519_verify::Ed25519VerifyPrecompileCircuit>(),
+    PrecompileDescriptor::of::<crate::schnorr_verify::SchnorrVerifyPrecompileCircuit>(),
+    PrecompileDescriptor::of::<crate::sha512_round_function::Sha512PrecompileCircuit>(),
+    PrecompileDescriptor::of::<crate::secp256r1_verify::Secp256r1RecoverPrecompileCircuit>(),
+    PrecompileDescriptor::of::<crate::secp256r1_verify::Secp256r1BatchVerifyPrecompileCircuit>(),
+    PrecompileDescriptor::of::<crate::poseidon2_hash::Poseidon2PrecompileCircuit>(),
+    PrecompileDescriptor::of::<crate::eip_2537::Bls12_381G1AddPrecompileCircuit>(),
+    PrecompileDescriptor::of::<crate::eip_2537::Bls12_381G1MsmPrecompileCircuit>(),
+    PrecompileDescriptor::of::<crate::eip_2537::Bls12_381G2AddPrecompileCircuit>(),
+    PrecompileDescriptor::of::<crate::eip_2537::Bls12_381G2MsmPrecompileCircuit>(),
+    PrecompileDescriptor::of::<crate::eip_2537::Bls12_381PairingCheckPrecompileCircuit>(),
+    PrecompileDescriptor::of::<crate::eip_2537::Bls12_381MapFpToG1PrecompileCircuit>(),
+    PrecompileDescriptor::of::<crate::eip_2537::Bls12_381MapFp2ToG2PrecompileCircuit>(),
+];
+
+// every scheduled circuit type takes a slot of some recursion tip
+const _: () = assert!(
+    crate::scheduler::auxiliary::NUM_CIRCUIT_TYPES_TO_SCHEDULE
+        <= crate::recursion::recursion_tip::input::RECURSION_TIP_ARITY
+            * crate::scheduler::NUM_RECURSION_TIPS_USED,
+    "scheduled circuit types do not fit into recursion tips"
+);
+
+pub fn precompile_by_demux_output(output: DemuxOutput) -> Option<&'static PrecompileDescriptor> {
+    PRECOMPILE_REGISTRY
+        .iter()
+        .find(|el| el.demux_output == output)
+}
+
+pub fn precompile_by_circuit_type(
+    circuit_type: BaseLayerCircuitType,
+) -> Option<&'static PrecompileDescriptor> {
+    PRECOMPILE_REGISTRY
+        .iter()
+        .find(|el| el.circuit_type == circuit_type)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_registry_is_consistent() {
+        let mut demux_outputs = HashSet::new();
+        let mut circuit_types = HashSet::new();
+        let mut addresses = HashSet::new();
+        for el in PRECOMPILE_REGISTRY.iter() {
+            assert!(demux_outputs.insert(el.demux_output), "{}", el.name);
+            assert!(circuit_types.insert(el.circuit_type as u8), "{}", el.name);
+            assert!(addresses.insert((el.formal_address)()), "{}", el.name);
+            assert!(
+                crate::demux_log_queue::ALL_DEMUX_OUTPUTS.contains(&el.demux_output),
+                "{}",
+                el.name
+            );
+            assert!(
+                crate::scheduler::SEQUENCE_OF_CIRCUIT_TYPES.contains(&el.circuit_type),
+                "{}",
+                el.name
+            );
+        }
+
+        // scheduler chains precompiles over memory queue in the order of circuit types
+        for pair in PRECOMPILE_REGISTRY.windows(2) {
+            assert!((pair[0].circuit_type as u8) < (pair[1].circuit_type as u8));
+        }
+    }
+}
@@ -18,6 +18,7 @@ use boojum::cs::traits::cs::ConstraintSystem;
 use boojum::field::SmallField;
 use boojum::gadgets::queue::full_state_queue::FullStateCircuitQueueWitness;
 use boojum::gadgets::traits::round_function::CircuitRoundFunction;
+use boojum::gadgets::traits::selectable::Selectable;
 use boojum::gadgets::{
     boolean::Boolean,
     num::Num,
@@ -29,6 +30,9 @@ use super::*;
 
 pub mod input;
 
+use crate::main_vm::variants::NUM_VM_CIRCUIT_VARIANTS;
+use crate::scheduler::auxiliary::BaseLayerCircuitType;
+
 use self::input::*;
 
 use boojum::cs::implementations::verifier::VerificationKeyCircuitGeometry;
@@ -49,9 +53,88 @@ pub struct LeafLayerRecursionConfig<
     pub proof_config: ProofConfig,
     pub vk_fixed_parameters: VerificationKeyCircuitGeometry,
     pub capacity: usize,
+    /// Verification key commitments of `VM_CIRCUIT_VARIANTS` in the same order. Only used
+    /// by the leaf for `BaseLayerCircuitType::VM`
+    pub vm_variants_vk_commitments: [[F; VK_COMMITMENT_LENGTH]; NUM_VM_CIRCUIT_VARIANTS],
     pub _marker: std::marker::PhantomData<(F, H, EXT)>,
 }
 
+/// Commitment to the set of verification keys of VM variants. It's used as a `basic_circuit_vk_commitment`
+/// in the leaf parameters for `BaseLayerCircuitType::VM`, so any of the variants can be proven
+pub fn commit_vm_variants_vk_set<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    vm_variants_vk_commitments: &[[Num<F>; VK_COMMITMENT_LENGTH]; NUM_VM_CIRCUIT_VARIANTS],
+    round_function: &R,
+) -> [Num<F>; VK_COMMITMENT_LENGTH] {
+    commit_variable_length_encodable_item(cs, vm_variants_vk_commitments, round_function)
+}
+
+fn vk_commitments_are_equal<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    a: &[Num<F>; VK_COMMITMENT_LENGTH],
+    b: &[Num<F>; VK_COMMITMENT_LENGTH],
+) -> Boolean<F> {
+    let equalities = a
+        .iter()
+        .zip(b.iter())
+        .map(|(a, b)| Num::equals(cs, a, b))
+        .collect::<Vec<_>>();
+
+    Boolean::multi_and(cs, &equalities)
+}
+
+/// VK must be the one committed to in the leaf parameters. The only exception is the VM, where
+/// the parameters commit to the set of VKs of all the variants, and VK must be any of them
+fn vk_is_valid_for_circuit_type<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    circuit_type: Num<F>,
+    basic_circuit_vk_commitment: &[Num<F>; VK_COMMITMENT_LENGTH],
+    vk_commitment: &[Num<F>; VK_COMMITMENT_LENGTH],
+    vm_variants_vk_commitments: &[[F; VK_COMMITMENT_LENGTH]; NUM_VM_CIRCUIT_VARIANTS],
+    round_function: &R,
+) -> Boolean<F> {
+    let vk_is_basic_circuit_vk =
+        vk_commitments_are_equal(cs, basic_circuit_vk_commitment, vk_commitment);
+
+    // VM can be proven by any of the variants, and in this case leaf parameters commit to the full set.
+    // All the proofs in the leaf use the same VK, so instances of different variants go into different leafs
+    let vm_circuit_type = Num::allocated_constant(
+        cs,
+        F::from_u64_unchecked(BaseLayerCircuitType::VM as u8 as u64),
+    );
+    let is_vm = Num::equals(cs, &circuit_type, &vm_circuit_type);
+
+    let vm_variants_vk_commitments =
+        vm_variants_vk_commitments.map(|el| el.map(|el| Num::allocated_constant(cs, el)));
+    let vm_variants_vk_set_commitment =
+        commit_vm_variants_vk_set(cs, &vm_variants_vk_commitments, round_function);
+    let vm_variants_vk_set_is_expected = vk_commitments_are_equal(
+        cs,
+        basic_circuit_vk_commitment,
+        &vm_variants_vk_set_commitment,
+    );
+
+    let vk_is_registered_vm_variant = vm_variants_vk_commitments
+        .iter()
+        .map(|el| vk_commitments_are_equal(cs, el, vk_commitment))
+        .collect::<Vec<_>>();
+    let vk_is_registered_vm_variant = Boolean::multi_or(cs, &vk_is_registered_vm_variant);
+    let vk_is_vm_variant_vk = Boolean::multi_and(
+        cs,
+        &[vm_variants_vk_set_is_expected, vk_is_registered_vm_variant],
+    );
+
+    Boolean::conditionally_select(cs, is_vm, &vk_is_vm_variant_vk, &vk_is_basic_circuit_vk)
+}
+
 // NOTE: does NOT allocate public inputs! we will deal with locations of public inputs being the same at the "outer" stage
 pub fn leaf_layer_recursion_entry_point<
     F: SmallField,
@@ -119,12 +202,15 @@ where
     let vk_commitment_computed: [_; VK_COMMITMENT_LENGTH] =
         commit_variable_length_encodable_item(cs, &vk, round_function);
 
-    for (a, b) in basic_circuit_vk_commitment
-        .iter()
-        .zip(vk_commitment_computed.iter())
-    {
-        Num::conditionally_enforce_equal(cs, is_meaningful, a, b);
-    }
+    let vk_is_valid = vk_is_valid_for_circuit_type(
+        cs,
+        circuit_type,
+        &basic_circuit_vk_commitment,
+        &vk_commitment_computed,
+        &config.vm_variants_vk_commitments,
+        round_function,
+    );
+    vk_is_valid.conditionally_enforce_true(cs, is_meaningful);
 
     let mut proof_witnesses = proof_witnesses;
 
@@ -204,3 +290,156 @@ where
 
     input_commitment
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use boojum::algebraic_props::poseidon2_parameters::{
+        Poseidon2GoldilocksExternalMatrix, Poseidon2GoldilocksInnerMatrix,
+    };
+    use boojum::config::DevCSConfig;
+    use boojum::cs::cs_builder::*;
+    use boojum::cs::cs_builder_reference::CsReferenceImplementationBuilder;
+    use boojum::cs::gates::*;
+    use boojum::cs::traits::gate::GatePlacementStrategy;
+    use boojum::cs::*;
+    use boojum::field::goldilocks::GoldilocksField;
+    use boojum::implementations::poseidon2::Poseidon2Goldilocks;
+    use boojum::worker::Worker;
+
+    type F = GoldilocksField;
+
+    fn vk_commitment(seed: u64) -> [F; VK_COMMITMENT_LENGTH] {
+        std::array::from_fn(|idx| F::from_u64_unchecked(seed * 100 + idx as u64))
+    }
+
+    fn registered_vm_variants_vk_commitments(
+    ) -> [[F; VK_COMMITMENT_LENGTH]; NUM_VM_CIRCUIT_VARIANTS] {
+        std::array::from_fn(|idx| vk_commitment(idx as u64 + 1))
+    }
+
+    /// Parameters of the VM leaf commit to the set of the registered variants,
+    /// and for any other circuit type to the `basic_circuit_vk_commitment`
+    fn leaf_accepts_vk(
+        circuit_type: BaseLayerCircuitType,
+        basic_circuit_vk_commitment: [F; VK_COMMITMENT_LENGTH],
+        vk_commitment: [F; VK_COMMITMENT_LENGTH],
+    ) -> bool {
+        let geometry = CSGeometry {
+            num_columns_under_copy_permutation: 60,
+            num_witness_columns: 0,
+            num_constant_columns: 4,
+            max_allowed_constraint_degree: 4,
+        };
+
+        fn configure<
+            T: CsBuilderImpl<F, T>,
+            GC: GateConfigurationHolder<F>,
+            TB: StaticToolboxHolder,
+        >(
+            builder: CsBuilder<T, F, GC, TB>,
+        ) -> CsBuilder<T, F, impl GateConfigurationHolder<F>, impl StaticToolboxHolder> {
+            let builder = ConstantsAllocatorGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = BooleanConstraintGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = FmaGateInBaseFieldWithoutConstant::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ReductionGate::<F, 4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ZeroCheckGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+                false,
+            );
+            let builder = SelectionGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = DotProductGate::<4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = MatrixMultiplicationGate::<F, 12, Poseidon2GoldilocksExternalMatrix>::configure_builder(builder,GatePlacementStrategy::UseGeneralPurposeColumns);
+            let builder = MatrixMultiplicationGate::<F, 12, Poseidon2GoldilocksInnerMatrix>::configure_builder(builder,GatePlacementStrategy::UseGeneralPurposeColumns);
+            let builder = NopGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+
+            builder
+        }
+
+        let builder_impl =
+            CsReferenceImplementationBuilder::<F, F, DevCSConfig>::new(geometry, 1 << 16);
+        let builder = new_builder::<_, F>(builder_impl);
+        let builder = configure(builder);
+        let mut owned_cs = builder.build(1 << 20);
+        let cs = &mut owned_cs;
+
+        let round_function = Poseidon2Goldilocks;
+        let registered = registered_vm_variants_vk_commitments();
+
+        let basic_circuit_vk_commitment = if circuit_type == BaseLayerCircuitType::VM {
+            let registered = registered.map(|el| el.map(|el| Num::allocated_constant(cs, el)));
+            commit_vm_variants_vk_set(cs, &registered, &round_function)
+        } else {
+            basic_circuit_vk_commitment.map(|el| Num::allocate(cs, el))
+        };
+        let circuit_type = Num::allocate(cs, F::from_u64_unchecked(circuit_type as u8 as u64));
+        let vk_commitment = vk_commitment.map(|el| Num::allocate(cs, el));
+
+        let vk_is_valid = vk_is_valid_for_circuit_type(
+            cs,
+            circuit_type,
+            &basic_circuit_vk_commitment,
+            &vk_commitment,
+            &registered,
+            &round_function,
+        );
+        let boolean_true = Boolean::allocated_constant(cs, true);
+        Boolean::enforce_equal(cs, &vk_is_valid, &boolean_true);
+
+        cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        let worker = Worker::new();
+        cs.check_if_satisfied(&worker)
+    }
+
+    #[test]
+    fn test_leaf_accepts_registered_vm_variants_only() {
+        let registered = registered_vm_variants_vk_commitments();
+        for vk in registered {
+            assert!(leaf_accepts_vk(BaseLayerCircuitType::VM, vk, vk));
+        }
+
+        // VK whose commitment is not in the set is rejected, even if it's the one in the parameters
+        let unregistered = vk_commitment(NUM_VM_CIRCUIT_VARIANTS as u64 + 1);
+        assert!(!leaf_accepts_vk(
+            BaseLayerCircuitType::VM,
+            unregistered,
+            unregistered
+        ));
+
+        // other circuit types only accept the basic circuit VK
+        assert!(leaf_accepts_vk(
+            BaseLayerCircuitType::EcrecoverPrecompile,
+            unregistered,
+            unregistered
+        ));
+        assert!(!leaf_accepts_vk(
+            BaseLayerCircuitType::EcrecoverPrecompile,
+            unregistered,
+            registered[0]
+        ));
+    }
+}
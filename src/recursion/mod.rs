@@ -7,4 +7,8 @@ pub mod node_layer;
 pub mod recursion_tip;
 
 pub const VK_COMMITMENT_LENGTH: usize = 4;
-pub const NUM_BASE_LAYER_CIRCUITS: usize = 16;
+// all the scheduled circuits and the EIP4844 repack circuit
+pub const NUM_BASE_LAYER_CIRCUITS: usize =
+    crate::scheduler::auxiliary::NON_PRECOMPILE_CIRCUIT_TYPES_TO_SCHEDULE.len()
+        + crate::precompile_registry::NUM_REGISTERED_PRECOMPILES
+        + 1;
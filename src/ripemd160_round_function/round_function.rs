@@ -0,0 +1,198 @@
+use super::*;
+
+use crate::tables::ripemd160_boolean_functions::*;
+use boojum::gadgets::tables::ByteSplitTable;
+
+pub const RIPEMD160_STATE_WIDTH: usize = 5;
+pub const RIPEMD160_BLOCK_WORDS: usize = 16;
+pub const RIPEMD160_NUM_STEPS: usize = 80;
+
+pub const RIPEMD160_IV: [u32; RIPEMD160_STATE_WIDTH] =
+    [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+const LEFT_CONSTANTS: [u32; 5] = [0x00000000, 0x5a827999, 0x6ed9eba1, 0x8f1bbcdc, 0xa953fd4e];
+const RIGHT_CONSTANTS: [u32; 5] = [0x50a28be6, 0x5c4dd124, 0x6d703ef3, 0x7a6d76e9, 0x00000000];
+
+const LEFT_WORD_SELECTION: [usize; RIPEMD160_NUM_STEPS] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, //
+    7, 4, 13, 1, 10, 6, 15, 3, 12, 0, 9, 5, 2, 14, 11, 8, //
+    3, 10, 14, 4, 9, 15, 8, 1, 2, 7, 0, 6, 13, 11, 5, 12, //
+    1, 9, 11, 10, 0, 8, 12, 4, 13, 3, 7, 15, 14, 5, 6, 2, //
+    4, 0, 5, 9, 7, 12, 2, 10, 14, 1, 3, 8, 11, 6, 15, 13,
+];
+const RIGHT_WORD_SELECTION: [usize; RIPEMD160_NUM_STEPS] = [
+    5, 14, 7, 0, 9, 2, 11, 4, 13, 6, 15, 8, 1, 10, 3, 12, //
+    6, 11, 3, 7, 0, 13, 5, 10, 14, 15, 8, 12, 4, 9, 1, 2, //
+    15, 5, 1, 3, 7, 14, 6, 9, 11, 8, 12, 2, 10, 0, 4, 13, //
+    8, 6, 4, 1, 3, 11, 15, 0, 5, 12, 2, 13, 9, 7, 10, 14, //
+    12, 15, 10, 4, 1, 5, 8, 7, 6, 2, 13, 14, 0, 3, 9, 11,
+];
+const LEFT_ROTATIONS: [usize; RIPEMD160_NUM_STEPS] = [
+    11, 14, 15, 12, 5, 8, 7, 9, 11, 13, 14, 15, 6, 7, 9, 8, //
+    7, 6, 8, 13, 11, 9, 7, 15, 7, 12, 15, 9, 11, 7, 13, 12, //
+    11, 13, 6, 7, 14, 9, 13, 15, 14, 8, 13, 6, 5, 12, 7, 5, //
+    11, 12, 14, 15, 14, 15, 9, 8, 9, 14, 5, 6, 8, 6, 5, 12, //
+    9, 15, 5, 11, 6, 8, 13, 12, 5, 12, 13, 14, 11, 8, 5, 6,
+];
+const RIGHT_ROTATIONS: [usize; RIPEMD160_NUM_STEPS] = [
+    8, 9, 9, 11, 13, 15, 15, 5, 7, 7, 8, 11, 14, 14, 12, 6, //
+    9, 13, 15, 7, 12, 8, 9, 11, 7, 7, 12, 7, 6, 15, 13, 11, //
+    9, 7, 15, 11, 8, 6, 6, 14, 12, 13, 5, 14, 13, 13, 7, 5, //
+    15, 5, 8, 11, 14, 14, 6, 14, 6, 9, 12, 9, 12, 5, 15, 8, //
+    8, 5, 12, 9, 12, 5, 14, 6, 8, 13, 6, 5, 15, 13, 11, 11,
+];
+
+pub fn ripemd160_ivs_as_uint32<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+) -> [UInt32<F>; RIPEMD160_STATE_WIDTH] {
+    RIPEMD160_IV.map(|el| UInt32::allocated_constant(cs, el))
+}
+
+fn split_into_nibbles<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    value: UInt32<F>,
+) -> [Variable; 8] {
+    let byte_split_id = cs
+        .get_table_id_for_marker::<ByteSplitTable<4>>()
+        .expect("table must exist");
+
+    let bytes = value.to_le_bytes(cs);
+    let mut result = [Variable::placeholder(); 8];
+    for (dst, byte) in result.array_chunks_mut::<2>().zip(bytes.iter()) {
+        let [low, high] = cs.perform_lookup::<1, 2>(byte_split_id, &[byte.get_variable()]);
+        *dst = [low, high];
+    }
+
+    result
+}
+
+// evaluates one of the five boolean functions over 4 bit chunks of the arguments
+fn boolean_function<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    function_index: usize,
+    x: UInt32<F>,
+    y: UInt32<F>,
+    z: UInt32<F>,
+) -> UInt32<F> {
+    let table_id = cs
+        .get_table_id_for_marker::<Ripemd160BooleanFunctionsTable>()
+        .expect("table must exist");
+
+    let x = split_into_nibbles(cs, x);
+    let y = split_into_nibbles(cs, y);
+    let z = split_into_nibbles(cs, z);
+    let function_index = cs.allocate_constant(F::from_u64_unchecked(
+        (function_index << RIPEMD160_BOOLEAN_FUNCTION_CHUNK_BITS) as u64,
+    ));
+
+    let shift = F::SHIFTS[RIPEMD160_BOOLEAN_FUNCTION_CHUNK_BITS];
+    let mut result_chunks = [(Variable::placeholder(), F::ZERO); 8];
+    for (i, dst) in result_chunks.iter_mut().enumerate() {
+        let key_0 = Num::linear_combination(cs, &[(x[i], F::ONE), (y[i], shift)]);
+        let key_1 = Num::linear_combination(cs, &[(z[i], F::ONE), (function_index, F::ONE)]);
+        let [chunk] =
+            cs.perform_lookup::<2, 1>(table_id, &[key_0.get_variable(), key_1.get_variable()]);
+        *dst = (chunk, F::SHIFTS[i * RIPEMD160_BOOLEAN_FUNCTION_CHUNK_BITS]);
+    }
+    let result = Num::linear_combination(cs, &result_chunks);
+
+    // it's a u32 by construction of the table
+    unsafe { UInt32::from_variable_unchecked(result.get_variable()) }
+}
+
+// takes the lowest 32 bits of the N bit value and rotates them to the left
+fn reduce_and_rotate_left<F: SmallField, CS: ConstraintSystem<F>, const N: usize>(
+    cs: &mut CS,
+    value: Num<F>,
+    shift: usize,
+) -> UInt32<F> {
+    let bits = value.spread_into_bits::<_, N>(cs);
+    let lc: Vec<_> = bits[..32]
+        .iter()
+        .enumerate()
+        .map(|(i, bit)| (bit.get_variable(), F::SHIFTS[(i + shift) % 32]))
+        .collect();
+    let result = Num::linear_combination(cs, &lc);
+
+    // it's a u32 by construction
+    unsafe { UInt32::from_variable_unchecked(result.get_variable()) }
+}
+
+fn step<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    state: &mut [UInt32<F>; RIPEMD160_STATE_WIDTH],
+    function_index: usize,
+    word: UInt32<F>,
+    constant: u32,
+    rotation: usize,
+) {
+    let [a, b, c, d, e] = *state;
+
+    let f = boolean_function(cs, function_index, b, c, d);
+    let constant = UInt32::allocated_constant(cs, constant);
+    let sum = Num::linear_combination(
+        cs,
+        &[
+            (a.get_variable(), F::ONE),
+            (f.get_variable(), F::ONE),
+            (word.get_variable(), F::ONE),
+            (constant.get_variable(), F::ONE),
+        ],
+    );
+    // sum of four u32 values fits into 34 bits
+    let t = reduce_and_rotate_left::<F, CS, 34>(cs, sum, rotation);
+    let (t, _) = t.overflowing_add(cs, e);
+    let c = reduce_and_rotate_left::<F, CS, 32>(cs, c.into_num(), 10);
+
+    *state = [e, t, b, c, d];
+}
+
+/// Compresses a single 64 byte block into the state. Block words are little-endian
+pub fn ripemd160_round_function<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    state: &mut [UInt32<F>; RIPEMD160_STATE_WIDTH],
+    block: &[UInt32<F>; RIPEMD160_BLOCK_WORDS],
+) {
+    let mut left = *state;
+    let mut right = *state;
+
+    for j in 0..RIPEMD160_NUM_STEPS {
+        let round = j / RIPEMD160_BLOCK_WORDS;
+        step(
+            cs,
+            &mut left,
+            round,
+            block[LEFT_WORD_SELECTION[j]],
+            LEFT_CONSTANTS[round],
+            LEFT_ROTATIONS[j],
+        );
+        step(
+            cs,
+            &mut right,
+            RIPEMD160_NUM_BOOLEAN_FUNCTIONS - 1 - round,
+            block[RIGHT_WORD_SELECTION[j]],
+            RIGHT_CONSTANTS[round],
+            RIGHT_ROTATIONS[j],
+        );
+    }
+
+    // combine both lines
+    *state = std::array::from_fn(|i| {
+        let sum = Num::linear_combination(
+            cs,
+            &[
+                (
+                    state[(i + 1) % RIPEMD160_STATE_WIDTH].get_variable(),
+                    F::ONE,
+                ),
+                (left[(i + 2) % RIPEMD160_STATE_WIDTH].get_variable(), F::ONE),
+                (
+                    right[(i + 3) % RIPEMD160_STATE_WIDTH].get_variable(),
+                    F::ONE,
+                ),
+            ],
+        );
+
+        reduce_and_rotate_left::<F, CS, 34>(cs, sum, 0)
+    });
+}
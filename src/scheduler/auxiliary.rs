@@ -45,19 +45,58 @@ pub enum BaseLayerCircuitType {
     L1MessagesHasher = 13,
     TransientStorageChecker = 14,
     Secp256r1Verify = 15,
+    ECAddPrecompile = 16,
+    ECMulPrecompile = 17,
+    ECPairingPrecompile = 18,
+    ModexpPrecompile = 19,
+    Blake2fPrecompile = 20,
+    Ripemd160Precompile = 21,
+    KzgPointEvaluationPrecompile = 22,
+    Ed25519VerifyPrecompile = 23,
+    SchnorrVerifyPrecompile = 24,
+    Sha512Precompile = 25,
+    Secp256r1RecoverPrecompile = 26,
+    Secp256r1BatchVerifyPrecompile = 27,
+    Poseidon2Precompile = 28,
+    Bls12_381G1AddPrecompile = 29,
+    Bls12_381G1MsmPrecompile = 30,
+    Bls12_381G2AddPrecompile = 31,
+    Bls12_381G2MsmPrecompile = 32,
+    Bls12_381PairingCheckPrecompile = 33,
+    Bls12_381MapFpToG1Precompile = 34,
+    Bls12_381MapFp2ToG2Precompile = 35,
     EIP4844Repack = 255,
 }
 
+/// Scheduled circuits that are not precompiles, the rest are taken from the precompile registry
+pub const NON_PRECOMPILE_CIRCUIT_TYPES_TO_SCHEDULE: [BaseLayerCircuitType; 11] = [
+    BaseLayerCircuitType::VM,
+    BaseLayerCircuitType::DecommitmentsFilter,
+    BaseLayerCircuitType::Decommiter,
+    BaseLayerCircuitType::LogDemultiplexer,
+    BaseLayerCircuitType::RamValidation,
+    BaseLayerCircuitType::StorageFilter,
+    BaseLayerCircuitType::StorageApplicator,
+    BaseLayerCircuitType::EventsRevertsFilter,
+    BaseLayerCircuitType::L1MessagesRevertsFilter,
+    BaseLayerCircuitType::L1MessagesHasher,
+    BaseLayerCircuitType::TransientStorageChecker,
+];
+
 impl BaseLayerCircuitType {
     pub fn from_numeric_value(value: u8) -> Self {
+        if let Some(precompile) = crate::precompile_registry::PRECOMPILE_REGISTRY
+            .iter()
+            .find(|el| el.circuit_type as u8 == value)
+        {
+            return precompile.circuit_type;
+        }
+
         let t: Self = match value {
             a if a == Self::VM as u8 => Self::VM,
             a if a == Self::DecommitmentsFilter as u8 => Self::DecommitmentsFilter,
             a if a == Self::Decommiter as u8 => Self::Decommiter,
             a if a == Self::LogDemultiplexer as u8 => Self::LogDemultiplexer,
-            a if a == Self::KeccakPrecompile as u8 => Self::KeccakPrecompile,
-            a if a == Self::Sha256Precompile as u8 => Self::Sha256Precompile,
-            a if a == Self::EcrecoverPrecompile as u8 => Self::EcrecoverPrecompile,
             a if a == Self::RamValidation as u8 => Self::RamValidation,
             a if a == Self::StorageFilter as u8 => Self::StorageFilter,
             a if a == Self::StorageApplicator as u8 => Self::StorageApplicator,
@@ -65,7 +104,6 @@ impl BaseLayerCircuitType {
             a if a == Self::L1MessagesRevertsFilter as u8 => Self::L1MessagesRevertsFilter,
             a if a == Self::L1MessagesHasher as u8 => Self::L1MessagesHasher,
             a if a == Self::TransientStorageChecker as u8 => Self::TransientStorageChecker,
-            a if a == Self::Secp256r1Verify as u8 => Self::Secp256r1Verify,
             a if a == Self::EIP4844Repack as u8 => Self::EIP4844Repack,
             _ => {
                 panic!("unknown circuit type {}", value);
@@ -76,7 +114,9 @@ impl BaseLayerCircuitType {
     }
 
     pub fn as_iter_u8() -> impl Iterator<Item = u8> {
-        (BaseLayerCircuitType::VM as u8..=BaseLayerCircuitType::Secp256r1Verify as u8)
+        crate::scheduler::SEQUENCE_OF_CIRCUIT_TYPES
+            .into_iter()
+            .map(|el| el as u8)
             .chain(once(BaseLayerCircuitType::EIP4844Repack as u8))
     }
 }
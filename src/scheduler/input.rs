@@ -6,6 +6,7 @@ use boojum::field::SmallField;
 use boojum::gadgets::{queue::*, traits::allocatable::*};
 
 use crate::base_structures::precompile_input_outputs::PrecompileFunctionOutputDataWitness;
+use crate::precompile_registry::NUM_REGISTERED_PRECOMPILES;
 
 use crate::base_structures::vm_state::*;
 use crate::code_unpacker_sha256::input::CodeDecommitterOutputDataWitness;
@@ -45,10 +46,9 @@ pub struct SchedulerCircuitInstanceWitness<
     pub decommits_sorter_observable_output: CodeDecommittmentsDeduplicatorOutputDataWitness<F>,
     pub code_decommitter_observable_output: CodeDecommitterOutputDataWitness<F>,
     pub log_demuxer_observable_output: LogDemuxerOutputDataWitness<F>,
-    pub keccak256_observable_output: PrecompileFunctionOutputDataWitness<F>,
-    pub sha256_observable_output: PrecompileFunctionOutputDataWitness<F>,
-    pub ecrecover_observable_output: PrecompileFunctionOutputDataWitness<F>,
-    pub secp256r1_verify_observable_output: PrecompileFunctionOutputDataWitness<F>,
+    // in the order of the precompile registry
+    pub precompile_observable_outputs:
+        [PrecompileFunctionOutputDataWitness<F>; NUM_REGISTERED_PRECOMPILES],
     // RAM permutation doesn't produce anything
     pub storage_sorter_observable_output: StorageDeduplicatorOutputDataWitness<F>,
     pub storage_application_observable_output: StorageApplicationOutputDataWitness<F>,
@@ -79,7 +79,8 @@ pub struct SchedulerCircuitInstanceWitness<
     // eip4844 witnesses
     pub eip4844_witnesses: [Option<EIP4844OutputDataWitness<F>>; MAX_4844_BLOBS_PER_BLOCK],
 
-    // proofs for every individual circuit type's aggregation subtree
+    // proofs of recursion tips, one per `NUM_RECURSION_TIPS_USED`, that aggregate
+    // every individual circuit type's aggregation subtree
     #[derivative(Debug = "ignore")]
     pub proof_witnesses: VecDeque<Proof<F, H::NonCircuitSimulator, EXT>>,
 }
@@ -98,10 +99,9 @@ impl<F: SmallField, H: RecursiveTreeHasher<F, Num<F>>, EXT: FieldExtension<2, Ba
             code_decommitter_observable_output: CodeDecommitterOutputData::placeholder_witness(),
             log_demuxer_observable_output: LogDemuxerOutputData::placeholder_witness(),
 
-            keccak256_observable_output: PrecompileFunctionOutputData::placeholder_witness(),
-            sha256_observable_output: PrecompileFunctionOutputData::placeholder_witness(),
-            ecrecover_observable_output: PrecompileFunctionOutputData::placeholder_witness(),
-            secp256r1_verify_observable_output: PrecompileFunctionOutputData::placeholder_witness(),
+            precompile_observable_outputs: std::array::from_fn(|_| {
+                PrecompileFunctionOutputData::placeholder_witness()
+            }),
 
             storage_sorter_observable_output: StorageDeduplicatorOutputData::placeholder_witness(),
             storage_application_observable_output:
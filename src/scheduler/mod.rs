@@ -34,10 +34,13 @@ use crate::demux_log_queue::DemuxOutput;
 use crate::fsm_input_output::circuit_inputs::INPUT_OUTPUT_COMMITMENT_LENGTH;
 use crate::linear_hasher::input::LinearHasherOutputData;
 use crate::main_vm::opcodes::normalize_bytecode_hash_for_decommit;
+use crate::precompile_registry::{NUM_REGISTERED_PRECOMPILES, PRECOMPILE_REGISTRY};
 use crate::recursion::recursion_tip::input::RecursionTipInput;
 use crate::recursion::recursion_tip::input::RECURSION_TIP_ARITY;
 use crate::recursion::VK_COMMITMENT_LENGTH;
-use crate::scheduler::auxiliary::NUM_CIRCUIT_TYPES_TO_SCHEDULE;
+use crate::scheduler::auxiliary::{
+    NON_PRECOMPILE_CIRCUIT_TYPES_TO_SCHEDULE, NUM_CIRCUIT_TYPES_TO_SCHEDULE,
+};
 use crate::utils::is_equal_queue_state;
 use boojum::gadgets::num::Num;
 use boojum::gadgets::recursion::recursive_tree_hasher::RecursiveTreeHasher;
@@ -77,25 +80,39 @@ pub const NUM_SCHEDULER_PUBLIC_INPUTS: usize = 4;
 pub const LEAF_LAYER_PARAMETERS_COMMITMENT_LENGTH: usize = 4;
 pub const QUEUE_FINAL_STATE_COMMITMENT_LENGTH: usize = 4;
 pub const NUM_CIRCUITS_FOR_VARIABLE_SCHEDULING: usize = NUM_CIRCUIT_TYPES_TO_SCHEDULE - 1;
-pub const NUM_RECURSION_TIPS_USED: usize = 1;
-
-pub const SEQUENCE_OF_CIRCUIT_TYPES: [BaseLayerCircuitType; NUM_CIRCUITS_FOR_VARIABLE_SCHEDULING] = [
-    BaseLayerCircuitType::VM,
-    BaseLayerCircuitType::DecommitmentsFilter,
-    BaseLayerCircuitType::Decommiter,
-    BaseLayerCircuitType::LogDemultiplexer,
-    BaseLayerCircuitType::KeccakPrecompile,
-    BaseLayerCircuitType::Sha256Precompile,
-    BaseLayerCircuitType::EcrecoverPrecompile,
-    BaseLayerCircuitType::RamValidation,
-    BaseLayerCircuitType::StorageFilter,
-    BaseLayerCircuitType::StorageApplicator,
-    BaseLayerCircuitType::EventsRevertsFilter,
-    BaseLayerCircuitType::L1MessagesRevertsFilter,
-    BaseLayerCircuitType::L1MessagesHasher,
-    BaseLayerCircuitType::TransientStorageChecker,
-    BaseLayerCircuitType::Secp256r1Verify,
-];
+/// Every recursion tip aggregates up to `RECURSION_TIP_ARITY` circuit types in the order of scheduling,
+/// and unused slots of the last one are left empty. Changing it changes the scheduler VK
+pub const NUM_RECURSION_TIPS_USED: usize = 2;
+
+/// Circuit types ordered by their numeric value, that is the order of scheduling
+pub const SEQUENCE_OF_CIRCUIT_TYPES: [BaseLayerCircuitType; NUM_CIRCUITS_FOR_VARIABLE_SCHEDULING] = {
+    let mut all = [None; NUM_CIRCUITS_FOR_VARIABLE_SCHEDULING];
+    let mut i = 0;
+    while i < NON_PRECOMPILE_CIRCUIT_TYPES_TO_SCHEDULE.len() {
+        let el = NON_PRECOMPILE_CIRCUIT_TYPES_TO_SCHEDULE[i];
+        all[el as usize - 1] = Some(el);
+        i += 1;
+    }
+    let mut i = 0;
+    while i < NUM_REGISTERED_PRECOMPILES {
+        let el = PRECOMPILE_REGISTRY[i].circuit_type;
+        assert!(all[el as usize - 1].is_none(), "circuit type is used twice");
+        all[el as usize - 1] = Some(el);
+        i += 1;
+    }
+
+    let mut result = [BaseLayerCircuitType::VM; NUM_CIRCUITS_FOR_VARIABLE_SCHEDULING];
+    let mut i = 0;
+    while i < NUM_CIRCUITS_FOR_VARIABLE_SCHEDULING {
+        match all[i] {
+            Some(el) => result[i] = el,
+            None => panic!("scheduled circuit types must not have gaps"),
+        }
+        i += 1;
+    }
+
+    result
+};
 
 #[derive(Derivative, serde::Serialize, serde::Deserialize)]
 #[derivative(Clone, Debug)]
@@ -198,19 +215,10 @@ pub fn scheduler_function<
     let log_demuxer_observable_output =
         LogDemuxerOutputData::allocate(cs, witness.log_demuxer_observable_output.clone());
 
-    let keccak256_observable_output =
-        PrecompileFunctionOutputData::allocate(cs, witness.keccak256_observable_output.clone());
-
-    let sha256_observable_output =
-        PrecompileFunctionOutputData::allocate(cs, witness.sha256_observable_output.clone());
-
-    let ecrecover_observable_output =
-        PrecompileFunctionOutputData::allocate(cs, witness.ecrecover_observable_output.clone());
-
-    let secp256r1_verify_observable_output = PrecompileFunctionOutputData::allocate(
-        cs,
-        witness.secp256r1_verify_observable_output.clone(),
-    );
+    let precompile_observable_outputs = witness
+        .precompile_observable_outputs
+        .clone()
+        .map(|el| PrecompileFunctionOutputData::allocate(cs, el));
 
     let storage_sorter_observable_output = StorageDeduplicatorOutputData::allocate(
         cs,
@@ -326,52 +334,29 @@ pub fn scheduler_function<
         commit_variable_length_encodable_item(cs, &log_demuxer_observable_output, round_function);
 
     // all intermediate queues for sorters
-    let keccak256_access_queue_state =
-        log_demuxer_observable_output.output_queue_states[DemuxOutput::Keccak as usize];
-    let sha256_access_queue_state =
-        log_demuxer_observable_output.output_queue_states[DemuxOutput::Sha256 as usize];
-    let ecrecover_access_queue_state =
-        log_demuxer_observable_output.output_queue_states[DemuxOutput::ECRecover as usize];
-    let secp256r1_verify_access_queue_state =
-        log_demuxer_observable_output.output_queue_states[DemuxOutput::Secp256r1Verify as usize];
-
-    // precompiles: keccak, sha256 and ecrecover
-    let (keccak_circuit_observable_input_commitment, keccak_circuit_observable_output_commitment) =
-        compute_precompile_commitment(
-            cs,
-            &keccak256_access_queue_state,
-            &code_decommitter_observable_output.memory_queue_final_state,
-            &keccak256_observable_output.final_memory_state,
-            round_function,
-        );
-    let (sha256_circuit_observable_input_commitment, sha256_circuit_observable_output_commitment) =
-        compute_precompile_commitment(
+    // precompiles are chained over the memory queue in the order of the registry
+    let mut precompile_input_commitments = Vec::with_capacity(NUM_REGISTERED_PRECOMPILES);
+    let mut precompile_output_commitments = Vec::with_capacity(NUM_REGISTERED_PRECOMPILES);
+    let mut memory_queue_state = code_decommitter_observable_output.memory_queue_final_state;
+    for (precompile, observable_output) in PRECOMPILE_REGISTRY
+        .iter()
+        .zip(precompile_observable_outputs.iter())
+    {
+        let access_queue_state =
+            log_demuxer_observable_output.output_queue_states[precompile.demux_output as usize];
+        let (input_commitment, output_commitment) = compute_precompile_commitment(
             cs,
-            &sha256_access_queue_state,
-            &keccak256_observable_output.final_memory_state,
-            &sha256_observable_output.final_memory_state,
+            &access_queue_state,
+            &memory_queue_state,
+            &observable_output.final_memory_state,
             round_function,
         );
-    let (
-        ecrecover_circuit_observable_input_commitment,
-        ecrecover_circuit_observable_output_commitment,
-    ) = compute_precompile_commitment(
-        cs,
-        &ecrecover_access_queue_state,
-        &sha256_observable_output.final_memory_state,
-        &ecrecover_observable_output.final_memory_state,
-        round_function,
-    );
-    let (
-        secp256r1_verify_circuit_observable_input_commitment,
-        secp256r1_verify_circuit_observable_output_commitment,
-    ) = compute_precompile_commitment(
-        cs,
-        &secp256r1_verify_access_queue_state,
-        &ecrecover_observable_output.final_memory_state,
-        &secp256r1_verify_observable_output.final_memory_state,
-        round_function,
-    );
+        precompile_input_commitments.push((precompile.circuit_type, input_commitment));
+        precompile_output_commitments.push((precompile.circuit_type, output_commitment));
+
+        memory_queue_state = observable_output.final_memory_state;
+    }
+    let precompiles_final_memory_state = memory_queue_state;
 
     // ram permutation and validation
     // NBL this circuit is terminal - it has no actual output
@@ -381,7 +366,7 @@ pub fn scheduler_function<
         QueueTailState::allocate(cs, witness.ram_sorted_queue_state.clone());
 
     let ram_validation_circuit_input = RamPermutationInputData {
-        unsorted_queue_initial_state: secp256r1_verify_observable_output.final_memory_state,
+        unsorted_queue_initial_state: precompiles_final_memory_state,
         sorted_queue_initial_state: ram_sorted_queue_state,
         non_deterministic_bootloader_memory_snapshot_length: bootloader_heap_memory_state.length,
     };
@@ -533,18 +518,6 @@ pub fn scheduler_function<
                     BaseLayerCircuitType::LogDemultiplexer,
                     log_demux_circuit_input_commitment,
                 ),
-                (
-                    BaseLayerCircuitType::KeccakPrecompile,
-                    keccak_circuit_observable_input_commitment,
-                ),
-                (
-                    BaseLayerCircuitType::Sha256Precompile,
-                    sha256_circuit_observable_input_commitment,
-                ),
-                (
-                    BaseLayerCircuitType::EcrecoverPrecompile,
-                    ecrecover_circuit_observable_input_commitment,
-                ),
                 (
                     BaseLayerCircuitType::RamValidation,
                     ram_validation_circuit_input_commitment,
@@ -573,12 +546,9 @@ pub fn scheduler_function<
                     BaseLayerCircuitType::TransientStorageChecker,
                     transient_storage_checker_input_com,
                 ),
-                (
-                    BaseLayerCircuitType::Secp256r1Verify,
-                    secp256r1_verify_circuit_observable_input_commitment,
-                ),
             ]
-            .into_iter(),
+            .into_iter()
+            .chain(precompile_input_commitments.into_iter()),
         );
 
     let output_commitments_as_map =
@@ -597,18 +567,6 @@ pub fn scheduler_function<
                     BaseLayerCircuitType::LogDemultiplexer,
                     log_demuxer_observable_output_commitment,
                 ),
-                (
-                    BaseLayerCircuitType::KeccakPrecompile,
-                    keccak_circuit_observable_output_commitment,
-                ),
-                (
-                    BaseLayerCircuitType::Sha256Precompile,
-                    sha256_circuit_observable_output_commitment,
-                ),
-                (
-                    BaseLayerCircuitType::EcrecoverPrecompile,
-                    ecrecover_circuit_observable_output_commitment,
-                ),
                 (
                     BaseLayerCircuitType::RamValidation,
                     [zero_num; CLOSED_FORM_COMMITTMENT_LENGTH], // formally set here
@@ -637,12 +595,9 @@ pub fn scheduler_function<
                     BaseLayerCircuitType::TransientStorageChecker,
                     transient_storage_checker_output_com,
                 ),
-                (
-                    BaseLayerCircuitType::Secp256r1Verify,
-                    secp256r1_verify_circuit_observable_output_commitment,
-                ),
             ]
-            .into_iter(),
+            .into_iter()
+            .chain(precompile_output_commitments.into_iter()),
         );
 
     assert_eq!(
@@ -719,51 +674,25 @@ pub fn scheduler_function<
         skip_flags[(BaseLayerCircuitType::LogDemultiplexer as u8 as usize) - 1] = Some(should_skip);
     }
 
-    // keccak, sha256 and ecrecover must not modify memory
-    {
-        let should_skip = keccak256_access_queue_state.tail.length.is_zero(cs);
-
-        let input_state = code_decommitter_observable_output.memory_queue_final_state;
-        let output_state = keccak256_observable_output.final_memory_state;
-
-        let same_state = is_equal_queue_state(cs, &input_state, &output_state);
-        same_state.conditionally_enforce_true(cs, should_skip);
-
-        skip_flags[(BaseLayerCircuitType::KeccakPrecompile as u8 as usize) - 1] = Some(should_skip);
-    }
-    {
-        let should_skip = sha256_access_queue_state.tail.length.is_zero(cs);
-
-        let input_state = keccak256_observable_output.final_memory_state;
-        let output_state = sha256_observable_output.final_memory_state;
-
-        let same_state = is_equal_queue_state(cs, &input_state, &output_state);
-        same_state.conditionally_enforce_true(cs, should_skip);
-
-        skip_flags[(BaseLayerCircuitType::Sha256Precompile as u8 as usize) - 1] = Some(should_skip);
-    }
+    // precompiles must not modify memory
+    let mut memory_queue_state = code_decommitter_observable_output.memory_queue_final_state;
+    for (precompile, observable_output) in PRECOMPILE_REGISTRY
+        .iter()
+        .zip(precompile_observable_outputs.iter())
     {
-        let should_skip = ecrecover_access_queue_state.tail.length.is_zero(cs);
+        let access_queue_state =
+            log_demuxer_observable_output.output_queue_states[precompile.demux_output as usize];
+        let should_skip = access_queue_state.tail.length.is_zero(cs);
 
-        let input_state = sha256_observable_output.final_memory_state;
-        let output_state = ecrecover_observable_output.final_memory_state;
+        let input_state = memory_queue_state;
+        let output_state = observable_output.final_memory_state;
 
         let same_state = is_equal_queue_state(cs, &input_state, &output_state);
         same_state.conditionally_enforce_true(cs, should_skip);
 
-        skip_flags[(BaseLayerCircuitType::EcrecoverPrecompile as u8 as usize) - 1] =
-            Some(should_skip);
-    }
-    {
-        let should_skip = secp256r1_verify_access_queue_state.tail.length.is_zero(cs);
-
-        let input_state = ecrecover_observable_output.final_memory_state;
-        let output_state = secp256r1_verify_observable_output.final_memory_state;
-
-        let same_state = is_equal_queue_state(cs, &input_state, &output_state);
-        same_state.conditionally_enforce_true(cs, should_skip);
+        skip_flags[(precompile.circuit_type as u8 as usize) - 1] = Some(should_skip);
 
-        skip_flags[(BaseLayerCircuitType::Secp256r1Verify as u8 as usize) - 1] = Some(should_skip);
+        memory_queue_state = output_state;
     }
 
     // well, in the very unlikely case of no RAM requests (that is unreachable because VM always starts) we just skip it as is
@@ -1132,6 +1061,8 @@ pub fn scheduler_function<
 
     // NOTE: values below are allocated constant, so their values end up in
     // scheduler setup -> verification key
+    // NOTE: for VM the basic circuit VK commitment is a commitment to VKs of all the VM variants,
+    // see `commit_vm_variants_vk_set`
     let leaf_layer_parameters = config
         .leaf_layer_parameters
         .clone()
@@ -1261,6 +1192,8 @@ pub fn scheduler_function<
                 dbg!(recursion_tip_input.witness_hook(cs)());
             }
 
+            // every recursion tip has its own proof, in the same order
+
             let expected_input_commitment: [_; INPUT_OUTPUT_COMMITMENT_LENGTH] =
                 commit_variable_length_encodable_item(cs, &recursion_tip_input, round_function);
 
@@ -1290,6 +1223,11 @@ pub fn scheduler_function<
                 Num::enforce_equal(cs, a, b);
             }
         }
+
+        assert!(
+            it.next().is_none(),
+            "all the circuit types must be covered by recursion tips"
+        );
     }
 
     // now we can collapse queues
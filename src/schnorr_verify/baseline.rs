@@ -0,0 +1,772 @@
+use super::*;
+
+use crate::base_structures::precompile_input_outputs::PrecompileFunctionOutputData;
+use crate::demux_log_queue::StorageLogQueue;
+use crate::ecdsa::{fixed_base_mul_by_generator, width_4_windowed_multiplication};
+use crate::ecrecover::baseline::convert_uint256_to_field_element;
+use crate::ecrecover::Secp256k1EcdsaCurve;
+use crate::ethereum_types::U256;
+use crate::fsm_input_output::circuit_inputs::INPUT_OUTPUT_COMMITMENT_LENGTH;
+
+use arrayvec::ArrayVec;
+use boojum::algebraic_props::round_function::AlgebraicRoundFunction;
+use boojum::cs::traits::cs::ConstraintSystem;
+use boojum::field::SmallField;
+use boojum::gadgets::boolean::Boolean;
+use boojum::gadgets::curves::sw_projective::SWProjectivePoint;
+use boojum::gadgets::sha256::round_function::round_function_over_uint32;
+
+use boojum::gadgets::num::Num;
+use boojum::gadgets::queue::CircuitQueueWitness;
+use boojum::gadgets::queue::QueueState;
+use boojum::gadgets::traits::allocatable::{CSAllocatableExt, CSPlaceholder};
+use boojum::gadgets::traits::round_function::CircuitRoundFunction;
+use boojum::gadgets::traits::selectable::Selectable;
+
+use boojum::gadgets::u160::UInt160;
+use boojum::gadgets::u256::UInt256;
+use boojum::gadgets::u32::UInt32;
+use boojum::gadgets::u8::UInt8;
+use boojum::pairing::GenericCurveAffine;
+
+use std::collections::VecDeque;
+use std::sync::{Arc, RwLock};
+use zkevm_opcode_defs::system_params::PRECOMPILE_AUX_BYTE;
+
+#[derive(Derivative, CSSelectable)]
+#[derivative(Clone, Debug)]
+pub struct SchnorrVerifyPrecompileCallParams<F: SmallField> {
+    pub input_page: UInt32<F>,
+    pub input_offset: UInt32<F>,
+    pub output_page: UInt32<F>,
+    pub output_offset: UInt32<F>,
+}
+
+impl<F: SmallField> SchnorrVerifyPrecompileCallParams<F> {
+    pub fn from_encoding<CS: ConstraintSystem<F>>(_cs: &mut CS, encoding: UInt256<F>) -> Self {
+        let input_offset = encoding.inner[0];
+        let output_offset = encoding.inner[2];
+        let input_page = encoding.inner[4];
+        let output_page = encoding.inner[5];
+
+        let new = Self {
+            input_page,
+            input_offset,
+            output_page,
+            output_offset,
+        };
+
+        new
+    }
+}
+
+const EXCEPTION_FLAGS_ARR_LEN: usize = 5;
+const X_POWERS_ARR_LEN: usize = 255;
+
+// SHA-256 state after compressing the first block of the tagged hash, that is
+// SHA256("BIP0340/challenge") || SHA256("BIP0340/challenge"), so we don't spend
+// a round function on the constant input
+const CHALLENGE_TAG_MIDSTATE: [u32; 8] = [
+    0x9cecba11, 0x23925381, 0x11679112, 0xd1627e0f, 0x97c87550, 0x003cc765, 0x90f61164, 0x33e9b66a,
+];
+// tag hashes, R.x, P.x and the message are 160 bytes in total
+const CHALLENGE_MESSAGE_BIT_LENGTH: u32 = 1280;
+
+fn u256_into_sha256_words<F: SmallField>(word: &UInt256<F>) -> [UInt32<F>; 8] {
+    std::array::from_fn(|i| word.inner[7 - i])
+}
+
+/// Computes e = int(hash_BIP0340/challenge(r || P.x || m)) mod n for the raw memory words,
+/// which are already big-endian as BIP-340 requires
+fn bip340_challenge<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    r: &UInt256<F>,
+    public_key: &UInt256<F>,
+    message: &UInt256<F>,
+    scalar_field_params: &Arc<Secp256ScalarNNFieldParams>,
+) -> Secp256ScalarNNField<F> {
+    let zero_u32 = UInt32::zero(cs);
+    let mut sha256_state = CHALLENGE_TAG_MIDSTATE.map(|el| UInt32::allocated_constant(cs, el));
+
+    let mut sha256_input = [zero_u32; 16];
+    sha256_input[..8].copy_from_slice(&u256_into_sha256_words(r));
+    sha256_input[8..].copy_from_slice(&u256_into_sha256_words(public_key));
+    let _ = round_function_over_uint32(cs, &mut sha256_state, &sha256_input);
+
+    let mut sha256_input = [zero_u32; 16];
+    sha256_input[..8].copy_from_slice(&u256_into_sha256_words(message));
+    sha256_input[8] = UInt32::allocated_constant(cs, 1 << 31);
+    sha256_input[15] = UInt32::allocated_constant(cs, CHALLENGE_MESSAGE_BIT_LENGTH);
+    let _ = round_function_over_uint32(cs, &mut sha256_state, &sha256_input);
+
+    let mut hash = UInt256::zero(cs);
+    for (dst, src) in hash.inner.iter_mut().zip(sha256_state.iter().rev()) {
+        *dst = *src;
+    }
+
+    let secp_n_u256 = U256([
+        scalar_field_params.modulus_u1024.as_ref().as_words()[0],
+        scalar_field_params.modulus_u1024.as_ref().as_words()[1],
+        scalar_field_params.modulus_u1024.as_ref().as_words()[2],
+        scalar_field_params.modulus_u1024.as_ref().as_words()[3],
+    ]);
+    let secp_n_u256 = UInt256::allocated_constant(cs, secp_n_u256);
+
+    // hash is less than 2n, so a single conditional subtraction is enough
+    let (hash_minus_n, hash_is_less) = hash.overflowing_sub(cs, &secp_n_u256);
+    let hash = UInt256::conditionally_select(cs, hash_is_less, &hash, &hash_minus_n);
+
+    let mut e = convert_uint256_to_field_element(cs, &hash, scalar_field_params);
+    e.normalize(cs);
+
+    e
+}
+
+fn schnorr_verify_function_inner<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    message: &UInt256<F>,
+    r: &UInt256<F>,
+    s: &UInt256<F>,
+    public_key: &UInt256<F>,
+    base_field_params: &Arc<Secp256BaseNNFieldParams>,
+    scalar_field_params: &Arc<Secp256ScalarNNFieldParams>,
+) -> (Boolean<F>, UInt256<F>) {
+    let curve_b = Secp256Affine::b_coeff();
+    let mut curve_b_nn =
+        Secp256BaseNNField::<F>::allocated_constant(cs, curve_b, &base_field_params);
+
+    let (gen_x, gen_y) = Secp256Affine::one().into_xy_unchecked();
+    let gen_x_nn = Secp256BaseNNField::allocated_constant(cs, gen_x, base_field_params);
+    let gen_y_nn = Secp256BaseNNField::allocated_constant(cs, gen_y, base_field_params);
+
+    let secp_n_u256 = U256([
+        scalar_field_params.modulus_u1024.as_ref().as_words()[0],
+        scalar_field_params.modulus_u1024.as_ref().as_words()[1],
+        scalar_field_params.modulus_u1024.as_ref().as_words()[2],
+        scalar_field_params.modulus_u1024.as_ref().as_words()[3],
+    ]);
+    let secp_n_u256 = UInt256::allocated_constant(cs, secp_n_u256);
+
+    let secp_p_u256 = U256([
+        base_field_params.modulus_u1024.as_ref().as_words()[0],
+        base_field_params.modulus_u1024.as_ref().as_words()[1],
+        base_field_params.modulus_u1024.as_ref().as_words()[2],
+        base_field_params.modulus_u1024.as_ref().as_words()[3],
+    ]);
+    let secp_p_u256 = UInt256::allocated_constant(cs, secp_p_u256);
+
+    let mut exception_flags = ArrayVec::<_, EXCEPTION_FLAGS_ARR_LEN>::new();
+
+    // we check that r < p, s < n and P.x < p as BIP-340 requires
+
+    let (_res, is_in_range) = r.overflowing_sub(cs, &secp_p_u256);
+    let r_as_u256 = r.mask(cs, is_in_range);
+    let r_is_not_in_range = is_in_range.negated(cs);
+    exception_flags.push(r_is_not_in_range);
+
+    let (_res, is_in_range) = s.overflowing_sub(cs, &secp_n_u256);
+    let s_as_u256 = s.mask(cs, is_in_range);
+    let s_is_not_in_range = is_in_range.negated(cs);
+    exception_flags.push(s_is_not_in_range);
+
+    let (_res, is_in_range) = public_key.overflowing_sub(cs, &secp_p_u256);
+    let x_as_u256 = public_key.mask(cs, is_in_range);
+    let x_is_not_in_range = is_in_range.negated(cs);
+    exception_flags.push(x_is_not_in_range);
+
+    let mut r_fe = convert_uint256_to_field_element(cs, &r_as_u256, base_field_params);
+    let mut s_fe = convert_uint256_to_field_element(cs, &s_as_u256, scalar_field_params);
+    s_fe.normalize(cs);
+    let mut x_fe = convert_uint256_to_field_element(cs, &x_as_u256, base_field_params);
+
+    // lift_x: we compute t = x^3 + b and take the candidate square root as in ecrecover,
+    // since p = 3 mod 4:
+    //           p = 2^256 - 2^32 - 2^9 - 2^8 - 2^7 - 2^6 - 2^4 - 1
+    // n = (p+1)/4 = 2^254 - 2^30 - 2^7 - 2^6 - 2^5 - 2^4 - 2^2
+    // t can't be zero, since there are no points of order 2 on the curve
+    let mut t = x_fe.square(cs);
+    t = t.mul(cs, &mut x_fe);
+    t = t.add(cs, &mut curve_b_nn);
+    t.normalize(cs);
+
+    // array of powers of t of the form t^{2^i} starting from i = 0 to 254
+    let mut t_powers = Vec::with_capacity(X_POWERS_ARR_LEN);
+    t_powers.push(t);
+
+    for _ in 1..X_POWERS_ARR_LEN {
+        let prev = t_powers.last_mut().unwrap();
+        let next = prev.square(cs);
+        t_powers.push(next);
+    }
+
+    let mut acc = t_powers[2].clone();
+    for idx in [4, 5, 6, 7, 30].into_iter() {
+        let other = &mut t_powers[idx];
+        acc = acc.mul(cs, other);
+    }
+    let mut may_be_y = t_powers[254].div_unchecked(cs, &mut acc);
+    may_be_y.normalize(cs);
+
+    let mut may_be_y_squared = may_be_y.square(cs);
+    let is_on_curve = Secp256BaseNNField::equals(cs, &mut may_be_y_squared, &mut t_powers[0]);
+    let is_not_on_curve = is_on_curve.negated(cs);
+    exception_flags.push(is_not_on_curve);
+
+    // public key is the point with even y
+    let [y_is_odd, ..] = Num::<F>::from_variable(may_be_y.limbs[0]).spread_into_bits::<_, 16>(cs);
+    let mut may_be_y_negated = may_be_y.negated(cs);
+    may_be_y_negated.normalize(cs);
+    let y = Selectable::conditionally_select(cs, y_is_odd, &may_be_y_negated, &may_be_y);
+
+    // we can mask point to ensure that our arithmetic formulas work
+    let x = Selectable::conditionally_select(cs, is_on_curve, &x_fe, &gen_x_nn);
+    let y = Selectable::conditionally_select(cs, is_on_curve, &y, &gen_y_nn);
+
+    let e = bip340_challenge(cs, r, public_key, message, scalar_field_params);
+    let mut e_negated = e.negated(cs);
+    e_negated.normalize(cs);
+
+    // now we compute R = s * G - e * P
+    let public_key_point =
+        SWProjectivePoint::<F, Secp256Affine, Secp256BaseNNField<F>>::from_xy_unchecked(cs, x, y);
+    let mut e_times_p = width_4_windowed_multiplication::<F, CS, Secp256k1EcdsaCurve, 17>(
+        cs,
+        public_key_point,
+        e_negated,
+        base_field_params,
+        scalar_field_params,
+    );
+
+    let mut s_times_g =
+        fixed_base_mul_by_generator::<F, CS, Secp256k1EcdsaCurve, 17>(cs, s_fe, &base_field_params);
+
+    let (mut s_times_g_affine, is_infinity) =
+        s_times_g.convert_to_affine_or_default(cs, Secp256Affine::one());
+    let sum = e_times_p.add_mixed(cs, &mut s_times_g_affine);
+    let mut sum = Selectable::conditionally_select(cs, is_infinity, &e_times_p, &sum);
+
+    let ((mut r_x, mut r_y), is_infinity) =
+        sum.convert_to_affine_or_default(cs, Secp256Affine::one());
+    exception_flags.push(is_infinity);
+
+    // signature is valid if R has even y and R.x = r
+    r_y.normalize(cs);
+    let [r_y_is_odd, ..] = Num::<F>::from_variable(r_y.limbs[0]).spread_into_bits::<_, 16>(cs);
+    let r_y_is_even = r_y_is_odd.negated(cs);
+    let r_x_is_equal = Secp256BaseNNField::equals(cs, &mut r_x, &mut r_fe);
+    let signature_equality = Boolean::multi_and(cs, &[r_x_is_equal, r_y_is_even]);
+
+    let any_exception = Boolean::multi_or(cs, &exception_flags[..]);
+    let written_value_bool = signature_equality.mask_negated(cs, any_exception);
+    let all_ok = any_exception.negated(cs);
+
+    let mut written_value = UInt256::zero(cs);
+    written_value.inner[0] =
+        unsafe { UInt32::from_variable_unchecked(written_value_bool.get_variable()) };
+
+    (all_ok, written_value)
+}
+
+pub fn schnorr_verify_function_entry_point<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    witness: SchnorrVerifyCircuitInstanceWitness<F>,
+    round_function: &R,
+    limit: usize,
+) -> [Num<F>; INPUT_OUTPUT_COMMITMENT_LENGTH]
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    assert!(limit <= u32::MAX as usize);
+
+    let SchnorrVerifyCircuitInstanceWitness {
+        closed_form_input,
+        requests_queue_witness,
+        memory_reads_witness,
+    } = witness;
+
+    let memory_reads_witness: VecDeque<_> = memory_reads_witness.into_iter().flatten().collect();
+
+    let precompile_address =
+        UInt160::allocated_constant(cs, SCHNORR_VERIFY_PRECOMPILE_FORMAL_ADDRESS);
+    let aux_byte_for_precompile = UInt8::allocated_constant(cs, PRECOMPILE_AUX_BYTE);
+
+    let scalar_params = Arc::new(secp256k1_scalar_field_params());
+    let base_params = Arc::new(secp256k1_base_field_params());
+
+    let mut structured_input =
+        SchnorrVerifyCircuitInputOutput::alloc_ignoring_outputs(cs, closed_form_input.clone());
+    let start_flag = structured_input.start_flag;
+
+    let requests_queue_state_from_input = structured_input.observable_input.initial_log_queue_state;
+
+    // it must be trivial
+    requests_queue_state_from_input.enforce_trivial_head(cs);
+
+    let requests_queue_state_from_fsm = structured_input.hidden_fsm_input.log_queue_state;
+
+    let requests_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &requests_queue_state_from_input,
+        &requests_queue_state_from_fsm,
+    );
+
+    let memory_queue_state_from_input =
+        structured_input.observable_input.initial_memory_queue_state;
+
+    // it must be trivial
+    memory_queue_state_from_input.enforce_trivial_head(cs);
+
+    let memory_queue_state_from_fsm = structured_input.hidden_fsm_input.memory_queue_state;
+
+    let memory_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &memory_queue_state_from_input,
+        &memory_queue_state_from_fsm,
+    );
+
+    let mut requests_queue = StorageLogQueue::<F, R>::from_state(cs, requests_queue_state);
+    let queue_witness = CircuitQueueWitness::from_inner_witness(requests_queue_witness);
+    requests_queue.witness = Arc::new(queue_witness);
+
+    let mut memory_queue = MemoryQueue::<F, R>::from_state(cs, memory_queue_state);
+
+    let one_u32 = UInt32::allocated_constant(cs, 1u32);
+    let zero_u256 = UInt256::zero(cs);
+    let boolean_false = Boolean::allocated_constant(cs, false);
+    let boolean_true = Boolean::allocated_constant(cs, true);
+
+    use crate::storage_application::ConditionalWitnessAllocator;
+    let read_queries_allocator = ConditionalWitnessAllocator::<F, UInt256<F>> {
+        witness_source: Arc::new(RwLock::new(memory_reads_witness)),
+    };
+
+    for _cycle in 0..limit {
+        let is_empty = requests_queue.is_empty(cs);
+        let should_process = is_empty.negated(cs);
+        let (request, _) = requests_queue.pop_front(cs, should_process);
+
+        let mut precompile_call_params =
+            SchnorrVerifyPrecompileCallParams::from_encoding(cs, request.key);
+
+        let timestamp_to_use_for_read = request.timestamp;
+        let timestamp_to_use_for_write = timestamp_to_use_for_read.add_no_overflow(cs, one_u32);
+
+        Num::conditionally_enforce_equal(
+            cs,
+            should_process,
+            &Num::from_variable(request.aux_byte.get_variable()),
+            &Num::from_variable(aux_byte_for_precompile.get_variable()),
+        );
+        for (a, b) in request
+            .address
+            .inner
+            .iter()
+            .zip(precompile_address.inner.iter())
+        {
+            Num::conditionally_enforce_equal(
+                cs,
+                should_process,
+                &Num::from_variable(a.get_variable()),
+                &Num::from_variable(b.get_variable()),
+            );
+        }
+
+        let mut read_values = [zero_u256; MEMORY_QUERIES_PER_CALL];
+        let mut bias_variable = should_process.get_variable();
+        for dst in read_values.iter_mut() {
+            let read_query_value: UInt256<F> = read_queries_allocator
+                .conditionally_allocate_biased(cs, should_process, bias_variable);
+            bias_variable = read_query_value.inner[0].get_variable();
+
+            *dst = read_query_value;
+
+            let read_query = MemoryQuery {
+                timestamp: timestamp_to_use_for_read,
+                memory_page: precompile_call_params.input_page,
+                index: precompile_call_params.input_offset,
+                rw_flag: boolean_false,
+                is_ptr: boolean_false,
+                value: read_query_value,
+            };
+
+            let _ = memory_queue.push(cs, read_query, should_process);
+
+            precompile_call_params.input_offset = precompile_call_params
+                .input_offset
+                .add_no_overflow(cs, one_u32);
+        }
+
+        let [message_as_u256, r_as_u256, s_as_u256, public_key_as_u256] = read_values;
+
+        let (success, written_value) = schnorr_verify_function_inner(
+            cs,
+            &message_as_u256,
+            &r_as_u256,
+            &s_as_u256,
+            &public_key_as_u256,
+            &base_params,
+            &scalar_params,
+        );
+
+        let success_as_u32 = unsafe { UInt32::from_variable_unchecked(success.get_variable()) };
+        let mut success_as_u256 = zero_u256;
+        success_as_u256.inner[0] = success_as_u32;
+
+        let success_query = MemoryQuery {
+            timestamp: timestamp_to_use_for_write,
+            memory_page: precompile_call_params.output_page,
+            index: precompile_call_params.output_offset,
+            rw_flag: boolean_true,
+            value: success_as_u256,
+            is_ptr: boolean_false,
+        };
+
+        precompile_call_params.output_offset = precompile_call_params
+            .output_offset
+            .add_no_overflow(cs, one_u32);
+
+        let _ = memory_queue.push(cs, success_query, should_process);
+
+        let value_query = MemoryQuery {
+            timestamp: timestamp_to_use_for_write,
+            memory_page: precompile_call_params.output_page,
+            index: precompile_call_params.output_offset,
+            rw_flag: boolean_true,
+            value: written_value,
+            is_ptr: boolean_false,
+        };
+
+        let _ = memory_queue.push(cs, value_query, should_process);
+    }
+
+    requests_queue.enforce_consistency(cs);
+
+    // form the final state
+    let done = requests_queue.is_empty(cs);
+    structured_input.completion_flag = done;
+    structured_input.observable_output = PrecompileFunctionOutputData::placeholder(cs);
+
+    let final_memory_state = memory_queue.into_state();
+    let final_requets_state = requests_queue.into_state();
+
+    structured_input.observable_output.final_memory_state = QueueState::conditionally_select(
+        cs,
+        structured_input.completion_flag,
+        &final_memory_state,
+        &structured_input.observable_output.final_memory_state,
+    );
+
+    structured_input.hidden_fsm_output.log_queue_state = final_requets_state;
+    structured_input.hidden_fsm_output.memory_queue_state = final_memory_state;
+
+    // self-check
+    structured_input.hook_compare_witness(cs, &closed_form_input);
+
+    use boojum::cs::gates::PublicInputGate;
+
+    let compact_form =
+        ClosedFormInputCompactForm::from_full_form(cs, &structured_input, round_function);
+    let input_commitment = commit_variable_length_encodable_item(cs, &compact_form, round_function);
+    for el in input_commitment.iter() {
+        let gate = PublicInputGate::new(el.get_variable());
+        gate.add_to_cs(cs);
+    }
+
+    input_commitment
+}
+
+#[cfg(test)]
+mod test {
+    use boojum::field::goldilocks::GoldilocksField;
+    use boojum::gadgets::traits::allocatable::CSAllocatable;
+    use boojum::worker::Worker;
+
+    use super::*;
+
+    type F = GoldilocksField;
+    type P = GoldilocksField;
+
+    use crate::ecrecover::secp256k1::fixed_base_mul_table::create_fixed_base_mul_table;
+    use boojum::config::DevCSConfig;
+
+    use boojum::cs::cs_builder::*;
+    use boojum::cs::cs_builder_reference::CsReferenceImplementationBuilder;
+    use boojum::cs::gates::*;
+    use boojum::cs::implementations::reference_cs::CSReferenceImplementation;
+    use boojum::cs::traits::gate::GatePlacementStrategy;
+    use boojum::cs::CSGeometry;
+    use boojum::cs::*;
+    use boojum::gadgets::tables::*;
+
+    // test vectors 0 and 1 from BIP-340
+    const PUBLIC_KEY_0: &'static str =
+        "f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9";
+    const MESSAGE_0: &'static str =
+        "0000000000000000000000000000000000000000000000000000000000000000";
+    const SIGNATURE_0: &'static str = "e907831f80848d1069a5371b402410364bdf1c5f8307b0084c55f1ce2dca821525f66a4a85ea8b71e482a74f382d2ce5ebeee8fdb2172f477df4900d310536c0";
+
+    const PUBLIC_KEY_1: &'static str =
+        "dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659";
+    const MESSAGE_1: &'static str =
+        "243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89";
+    const SIGNATURE_1: &'static str = "6896bd60eeae296db48a229ff71dfe071bde413e6d43f917dc8dcf8c78de33418906d11ac976abccb20b091292bff4ea897efcb639ea871cfa95f6de339e4b0a";
+
+    fn create_cs() -> CSReferenceImplementation<
+        F,
+        P,
+        DevCSConfig,
+        impl GateConfigurationHolder<F>,
+        impl StaticToolboxHolder,
+    > {
+        let geometry = CSGeometry {
+            num_columns_under_copy_permutation: 80,
+            num_witness_columns: 0,
+            num_constant_columns: 4,
+            max_allowed_constraint_degree: 8,
+        };
+
+        let max_variables = 1 << 26;
+        let max_trace_len = 1 << 21;
+
+        fn configure<
+            F: SmallField,
+            T: CsBuilderImpl<F, T>,
+            GC: GateConfigurationHolder<F>,
+            TB: StaticToolboxHolder,
+        >(
+            builder: CsBuilder<T, F, GC, TB>,
+        ) -> CsBuilder<T, F, impl GateConfigurationHolder<F>, impl StaticToolboxHolder> {
+            let builder = builder.allow_lookup(
+                LookupParameters::UseSpecializedColumnsWithTableIdAsConstant {
+                    width: 4,
+                    num_repetitions: 8,
+                    share_table_id: true,
+                },
+            );
+
+            let builder = ConstantsAllocatorGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = BooleanConstraintGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseSpecializedColumns {
+                    num_repetitions: 1,
+                    share_constants: false,
+                },
+            );
+            let builder = U8x4FMAGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ZeroCheckGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+                false,
+            );
+            let builder = FmaGateInBaseFieldWithoutConstant::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<32>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<16>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<8>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = DotProductGate::<4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = SelectionGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ParallelSelectionGate::<4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = PublicInputGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ReductionGate::<_, 4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = NopGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+
+            builder
+        }
+
+        let builder_impl =
+            CsReferenceImplementationBuilder::<F, P, DevCSConfig>::new(geometry, max_trace_len);
+        let builder = new_builder::<_, F>(builder_impl);
+
+        let builder = configure(builder);
+        let mut owned_cs = builder.build(max_variables);
+
+        // add tables
+        let table = create_xor8_table();
+        owned_cs.add_lookup_table::<Xor8Table, 3>(table);
+
+        let table = create_and8_table();
+        owned_cs.add_lookup_table::<And8Table, 3>(table);
+
+        seq_macro::seq!(C in 0..32 {
+            let table = create_fixed_base_mul_table::<F, 0, C>();
+            owned_cs.add_lookup_table::<FixedBaseMulTable<0, C>, 3>(table);
+            let table = create_fixed_base_mul_table::<F, 1, C>();
+            owned_cs.add_lookup_table::<FixedBaseMulTable<1, C>, 3>(table);
+            let table = create_fixed_base_mul_table::<F, 2, C>();
+            owned_cs.add_lookup_table::<FixedBaseMulTable<2, C>, 3>(table);
+            let table = create_fixed_base_mul_table::<F, 3, C>();
+            owned_cs.add_lookup_table::<FixedBaseMulTable<3, C>, 3>(table);
+            let table = create_fixed_base_mul_table::<F, 4, C>();
+            owned_cs.add_lookup_table::<FixedBaseMulTable<4, C>, 3>(table);
+            let table = create_fixed_base_mul_table::<F, 5, C>();
+            owned_cs.add_lookup_table::<FixedBaseMulTable<5, C>, 3>(table);
+            let table = create_fixed_base_mul_table::<F, 6, C>();
+            owned_cs.add_lookup_table::<FixedBaseMulTable<6, C>, 3>(table);
+            let table = create_fixed_base_mul_table::<F, 7, C>();
+            owned_cs.add_lookup_table::<FixedBaseMulTable<7, C>, 3>(table);
+        });
+
+        let table = create_byte_split_table::<F, 1>();
+        owned_cs.add_lookup_table::<ByteSplitTable<1>, 3>(table);
+        let table = create_byte_split_table::<F, 2>();
+        owned_cs.add_lookup_table::<ByteSplitTable<2>, 3>(table);
+        let table = create_byte_split_table::<F, 3>();
+        owned_cs.add_lookup_table::<ByteSplitTable<3>, 3>(table);
+        let table = create_byte_split_table::<F, 4>();
+        owned_cs.add_lookup_table::<ByteSplitTable<4>, 3>(table);
+
+        // tables for sha256 of the challenge
+        let table = create_maj4_table();
+        owned_cs.add_lookup_table::<Maj4Table, 4>(table);
+
+        let table = create_tri_xor_table();
+        owned_cs.add_lookup_table::<TriXor4Table, 4>(table);
+
+        let table = create_ch4_table();
+        owned_cs.add_lookup_table::<Ch4Table, 4>(table);
+
+        let table = create_4bit_chunk_split_table::<F, 1>();
+        owned_cs.add_lookup_table::<chunk4bits::Split4BitChunkTable<1>, 4>(table);
+        let table = create_4bit_chunk_split_table::<F, 2>();
+        owned_cs.add_lookup_table::<chunk4bits::Split4BitChunkTable<2>, 4>(table);
+
+        owned_cs
+    }
+
+    fn run_verification(message: &[u8], signature: &[u8], public_key: &[u8]) -> (bool, U256) {
+        let mut owned_cs = create_cs();
+        let cs = &mut owned_cs;
+
+        let scalar_params = Arc::new(secp256k1_scalar_field_params());
+        let base_params = Arc::new(secp256k1_base_field_params());
+
+        let message = UInt256::allocate(cs, U256::from_big_endian(message));
+        let r = UInt256::allocate(cs, U256::from_big_endian(&signature[..32]));
+        let s = UInt256::allocate(cs, U256::from_big_endian(&signature[32..]));
+        let public_key = UInt256::allocate(cs, U256::from_big_endian(public_key));
+
+        let (no_error, is_valid) = schnorr_verify_function_inner(
+            cs,
+            &message,
+            &r,
+            &s,
+            &public_key,
+            &base_params,
+            &scalar_params,
+        );
+
+        let no_error = no_error.witness_hook(&*cs)().unwrap();
+        let is_valid = is_valid.witness_hook(&*cs)().unwrap();
+
+        dbg!(cs.next_available_row());
+
+        cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        cs.print_gate_stats();
+        let worker = Worker::new();
+        assert!(cs.check_if_satisfied(&worker));
+
+        (no_error, is_valid)
+    }
+
+    #[test]
+    fn test_schnorr_verification() {
+        for (message, signature, public_key) in [
+            (MESSAGE_0, SIGNATURE_0, PUBLIC_KEY_0),
+            (MESSAGE_1, SIGNATURE_1, PUBLIC_KEY_1),
+        ] {
+            let message = hex::decode(message).unwrap();
+            let signature = hex::decode(signature).unwrap();
+            let public_key = hex::decode(public_key).unwrap();
+
+            let (no_error, is_valid) = run_verification(&message, &signature, &public_key);
+            assert!(no_error == true);
+            assert!(is_valid == U256::one());
+        }
+    }
+
+    #[test]
+    fn test_schnorr_verification_invalid_inputs() {
+        let message = hex::decode(MESSAGE_1).unwrap();
+        let signature = hex::decode(SIGNATURE_1).unwrap();
+        let public_key = hex::decode(PUBLIC_KEY_1).unwrap();
+
+        // different message is a well-formed input with invalid signature
+        let mut other_message = message.clone();
+        other_message[0] ^= 1;
+        let (no_error, is_valid) = run_verification(&other_message, &signature, &public_key);
+        assert!(no_error == true);
+        assert!(is_valid == U256::zero());
+
+        // test vector 6 from BIP-340, R has odd y
+        let odd_r_signature = hex::decode("fff97bd5755eeea420453a14355235d382f6472f8568a18b2f057a14602975563cc27944640ac607cd107ae10923d9ef7a73c643e166be5ebeafa34b1ac553e2").unwrap();
+        let (no_error, is_valid) = run_verification(&message, &odd_r_signature, &public_key);
+        assert!(no_error == true);
+        assert!(is_valid == U256::zero());
+
+        // test vector 5 from BIP-340, public key is not on curve
+        let invalid_public_key =
+            hex::decode("eefdea4cdb677750a420fee807eacf21eb9898ae79b9768766e4faa04a2d4a34")
+                .unwrap();
+        let (no_error, is_valid) = run_verification(&message, &signature, &invalid_public_key);
+        assert!(no_error == false);
+        assert!(is_valid == U256::zero());
+
+        // s = n is not in range
+        let n = U256::from_str_radix(
+            "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141",
+            16,
+        )
+        .unwrap();
+        let mut malleated_signature = signature.clone();
+        n.to_big_endian(&mut malleated_signature[32..]);
+        let (no_error, is_valid) = run_verification(&message, &malleated_signature, &public_key);
+        assert!(no_error == false);
+        assert!(is_valid == U256::zero());
+    }
+}
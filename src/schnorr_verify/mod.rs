@@ -0,0 +1,66 @@
+use super::*;
+use crate::base_structures::log_query::*;
+use crate::base_structures::memory_query::*;
+
+use crate::ethereum_types::U256;
+
+use crate::fsm_input_output::*;
+
+use boojum::cs::traits::cs::ConstraintSystem;
+use boojum::field::SmallField;
+use boojum::gadgets::boolean::Boolean;
+
+use boojum::gadgets::non_native_field::implementations::*;
+
+use boojum::gadgets::queue::QueueState;
+
+use boojum::gadgets::traits::selectable::Selectable;
+use boojum::gadgets::traits::witnessable::WitnessHookable;
+
+use cs_derive::*;
+use std::sync::Arc;
+
+use crate::base_structures::precompile_input_outputs::formal_precompile_address;
+use zkevm_opcode_defs::ethereum_types::H160;
+
+use crate::base_structures::precompile_input_outputs::*;
+use crate::demux_log_queue::DemuxOutput;
+use crate::precompile_registry::PrecompileCircuit;
+use crate::scheduler::auxiliary::BaseLayerCircuitType;
+
+pub mod input;
+pub use self::input::*;
+
+// message, r, s and x-only public key, all as 32 byte strings of BIP-340
+pub const MEMORY_QUERIES_PER_CALL: usize = 4;
+
+// placed right after the ed25519 signature verification precompile
+pub const SCHNORR_VERIFY_PRECOMPILE_ADDRESS: u16 = 0x102;
+pub const SCHNORR_VERIFY_PRECOMPILE_FORMAL_ADDRESS: H160 =
+    formal_precompile_address(SCHNORR_VERIFY_PRECOMPILE_ADDRESS);
+
+pub struct SchnorrVerifyPrecompileCircuit;
+
+impl PrecompileCircuit for SchnorrVerifyPrecompileCircuit {
+    const NAME: &'static str = "schnorr_verify";
+    const DEMUX_OUTPUT: DemuxOutput = DemuxOutput::SchnorrVerify;
+    const CIRCUIT_TYPE: BaseLayerCircuitType = BaseLayerCircuitType::SchnorrVerifyPrecompile;
+
+    type InputData<F: SmallField> = PrecompileFunctionInputData<F>;
+    type OutputData<F: SmallField> = PrecompileFunctionOutputData<F>;
+
+    fn formal_address() -> H160 {
+        SCHNORR_VERIFY_PRECOMPILE_FORMAL_ADDRESS
+    }
+}
+
+pub mod baseline;
+
+// curve is the same as for ecrecover, so we reuse its field types, parameters and fixed base tables
+use crate::ecrecover::{
+    secp256k1_base_field_params, secp256k1_scalar_field_params, Secp256Affine, Secp256BaseNNField,
+    Secp256BaseNNFieldParams, Secp256ScalarNNField, Secp256ScalarNNFieldParams,
+};
+
+// re-exports for integration
+pub use self::baseline::{schnorr_verify_function_entry_point, SchnorrVerifyPrecompileCallParams};
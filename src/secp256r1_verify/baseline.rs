@@ -5,12 +5,10 @@ use crate::demux_log_queue::StorageLogQueue;
 use crate::ethereum_types::U256;
 use crate::fsm_input_output::circuit_inputs::INPUT_OUTPUT_COMMITMENT_LENGTH;
 
-use arrayvec::ArrayVec;
 use boojum::algebraic_props::round_function::AlgebraicRoundFunction;
 use boojum::cs::traits::cs::ConstraintSystem;
 use boojum::field::SmallField;
 use boojum::gadgets::boolean::Boolean;
-use boojum::gadgets::curves::sw_projective::SWProjectivePoint;
 
 use boojum::gadgets::num::Num;
 use boojum::gadgets::queue::CircuitQueueWitness;
@@ -28,43 +26,9 @@ use std::collections::VecDeque;
 use std::sync::{Arc, RwLock};
 use zkevm_opcode_defs::system_params::PRECOMPILE_AUX_BYTE;
 
-use crate::ecrecover::baseline::convert_uint256_to_field_element;
-use crate::ecrecover::baseline::convert_uint256_to_field_element_masked;
-use crate::ecrecover::new_optimized::fixed_base_mul;
-
-const WINDOW_WIDTH: usize = 4;
-const NUM_MULTIPLICATION_STEPS_FOR_WIDTH_4: usize = 64;
-const PRECOMPUTATION_TABLE_SIZE: usize = (1 << WINDOW_WIDTH) - 1;
-
-#[derive(Derivative, CSSelectable)]
-#[derivative(Clone, Debug)]
-pub struct Secp256r1VerifyPrecompileCallParams<F: SmallField> {
-    pub input_page: UInt32<F>,
-    pub input_offset: UInt32<F>,
-    pub output_page: UInt32<F>,
-    pub output_offset: UInt32<F>,
-}
-
-impl<F: SmallField> Secp256r1VerifyPrecompileCallParams<F> {
-    pub fn from_encoding<CS: ConstraintSystem<F>>(_cs: &mut CS, encoding: UInt256<F>) -> Self {
-        let input_offset = encoding.inner[0];
-        let output_offset = encoding.inner[2];
-        let input_page = encoding.inner[4];
-        let output_page = encoding.inner[5];
-
-        let new = Self {
-            input_page,
-            input_offset,
-            output_page,
-            output_offset,
-        };
+use crate::ecdsa::{ecdsa_verify_function_inner, EcdsaPrecompileCallParams};
 
-        new
-    }
-}
-
-const NUM_WORDS: usize = 17;
-const EXCEPTION_FLAGS_ARR_LEN: usize = 8;
+pub type Secp256r1VerifyPrecompileCallParams<F> = EcdsaPrecompileCallParams<F>;
 
 fn secp256r1_verify_function_inner<F: SmallField, CS: ConstraintSystem<F>>(
     cs: &mut CS,
@@ -73,188 +37,21 @@ fn secp256r1_verify_function_inner<F: SmallField, CS: ConstraintSystem<F>>(
     message_hash: &UInt256<F>,
     x: &UInt256<F>,
     y: &UInt256<F>,
+    enforce_low_s: Boolean<F>,
     base_field_params: &Arc<Secp256BaseNNFieldParams>,
     scalar_field_params: &Arc<Secp256ScalarNNFieldParams>,
 ) -> (Boolean<F>, UInt256<F>) {
-    use boojum::pairing::GenericCurveAffine;
-    let curve_a = Secp256Affine::a_coeff();
-    let curve_b = Secp256Affine::b_coeff();
-
-    let mut curve_a_nn =
-        Secp256BaseNNField::<F>::allocated_constant(cs, curve_a, &base_field_params);
-    let mut curve_b_nn =
-        Secp256BaseNNField::<F>::allocated_constant(cs, curve_b, &base_field_params);
-
-    let generator = Secp256Affine::one();
-    let (gen_x, gen_y) = generator.into_xy_unchecked();
-    let gen_x_nn = Secp256BaseNNField::allocated_constant(cs, gen_x, base_field_params);
-    let gen_y_nn = Secp256BaseNNField::allocated_constant(cs, gen_y, base_field_params);
-
-    let secp_n_u256 = U256([
-        scalar_field_params.modulus_u1024.as_ref().as_words()[0],
-        scalar_field_params.modulus_u1024.as_ref().as_words()[1],
-        scalar_field_params.modulus_u1024.as_ref().as_words()[2],
-        scalar_field_params.modulus_u1024.as_ref().as_words()[3],
-    ]);
-    let secp_n_u256 = UInt256::allocated_constant(cs, secp_n_u256);
-
-    let secp_p_u256 = U256([
-        base_field_params.modulus_u1024.as_ref().as_words()[0],
-        base_field_params.modulus_u1024.as_ref().as_words()[1],
-        base_field_params.modulus_u1024.as_ref().as_words()[2],
-        base_field_params.modulus_u1024.as_ref().as_words()[3],
-    ]);
-    let secp_p_u256 = UInt256::allocated_constant(cs, secp_p_u256);
-
-    let mut exception_flags = ArrayVec::<_, EXCEPTION_FLAGS_ARR_LEN>::new();
-
-    // we use non-compressed point, so we:
-    // - check that public key is on curve (no special handling of zeroes)
-    // - check verification equation
-
-    // we check ranges upfront. We only need to check <modulus, and conversion functions will perform masking internally for values that are >= 1
-
-    let mut r_as_u256 = *r;
-    let mut s_as_u256 = *s;
-    let mut x_as_u256 = *x;
-    let mut y_as_u256 = *y;
-
-    let (_res, is_in_range) = r_as_u256.overflowing_sub(cs, &secp_n_u256);
-    r_as_u256 = r_as_u256.mask(cs, is_in_range);
-    let r_is_not_in_range = is_in_range.negated(cs);
-    exception_flags.push(r_is_not_in_range);
-
-    let (_res, is_in_range) = s_as_u256.overflowing_sub(cs, &secp_n_u256);
-    s_as_u256 = s_as_u256.mask(cs, is_in_range);
-    let s_is_not_in_range = is_in_range.negated(cs);
-    exception_flags.push(s_is_not_in_range);
-
-    let (_res, is_in_range) = x_as_u256.overflowing_sub(cs, &secp_p_u256);
-    x_as_u256 = x_as_u256.mask(cs, is_in_range);
-    let x_is_not_in_range = is_in_range.negated(cs);
-    exception_flags.push(x_is_not_in_range);
-
-    let (_res, is_in_range) = y_as_u256.overflowing_sub(cs, &secp_p_u256);
-    y_as_u256 = y_as_u256.mask(cs, is_in_range);
-    let y_is_not_in_range = is_in_range.negated(cs);
-    exception_flags.push(y_is_not_in_range);
-
-    let mut x_fe = convert_uint256_to_field_element(cs, &x_as_u256, &base_field_params);
-    let mut y_fe = convert_uint256_to_field_element(cs, &y_as_u256, &base_field_params);
-
-    let (mut r_fe, r_is_zero) =
-        convert_uint256_to_field_element_masked(cs, &r_as_u256, &scalar_field_params);
-    exception_flags.push(r_is_zero);
-    let (mut s_fe, s_is_zero) =
-        convert_uint256_to_field_element_masked(cs, &s_as_u256, &scalar_field_params);
-    exception_flags.push(s_is_zero);
-
-    let mut message_hash_fe =
-        convert_uint256_to_field_element(cs, &message_hash, &scalar_field_params);
-
-    // perform on-curve check
-    let mut lhs = y_fe.clone();
-    let mut lhs = lhs.mul(cs, &mut y_fe);
-    lhs.normalize(cs);
-
-    let mut rhs = x_fe.clone();
-    let mut rhs = rhs.mul(cs, &mut x_fe);
-    let mut rhs = rhs.add(cs, &mut curve_a_nn);
-    let mut rhs = rhs.mul(cs, &mut x_fe);
-    let mut rhs = rhs.add(cs, &mut curve_b_nn);
-    rhs.normalize(cs);
-
-    let is_on_curve = NonNativeFieldOverU16::equals(cs, &mut lhs, &mut rhs);
-    let not_on_curve = is_on_curve.negated(cs);
-    exception_flags.push(not_on_curve);
-
-    // we can mask point to ensure that our arithmetic formulas work
-    let x_fe: NonNativeFieldOverU16<F, Secp256Fq, 17> =
-        NonNativeFieldOverU16::conditionally_select(cs, is_on_curve, &x_fe, &gen_x_nn);
-    let y_fe = NonNativeFieldOverU16::conditionally_select(cs, is_on_curve, &y_fe, &gen_y_nn);
-
-    // this always exists (0 was an exception and was masked)
-    let mut s_fe_inversed = s_fe.inverse_unchecked(cs);
-    let mut r_by_s_inv = r_fe.mul(cs, &mut s_fe_inversed);
-    let mut message_hash_by_s_inv = message_hash_fe.mul(cs, &mut s_fe_inversed);
-
-    r_by_s_inv.normalize(cs);
-    message_hash_by_s_inv.normalize(cs);
-
-    // now we do multiplication
-    // it's safe since we checked not-on-curve above
-    let point = SWProjectivePoint::<F, Secp256Affine, Secp256BaseNNField<F>>::from_xy_unchecked(
-        cs, x_fe, y_fe,
-    );
-    let mut r_by_s_inv_mul_by_pubkey =
-        width_4_windowed_multiplication(cs, point, r_by_s_inv.clone(), &base_field_params);
-
-    let mut full_table_ids = vec![];
-    seq_macro::seq!(C in 0..32 {
-        let ids = [
-            cs.get_table_id_for_marker::<Secp256r1FixedBaseMulTable<0, C>>()
-                .expect("table must exist"),
-            cs.get_table_id_for_marker::<Secp256r1FixedBaseMulTable<1, C>>()
-                .expect("table must exist"),
-            cs.get_table_id_for_marker::<Secp256r1FixedBaseMulTable<2, C>>()
-                .expect("table must exist"),
-            cs.get_table_id_for_marker::<Secp256r1FixedBaseMulTable<3, C>>()
-                .expect("table must exist"),
-            cs.get_table_id_for_marker::<Secp256r1FixedBaseMulTable<4, C>>()
-                .expect("table must exist"),
-            cs.get_table_id_for_marker::<Secp256r1FixedBaseMulTable<5, C>>()
-                .expect("table must exist"),
-            cs.get_table_id_for_marker::<Secp256r1FixedBaseMulTable<6, C>>()
-                .expect("table must exist"),
-            cs.get_table_id_for_marker::<Secp256r1FixedBaseMulTable<7, C>>()
-                .expect("table must exist"),
-        ];
-        full_table_ids.push(ids);
-    });
-
-    let mut hash_times_g = fixed_base_mul::<F, CS, Secp256Fr, Secp256Fq, Secp256Affine, 17>(
+    ecdsa_verify_function_inner::<F, CS, Secp256r1EcdsaCurve, 17>(
         cs,
-        message_hash_by_s_inv,
-        &base_field_params,
-        SCALAR_FIELD_CANONICAL_REPR_LIMBS,
-        BASE_FIELD_CANONICAL_REPR_LIMBS,
-        &full_table_ids,
-    );
-
-    let (mut q_acc, is_infinity) =
-        hash_times_g.convert_to_affine_or_default(cs, Secp256Affine::one());
-    let q_acc_added = r_by_s_inv_mul_by_pubkey.add_mixed(cs, &mut q_acc);
-    let mut q_acc =
-        Selectable::conditionally_select(cs, is_infinity, &r_by_s_inv_mul_by_pubkey, &q_acc_added);
-
-    let ((mut q_x, _q_y), is_infinity) =
-        q_acc.convert_to_affine_or_default(cs, Secp256Affine::one());
-    exception_flags.push(is_infinity);
-    let any_exception = Boolean::multi_or(cs, &exception_flags[..]);
-
-    q_x.normalize(cs);
-
-    // now compare mod n. For that we go out of limbs and back
-    let limbs = q_x.limbs;
-    let mut q_x_mod_n = NonNativeFieldOverU16 {
-        limbs: limbs,
-        non_zero_limbs: 16,
-        tracker: OverflowTracker { max_moduluses: 2 }, // |Fr|*2 < |Fq|
-        form: RepresentationForm::Normalized,
-        params: scalar_field_params.clone(),
-        _marker: std::marker::PhantomData,
-    };
-    q_x_mod_n.normalize(cs);
-
-    let signature_equality = NonNativeFieldOverU16::equals(cs, &mut q_x_mod_n, &mut r_fe);
-    let written_value_bool = signature_equality.mask_negated(cs, any_exception);
-    let all_ok = any_exception.negated(cs);
-
-    let mut written_value = UInt256::zero(cs);
-    written_value.inner[0] =
-        unsafe { UInt32::from_variable_unchecked(written_value_bool.get_variable()) };
-
-    (all_ok, written_value)
+        r,
+        s,
+        message_hash,
+        x,
+        y,
+        enforce_low_s,
+        base_field_params,
+        scalar_field_params,
+    )
 }
 
 pub fn secp256r1_verify_function_entry_point<
@@ -406,6 +203,7 @@ where
             &message_hash_as_u256,
             &x_as_u256,
             &y_as_u256,
+            precompile_call_params.enforce_low_s,
             &base_params,
             &scalar_params,
         );
@@ -477,105 +275,6 @@ where
     input_commitment
 }
 
-fn width_4_windowed_multiplication<F: SmallField, CS: ConstraintSystem<F>>(
-    cs: &mut CS,
-    mut point: SWProjectivePoint<F, Secp256Affine, Secp256BaseNNField<F>>,
-    mut scalar: Secp256ScalarNNField<F>,
-    base_field_params: &Arc<Secp256BaseNNFieldParams>,
-) -> SWProjectivePoint<F, Secp256Affine, Secp256BaseNNField<F>> {
-    scalar.enforce_reduced(cs);
-
-    use boojum::pairing::GenericCurveAffine;
-
-    // create precomputed table of size 1<<4 - 1
-    // there is no 0 * P in the table, we will handle it below
-    let mut table = Vec::with_capacity(PRECOMPUTATION_TABLE_SIZE);
-    let mut tmp = point.clone();
-    let (mut p_affine, _) = point.convert_to_affine_or_default(cs, Secp256Affine::one());
-    table.push(p_affine.clone());
-    for _ in 1..PRECOMPUTATION_TABLE_SIZE {
-        // 2P, 3P, ...
-        tmp = tmp.add_mixed(cs, &mut p_affine);
-        let (affine, _) = tmp.convert_to_affine_or_default(cs, Secp256Affine::one());
-        table.push(affine);
-    }
-    assert_eq!(table.len(), PRECOMPUTATION_TABLE_SIZE);
-
-    // now decompose every scalar we are interested in
-    let msb_decomposition = to_width_4_window_form(cs, scalar);
-
-    let mut comparison_constants = Vec::with_capacity(PRECOMPUTATION_TABLE_SIZE);
-    for i in 1..=PRECOMPUTATION_TABLE_SIZE {
-        let constant = Num::allocated_constant(cs, F::from_u64_unchecked(i as u64));
-        comparison_constants.push(constant);
-    }
-
-    // now we just do double and add
-    let mut acc = SWProjectivePoint::zero(cs, base_field_params);
-    assert_eq!(
-        msb_decomposition.len(),
-        NUM_MULTIPLICATION_STEPS_FOR_WIDTH_4
-    );
-
-    for (idx, window_idx) in msb_decomposition.into_iter().enumerate() {
-        let ignore_part = window_idx.is_zero(cs);
-
-        let (mut selected_part_x, mut selected_part_y) = table[0].clone();
-        for i in 1..PRECOMPUTATION_TABLE_SIZE {
-            let should_select = Num::equals(cs, &comparison_constants[i], &window_idx);
-            selected_part_x =
-                Selectable::conditionally_select(cs, should_select, &table[i].0, &selected_part_x);
-            selected_part_y =
-                Selectable::conditionally_select(cs, should_select, &table[i].1, &selected_part_y);
-        }
-
-        let tmp_acc = acc.add_mixed(cs, &mut (selected_part_x, selected_part_y));
-        acc = Selectable::conditionally_select(cs, ignore_part, &acc, &tmp_acc);
-
-        if idx != NUM_MULTIPLICATION_STEPS_FOR_WIDTH_4 - 1 {
-            for _ in 0..WINDOW_WIDTH {
-                acc = acc.double(cs);
-            }
-        }
-    }
-
-    acc
-}
-
-fn to_width_4_window_form<F: SmallField, CS: ConstraintSystem<F>>(
-    cs: &mut CS,
-    mut limited_width_scalar: Secp256ScalarNNField<F>,
-) -> Vec<Num<F>> {
-    limited_width_scalar.enforce_reduced(cs);
-    // we know that width is 128 bits, so just do BE decomposition and put into resulting array
-    let zero_num = Num::zero(cs);
-    for word in limited_width_scalar.limbs[16..].iter() {
-        let word = Num::from_variable(*word);
-        Num::enforce_equal(cs, &word, &zero_num);
-    }
-
-    use boojum::gadgets::tables::ByteSplitTable;
-    use boojum::gadgets::u16::UInt16;
-    let byte_split_id = cs
-        .get_table_id_for_marker::<ByteSplitTable<4>>()
-        .expect("table should exist");
-    let mut result = Vec::with_capacity(NUM_MULTIPLICATION_STEPS_FOR_WIDTH_4);
-    for word in limited_width_scalar.limbs[..16].iter().rev() {
-        let word = unsafe { UInt16::from_variable_unchecked(*word) };
-        let [high, low] = word.to_be_bytes(cs);
-        for t in [high, low].into_iter() {
-            let [l, h] = cs.perform_lookup::<1, 2>(byte_split_id, &[t.get_variable()]);
-            let h = Num::from_variable(h);
-            let l = Num::from_variable(l);
-            result.push(h);
-            result.push(l);
-        }
-    }
-    assert_eq!(result.len(), NUM_MULTIPLICATION_STEPS_FOR_WIDTH_4);
-
-    result
-}
-
 #[cfg(test)]
 mod test {
     use boojum::field::goldilocks::GoldilocksField;
@@ -754,6 +453,9 @@ mod test {
         let scalar_params = Arc::new(scalar_params);
         let base_params = Arc::new(base_params);
 
+        let boolean_false = Boolean::allocated_constant(cs, false);
+        let boolean_true = Boolean::allocated_constant(cs, true);
+
         let (no_error, is_valid) = secp256r1_verify_function_inner(
             cs,
             &r,
@@ -761,6 +463,46 @@ mod test {
             &digest,
             &pk_x,
             &pk_y,
+            boolean_false,
+            &base_params,
+            &scalar_params,
+        );
+
+        assert!(no_error.witness_hook(&*cs)().unwrap() == true);
+        assert!(is_valid.witness_hook(&*cs)().unwrap() == U256::one());
+
+        // s of this vector is high, so it's rejected in the malleability-strict mode
+        let (no_error, is_valid) = secp256r1_verify_function_inner(
+            cs,
+            &r,
+            &s,
+            &digest,
+            &pk_x,
+            &pk_y,
+            boolean_true,
+            &base_params,
+            &scalar_params,
+        );
+
+        assert!(no_error.witness_hook(&*cs)().unwrap() == false);
+        assert!(is_valid.witness_hook(&*cs)().unwrap() == U256::zero());
+
+        // while the normalized signature (r, n - s) passes
+        let n = U256::from_str_radix(
+            "ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551",
+            16,
+        )
+        .unwrap();
+        let low_s = UInt256::allocate(cs, n - s_u256);
+
+        let (no_error, is_valid) = secp256r1_verify_function_inner(
+            cs,
+            &r,
+            &low_s,
+            &digest,
+            &pk_x,
+            &pk_y,
+            boolean_true,
             &base_params,
             &scalar_params,
         );
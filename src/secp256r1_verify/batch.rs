@@ -0,0 +1,1099 @@
+use super::*;
+
+use crate::base_structures::precompile_input_outputs::PrecompileFunctionOutputData;
+use crate::demux_log_queue::StorageLogQueue;
+use crate::ecdsa::{
+    fixed_base_mul_by_generator, high_s_exception, modulus_as_uint256, range_check_and_mask,
+    to_width_4_window_form, width_4_windowed_multi_scalar_multiplication,
+    EcdsaPrecompileCallParams,
+};
+use crate::ecrecover::baseline::convert_uint256_to_field_element;
+use crate::ecrecover::baseline::convert_uint256_to_field_element_masked;
+use crate::fsm_input_output::circuit_inputs::INPUT_OUTPUT_COMMITMENT_LENGTH;
+
+use arrayvec::ArrayVec;
+use boojum::algebraic_props::round_function::AlgebraicRoundFunction;
+use boojum::cs::traits::cs::ConstraintSystem;
+use boojum::field::SmallField;
+use boojum::gadgets::boolean::Boolean;
+use boojum::gadgets::curves::sw_projective::SWProjectivePoint;
+
+use boojum::gadgets::num::Num;
+use boojum::gadgets::queue::CircuitQueueWitness;
+use boojum::gadgets::queue::QueueState;
+use boojum::gadgets::traits::allocatable::{CSAllocatableExt, CSPlaceholder};
+use boojum::gadgets::traits::round_function::CircuitRoundFunction;
+use boojum::gadgets::traits::selectable::Selectable;
+
+use boojum::gadgets::u160::UInt160;
+use boojum::gadgets::u256::UInt256;
+use boojum::gadgets::u32::UInt32;
+use boojum::gadgets::u8::UInt8;
+use boojum::pairing::ff::Field;
+use boojum::pairing::GenericCurveAffine;
+
+use std::collections::VecDeque;
+use std::sync::{Arc, RwLock};
+use zkevm_opcode_defs::system_params::PRECOMPILE_AUX_BYTE;
+
+type Secp256BaseNNField<F> = NonNativeFieldOverU16<F, Secp256Fq, 17>;
+type Secp256ScalarNNField<F> = NonNativeFieldOverU16<F, Secp256Fr, 17>;
+
+const BATCH_EXCEPTION_FLAGS_ARR_LEN: usize = 9;
+// random coefficients of the linear combination are 128 bits long
+const CHALLENGE_BYTES: usize = 16;
+const CHALLENGE_WINDOWS: usize = CHALLENGE_BYTES * 2;
+
+#[derive(Derivative, CSSelectable)]
+#[derivative(Clone, Debug)]
+pub struct Secp256r1BatchVerifyPrecompileCallParams<F: SmallField> {
+    pub input_page: UInt32<F>,
+    pub input_offset: UInt32<F>,
+    pub output_page: UInt32<F>,
+    pub output_offset: UInt32<F>,
+    pub enforce_low_s: Boolean<F>,
+    pub num_signatures: UInt32<F>,
+}
+
+impl<F: SmallField> Secp256r1BatchVerifyPrecompileCallParams<F> {
+    pub fn from_encoding<CS: ConstraintSystem<F>>(cs: &mut CS, encoding: UInt256<F>) -> Self {
+        let EcdsaPrecompileCallParams {
+            input_page,
+            input_offset,
+            output_page,
+            output_offset,
+            enforce_low_s,
+        } = EcdsaPrecompileCallParams::from_encoding(cs, encoding);
+        let num_signatures = encoding.inner[3];
+
+        let new = Self {
+            input_page,
+            input_offset,
+            output_page,
+            output_offset,
+            enforce_low_s,
+            num_signatures,
+        };
+
+        new
+    }
+}
+
+/// Derives the coefficients of the random linear combination from all the inputs of the call
+/// and the claimed points, so the prover can't choose the points after the coefficients
+fn batch_challenges<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    fs_input: &[Num<F>],
+) -> [UInt256<F>; BATCH_VERIFY_MAX_SIGNATURES] {
+    let mut state = R::create_empty_state(cs);
+    let length = UInt32::allocated_constant(cs, fs_input.len() as u32);
+    R::apply_length_specialization(cs, &mut state, length.get_variable());
+
+    let zero_num = Num::allocated_constant(cs, F::ZERO);
+
+    let mut state = state.map(|el| Num::from_variable(el));
+
+    let mut it = fs_input.array_chunks::<8>();
+    for chunk in &mut it {
+        let mut state_to_keep = [zero_num; 4];
+        state_to_keep.copy_from_slice(&state[8..]);
+        state = R::absorb_with_replacement_over_nums(cs, *chunk, state_to_keep);
+        state = R::compute_round_function_over_nums(cs, state);
+    }
+
+    let remainder = it.remainder();
+    if remainder.len() != 0 {
+        let mut state_to_keep = [zero_num; 4];
+        state_to_keep.copy_from_slice(&state[8..]);
+        let mut padded_chunk = [zero_num; 8];
+        padded_chunk[..remainder.len()].copy_from_slice(remainder);
+        state = R::absorb_with_replacement_over_nums(cs, padded_chunk, state_to_keep);
+        state = R::compute_round_function_over_nums(cs, state);
+    }
+
+    // every coefficient takes two field elements, and all of them fit into the rate
+    const NUMS_PER_CHALLENGE: usize = CHALLENGE_BYTES / 8;
+    assert!(BATCH_VERIFY_MAX_SIGNATURES * NUMS_PER_CHALLENGE <= 8);
+
+    let zero_u8 = UInt8::zero(cs);
+    let zero_u256 = UInt256::zero(cs);
+    let mut result = [zero_u256; BATCH_VERIFY_MAX_SIGNATURES];
+    for (dst, src) in result
+        .iter_mut()
+        .zip(state[..8].array_chunks::<NUMS_PER_CHALLENGE>())
+    {
+        let mut le_bytes = [zero_u8; 32];
+        for (dst, src) in le_bytes[..CHALLENGE_BYTES]
+            .array_chunks_mut::<8>()
+            .zip(src.iter())
+        {
+            let bytes = src.constraint_bit_length_as_bytes(cs, 64);
+            dst.copy_from_slice(&bytes[..]);
+        }
+        *dst = UInt256::from_le_bytes(cs, le_bytes);
+    }
+
+    result
+}
+
+/// Verifies up to `BATCH_VERIFY_MAX_SIGNATURES` signatures (hash, r, s, x, y) at once and returns
+/// the verification result of every one of them, that is false for inactive and malformed ones.
+///
+/// The prover claims the point P_i = u1_i * G + u2_i * Q_i for every signature, so the result is
+/// just the comparison of x(P_i) mod n with r_i. All the claims are checked at once as
+/// sum(c_i * u2_i * Q_i) + sum(c_i * u1_i) * G - sum(c_i * P_i) == O for random c_i, where all
+/// the variable base terms share the doublings, and there is only one fixed base multiplication
+fn secp256r1_batch_verify_function_inner<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    signatures: &[[UInt256<F>; MEMORY_QUERIES_PER_CALL]; BATCH_VERIFY_MAX_SIGNATURES],
+    claimed_points: &[[UInt256<F>; 2]; BATCH_VERIFY_MAX_SIGNATURES],
+    is_active: &[Boolean<F>; BATCH_VERIFY_MAX_SIGNATURES],
+    enforce_low_s: Boolean<F>,
+    should_enforce: Boolean<F>,
+    base_field_params: &Arc<Secp256BaseNNFieldParams>,
+    scalar_field_params: &Arc<Secp256ScalarNNFieldParams>,
+) -> [Boolean<F>; BATCH_VERIFY_MAX_SIGNATURES] {
+    let curve_a = Secp256Affine::a_coeff();
+    let curve_b = Secp256Affine::b_coeff();
+
+    let mut curve_a_nn =
+        Secp256BaseNNField::<F>::allocated_constant(cs, curve_a, &base_field_params);
+    let mut curve_b_nn =
+        Secp256BaseNNField::<F>::allocated_constant(cs, curve_b, &base_field_params);
+
+    let generator = Secp256Affine::one();
+    let (gen_x, gen_y) = generator.into_xy_unchecked();
+    let gen_x_nn = Secp256BaseNNField::allocated_constant(cs, gen_x, base_field_params);
+    let gen_y_nn = Secp256BaseNNField::allocated_constant(cs, gen_y, base_field_params);
+
+    let n_u256 = modulus_as_uint256(cs, scalar_field_params);
+    let p_u256 = modulus_as_uint256(cs, base_field_params);
+
+    let one_num = Num::allocated_constant(cs, F::ONE);
+
+    let mut fs_input = vec![];
+    let mut is_well_formed = ArrayVec::<Boolean<F>, BATCH_VERIFY_MAX_SIGNATURES>::new();
+    let mut claimed_infinity = ArrayVec::<Boolean<F>, BATCH_VERIFY_MAX_SIGNATURES>::new();
+    let mut u1_and_u2 = ArrayVec::<_, BATCH_VERIFY_MAX_SIGNATURES>::new();
+    let mut public_keys = ArrayVec::<_, BATCH_VERIFY_MAX_SIGNATURES>::new();
+    let mut negated_claimed_points = ArrayVec::<_, BATCH_VERIFY_MAX_SIGNATURES>::new();
+    let mut results = [Boolean::allocated_constant(cs, false); BATCH_VERIFY_MAX_SIGNATURES];
+
+    for (((signature, claimed_point), is_active), result) in signatures
+        .iter()
+        .zip(claimed_points.iter())
+        .zip(is_active.iter())
+        .zip(results.iter_mut())
+    {
+        for word in signature.iter().chain(claimed_point.iter()) {
+            fs_input.extend(word.inner.iter().map(|el| el.into_num()));
+        }
+
+        let [message_hash, r, s, x, y] = signature;
+        let mut exception_flags = ArrayVec::<_, BATCH_EXCEPTION_FLAGS_ARR_LEN>::new();
+
+        // same checks of the inputs as for a single signature
+        let (r_as_u256, r_is_not_in_range) = range_check_and_mask(cs, r, &n_u256);
+        exception_flags.push(r_is_not_in_range);
+        let (s_as_u256, s_is_not_in_range) = range_check_and_mask(cs, s, &n_u256);
+        exception_flags.push(s_is_not_in_range);
+        let s_is_high = high_s_exception(cs, &s_as_u256, enforce_low_s, scalar_field_params);
+        exception_flags.push(s_is_high);
+        let (x_as_u256, x_is_not_in_range) = range_check_and_mask(cs, x, &p_u256);
+        exception_flags.push(x_is_not_in_range);
+        let (y_as_u256, y_is_not_in_range) = range_check_and_mask(cs, y, &p_u256);
+        exception_flags.push(y_is_not_in_range);
+
+        let mut x_fe = convert_uint256_to_field_element(cs, &x_as_u256, &base_field_params);
+        let mut y_fe = convert_uint256_to_field_element(cs, &y_as_u256, &base_field_params);
+
+        let (mut r_fe, r_is_zero) =
+            convert_uint256_to_field_element_masked(cs, &r_as_u256, &scalar_field_params);
+        exception_flags.push(r_is_zero);
+        let (mut s_fe, s_is_zero) =
+            convert_uint256_to_field_element_masked(cs, &s_as_u256, &scalar_field_params);
+        exception_flags.push(s_is_zero);
+
+        let mut message_hash_fe =
+            convert_uint256_to_field_element(cs, &message_hash, &scalar_field_params);
+
+        let is_on_curve = is_on_curve(cs, &mut x_fe, &mut y_fe, &mut curve_a_nn, &mut curve_b_nn);
+        let not_on_curve = is_on_curve.negated(cs);
+        exception_flags.push(not_on_curve);
+
+        let any_exception = Boolean::multi_or(cs, &exception_flags[..]);
+        let no_exception = any_exception.negated(cs);
+        let signature_is_well_formed = Boolean::multi_and(cs, &[*is_active, no_exception]);
+
+        let x_fe = Selectable::conditionally_select(cs, is_on_curve, &x_fe, &gen_x_nn);
+        let y_fe = Selectable::conditionally_select(cs, is_on_curve, &y_fe, &gen_y_nn);
+        public_keys.push(
+            SWProjectivePoint::<F, Secp256Affine, Secp256BaseNNField<F>>::from_xy_unchecked(
+                cs, x_fe, y_fe,
+            ),
+        );
+
+        // this always exists (0 was an exception and was masked)
+        let mut s_fe_inversed = s_fe.inverse_unchecked(cs);
+        let mut r_by_s_inv = r_fe.mul(cs, &mut s_fe_inversed);
+        let mut message_hash_by_s_inv = message_hash_fe.mul(cs, &mut s_fe_inversed);
+        r_by_s_inv.normalize(cs);
+        message_hash_by_s_inv.normalize(cs);
+        u1_and_u2.push((message_hash_by_s_inv, r_by_s_inv));
+
+        // now the claimed point
+        let [p_x, p_y] = claimed_point;
+        let p_x_is_zero = p_x.is_zero(cs);
+        let p_y_is_zero = p_y.is_zero(cs);
+        let p_is_infinity = Boolean::multi_and(cs, &[p_x_is_zero, p_y_is_zero]);
+
+        let (p_x_as_u256, p_x_is_not_in_range) = range_check_and_mask(cs, p_x, &p_u256);
+        let (p_y_as_u256, p_y_is_not_in_range) = range_check_and_mask(cs, p_y, &p_u256);
+        let mut p_x_fe = convert_uint256_to_field_element(cs, &p_x_as_u256, &base_field_params);
+        let mut p_y_fe = convert_uint256_to_field_element(cs, &p_y_as_u256, &base_field_params);
+        let p_is_on_curve = is_on_curve(
+            cs,
+            &mut p_x_fe,
+            &mut p_y_fe,
+            &mut curve_a_nn,
+            &mut curve_b_nn,
+        );
+        let p_x_is_in_range = p_x_is_not_in_range.negated(cs);
+        let p_y_is_in_range = p_y_is_not_in_range.negated(cs);
+        let p_is_valid = Boolean::multi_and(cs, &[p_x_is_in_range, p_y_is_in_range, p_is_on_curve]);
+
+        // the point must be valid if it takes part in the linear combination, otherwise
+        // the group law doesn't hold
+        let p_is_not_infinity = p_is_infinity.negated(cs);
+        let uses_claimed_point =
+            Boolean::multi_and(cs, &[signature_is_well_formed, p_is_not_infinity]);
+        let should_enforce_validity = Boolean::multi_and(cs, &[uses_claimed_point, should_enforce]);
+        Num::conditionally_enforce_equal(
+            cs,
+            should_enforce_validity,
+            &p_is_valid.into_num(),
+            &one_num,
+        );
+
+        let p_x_fe = Selectable::conditionally_select(cs, uses_claimed_point, &p_x_fe, &gen_x_nn);
+        let p_y_fe = Selectable::conditionally_select(cs, uses_claimed_point, &p_y_fe, &gen_y_nn);
+        let mut p_y_fe_negated = p_y_fe.negated(cs);
+        p_y_fe_negated.normalize(cs);
+        negated_claimed_points.push(
+            SWProjectivePoint::<F, Secp256Affine, Secp256BaseNNField<F>>::from_xy_unchecked(
+                cs,
+                p_x_fe,
+                p_y_fe_negated,
+            ),
+        );
+
+        // x(P) < p < 2n, so it's enough to subtract n once to compare it with r mod n
+        let (p_x_minus_n, borrow) = p_x_as_u256.overflowing_sub(cs, &n_u256);
+        let p_x_mod_n = UInt256::conditionally_select(cs, borrow, &p_x_as_u256, &p_x_minus_n);
+        let (diff, _) = p_x_mod_n.overflowing_sub(cs, &r_as_u256);
+        let signature_equality = diff.is_zero(cs);
+
+        *result = Boolean::multi_and(cs, &[uses_claimed_point, signature_equality]);
+
+        is_well_formed.push(signature_is_well_formed);
+        claimed_infinity.push(p_is_infinity);
+    }
+
+    let challenges = batch_challenges::<F, CS, R>(cs, &fs_input);
+
+    let mut sum_of_u1 =
+        Secp256ScalarNNField::allocated_constant(cs, Secp256Fr::zero(), scalar_field_params);
+    let mut points = Vec::with_capacity(BATCH_VERIFY_MAX_SIGNATURES * 2);
+    let mut decompositions = Vec::with_capacity(BATCH_VERIFY_MAX_SIGNATURES * 2);
+
+    for (((((challenge, signature_is_well_formed), p_is_infinity), (mut u1, mut u2)), q), p) in
+        challenges
+            .iter()
+            .zip(is_well_formed.into_iter())
+            .zip(claimed_infinity.into_iter())
+            .zip(u1_and_u2.into_iter())
+            .zip(public_keys.into_iter())
+            .zip(negated_claimed_points.into_iter())
+    {
+        // malformed signatures don't take part in the linear combination at all,
+        // and the point at infinity contributes nothing
+        let c_q = challenge.mask(cs, signature_is_well_formed);
+        let c_p = c_q.mask_negated(cs, p_is_infinity);
+
+        let mut c_q_fe = convert_uint256_to_field_element(cs, &c_q, &scalar_field_params);
+        let c_p_fe = convert_uint256_to_field_element(cs, &c_p, &scalar_field_params);
+
+        let mut c_q_by_u1 = c_q_fe.mul(cs, &mut u1);
+        sum_of_u1 = sum_of_u1.add(cs, &mut c_q_by_u1);
+        sum_of_u1.normalize(cs);
+
+        let mut c_q_by_u2 = c_q_fe.mul(cs, &mut u2);
+        c_q_by_u2.normalize(cs);
+
+        points.push(q);
+        decompositions.push(to_width_4_window_form(
+            cs,
+            c_q_by_u2,
+            SCALAR_FIELD_CANONICAL_REPR_LIMBS,
+        ));
+
+        // coefficient is constructed from 16 bytes, so all the higher windows are zero
+        let mut c_p_decomposition =
+            to_width_4_window_form(cs, c_p_fe, SCALAR_FIELD_CANONICAL_REPR_LIMBS);
+        let c_p_decomposition =
+            c_p_decomposition.split_off(c_p_decomposition.len() - CHALLENGE_WINDOWS);
+        points.push(p);
+        decompositions.push(c_p_decomposition);
+    }
+
+    let mut combination = width_4_windowed_multi_scalar_multiplication::<
+        F,
+        CS,
+        Secp256r1EcdsaCurve,
+        17,
+    >(cs, points, decompositions, &base_field_params);
+
+    let mut sum_of_u1_times_g = fixed_base_mul_by_generator::<F, CS, Secp256r1EcdsaCurve, 17>(
+        cs,
+        sum_of_u1,
+        &base_field_params,
+    );
+
+    let (mut sum_of_u1_times_g, is_infinity) =
+        sum_of_u1_times_g.convert_to_affine_or_default(cs, Secp256Affine::one());
+    let combination_added = combination.add_mixed(cs, &mut sum_of_u1_times_g);
+    let mut combination =
+        Selectable::conditionally_select(cs, is_infinity, &combination, &combination_added);
+
+    let (_, combination_is_infinity) =
+        combination.convert_to_affine_or_default(cs, Secp256Affine::one());
+    Num::conditionally_enforce_equal(
+        cs,
+        should_enforce,
+        &combination_is_infinity.into_num(),
+        &one_num,
+    );
+
+    results
+}
+
+fn is_on_curve<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    x: &mut Secp256BaseNNField<F>,
+    y: &mut Secp256BaseNNField<F>,
+    curve_a: &mut Secp256BaseNNField<F>,
+    curve_b: &mut Secp256BaseNNField<F>,
+) -> Boolean<F> {
+    let mut lhs = y.clone();
+    let mut lhs = lhs.mul(cs, y);
+    lhs.normalize(cs);
+
+    let mut rhs = x.clone();
+    let mut rhs = rhs.mul(cs, x);
+    let mut rhs = rhs.add(cs, curve_a);
+    let mut rhs = rhs.mul(cs, x);
+    let mut rhs = rhs.add(cs, curve_b);
+    rhs.normalize(cs);
+
+    Secp256BaseNNField::equals(cs, &mut lhs, &mut rhs)
+}
+
+/// Reads the number of signatures from the call ABI, then (hash, r, s, x, y) for every one of
+/// them, and writes the success flag, that is false if the number of signatures is not in
+/// [1, BATCH_VERIFY_MAX_SIGNATURES], followed by the bitmap of verification results
+pub fn secp256r1_batch_verify_function_entry_point<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    witness: Secp256r1BatchVerifyCircuitInstanceWitness<F>,
+    round_function: &R,
+    limit: usize,
+) -> [Num<F>; INPUT_OUTPUT_COMMITMENT_LENGTH]
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    assert!(limit <= u32::MAX as usize);
+
+    let Secp256r1BatchVerifyCircuitInstanceWitness {
+        closed_form_input,
+        requests_queue_witness,
+        memory_reads_witness,
+        claimed_points_witness,
+    } = witness;
+
+    let memory_reads_witness: VecDeque<_> = memory_reads_witness
+        .into_iter()
+        .flatten()
+        .flatten()
+        .collect();
+    let claimed_points_witness: VecDeque<_> = claimed_points_witness
+        .into_iter()
+        .flatten()
+        .flatten()
+        .collect();
+
+    let precompile_address =
+        UInt160::allocated_constant(cs, SECP256R1_BATCH_VERIFY_PRECOMPILE_FORMAL_ADDRESS);
+    let aux_byte_for_precompile = UInt8::allocated_constant(cs, PRECOMPILE_AUX_BYTE);
+
+    let scalar_params = Arc::new(secp256r1_scalar_field_params());
+    let base_params = Arc::new(secp256r1_base_field_params());
+
+    let mut structured_input = Secp256r1BatchVerifyCircuitInputOutput::alloc_ignoring_outputs(
+        cs,
+        closed_form_input.clone(),
+    );
+    let start_flag = structured_input.start_flag;
+
+    let requests_queue_state_from_input = structured_input.observable_input.initial_log_queue_state;
+
+    // it must be trivial
+    requests_queue_state_from_input.enforce_trivial_head(cs);
+
+    let requests_queue_state_from_fsm = structured_input.hidden_fsm_input.log_queue_state;
+
+    let requests_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &requests_queue_state_from_input,
+        &requests_queue_state_from_fsm,
+    );
+
+    let memory_queue_state_from_input =
+        structured_input.observable_input.initial_memory_queue_state;
+
+    // it must be trivial
+    memory_queue_state_from_input.enforce_trivial_head(cs);
+
+    let memory_queue_state_from_fsm = structured_input.hidden_fsm_input.memory_queue_state;
+
+    let memory_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &memory_queue_state_from_input,
+        &memory_queue_state_from_fsm,
+    );
+
+    let mut requests_queue = StorageLogQueue::<F, R>::from_state(cs, requests_queue_state);
+    let queue_witness = CircuitQueueWitness::from_inner_witness(requests_queue_witness);
+    requests_queue.witness = Arc::new(queue_witness);
+
+    let mut memory_queue = MemoryQueue::<F, R>::from_state(cs, memory_queue_state);
+
+    let one_u32 = UInt32::allocated_constant(cs, 1u32);
+    let max_signatures_u32 = UInt32::allocated_constant(cs, BATCH_VERIFY_MAX_SIGNATURES as u32);
+    let zero_num = Num::allocated_constant(cs, F::ZERO);
+    let one_num = Num::allocated_constant(cs, F::ONE);
+    let zero_u256 = UInt256::zero(cs);
+    let boolean_false = Boolean::allocated_constant(cs, false);
+    let boolean_true = Boolean::allocated_constant(cs, true);
+
+    use crate::storage_application::ConditionalWitnessAllocator;
+    let read_queries_allocator = ConditionalWitnessAllocator::<F, UInt256<F>> {
+        witness_source: Arc::new(RwLock::new(memory_reads_witness)),
+    };
+    let claimed_points_allocator = ConditionalWitnessAllocator::<F, UInt256<F>> {
+        witness_source: Arc::new(RwLock::new(claimed_points_witness)),
+    };
+
+    for _cycle in 0..limit {
+        let is_empty = requests_queue.is_empty(cs);
+        let should_process = is_empty.negated(cs);
+        let (request, _) = requests_queue.pop_front(cs, should_process);
+
+        let mut precompile_call_params =
+            Secp256r1BatchVerifyPrecompileCallParams::from_encoding(cs, request.key);
+
+        let timestamp_to_use_for_read = request.timestamp;
+        let timestamp_to_use_for_write = timestamp_to_use_for_read.add_no_overflow(cs, one_u32);
+
+        Num::conditionally_enforce_equal(
+            cs,
+            should_process,
+            &Num::from_variable(request.aux_byte.get_variable()),
+            &Num::from_variable(aux_byte_for_precompile.get_variable()),
+        );
+        for (a, b) in request
+            .address
+            .inner
+            .iter()
+            .zip(precompile_address.inner.iter())
+        {
+            Num::conditionally_enforce_equal(
+                cs,
+                should_process,
+                &Num::from_variable(a.get_variable()),
+                &Num::from_variable(b.get_variable()),
+            );
+        }
+
+        let num_signatures = precompile_call_params.num_signatures;
+        let (_, too_many_signatures) = max_signatures_u32.overflowing_sub(cs, num_signatures);
+        let no_signatures = num_signatures.is_zero(cs);
+        let invalid_num_signatures = Boolean::multi_or(cs, &[too_many_signatures, no_signatures]);
+        let valid_num_signatures = invalid_num_signatures.negated(cs);
+        let should_read = Boolean::multi_and(cs, &[should_process, valid_num_signatures]);
+
+        let mut signatures = [[zero_u256; MEMORY_QUERIES_PER_CALL]; BATCH_VERIFY_MAX_SIGNATURES];
+        let mut claimed_points = [[zero_u256; 2]; BATCH_VERIFY_MAX_SIGNATURES];
+        let mut is_active = [boolean_false; BATCH_VERIFY_MAX_SIGNATURES];
+        let mut bias_variable = should_process.get_variable();
+        for (idx, ((signature, claimed_point), is_active)) in signatures
+            .iter_mut()
+            .zip(claimed_points.iter_mut())
+            .zip(is_active.iter_mut())
+            .enumerate()
+        {
+            let idx_u32 = UInt32::allocated_constant(cs, idx as u32);
+            let (_, idx_is_less) = idx_u32.overflowing_sub(cs, num_signatures);
+            *is_active = Boolean::multi_and(cs, &[should_read, idx_is_less]);
+
+            for dst in signature.iter_mut() {
+                let read_query_value: UInt256<F> = read_queries_allocator
+                    .conditionally_allocate_biased(cs, *is_active, bias_variable);
+                bias_variable = read_query_value.inner[0].get_variable();
+
+                *dst = read_query_value;
+
+                let read_query = MemoryQuery {
+                    timestamp: timestamp_to_use_for_read,
+                    memory_page: precompile_call_params.input_page,
+                    index: precompile_call_params.input_offset,
+                    rw_flag: boolean_false,
+                    is_ptr: boolean_false,
+                    value: read_query_value,
+                };
+
+                let _ = memory_queue.push(cs, read_query, *is_active);
+
+                precompile_call_params.input_offset = precompile_call_params
+                    .input_offset
+                    .add_no_overflow(cs, one_u32);
+            }
+
+            for dst in claimed_point.iter_mut() {
+                let coordinate: UInt256<F> = claimed_points_allocator
+                    .conditionally_allocate_biased(cs, *is_active, bias_variable);
+                bias_variable = coordinate.inner[0].get_variable();
+
+                *dst = coordinate;
+            }
+        }
+
+        let results = secp256r1_batch_verify_function_inner::<F, CS, R>(
+            cs,
+            &signatures,
+            &claimed_points,
+            &is_active,
+            precompile_call_params.enforce_low_s,
+            should_process,
+            &base_params,
+            &scalar_params,
+        );
+
+        let success_as_u32 =
+            unsafe { UInt32::from_variable_unchecked(valid_num_signatures.get_variable()) };
+        let mut success_as_u256 = zero_u256;
+        success_as_u256.inner[0] = success_as_u32;
+
+        let mut bitmap = zero_num;
+        for (idx, result) in results.iter().enumerate() {
+            bitmap = Num::fma(
+                cs,
+                &result.into_num(),
+                &one_num,
+                &F::from_u64_unchecked(1u64 << idx),
+                &bitmap,
+                &F::ONE,
+            );
+        }
+        let mut bitmap_as_u256 = zero_u256;
+        bitmap_as_u256.inner[0] = unsafe { UInt32::from_variable_unchecked(bitmap.get_variable()) };
+
+        for value in [success_as_u256, bitmap_as_u256].into_iter() {
+            let write_query = MemoryQuery {
+                timestamp: timestamp_to_use_for_write,
+                memory_page: precompile_call_params.output_page,
+                index: precompile_call_params.output_offset,
+                rw_flag: boolean_true,
+                value,
+                is_ptr: boolean_false,
+            };
+
+            precompile_call_params.output_offset = precompile_call_params
+                .output_offset
+                .add_no_overflow(cs, one_u32);
+
+            let _ = memory_queue.push(cs, write_query, should_process);
+        }
+    }
+
+    requests_queue.enforce_consistency(cs);
+
+    // form the final state
+    let done = requests_queue.is_empty(cs);
+    structured_input.completion_flag = done;
+    structured_input.observable_output = PrecompileFunctionOutputData::placeholder(cs);
+
+    let final_memory_state = memory_queue.into_state();
+    let final_requets_state = requests_queue.into_state();
+
+    structured_input.observable_output.final_memory_state = QueueState::conditionally_select(
+        cs,
+        structured_input.completion_flag,
+        &final_memory_state,
+        &structured_input.observable_output.final_memory_state,
+    );
+
+    structured_input.hidden_fsm_output.log_queue_state = final_requets_state;
+    structured_input.hidden_fsm_output.memory_queue_state = final_memory_state;
+
+    // self-check
+    structured_input.hook_compare_witness(cs, &closed_form_input);
+
+    use boojum::cs::gates::PublicInputGate;
+
+    let compact_form =
+        ClosedFormInputCompactForm::from_full_form(cs, &structured_input, round_function);
+    let input_commitment = commit_variable_length_encodable_item(cs, &compact_form, round_function);
+    for el in input_commitment.iter() {
+        let gate = PublicInputGate::new(el.get_variable());
+        gate.add_to_cs(cs);
+    }
+
+    input_commitment
+}
+
+#[cfg(test)]
+mod test {
+    use boojum::algebraic_props::poseidon2_parameters::*;
+    use boojum::field::goldilocks::GoldilocksField;
+    use boojum::gadgets::traits::allocatable::CSAllocatable;
+    use boojum::implementations::poseidon2::Poseidon2Goldilocks;
+    use boojum::pairing::ff::{PrimeField, PrimeFieldRepr};
+    use boojum::pairing::GenericCurveProjective;
+    use boojum::worker::Worker;
+
+    use super::*;
+    use crate::ecdsa::ecdsa_verify_function_inner;
+
+    type F = GoldilocksField;
+    type P = GoldilocksField;
+
+    use boojum::config::DevCSConfig;
+
+    use boojum::cs::cs_builder::*;
+    use boojum::cs::cs_builder_reference::CsReferenceImplementationBuilder;
+    use boojum::cs::gates::*;
+    use boojum::cs::implementations::reference_cs::CSReferenceImplementation;
+    use boojum::cs::traits::gate::GatePlacementStrategy;
+    use boojum::cs::CSGeometry;
+    use boojum::cs::*;
+    use boojum::gadgets::tables::*;
+
+    fn repr_into_u256<T: PrimeFieldRepr>(repr: T) -> U256 {
+        let mut u256 = U256::zero();
+        u256.0.copy_from_slice(&repr.as_ref()[..4]);
+
+        u256
+    }
+
+    fn scalar_from_u256(value: U256) -> Secp256Fr {
+        Secp256Fr::from_str(&value.to_string()).unwrap()
+    }
+
+    fn base_from_u256(value: U256) -> Secp256Fq {
+        Secp256Fq::from_str(&value.to_string()).unwrap()
+    }
+
+    // u1 * G + u2 * Q computed out of circuit
+    fn claimed_point(signature: &[U256; MEMORY_QUERIES_PER_CALL]) -> [U256; 2] {
+        let [digest, r, s, x, y] = *signature;
+        let s_inv = scalar_from_u256(s).inverse().unwrap();
+        let mut u1 = scalar_from_u256(digest);
+        u1.mul_assign(&s_inv);
+        let mut u2 = scalar_from_u256(r);
+        u2.mul_assign(&s_inv);
+
+        let q = Secp256Affine::from_xy_checked(base_from_u256(x), base_from_u256(y)).unwrap();
+        let mut point = Secp256Affine::one().mul(u1.into_repr());
+        point.add_assign(&q.mul(u2.into_repr()));
+        let point = point.into_affine();
+        if point.is_zero() {
+            return [U256::zero(); 2];
+        }
+        let (x, y) = point.into_xy_unchecked();
+
+        [repr_into_u256(x.into_repr()), repr_into_u256(y.into_repr())]
+    }
+
+    fn simulate_signature(sk: Secp256Fr, digest: Secp256Fr, k: Secp256Fr) -> [U256; 5] {
+        let pk = Secp256Affine::one().mul(sk.into_repr()).into_affine();
+        let r_point = Secp256Affine::one().mul(k.into_repr()).into_affine();
+        let r = scalar_from_u256(repr_into_u256(r_point.into_xy_unchecked().0.into_repr()));
+
+        let mut s = r;
+        s.mul_assign(&sk);
+        s.add_assign(&digest);
+        s.mul_assign(&k.inverse().unwrap());
+
+        let (pk_x, pk_y) = pk.into_xy_unchecked();
+        [
+            repr_into_u256(digest.into_repr()),
+            repr_into_u256(r.into_repr()),
+            repr_into_u256(s.into_repr()),
+            repr_into_u256(pk_x.into_repr()),
+            repr_into_u256(pk_y.into_repr()),
+        ]
+    }
+
+    fn create_test_cs() -> CSReferenceImplementation<
+        F,
+        P,
+        DevCSConfig,
+        impl GateConfigurationHolder<F>,
+        impl StaticToolboxHolder,
+    > {
+        let geometry = CSGeometry {
+            num_columns_under_copy_permutation: 80,
+            num_witness_columns: 0,
+            num_constant_columns: 4,
+            max_allowed_constraint_degree: 8,
+        };
+
+        let max_variables = 1 << 26;
+        let max_trace_len = 1 << 21;
+
+        fn configure<
+            F: SmallField,
+            T: CsBuilderImpl<F, T>,
+            GC: GateConfigurationHolder<F>,
+            TB: StaticToolboxHolder,
+        >(
+            builder: CsBuilder<T, F, GC, TB>,
+        ) -> CsBuilder<T, F, impl GateConfigurationHolder<F>, impl StaticToolboxHolder> {
+            let builder = builder.allow_lookup(
+                LookupParameters::UseSpecializedColumnsWithTableIdAsConstant {
+                    width: 3,
+                    num_repetitions: 16,
+                    share_table_id: true,
+                },
+            );
+
+            let builder = ConstantsAllocatorGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = BooleanConstraintGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseSpecializedColumns {
+                    num_repetitions: 1,
+                    share_constants: false,
+                },
+            );
+            let builder = U8x4FMAGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ZeroCheckGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+                false,
+            );
+            let builder = FmaGateInBaseFieldWithoutConstant::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<32>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<16>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<8>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = DotProductGate::<4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = SelectionGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ParallelSelectionGate::<4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = PublicInputGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ReductionGate::<_, 4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = MatrixMultiplicationGate::<F, 12, Poseidon2GoldilocksExternalMatrix>::configure_builder(builder,GatePlacementStrategy::UseGeneralPurposeColumns);
+            let builder = MatrixMultiplicationGate::<F, 12, Poseidon2GoldilocksInnerMatrix>::configure_builder(builder,GatePlacementStrategy::UseGeneralPurposeColumns);
+            let builder = NopGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+
+            builder
+        }
+
+        let builder_impl =
+            CsReferenceImplementationBuilder::<F, P, DevCSConfig>::new(geometry, max_trace_len);
+        let builder = new_builder::<_, F>(builder_impl);
+
+        let builder = configure(builder);
+        let mut owned_cs = builder.build(max_variables);
+
+        // add tables
+        let table = create_xor8_table();
+        owned_cs.add_lookup_table::<Xor8Table, 3>(table);
+
+        seq_macro::seq!(C in 0..32 {
+            let table = create_secp256r1_fixed_base_mul_table::<F, 0, C>();
+            owned_cs.add_lookup_table::<Secp256r1FixedBaseMulTable<0, C>, 3>(table);
+            let table = create_secp256r1_fixed_base_mul_table::<F, 1, C>();
+            owned_cs.add_lookup_table::<Secp256r1FixedBaseMulTable<1, C>, 3>(table);
+            let table = create_secp256r1_fixed_base_mul_table::<F, 2, C>();
+            owned_cs.add_lookup_table::<Secp256r1FixedBaseMulTable<2, C>, 3>(table);
+            let table = create_secp256r1_fixed_base_mul_table::<F, 3, C>();
+            owned_cs.add_lookup_table::<Secp256r1FixedBaseMulTable<3, C>, 3>(table);
+            let table = create_secp256r1_fixed_base_mul_table::<F, 4, C>();
+            owned_cs.add_lookup_table::<Secp256r1FixedBaseMulTable<4, C>, 3>(table);
+            let table = create_secp256r1_fixed_base_mul_table::<F, 5, C>();
+            owned_cs.add_lookup_table::<Secp256r1FixedBaseMulTable<5, C>, 3>(table);
+            let table = create_secp256r1_fixed_base_mul_table::<F, 6, C>();
+            owned_cs.add_lookup_table::<Secp256r1FixedBaseMulTable<6, C>, 3>(table);
+            let table = create_secp256r1_fixed_base_mul_table::<F, 7, C>();
+            owned_cs.add_lookup_table::<Secp256r1FixedBaseMulTable<7, C>, 3>(table);
+        });
+
+        let table = create_byte_split_table::<F, 4>();
+        owned_cs.add_lookup_table::<ByteSplitTable<4>, 3>(table);
+
+        owned_cs
+    }
+
+    // same vector as for single signature verification
+    fn reference_signature() -> [U256; MEMORY_QUERIES_PER_CALL] {
+        [
+            "3fec5769b5cf4e310a7d150508e82fb8e3eda1c2c94c61492d3bd8aea99e06c9",
+            "e22466e928fdccef0de49e3503d2657d00494a00e764fd437bdafa05f5922b1f",
+            "bbb77c6817ccf50748419477e843d5bac67e6a70e97dde5a57e0c983b777e1ad",
+            "31a80482dadf89de6302b1988c82c29544c9c07bb910596158f6062517eb089a",
+            "2f54c9a0f348752950094d3228d3b940258c75fe2a413cb70baa21dc2e352fc5",
+        ]
+        .map(|el| U256::from_str_radix(el, 16).unwrap())
+    }
+
+    fn other_signature() -> [U256; MEMORY_QUERIES_PER_CALL] {
+        simulate_signature(
+            Secp256Fr::from_str("1234567890").unwrap(),
+            Secp256Fr::from_str("987654321").unwrap(),
+            Secp256Fr::from_str("55555555555").unwrap(),
+        )
+    }
+
+    fn run_batch_verification<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        signatures: [[U256; MEMORY_QUERIES_PER_CALL]; BATCH_VERIFY_MAX_SIGNATURES],
+        claimed_points: [[U256; 2]; BATCH_VERIFY_MAX_SIGNATURES],
+        is_active: [bool; BATCH_VERIFY_MAX_SIGNATURES],
+    ) -> [bool; BATCH_VERIFY_MAX_SIGNATURES] {
+        let scalar_params = Arc::new(secp256r1_scalar_field_params());
+        let base_params = Arc::new(secp256r1_base_field_params());
+
+        let signatures = signatures.map(|el| el.map(|el| UInt256::allocate(cs, el)));
+        let claimed_points = claimed_points.map(|el| el.map(|el| UInt256::allocate(cs, el)));
+        let is_active = is_active.map(|el| Boolean::allocate(cs, el));
+        let boolean_false = Boolean::allocated_constant(cs, false);
+        let boolean_true = Boolean::allocated_constant(cs, true);
+
+        let results = secp256r1_batch_verify_function_inner::<F, CS, Poseidon2Goldilocks>(
+            cs,
+            &signatures,
+            &claimed_points,
+            &is_active,
+            boolean_false,
+            boolean_true,
+            &base_params,
+            &scalar_params,
+        );
+
+        results.map(|el| el.witness_hook(&*cs)().unwrap())
+    }
+
+    fn is_satisfied(
+        mut owned_cs: CSReferenceImplementation<
+            F,
+            P,
+            DevCSConfig,
+            impl GateConfigurationHolder<F>,
+            impl StaticToolboxHolder,
+        >,
+    ) -> bool {
+        owned_cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        cs.print_gate_stats();
+        let worker = Worker::new();
+        cs.check_if_satisfied(&worker)
+    }
+
+    #[test]
+    fn test_secp256r1_batch_verification() {
+        let mut owned_cs = create_test_cs();
+        let cs = &mut owned_cs;
+
+        let mut tampered_signature = reference_signature();
+        tampered_signature[0] = tampered_signature[0] + U256::one();
+
+        let signatures = [
+            reference_signature(),
+            other_signature(),
+            tampered_signature,
+            [U256::zero(); MEMORY_QUERIES_PER_CALL],
+        ];
+        let claimed_points = [
+            claimed_point(&signatures[0]),
+            claimed_point(&signatures[1]),
+            claimed_point(&signatures[2]),
+            [U256::zero(); 2],
+        ];
+        let is_active = [true, true, true, false];
+
+        let results = run_batch_verification(cs, signatures, claimed_points, is_active);
+        assert_eq!(results, [true, true, false, false]);
+
+        dbg!(cs.next_available_row());
+
+        assert!(is_satisfied(owned_cs));
+    }
+
+    #[test]
+    fn test_secp256r1_batch_verification_mixed_validity() {
+        let mut owned_cs = create_test_cs();
+        let cs = &mut owned_cs;
+
+        let mut wrong_r_signature = other_signature();
+        wrong_r_signature[1] = wrong_r_signature[1] + U256::one();
+        let mut zero_s_signature = reference_signature();
+        zero_s_signature[2] = U256::zero();
+
+        let signatures = [
+            wrong_r_signature,
+            reference_signature(),
+            zero_s_signature,
+            other_signature(),
+        ];
+        // malformed signature doesn't need a claimed point
+        let claimed_points = [
+            claimed_point(&signatures[0]),
+            claimed_point(&signatures[1]),
+            [U256::zero(); 2],
+            claimed_point(&signatures[3]),
+        ];
+        let is_active = [true; BATCH_VERIFY_MAX_SIGNATURES];
+
+        let results = run_batch_verification(cs, signatures, claimed_points, is_active);
+        assert_eq!(results, [false, true, false, true]);
+
+        assert!(is_satisfied(owned_cs));
+    }
+
+    #[test]
+    fn test_secp256r1_batch_verification_wrong_claimed_point() {
+        let mut owned_cs = create_test_cs();
+        let cs = &mut owned_cs;
+
+        let signatures = [
+            reference_signature(),
+            other_signature(),
+            [U256::zero(); MEMORY_QUERIES_PER_CALL],
+            [U256::zero(); MEMORY_QUERIES_PER_CALL],
+        ];
+        // valid curve point, but not the one u1 * G + u2 * Q of the second signature
+        let claimed_points = [
+            claimed_point(&signatures[0]),
+            claimed_point(&signatures[0]),
+            [U256::zero(); 2],
+            [U256::zero(); 2],
+        ];
+        let is_active = [true, true, false, false];
+
+        let _ = run_batch_verification(cs, signatures, claimed_points, is_active);
+
+        assert!(is_satisfied(owned_cs) == false);
+    }
+
+    #[test]
+    fn test_secp256r1_batch_verification_is_cheaper_than_single_verifications() {
+        let mut signatures = [reference_signature(); BATCH_VERIFY_MAX_SIGNATURES];
+        for (idx, signature) in signatures.iter_mut().enumerate().skip(1) {
+            *signature = simulate_signature(
+                Secp256Fr::from_str(&(1234567890 + idx).to_string()).unwrap(),
+                Secp256Fr::from_str("987654321").unwrap(),
+                Secp256Fr::from_str(&(55555555555u64 + idx as u64).to_string()).unwrap(),
+            );
+        }
+
+        let mut owned_cs = create_test_cs();
+        let cs = &mut owned_cs;
+        let rows_before = cs.next_available_row();
+        let results = run_batch_verification(
+            cs,
+            signatures,
+            signatures.map(|el| claimed_point(&el)),
+            [true; BATCH_VERIFY_MAX_SIGNATURES],
+        );
+        assert_eq!(results, [true; BATCH_VERIFY_MAX_SIGNATURES]);
+        let batch_rows = cs.next_available_row() - rows_before;
+
+        let mut owned_cs = create_test_cs();
+        let cs = &mut owned_cs;
+        let scalar_params = Arc::new(secp256r1_scalar_field_params());
+        let base_params = Arc::new(secp256r1_base_field_params());
+        let boolean_false = Boolean::allocated_constant(cs, false);
+        let rows_before = cs.next_available_row();
+        for signature in signatures.iter() {
+            let [message_hash, r, s, x, y] = signature.map(|el| UInt256::allocate(cs, el));
+            let (no_error, _) = ecdsa_verify_function_inner::<F, _, Secp256r1EcdsaCurve, 17>(
+                cs,
+                &r,
+                &s,
+                &message_hash,
+                &x,
+                &y,
+                boolean_false,
+                &base_params,
+                &scalar_params,
+            );
+            assert!(no_error.witness_hook(&*cs)().unwrap());
+        }
+        let single_rows = cs.next_available_row() - rows_before;
+
+        dbg!(batch_rows, single_rows);
+        assert!(batch_rows < single_rows);
+    }
+}
@@ -49,3 +49,32 @@ pub struct Secp256r1VerifyCircuitInstanceWitness<F: SmallField> {
     pub requests_queue_witness: CircuitQueueRawWitness<F, LogQuery<F>, 4, LOG_QUERY_PACKED_WIDTH>,
     pub memory_reads_witness: VecDeque<[U256; MEMORY_QUERIES_PER_CALL]>,
 }
+
+pub type Secp256r1RecoverCircuitInputOutput<F> = Secp256r1VerifyCircuitInputOutput<F>;
+pub type Secp256r1RecoverCircuitInputOutputWitness<F> = Secp256r1VerifyCircuitInputOutputWitness<F>;
+
+#[derive(Derivative, serde::Serialize, serde::Deserialize)]
+#[derivative(Clone, Debug, Default)]
+#[serde(bound = "")]
+pub struct Secp256r1RecoverCircuitInstanceWitness<F: SmallField> {
+    pub closed_form_input: Secp256r1RecoverCircuitInputOutputWitness<F>,
+    pub requests_queue_witness: CircuitQueueRawWitness<F, LogQuery<F>, 4, LOG_QUERY_PACKED_WIDTH>,
+    pub memory_reads_witness: VecDeque<[U256; RECOVER_MEMORY_QUERIES_PER_CALL]>,
+}
+
+pub type Secp256r1BatchVerifyCircuitInputOutput<F> = Secp256r1VerifyCircuitInputOutput<F>;
+pub type Secp256r1BatchVerifyCircuitInputOutputWitness<F> =
+    Secp256r1VerifyCircuitInputOutputWitness<F>;
+
+#[derive(Derivative, serde::Serialize, serde::Deserialize)]
+#[derivative(Clone, Debug, Default)]
+#[serde(bound = "")]
+pub struct Secp256r1BatchVerifyCircuitInstanceWitness<F: SmallField> {
+    pub closed_form_input: Secp256r1BatchVerifyCircuitInputOutputWitness<F>,
+    pub requests_queue_witness: CircuitQueueRawWitness<F, LogQuery<F>, 4, LOG_QUERY_PACKED_WIDTH>,
+    // (hash, r, s, x, y) for every signature of the call
+    pub memory_reads_witness: VecDeque<Vec<[U256; MEMORY_QUERIES_PER_CALL]>>,
+    // u1 * G + u2 * Q for every signature of the call, where the point at infinity is
+    // encoded as (0, 0). Values for malformed signatures are ignored
+    pub claimed_points_witness: VecDeque<Vec<[U256; 2]>>,
+}
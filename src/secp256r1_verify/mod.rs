@@ -19,6 +19,14 @@ use boojum::gadgets::traits::witnessable::WitnessHookable;
 
 use cs_derive::*;
 
+use crate::ecdsa::EcdsaCurve;
+
+use crate::base_structures::precompile_input_outputs::*;
+use crate::demux_log_queue::DemuxOutput;
+use crate::precompile_registry::PrecompileCircuit;
+use crate::scheduler::auxiliary::BaseLayerCircuitType;
+use zkevm_opcode_defs::ethereum_types::H160;
+
 pub mod input;
 pub use self::input::*;
 
@@ -28,8 +36,12 @@ pub mod secp256r1;
 pub use self::fixed_base_mul_table::*;
 
 pub const MEMORY_QUERIES_PER_CALL: usize = 5;
+pub const RECOVER_MEMORY_QUERIES_PER_CALL: usize = 4;
+pub const BATCH_VERIFY_MAX_SIGNATURES: usize = 4;
 
 pub mod baseline;
+pub mod batch;
+pub mod recover;
 
 // characteristics of the base field for secp curve
 use self::secp256r1::fq::Fq as Secp256Fq;
@@ -46,9 +58,6 @@ const SCALAR_FIELD_CANONICAL_REPR_LIMBS: usize = 16;
 type Secp256BaseNNFieldParams = NonNativeFieldOverU16Params<Secp256Fq, 17>;
 type Secp256ScalarNNFieldParams = NonNativeFieldOverU16Params<Secp256Fr, 17>;
 
-type Secp256BaseNNField<F> = NonNativeFieldOverU16<F, Secp256Fq, 17>;
-type Secp256ScalarNNField<F> = NonNativeFieldOverU16<F, Secp256Fr, 17>;
-
 fn secp256r1_base_field_params() -> Secp256BaseNNFieldParams {
     NonNativeFieldOverU16Params::create()
 }
@@ -57,7 +66,116 @@ fn secp256r1_scalar_field_params() -> Secp256ScalarNNFieldParams {
     NonNativeFieldOverU16Params::create()
 }
 
+#[derive(Clone, Copy, Debug)]
+pub struct Secp256r1EcdsaCurve;
+
+impl EcdsaCurve<17> for Secp256r1EcdsaCurve {
+    type Base = Secp256Fq;
+    type Scalar = Secp256Fr;
+    type Affine = Secp256Affine;
+
+    const BASE_FIELD_CANONICAL_REPR_LIMBS: usize = BASE_FIELD_CANONICAL_REPR_LIMBS;
+    const SCALAR_FIELD_CANONICAL_REPR_LIMBS: usize = SCALAR_FIELD_CANONICAL_REPR_LIMBS;
+
+    fn base_field_params() -> Secp256BaseNNFieldParams {
+        secp256r1_base_field_params()
+    }
+
+    fn scalar_field_params() -> Secp256ScalarNNFieldParams {
+        secp256r1_scalar_field_params()
+    }
+
+    fn fixed_base_table_ids<F: SmallField, CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+    ) -> Option<Vec<[u32; 8]>> {
+        let mut full_table_ids = vec![];
+        seq_macro::seq!(C in 0..32 {
+            let ids = [
+                cs.get_table_id_for_marker::<Secp256r1FixedBaseMulTable<0, C>>()
+                    .expect("table must exist"),
+                cs.get_table_id_for_marker::<Secp256r1FixedBaseMulTable<1, C>>()
+                    .expect("table must exist"),
+                cs.get_table_id_for_marker::<Secp256r1FixedBaseMulTable<2, C>>()
+                    .expect("table must exist"),
+                cs.get_table_id_for_marker::<Secp256r1FixedBaseMulTable<3, C>>()
+                    .expect("table must exist"),
+                cs.get_table_id_for_marker::<Secp256r1FixedBaseMulTable<4, C>>()
+                    .expect("table must exist"),
+                cs.get_table_id_for_marker::<Secp256r1FixedBaseMulTable<5, C>>()
+                    .expect("table must exist"),
+                cs.get_table_id_for_marker::<Secp256r1FixedBaseMulTable<6, C>>()
+                    .expect("table must exist"),
+                cs.get_table_id_for_marker::<Secp256r1FixedBaseMulTable<7, C>>()
+                    .expect("table must exist"),
+            ];
+            full_table_ids.push(ids);
+        });
+
+        Some(full_table_ids)
+    }
+}
+
+pub struct Secp256r1VerifyPrecompileCircuit;
+
+impl PrecompileCircuit for Secp256r1VerifyPrecompileCircuit {
+    const NAME: &'static str = "secp256r1_verify";
+    const DEMUX_OUTPUT: DemuxOutput = DemuxOutput::Secp256r1Verify;
+    const CIRCUIT_TYPE: BaseLayerCircuitType = BaseLayerCircuitType::Secp256r1Verify;
+
+    type InputData<F: SmallField> = PrecompileFunctionInputData<F>;
+    type OutputData<F: SmallField> = PrecompileFunctionOutputData<F>;
+
+    fn formal_address() -> H160 {
+        *zkevm_opcode_defs::system_params::SECP256R1_VERIFY_INNER_FUNCTION_PRECOMPILE_FORMAL_ADDRESS
+    }
+}
+
+// placed right after the SHA-512 round function precompile
+pub const SECP256R1_RECOVER_PRECOMPILE_ADDRESS: u16 = 0x104;
+pub const SECP256R1_RECOVER_PRECOMPILE_FORMAL_ADDRESS: H160 =
+    formal_precompile_address(SECP256R1_RECOVER_PRECOMPILE_ADDRESS);
+
+pub struct Secp256r1RecoverPrecompileCircuit;
+
+impl PrecompileCircuit for Secp256r1RecoverPrecompileCircuit {
+    const NAME: &'static str = "secp256r1_recover";
+    const DEMUX_OUTPUT: DemuxOutput = DemuxOutput::Secp256r1Recover;
+    const CIRCUIT_TYPE: BaseLayerCircuitType = BaseLayerCircuitType::Secp256r1RecoverPrecompile;
+
+    type InputData<F: SmallField> = PrecompileFunctionInputData<F>;
+    type OutputData<F: SmallField> = PrecompileFunctionOutputData<F>;
+
+    fn formal_address() -> H160 {
+        SECP256R1_RECOVER_PRECOMPILE_FORMAL_ADDRESS
+    }
+}
+
+pub const SECP256R1_BATCH_VERIFY_PRECOMPILE_ADDRESS: u16 = 0x105;
+pub const SECP256R1_BATCH_VERIFY_PRECOMPILE_FORMAL_ADDRESS: H160 =
+    formal_precompile_address(SECP256R1_BATCH_VERIFY_PRECOMPILE_ADDRESS);
+
+pub struct Secp256r1BatchVerifyPrecompileCircuit;
+
+impl PrecompileCircuit for Secp256r1BatchVerifyPrecompileCircuit {
+    const NAME: &'static str = "secp256r1_batch_verify";
+    const DEMUX_OUTPUT: DemuxOutput = DemuxOutput::Secp256r1BatchVerify;
+    const CIRCUIT_TYPE: BaseLayerCircuitType = BaseLayerCircuitType::Secp256r1BatchVerifyPrecompile;
+
+    type InputData<F: SmallField> = PrecompileFunctionInputData<F>;
+    type OutputData<F: SmallField> = PrecompileFunctionOutputData<F>;
+
+    fn formal_address() -> H160 {
+        SECP256R1_BATCH_VERIFY_PRECOMPILE_FORMAL_ADDRESS
+    }
+}
+
 // re-exports for integration
 pub use self::baseline::{
     secp256r1_verify_function_entry_point, Secp256r1VerifyPrecompileCallParams,
 };
+pub use self::batch::{
+    secp256r1_batch_verify_function_entry_point, Secp256r1BatchVerifyPrecompileCallParams,
+};
+pub use self::recover::{
+    secp256r1_recover_function_entry_point, Secp256r1RecoverPrecompileCallParams,
+};
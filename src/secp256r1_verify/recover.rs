@@ -0,0 +1,663 @@
+use super::*;
+
+use crate::base_structures::precompile_input_outputs::PrecompileFunctionOutputData;
+use crate::demux_log_queue::StorageLogQueue;
+use crate::ecdsa::{
+    fixed_base_mul_by_generator, high_s_exception, modulus_as_uint256, range_check_and_mask,
+    width_4_windowed_multiplication, EcdsaPrecompileCallParams,
+};
+use crate::ecrecover::baseline::convert_uint256_to_field_element;
+use crate::ecrecover::baseline::convert_uint256_to_field_element_masked;
+use crate::ecrecover::new_optimized::convert_field_element_to_uint256;
+use crate::fsm_input_output::circuit_inputs::INPUT_OUTPUT_COMMITMENT_LENGTH;
+
+use arrayvec::ArrayVec;
+use boojum::algebraic_props::round_function::AlgebraicRoundFunction;
+use boojum::cs::traits::cs::ConstraintSystem;
+use boojum::field::SmallField;
+use boojum::gadgets::boolean::Boolean;
+use boojum::gadgets::curves::sw_projective::SWProjectivePoint;
+
+use boojum::gadgets::num::Num;
+use boojum::gadgets::queue::CircuitQueueWitness;
+use boojum::gadgets::queue::QueueState;
+use boojum::gadgets::traits::allocatable::{CSAllocatableExt, CSPlaceholder};
+use boojum::gadgets::traits::round_function::CircuitRoundFunction;
+use boojum::gadgets::traits::selectable::Selectable;
+
+use boojum::gadgets::u160::UInt160;
+use boojum::gadgets::u256::UInt256;
+use boojum::gadgets::u32::UInt32;
+use boojum::gadgets::u8::UInt8;
+use boojum::pairing::GenericCurveAffine;
+
+use std::collections::VecDeque;
+use std::sync::{Arc, RwLock};
+use zkevm_opcode_defs::system_params::PRECOMPILE_AUX_BYTE;
+
+pub type Secp256r1RecoverPrecompileCallParams<F> = EcdsaPrecompileCallParams<F>;
+
+type Secp256BaseNNField<F> = NonNativeFieldOverU16<F, Secp256Fq, 17>;
+
+const RECOVER_EXCEPTION_FLAGS_ARR_LEN: usize = 9;
+const X_POWERS_ARR_LEN: usize = 255;
+
+/// Recovers the public key Q from the signature (r, s) of the message hash, where the lowest bit
+/// of the recovery id is the parity of the y coordinate of the nonce point R, and the second bit
+/// tells that R.x = r + n. Returns the success flag and the affine coordinates of Q, that are
+/// zero if any of the inputs is malformed. If `enforce_low_s` is set, then signatures with
+/// s > n/2 are treated as malformed
+fn secp256r1_recover_function_inner<F: SmallField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    recid: &UInt8<F>,
+    r: &UInt256<F>,
+    s: &UInt256<F>,
+    message_hash: &UInt256<F>,
+    enforce_low_s: Boolean<F>,
+    base_field_params: &Arc<Secp256BaseNNFieldParams>,
+    scalar_field_params: &Arc<Secp256ScalarNNFieldParams>,
+) -> (Boolean<F>, UInt256<F>, UInt256<F>) {
+    let curve_a = Secp256Affine::a_coeff();
+    let curve_b = Secp256Affine::b_coeff();
+
+    let mut curve_a_nn =
+        Secp256BaseNNField::<F>::allocated_constant(cs, curve_a, &base_field_params);
+    let mut curve_b_nn =
+        Secp256BaseNNField::<F>::allocated_constant(cs, curve_b, &base_field_params);
+
+    let generator = Secp256Affine::one();
+    let (gen_x, gen_y) = generator.into_xy_unchecked();
+    let gen_x_nn = Secp256BaseNNField::allocated_constant(cs, gen_x, base_field_params);
+    let gen_y_nn = Secp256BaseNNField::allocated_constant(cs, gen_y, base_field_params);
+
+    let n_u256 = modulus_as_uint256(cs, scalar_field_params);
+    let p_u256 = modulus_as_uint256(cs, base_field_params);
+
+    let mut exception_flags = ArrayVec::<_, RECOVER_EXCEPTION_FLAGS_ARR_LEN>::new();
+
+    // same recovery id encoding as for ecrecover: for secp256r1 n < p, so some r determine
+    // two candidates x = r and x = r + n, and the second bit of recid selects the latter one
+    let [y_is_odd, x_overflow, ..] =
+        Num::<F>::from_variable(recid.get_variable()).spread_into_bits::<_, 8>(cs);
+
+    let (r_plus_n, of) = r.overflowing_add(cs, &n_u256);
+    let x_as_u256 = UInt256::conditionally_select(cs, x_overflow, &r_plus_n, &r);
+    let error = Boolean::multi_and(cs, &[x_overflow, of]);
+    exception_flags.push(error);
+
+    let (x_as_u256, x_is_not_in_range) = range_check_and_mask(cs, &x_as_u256, &p_u256);
+    exception_flags.push(x_is_not_in_range);
+    let (r_as_u256, r_is_not_in_range) = range_check_and_mask(cs, r, &n_u256);
+    exception_flags.push(r_is_not_in_range);
+    let (s_as_u256, s_is_not_in_range) = range_check_and_mask(cs, s, &n_u256);
+    exception_flags.push(s_is_not_in_range);
+    let s_is_high = high_s_exception(cs, &s_as_u256, enforce_low_s, scalar_field_params);
+    exception_flags.push(s_is_high);
+
+    let mut x_fe = convert_uint256_to_field_element(cs, &x_as_u256, &base_field_params);
+
+    let (mut r_fe, r_is_zero) =
+        convert_uint256_to_field_element_masked(cs, &r_as_u256, &scalar_field_params);
+    exception_flags.push(r_is_zero);
+    let (mut s_fe, s_is_zero) =
+        convert_uint256_to_field_element_masked(cs, &s_as_u256, &scalar_field_params);
+    exception_flags.push(s_is_zero);
+
+    let mut message_hash_fe =
+        convert_uint256_to_field_element(cs, &message_hash, &scalar_field_params);
+
+    // curve equation is y^2 = x^3 + ax + b. The group has prime order, so there are no points of
+    // order 2 and t = x^3 + ax + b is never zero. As p = 3 mod 4 the square root candidate is
+    // t^{(p+1)/4}, where for
+    //           p = 2^256 - 2^224 + 2^192 + 2^96 - 1
+    // (p+1)/4 = 2^254 - 2^222 + 2^190 + 2^94
+    // and t is a quadratic residue if and only if the candidate squares back to t
+    let mut t = x_fe.square(cs);
+    let mut t = t.add(cs, &mut curve_a_nn);
+    let mut t = t.mul(cs, &mut x_fe);
+    let mut t = t.add(cs, &mut curve_b_nn);
+    t.normalize(cs);
+
+    // array of powers of t of the form t^{2^i} starting from i = 0 to 254
+    let mut t_powers = Vec::with_capacity(X_POWERS_ARR_LEN);
+    t_powers.push(t.clone());
+
+    for _ in 1..X_POWERS_ARR_LEN {
+        let prev = t_powers.last_mut().unwrap();
+        let next = prev.square(cs);
+        t_powers.push(next);
+    }
+
+    let mut acc = t_powers[254].clone();
+    for idx in [190, 94].into_iter() {
+        let other = &mut t_powers[idx];
+        acc = acc.mul(cs, other);
+    }
+    let mut may_be_recovered_y = acc.div_unchecked(cs, &mut t_powers[222]);
+    may_be_recovered_y.normalize(cs);
+
+    let mut y_squared = may_be_recovered_y.square(cs);
+    y_squared.normalize(cs);
+    let t_is_residue = Secp256BaseNNField::<F>::equals(cs, &mut y_squared, &mut t);
+    let t_is_nonresidue = t_is_residue.negated(cs);
+    exception_flags.push(t_is_nonresidue);
+
+    let may_be_recovered_y_negated = may_be_recovered_y.negated(cs);
+
+    let [lowest_bit, ..] =
+        Num::<F>::from_variable(may_be_recovered_y.limbs[0]).spread_into_bits::<_, 16>(cs);
+
+    // if lowest bit != parity bit, then we need conditionally select
+    let should_swap = lowest_bit.xor(cs, y_is_odd);
+    let may_be_recovered_y = Selectable::conditionally_select(
+        cs,
+        should_swap,
+        &may_be_recovered_y_negated,
+        &may_be_recovered_y,
+    );
+
+    // if there is no point with such x we continue with the generator, so our arithmetic
+    // formulas work, and the result is masked anyway
+    let x = Selectable::conditionally_select(cs, t_is_nonresidue, &gen_x_nn, &x_fe);
+    let y = Selectable::conditionally_select(cs, t_is_nonresidue, &gen_y_nn, &may_be_recovered_y);
+
+    // this always exists (0 was an exception and was masked)
+    let mut r_fe_inversed = r_fe.inverse_unchecked(cs);
+    let mut s_by_r_inv = s_fe.mul(cs, &mut r_fe_inversed);
+    let mut message_hash_by_r_inv = message_hash_fe.mul(cs, &mut r_fe_inversed);
+
+    s_by_r_inv.normalize(cs);
+    let mut message_hash_by_r_inv_negated = message_hash_by_r_inv.negated(cs);
+    message_hash_by_r_inv_negated.normalize(cs);
+
+    // Q = (s * R - hash * G) / r
+    let recovered_point =
+        SWProjectivePoint::<F, Secp256Affine, Secp256BaseNNField<F>>::from_xy_unchecked(cs, x, y);
+
+    let mut s_times_r = width_4_windowed_multiplication::<F, CS, Secp256r1EcdsaCurve, 17>(
+        cs,
+        recovered_point,
+        s_by_r_inv,
+        &base_field_params,
+        &scalar_field_params,
+    );
+
+    let mut hash_times_g = fixed_base_mul_by_generator::<F, CS, Secp256r1EcdsaCurve, 17>(
+        cs,
+        message_hash_by_r_inv_negated,
+        &base_field_params,
+    );
+
+    let (mut q_acc, is_infinity) =
+        hash_times_g.convert_to_affine_or_default(cs, Secp256Affine::one());
+    let q_acc_added = s_times_r.add_mixed(cs, &mut q_acc);
+    let mut q_acc = Selectable::conditionally_select(cs, is_infinity, &s_times_r, &q_acc_added);
+
+    let ((mut q_x, mut q_y), is_infinity) =
+        q_acc.convert_to_affine_or_default(cs, Secp256Affine::one());
+    exception_flags.push(is_infinity);
+    let any_exception = Boolean::multi_or(cs, &exception_flags[..]);
+
+    q_x.enforce_reduced(cs);
+    q_y.enforce_reduced(cs);
+
+    let q_x = convert_field_element_to_uint256(cs, q_x);
+    let q_y = convert_field_element_to_uint256(cs, q_y);
+
+    let q_x = q_x.mask_negated(cs, any_exception);
+    let q_y = q_y.mask_negated(cs, any_exception);
+    let all_ok = any_exception.negated(cs);
+
+    (all_ok, q_x, q_y)
+}
+
+/// Reads (hash, v, r, s) for every call and writes the success flag followed by the x and y
+/// coordinates of the recovered public key
+pub fn secp256r1_recover_function_entry_point<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    witness: Secp256r1RecoverCircuitInstanceWitness<F>,
+    round_function: &R,
+    limit: usize,
+) -> [Num<F>; INPUT_OUTPUT_COMMITMENT_LENGTH]
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    assert!(limit <= u32::MAX as usize);
+
+    let Secp256r1RecoverCircuitInstanceWitness {
+        closed_form_input,
+        requests_queue_witness,
+        memory_reads_witness,
+    } = witness;
+
+    let memory_reads_witness: VecDeque<_> = memory_reads_witness.into_iter().flatten().collect();
+
+    let precompile_address =
+        UInt160::allocated_constant(cs, SECP256R1_RECOVER_PRECOMPILE_FORMAL_ADDRESS);
+    let aux_byte_for_precompile = UInt8::allocated_constant(cs, PRECOMPILE_AUX_BYTE);
+
+    let scalar_params = Arc::new(secp256r1_scalar_field_params());
+    let base_params = Arc::new(secp256r1_base_field_params());
+
+    let mut structured_input =
+        Secp256r1RecoverCircuitInputOutput::alloc_ignoring_outputs(cs, closed_form_input.clone());
+    let start_flag = structured_input.start_flag;
+
+    let requests_queue_state_from_input = structured_input.observable_input.initial_log_queue_state;
+
+    // it must be trivial
+    requests_queue_state_from_input.enforce_trivial_head(cs);
+
+    let requests_queue_state_from_fsm = structured_input.hidden_fsm_input.log_queue_state;
+
+    let requests_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &requests_queue_state_from_input,
+        &requests_queue_state_from_fsm,
+    );
+
+    let memory_queue_state_from_input =
+        structured_input.observable_input.initial_memory_queue_state;
+
+    // it must be trivial
+    memory_queue_state_from_input.enforce_trivial_head(cs);
+
+    let memory_queue_state_from_fsm = structured_input.hidden_fsm_input.memory_queue_state;
+
+    let memory_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &memory_queue_state_from_input,
+        &memory_queue_state_from_fsm,
+    );
+
+    let mut requests_queue = StorageLogQueue::<F, R>::from_state(cs, requests_queue_state);
+    let queue_witness = CircuitQueueWitness::from_inner_witness(requests_queue_witness);
+    requests_queue.witness = Arc::new(queue_witness);
+
+    let mut memory_queue = MemoryQueue::<F, R>::from_state(cs, memory_queue_state);
+
+    let one_u32 = UInt32::allocated_constant(cs, 1u32);
+    let zero_u256 = UInt256::zero(cs);
+    let boolean_false = Boolean::allocated_constant(cs, false);
+    let boolean_true = Boolean::allocated_constant(cs, true);
+
+    use crate::storage_application::ConditionalWitnessAllocator;
+    let read_queries_allocator = ConditionalWitnessAllocator::<F, UInt256<F>> {
+        witness_source: Arc::new(RwLock::new(memory_reads_witness)),
+    };
+
+    for _cycle in 0..limit {
+        let is_empty = requests_queue.is_empty(cs);
+        let should_process = is_empty.negated(cs);
+        let (request, _) = requests_queue.pop_front(cs, should_process);
+
+        let mut precompile_call_params =
+            Secp256r1RecoverPrecompileCallParams::from_encoding(cs, request.key);
+
+        let timestamp_to_use_for_read = request.timestamp;
+        let timestamp_to_use_for_write = timestamp_to_use_for_read.add_no_overflow(cs, one_u32);
+
+        Num::conditionally_enforce_equal(
+            cs,
+            should_process,
+            &Num::from_variable(request.aux_byte.get_variable()),
+            &Num::from_variable(aux_byte_for_precompile.get_variable()),
+        );
+        for (a, b) in request
+            .address
+            .inner
+            .iter()
+            .zip(precompile_address.inner.iter())
+        {
+            Num::conditionally_enforce_equal(
+                cs,
+                should_process,
+                &Num::from_variable(a.get_variable()),
+                &Num::from_variable(b.get_variable()),
+            );
+        }
+
+        let mut read_values = [zero_u256; RECOVER_MEMORY_QUERIES_PER_CALL];
+        let mut bias_variable = should_process.get_variable();
+        for dst in read_values.iter_mut() {
+            let read_query_value: UInt256<F> = read_queries_allocator
+                .conditionally_allocate_biased(cs, should_process, bias_variable);
+            bias_variable = read_query_value.inner[0].get_variable();
+
+            *dst = read_query_value;
+
+            let read_query = MemoryQuery {
+                timestamp: timestamp_to_use_for_read,
+                memory_page: precompile_call_params.input_page,
+                index: precompile_call_params.input_offset,
+                rw_flag: boolean_false,
+                is_ptr: boolean_false,
+                value: read_query_value,
+            };
+
+            let _ = memory_queue.push(cs, read_query, should_process);
+
+            precompile_call_params.input_offset = precompile_call_params
+                .input_offset
+                .add_no_overflow(cs, one_u32);
+        }
+
+        let [message_hash_as_u256, v_as_u256, r_as_u256, s_as_u256] = read_values;
+        let rec_id = v_as_u256.inner[0].to_le_bytes(cs)[0];
+
+        let (success, q_x, q_y) = secp256r1_recover_function_inner(
+            cs,
+            &rec_id,
+            &r_as_u256,
+            &s_as_u256,
+            &message_hash_as_u256,
+            precompile_call_params.enforce_low_s,
+            &base_params,
+            &scalar_params,
+        );
+
+        let success_as_u32 = unsafe { UInt32::from_variable_unchecked(success.get_variable()) };
+        let mut success_as_u256 = zero_u256;
+        success_as_u256.inner[0] = success_as_u32;
+
+        for value in [success_as_u256, q_x, q_y].into_iter() {
+            let write_query = MemoryQuery {
+                timestamp: timestamp_to_use_for_write,
+                memory_page: precompile_call_params.output_page,
+                index: precompile_call_params.output_offset,
+                rw_flag: boolean_true,
+                value,
+                is_ptr: boolean_false,
+            };
+
+            precompile_call_params.output_offset = precompile_call_params
+                .output_offset
+                .add_no_overflow(cs, one_u32);
+
+            let _ = memory_queue.push(cs, write_query, should_process);
+        }
+    }
+
+    requests_queue.enforce_consistency(cs);
+
+    // form the final state
+    let done = requests_queue.is_empty(cs);
+    structured_input.completion_flag = done;
+    structured_input.observable_output = PrecompileFunctionOutputData::placeholder(cs);
+
+    let final_memory_state = memory_queue.into_state();
+    let final_requets_state = requests_queue.into_state();
+
+    structured_input.observable_output.final_memory_state = QueueState::conditionally_select(
+        cs,
+        structured_input.completion_flag,
+        &final_memory_state,
+        &structured_input.observable_output.final_memory_state,
+    );
+
+    structured_input.hidden_fsm_output.log_queue_state = final_requets_state;
+    structured_input.hidden_fsm_output.memory_queue_state = final_memory_state;
+
+    // self-check
+    structured_input.hook_compare_witness(cs, &closed_form_input);
+
+    use boojum::cs::gates::PublicInputGate;
+
+    let compact_form =
+        ClosedFormInputCompactForm::from_full_form(cs, &structured_input, round_function);
+    let input_commitment = commit_variable_length_encodable_item(cs, &compact_form, round_function);
+    for el in input_commitment.iter() {
+        let gate = PublicInputGate::new(el.get_variable());
+        gate.add_to_cs(cs);
+    }
+
+    input_commitment
+}
+
+#[cfg(test)]
+mod test {
+    use boojum::field::goldilocks::GoldilocksField;
+    use boojum::gadgets::traits::allocatable::CSAllocatable;
+    use boojum::worker::Worker;
+
+    use super::*;
+
+    type F = GoldilocksField;
+    type P = GoldilocksField;
+
+    use boojum::config::DevCSConfig;
+
+    use boojum::cs::cs_builder::*;
+    use boojum::cs::cs_builder_reference::CsReferenceImplementationBuilder;
+    use boojum::cs::gates::*;
+    use boojum::cs::traits::gate::GatePlacementStrategy;
+    use boojum::cs::CSGeometry;
+    use boojum::cs::*;
+    use boojum::gadgets::tables::*;
+
+    #[test]
+    fn test_secp256r1_recovery() {
+        let geometry = CSGeometry {
+            num_columns_under_copy_permutation: 80,
+            num_witness_columns: 0,
+            num_constant_columns: 4,
+            max_allowed_constraint_degree: 8,
+        };
+
+        let max_variables = 1 << 26;
+        let max_trace_len = 1 << 20;
+
+        fn configure<
+            F: SmallField,
+            T: CsBuilderImpl<F, T>,
+            GC: GateConfigurationHolder<F>,
+            TB: StaticToolboxHolder,
+        >(
+            builder: CsBuilder<T, F, GC, TB>,
+        ) -> CsBuilder<T, F, impl GateConfigurationHolder<F>, impl StaticToolboxHolder> {
+            let builder = builder.allow_lookup(
+                LookupParameters::UseSpecializedColumnsWithTableIdAsConstant {
+                    width: 3,
+                    num_repetitions: 16,
+                    share_table_id: true,
+                },
+            );
+
+            let builder = ConstantsAllocatorGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = BooleanConstraintGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseSpecializedColumns {
+                    num_repetitions: 1,
+                    share_constants: false,
+                },
+            );
+            let builder = U8x4FMAGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ZeroCheckGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+                false,
+            );
+            let builder = FmaGateInBaseFieldWithoutConstant::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<32>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<16>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<8>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = DotProductGate::<4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = SelectionGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ParallelSelectionGate::<4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = PublicInputGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ReductionGate::<_, 4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = NopGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+
+            builder
+        }
+
+        let builder_impl =
+            CsReferenceImplementationBuilder::<F, P, DevCSConfig>::new(geometry, max_trace_len);
+        let builder = new_builder::<_, F>(builder_impl);
+
+        let builder = configure(builder);
+        let mut owned_cs = builder.build(max_variables);
+
+        // add tables
+        let table = create_xor8_table();
+        owned_cs.add_lookup_table::<Xor8Table, 3>(table);
+
+        seq_macro::seq!(C in 0..32 {
+            let table = create_secp256r1_fixed_base_mul_table::<F, 0, C>();
+            owned_cs.add_lookup_table::<Secp256r1FixedBaseMulTable<0, C>, 3>(table);
+            let table = create_secp256r1_fixed_base_mul_table::<F, 1, C>();
+            owned_cs.add_lookup_table::<Secp256r1FixedBaseMulTable<1, C>, 3>(table);
+            let table = create_secp256r1_fixed_base_mul_table::<F, 2, C>();
+            owned_cs.add_lookup_table::<Secp256r1FixedBaseMulTable<2, C>, 3>(table);
+            let table = create_secp256r1_fixed_base_mul_table::<F, 3, C>();
+            owned_cs.add_lookup_table::<Secp256r1FixedBaseMulTable<3, C>, 3>(table);
+            let table = create_secp256r1_fixed_base_mul_table::<F, 4, C>();
+            owned_cs.add_lookup_table::<Secp256r1FixedBaseMulTable<4, C>, 3>(table);
+            let table = create_secp256r1_fixed_base_mul_table::<F, 5, C>();
+            owned_cs.add_lookup_table::<Secp256r1FixedBaseMulTable<5, C>, 3>(table);
+            let table = create_secp256r1_fixed_base_mul_table::<F, 6, C>();
+            owned_cs.add_lookup_table::<Secp256r1FixedBaseMulTable<6, C>, 3>(table);
+            let table = create_secp256r1_fixed_base_mul_table::<F, 7, C>();
+            owned_cs.add_lookup_table::<Secp256r1FixedBaseMulTable<7, C>, 3>(table);
+        });
+
+        let table = create_byte_split_table::<F, 4>();
+        owned_cs.add_lookup_table::<ByteSplitTable<4>, 3>(table);
+
+        let cs = &mut owned_cs;
+
+        // same vector as for verification
+        let digest =
+            hex::decode("3fec5769b5cf4e310a7d150508e82fb8e3eda1c2c94c61492d3bd8aea99e06c9")
+                .unwrap();
+        let pk_x = hex::decode("31a80482dadf89de6302b1988c82c29544c9c07bb910596158f6062517eb089a")
+            .unwrap();
+        let pk_y = hex::decode("2f54c9a0f348752950094d3228d3b940258c75fe2a413cb70baa21dc2e352fc5")
+            .unwrap();
+        let r = hex::decode("e22466e928fdccef0de49e3503d2657d00494a00e764fd437bdafa05f5922b1f")
+            .unwrap();
+        let s = hex::decode("bbb77c6817ccf50748419477e843d5bac67e6a70e97dde5a57e0c983b777e1ad")
+            .unwrap();
+
+        let scalar_params = Arc::new(secp256r1_scalar_field_params());
+        let base_params = Arc::new(secp256r1_base_field_params());
+
+        let pk_x_u256 = U256::from_big_endian(&pk_x);
+        let pk_y_u256 = U256::from_big_endian(&pk_y);
+
+        let r = UInt256::allocate(cs, U256::from_big_endian(&r));
+        let s = UInt256::allocate(cs, U256::from_big_endian(&s));
+        let digest = UInt256::allocate(cs, U256::from_big_endian(&digest));
+
+        // parity of R.y is not part of the vector, so both candidates are recovered and
+        // exactly one of them must be the signer's key
+        let boolean_false = Boolean::allocated_constant(cs, false);
+        let mut num_matches = 0;
+        for parity in [0u8, 1u8] {
+            let rec_id = UInt8::allocate_checked(cs, parity);
+            let (no_error, q_x, q_y) = secp256r1_recover_function_inner(
+                cs,
+                &rec_id,
+                &r,
+                &s,
+                &digest,
+                boolean_false,
+                &base_params,
+                &scalar_params,
+            );
+
+            assert!(no_error.witness_hook(&*cs)().unwrap() == true);
+            let q_x = q_x.witness_hook(&*cs)().unwrap();
+            let q_y = q_y.witness_hook(&*cs)().unwrap();
+            if q_x == pk_x_u256 && q_y == pk_y_u256 {
+                num_matches += 1;
+            }
+        }
+        assert_eq!(num_matches, 1);
+
+        // s of this vector is high, so it's rejected in the malleability-strict mode
+        let boolean_true = Boolean::allocated_constant(cs, true);
+        let rec_id = UInt8::allocate_checked(cs, 0);
+        let (no_error, _q_x, _q_y) = secp256r1_recover_function_inner(
+            cs,
+            &rec_id,
+            &r,
+            &s,
+            &digest,
+            boolean_true,
+            &base_params,
+            &scalar_params,
+        );
+        assert!(no_error.witness_hook(&*cs)().unwrap() == false);
+
+        // x = r + n is out of the base field for this r
+        let rec_id = UInt8::allocate_checked(cs, 2);
+        let (no_error, q_x, q_y) = secp256r1_recover_function_inner(
+            cs,
+            &rec_id,
+            &r,
+            &s,
+            &digest,
+            boolean_false,
+            &base_params,
+            &scalar_params,
+        );
+        assert!(no_error.witness_hook(&*cs)().unwrap() == false);
+        assert!(q_x.witness_hook(&*cs)().unwrap() == U256::zero());
+        assert!(q_y.witness_hook(&*cs)().unwrap() == U256::zero());
+
+        dbg!(cs.next_available_row());
+
+        cs.pad_and_shrink();
+
+        let mut cs = owned_cs.into_assembly::<std::alloc::Global>();
+        cs.print_gate_stats();
+        let worker = Worker::new();
+        assert!(cs.check_if_satisfied(&worker));
+    }
+}
@@ -4,6 +4,7 @@ use super::*;
 
 use crate::base_structures::precompile_input_outputs::*;
 use crate::base_structures::vm_state::*;
+use crate::keccak256_round_function::buffer::ByteBuffer;
 use boojum::cs::Variable;
 use boojum::gadgets::queue::*;
 use boojum::gadgets::traits::allocatable::CSAllocatable;
@@ -18,17 +19,25 @@ use boojum::gadgets::traits::selectable::Selectable;
 use boojum::gadgets::traits::witnessable::WitnessHookable;
 use boojum::serde_utils::BigArraySerde;
 
+pub const MEMORY_READ_QUERIES_PER_CYCLE: usize = 2;
+// byte granular input may be unaligned, so it takes one more read to get a full block
+pub const UNALIGNED_MEMORY_READ_QUERIES_PER_CYCLE: usize = 3;
+pub const SHA256_PRECOMPILE_BUFFER_SIZE: usize = UNALIGNED_MEMORY_READ_QUERIES_PER_CYCLE * 32;
+
 #[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
 #[derivative(Clone, Copy, Debug)]
 #[DerivePrettyComparison("true")]
 pub struct Sha256RoundFunctionFSM<F: SmallField> {
     pub read_precompile_call: Boolean<F>,
     pub read_words_for_round: Boolean<F>,
+    pub read_unaligned_words_for_round: Boolean<F>,
+    pub padding_round: Boolean<F>,
     pub completed: Boolean<F>,
     pub sha256_inner_state: [UInt32<F>; 8],
     pub timestamp_to_use_for_read: UInt32<F>,
     pub timestamp_to_use_for_write: UInt32<F>,
     pub precompile_call_params: Sha256PrecompileCallParams<F>,
+    pub buffer: ByteBuffer<F, SHA256_PRECOMPILE_BUFFER_SIZE>,
 }
 
 impl<F: SmallField> CSPlaceholder<F> for Sha256RoundFunctionFSM<F> {
@@ -38,11 +47,14 @@ impl<F: SmallField> CSPlaceholder<F> for Sha256RoundFunctionFSM<F> {
         Self {
             read_precompile_call: boolean_false,
             read_words_for_round: boolean_false,
+            read_unaligned_words_for_round: boolean_false,
+            padding_round: boolean_false,
             completed: boolean_false,
             sha256_inner_state: boojum::gadgets::sha256::ivs_as_uint32(cs),
             timestamp_to_use_for_read: zero_u32,
             timestamp_to_use_for_write: zero_u32,
             precompile_call_params: Sha256PrecompileCallParams::<F>::placeholder(cs),
+            buffer: ByteBuffer::<F, SHA256_PRECOMPILE_BUFFER_SIZE>::placeholder(cs),
         }
     }
 }
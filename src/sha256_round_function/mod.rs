@@ -33,11 +33,37 @@ use boojum::gadgets::traits::encodable::CircuitVarLengthEncodable;
 use boojum::gadgets::traits::round_function::CircuitRoundFunction;
 use boojum::gadgets::u160::UInt160;
 use boojum::gadgets::u8::UInt8;
+use boojum::serde_utils::BigArraySerde;
 use std::sync::{Arc, RwLock};
 
+use crate::keccak256_round_function::buffer::ByteBuffer;
+use crate::keccak256_round_function::trivial_mapping_function;
+
+use crate::base_structures::precompile_input_outputs::*;
+use crate::demux_log_queue::DemuxOutput;
+use crate::precompile_registry::PrecompileCircuit;
+use crate::scheduler::auxiliary::BaseLayerCircuitType;
+use zkevm_opcode_defs::ethereum_types::H160;
+
 pub mod input;
+
 use self::input::*;
 
+pub struct Sha256PrecompileCircuit;
+
+impl PrecompileCircuit for Sha256PrecompileCircuit {
+    const NAME: &'static str = "sha256";
+    const DEMUX_OUTPUT: DemuxOutput = DemuxOutput::Sha256;
+    const CIRCUIT_TYPE: BaseLayerCircuitType = BaseLayerCircuitType::Sha256Precompile;
+
+    type InputData<F: SmallField> = PrecompileFunctionInputData<F>;
+    type OutputData<F: SmallField> = PrecompileFunctionOutputData<F>;
+
+    fn formal_address() -> H160 {
+        *zkevm_opcode_defs::system_params::SHA256_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS
+    }
+}
+
 #[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
 #[derivative(Clone, Copy, Debug)]
 // #[DerivePrettyComparison("true")]
@@ -47,23 +73,40 @@ pub struct Sha256PrecompileCallParams<F: SmallField> {
     pub output_page: UInt32<F>,
     pub output_offset: UInt32<F>,
     pub num_rounds: UInt32<F>,
+    pub byte_granular_input: Boolean<F>,
+    pub input_memory_byte_offset: UInt32<F>,
+    pub input_memory_byte_length: UInt32<F>,
+    pub needs_extra_padding_round: Boolean<F>,
+    pub extra_padding_round_starts_with_marker: Boolean<F>,
+    pub message_bit_length_be_bytes: [UInt8<F>; 8],
 }
 
 impl<F: SmallField> CSPlaceholder<F> for Sha256PrecompileCallParams<F> {
     fn placeholder<CS: ConstraintSystem<F>>(cs: &mut CS) -> Self {
         let zero_u32 = UInt32::zero(cs);
+        let zero_u8 = UInt8::zero(cs);
+        let boolean_false = Boolean::allocated_constant(cs, false);
         Self {
             input_page: zero_u32,
             input_offset: zero_u32,
             output_page: zero_u32,
             output_offset: zero_u32,
             num_rounds: zero_u32,
+            byte_granular_input: boolean_false,
+            input_memory_byte_offset: zero_u32,
+            input_memory_byte_length: zero_u32,
+            needs_extra_padding_round: boolean_false,
+            extra_padding_round_starts_with_marker: boolean_false,
+            message_bit_length_be_bytes: [zero_u8; 8],
         }
     }
 }
 
 impl<F: SmallField> Sha256PrecompileCallParams<F> {
-    pub fn from_encoding<CS: ConstraintSystem<F>>(_cs: &mut CS, encoding: UInt256<F>) -> Self {
+    // from PrecompileCallABI. If the high word of the interpreted data is zero, then the input is
+    // given by word offset and the number of already padded 64 byte blocks. Otherwise the input is
+    // an arbitrary byte slice given by byte offset and byte length, and the circuit does the padding
+    pub fn from_encoding<CS: ConstraintSystem<F>>(cs: &mut CS, encoding: UInt256<F>) -> Self {
         let input_offset = encoding.inner[0];
         let output_offset = encoding.inner[2];
         let input_page = encoding.inner[4];
@@ -71,19 +114,56 @@ impl<F: SmallField> Sha256PrecompileCallParams<F> {
 
         let num_rounds = encoding.inner[6];
 
+        let byte_granular_input = encoding.inner[7].is_zero(cs).negated(cs);
+        let input_memory_byte_offset = encoding.inner[0];
+        let input_memory_byte_length = encoding.inner[1];
+
+        // padding is 0x80, zeroes and 8 bytes of message length in bits, so if there are less than 9 free
+        // bytes in the last block we need one more block. We also need it if the message fills the last block
+        // in full, but not for empty message
+        let (_, rem) = input_memory_byte_length.div_by_constant(cs, SHA256_BLOCK_SIZE_BYTES as u32);
+        let rem_is_zero = rem.is_zero(cs);
+        let message_is_empty = input_memory_byte_length.is_zero(cs);
+        let message_is_not_empty = message_is_empty.negated(cs);
+        let last_block_is_full = Boolean::multi_and(cs, &[rem_is_zero, message_is_not_empty]);
+        let max_rem_for_single_padding_block =
+            UInt32::allocated_constant(cs, (SHA256_BLOCK_SIZE_BYTES - 9) as u32);
+        let (_, no_space_for_padding) = max_rem_for_single_padding_block.overflowing_sub(cs, rem);
+        let needs_extra_padding_round =
+            Boolean::multi_or(cs, &[last_block_is_full, no_space_for_padding]);
+        let needs_extra_padding_round =
+            Boolean::multi_and(cs, &[needs_extra_padding_round, byte_granular_input]);
+
+        // message length in bits as BE u64
+        let (bit_length_high, bit_length_low) =
+            input_memory_byte_length.div_by_constant(cs, 1u32 << 29);
+        let bit_length_low = Num::allocated_constant(cs, F::from_u64_unchecked(8))
+            .mul(cs, &bit_length_low.into_num());
+        let bit_length_low =
+            unsafe { UInt32::from_variable_unchecked(bit_length_low.get_variable()) };
+        let mut message_bit_length_be_bytes = [UInt8::zero(cs); 8];
+        message_bit_length_be_bytes[..4].copy_from_slice(&bit_length_high.to_be_bytes(cs));
+        message_bit_length_be_bytes[4..].copy_from_slice(&bit_length_low.to_be_bytes(cs));
+
         let new = Self {
             input_page,
             input_offset,
             output_page,
             output_offset,
             num_rounds,
+            byte_granular_input,
+            input_memory_byte_offset,
+            input_memory_byte_length,
+            needs_extra_padding_round,
+            extra_padding_round_starts_with_marker: last_block_is_full,
+            message_bit_length_be_bytes,
         };
 
         new
     }
 }
 
-pub const MEMORY_READ_QUERIES_PER_CYCLE: usize = 2;
+pub const SHA256_BLOCK_SIZE_BYTES: usize = 64;
 
 pub fn sha256_precompile_inner<
     F: SmallField,
@@ -114,8 +194,13 @@ where
 
     let boolean_false = Boolean::allocated_constant(cs, false);
     let boolean_true = Boolean::allocated_constant(cs, true);
+    let zero_u8 = UInt8::zero(cs);
     let zero_u32 = UInt32::zero(cs);
     let zero_u256 = UInt256::zero(cs);
+    let one_num = Num::allocated_constant(cs, F::ONE);
+    let padding_marker = UInt8::allocated_constant(cs, 0x80);
+
+    let empty_buffer = ByteBuffer::<F, SHA256_PRECOMPILE_BUFFER_SIZE>::placeholder(cs);
 
     // we can have a degenerate case when queue is empty, but it's a first circuit in the queue,
     // so we taken default FSM state that has state.read_precompile_call = true;
@@ -135,6 +220,9 @@ where
     state.read_words_for_round = state
         .read_words_for_round
         .mask_negated(cs, can_finish_immediatelly);
+    state.read_unaligned_words_for_round = state
+        .read_unaligned_words_for_round
+        .mask_negated(cs, can_finish_immediatelly);
     state.completed = Boolean::multi_or(cs, &[state.completed, can_finish_immediatelly]);
 
     if crate::config::CIRCUIT_VERSOBE {
@@ -202,56 +290,190 @@ where
         );
 
         let reset_buffer = Boolean::multi_or(cs, &[state.read_precompile_call, state.completed]);
-        state.read_words_for_round = Boolean::multi_or(
+        // word aligned and already padded input is read directly, and only byte granular input
+        // goes over the buffer and gets padded in circuit
+        let start_byte_granular_call = Boolean::multi_and(
             cs,
-            &[state.read_precompile_call, state.read_words_for_round],
+            &[
+                state.read_precompile_call,
+                state.precompile_call_params.byte_granular_input,
+            ],
+        );
+        let word_aligned_input = state.precompile_call_params.byte_granular_input.negated(cs);
+        let start_word_aligned_call =
+            Boolean::multi_and(cs, &[state.read_precompile_call, word_aligned_input]);
+        state.read_words_for_round =
+            Boolean::multi_or(cs, &[start_word_aligned_call, state.read_words_for_round]);
+        state.read_unaligned_words_for_round = Boolean::multi_or(
+            cs,
+            &[
+                start_byte_granular_call,
+                state.read_unaligned_words_for_round,
+            ],
         );
         state.read_precompile_call = boolean_false;
 
         // ---------------------------------
         // Now perform few memory queries to read content
 
+        state.buffer = ByteBuffer::<F, SHA256_PRECOMPILE_BUFFER_SIZE>::conditionally_select(
+            cs,
+            reset_buffer,
+            &empty_buffer,
+            &state.buffer,
+        );
+
         let zero_rounds_left = state.precompile_call_params.num_rounds.is_zero(cs);
+        let have_rounds_left = zero_rounds_left.negated(cs);
+        let should_read_words =
+            Boolean::multi_and(cs, &[have_rounds_left, state.read_words_for_round]);
+
+        let no_more_bytes = state
+            .precompile_call_params
+            .input_memory_byte_length
+            .is_zero(cs);
+        let have_leftover_bytes = no_more_bytes.negated(cs);
+        let should_read_in_general = Boolean::multi_and(
+            cs,
+            &[have_leftover_bytes, state.read_unaligned_words_for_round],
+        );
+        let should_read_in_general =
+            Boolean::multi_or(cs, &[should_read_in_general, should_read_words]);
+
+        let mapping_function = |cs: &mut CS,
+                                bytes_to_consume: UInt8<F>,
+                                current_fill_factor: UInt8<F>,
+                                _unused: [(); 32]| {
+            trivial_mapping_function::<F, CS, 32, SHA256_PRECOMPILE_BUFFER_SIZE>(
+                cs,
+                &bytes_to_consume,
+                &current_fill_factor,
+                _unused,
+            )
+        };
+
+        let mut aligned_block = [zero_u8; SHA256_BLOCK_SIZE_BYTES];
+        let mut bias_variable = should_read_in_general.get_variable();
+        // word aligned input needs 2 reads for a block. Same as for keccak256 we always try to read
+        // unaligned input into the buffer, and it's enough to have 3 reads to fill at least one full block
+        for idx in 0..UNALIGNED_MEMORY_READ_QUERIES_PER_CYCLE {
+            let (aligned_memory_index, unalignment) = state
+                .precompile_call_params
+                .input_memory_byte_offset
+                .div_by_constant(cs, 32);
+            let at_most_meaningful_bytes_in_query = UInt32::allocated_constant(cs, 32)
+                .into_num()
+                .sub(cs, &unalignment.into_num());
+            let at_most_meaningful_bytes_in_query = unsafe {
+                UInt32::from_variable_unchecked(at_most_meaningful_bytes_in_query.get_variable())
+            };
+            let (_, uf) = state
+                .precompile_call_params
+                .input_memory_byte_length
+                .overflowing_sub(cs, at_most_meaningful_bytes_in_query);
+            let meaningful_bytes_in_query = UInt32::conditionally_select(
+                cs,
+                uf,
+                &state.precompile_call_params.input_memory_byte_length,
+                &at_most_meaningful_bytes_in_query,
+            );
+
+            let nothing_to_read = meaningful_bytes_in_query.is_zero(cs);
+            let have_something_to_read = nothing_to_read.negated(cs);
+            let bytes_to_fill =
+                unsafe { UInt8::from_variable_unchecked(meaningful_bytes_in_query.get_variable()) };
+            let enough_buffer_space = state.buffer.can_fill_bytes(cs, bytes_to_fill);
+            let should_read_unaligned = Boolean::multi_and(
+                cs,
+                &[
+                    have_something_to_read,
+                    enough_buffer_space,
+                    state.read_unaligned_words_for_round,
+                ],
+            );
+
+            let should_read_aligned = if idx < MEMORY_READ_QUERIES_PER_CYCLE {
+                should_read_words
+            } else {
+                boolean_false
+            };
+            let should_read = Boolean::multi_or(cs, &[should_read_aligned, should_read_unaligned]);
 
-        let mut memory_queries_as_u32_words = [zero_u32; 8 * MEMORY_READ_QUERIES_PER_CYCLE];
-        let should_read = zero_rounds_left.negated(cs);
-        let mut bias_variable = should_read.get_variable();
-        for dst in memory_queries_as_u32_words.array_chunks_mut::<8>() {
             let read_query_value =
                 memory_read_witness.conditionally_allocate_biased(cs, should_read, bias_variable);
             bias_variable = read_query_value.inner[0].get_variable();
 
+            let index = UInt32::conditionally_select(
+                cs,
+                state.read_words_for_round,
+                &state.precompile_call_params.input_offset,
+                &aligned_memory_index,
+            );
+
             let read_query = MemoryQuery {
                 timestamp: state.timestamp_to_use_for_read,
                 memory_page: state.precompile_call_params.input_page,
-                index: state.precompile_call_params.input_offset,
+                index,
                 rw_flag: boolean_false,
                 is_ptr: boolean_false,
                 value: read_query_value,
             };
 
-            let may_be_new_offset = unsafe {
-                state
-                    .precompile_call_params
-                    .input_offset
-                    .increment_unchecked(cs)
-            };
-            state.precompile_call_params.input_offset = UInt32::conditionally_select(
-                cs,
-                state.read_words_for_round,
-                &may_be_new_offset,
-                &state.precompile_call_params.input_offset,
-            );
-
             // perform read
             memory_queue.push(cs, read_query, should_read);
 
-            // we need to change endianess. Memory is BE, and each of 4 byte chunks should be interpreted as BE u32 for sha256
             let be_bytes = read_query_value.to_be_bytes(cs);
-            for (dst, src) in dst.iter_mut().zip(be_bytes.array_chunks::<4>()) {
-                let as_u32 = UInt32::from_be_bytes(cs, *src);
-                *dst = as_u32;
+
+            // word aligned input goes directly into the block
+            if idx < MEMORY_READ_QUERIES_PER_CYCLE {
+                aligned_block[(idx * 32)..((idx + 1) * 32)].copy_from_slice(&be_bytes);
+
+                let may_be_new_offset = unsafe {
+                    state
+                        .precompile_call_params
+                        .input_offset
+                        .increment_unchecked(cs)
+                };
+                state.precompile_call_params.input_offset = UInt32::conditionally_select(
+                    cs,
+                    state.read_words_for_round,
+                    &may_be_new_offset,
+                    &state.precompile_call_params.input_offset,
+                );
             }
+
+            // and unaligned one into the buffer
+            let may_be_new_input_memory_byte_offset = state
+                .precompile_call_params
+                .input_memory_byte_offset
+                .add_no_overflow(cs, meaningful_bytes_in_query);
+            let may_be_new_input_memory_byte_length = state
+                .precompile_call_params
+                .input_memory_byte_length
+                .sub_no_overflow(cs, meaningful_bytes_in_query);
+
+            state.precompile_call_params.input_memory_byte_offset = UInt32::conditionally_select(
+                cs,
+                should_read_unaligned,
+                &may_be_new_input_memory_byte_offset,
+                &state.precompile_call_params.input_memory_byte_offset,
+            );
+            state.precompile_call_params.input_memory_byte_length = UInt32::conditionally_select(
+                cs,
+                should_read_unaligned,
+                &may_be_new_input_memory_byte_length,
+                &state.precompile_call_params.input_memory_byte_length,
+            );
+
+            // update if we do not read
+            let bytes_to_fill = bytes_to_fill.mask(cs, should_read_unaligned);
+
+            // fill the buffer
+            let offset = unsafe { UInt8::from_variable_unchecked(unalignment.get_variable()) };
+
+            state
+                .buffer
+                .fill_with_bytes(cs, &be_bytes, offset, bytes_to_fill, mapping_function);
         }
 
         let may_be_new_num_rounds = unsafe {
@@ -267,6 +489,69 @@ where
             &state.precompile_call_params.num_rounds,
         );
 
+        // now take a block from the buffer and pad it if needed
+        let zero_bytes_left = state
+            .precompile_call_params
+            .input_memory_byte_length
+            .is_zero(cs);
+
+        let currently_filled = state.buffer.filled;
+        let mut block = state
+            .buffer
+            .consume::<CS, SHA256_BLOCK_SIZE_BYTES>(cs, boolean_true);
+        let buffer_now_empty = state.buffer.filled.is_zero(cs);
+        let last_data_block = Boolean::multi_and(
+            cs,
+            &[
+                zero_bytes_left,
+                buffer_now_empty,
+                state.read_unaligned_words_for_round,
+            ],
+        );
+        let no_extra_padding_round_required = state
+            .precompile_call_params
+            .needs_extra_padding_round
+            .negated(cs);
+
+        // marker goes right after the data if there is a space for it
+        let mut tmp = currently_filled.into_num();
+        for dst in block.iter_mut() {
+            let pad_this_byte = tmp.is_zero(cs);
+            let pad_this_byte = Boolean::multi_and(cs, &[last_data_block, pad_this_byte]);
+            *dst = UInt8::conditionally_select(cs, pad_this_byte, &padding_marker, &*dst);
+            tmp = tmp.sub(cs, &one_num);
+        }
+        // and message length goes to the end if there is no extra round
+        let put_message_length =
+            Boolean::multi_and(cs, &[last_data_block, no_extra_padding_round_required]);
+        for (dst, src) in block[(SHA256_BLOCK_SIZE_BYTES - 8)..].iter_mut().zip(
+            state
+                .precompile_call_params
+                .message_bit_length_be_bytes
+                .iter(),
+        ) {
+            *dst = UInt8::conditionally_select(cs, put_message_length, src, &*dst);
+        }
+
+        let mut padding_block = [zero_u8; SHA256_BLOCK_SIZE_BYTES];
+        padding_block[0] = padding_marker.mask(
+            cs,
+            state
+                .precompile_call_params
+                .extra_padding_round_starts_with_marker,
+        );
+        padding_block[(SHA256_BLOCK_SIZE_BYTES - 8)..]
+            .copy_from_slice(&state.precompile_call_params.message_bit_length_be_bytes);
+        let block = UInt8::<F>::parallel_select(cs, state.padding_round, &padding_block, &block);
+        let block =
+            UInt8::<F>::parallel_select(cs, state.read_words_for_round, &aligned_block, &block);
+
+        // we need to change endianess. Memory is BE, and each of 4 byte chunks should be interpreted as BE u32 for sha256
+        let mut block_as_u32_words = [zero_u32; SHA256_BLOCK_SIZE_BYTES / 4];
+        for (dst, src) in block_as_u32_words.iter_mut().zip(block.array_chunks::<4>()) {
+            *dst = UInt32::from_be_bytes(cs, *src);
+        }
+
         // absorb
         let sha256_empty_internal_state = sha256::ivs_as_uint32(cs);
 
@@ -280,12 +565,19 @@ where
         let sha256_output = sha256::round_function::round_function_over_uint32(
             cs,
             &mut current_sha256_state,
-            &memory_queries_as_u32_words,
+            &block_as_u32_words,
         );
         state.sha256_inner_state = current_sha256_state;
 
         let no_rounds_left = state.precompile_call_params.num_rounds.is_zero(cs);
-        let write_result = Boolean::multi_and(cs, &[state.read_words_for_round, no_rounds_left]);
+        let finished_with_words =
+            Boolean::multi_and(cs, &[state.read_words_for_round, no_rounds_left]);
+        let finished_with_data =
+            Boolean::multi_and(cs, &[last_data_block, no_extra_padding_round_required]);
+        let write_result = Boolean::multi_or(
+            cs,
+            &[finished_with_words, finished_with_data, state.padding_round],
+        );
 
         let mut write_word = zero_u256;
         // some endianess magic
@@ -320,8 +612,24 @@ where
 
         state.read_precompile_call = process_next;
         state.completed = Boolean::multi_or(cs, &[nothing_left, state.completed]);
-        let t = Boolean::multi_or(cs, &[state.read_precompile_call, state.completed]);
-        state.read_words_for_round = t.negated(cs);
+        state.padding_round = Boolean::multi_and(
+            cs,
+            &[
+                last_data_block,
+                state.precompile_call_params.needs_extra_padding_round,
+            ],
+        );
+        let t = Boolean::multi_or(
+            cs,
+            &[
+                state.read_precompile_call,
+                state.padding_round,
+                state.completed,
+            ],
+        );
+        state.read_words_for_round = state.read_words_for_round.mask_negated(cs, t);
+        state.read_unaligned_words_for_round =
+            state.read_unaligned_words_for_round.mask_negated(cs, t);
 
         if crate::config::CIRCUIT_VERSOBE {
             dbg!(state.witness_hook(cs)());
@@ -462,3 +770,364 @@ where
 
     input_commitment
 }
+
+#[cfg(test)]
+mod test {
+    use boojum::algebraic_props::poseidon2_parameters::*;
+    use boojum::config::DevCSConfig;
+    use boojum::cs::cs_builder::*;
+    use boojum::cs::gates::*;
+    use boojum::cs::implementations::reference_cs::CSReferenceImplementation;
+    use boojum::cs::traits::gate::*;
+    use boojum::cs::*;
+    use boojum::field::goldilocks::GoldilocksField;
+    use boojum::gadgets::tables::*;
+    use boojum::implementations::poseidon2::Poseidon2Goldilocks;
+    use boojum::worker::Worker;
+    use zkevm_opcode_defs::PrecompileCallABI;
+
+    use super::*;
+
+    type F = GoldilocksField;
+    type P = GoldilocksField;
+    type R = Poseidon2Goldilocks;
+
+    fn create_test_cs() -> CSReferenceImplementation<
+        GoldilocksField,
+        GoldilocksField,
+        DevCSConfig,
+        impl GateConfigurationHolder<GoldilocksField>,
+        impl StaticToolboxHolder,
+    > {
+        let geometry = CSGeometry {
+            num_columns_under_copy_permutation: 100,
+            num_witness_columns: 0,
+            num_constant_columns: 8,
+            max_allowed_constraint_degree: 4,
+        };
+
+        fn configure<
+            T: CsBuilderImpl<F, T>,
+            GC: GateConfigurationHolder<F>,
+            TB: StaticToolboxHolder,
+        >(
+            builder: CsBuilder<T, F, GC, TB>,
+        ) -> CsBuilder<T, F, impl GateConfigurationHolder<F>, impl StaticToolboxHolder> {
+            let builder = builder.allow_lookup(
+                LookupParameters::UseSpecializedColumnsWithTableIdAsConstant {
+                    width: 4,
+                    num_repetitions: 8,
+                    share_table_id: true,
+                },
+            );
+            let builder = ConstantsAllocatorGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = FmaGateInBaseFieldWithoutConstant::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ReductionGate::<F, 4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = BooleanConstraintGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<32>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<16>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<8>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = SelectionGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ZeroCheckGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+                false,
+            );
+            let builder = DotProductGate::<4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = MatrixMultiplicationGate::<F, 12, Poseidon2GoldilocksExternalMatrix>::configure_builder(builder,GatePlacementStrategy::UseGeneralPurposeColumns);
+            let builder = MatrixMultiplicationGate::<F, 12, Poseidon2GoldilocksInnerMatrix>::configure_builder(builder,GatePlacementStrategy::UseGeneralPurposeColumns);
+            let builder = NopGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+
+            builder
+        }
+
+        use boojum::cs::cs_builder_reference::CsReferenceImplementationBuilder;
+
+        let builder_impl =
+            CsReferenceImplementationBuilder::<F, P, DevCSConfig>::new(geometry, 1 << 20);
+        let builder = new_builder::<_, F>(builder_impl);
+
+        let builder = configure(builder);
+        let mut owned_cs = builder.build(1 << 26);
+
+        // add tables for sha256
+        let table = create_maj4_table();
+        owned_cs.add_lookup_table::<Maj4Table, 4>(table);
+
+        let table = create_tri_xor_table();
+        owned_cs.add_lookup_table::<TriXor4Table, 4>(table);
+
+        let table = create_ch4_table();
+        owned_cs.add_lookup_table::<Ch4Table, 4>(table);
+
+        let table = create_4bit_chunk_split_table::<F, 1>();
+        owned_cs.add_lookup_table::<chunk4bits::Split4BitChunkTable<1>, 4>(table);
+        let table = create_4bit_chunk_split_table::<F, 2>();
+        owned_cs.add_lookup_table::<chunk4bits::Split4BitChunkTable<2>, 4>(table);
+
+        owned_cs
+    }
+
+    fn bytes_to_u256_words(input: Vec<u8>, unalignement: usize) -> Vec<U256> {
+        let mut result = vec![];
+        let mut it = std::iter::repeat(0xffu8)
+            .take(unalignement)
+            .chain(input.into_iter());
+        'outer: loop {
+            let mut done = false;
+            let mut buffer = [0u8; 32];
+            for (idx, dst) in buffer.iter_mut().enumerate() {
+                if let Some(src) = it.next() {
+                    *dst = src;
+                } else {
+                    done = true;
+                    if idx == 0 {
+                        break 'outer;
+                    }
+                    break;
+                }
+            }
+            let el = U256::from_big_endian(&buffer);
+            result.push(el);
+            if done {
+                break 'outer;
+            }
+        }
+
+        result
+    }
+
+    // runs the single call and returns the written result and the number of memory reads
+    fn run_sha256_call(
+        precompile_abi: PrecompileCallABI,
+        input_witness: Vec<U256>,
+        limit: usize,
+    ) -> ([u8; 32], usize) {
+        let mut owned_cs = create_test_cs();
+        let cs = &mut owned_cs;
+        let mut memory_queue = MemoryQueue::<F, R>::empty(cs);
+
+        let encoded_precompile_abi = precompile_abi.to_u256();
+        let boolean_true = Boolean::allocated_constant(cs, true);
+
+        let mut precompile_calls_queue = StorageLogQueue::<F, R>::empty(cs);
+        let el = LogQueryWitness {
+            address:
+                *zkevm_opcode_defs::system_params::SHA256_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS,
+            key: encoded_precompile_abi,
+            read_value: U256::zero(),
+            written_value: U256::zero(),
+            aux_byte: PRECOMPILE_AUX_BYTE,
+            rw_flag: true,
+            rollback: false,
+            is_service: false,
+            shard_id: 0,
+            tx_number_in_block: 0,
+            timestamp: 0,
+        };
+        let el = LogQuery::allocate(cs, el);
+        precompile_calls_queue.push(cs, el, boolean_true);
+
+        let mut state = Sha256RoundFunctionFSM::placeholder_witness();
+        state.read_precompile_call = true;
+        state.timestamp_to_use_for_read = 1;
+        state.timestamp_to_use_for_write = 2;
+
+        let state = Sha256RoundFunctionFSM::allocate(cs, state);
+        let round_function = Poseidon2Goldilocks;
+
+        let memory_read_witness = ConditionalWitnessAllocator::<F, UInt256<F>> {
+            witness_source: std::sync::Arc::new(std::sync::RwLock::new(input_witness.into())),
+        };
+
+        let new_state = sha256_precompile_inner(
+            cs,
+            &mut memory_queue,
+            &mut precompile_calls_queue,
+            memory_read_witness,
+            state,
+            &round_function,
+            limit,
+        );
+
+        dbg!(new_state.witness_hook(cs)().unwrap());
+
+        drop(cs);
+
+        let elements = memory_queue.witness.elements.read().unwrap();
+        let num_reads = elements.iter().filter(|el| el.0.rw_flag == false).count();
+        let output = elements.back().unwrap().clone();
+        drop(elements);
+        let mut buffer = [0u8; 32];
+        assert!(output.0.rw_flag);
+        output.0.value.to_big_endian(&mut buffer);
+
+        let _ = owned_cs.pad_and_shrink();
+        let mut assembly = owned_cs.into_assembly::<std::alloc::Global>();
+        let worker = Worker::new();
+        let is_satisfied = assembly.check_if_satisfied(&worker);
+        assert!(is_satisfied);
+
+        (buffer, num_reads)
+    }
+
+    fn test_for_length_and_unalignment(length: usize, unalignement: usize) {
+        use rand_new::{Rng, SeedableRng};
+        let mut rng = rand_new::rngs::StdRng::from_seed([1u8; 32]);
+        let input: Vec<u8> = (0..length).map(|_| rng.gen()).collect();
+        dbg!(hex::encode(&input));
+        let input_witness = bytes_to_u256_words(input.clone(), unalignement);
+
+        use boojum::sha2::Digest;
+        let reference: [u8; 32] = boojum::sha2::Sha256::digest(&input)
+            .as_slice()
+            .try_into()
+            .unwrap();
+
+        // non-zero high word of the interpreted data selects byte granular input
+        let precompile_abi = PrecompileCallABI {
+            input_memory_offset: unalignement as u32,
+            input_memory_length: length as u32,
+            output_memory_offset: 0,
+            output_memory_length: 1,
+            memory_page_to_read: 123,
+            memory_page_to_write: 456,
+            precompile_interpreted_data: 1u64 << 32,
+        };
+
+        let (buffer, _) = run_sha256_call(precompile_abi, input_witness, 5);
+
+        dbg!(hex::encode(&reference));
+        dbg!(hex::encode(&buffer));
+
+        assert_eq!(buffer, reference);
+    }
+
+    fn test_for_padded_input(length: usize) {
+        use rand_new::{Rng, SeedableRng};
+        let mut rng = rand_new::rngs::StdRng::from_seed([2u8; 32]);
+        let input: Vec<u8> = (0..length).map(|_| rng.gen()).collect();
+
+        use boojum::sha2::Digest;
+        let reference: [u8; 32] = boojum::sha2::Sha256::digest(&input)
+            .as_slice()
+            .try_into()
+            .unwrap();
+
+        // caller does the padding
+        let mut padded = input.clone();
+        padded.push(0x80);
+        while padded.len() % SHA256_BLOCK_SIZE_BYTES != SHA256_BLOCK_SIZE_BYTES - 8 {
+            padded.push(0);
+        }
+        padded.extend(((length as u64) * 8).to_be_bytes());
+        let num_rounds = padded.len() / SHA256_BLOCK_SIZE_BYTES;
+        let input_witness = bytes_to_u256_words(padded, 0);
+
+        // word offset and the number of rounds, with zero high word of the interpreted data
+        let precompile_abi = PrecompileCallABI {
+            input_memory_offset: 7,
+            input_memory_length: 0,
+            output_memory_offset: 0,
+            output_memory_length: 1,
+            memory_page_to_read: 123,
+            memory_page_to_write: 456,
+            precompile_interpreted_data: num_rounds as u64,
+        };
+
+        let (buffer, num_reads) = run_sha256_call(precompile_abi, input_witness, num_rounds);
+
+        assert_eq!(buffer, reference);
+        assert_eq!(num_reads, num_rounds * MEMORY_READ_QUERIES_PER_CYCLE);
+    }
+
+    #[test]
+    fn sha256_empty_input() {
+        test_for_length_and_unalignment(0, 0);
+    }
+
+    #[test]
+    fn sha256_aligned_one_round() {
+        test_for_length_and_unalignment(50, 0);
+    }
+
+    #[test]
+    fn sha256_aligned_one_round_to_the_end() {
+        test_for_length_and_unalignment(55, 0);
+    }
+
+    #[test]
+    fn sha256_aligned_one_round_and_padding_round() {
+        test_for_length_and_unalignment(56, 0);
+    }
+
+    #[test]
+    fn sha256_aligned_full_block_and_padding_round() {
+        test_for_length_and_unalignment(64, 0);
+    }
+
+    #[test]
+    fn sha256_aligned_three_rounds() {
+        test_for_length_and_unalignment(150, 0);
+    }
+
+    #[test]
+    fn sha256_unaligned_one_round() {
+        test_for_length_and_unalignment(50, 31);
+    }
+
+    #[test]
+    fn sha256_unaligned_one_round_and_padding_round() {
+        test_for_length_and_unalignment(60, 31);
+    }
+
+    #[test]
+    fn sha256_unaligned_full_block_and_padding_round() {
+        test_for_length_and_unalignment(64, 17);
+    }
+
+    #[test]
+    fn sha256_unaligned_three_rounds() {
+        test_for_length_and_unalignment(150, 22);
+    }
+
+    #[test]
+    fn sha256_padded_one_round() {
+        test_for_padded_input(50);
+    }
+
+    #[test]
+    fn sha256_padded_three_rounds() {
+        test_for_padded_input(150);
+    }
+}
@@ -0,0 +1,730 @@
+use super::*;
+
+use boojum::field::SmallField;
+
+use boojum::gadgets::traits::witnessable::WitnessHookable;
+
+use boojum::cs::traits::cs::ConstraintSystem;
+use boojum::gadgets::boolean::Boolean;
+use boojum::gadgets::traits::selectable::Selectable;
+use boojum::gadgets::u256::UInt256;
+use boojum::gadgets::u32::UInt32;
+use cs_derive::*;
+
+use crate::ethereum_types::U256;
+use crate::fsm_input_output::circuit_inputs::INPUT_OUTPUT_COMMITMENT_LENGTH;
+use boojum::gadgets::num::Num;
+use zkevm_opcode_defs::system_params::PRECOMPILE_AUX_BYTE;
+
+use crate::base_structures::log_query::*;
+use crate::base_structures::memory_query::*;
+use crate::base_structures::precompile_input_outputs::formal_precompile_address;
+use crate::base_structures::precompile_input_outputs::PrecompileFunctionOutputData;
+use crate::demux_log_queue::StorageLogQueue;
+use crate::ed25519_verify::sha512::*;
+use crate::fsm_input_output::*;
+use crate::storage_application::ConditionalWitnessAllocator;
+use boojum::algebraic_props::round_function::AlgebraicRoundFunction;
+use boojum::cs::Variable;
+use boojum::gadgets::queue::CircuitQueueWitness;
+use boojum::gadgets::queue::QueueState;
+use boojum::gadgets::traits::allocatable::CSAllocatable;
+use boojum::gadgets::traits::allocatable::{CSAllocatableExt, CSPlaceholder};
+use boojum::gadgets::traits::encodable::CircuitVarLengthEncodable;
+use boojum::gadgets::traits::round_function::CircuitRoundFunction;
+use boojum::gadgets::u160::UInt160;
+use boojum::gadgets::u8::UInt8;
+use std::sync::{Arc, RwLock};
+use zkevm_opcode_defs::ethereum_types::H160;
+
+use crate::base_structures::precompile_input_outputs::*;
+use crate::demux_log_queue::DemuxOutput;
+use crate::precompile_registry::PrecompileCircuit;
+use crate::scheduler::auxiliary::BaseLayerCircuitType;
+
+pub mod input;
+use self::input::*;
+
+// placed right after the Schnorr signature verification precompile
+pub const SHA512_ROUND_FUNCTION_PRECOMPILE_ADDRESS: u16 = 0x103;
+pub const SHA512_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS: H160 =
+    formal_precompile_address(SHA512_ROUND_FUNCTION_PRECOMPILE_ADDRESS);
+
+pub struct Sha512PrecompileCircuit;
+
+impl PrecompileCircuit for Sha512PrecompileCircuit {
+    const NAME: &'static str = "sha512";
+    const DEMUX_OUTPUT: DemuxOutput = DemuxOutput::Sha512;
+    const CIRCUIT_TYPE: BaseLayerCircuitType = BaseLayerCircuitType::Sha512Precompile;
+
+    type InputData<F: SmallField> = PrecompileFunctionInputData<F>;
+    type OutputData<F: SmallField> = PrecompileFunctionOutputData<F>;
+
+    fn formal_address() -> H160 {
+        SHA512_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS
+    }
+}
+
+#[derive(Derivative, CSAllocatable, CSSelectable, CSVarLengthEncodable, WitnessHookable)]
+#[derivative(Clone, Copy, Debug)]
+// #[DerivePrettyComparison("true")]
+pub struct Sha512PrecompileCallParams<F: SmallField> {
+    pub input_page: UInt32<F>,
+    pub input_offset: UInt32<F>,
+    pub output_page: UInt32<F>,
+    pub output_offset: UInt32<F>,
+    pub num_rounds: UInt32<F>,
+}
+
+impl<F: SmallField> CSPlaceholder<F> for Sha512PrecompileCallParams<F> {
+    fn placeholder<CS: ConstraintSystem<F>>(cs: &mut CS) -> Self {
+        let zero_u32 = UInt32::zero(cs);
+        Self {
+            input_page: zero_u32,
+            input_offset: zero_u32,
+            output_page: zero_u32,
+            output_offset: zero_u32,
+            num_rounds: zero_u32,
+        }
+    }
+}
+
+impl<F: SmallField> Sha512PrecompileCallParams<F> {
+    // same layout as for sha256: input is already padded and is given as a number of 128 byte blocks
+    pub fn from_encoding<CS: ConstraintSystem<F>>(_cs: &mut CS, encoding: UInt256<F>) -> Self {
+        let input_offset = encoding.inner[0];
+        let output_offset = encoding.inner[2];
+        let input_page = encoding.inner[4];
+        let output_page = encoding.inner[5];
+
+        let num_rounds = encoding.inner[6];
+
+        let new = Self {
+            input_page,
+            input_offset,
+            output_page,
+            output_offset,
+            num_rounds,
+        };
+
+        new
+    }
+}
+
+// 128 byte block
+pub const MEMORY_READ_QUERIES_PER_CYCLE: usize = 4;
+
+pub fn sha512_precompile_inner<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    memory_queue: &mut MemoryQueue<F, R>,
+    precompile_calls_queue: &mut StorageLogQueue<F, R>,
+    memory_read_witness: ConditionalWitnessAllocator<F, UInt256<F>>,
+    mut state: Sha512RoundFunctionFSM<F>,
+    _round_function: &R,
+    limit: usize,
+) -> Sha512RoundFunctionFSM<F>
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    assert!(limit <= u32::MAX as usize);
+
+    let precompile_address =
+        UInt160::allocated_constant(cs, SHA512_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS);
+    let aux_byte_for_precompile = UInt8::allocated_constant(cs, PRECOMPILE_AUX_BYTE);
+
+    let boolean_false = Boolean::allocated_constant(cs, false);
+    let boolean_true = Boolean::allocated_constant(cs, true);
+    let zero_u32 = UInt32::zero(cs);
+
+    // we can have a degenerate case when queue is empty, but it's a first circuit in the queue,
+    // so we taken default FSM state that has state.read_precompile_call = true;
+    let input_queue_is_empty = precompile_calls_queue.is_empty(cs);
+    // we can only skip the full circuit if we are not in any form of progress
+    let can_finish_immediatelly =
+        Boolean::multi_and(cs, &[state.read_precompile_call, input_queue_is_empty]);
+
+    if crate::config::CIRCUIT_VERSOBE {
+        dbg!(can_finish_immediatelly.witness_hook(cs)());
+        dbg!(state.witness_hook(cs)());
+    }
+
+    state.read_precompile_call = state
+        .read_precompile_call
+        .mask_negated(cs, can_finish_immediatelly);
+    state.read_words_for_round = state
+        .read_words_for_round
+        .mask_negated(cs, can_finish_immediatelly);
+    state.completed = Boolean::multi_or(cs, &[state.completed, can_finish_immediatelly]);
+
+    if crate::config::CIRCUIT_VERSOBE {
+        dbg!(state.witness_hook(cs)());
+        dbg!(precompile_calls_queue.into_state().witness_hook(cs)());
+        memory_read_witness.print_debug_info();
+    }
+    // main work cycle
+    for _cycle in 0..limit {
+        if crate::config::CIRCUIT_VERSOBE {
+            dbg!(_cycle);
+            dbg!(state.witness_hook(cs)());
+            dbg!(precompile_calls_queue.into_state().witness_hook(cs)());
+        }
+        // if we are in a proper state then get the ABI from the queue
+        let (precompile_call, _) = precompile_calls_queue.pop_front(cs, state.read_precompile_call);
+
+        Num::conditionally_enforce_equal(
+            cs,
+            state.read_precompile_call,
+            &Num::from_variable(precompile_call.aux_byte.get_variable()),
+            &Num::from_variable(aux_byte_for_precompile.get_variable()),
+        );
+        for (a, b) in precompile_call
+            .address
+            .inner
+            .iter()
+            .zip(precompile_address.inner.iter())
+        {
+            Num::conditionally_enforce_equal(
+                cs,
+                state.read_precompile_call,
+                &Num::from_variable(a.get_variable()),
+                &Num::from_variable(b.get_variable()),
+            );
+        }
+
+        // now compute some parameters that describe the call itself
+
+        let params_encoding = precompile_call.key;
+        let call_params = Sha512PrecompileCallParams::from_encoding(cs, params_encoding);
+
+        state.precompile_call_params = Sha512PrecompileCallParams::conditionally_select(
+            cs,
+            state.read_precompile_call,
+            &call_params,
+            &state.precompile_call_params,
+        );
+        // also set timestamps
+        state.timestamp_to_use_for_read = UInt32::conditionally_select(
+            cs,
+            state.read_precompile_call,
+            &precompile_call.timestamp,
+            &state.timestamp_to_use_for_read,
+        );
+
+        // timestamps have large space, so this can be expected
+        let timestamp_to_use_for_write =
+            unsafe { state.timestamp_to_use_for_read.increment_unchecked(cs) };
+        state.timestamp_to_use_for_write = UInt32::conditionally_select(
+            cs,
+            state.read_precompile_call,
+            &timestamp_to_use_for_write,
+            &state.timestamp_to_use_for_write,
+        );
+
+        let reset_buffer = Boolean::multi_or(cs, &[state.read_precompile_call, state.completed]);
+        state.read_words_for_round = Boolean::multi_or(
+            cs,
+            &[state.read_precompile_call, state.read_words_for_round],
+        );
+        state.read_precompile_call = boolean_false;
+
+        // ---------------------------------
+        // Now perform few memory queries to read content
+
+        let zero_rounds_left = state.precompile_call_params.num_rounds.is_zero(cs);
+
+        let mut block = [[zero_u32; 2]; SHA512_BLOCK_WORDS];
+        let should_read = zero_rounds_left.negated(cs);
+        let mut bias_variable = should_read.get_variable();
+        for dst in block.array_chunks_mut::<4>() {
+            let read_query_value =
+                memory_read_witness.conditionally_allocate_biased(cs, should_read, bias_variable);
+            bias_variable = read_query_value.inner[0].get_variable();
+
+            let read_query = MemoryQuery {
+                timestamp: state.timestamp_to_use_for_read,
+                memory_page: state.precompile_call_params.input_page,
+                index: state.precompile_call_params.input_offset,
+                rw_flag: boolean_false,
+                is_ptr: boolean_false,
+                value: read_query_value,
+            };
+
+            let may_be_new_offset = unsafe {
+                state
+                    .precompile_call_params
+                    .input_offset
+                    .increment_unchecked(cs)
+            };
+            state.precompile_call_params.input_offset = UInt32::conditionally_select(
+                cs,
+                state.read_words_for_round,
+                &may_be_new_offset,
+                &state.precompile_call_params.input_offset,
+            );
+
+            // perform read
+            memory_queue.push(cs, read_query, should_read);
+
+            // memory is BE, so the most significant 64 bits of the word go first into the block
+            *dst = u256_into_sha512_words(&read_query_value);
+        }
+
+        let may_be_new_num_rounds = unsafe {
+            state
+                .precompile_call_params
+                .num_rounds
+                .decrement_unchecked(cs)
+        };
+        state.precompile_call_params.num_rounds = UInt32::conditionally_select(
+            cs,
+            state.read_words_for_round,
+            &may_be_new_num_rounds,
+            &state.precompile_call_params.num_rounds,
+        );
+
+        // absorb
+        let sha512_empty_internal_state = sha512_ivs_as_uint32_pairs(cs);
+
+        let mut current_sha512_state = <[[UInt32<F>; 2]; SHA512_STATE_WIDTH]>::conditionally_select(
+            cs,
+            reset_buffer,
+            &sha512_empty_internal_state,
+            &state.sha512_inner_state,
+        );
+
+        sha512_round_function(cs, &mut current_sha512_state, &block);
+        state.sha512_inner_state = current_sha512_state;
+
+        let no_rounds_left = state.precompile_call_params.num_rounds.is_zero(cs);
+        let write_result = Boolean::multi_and(cs, &[state.read_words_for_round, no_rounds_left]);
+
+        // digest takes two memory words
+        for src in current_sha512_state.array_chunks::<4>() {
+            let write_word = sha512_words_into_u256(src);
+
+            let write_query = MemoryQuery {
+                timestamp: state.timestamp_to_use_for_write,
+                memory_page: state.precompile_call_params.output_page,
+                index: state.precompile_call_params.output_offset,
+                rw_flag: boolean_true,
+                is_ptr: boolean_false,
+                value: write_word,
+            };
+
+            // perform write
+            memory_queue.push(cs, write_query, write_result);
+
+            let may_be_new_offset = unsafe {
+                state
+                    .precompile_call_params
+                    .output_offset
+                    .increment_unchecked(cs)
+            };
+            state.precompile_call_params.output_offset = UInt32::conditionally_select(
+                cs,
+                write_result,
+                &may_be_new_offset,
+                &state.precompile_call_params.output_offset,
+            );
+        }
+
+        // ---------------------------------
+
+        // update state
+        let input_is_empty = precompile_calls_queue.is_empty(cs);
+        let input_is_not_empty = input_is_empty.negated(cs);
+        let nothing_left = Boolean::multi_and(cs, &[write_result, input_is_empty]);
+        let process_next = Boolean::multi_and(cs, &[write_result, input_is_not_empty]);
+
+        state.read_precompile_call = process_next;
+        state.completed = Boolean::multi_or(cs, &[nothing_left, state.completed]);
+        let t = Boolean::multi_or(cs, &[state.read_precompile_call, state.completed]);
+        state.read_words_for_round = t.negated(cs);
+
+        if crate::config::CIRCUIT_VERSOBE {
+            dbg!(state.witness_hook(cs)());
+            dbg!(precompile_calls_queue.into_state().witness_hook(cs)());
+        }
+    }
+
+    if crate::config::CIRCUIT_VERSOBE {
+        dbg!(state.witness_hook(cs)());
+        dbg!(precompile_calls_queue.into_state().witness_hook(cs)());
+    }
+
+    precompile_calls_queue.enforce_consistency(cs);
+
+    state
+}
+#[track_caller]
+pub fn sha512_round_function_entry_point<
+    F: SmallField,
+    CS: ConstraintSystem<F>,
+    R: CircuitRoundFunction<F, 8, 12, 4> + AlgebraicRoundFunction<F, 8, 12, 4>,
+>(
+    cs: &mut CS,
+    witness: Sha512RoundFunctionCircuitInstanceWitness<F>,
+    round_function: &R,
+    limit: usize,
+) -> [Num<F>; INPUT_OUTPUT_COMMITMENT_LENGTH]
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+{
+    let Sha512RoundFunctionCircuitInstanceWitness {
+        closed_form_input,
+        requests_queue_witness,
+        memory_reads_witness,
+    } = witness;
+
+    let mut structured_input = Sha512RoundFunctionCircuitInputOutput::alloc_ignoring_outputs(
+        cs,
+        closed_form_input.clone(),
+    );
+
+    let start_flag = structured_input.start_flag;
+
+    let requests_queue_state_from_input = structured_input.observable_input.initial_log_queue_state;
+
+    // it must be trivial
+    requests_queue_state_from_input.enforce_trivial_head(cs);
+
+    let requests_queue_state_from_fsm = structured_input.hidden_fsm_input.log_queue_state;
+
+    let requests_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &requests_queue_state_from_input,
+        &requests_queue_state_from_fsm,
+    );
+
+    let memory_queue_state_from_input =
+        structured_input.observable_input.initial_memory_queue_state;
+
+    // it must be trivial
+    memory_queue_state_from_input.enforce_trivial_head(cs);
+
+    let memory_queue_state_from_fsm = structured_input.hidden_fsm_input.memory_queue_state;
+
+    let memory_queue_state = QueueState::conditionally_select(
+        cs,
+        start_flag,
+        &memory_queue_state_from_input,
+        &memory_queue_state_from_fsm,
+    );
+
+    let mut requests_queue = StorageLogQueue::<F, R>::from_state(cs, requests_queue_state);
+    let queue_witness = CircuitQueueWitness::from_inner_witness(requests_queue_witness);
+    requests_queue.witness = Arc::new(queue_witness);
+
+    let mut memory_queue = MemoryQueue::<F, R>::from_state(cs, memory_queue_state);
+
+    let read_queries_allocator = ConditionalWitnessAllocator::<F, UInt256<F>> {
+        witness_source: Arc::new(RwLock::new(memory_reads_witness)),
+    };
+
+    let mut starting_fsm_state = Sha512RoundFunctionFSM::placeholder(cs);
+    starting_fsm_state.read_precompile_call = Boolean::allocated_constant(cs, true);
+
+    let initial_state = Sha512RoundFunctionFSM::conditionally_select(
+        cs,
+        start_flag,
+        &starting_fsm_state,
+        &structured_input.hidden_fsm_input.internal_fsm,
+    );
+
+    let final_state = sha512_precompile_inner::<F, CS, R>(
+        cs,
+        &mut memory_queue,
+        &mut requests_queue,
+        read_queries_allocator,
+        initial_state,
+        round_function,
+        limit,
+    );
+
+    let final_memory_state = memory_queue.into_state();
+    let final_requets_state = requests_queue.into_state();
+
+    // form the final state
+    let done = final_state.completed;
+    structured_input.completion_flag = done;
+    structured_input.observable_output = PrecompileFunctionOutputData::placeholder(cs);
+
+    structured_input.observable_output.final_memory_state = QueueState::conditionally_select(
+        cs,
+        structured_input.completion_flag,
+        &final_memory_state,
+        &structured_input.observable_output.final_memory_state,
+    );
+
+    structured_input.hidden_fsm_output.internal_fsm = final_state;
+    structured_input.hidden_fsm_output.log_queue_state = final_requets_state;
+    structured_input.hidden_fsm_output.memory_queue_state = final_memory_state;
+
+    // self-check
+    structured_input.hook_compare_witness(cs, &closed_form_input);
+
+    use boojum::cs::gates::PublicInputGate;
+
+    let compact_form =
+        ClosedFormInputCompactForm::from_full_form(cs, &structured_input, round_function);
+    let input_commitment = commit_variable_length_encodable_item(cs, &compact_form, round_function);
+    for el in input_commitment.iter() {
+        let gate = PublicInputGate::new(el.get_variable());
+        gate.add_to_cs(cs);
+    }
+
+    input_commitment
+}
+#[cfg(test)]
+mod test {
+    use boojum::algebraic_props::poseidon2_parameters::*;
+    use boojum::config::DevCSConfig;
+    use boojum::cs::cs_builder::*;
+    use boojum::cs::gates::*;
+    use boojum::cs::implementations::reference_cs::CSReferenceImplementation;
+    use boojum::cs::traits::gate::*;
+    use boojum::cs::*;
+    use boojum::field::goldilocks::GoldilocksField;
+    use boojum::gadgets::tables::*;
+    use boojum::implementations::poseidon2::Poseidon2Goldilocks;
+    use boojum::worker::Worker;
+    use zkevm_opcode_defs::PrecompileCallABI;
+
+    use super::*;
+
+    type F = GoldilocksField;
+    type P = GoldilocksField;
+    type R = Poseidon2Goldilocks;
+
+    fn create_test_cs() -> CSReferenceImplementation<
+        GoldilocksField,
+        GoldilocksField,
+        DevCSConfig,
+        impl GateConfigurationHolder<GoldilocksField>,
+        impl StaticToolboxHolder,
+    > {
+        let geometry = CSGeometry {
+            num_columns_under_copy_permutation: 100,
+            num_witness_columns: 0,
+            num_constant_columns: 8,
+            max_allowed_constraint_degree: 4,
+        };
+
+        fn configure<
+            T: CsBuilderImpl<F, T>,
+            GC: GateConfigurationHolder<F>,
+            TB: StaticToolboxHolder,
+        >(
+            builder: CsBuilder<T, F, GC, TB>,
+        ) -> CsBuilder<T, F, impl GateConfigurationHolder<F>, impl StaticToolboxHolder> {
+            let builder = builder.allow_lookup(
+                LookupParameters::UseSpecializedColumnsWithTableIdAsConstant {
+                    width: 4,
+                    num_repetitions: 8,
+                    share_table_id: true,
+                },
+            );
+            let builder = ConstantsAllocatorGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = FmaGateInBaseFieldWithoutConstant::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ReductionGate::<F, 4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = BooleanConstraintGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<32>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<16>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = UIntXAddGate::<8>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = SelectionGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = ZeroCheckGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+                false,
+            );
+            let builder = DotProductGate::<4>::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+            let builder = MatrixMultiplicationGate::<F, 12, Poseidon2GoldilocksExternalMatrix>::configure_builder(builder,GatePlacementStrategy::UseGeneralPurposeColumns);
+            let builder = MatrixMultiplicationGate::<F, 12, Poseidon2GoldilocksInnerMatrix>::configure_builder(builder,GatePlacementStrategy::UseGeneralPurposeColumns);
+            let builder = NopGate::configure_builder(
+                builder,
+                GatePlacementStrategy::UseGeneralPurposeColumns,
+            );
+
+            builder
+        }
+
+        use boojum::cs::cs_builder_reference::CsReferenceImplementationBuilder;
+
+        let builder_impl =
+            CsReferenceImplementationBuilder::<F, P, DevCSConfig>::new(geometry, 1 << 20);
+        let builder = new_builder::<_, F>(builder_impl);
+
+        let builder = configure(builder);
+        let mut owned_cs = builder.build(1 << 26);
+
+        // range checks of the words we read use these tables
+        let table = create_maj4_table();
+        owned_cs.add_lookup_table::<Maj4Table, 4>(table);
+
+        let table = create_tri_xor_table();
+        owned_cs.add_lookup_table::<TriXor4Table, 4>(table);
+
+        let table = create_ch4_table();
+        owned_cs.add_lookup_table::<Ch4Table, 4>(table);
+
+        let table = create_4bit_chunk_split_table::<F, 1>();
+        owned_cs.add_lookup_table::<chunk4bits::Split4BitChunkTable<1>, 4>(table);
+        let table = create_4bit_chunk_split_table::<F, 2>();
+        owned_cs.add_lookup_table::<chunk4bits::Split4BitChunkTable<2>, 4>(table);
+
+        owned_cs
+    }
+
+    // pads the message in the same way as the caller has to do it
+    fn padded_message_as_u256_words(message: &[u8]) -> (Vec<U256>, u32) {
+        let mut padded = message.to_vec();
+        padded.push(0x80);
+        while padded.len() % 128 != 112 {
+            padded.push(0);
+        }
+        padded.extend_from_slice(&((message.len() as u128) * 8).to_be_bytes());
+        let num_rounds = (padded.len() / 128) as u32;
+
+        let words = padded
+            .chunks(32)
+            .map(|el| U256::from_big_endian(el))
+            .collect();
+
+        (words, num_rounds)
+    }
+
+    fn test_for_message(message: &[u8], expected: &str) {
+        let (input_witness, num_rounds) = padded_message_as_u256_words(message);
+        let reference = hex::decode(expected).unwrap();
+
+        let mut owned_cs = create_test_cs();
+        let cs = &mut owned_cs;
+        let mut memory_queue = MemoryQueue::<F, R>::empty(cs);
+
+        let precompile_abi = PrecompileCallABI {
+            input_memory_offset: 0,
+            input_memory_length: 0,
+            output_memory_offset: 0,
+            output_memory_length: 2,
+            memory_page_to_read: 123,
+            memory_page_to_write: 456,
+            precompile_interpreted_data: num_rounds as u64,
+        };
+        let encoded_precompile_abi = precompile_abi.to_u256();
+        let boolean_true = Boolean::allocated_constant(cs, true);
+
+        let mut precompile_calls_queue = StorageLogQueue::<F, R>::empty(cs);
+        let el = LogQueryWitness {
+            address: SHA512_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS,
+            key: encoded_precompile_abi,
+            read_value: U256::zero(),
+            written_value: U256::zero(),
+            aux_byte: PRECOMPILE_AUX_BYTE,
+            rw_flag: true,
+            rollback: false,
+            is_service: false,
+            shard_id: 0,
+            tx_number_in_block: 0,
+            timestamp: 0,
+        };
+        let el = LogQuery::allocate(cs, el);
+        precompile_calls_queue.push(cs, el, boolean_true);
+
+        let mut state = Sha512RoundFunctionFSM::placeholder_witness();
+        state.read_precompile_call = true;
+        state.timestamp_to_use_for_read = 1;
+        state.timestamp_to_use_for_write = 2;
+
+        let state = Sha512RoundFunctionFSM::allocate(cs, state);
+        let round_function = Poseidon2Goldilocks;
+
+        let memory_read_witness = ConditionalWitnessAllocator::<F, UInt256<F>> {
+            witness_source: std::sync::Arc::new(std::sync::RwLock::new(input_witness.into())),
+        };
+
+        let new_state = sha512_precompile_inner(
+            cs,
+            &mut memory_queue,
+            &mut precompile_calls_queue,
+            memory_read_witness,
+            state,
+            &round_function,
+            num_rounds as usize,
+        );
+
+        assert!(new_state.completed.witness_hook(cs)().unwrap());
+
+        drop(cs);
+
+        let mut buffer = vec![];
+        for (output, _) in memory_queue.witness.elements.read().unwrap().iter() {
+            if output.rw_flag {
+                let mut word = [0u8; 32];
+                output.value.to_big_endian(&mut word);
+                buffer.extend_from_slice(&word);
+            }
+        }
+
+        assert_eq!(buffer, reference);
+
+        let _ = owned_cs.pad_and_shrink();
+        let mut assembly = owned_cs.into_assembly::<std::alloc::Global>();
+        let worker = Worker::new();
+        let is_satisfied = assembly.check_if_satisfied(&worker);
+        assert!(is_satisfied);
+    }
+
+    #[test]
+    fn sha512_one_round() {
+        test_for_message(
+            b"abc",
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+        );
+    }
+
+    #[test]
+    fn sha512_two_rounds() {
+        test_for_message(
+            b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu",
+            "8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909",
+        );
+    }
+}
@@ -12,6 +12,25 @@ pub(crate) fn integer_into_flags(encoding: u8) -> (bool, bool, bool) {
     )
 }
 
+pub(crate) fn resolve_condition(
+    condition: zkevm_opcode_defs::Condition,
+    of: bool,
+    eq: bool,
+    gt: bool,
+) -> bool {
+    use zkevm_opcode_defs::Condition;
+    match condition {
+        Condition::Always => true,
+        Condition::Lt => of,
+        Condition::Eq => eq,
+        Condition::Gt => gt,
+        Condition::Ge => gt || eq,
+        Condition::Le => of || eq,
+        Condition::Ne => !eq,
+        Condition::GtOrLt => gt || of,
+    }
+}
+
 pub const VM_CONDITIONAL_RESOLUTION_TABLE_NAME: &'static str = "Conditional resolution table";
 
 #[derive(Derivative)]
@@ -24,21 +43,11 @@ pub fn create_conditionals_resolution_table<F: SmallField>() -> LookupTable<F, 3
     let mut all_keys = Vec::with_capacity(num_rows);
 
     let all_conditions = zkevm_opcode_defs::ALL_CONDITIONS;
-    use zkevm_opcode_defs::Condition;
     for condition in all_conditions.iter() {
         let x = condition.variant_index(); // integer encoding
         for i in 0..(1 << FLAGS_PACKED_ENCODING_BIT_WIDTH) {
             let (of, eq, gt) = integer_into_flags(i as u8);
-            let resolution = match condition {
-                Condition::Always => true,
-                Condition::Lt => of,
-                Condition::Eq => eq,
-                Condition::Gt => gt,
-                Condition::Ge => gt || eq,
-                Condition::Le => of || eq,
-                Condition::Ne => !eq,
-                Condition::GtOrLt => gt || of,
-            };
+            let resolution = resolve_condition(*condition, of, eq, gt);
 
             let row = [
                 F::from_u64(x as u64).unwrap(),
@@ -6,6 +6,7 @@ pub mod conditional;
 pub mod integer_to_boolean_mask;
 pub mod opcodes_decoding;
 pub mod pubdata_cost_validity;
+pub mod ripemd160_boolean_functions;
 pub mod test_bit;
 pub mod uma_ptr_read_cleanup;
 
@@ -15,5 +16,6 @@ pub use self::conditional::*;
 pub use self::integer_to_boolean_mask::*;
 pub use self::opcodes_decoding::*;
 pub use self::pubdata_cost_validity::*;
+pub use self::ripemd160_boolean_functions::*;
 pub use self::test_bit::*;
 pub use self::uma_ptr_read_cleanup::*;
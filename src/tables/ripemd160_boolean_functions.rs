@@ -0,0 +1,61 @@
+use super::*;
+use boojum::cs::implementations::lookup_table::LookupTable;
+use boojum::field::SmallField;
+
+pub const RIPEMD160_BOOLEAN_FUNCTIONS_TABLE_NAME: &'static str =
+    "RIPEMD-160 boolean functions table";
+
+pub const RIPEMD160_NUM_BOOLEAN_FUNCTIONS: usize = 5;
+pub const RIPEMD160_BOOLEAN_FUNCTION_CHUNK_BITS: usize = 4;
+
+pub(crate) fn ripemd160_boolean_function(function_index: usize, x: u32, y: u32, z: u32) -> u32 {
+    match function_index {
+        0 => x ^ y ^ z,
+        1 => (x & y) | (!x & z),
+        2 => (x | !y) ^ z,
+        3 => (x & z) | (y & !z),
+        4 => x ^ (y | !z),
+        _ => unreachable!(),
+    }
+}
+
+#[derive(Derivative)]
+#[derivative(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ripemd160BooleanFunctionsTable;
+
+pub fn create_ripemd160_boolean_functions_table<F: SmallField>() -> LookupTable<F, 3> {
+    // we work over 4 bit chunks, and put x | (y << 4) into the first element,
+    // and z | (function index << 4) into the second element
+    const MASK: u32 = (1 << RIPEMD160_BOOLEAN_FUNCTION_CHUNK_BITS) - 1;
+
+    let num_rows = RIPEMD160_NUM_BOOLEAN_FUNCTIONS << (3 * RIPEMD160_BOOLEAN_FUNCTION_CHUNK_BITS);
+    let mut all_keys = Vec::with_capacity(num_rows);
+
+    for function_index in 0..RIPEMD160_NUM_BOOLEAN_FUNCTIONS {
+        for x in 0..=MASK {
+            for y in 0..=MASK {
+                for z in 0..=MASK {
+                    let result = ripemd160_boolean_function(function_index, x, y, z) & MASK;
+                    let row = [
+                        F::from_u64_unchecked(
+                            (x | (y << RIPEMD160_BOOLEAN_FUNCTION_CHUNK_BITS)) as u64,
+                        ),
+                        F::from_u64_unchecked(
+                            (z | ((function_index as u32) << RIPEMD160_BOOLEAN_FUNCTION_CHUNK_BITS))
+                                as u64,
+                        ),
+                        F::from_u64_unchecked(result as u64),
+                    ];
+
+                    all_keys.push(row);
+                }
+            }
+        }
+    }
+
+    LookupTable::new_from_content(
+        all_keys,
+        RIPEMD160_BOOLEAN_FUNCTIONS_TABLE_NAME.to_string(),
+        2,
+    )
+}